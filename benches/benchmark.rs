@@ -1,4 +1,4 @@
-use criterion::{Criterion, criterion_group, criterion_main};
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
 use elf_loader::{Loader, input::ElfFile};
 use libloading::Library;
 use std::path::PathBuf;
@@ -39,5 +39,371 @@ fn get_symbol_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, load_benchmark, get_symbol_benchmark);
+fn repeated_load_benchmark(c: &mut Criterion) {
+    use elf_loader::input::ElfBinary;
+    use gen_elf::{Arch, DylibWriter, SymbolDesc};
+
+    // Plugin-sized libraries, loaded many times in a row
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("plugin_data", &[0; 64])])
+        .unwrap();
+    let data = &output.data;
+
+    c.bench_function("elf_loader:load_fresh_loader", |b| {
+        b.iter(|| {
+            let mut loader = Loader::new();
+            let lib = loader
+                .load_dylib(ElfBinary::new("libplugin.so", data))
+                .unwrap();
+            let _ = lib.relocator().relocate().unwrap();
+        });
+    });
+    let mut loader = Loader::new();
+    loader.preallocate(16);
+    c.bench_function("elf_loader:load_reused_loader", |b| {
+        b.iter(|| {
+            let lib = loader
+                .load_dylib(ElfBinary::new("libplugin.so", data))
+                .unwrap();
+            let _ = lib.relocator().relocate().unwrap();
+        });
+    });
+    c.bench_function("elf_loader:probe", |b| {
+        b.iter(|| loader.probe(ElfBinary::new("libplugin.so", data)).unwrap());
+    });
+}
+
+fn batch_lookup_benchmark(c: &mut Criterion) {
+    use elf_loader::input::ElfBinary;
+    use gen_elf::{Arch, DylibWriter, SymbolDesc};
+
+    // A large FFI surface bound symbol by symbol at startup
+    let names: Vec<String> = (0..10_000).map(|i| format!("ffi_{i}")).collect();
+    let symbols: Vec<_> = names
+        .iter()
+        .map(|name| SymbolDesc::global_func(name, &[0xc3]))
+        .collect();
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &symbols)
+        .unwrap();
+    let mut loader = Loader::new();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libffi.so", &output.data))
+        .unwrap()
+        .relocator()
+        .relocate()
+        .unwrap();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+    c.bench_function("elf_loader:get_10k", |b| {
+        b.iter(|| {
+            names
+                .iter()
+                .map(|name| unsafe { lib.get::<()>(name) }.map(|sym| sym.into_raw()))
+                .collect::<Vec<_>>()
+        })
+    });
+    c.bench_function("elf_loader:lookup_batch_10k", |b| {
+        b.iter(|| lib.lookup_batch(&names))
+    });
+}
+
+fn link_batch_benchmark(c: &mut Criterion) {
+    use elf_loader::{
+        arch::REL_GOT,
+        image::{LoadedDylib, RawElf},
+        input::ElfBinary,
+        relocation::Linker,
+    };
+    use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+
+    // 30 plugins that all use the symbols of the same 5 base libraries
+    let arch = Arch::current();
+    let names: Vec<String> = (0..5 * 20).map(|i| format!("base_{i}")).collect();
+    let bases: Vec<_> = names
+        .chunks(20)
+        .map(|chunk| {
+            let symbols: Vec<_> = chunk
+                .iter()
+                .map(|name| SymbolDesc::global_object(name, &[0; 8]))
+                .collect();
+            DylibWriter::new(arch).write(&[], &symbols).unwrap()
+        })
+        .collect();
+    let relocs: Vec<_> = names
+        .iter()
+        .map(|name| RelocEntry::with_name(name, REL_GOT))
+        .collect();
+    let symbols: Vec<_> = names.iter().map(SymbolDesc::undefined_object).collect();
+    let plugin = DylibWriter::new(arch).write(&relocs, &symbols).unwrap();
+
+    let mut loader = Loader::new();
+    let bases: Vec<LoadedDylib<()>> = bases
+        .iter()
+        .map(|output| {
+            loader
+                .load_dylib(ElfBinary::new("libbase.so", &output.data))
+                .unwrap()
+                .relocator()
+                .relocate()
+                .unwrap()
+        })
+        .collect();
+
+    // Loading is done in the setup so that only the relocation is measured
+    let mut load_plugins = || -> Vec<_> {
+        (0..30)
+            .map(|_| {
+                loader
+                    .load_dylib(ElfBinary::new("libplugin.so", &plugin.data))
+                    .unwrap()
+            })
+            .collect()
+    };
+    c.bench_function("elf_loader:relocate_30_plugins", |b| {
+        b.iter_batched(
+            &mut load_plugins,
+            |plugins| {
+                plugins
+                    .into_iter()
+                    .map(|plugin| plugin.relocator().scope(&bases).relocate().unwrap())
+                    .collect::<Vec<_>>()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("elf_loader:link_30_plugins", |b| {
+        b.iter_batched(
+            &mut load_plugins,
+            |plugins| {
+                Linker::new()
+                    .scope(&bases)
+                    .images(plugins.into_iter().map(RawElf::Dylib))
+                    .link()
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+fn scope_index_benchmark(c: &mut Criterion) {
+    use elf_loader::{arch::REL_GOT, image::LoadedDylib, input::ElfBinary, relocation::ScopeIndex};
+    use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+
+    // A plugin using one symbol of each of the 200 modules of a scope
+    let arch = Arch::current();
+    let modules: Vec<_> = (0..200)
+        .map(|i| {
+            let symbols: Vec<_> = (0..10)
+                .map(|j| SymbolDesc::global_object(format!("mod{i}_sym{j}"), &[0; 8]))
+                .collect();
+            DylibWriter::new(arch).write(&[], &symbols).unwrap()
+        })
+        .collect();
+    let names: Vec<String> = (0..200).map(|i| format!("mod{i}_sym{}", i % 10)).collect();
+    let relocs: Vec<_> = names
+        .iter()
+        .map(|name| RelocEntry::with_name(name, REL_GOT))
+        .collect();
+    let symbols: Vec<_> = names.iter().map(SymbolDesc::undefined_object).collect();
+    let plugin = DylibWriter::new(arch).write(&relocs, &symbols).unwrap();
+
+    let mut loader = Loader::new();
+    let scope: Vec<LoadedDylib<()>> = modules
+        .iter()
+        .map(|output| {
+            loader
+                .load_dylib(ElfBinary::new("libmodule.so", &output.data))
+                .unwrap()
+                .relocator()
+                .relocate()
+                .unwrap()
+        })
+        .collect();
+    let index = ScopeIndex::build(&scope);
+
+    let mut load_plugin = || {
+        loader
+            .load_dylib(ElfBinary::new("libplugin.so", &plugin.data))
+            .unwrap()
+    };
+    c.bench_function("elf_loader:relocate_200_module_scope", |b| {
+        b.iter_batched(
+            &mut load_plugin,
+            |plugin| plugin.relocator().scope(&scope).relocate().unwrap(),
+            BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("elf_loader:relocate_200_module_scope_indexed", |b| {
+        b.iter_batched(
+            &mut load_plugin,
+            |plugin| {
+                plugin
+                    .relocator()
+                    .scope(&scope)
+                    .scope_index(&index)
+                    .relocate()
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+fn object_sections_benchmark(c: &mut Criterion) {
+    use elf_loader::input::{ElfBinary, IntoElfReader};
+    use gen_elf::{Arch, ObjectWriter, SymbolDesc};
+
+    // A 4 MiB object built with one section per variable, as with `-fdata-sections`
+    let data = vec![0x5a; 2048];
+    let names: Vec<String> = (0..2048).map(|i| format!("var_{i}")).collect();
+    let symbols: Vec<_> = names
+        .iter()
+        .map(|name| SymbolDesc::global_object(name, &data))
+        .collect();
+    let output = ObjectWriter::new(Arch::current())
+        .with_section_per_symbol()
+        .write(&symbols, &[])
+        .unwrap();
+    let path = std::env::temp_dir().join("elf_loader_bench_sections.o");
+    std::fs::write(&path, &output.data).unwrap();
+    let path = path.to_str().unwrap();
+
+    fn load<'a>(object: impl IntoElfReader<'a>) {
+        let mut loader = Loader::new();
+        let obj = loader.load_object(object).unwrap();
+        let _ = obj.relocator().relocate().unwrap();
+    }
+
+    // Adjacent sections are copied together instead of by one read each
+    c.bench_function("elf_loader:load_object_sections", |b| {
+        b.iter(|| load(ElfBinary::new("sections.o", &output.data)));
+    });
+    c.bench_function("elf_loader:read_object_sections", |b| {
+        b.iter(|| load(ElfFile::from_path(path).unwrap()));
+    });
+}
+
+#[cfg(all(feature = "mmap-file", unix))]
+fn file_reader_benchmark(c: &mut Criterion) {
+    use elf_loader::input::{ElfMmapFile, IntoElfReader};
+    use gen_elf::{Arch, DylibWriter, SymbolDesc};
+    use std::{fs::File, os::fd::IntoRawFd};
+
+    // A library with a few megabytes of data and a large symbol table
+    let data = vec![0x5a; 4 << 20];
+    let mut symbols = vec![SymbolDesc::global_object("big_data", &data)];
+    let names: Vec<String> = (0..20_000).map(|i| format!("func_{i}")).collect();
+    symbols.extend(
+        names
+            .iter()
+            .map(|name| SymbolDesc::global_func(name, &[0xc3])),
+    );
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &symbols)
+        .unwrap();
+    let path = std::env::temp_dir().join("elf_loader_bench_big.so");
+    std::fs::write(&path, &output.data).unwrap();
+    let path = path.to_str().unwrap();
+
+    fn load(object: impl IntoElfReader<'static>) {
+        let mut loader = Loader::new();
+        let lib = loader.load_dylib(object).unwrap();
+        let _ = lib.relocator().relocate().unwrap();
+    }
+
+    // `ElfFile::from_path` maps the file with this feature, so open the
+    // buffered reader from a file descriptor instead
+    c.bench_function("elf_loader:read_file", |b| {
+        b.iter(|| {
+            let fd = File::open(path).unwrap().into_raw_fd();
+            load(unsafe { ElfFile::from_owned_fd(path, fd) })
+        });
+    });
+    c.bench_function("elf_loader:mmap_file", |b| {
+        b.iter(|| load(ElfMmapFile::from_path(path).unwrap()));
+    });
+}
+
+#[cfg(not(all(feature = "mmap-file", unix)))]
+fn file_reader_benchmark(_c: &mut Criterion) {}
+
+#[cfg(feature = "prelink")]
+fn prelink_benchmark(c: &mut Criterion) {
+    use elf_loader::{arch::REL_GOT, input::ElfBinary};
+    use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+
+    // A library that binds a few hundred symbols of a base library at startup
+    let arch = Arch::current();
+    let names: Vec<String> = (0..500).map(|i| format!("base_{i}")).collect();
+    let symbols: Vec<_> = names
+        .iter()
+        .map(|name| SymbolDesc::global_object(name, &[0; 8]))
+        .collect();
+    let base = DylibWriter::new(arch).write(&[], &symbols).unwrap();
+    let relocs: Vec<_> = names
+        .iter()
+        .map(|name| RelocEntry::with_name(name, REL_GOT))
+        .collect();
+    let symbols: Vec<_> = names.iter().map(SymbolDesc::undefined_object).collect();
+    let plugin = DylibWriter::new(arch).write(&relocs, &symbols).unwrap();
+
+    let mut loader = Loader::new();
+    let base = loader
+        .load_dylib(ElfBinary::new("libbase.so", &base.data))
+        .unwrap()
+        .relocator()
+        .relocate()
+        .unwrap();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libplugin.so", &plugin.data))
+        .unwrap()
+        .relocator()
+        .scope([&base])
+        .relocate()
+        .unwrap();
+    let mut blob = Vec::new();
+    lib.serialize_prelinked(|bytes| {
+        blob.extend_from_slice(bytes);
+        Ok(())
+    })
+    .unwrap();
+    let deps = lib.deps().to_vec();
+
+    c.bench_function("elf_loader:load_and_relocate", |b| {
+        b.iter(|| {
+            loader
+                .load_dylib(ElfBinary::new("libplugin.so", &plugin.data))
+                .unwrap()
+                .relocator()
+                .scope([&base])
+                .relocate()
+                .unwrap()
+        });
+    });
+    c.bench_function("elf_loader:load_prelinked", |b| {
+        b.iter(|| {
+            loader
+                .load_prelinked(ElfBinary::new("libplugin.so", &blob), None, &deps)
+                .unwrap()
+        });
+    });
+}
+
+#[cfg(not(feature = "prelink"))]
+fn prelink_benchmark(_c: &mut Criterion) {}
+
+criterion_group!(
+    benches,
+    load_benchmark,
+    get_symbol_benchmark,
+    repeated_load_benchmark,
+    batch_lookup_benchmark,
+    link_batch_benchmark,
+    scope_index_benchmark,
+    object_sections_benchmark,
+    file_reader_benchmark,
+    prelink_benchmark
+);
 criterion_main!(benches);
@@ -10,6 +10,8 @@ fn main() {
 
     println!("cargo:rerun-if-changed=tests/fixtures");
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=examples/fixtures");
+    println!("cargo:rerun-if-changed=src/plugin/abi.rs");
 
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
     let target = env::var("TARGET").unwrap();
@@ -23,8 +25,14 @@ fn main() {
 
     // Re-create small set of runtime fixtures required by doctests/examples
     // (liba/libb/libc) from `examples/fixtures/rust` so doctests that load
-    // `liba.so` continue to work.
-    let rust_dylibs = [("liba", "a"), ("libb", "b"), ("libc", "c")];
+    // `liba.so` continue to work. `libplugin` declares an entry point for the
+    // `plugin-abi` tests.
+    let rust_dylibs = [
+        ("liba", "a"),
+        ("libb", "b"),
+        ("libc", "c"),
+        ("libplugin", "plugin"),
+    ];
     for (filename, crate_name) in &rust_dylibs {
         let src = format!("examples/fixtures/{}.rs", filename);
         let mut cmd = Command::new("rustc");
@@ -47,10 +55,10 @@ fn main() {
     }
 
     // Get the compiler/linker to use
-    let cc_target = if target == "aarch64-unknown-none" {
-        "aarch64-unknown-linux-gnu"
-    } else {
-        &target
+    let cc_target = match target.as_str() {
+        "aarch64-unknown-none" => "aarch64-unknown-linux-gnu",
+        "riscv64gc-unknown-none-elf" => "riscv64gc-unknown-linux-gnu",
+        _ => &target,
     };
     let compiler = cc::Build::new().target(cc_target).get_compiler();
     let cc_path = compiler.path();
@@ -69,11 +77,113 @@ fn main() {
     }
     let _ = cmd.status();
 
-    // Copy exec_a to target/exec_a for mini-loader tests
+    // A static executable needs no interpreter, so it also runs under qemu-user
+    let hello_static_c = "tests/fixtures/c/hello_static.c";
+    let hello_static = out_dir.join("hello_static");
+    let mut cmd = Command::new(cc_path);
+    cmd.arg(hello_static_c)
+        .arg("-static")
+        .arg("-o")
+        .arg(&hello_static);
+
+    for arg in compiler.args() {
+        cmd.arg(arg);
+    }
+    let _ = cmd.status();
+
+    // Position-independent builds of the same program, with and without an
+    // interpreter, for the classification done by `Loader::load`
+    let hello_static_pie = out_dir.join("hello_static_pie");
+    let hello_pie = out_dir.join("hello_pie");
+    for (exec, flags) in [
+        (&hello_static_pie, ["-fPIE", "-static-pie"]),
+        (&hello_pie, ["-fPIE", "-pie"]),
+    ] {
+        let mut cmd = Command::new(cc_path);
+        cmd.arg(hello_static_c).args(flags).arg("-o").arg(exec);
+        for arg in compiler.args() {
+            cmd.arg(arg);
+        }
+        let _ = cmd.status();
+    }
+
+    // Libraries opened by both the system `dlopen` and `Relocator::dlopen_compat`
+    // to compare their bindings. Lazy binding and every `DT_NEEDED` entry must
+    // survive toolchains that default to `-z now` or `--as-needed`.
+    let compat_libs: [(&str, &[&str]); 6] = [
+        ("global", &[]),
+        ("dep1", &[]),
+        ("dep2", &[]),
+        ("top", &["dep1", "dep2"]),
+        ("lazy", &[]),
+        ("late", &[]),
+    ];
+    for (name, needed) in compat_libs {
+        let mut cmd = Command::new(cc_path);
+        cmd.arg(format!("tests/fixtures/c/compat/{name}.c"))
+            .args([
+                "-shared",
+                "-fPIC",
+                "-nostdlib",
+                "-Wl,-z,lazy",
+                "-Wl,--no-as-needed",
+            ])
+            .arg(format!("-Wl,-soname,libcompat_{name}.so"))
+            .arg("-Wl,-rpath,$ORIGIN");
+        for dep in needed {
+            cmd.arg(out_dir.join(format!("libcompat_{dep}.so")));
+        }
+        cmd.arg("-o")
+            .arg(out_dir.join(format!("libcompat_{name}.so")));
+        for arg in compiler.args() {
+            cmd.arg(arg);
+        }
+        let _ = cmd.status();
+    }
+
+    // A library whose constructor and destructor call back into the host, to
+    // order them against the hooks of the relocator
+    let mut cmd = Command::new(cc_path);
+    cmd.arg("tests/fixtures/c/init_hooks.c")
+        .args(["-shared", "-fPIC", "-nostdlib", "-o"])
+        .arg(out_dir.join("libinit_hooks.so"));
+    for arg in compiler.args() {
+        cmd.arg(arg);
+    }
+    let _ = cmd.status();
+
+    // Two libraries and a C host for the C API test, which links the host
+    // into the test binary
+    if env::var_os("CARGO_FEATURE_CAPI").is_some() {
+        println!("cargo:rerun-if-changed=include/elf_loader.h");
+        for name in ["base", "top"] {
+            let mut cmd = Command::new(cc_path);
+            cmd.arg(format!("tests/fixtures/c/capi/{name}.c"))
+                .args(["-shared", "-fPIC", "-nostdlib", "-o"])
+                .arg(out_dir.join(format!("libcapi_{name}.so")));
+            for arg in compiler.args() {
+                cmd.arg(arg);
+            }
+            let _ = cmd.status();
+        }
+        // Kept apart from the fixture named libc.so, which must not shadow the
+        // C library when linking
+        let host_dir = out_dir.join("capi");
+        cc::Build::new()
+            .file("tests/fixtures/c/capi/host.c")
+            .include("include")
+            .cargo_metadata(false)
+            .out_dir(&host_dir)
+            .compile("capi_host");
+        println!("cargo:rustc-link-search=native={}", host_dir.display());
+    }
+
+    // Copy the executables to target/ for mini-loader tests
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
     let target_dir = manifest_dir.join("target");
     if target_dir.exists() {
-        let dest = target_dir.join("exec_a");
-        let _ = std::fs::copy(&exec_a, &dest);
+        for exec in [&exec_a, &hello_static, &hello_static_pie, &hello_pie] {
+            let _ = std::fs::copy(exec, target_dir.join(exec.file_name().unwrap()));
+        }
     }
 }
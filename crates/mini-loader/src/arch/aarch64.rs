@@ -27,7 +27,11 @@ global_asm!(
 trampoline:
 	mov sp, x1
 	mov x1, x0
+	// rtld_fini
 	mov x0, #0
+	// Terminate the frame chain for unwinders
+	mov x29, #0
+	mov x30, #0
 	br x1
 	wfi"
 );
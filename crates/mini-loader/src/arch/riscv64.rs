@@ -8,10 +8,14 @@ global_asm!(
     .type _start,@function
 _start:
 	lla   gp, __global_pointer$
+    // 清空帧指针和返回地址，作为栈回溯的终点
+    li      fp, 0
+    li      ra, 0
     mv      a0, sp
     .weak   _DYNAMIC
     .hidden _DYNAMIC
     lla      a1, _DYNAMIC
+    andi    sp, sp, -16
     // 调用 rust_main 函数
     tail    rust_main
 "
@@ -28,6 +32,8 @@ trampoline:
 	mv      t0, a0
 	// rtld_fini
 	li      a0, 0
+    li      ra, 0
+    li      fp, 0
     jr      t0
 	ebreak
 "
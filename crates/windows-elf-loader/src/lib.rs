@@ -4,6 +4,7 @@ use std::sync::Arc;
 /// elf loader
 pub struct WinElfLoader {
     loader: Loader<WindowsMmap>,
+    lazy: Option<bool>,
 }
 
 impl WinElfLoader {
@@ -18,7 +19,13 @@ impl WinElfLoader {
         });
         loader.set_init(sysv_abi.clone());
         loader.set_fini(sysv_abi);
-        Self { loader }
+        Self { loader, lazy: None }
+    }
+
+    /// Whether dynamic libraries loaded afterwards use lazy binding.
+    pub fn set_lazy(&mut self, lazy: bool) -> &mut Self {
+        self.lazy = Some(lazy);
+        self
     }
 
     pub fn load_dylib(
@@ -27,11 +34,12 @@ impl WinElfLoader {
         bytes: impl AsRef<[u8]>,
     ) -> Result<ElfDylib, elf_loader::Error> {
         let object = ElfBinary::new(name, bytes.as_ref());
-        self.loader.load_dylib(object, Some(false))
+        self.loader
+            .load_dylib(object, Some(self.lazy.unwrap_or(false)))
     }
 
     pub fn load_file(&mut self, name: &str) -> Result<ElfDylib, elf_loader::Error> {
         let object = elf_loader::object::ElfFile::from_path(name)?;
-        self.loader.load_dylib(object, None)
+        self.loader.load_dylib(object, self.lazy)
     }
 }
@@ -0,0 +1,33 @@
+#![cfg(all(windows, target_arch = "x86_64"))]
+
+use std::{collections::HashMap, ffi::CStr};
+
+use windows_elf_loader::WinElfLoader;
+
+#[test]
+fn lazy_binding() {
+    extern "sysv64" fn print(s: *const i8) {
+        let s = unsafe { CStr::from_ptr(s).to_str().unwrap() };
+        println!("{}", s);
+    }
+
+    let mut map = HashMap::new();
+    map.insert("print", print as _);
+    let pre_find = |name: &str| -> Option<*const ()> { map.get(name).copied() };
+    let mut loader = WinElfLoader::new();
+    loader.set_lazy(true);
+    let liba = loader
+        .load_dylib("liba", include_bytes!("../example_dylib/liba.so"))
+        .unwrap()
+        .easy_relocate([], &pre_find)
+        .unwrap();
+    let libb = loader
+        .load_dylib("libb", include_bytes!("../example_dylib/libb.so"))
+        .unwrap()
+        .easy_relocate([&liba], &pre_find)
+        .unwrap();
+    // Calls through the PLT go through dl_runtime_resolve on first use.
+    let f = unsafe { libb.get::<extern "sysv64" fn() -> i32>("b").unwrap() };
+    assert_eq!(f(), 2);
+    assert_eq!(f(), 2);
+}
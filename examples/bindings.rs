@@ -0,0 +1,86 @@
+//! Prints every symbol binding of a small library tree, like `LD_DEBUG=bindings`
+//! does for the GNU dynamic linker.
+use elf_loader::{
+    Loader,
+    arch::{REL_GOT, REL_JUMP_SLOT, REL_SYMBOLIC},
+    image::LoadedCore,
+    input::ElfBinary,
+    relocation::{Auditor, SymbolBinding},
+};
+use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+use std::sync::Arc;
+
+struct Bindings;
+
+impl Auditor for Bindings {
+    fn object_loaded(&self, core: &LoadedCore<()>) {
+        println!("file={} [0];  relocated at {:#x}", core.name(), core.base());
+    }
+
+    fn symbol_bound(&self, binding: &SymbolBinding<'_>) -> Option<usize> {
+        println!(
+            "binding file {} [0] to {} [0]: normal symbol `{}' at {:#x}",
+            binding.requester(),
+            binding.provider().unwrap_or("<lookup>"),
+            binding.name(),
+            binding.addr()
+        );
+        None
+    }
+
+    fn lazy_resolved(&self, module: &str, name: &str, addr: usize) {
+        println!("binding file {module} [0] lazily: normal symbol `{name}' at {addr:#x}");
+    }
+}
+
+extern "C" fn host_add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+fn main() {
+    let arch = Arch::current();
+    let libc = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object("errno", &[0; 8])])
+        .unwrap();
+    let libfoo = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name("errno", REL_GOT),
+                RelocEntry::with_name("foo_table", REL_SYMBOLIC),
+                RelocEntry::with_name("host_add", REL_JUMP_SLOT),
+            ],
+            &[
+                SymbolDesc::undefined_object("errno"),
+                SymbolDesc::global_object("foo_table", &[0; 16]),
+                SymbolDesc::undefined_func("host_add"),
+            ],
+        )
+        .unwrap();
+
+    let mut loader = Loader::new();
+    loader.set_auditor(Arc::new(Bindings));
+    let libc = loader
+        .load_dylib(ElfBinary::new("libc.so", &libc.data))
+        .unwrap()
+        .relocator()
+        .relocate()
+        .unwrap();
+    let pre_find = |name: &str| (name == "host_add").then_some(host_add as *const ());
+    let libfoo = loader
+        .load_dylib(ElfBinary::new("libfoo.so", &libfoo.data))
+        .unwrap()
+        .relocator()
+        .scope([&libc])
+        .lazy_scope(pre_find)
+        .lazy(true)
+        .relocate()
+        .unwrap();
+
+    // The PLT entry is bound on the first call
+    let add = unsafe {
+        libfoo
+            .get::<extern "C" fn(i64, i64) -> i64>("host_add@helper")
+            .unwrap()
+    };
+    assert_eq!(add(2, 3), 5);
+}
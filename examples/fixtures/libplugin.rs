@@ -0,0 +1,25 @@
+#![no_std]
+#![crate_type = "cdylib"]
+#![crate_name = "plugin"]
+
+use core::panic::PanicInfo;
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    loop {}
+}
+
+// Stands in for `elf_loader::plugin`, this crate is built without dependencies
+#[macro_use]
+mod plugin {
+    include!("../../src/plugin/abi.rs");
+}
+
+pub type PluginEntry = extern "C" fn(u32) -> u32;
+
+#[unsafe(no_mangle)]
+extern "C" fn plugin_entry(x: u32) -> u32 {
+    x + 1
+}
+
+export_plugin!(PluginEntry, plugin_entry);
@@ -0,0 +1,90 @@
+//! Logs every call a library makes to other modules by redirecting its PLT
+//! entries through trampolines.
+use elf_loader::{Loader, arch::REL_JUMP_SLOT, input::ElfBinary};
+use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+use std::sync::{
+    Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+type HostFn = extern "C" fn(i64, i64) -> i64;
+
+extern "C" fn host_add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+extern "C" fn host_mul(a: i64, b: i64) -> i64 {
+    a * b
+}
+
+/// The original targets of the hooked slots
+static TARGETS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+/// The names of the hooked symbols
+static NAMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+macro_rules! trampoline {
+    ($name:ident, $idx:expr) => {
+        extern "C" fn $name(a: i64, b: i64) -> i64 {
+            let target: HostFn =
+                unsafe { core::mem::transmute(TARGETS[$idx].load(Ordering::Acquire)) };
+            let ret = target(a, b);
+            println!("{}({a}, {b}) = {ret}", NAMES.lock().unwrap()[$idx]);
+            ret
+        }
+    };
+}
+
+trampoline!(trampoline0, 0);
+trampoline!(trampoline1, 1);
+const TRAMPOLINES: [HostFn; 2] = [trampoline0, trampoline1];
+
+fn main() {
+    // The library calls host_add and host_mul through its PLT from the
+    // `host_add@helper` and `host_mul@helper` functions
+    let plugin = DylibWriter::new(Arch::current())
+        .write(
+            &[
+                RelocEntry::with_name("host_add", REL_JUMP_SLOT),
+                RelocEntry::with_name("host_mul", REL_JUMP_SLOT),
+            ],
+            &[
+                SymbolDesc::undefined_func("host_add"),
+                SymbolDesc::undefined_func("host_mul"),
+            ],
+        )
+        .unwrap();
+
+    let pre_find = |name: &str| -> Option<*const ()> {
+        match name {
+            "host_add" => Some(host_add as *const ()),
+            "host_mul" => Some(host_mul as *const ()),
+            _ => None,
+        }
+    };
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libplugin.so", &plugin.data))
+        .unwrap()
+        .relocator()
+        .pre_find_fn(pre_find)
+        .lazy(false)
+        .relocate()
+        .unwrap();
+
+    println!("GOT of the PLT: {:x?}", lib.got_plt_range());
+    for (idx, entry) in lib.plt_entries().enumerate() {
+        println!(
+            "hooking {} at {:p}, bound to {:#x}",
+            entry.symbol(),
+            entry.got_slot(),
+            entry.current_target()
+        );
+        TARGETS[idx].store(entry.current_target(), Ordering::Release);
+        NAMES.lock().unwrap().push(entry.symbol().to_string());
+        unsafe { entry.redirect(TRAMPOLINES[idx] as *const ()).unwrap() };
+    }
+
+    let add = unsafe { lib.get::<HostFn>("host_add@helper").unwrap() };
+    let mul = unsafe { lib.get::<HostFn>("host_mul@helper").unwrap() };
+    assert_eq!(add(2, 3), 5);
+    assert_eq!(mul(4, 5), 20);
+}
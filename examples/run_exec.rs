@@ -0,0 +1,40 @@
+//! Runs an executable in place of this program.
+//!
+//! ```text
+//! cargo run --example run_exec --features std -- [path] [args...]
+//! ```
+//!
+//! `path` defaults to `target/hello_static`. A dynamically linked executable such
+//! as `target/exec_a` is started through its interpreter, which relocates it.
+use elf_loader::{Loader, os::exec::StackBuilder};
+use std::env;
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        args.push("target/hello_static".into());
+    }
+    let envp = env::vars_os().map(|(key, value)| {
+        let mut entry = key;
+        entry.push("=");
+        entry.push(value);
+        entry
+    });
+    let stack = StackBuilder::new(&args, envp);
+
+    let mut loader = Loader::new();
+    let exec = loader.load_exec(args[0].as_str()).unwrap();
+    match exec.interp().map(String::from) {
+        // The interpreter maps the dependencies and relocates everything itself
+        Some(interp) => {
+            let interp = loader.load_dylib(interp.as_str()).unwrap();
+            let stack = stack.interpreter(interp).build_raw(&exec).unwrap();
+            unsafe { exec.run(stack) }
+        }
+        None => {
+            let exec = exec.relocator().relocate().unwrap();
+            let stack = stack.build(&exec).unwrap();
+            unsafe { exec.run(stack) }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Prints the trace events of a load without allocating, the way a `no_std`
+//! loader such as `mini-loader` can debug a failing load with nothing but its
+//! `print_str`.
+use core::fmt::Write;
+use elf_loader::{
+    Loader,
+    arch::REL_GOT,
+    input::ElfBinary,
+    trace::{TraceEvent, set_trace_fn},
+};
+use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+use std::io::Write as _;
+
+/// Stands in for `mini_loader::print_str`, which writes to fd 1 with a raw syscall
+fn print_str(s: &str) {
+    std::io::stdout().write_all(s.as_bytes()).unwrap();
+}
+
+/// A line formatted on the stack, truncated if it does not fit
+struct Line {
+    buf: [u8; 256],
+    len: usize,
+}
+
+impl Write for Line {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let n = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+fn trace(event: TraceEvent<'_>) {
+    let mut line = Line {
+        buf: [0; 256],
+        len: 0,
+    };
+    let _ = writeln!(line, "{event}");
+    // A truncated line may end in the middle of a character
+    let valid = match core::str::from_utf8(&line.buf[..line.len]) {
+        Ok(s) => s,
+        Err(err) => core::str::from_utf8(&line.buf[..err.valid_up_to()]).unwrap(),
+    };
+    print_str(valid);
+}
+
+fn main() {
+    let arch = Arch::current();
+    let libc = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object("errno", &[0; 8])])
+        .unwrap();
+    let libfoo = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name("errno", REL_GOT)],
+            &[SymbolDesc::undefined_object("errno")],
+        )
+        .unwrap();
+
+    set_trace_fn(Some(trace));
+    let mut loader = Loader::new();
+    let libc = loader
+        .load_dylib(ElfBinary::new("libc.so", &libc.data))
+        .unwrap()
+        .relocator()
+        .relocate()
+        .unwrap();
+    let _libfoo = loader
+        .load_dylib(ElfBinary::new("libfoo.so", &libfoo.data))
+        .unwrap()
+        .relocator()
+        .scope([&libc])
+        .relocate()
+        .unwrap();
+}
@@ -0,0 +1,108 @@
+#ifndef ELF_LOADER_H
+#define ELF_LOADER_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Maps libraries into memory.
+typedef struct ElfLoader ElfLoader;
+
+// A library, before or after relocation.
+typedef struct ElfModule ElfModule;
+
+// Finds the address of a symbol for [`elf_dylib_relocate`].
+//
+// It receives the `user` pointer given to [`elf_dylib_relocate`] and the
+// NUL-terminated name of the symbol, and returns its address, or a null
+// pointer to continue the search in the scope.
+typedef const void *(*ElfSymbolLookupFn)(void *user, const char *name);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the message of the last error of the calling thread.
+//
+// The string is owned by the library and stays valid until the next failing
+// call on the same thread. Returns a null pointer if no call failed yet.
+const char *elf_loader_last_error_message(void);
+
+// Creates a loader with the default settings.
+//
+// The loader must be released with [`elf_loader_free`].
+struct ElfLoader *elf_loader_new(void);
+
+// Releases a loader.
+//
+// The modules it loaded stay valid.
+//
+// # Safety
+// `loader` must be null or returned by [`elf_loader_new`], and not used
+// afterwards.
+void elf_loader_free(struct ElfLoader *loader);
+
+// Maps a dynamic library from memory.
+//
+// The bytes are copied, so they can be released once the call returns. The
+// library must be relocated with [`elf_dylib_relocate`] before its symbols
+// can be used.
+//
+// # Returns
+// 0 with the new handle in `out_handle`, or -1 on failure.
+//
+// # Safety
+// `loader` must be a valid loader, `name` a NUL-terminated string, `bytes`
+// valid for `len` bytes and `out_handle` valid for writes.
+int elf_loader_load_dylib(struct ElfLoader *loader,
+                          const char *name,
+                          const uint8_t *bytes,
+                          size_t len,
+                          struct ElfModule **out_handle);
+
+// Relocates a library.
+//
+// Symbols are searched with `pre_find` first, then in the `n` modules of
+// `scope`, in order, and nowhere else. Every symbol is bound during the
+// call, so `pre_find` and `user` are not used once it returns, and the call
+// fails if a symbol is not found.
+// The library keeps the modules of `scope` it depends on alive.
+//
+// # Returns
+// 0 on success, or -1 on failure. A library that fails to relocate can only
+// be released with [`elf_module_unload`].
+//
+// # Safety
+// `handle` must be a valid handle, `scope` must point to `n` valid handles
+// (or be null if `n` is 0) and `pre_find` must be safe to call with `user`.
+int elf_dylib_relocate(struct ElfModule *handle,
+                       const struct ElfModule *const *scope,
+                       size_t n,
+                       ElfSymbolLookupFn pre_find,
+                       void *user);
+
+// Gets the address of a symbol of a relocated module.
+//
+// # Returns
+// The address, or a null pointer if the module is not relocated or does not
+// define the symbol.
+//
+// # Safety
+// `handle` must be a valid handle and `name` a NUL-terminated string.
+void *elf_module_get(const struct ElfModule *handle, const char *name);
+
+// Releases a module handle.
+//
+// The library is unmapped, after its finalizers run, once no other module
+// depends on it.
+//
+// # Safety
+// `handle` must be null or a valid handle, and not used afterwards.
+void elf_module_unload(struct ElfModule *handle);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ELF_LOADER_H */
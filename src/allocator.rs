@@ -0,0 +1,191 @@
+//! Allocator of the per-load data of a loader
+//!
+//! With the `allocator_api` feature (nightly only), a loader created with
+//! [`Loader::new_in`](crate::Loader::new_in) allocates the buffers that every
+//! load creates from the given allocator: the header scratch buffer, the program
+//! headers, the name and path of each module and its `DT_NEEDED` list.
+//!
+//! Data shared through `Arc`, such as the core of a module or the loader
+//! handlers, stays on the global allocator, since `Arc` cannot be used with
+//! another allocator without becoming part of every public type. Each load
+//! makes only a few of these allocations. Without the feature, everything is
+//! allocated from the global allocator.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    ffi::CStr,
+    fmt::{Debug, Display},
+    ops::Deref,
+};
+
+#[cfg(feature = "allocator_api")]
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    ptr::NonNull,
+};
+
+#[cfg(all(feature = "allocator_api", not(feature = "portable-atomic")))]
+use alloc::sync::Arc;
+#[cfg(all(feature = "allocator_api", feature = "portable-atomic"))]
+use portable_atomic_util::Arc;
+
+/// A vector allocated from the allocator of a loader
+#[cfg(feature = "allocator_api")]
+pub(crate) type AllocVec<T> = Vec<T, LoaderAlloc>;
+/// A vector allocated from the allocator of a loader
+#[cfg(not(feature = "allocator_api"))]
+pub(crate) type AllocVec<T> = Vec<T>;
+
+/// A box allocated from the allocator of a loader
+#[cfg(feature = "allocator_api")]
+pub(crate) type AllocBox<T> = Box<T, LoaderAlloc>;
+/// A box allocated from the allocator of a loader
+#[cfg(not(feature = "allocator_api"))]
+pub(crate) type AllocBox<T> = Box<T>;
+
+/// The allocator of a loader, the global allocator unless one was provided
+#[derive(Clone, Default)]
+pub(crate) struct LoaderAlloc {
+    #[cfg(feature = "allocator_api")]
+    inner: Option<Arc<dyn Allocator + Send + Sync>>,
+}
+
+impl LoaderAlloc {
+    /// Uses `alloc` for the per-load data
+    #[cfg(feature = "allocator_api")]
+    pub(crate) fn new(alloc: impl Allocator + Send + Sync + 'static) -> Self {
+        Self {
+            inner: Some(Arc::new(alloc)),
+        }
+    }
+
+    /// Creates an empty vector
+    #[inline]
+    pub(crate) fn vec<T>(&self) -> AllocVec<T> {
+        #[cfg(feature = "allocator_api")]
+        return Vec::new_in(self.clone());
+        #[cfg(not(feature = "allocator_api"))]
+        Vec::new()
+    }
+
+    /// Creates a vector holding the items of `iter`
+    pub(crate) fn collect<T>(&self, iter: impl IntoIterator<Item = T>) -> AllocVec<T> {
+        let mut vec = self.vec();
+        vec.extend(iter);
+        vec
+    }
+
+    /// Moves `value` to the heap
+    #[inline]
+    pub(crate) fn boxed<T>(&self, value: T) -> AllocBox<T> {
+        #[cfg(feature = "allocator_api")]
+        return Box::new_in(value, self.clone());
+        #[cfg(not(feature = "allocator_api"))]
+        Box::new(value)
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl LoaderAlloc {
+    #[inline]
+    fn get(&self) -> &dyn Allocator {
+        match &self.inner {
+            Some(alloc) => &**alloc,
+            None => &alloc::alloc::Global,
+        }
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+unsafe impl Allocator for LoaderAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.get().allocate(layout)
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.get().allocate_zeroed(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { self.get().deallocate(ptr, layout) }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { self.get().grow(ptr, old_layout, new_layout) }
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { self.get().grow_zeroed(ptr, old_layout, new_layout) }
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        unsafe { self.get().shrink(ptr, old_layout, new_layout) }
+    }
+}
+
+/// A string stored with a terminating NUL, so that it is also a C string
+#[derive(Clone)]
+pub(crate) struct NulStr {
+    bytes: AllocBox<[u8]>,
+}
+
+impl NulStr {
+    /// Copies `s` into the allocator of a loader
+    pub(crate) fn new(s: &str, alloc: &LoaderAlloc) -> Self {
+        let mut bytes = alloc.vec();
+        bytes.reserve_exact(s.len() + 1);
+        bytes.extend_from_slice(s.as_bytes());
+        bytes.push(0);
+        Self {
+            bytes: bytes.into_boxed_slice(),
+        }
+    }
+
+    /// Gets the string without the terminating NUL
+    #[inline]
+    pub(crate) fn as_str(&self) -> &str {
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.bytes.len() - 1]) }
+    }
+
+    /// Gets the C string, which is empty if the string contains a NUL
+    #[inline]
+    pub(crate) fn as_c_str(&self) -> &CStr {
+        CStr::from_bytes_with_nul(&self.bytes).unwrap_or_default()
+    }
+}
+
+impl Deref for NulStr {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Debug for NulStr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for NulStr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
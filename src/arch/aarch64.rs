@@ -35,6 +35,45 @@ pub(crate) const DYLIB_OFFSET: usize = 1;
 /// Offset in GOT for resolver function pointer.
 pub(crate) const RESOLVE_FUNCTION_OFFSET: usize = 2;
 
+/// Removes the pointer authentication code from a code pointer.
+///
+/// Uses `XPACLRI`, which is in the hint space: on a CPU without pointer
+/// authentication it does nothing and the pointer is returned unchanged.
+#[cfg(feature = "aarch64-pac")]
+#[inline]
+pub(crate) fn strip_pac(ptr: usize) -> usize {
+    let mut lr = ptr;
+    unsafe {
+        // xpaclri
+        core::arch::asm!(
+            "hint #7",
+            inout("x30") lr,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    lr
+}
+
+/// Signs a code pointer with the IA key and a zero modifier.
+///
+/// Uses `PACIA1716`, which is in the hint space as well and leaves the
+/// pointer unchanged on a CPU without pointer authentication.
+#[cfg(feature = "aarch64-pac")]
+#[inline]
+pub(crate) fn sign_ia(ptr: usize) -> usize {
+    let mut x17 = ptr;
+    unsafe {
+        // pacia1716
+        core::arch::asm!(
+            "hint #8",
+            inout("x17") x17,
+            in("x16") 0usize,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    x17
+}
+
 /// Dynamic linker runtime resolver for AArch64 PLT entries.
 ///
 /// This function is called when a PLT entry needs to resolve a symbol address
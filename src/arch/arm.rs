@@ -30,6 +30,13 @@ pub const REL_COPY: u32 = R_ARM_COPY;
 /// TLS TPOFF relocation type - set to TLS offset relative to thread pointer.
 pub const REL_TPOFF: u32 = R_ARM_TLS_TPOFF32;
 
+/// Symbol type of Thumb functions emitted by older toolchains.
+///
+/// Thumb functions of both `STT_FUNC` and this type have bit 0 of their value
+/// set. The bit is kept in every address written by relocation, so that calls
+/// through the GOT switch to Thumb state.
+pub(crate) const STT_ARM_TFUNC: u8 = 13;
+
 /// Offset in GOT for dynamic library handle.
 pub(crate) const DYLIB_OFFSET: usize = 1;
 /// Offset in GOT for resolver function pointer.
@@ -111,6 +118,9 @@ pub(crate) fn rel_type_to_str(r_type: usize) -> &'static str {
         R_ARM_RELATIVE => "R_ARM_RELATIVE",
         R_ARM_IRELATIVE => "R_ARM_IRELATIVE",
         R_ARM_COPY => "R_ARM_COPY",
+        R_ARM_TLS_DTPMOD32 => "R_ARM_TLS_DTPMOD32",
+        R_ARM_TLS_DTPOFF32 => "R_ARM_TLS_DTPOFF32",
+        R_ARM_TLS_TPOFF32 => "R_ARM_TLS_TPOFF32",
         _ => "UNKNOWN",
     }
 }
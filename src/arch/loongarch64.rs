@@ -45,6 +45,11 @@ pub const REL_GOT: u32 = R_LARCH_64;
 pub(crate) const DYLIB_OFFSET: usize = 1;
 pub(crate) const RESOLVE_FUNCTION_OFFSET: usize = 0;
 
+/// Lazy binding trampoline, entered from PLT0 with GOT[1] in `$t0` and the
+/// offset of the GOT slot from the first PLT slot in `$t1`.
+///
+/// Unlike x86_64, where the PLT entry pushes the relocation index, the index is
+/// recovered from the slot offset, which is 8 bytes per entry.
 #[unsafe(naked)]
 pub(crate) extern "C" fn dl_runtime_resolve() {
     core::arch::naked_asm!(
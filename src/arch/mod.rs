@@ -59,6 +59,20 @@ cfg_if::cfg_if! {
 
 pub const REL_NONE: u32 = 0;
 
+/// Pointer authentication only exists on AArch64, other pointers carry no code
+#[cfg(all(feature = "aarch64-pac", not(target_arch = "aarch64")))]
+#[inline]
+pub(crate) fn strip_pac(ptr: usize) -> usize {
+    ptr
+}
+
+/// Pointer authentication only exists on AArch64, other pointers are not signed
+#[cfg(all(feature = "aarch64-pac", not(target_arch = "aarch64")))]
+#[inline]
+pub(crate) fn sign_ia(ptr: usize) -> usize {
+    ptr
+}
+
 #[inline]
 pub(crate) fn prepare_lazy_bind(got: *mut usize, dylib: usize) {
     // 这是安全的，延迟绑定时库是存在的
@@ -29,6 +29,8 @@ pub const REL_IRELATIVE: u32 = R_RISCV_IRELATIVE;
 pub const REL_COPY: u32 = R_RISCV_COPY;
 /// TLS TPOFF relocation type - set to TLS offset relative to thread pointer.
 pub const REL_TPOFF: u32 = R_RISCV_TLS_TPREL64;
+/// TLS descriptor relocation type - set a two-word descriptor to a resolver and its argument.
+pub const REL_TLSDESC: u32 = 12;
 
 /// Offset in GOT for dynamic library handle.
 pub(crate) const DYLIB_OFFSET: usize = 1;
@@ -157,6 +159,26 @@ riscv64_dl_runtime_resolve!(
 #[cfg(not(target_feature = "f"))]
 riscv64_dl_runtime_resolve!("", "");
 
+/// TLS descriptor resolver for variables in the static TLS block.
+///
+/// The caller passes the address of the descriptor in `a0` and the return
+/// address in `t0`. The second word of the descriptor already holds the offset
+/// of the variable from the thread pointer, so it is returned as is. No other
+/// register is touched.
+///
+/// # Safety
+/// This function uses naked assembly and must only be called through a TLS
+/// descriptor by code following the RISC-V TLSDESC calling convention.
+#[unsafe(naked)]
+pub(crate) extern "C" fn tlsdesc_static() {
+    core::arch::naked_asm!(
+        "
+        ld a0,8(a0)
+        jr t0
+        "
+    )
+}
+
 /// Map riscv64 relocation types to human readable names
 pub(crate) fn rel_type_to_str(r_type: usize) -> &'static str {
     match r_type as u32 {
@@ -166,6 +188,10 @@ pub(crate) fn rel_type_to_str(r_type: usize) -> &'static str {
         R_RISCV_COPY => "R_RISCV_COPY",
         R_RISCV_JUMP_SLOT => "R_RISCV_JUMP_SLOT",
         R_RISCV_IRELATIVE => "R_RISCV_IRELATIVE",
+        R_RISCV_TLS_DTPMOD64 => "R_RISCV_TLS_DTPMOD64",
+        R_RISCV_TLS_DTPREL64 => "R_RISCV_TLS_DTPREL64",
+        R_RISCV_TLS_TPREL64 => "R_RISCV_TLS_TPREL64",
+        REL_TLSDESC => "R_RISCV_TLSDESC",
         _ => "UNKNOWN",
     }
 }
@@ -31,9 +31,23 @@ pub const REL_IRELATIVE: u32 = R_386_IRELATIVE;
 pub const REL_COPY: u32 = R_386_COPY;
 pub const REL_TPOFF: u32 = R_386_TLS_TPOFF;
 
+/// Offset in GOT for dynamic library handle.
 pub(crate) const DYLIB_OFFSET: usize = 1;
+/// Offset in GOT for resolver function pointer.
 pub(crate) const RESOLVE_FUNCTION_OFFSET: usize = 2;
 
+/// The PLT entries push the byte offset of their `.rel.plt` entry, while
+/// `dl_fixup` takes its index: the offset is shifted right by this amount.
+const PLT_REL_SHIFT: u32 = size_of::<crate::elf::ElfRelType>().trailing_zeros();
+
+/// Dynamic linker runtime resolver for x86 PLT entries.
+///
+/// PLT0 pushes GOT[1], the module handle, on top of the relocation offset
+/// pushed by the PLT entry, then jumps here through GOT[2]. The module handle
+/// and the relocation index are passed to `dl_fixup` on the stack, as the
+/// i386 C ABI requires, and the resolved function is entered with the
+/// caller's registers and return address in place.
+
 #[unsafe(naked)]
 pub(crate) extern "C" fn dl_runtime_resolve() {
     core::arch::naked_asm!(
@@ -52,9 +66,9 @@ pub(crate) extern "C" fn dl_runtime_resolve() {
     // [esp + 20] : 返回地址
 
     // 准备 dl_fixup(link_map, reloc_idx) 的参数
-    // reloc_idx = reloc_offset / 8 (x86 Rel 条目大小为 8)
+    // reloc_idx = reloc_offset / sizeof(Elf32_Rel)
     mov eax, [esp + 16]
-    shr eax, 3
+    shr eax, {1}
     
     push eax         // 参数 2: reloc_idx
     push dword ptr [esp + 16]  // 参数 1: link_map (原本在 +12，现在因为 push eax 变成了 +16)
@@ -79,6 +93,7 @@ pub(crate) extern "C" fn dl_runtime_resolve() {
     ret
     ",
         sym crate::relocation::dl_fixup,
+        const PLT_REL_SHIFT,
     )
 }
 
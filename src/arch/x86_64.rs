@@ -4,8 +4,11 @@
 //! dynamic linking, and procedure linkage table (PLT) handling.
 
 use crate::{
+    RelocationErrorContext,
     elf::ElfRelType,
-    relocation::{RelocValue, StaticReloc, SymbolLookup, find_symbol_addr, reloc_error},
+    relocation::{
+        RelocValue, StaticReloc, SymbolLookup, find_symbol_addr, reloc_error, searched_sources,
+    },
     segment::section::{GotEntry, PltEntry, PltGotSection},
 };
 use elf::abi::*;
@@ -54,23 +57,14 @@ pub(crate) const PLT_ENTRY: [u8; PLT_ENTRY_SIZE] = [
     0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, // (padding)
 ];
 
-/// Dynamic linker runtime resolver for x86-64 PLT entries.
-///
-/// This function is called when a PLT entry needs to resolve a symbol address
-/// at runtime. It saves the current register state, calls the dynamic linker
-/// resolution function, and then restores the state before jumping to the
-/// resolved function.
+/// Expands to the body of `dl_runtime_resolve` around the given call sequence.
 ///
-/// The function preserves all caller-saved registers and SIMD registers
-/// to ensure compatibility with various calling conventions.
-///
-/// # Safety
-/// This function uses naked assembly and must be called with the correct
-/// stack layout set up by the PLT stub code.
-#[unsafe(naked)]
-pub(crate) extern "C" fn dl_runtime_resolve() {
-    core::arch::naked_asm!(
-        "
+/// Both variants save and restore the same register set; only the way the
+/// arguments are handed to `dl_fixup` differs between the SysV and Win64 ABIs.
+macro_rules! dl_runtime_resolve_asm {
+    ($call:literal) => {
+        concat!(
+            "
     // Save caller-saved registers
     push rdi
     push rsi
@@ -102,12 +96,9 @@ pub(crate) extern "C" fn dl_runtime_resolve() {
     // [rsp + 200]     : link_map
     // [rsp + 208]     : reloc_idx
     // [rsp + 216]     : return address to caller
-    mov rdi, [rsp + 200]
-    mov rsi, [rsp + 208]
-
-    // Call the resolver
-    call {0}
-
+",
+            $call,
+            "
     // Restore xmm registers
     movdqu xmm0, [rsp + 0]
     movdqu xmm1, [rsp + 16]
@@ -134,7 +125,68 @@ pub(crate) extern "C" fn dl_runtime_resolve() {
 
     // Jump to the resolved function
     jmp rax
-    ",
+"
+        )
+    };
+}
+
+/// Dynamic linker runtime resolver for x86-64 PLT entries.
+///
+/// This function is called when a PLT entry needs to resolve a symbol address
+/// at runtime. It saves the current register state, calls the dynamic linker
+/// resolution function, and then restores the state before jumping to the
+/// resolved function.
+///
+/// The function preserves all caller-saved registers and SIMD registers
+/// to ensure compatibility with various calling conventions.
+///
+/// # Safety
+/// This function uses naked assembly and must be called with the correct
+/// stack layout set up by the PLT stub code.
+#[cfg(not(windows))]
+#[unsafe(naked)]
+pub(crate) extern "C" fn dl_runtime_resolve() {
+    core::arch::naked_asm!(
+        dl_runtime_resolve_asm!(
+            "
+    mov rdi, [rsp + 200]
+    mov rsi, [rsp + 208]
+
+    // Call the resolver
+    call {0}
+"
+        ),
+        sym crate::relocation::dl_fixup,
+    )
+}
+
+/// Dynamic linker runtime resolver for x86-64 PLT entries on Windows.
+///
+/// The loaded ELF code follows the SysV ABI, so the full SysV argument
+/// register set (rdi, rsi, rdx, rcx, r8, r9 and xmm0-xmm7) is preserved
+/// exactly as on other targets. `dl_fixup` itself is compiled for the Win64
+/// ABI, which takes its arguments in rcx/rdx and expects 32 bytes of shadow
+/// space above the return address.
+///
+/// # Safety
+/// This function uses naked assembly and must be called with the correct
+/// stack layout set up by the PLT stub code.
+#[cfg(windows)]
+#[unsafe(naked)]
+pub(crate) extern "C" fn dl_runtime_resolve() {
+    core::arch::naked_asm!(
+        dl_runtime_resolve_asm!(
+            "
+    mov rcx, [rsp + 200]
+    mov rdx, [rsp + 208]
+
+    // Reserve the shadow space required by the Win64 ABI; the stack stays
+    // 16-byte aligned at the call
+    sub rsp, 32
+    call {0}
+    add rsp, 32
+"
+        ),
         sym crate::relocation::dl_fixup,
     )
 }
@@ -218,7 +270,14 @@ impl StaticReloc for X86_64Relocator {
         let find_symbol = |r_sym: usize| {
             find_symbol_addr(pre_find, post_find, core, symtab, scope, r_sym).map(|(val, _)| val)
         };
-        let boxed_error = || reloc_error(rel_type, "unknown symbol", core);
+        let context = || RelocationErrorContext::new(rel_type, core);
+        let boxed_error = || {
+            let context = match r_sym {
+                0 => context(),
+                _ => context().with_searched(searched_sources(scope)),
+            };
+            reloc_error(context, "unknown symbol")
+        };
         match r_type as _ {
             R_X86_64_64 => {
                 let Some(sym) = find_symbol(r_sym) else {
@@ -231,11 +290,7 @@ impl StaticReloc for X86_64Relocator {
                     return Err(boxed_error());
                 };
                 let val: RelocValue<i32> = (sym + append - p).try_into().map_err(|_| {
-                    reloc_error(
-                        rel_type,
-                        "out of range integral type conversion attempted",
-                        core,
-                    )
+                    reloc_error(context(), "out of range integral type conversion attempted")
                 })?;
                 segments.write(offset, val);
             }
@@ -282,11 +337,7 @@ impl StaticReloc for X86_64Relocator {
                     return Err(boxed_error());
                 };
                 let val: RelocValue<u32> = (sym + append).try_into().map_err(|_| {
-                    reloc_error(
-                        rel_type,
-                        "out of range integral type conversion attempted",
-                        core,
-                    )
+                    reloc_error(context(), "out of range integral type conversion attempted")
                 })?;
                 segments.write(offset, val);
             }
@@ -295,11 +346,7 @@ impl StaticReloc for X86_64Relocator {
                     return Err(boxed_error());
                 };
                 let val: RelocValue<i32> = (sym + append).try_into().map_err(|_| {
-                    reloc_error(
-                        rel_type,
-                        "out of range integral type conversion attempted",
-                        core,
-                    )
+                    reloc_error(context(), "out of range integral type conversion attempted")
                 })?;
                 segments.write(offset, val);
             }
@@ -0,0 +1,302 @@
+//! C API
+//!
+//! An `extern "C"` layer for hosts written in C or C++. The declarations are
+//! in `include/elf_loader.h`, generated by `cbindgen` from this module. Build
+//! the crate as a static or shared library with the feature enabled:
+//!
+//! ```text
+//! cargo rustc --release --lib --features capi --crate-type staticlib
+//! ```
+//!
+//! Libraries are handed out as opaque [`ElfModule`] handles. A module keeps the
+//! modules it was relocated against alive, so handles can be released in any
+//! order with [`elf_module_unload`]. Functions that fail return a negative
+//! value or a null pointer, and [`elf_loader_last_error_message`] describes
+//! the failure.
+use crate::{
+    Error, Loader,
+    image::{LoadedDylib, RawDylib},
+    input::ElfBinary,
+    os::DefaultMmap,
+    relocation::SymbolLookup,
+};
+use alloc::{boxed::Box, ffi::CString, string::ToString, vec::Vec};
+use core::{
+    cell::RefCell,
+    ffi::{CStr, c_char, c_int, c_void},
+    ptr,
+};
+
+/// Finds the address of a symbol for [`elf_dylib_relocate`].
+///
+/// It receives the `user` pointer given to [`elf_dylib_relocate`] and the
+/// NUL-terminated name of the symbol, and returns its address, or a null
+/// pointer to continue the search in the scope.
+pub type ElfSymbolLookupFn =
+    Option<unsafe extern "C" fn(user: *mut c_void, name: *const c_char) -> *const c_void>;
+
+/// Maps libraries into memory.
+pub struct ElfLoader {
+    inner: Loader<DefaultMmap, (), ()>,
+}
+
+/// A library, before or after relocation.
+pub struct ElfModule {
+    state: ModuleState,
+}
+
+enum ModuleState {
+    Raw(Box<RawDylib<()>>),
+    Loaded(LoadedDylib<()>),
+    /// Relocation failed and consumed the library
+    Failed,
+}
+
+std::thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records the error of the current call for `elf_loader_last_error_message`
+#[cold]
+fn set_last_error(message: impl ToString) {
+    let message = message.to_string().replace('\0', "\\0");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+/// Records `err` and returns the error code of the C API
+#[cold]
+fn fail(err: Error) -> c_int {
+    set_last_error(err);
+    -1
+}
+
+/// Reads a NUL-terminated UTF-8 argument
+unsafe fn str_arg<'a>(ptr: *const c_char, what: &str) -> Option<&'a str> {
+    if ptr.is_null() {
+        set_last_error(alloc::format!("{what} is null"));
+        return None;
+    }
+    match unsafe { CStr::from_ptr(ptr) }.to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_last_error(alloc::format!("{what} is not valid UTF-8"));
+            None
+        }
+    }
+}
+
+/// A lookup calling back into the host
+struct CallbackLookup {
+    callback: unsafe extern "C" fn(*mut c_void, *const c_char) -> *const c_void,
+    user: *mut c_void,
+}
+
+impl SymbolLookup for CallbackLookup {
+    fn lookup(&self, name: &str) -> Option<*const ()> {
+        let name = CString::new(name).ok()?;
+        let addr = unsafe { (self.callback)(self.user, name.as_ptr()) };
+        (!addr.is_null()).then_some(addr.cast())
+    }
+}
+
+/// Returns the message of the last error of the calling thread.
+///
+/// The string is owned by the library and stays valid until the next failing
+/// call on the same thread. Returns a null pointer if no call failed yet.
+#[unsafe(no_mangle)]
+pub extern "C" fn elf_loader_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Creates a loader with the default settings.
+///
+/// The loader must be released with [`elf_loader_free`].
+#[unsafe(no_mangle)]
+pub extern "C" fn elf_loader_new() -> *mut ElfLoader {
+    Box::into_raw(Box::new(ElfLoader {
+        inner: Loader::new(),
+    }))
+}
+
+/// Releases a loader.
+///
+/// The modules it loaded stay valid.
+///
+/// # Safety
+/// `loader` must be null or returned by [`elf_loader_new`], and not used
+/// afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn elf_loader_free(loader: *mut ElfLoader) {
+    if !loader.is_null() {
+        drop(unsafe { Box::from_raw(loader) });
+    }
+}
+
+/// Maps a dynamic library from memory.
+///
+/// The bytes are copied, so they can be released once the call returns. The
+/// library must be relocated with [`elf_dylib_relocate`] before its symbols
+/// can be used.
+///
+/// # Returns
+/// 0 with the new handle in `out_handle`, or -1 on failure.
+///
+/// # Safety
+/// `loader` must be a valid loader, `name` a NUL-terminated string, `bytes`
+/// valid for `len` bytes and `out_handle` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn elf_loader_load_dylib(
+    loader: *mut ElfLoader,
+    name: *const c_char,
+    bytes: *const u8,
+    len: usize,
+    out_handle: *mut *mut ElfModule,
+) -> c_int {
+    let Some(loader) = (unsafe { loader.as_mut() }) else {
+        set_last_error("loader is null");
+        return -1;
+    };
+    let Some(name) = (unsafe { str_arg(name, "name") }) else {
+        return -1;
+    };
+    if bytes.is_null() || out_handle.is_null() {
+        set_last_error("bytes or out_handle is null");
+        return -1;
+    }
+    let bytes = unsafe { core::slice::from_raw_parts(bytes, len) };
+    match loader.inner.load_dylib(ElfBinary::new(name, bytes)) {
+        Ok(lib) => {
+            let module = Box::new(ElfModule {
+                state: ModuleState::Raw(Box::new(lib)),
+            });
+            unsafe { out_handle.write(Box::into_raw(module)) };
+            0
+        }
+        Err(err) => fail(err),
+    }
+}
+
+/// Relocates a library.
+///
+/// Symbols are searched with `pre_find` first, then in the `n` modules of
+/// `scope`, in order, and nowhere else. Every symbol is bound during the
+/// call, so `pre_find` and `user` are not used once it returns, and the call
+/// fails if a symbol is not found.
+/// The library keeps the modules of `scope` it depends on alive.
+///
+/// # Returns
+/// 0 on success, or -1 on failure. A library that fails to relocate can only
+/// be released with [`elf_module_unload`].
+///
+/// # Safety
+/// `handle` must be a valid handle, `scope` must point to `n` valid handles
+/// (or be null if `n` is 0) and `pre_find` must be safe to call with `user`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn elf_dylib_relocate(
+    handle: *mut ElfModule,
+    scope: *const *const ElfModule,
+    n: usize,
+    pre_find: ElfSymbolLookupFn,
+    user: *mut c_void,
+) -> c_int {
+    let Some(module) = (unsafe { handle.as_mut() }) else {
+        set_last_error("handle is null");
+        return -1;
+    };
+    let scope: &[*const ElfModule] = if n == 0 {
+        &[]
+    } else if scope.is_null() {
+        set_last_error("scope is null");
+        return -1;
+    } else {
+        unsafe { core::slice::from_raw_parts(scope, n) }
+    };
+    let mut libs = Vec::with_capacity(scope.len());
+    for &dep in scope {
+        match unsafe { dep.as_ref() }.map(|dep| &dep.state) {
+            Some(ModuleState::Loaded(lib)) => libs.push(lib.clone()),
+            _ => {
+                set_last_error("scope contains a module that is not relocated");
+                return -1;
+            }
+        }
+    }
+    let lib = match core::mem::replace(&mut module.state, ModuleState::Failed) {
+        ModuleState::Raw(lib) => lib,
+        state => {
+            module.state = state;
+            set_last_error("module is already relocated or failed to relocate");
+            return -1;
+        }
+    };
+    // Undefined symbols fail the call instead of leaving their slots unbound
+    let relocator = (*lib)
+        .relocator()
+        .scope(&libs)
+        .lazy(false)
+        .collect_missing(true);
+    let result = match pre_find {
+        Some(callback) => relocator
+            .pre_find(CallbackLookup { callback, user })
+            .relocate(),
+        None => relocator.relocate(),
+    };
+    match result {
+        Ok(lib) => {
+            module.state = ModuleState::Loaded(lib);
+            0
+        }
+        Err(err) => fail(err),
+    }
+}
+
+/// Gets the address of a symbol of a relocated module.
+///
+/// # Returns
+/// The address, or a null pointer if the module is not relocated or does not
+/// define the symbol.
+///
+/// # Safety
+/// `handle` must be a valid handle and `name` a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn elf_module_get(
+    handle: *const ElfModule,
+    name: *const c_char,
+) -> *mut c_void {
+    let Some(module) = (unsafe { handle.as_ref() }) else {
+        set_last_error("handle is null");
+        return ptr::null_mut();
+    };
+    let Some(name) = (unsafe { str_arg(name, "name") }) else {
+        return ptr::null_mut();
+    };
+    let ModuleState::Loaded(lib) = &module.state else {
+        set_last_error("module is not relocated");
+        return ptr::null_mut();
+    };
+    match unsafe { lib.get::<()>(name) } {
+        Some(sym) => sym.into_raw() as *mut c_void,
+        None => {
+            set_last_error(alloc::format!("{}: undefined symbol: {name}", lib.name()));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Releases a module handle.
+///
+/// The library is unmapped, after its finalizers run, once no other module
+/// depends on it.
+///
+/// # Safety
+/// `handle` must be null or a valid handle, and not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn elf_module_unload(handle: *mut ElfModule) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
@@ -0,0 +1,205 @@
+//! Debugger interface (`r_debug`)
+//!
+//! Debuggers such as GDB and LLDB discover shared libraries through the
+//! `r_debug` structure of the dynamic linker: they walk its list of
+//! `link_map` entries and place a breakpoint on the `r_brk` function, which
+//! the linker calls before and after every change to that list.
+//!
+//! Modules added to the process-wide registry (see
+//! [`Loader::enable_registry`](crate::Loader::enable_registry)) are published
+//! in a crate-owned [`RDebug`] in the same way. Since a debugger only knows
+//! the `r_debug` of the system dynamic linker, [`chain_r_debug`] can link the
+//! crate-owned one into the namespace chain of the system one.
+use crate::{elf::Dyn, image::LinkMapView};
+use alloc::{boxed::Box, vec::Vec};
+use core::{cell::UnsafeCell, ffi::c_char, ptr::null_mut};
+use spin::Mutex;
+
+/// The list of modules is consistent.
+pub const RT_CONSISTENT: i32 = 0;
+/// A module is being added to the list.
+pub const RT_ADD: i32 = 1;
+/// A module is being removed from the list.
+pub const RT_DELETE: i32 = 2;
+
+/// A module entry of the debugger interface, laid out like glibc's public
+/// `struct link_map`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct LinkMap {
+    /// Difference between the load address and the link-time address
+    pub l_addr: usize,
+    /// NUL-terminated name of the module
+    pub l_name: *const c_char,
+    /// Address of the dynamic section
+    pub l_ld: *const Dyn,
+    /// Next entry of the list
+    pub l_next: *mut LinkMap,
+    /// Previous entry of the list
+    pub l_prev: *mut LinkMap,
+}
+
+/// The debugger interface, laid out like glibc's `struct r_debug_extended`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RDebug {
+    /// Version of the protocol; `2` means that `r_next` is valid
+    pub r_version: i32,
+    /// Head of the list of loaded modules
+    pub r_map: *mut LinkMap,
+    /// Address of the function called around every change of the list
+    pub r_brk: usize,
+    /// One of [`RT_CONSISTENT`], [`RT_ADD`] or [`RT_DELETE`]
+    pub r_state: i32,
+    /// Base address of the dynamic linker
+    pub r_ldbase: usize,
+    /// Next namespace in the chain
+    pub r_next: *mut RDebug,
+}
+
+struct DebugCell(UnsafeCell<RDebug>);
+
+// Safety: the structure is only modified while `STATE` is locked
+unsafe impl Sync for DebugCell {}
+
+static R_DEBUG: DebugCell = DebugCell(UnsafeCell::new(RDebug {
+    r_version: 2,
+    r_map: null_mut(),
+    r_brk: 0,
+    r_state: RT_CONSISTENT,
+    r_ldbase: 0,
+    r_next: null_mut(),
+}));
+
+/// A published module
+struct Node {
+    /// Address of the module's `CoreInner`, used to identify it on removal
+    id: usize,
+    map: *mut LinkMap,
+}
+
+// Safety: the nodes are owned by `STATE` and only accessed while it is locked
+unsafe impl Send for Node {}
+
+static STATE: Mutex<Vec<Node>> = Mutex::new(Vec::new());
+
+/// The breakpoint function of the crate-owned [`RDebug`].
+///
+/// It does nothing; a debugger places a breakpoint on it to be notified of
+/// changes to the list of modules.
+#[inline(never)]
+pub extern "C" fn dl_debug_state() {
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+/// Returns the crate-owned [`RDebug`].
+///
+/// The structure may only be read while no module is loaded or unloaded.
+pub fn r_debug() -> *mut RDebug {
+    R_DEBUG.0.get()
+}
+
+/// Links the crate-owned [`RDebug`] into the namespace chain of `system`.
+///
+/// Debuggers that understand `r_debug_extended` (GDB 12 and later) then list
+/// the modules of this crate as a separate namespace. The breakpoint function
+/// of `system` is called around every change, so the debugger is notified
+/// through the breakpoint it already has.
+///
+/// # Safety
+/// `system` must point to a valid `r_debug_extended` that outlives the
+/// program, like the `_r_debug` of glibc 2.35 and later. Its chain is extended
+/// without taking the lock of the system dynamic linker.
+///
+/// # Returns
+/// `false` if `system` does not support chaining (`r_version < 2`).
+pub unsafe fn chain_r_debug(system: *mut RDebug) -> bool {
+    let _state = STATE.lock();
+    let own = r_debug();
+    unsafe {
+        if (*system).r_version < 2 {
+            return false;
+        }
+        let mut tail = system;
+        while !(*tail).r_next.is_null() {
+            if tail == own {
+                return true;
+            }
+            tail = (*tail).r_next;
+        }
+        if tail != own {
+            if (*system).r_brk != 0 {
+                (*own).r_brk = (*system).r_brk;
+            }
+            (*tail).r_next = own;
+        }
+    }
+    true
+}
+
+/// Returns the `_r_debug` of the system dynamic linker.
+#[cfg(all(feature = "std", target_os = "linux", target_env = "gnu"))]
+pub fn system_r_debug() -> *mut RDebug {
+    unsafe extern "C" {
+        static mut _r_debug: RDebug;
+    }
+    &raw mut _r_debug
+}
+
+/// Calls the breakpoint function after setting the state
+unsafe fn notify(r_debug: *mut RDebug, state: i32) {
+    unsafe {
+        (*r_debug).r_state = state;
+        if (*r_debug).r_brk == 0 {
+            (*r_debug).r_brk = dl_debug_state as *const () as usize;
+        }
+        let brk: extern "C" fn() = core::mem::transmute((*r_debug).r_brk);
+        brk();
+    }
+}
+
+/// Appends a module to the list.
+pub(crate) fn add(id: usize, view: LinkMapView) {
+    let mut state = STATE.lock();
+    let r_debug = r_debug();
+    let map = Box::into_raw(Box::new(LinkMap {
+        l_addr: view.l_addr,
+        l_name: view.l_name,
+        l_ld: view.l_ld,
+        l_next: null_mut(),
+        l_prev: state.last().map_or(null_mut(), |node| node.map),
+    }));
+    unsafe {
+        notify(r_debug, RT_ADD);
+        match state.last() {
+            Some(node) => (*node.map).l_next = map,
+            None => (*r_debug).r_map = map,
+        }
+        state.push(Node { id, map });
+        notify(r_debug, RT_CONSISTENT);
+    }
+}
+
+/// Removes a module from the list.
+pub(crate) fn remove(id: usize) {
+    let mut state = STATE.lock();
+    let Some(idx) = state.iter().position(|node| node.id == id) else {
+        return;
+    };
+    let r_debug = r_debug();
+    let node = state.remove(idx);
+    unsafe {
+        notify(r_debug, RT_DELETE);
+        let LinkMap { l_next, l_prev, .. } = *node.map;
+        if l_prev.is_null() {
+            (*r_debug).r_map = l_next;
+        } else {
+            (*l_prev).l_next = l_next;
+        }
+        if !l_next.is_null() {
+            (*l_next).l_prev = l_prev;
+        }
+        notify(r_debug, RT_CONSISTENT);
+        drop(Box::from_raw(node.map));
+    }
+}
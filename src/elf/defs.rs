@@ -8,10 +8,10 @@
 use core::ops::{Deref, DerefMut};
 use elf::abi::{
     SHN_UNDEF, STB_GLOBAL, STB_GNU_UNIQUE, STB_LOCAL, STB_WEAK, STT_COMMON, STT_FUNC,
-    STT_GNU_IFUNC, STT_NOTYPE, STT_OBJECT, STT_TLS,
+    STT_GNU_IFUNC, STT_NOTYPE, STT_OBJECT, STT_TLS, STV_DEFAULT, STV_PROTECTED,
 };
 
-use crate::arch::rel_type_to_str;
+use crate::arch::{REL_GOT, REL_JUMP_SLOT, rel_type_to_str};
 
 /// Valid symbol binding types bitmask.
 /// This mask includes STB_GLOBAL, STB_WEAK, and STB_GNU_UNIQUE bindings.
@@ -19,6 +19,7 @@ const OK_BINDS: usize = 1 << STB_GLOBAL | 1 << STB_WEAK | 1 << STB_GNU_UNIQUE;
 
 /// Valid symbol type bitmask.
 /// This mask includes STT_NOTYPE, STT_OBJECT, STT_FUNC, STT_COMMON, STT_TLS, and STT_GNU_IFUNC types.
+#[cfg(not(target_arch = "arm"))]
 const OK_TYPES: usize = 1 << STT_NOTYPE
     | 1 << STT_OBJECT
     | 1 << STT_FUNC
@@ -26,6 +27,17 @@ const OK_TYPES: usize = 1 << STT_NOTYPE
     | 1 << STT_TLS
     | 1 << STT_GNU_IFUNC;
 
+/// Valid symbol type bitmask.
+/// On ARM, this also includes the STT_ARM_TFUNC Thumb functions of older toolchains.
+#[cfg(target_arch = "arm")]
+const OK_TYPES: usize = 1 << STT_NOTYPE
+    | 1 << STT_OBJECT
+    | 1 << STT_FUNC
+    | 1 << STT_COMMON
+    | 1 << STT_TLS
+    | 1 << STT_GNU_IFUNC
+    | 1 << crate::arch::STT_ARM_TFUNC;
+
 cfg_if::cfg_if! {
     if #[cfg(target_pointer_width = "64")]{
         pub(crate) const E_CLASS: u8 = elf::abi::ELFCLASS64;
@@ -61,7 +73,7 @@ cfg_if::cfg_if! {
 /// 32-bit ELF symbol table entry.
 /// This struct represents the native 32-bit symbol format used in ELF32 files.
 /// For 64-bit targets, the `Sym` type alias points to `elf::symbol::Elf64_Sym` instead.
-struct Elf32Sym {
+pub(crate) struct Elf32Sym {
     pub st_name: u32,
     pub st_value: u32,
     pub st_size: u32,
@@ -129,12 +141,40 @@ impl ElfRela {
         self.rela.r_addend as isize
     }
 
+    /// Returns the addend the relocation is applied with.
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn reloc_addend(&self, base: usize) -> isize {
+        self.r_addend(base)
+    }
+
     /// Sets the relocation offset.
     /// This is used internally when adjusting relocation entries during loading.
     #[inline]
     pub(crate) fn set_offset(&mut self, offset: usize) {
         self.rela.r_offset = offset as _;
     }
+
+    /// Creates a RELA entry from its raw parts.
+    /// This is used internally when converting REL tables into the native format.
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn new(r_offset: usize, r_info: usize, r_addend: isize) -> Self {
+        ElfRela {
+            rela: Rela {
+                r_offset: r_offset as _,
+                r_info: r_info as _,
+                r_addend: r_addend as _,
+            },
+        }
+    }
+
+    /// Returns the raw `r_info` field.
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn r_info(&self) -> usize {
+        self.rela.r_info as usize
+    }
 }
 
 /// ELF REL relocation entry.
@@ -174,6 +214,19 @@ impl ElfRel {
         unsafe { ptr.read() as isize }
     }
 
+    /// Returns the addend the relocation is applied with.
+    ///
+    /// GOT and jump slot entries are overwritten with the symbol address: the
+    /// word the linker left at their target is not an addend.
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn reloc_addend(&self, base: usize) -> isize {
+        match self.r_type() as u32 {
+            REL_GOT | REL_JUMP_SLOT => 0,
+            _ => self.r_addend(base),
+        }
+    }
+
     /// Sets the relocation offset.
     /// This is used internally when adjusting relocation entries during loading.
     /// Currently unimplemented for REL entries.
@@ -182,6 +235,26 @@ impl ElfRel {
     pub(crate) fn set_offset(&mut self, _offset: usize) {
         todo!()
     }
+
+    /// Creates a REL entry from its raw parts.
+    /// This is used internally when converting RELA tables into the native format.
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn new(r_offset: usize, r_info: usize) -> Self {
+        ElfRel {
+            rel: Rel {
+                r_offset: r_offset as _,
+                r_info: r_info as _,
+            },
+        }
+    }
+
+    /// Returns the raw `r_info` field.
+    #[inline]
+    #[allow(unused)]
+    pub(crate) fn r_info(&self) -> usize {
+        self.rel.r_info as usize
+    }
 }
 
 #[repr(transparent)]
@@ -247,6 +320,28 @@ impl ElfSymbol {
         self.sym.st_other
     }
 
+    /// Returns the `STV_*` visibility, the low bits of `st_other`.
+    #[inline]
+    pub fn st_visibility(&self) -> u8 {
+        self.sym.st_other & 0x3
+    }
+
+    /// Returns true if other modules can bind to the symbol, that is if its
+    /// visibility is `STV_DEFAULT` or `STV_PROTECTED`.
+    /// `STV_HIDDEN` and `STV_INTERNAL` symbols are private to their module.
+    #[inline]
+    pub fn is_exported(&self) -> bool {
+        matches!(self.st_visibility(), STV_DEFAULT | STV_PROTECTED)
+    }
+
+    /// Returns true if the symbol has `STV_PROTECTED` visibility.
+    /// Other modules can bind to it, but references from its own module
+    /// always bind to this definition and are never preempted.
+    #[inline]
+    pub fn is_protected(&self) -> bool {
+        self.st_visibility() == STV_PROTECTED
+    }
+
     /// Returns true if the symbol is undefined (not defined in this object file).
     /// Undefined symbols typically need to be resolved from other object files or libraries.
     #[inline]
@@ -288,6 +383,12 @@ impl ElfSymbol {
     pub(crate) fn set_value(&mut self, value: usize) {
         self.sym.st_value = value as _;
     }
+
+    /// Sets the symbol binding, keeping the symbol type.
+    #[inline]
+    pub(crate) fn set_bind(&mut self, bind: u8) {
+        self.sym.st_info = (bind << 4) | (self.sym.st_info & 0xf);
+    }
 }
 
 /// ELF program header.
@@ -413,6 +514,30 @@ impl ElfShdr {
     }
 }
 
+impl ElfPhdr {
+    /// Converts a program header parsed from an object of any ELF class.
+    ///
+    /// # Returns
+    /// `None` if a field does not fit in the native program header.
+    pub(crate) fn from_parsed(phdr: &elf::segment::ProgramHeader) -> Option<Self> {
+        fn narrow<T: TryFrom<u64>>(val: u64) -> Option<T> {
+            T::try_from(val).ok()
+        }
+        Some(Self {
+            phdr: Phdr {
+                p_type: phdr.p_type,
+                p_flags: phdr.p_flags,
+                p_align: narrow(phdr.p_align)?,
+                p_offset: narrow(phdr.p_offset)?,
+                p_vaddr: narrow(phdr.p_vaddr)?,
+                p_paddr: narrow(phdr.p_paddr)?,
+                p_filesz: narrow(phdr.p_filesz)?,
+                p_memsz: narrow(phdr.p_memsz)?,
+            },
+        })
+    }
+}
+
 impl Deref for ElfPhdr {
     type Target = Phdr;
 
@@ -452,6 +577,64 @@ pub type ElfRelType = ElfRela;
 #[cfg(any(target_arch = "x86", target_arch = "arm"))]
 pub type ElfRelType = ElfRel;
 
+/// The relocation entry type that is not native to the current architecture.
+///
+/// Objects may carry tables in this format (e.g. `DT_REL` on x86_64). Such
+/// tables are converted into [`ElfRelType`] before relocation.
+#[cfg(all(not(target_arch = "x86"), not(target_arch = "arm")))]
+pub(crate) type ElfAltRelType = ElfRel;
+#[cfg(any(target_arch = "x86", target_arch = "arm"))]
+pub(crate) type ElfAltRelType = ElfRela;
+
+/// The fields of a relocation entry, whatever its table format:
+/// `(r_offset, r_type, r_symbol, addend)`.
+pub(crate) type RelFields = (usize, usize, usize, isize);
+
+/// Accessors shared by [`ElfRel`] and [`ElfRela`] entries.
+pub(crate) trait RelEntry {
+    /// Returns the fields of the entry. The addend of a REL entry is read from
+    /// its target word, so this must be called before the word is relocated.
+    fn fields(&self, base: usize) -> RelFields;
+}
+
+impl RelEntry for ElfRela {
+    #[inline]
+    fn fields(&self, base: usize) -> RelFields {
+        (
+            self.r_offset(),
+            self.r_type(),
+            self.r_symbol(),
+            self.reloc_addend(base),
+        )
+    }
+}
+
+impl RelEntry for ElfRel {
+    #[inline]
+    fn fields(&self, base: usize) -> RelFields {
+        (
+            self.r_offset(),
+            self.r_type(),
+            self.r_symbol(),
+            self.reloc_addend(base),
+        )
+    }
+}
+
+/// Iterates over a relocation table stored in the native format followed by
+/// one stored in the other format.
+#[inline]
+pub(crate) fn rel_entries<'a>(
+    native: &'a [ElfRelType],
+    alt: &'a [ElfAltRelType],
+    base: usize,
+) -> impl Iterator<Item = RelFields> + 'a {
+    native
+        .iter()
+        .map(move |rel| rel.fields(base))
+        .chain(alt.iter().map(move |rel| rel.fields(base)))
+}
+
 impl ElfRelType {
     /// Return a human readable relocation type name for the current arch
     #[inline]
@@ -1,12 +1,15 @@
 //! Parsing `.dynamic` section
 use crate::{
-    Result,
-    elf::{DT_RELR, DT_RELRSZ, Dyn, ElfRel, ElfRelType, ElfRela, ElfRelr},
+    InitFn, Result, UnknownDynamicPolicy,
+    elf::{
+        DT_RELR, DT_RELRSZ, Dyn, ElfAltRelType, ElfRel, ElfRelType, ElfRela, ElfRelr, ElfSymbol,
+    },
     parse_dynamic_error,
     segment::ElfSegments,
 };
-use alloc::vec::Vec;
+use alloc::{format, vec::Vec};
 use core::{
+    marker::PhantomData,
     num::NonZeroUsize,
     ops::{Add, AddAssign, Sub, SubAssign},
     ptr::{NonNull, null_mut},
@@ -15,24 +18,39 @@ use elf::abi::*;
 
 impl ElfDynamic {
     /// Parse the dynamic section of an ELF file
-    pub fn new(dynamic_ptr: *const Dyn, segments: &ElfSegments) -> Result<Self> {
+    ///
+    /// `unknown` decides what happens to the OS- and processor-specific tags
+    /// that are not interpreted.
+    pub fn new(
+        dynamic_ptr: *const Dyn,
+        segments: &ElfSegments,
+        unknown: UnknownDynamicPolicy,
+    ) -> Result<Self> {
         // These are required fields in a valid ELF dynamic library
         let mut symtab_off = 0; // Symbol table offset
         let mut strtab_off = 0; // String table offset
+        let mut strtab_size = None; // String table size
         let mut elf_hash_off = None; // ELF hash table offset
         let mut gnu_hash_off = None; // GNU hash table offset
         let mut got_off = None; // Global Offset Table offset
         let mut pltrel_size = None; // PLT relocation table size
         let mut pltrel_off = None; // PLT relocation table offset
-        let mut rel_off = None; // Relocation table offset
-        let mut rel_size = None; // Relocation table size
-        let mut rel_count = None; // Relocation count
+        let mut rel_off = None; // REL relocation table offset
+        let mut rel_size = None; // REL relocation table size
+        let mut rel_ent = None; // REL relocation entry size
+        let mut rela_off = None; // RELA relocation table offset
+        let mut rela_size = None; // RELA relocation table size
+        let mut rela_ent = None; // RELA relocation entry size
+        let mut rel_count = None; // Relative relocation count (DT_RELCOUNT)
+        let mut rela_count = None; // Relative relocation count (DT_RELACOUNT)
         let mut relr_off = None; // RELR relocation table offset
         let mut relr_size = None; // RELR relocation table size
         let mut init_off = None; // Initialization function offset
         let mut fini_off = None; // Finalization function offset
         let mut init_array_off = None; // Initialization function array offset
         let mut init_array_size = None; // Initialization function array size
+        let mut preinit_array_off = None; // Pre-initialization function array offset
+        let mut preinit_array_size = None; // Pre-initialization function array size
         let mut fini_array_off = None; // Finalization function array offset
         let mut fini_array_size = None; // Finalization function array size
         let mut version_ids_off = None; // Symbol versioning information offset
@@ -42,10 +60,14 @@ impl ElfDynamic {
         let mut verdef_num = None; // Number of version definition entries
         let mut rpath_off = None; // Runtime library search path offset
         let mut runpath_off = None; // Runtime library search path offset (overrides RPATH)
+        let mut soname_off = None; // Shared object name offset
         let mut flags = 0; // Dynamic section flags
         let mut flags_1 = 0; // Additional dynamic section flags
-        let mut is_rela = None; // Indicates if RELA or REL relocations are used
+        let mut symbolic = false; // DT_SYMBOLIC is present
+        let mut textrel = false; // DT_TEXTREL is present
+        let mut pltrel_is_rela = None; // Indicates if PLT relocations use RELA or REL
         let mut needed_libs = Vec::new(); // Required libraries (dependencies)
+        let mut vendor_entries = Vec::new(); // Uninterpreted OS- and processor-specific tags
 
         let mut cur_dyn_ptr = dynamic_ptr;
         let mut dynamic = unsafe { &*cur_dyn_ptr };
@@ -57,6 +79,8 @@ impl ElfDynamic {
                 match dynamic.d_tag as _ {
                     DT_FLAGS => flags = dynamic.d_un as usize,
                     DT_FLAGS_1 => flags_1 = dynamic.d_un as usize,
+                    DT_SYMBOLIC => symbolic = true,
+                    DT_TEXTREL => textrel = true,
                     DT_PLTGOT => got_off = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_NEEDED => {
                         if let Some(val) = NonZeroUsize::new(dynamic.d_un as usize) {
@@ -67,25 +91,32 @@ impl ElfDynamic {
                     DT_GNU_HASH => gnu_hash_off = Some(dynamic.d_un as usize),
                     DT_SYMTAB => symtab_off = dynamic.d_un as usize,
                     DT_STRTAB => strtab_off = dynamic.d_un as usize,
+                    DT_STRSZ => strtab_size = Some(dynamic.d_un as usize),
                     DT_PLTRELSZ => pltrel_size = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_PLTREL => {
-                        is_rela = Some(dynamic.d_un as i64 == DT_RELA);
+                        pltrel_is_rela = Some(dynamic.d_un as i64 == DT_RELA);
                     }
                     DT_JMPREL => pltrel_off = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_RELR => relr_off = NonZeroUsize::new(dynamic.d_un as usize),
-                    DT_RELA | DT_REL => {
-                        is_rela = Some(dynamic.d_tag as i64 == DT_RELA);
-                        rel_off = NonZeroUsize::new(dynamic.d_un as usize)
-                    }
-                    DT_RELASZ | DT_RELSZ => rel_size = NonZeroUsize::new(dynamic.d_un as usize),
+                    DT_RELA => rela_off = NonZeroUsize::new(dynamic.d_un as usize),
+                    DT_RELASZ => rela_size = NonZeroUsize::new(dynamic.d_un as usize),
+                    DT_RELAENT => rela_ent = Some(dynamic.d_un as usize),
+                    DT_REL => rel_off = NonZeroUsize::new(dynamic.d_un as usize),
+                    DT_RELSZ => rel_size = NonZeroUsize::new(dynamic.d_un as usize),
+                    DT_RELENT => rel_ent = Some(dynamic.d_un as usize),
                     DT_RELRSZ => relr_size = NonZeroUsize::new(dynamic.d_un as usize),
-                    DT_RELACOUNT | DT_RELCOUNT => {
-                        rel_count = NonZeroUsize::new(dynamic.d_un as usize)
-                    }
+                    DT_RELACOUNT => rela_count = NonZeroUsize::new(dynamic.d_un as usize),
+                    DT_RELCOUNT => rel_count = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_INIT => init_off = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_FINI => fini_off = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_INIT_ARRAY => init_array_off = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_INIT_ARRAYSZ => init_array_size = NonZeroUsize::new(dynamic.d_un as usize),
+                    DT_PREINIT_ARRAY => {
+                        preinit_array_off = NonZeroUsize::new(dynamic.d_un as usize)
+                    }
+                    DT_PREINIT_ARRAYSZ => {
+                        preinit_array_size = NonZeroUsize::new(dynamic.d_un as usize)
+                    }
                     DT_FINI_ARRAY => fini_array_off = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_FINI_ARRAYSZ => fini_array_size = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_VERSYM => {
@@ -109,7 +140,11 @@ impl ElfDynamic {
                     DT_RUNPATH => {
                         runpath_off = Some(NonZeroUsize::new_unchecked(dynamic.d_un as usize))
                     }
+                    DT_SONAME => soname_off = NonZeroUsize::new(dynamic.d_un as usize),
                     DT_NULL => break,
+                    tag if unknown != UnknownDynamicPolicy::Ignore && is_vendor_tag(tag) => {
+                        vendor_entries.push((tag, dynamic.d_un as u64));
+                    }
                     _ => {}
                 }
                 cur_dyn_ptr = cur_dyn_ptr.add(1);
@@ -117,12 +152,22 @@ impl ElfDynamic {
             }
         }
 
-        // Verify relocation type consistency
-        if let Some(is_rela) = is_rela {
-            assert!(
-                is_rela && size_of::<ElfRelType>() == size_of::<ElfRela>()
-                    || !is_rela && size_of::<ElfRelType>() == size_of::<ElfRel>()
-            );
+        if unknown == UnknownDynamicPolicy::Error && !vendor_entries.is_empty() {
+            let tags: Vec<_> = vendor_entries
+                .iter()
+                .map(|(tag, _)| format!("{tag:#x}"))
+                .collect();
+            return Err(parse_dynamic_error(format!(
+                "unknown dynamic tags: {}",
+                tags.join(", ")
+            )));
+        }
+
+        // Verify relocation entry sizes
+        if rel_ent.is_some_and(|ent| ent != size_of::<ElfRel>())
+            || rela_ent.is_some_and(|ent| ent != size_of::<ElfRela>())
+        {
+            return Err(parse_dynamic_error("invalid relocation entry size"));
         }
 
         // Determine which hash table to use (prefer GNU hash)
@@ -149,41 +194,96 @@ impl ElfDynamic {
             ));
         };
 
+        // The tables used for symbol lookup must lie within the image
+        let (table_off, header_len) = match hash_off {
+            ElfDynamicHashTab::Gnu(off) => (off, 4 * size_of::<u32>()),
+            ElfDynamicHashTab::Elf(off) => (off, 2 * size_of::<u32>()),
+        };
+        segments.check_range(table_off, header_len)?;
+        segments.check_range(symtab_off, size_of::<ElfSymbol>())?;
+        segments.check_range(strtab_off, strtab_size.unwrap_or(1))?;
+
+        // Both REL and RELA tables may be present. Tables in the native format are used
+        // directly, the others are converted before relocation.
+        let native_is_rela = size_of::<ElfRelType>() == size_of::<ElfRela>();
+        let (native_off, native_size, alt_off, alt_size, rel_count) = if native_is_rela {
+            (rela_off, rela_size, rel_off, rel_size, rela_count)
+        } else {
+            (rel_off, rel_size, rela_off, rela_size, rel_count)
+        };
+
         // Extract relocation tables
-        let pltrel = pltrel_off.map(|pltrel_off| {
-            segments.get_slice(pltrel_off.get(), pltrel_size.map(|s| s.get()).unwrap_or(0))
-        });
-        let dynrel = rel_off.map(|rel_off| {
-            segments.get_slice(rel_off.get(), rel_size.map(|s| s.get()).unwrap_or(0))
-        });
-        let relr = relr_off.map(|relr_off| {
-            segments.get_slice(relr_off.get(), relr_size.map(|s| s.get()).unwrap_or(0))
-        });
+        let pltrel_native = pltrel_is_rela.is_none_or(|is_rela| is_rela == native_is_rela);
+        let pltrel = pltrel_off
+            .filter(|_| pltrel_native)
+            .map(|pltrel_off| {
+                segments.get_slice(pltrel_off.get(), pltrel_size.map(|s| s.get()).unwrap_or(0))
+            })
+            .transpose()?;
+        let alt_pltrel = pltrel_off
+            .filter(|_| !pltrel_native)
+            .map(|pltrel_off| {
+                segments.get_slice(pltrel_off.get(), pltrel_size.map(|s| s.get()).unwrap_or(0))
+            })
+            .transpose()?;
+        let dynrel = native_off
+            .map(|rel_off| {
+                segments.get_slice(rel_off.get(), native_size.map(|s| s.get()).unwrap_or(0))
+            })
+            .transpose()?;
+        let alt_dynrel = alt_off
+            .map(|rel_off| {
+                segments.get_slice(rel_off.get(), alt_size.map(|s| s.get()).unwrap_or(0))
+            })
+            .transpose()?;
+        let relr = relr_off
+            .map(|relr_off| {
+                segments.get_slice(relr_off.get(), relr_size.map(|s| s.get()).unwrap_or(0))
+            })
+            .transpose()?;
 
         // Extract initialization and finalization functions
         let init_fn = init_off
-            .map(|val| unsafe { core::mem::transmute(segments.get_ptr::<fn()>(val.get())) });
-        let init_array_fn = init_array_off.map(|init_array_off| {
-            segments.get_slice(
-                init_array_off.get(),
-                init_array_size.map(|s| s.get()).unwrap_or(0),
-            )
-        });
-        let fini_fn = fini_off.map(|fini_off| unsafe {
-            core::mem::transmute(segments.get_ptr::<fn()>(fini_off.get()))
-        });
-        let fini_array_fn = fini_array_off.map(|fini_array_off| {
-            segments.get_slice(
-                fini_array_off.get(),
-                fini_array_size.map(|s| s.get()).unwrap_or(0),
-            )
-        });
+            .map(|val| segments.get_ptr::<u8>(val.get()))
+            .transpose()?
+            .map(|ptr| unsafe { core::mem::transmute(ptr) });
+        let init_array_fn = init_array_off
+            .map(|init_array_off| {
+                segments.get_slice(
+                    init_array_off.get(),
+                    init_array_size.map(|s| s.get()).unwrap_or(0),
+                )
+            })
+            .transpose()?;
+        let preinit_array_fn = preinit_array_off
+            .map(|preinit_array_off| {
+                segments.get_slice(
+                    preinit_array_off.get(),
+                    preinit_array_size.map(|s| s.get()).unwrap_or(0),
+                )
+            })
+            .transpose()?;
+        let fini_fn = fini_off
+            .map(|fini_off| segments.get_ptr::<u8>(fini_off.get()))
+            .transpose()?
+            .map(|ptr| unsafe { core::mem::transmute(ptr) });
+        let fini_array_fn = fini_array_off
+            .map(|fini_array_off| {
+                segments.get_slice(
+                    fini_array_off.get(),
+                    fini_array_size.map(|s| s.get()).unwrap_or(0),
+                )
+            })
+            .transpose()?;
 
         // Extract versioning information
+        // A table without its entry count is ignored rather than guessed at
         let verneed = verneed_off
-            .map(|verneed_off| (verneed_off.checked_add(base).unwrap(), verneed_num.unwrap()));
+            .zip(verneed_num)
+            .map(|(verneed_off, num)| (verneed_off.checked_add(base).unwrap(), num));
         let verdef = verdef_off
-            .map(|verdef_off| (verdef_off.checked_add(base).unwrap(), verdef_num.unwrap()));
+            .zip(verdef_num)
+            .map(|(verdef_off, num)| (verdef_off.checked_add(base).unwrap(), num));
         let version_idx = version_ids_off.map(|off| off.checked_add(base).unwrap());
 
         Ok(ElfDynamic {
@@ -193,22 +293,30 @@ impl ElfDynamic {
             strtab: strtab_off + base,
             // Check if binding should be done immediately
             bind_now: flags & DF_BIND_NOW as usize != 0 || flags_1 & DF_1_NOW as usize != 0,
+            flags_1,
+            symbolic: symbolic || flags & DF_SYMBOLIC as usize != 0,
+            textrel: textrel || flags & DF_TEXTREL as usize != 0,
             got_plt: NonNull::new(
                 got_off
                     .map(|off| (base + off.get()) as *mut usize)
                     .unwrap_or(null_mut()),
             ),
             needed_libs,
+            vendor_entries,
             pltrel,
             dynrel,
+            alt_pltrel,
+            alt_dynrel,
             relr,
             init_fn,
             init_array_fn,
+            preinit_array_fn,
             fini_fn,
             fini_array_fn,
             rel_count,
             rpath_off,
             runpath_off,
+            soname_off,
             version_idx,
             verneed,
             verdef,
@@ -216,6 +324,12 @@ impl ElfDynamic {
     }
 }
 
+/// Whether `tag` is in the OS- or processor-specific range of dynamic tags
+#[inline]
+fn is_vendor_tag(tag: i64) -> bool {
+    (DT_LOOS..=DT_HIOS).contains(&tag) || (DT_LOPROC..=DT_HIPROC).contains(&tag)
+}
+
 /// Hash table type used for symbol lookup
 pub enum ElfDynamicHashTab {
     /// GNU-style hash table (DT_GNU_HASH)
@@ -286,26 +400,41 @@ pub(crate) struct ElfDynamic {
     pub strtab: usize,
     /// Whether to bind symbols immediately.
     pub bind_now: bool,
+    /// Value of `DT_FLAGS_1`.
+    pub flags_1: usize,
+    /// Whether the object was linked with `-Bsymbolic` (`DT_SYMBOLIC` or `DF_SYMBOLIC`).
+    pub symbolic: bool,
+    /// Whether relocations may patch non-writable segments (`DT_TEXTREL` or `DF_TEXTREL`).
+    pub textrel: bool,
     /// Global Offset Table address.
     pub got_plt: Option<NonNull<usize>>,
     /// Initialization function.
-    pub init_fn: Option<fn()>,
+    pub init_fn: Option<InitFn>,
     /// Initialization function array.
-    pub init_array_fn: Option<&'static [fn()]>,
+    pub init_array_fn: Option<&'static [InitFn]>,
+    /// Pre-initialization function array.
+    pub preinit_array_fn: Option<&'static [InitFn]>,
     /// Finalization function.
-    pub fini_fn: Option<fn()>,
+    pub fini_fn: Option<InitFn>,
     /// Finalization function array.
-    pub fini_array_fn: Option<&'static [fn()]>,
+    pub fini_array_fn: Option<&'static [InitFn]>,
     /// PLT relocation entries.
     pub pltrel: Option<&'static [ElfRelType]>,
     /// Dynamic relocation entries.
     pub dynrel: Option<&'static [ElfRelType]>,
+    /// PLT relocation entries in the non-native format.
+    pub alt_pltrel: Option<&'static [ElfAltRelType]>,
+    /// Dynamic relocation entries in the non-native format.
+    pub alt_dynrel: Option<&'static [ElfAltRelType]>,
     /// RELR relocation entries.
     pub relr: Option<&'static [ElfRelr]>,
     /// Count of relative relocations.
     pub rel_count: Option<NonZeroUsize>,
     /// Required libraries.
     pub needed_libs: Vec<NonZeroUsize>,
+    /// OS- and processor-specific `(d_tag, d_un)` entries that are not
+    /// interpreted, kept by [`UnknownDynamicPolicy::Collect`].
+    pub vendor_entries: Vec<(i64, u64)>,
     /// Symbol version index.
     pub version_idx: Option<NonZeroUsize>,
     /// Version needed information.
@@ -316,4 +445,50 @@ pub(crate) struct ElfDynamic {
     pub rpath_off: Option<NonZeroUsize>,
     /// Runtime library search path (overrides RPATH).
     pub runpath_off: Option<NonZeroUsize>,
+    /// Shared object name.
+    pub soname_off: Option<NonZeroUsize>,
+}
+
+/// Iterator over the raw entries of a mapped dynamic section.
+///
+/// Yields `(d_tag, d_un)` pairs in the order they appear, including tags that
+/// occur more than once or are not interpreted by the loader. Iteration stops
+/// at the first `DT_NULL` entry, which is not yielded.
+#[derive(Clone)]
+pub struct DynamicEntries<'a> {
+    cur: *const Dyn,
+    _marker: PhantomData<&'a Dyn>,
+}
+
+impl DynamicEntries<'_> {
+    /// Creates an iterator starting at `dynamic_ptr`, or an empty one if it is null.
+    ///
+    /// # Safety
+    /// A non-null `dynamic_ptr` must point to a `DT_NULL` terminated dynamic
+    /// section that stays mapped for the lifetime of the iterator.
+    pub(crate) unsafe fn new(dynamic_ptr: *const Dyn) -> Self {
+        Self {
+            cur: dynamic_ptr,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Iterator for DynamicEntries<'_> {
+    type Item = (i64, u64);
+
+    // The conversions widen the entries of 32-bit targets
+    #[allow(clippy::useless_conversion)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cur.is_null() {
+            return None;
+        }
+        let dynamic = unsafe { &*self.cur };
+        if i64::from(dynamic.d_tag) == DT_NULL {
+            self.cur = null_mut();
+            return None;
+        }
+        self.cur = unsafe { self.cur.add(1) };
+        Some((i64::from(dynamic.d_tag), u64::from(dynamic.d_un)))
+    }
 }
@@ -5,13 +5,71 @@
 //! file type, and section/program header information.
 
 use crate::{
-    Result,
+    Error, Result,
     arch::EM_ARCH,
-    elf::{E_CLASS, EHDR_SIZE, Ehdr},
+    elf::{E_CLASS, EHDR_SIZE, Ehdr, ElfPhdr, ElfShdr},
     parse_ehdr_error,
 };
+use alloc::{borrow::Cow, format};
 use core::ops::Deref;
-use elf::abi::{EI_CLASS, EI_VERSION, ELFMAGIC, ET_DYN, ET_EXEC, EV_CURRENT};
+use elf::abi::{
+    EI_ABIVERSION, EI_CLASS, EI_DATA, EI_OSABI, EI_VERSION, ELFCLASS32, ELFCLASS64, ELFDATA2LSB,
+    ELFDATA2MSB, ELFMAGIC, EM_68K, EM_386, EM_AARCH64, EM_ARM, EM_BPF, EM_IA_64, EM_LOONGARCH,
+    EM_MIPS, EM_PPC, EM_PPC64, EM_RISCV, EM_S390, EM_SH, EM_SPARC, EM_SPARCV9, EM_X86_64, ET_DYN,
+    ET_EXEC, EV_CURRENT, PN_XNUM,
+};
+
+/// Byte order of the host
+const E_DATA: u8 = if cfg!(target_endian = "little") {
+    ELFDATA2LSB
+} else {
+    ELFDATA2MSB
+};
+
+/// Returns the name of the architecture identified by `e_machine`.
+///
+/// # Returns
+/// `None` if the machine is not one of the common ones known to this crate.
+pub fn machine_name(e_machine: u16) -> Option<&'static str> {
+    let name = match e_machine {
+        EM_386 => "x86",
+        EM_X86_64 => "x86_64",
+        EM_ARM => "arm",
+        EM_AARCH64 => "aarch64",
+        EM_RISCV => "riscv",
+        EM_LOONGARCH => "loongarch",
+        EM_MIPS => "mips",
+        EM_PPC => "powerpc",
+        EM_PPC64 => "powerpc64",
+        EM_S390 => "s390",
+        EM_SPARC => "sparc",
+        EM_SPARCV9 => "sparcv9",
+        EM_IA_64 => "ia64",
+        EM_68K => "m68k",
+        EM_SH => "superh",
+        EM_BPF => "bpf",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Returns the name of an `EI_CLASS` value
+fn class_name(class: u8) -> &'static str {
+    match class {
+        ELFCLASS32 => "ELFCLASS32",
+        ELFCLASS64 => "ELFCLASS64",
+        _ => "an invalid class",
+    }
+}
+
+/// Returns the name of an `EI_DATA` value
+fn data_name(data: u8) -> &'static str {
+    match data {
+        ELFDATA2LSB => "little endian",
+        ELFDATA2MSB => "big endian",
+        _ => "an invalid byte order",
+    }
+}
 
 /// A wrapper around the ELF header structure
 ///
@@ -72,6 +130,7 @@ impl ElfHeader {
     ///
     /// # Arguments
     /// * `data` - A byte slice containing the ELF header data
+    /// * `allowed_arch` - Machines accepted in addition to the host's
     ///
     /// # Returns
     /// * `Ok(&ElfHeader)` - A reference to the parsed and validated ELF header
@@ -80,13 +139,25 @@ impl ElfHeader {
     /// # Safety
     /// The caller must ensure that the data slice contains at least
     /// EHDR_SIZE bytes of valid ELF header data.
-    pub(crate) fn new(data: &[u8]) -> Result<&Self> {
+    pub(crate) fn new<'a>(data: &'a [u8], allowed_arch: &[u16]) -> Result<&'a Self> {
         debug_assert!(data.len() >= EHDR_SIZE);
         let ehdr: &ElfHeader = unsafe { &*(data.as_ptr().cast()) };
-        ehdr.vaildate()?;
+        ehdr.vaildate(allowed_arch)?;
         Ok(ehdr)
     }
 
+    /// Gets the OS/ABI identification (`EI_OSABI`)
+    #[inline]
+    pub fn osabi(&self) -> u8 {
+        self.e_ident[EI_OSABI]
+    }
+
+    /// Gets the version of the ABI identified by [`osabi`](Self::osabi) (`EI_ABIVERSION`)
+    #[inline]
+    pub fn abi_version(&self) -> u8 {
+        self.e_ident[EI_ABIVERSION]
+    }
+
     /// Checks if the ELF file is a dynamic library (shared object)
     ///
     /// This method determines whether the ELF file is a shared object
@@ -118,32 +189,50 @@ impl ElfHeader {
     /// This method performs several validation checks on the ELF header
     /// to ensure it is valid and compatible with the target architecture:
     /// 1. Checks the ELF magic bytes
-    /// 2. Verifies the file class matches the target architecture
+    /// 2. Verifies the file class and byte order match the target architecture
     /// 3. Ensures the ELF version is current
-    /// 4. Confirms the machine architecture matches
+    /// 4. Confirms the machine architecture matches, or is in `allowed_arch`
     ///
     /// # Returns
     /// * `Ok(())` - If all validation checks pass
     /// * `Err(Error)` - If any validation check fails
-    pub(crate) fn vaildate(&self) -> Result<()> {
+    pub(crate) fn vaildate(&self, allowed_arch: &[u16]) -> Result<()> {
         // Check ELF magic bytes
         if self.e_ident[0..4] != ELFMAGIC {
             return Err(parse_ehdr_error("invalid ELF magic"));
         }
 
         // Check file class (32-bit vs 64-bit)
-        if self.e_ident[EI_CLASS] != E_CLASS {
-            return Err(parse_ehdr_error("file class mismatch"));
+        let class = self.e_ident[EI_CLASS];
+        if class != E_CLASS {
+            return Err(parse_ehdr_error(format!(
+                "file class mismatch: found {}, expected {}",
+                class_name(class),
+                class_name(E_CLASS)
+            )));
+        }
+
+        // Check byte order
+        let data = self.e_ident[EI_DATA];
+        if data != E_DATA {
+            return Err(parse_ehdr_error(format!(
+                "byte order mismatch: found {}, expected {}",
+                data_name(data),
+                data_name(E_DATA)
+            )));
         }
 
-        // Check ELF version
-        if self.e_ident[EI_VERSION] != EV_CURRENT {
+        // Check ELF version, which is recorded twice
+        if self.e_ident[EI_VERSION] != EV_CURRENT || self.e_version != u32::from(EV_CURRENT) {
             return Err(parse_ehdr_error("invalid ELF version"));
         }
 
         // Check machine architecture
-        if self.e_machine != EM_ARCH {
-            return Err(parse_ehdr_error("file arch mismatch"));
+        if self.e_machine != EM_ARCH && !allowed_arch.contains(&self.e_machine) {
+            return Err(Error::ArchMismatch {
+                found: self.e_machine,
+                expected: EM_ARCH,
+            });
         }
 
         Ok(())
@@ -203,6 +292,79 @@ impl ElfHeader {
         self.ehdr.e_shnum as usize
     }
 
+    /// Gets the number of program headers, following the `PN_XNUM` escape
+    ///
+    /// Files with `PN_XNUM` or more program headers store `PN_XNUM` in
+    /// `e_phnum` and the real count in the `sh_info` field of section header 0,
+    /// which is read through `read`.
+    ///
+    /// # Returns
+    /// The number of program header entries, or an error if the count is
+    /// escaped but section header 0 is missing.
+    pub(crate) fn phnum(&self, read: impl FnOnce(&mut [u8], usize) -> Result<()>) -> Result<usize> {
+        if self.ehdr.e_phnum != PN_XNUM {
+            return Ok(self.e_phnum());
+        }
+        if self.e_shoff() == 0 || self.e_shentsize() != size_of::<ElfShdr>() {
+            return Err(self.malformed(
+                "e_phnum is PN_XNUM but there is no section header 0",
+                self.e_phnum(),
+            ));
+        }
+        let mut buf = [0u8; size_of::<ElfShdr>()];
+        read(&mut buf, self.e_shoff())?;
+        let shdr: ElfShdr = unsafe { buf.as_ptr().cast::<ElfShdr>().read_unaligned() };
+        Ok(shdr.sh_info as usize)
+    }
+
+    /// Calculates the byte range of a table of `phnum` program headers
+    ///
+    /// Unlike [`phdr_range`](Self::phdr_range), the entry size is checked
+    /// against the program header of the host and the range is checked for
+    /// overflow and, if `file_len` is known, against the end of the file.
+    ///
+    /// # Returns
+    /// The start and end offsets of the program header table
+    pub(crate) fn checked_phdr_range(
+        &self,
+        phnum: usize,
+        file_len: Option<usize>,
+    ) -> Result<(usize, usize)> {
+        if phnum != 0 && self.e_phentsize() != size_of::<ElfPhdr>() {
+            return Err(self.malformed(
+                format!(
+                    "e_phentsize does not match the {} byte program header",
+                    size_of::<ElfPhdr>()
+                ),
+                phnum,
+            ));
+        }
+        let phdr_start = self.e_phoff();
+        let phdr_end = phnum
+            .checked_mul(size_of::<ElfPhdr>())
+            .and_then(|size| phdr_start.checked_add(size))
+            .ok_or_else(|| self.malformed("program header table overflows", phnum))?;
+        if let Some(file_len) = file_len
+            && phdr_end > file_len
+        {
+            return Err(self.malformed(
+                format!("program header table ends past the {file_len:#x} byte file"),
+                phnum,
+            ));
+        }
+        Ok((phdr_start, phdr_end))
+    }
+
+    #[cold]
+    fn malformed(&self, msg: impl Into<Cow<'static, str>>, phnum: usize) -> Error {
+        Error::MalformedHeader {
+            msg: msg.into(),
+            phoff: self.e_phoff(),
+            phnum,
+            phentsize: self.e_phentsize(),
+        }
+    }
+
     /// Calculates the byte range of the program header table
     ///
     /// This method calculates the start and end file offsets of the
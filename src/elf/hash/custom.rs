@@ -9,13 +9,14 @@ use crate::{
     elf::{ElfShdr, ElfSymbol},
     elf::{ElfStringTable, PreCompute, SymbolTable, symbol::SymbolInfo},
 };
+use alloc::{borrow::Cow, string::String};
 use core::hash::{Hash, Hasher};
 use elf::abi::STT_FILE;
 use foldhash::{SharedSeed, fast::FoldHasher};
 use hashbrown::HashTable;
 
 struct TableEntry {
-    name: &'static str,
+    name: Cow<'static, str>,
     idx: usize,
 }
 
@@ -29,6 +30,9 @@ const HASHER: FoldHasher<'static> = FoldHasher::with_seed(0, SharedSeed::global_
 pub(crate) struct CustomHash {
     /// Hash map from symbol names to symbol indices
     map: HashTable<TableEntry>,
+
+    /// Number of entries in the symbol table, including unnamed ones
+    nsyms: usize,
 }
 
 impl CustomHash {
@@ -61,12 +65,44 @@ impl CustomHash {
             // Get the symbol name and add it to the hash map
             let name = strtab.get_str(symbol.st_name() as usize);
             let hash = Self::hash(name.as_bytes());
-            map.insert_unique(hash, TableEntry { name, idx }, |val| {
-                Self::hash(val.name.as_bytes())
-            });
+            map.insert_unique(
+                hash,
+                TableEntry {
+                    name: Cow::Borrowed(name),
+                    idx,
+                },
+                |val| Self::hash(val.name.as_bytes()),
+            );
         }
 
-        Self { map }
+        Self {
+            map,
+            nsyms: symbols.len(),
+        }
+    }
+
+    /// Iterate over the names in the table along with their symbol indices
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.map.iter().map(|entry| (&*entry.name, entry.idx))
+    }
+
+    /// Make the symbol at `idx` reachable under `name` instead of `old`
+    pub(crate) fn rename(&mut self, old: &str, idx: usize, name: String) {
+        let hash = Self::hash(old.as_bytes());
+        if let Ok(entry) = self
+            .map
+            .find_entry(hash, |entry| entry.idx == idx && entry.name == old)
+        {
+            entry.remove();
+        }
+        self.map.insert_unique(
+            Self::hash(name.as_bytes()),
+            TableEntry {
+                name: Cow::Owned(name),
+                idx,
+            },
+            |val| Self::hash(val.name.as_bytes()),
+        );
     }
 }
 
@@ -87,12 +123,14 @@ impl ElfHashTable for CustomHash {
         hasher.finish()
     }
 
-    /// Get the number of symbols in the hash table
+    /// Get the number of symbols in the symbol table
+    ///
+    /// File symbols are not hashed, but they still occupy an index.
     ///
     /// # Returns
-    /// The number of symbols in the hash table
+    /// The number of entries in the symbol table
     fn count_syms(&self) -> usize {
-        self.map.len()
+        self.nsyms
     }
 
     /// Look up a symbol in the custom hash table
@@ -5,6 +5,8 @@
 //! to the traditional SYSV hash table.
 
 use super::ElfHashTable;
+#[cfg(feature = "version")]
+use crate::elf::version::VersionMatch;
 use crate::{
     elf::ElfSymbol,
     elf::{PreCompute, SymbolTable, symbol::SymbolInfo},
@@ -81,6 +83,15 @@ impl ElfGnuHash {
             chains: chains.cast(),
         }
     }
+
+    /// Get the bucket a symbol hashes to
+    ///
+    /// # Arguments
+    /// * `hash` - The GNU hash of the symbol name
+    #[inline]
+    pub(crate) fn bucket(&self, hash: u32) -> usize {
+        hash as usize % self.header.nbucket as usize
+    }
 }
 
 impl ElfHashTable for ElfGnuHash {
@@ -120,15 +131,18 @@ impl ElfHashTable for ElfGnuHash {
             nsym = nsym.max(unsafe { self.buckets.add(i).read() as usize });
         }
 
-        // If we found a valid symbol index, check the chains for the end marker
-        if nsym > 0 {
-            unsafe {
-                let mut val = self.chains.add(nsym - self.header.symbias as usize);
-                // Find the end of the chain (marked by LSB = 1)
-                while val.read() & 1 == 0 {
-                    nsym += 1;
-                    val = val.add(1);
-                }
+        // Without hashed symbols, only the unhashed ones below `symbias` exist
+        if nsym == 0 {
+            return (self.header.symbias as usize).max(1);
+        }
+
+        // Check the chains for the end marker
+        unsafe {
+            let mut val = self.chains.add(nsym - self.header.symbias as usize);
+            // Find the end of the chain (marked by LSB = 1)
+            while val.read() & 1 == 0 {
+                nsym += 1;
+                val = val.add(1);
             }
         }
 
@@ -195,6 +209,9 @@ impl ElfHashTable for ElfGnuHash {
         let mut dynsym_idx = chain_start_idx;
         let mut cur_chain = unsafe { hashtab.chains.add(dynsym_idx - table_start_idx) };
         let mut cur_symbol_ptr = unsafe { table.symtab.add(dynsym_idx) };
+        // The default version is only used if no exact version match exists
+        #[cfg(feature = "version")]
+        let mut fallback = None;
 
         loop {
             let chain_hash = unsafe { cur_chain.read() };
@@ -206,8 +223,14 @@ impl ElfHashTable for ElfGnuHash {
 
                 // Check if this is the symbol we're looking for
                 #[cfg(feature = "version")]
-                if sym_name == symbol.name() && table.check_match(dynsym_idx, symbol.version()) {
-                    return Some(cur_symbol);
+                if sym_name == symbol.name() {
+                    match table.check_match(dynsym_idx, symbol.version()) {
+                        VersionMatch::Exact => return Some(cur_symbol),
+                        VersionMatch::Default => {
+                            fallback.get_or_insert(cur_symbol);
+                        }
+                        VersionMatch::None => {}
+                    }
                 }
                 #[cfg(not(feature = "version"))]
                 if sym_name == symbol.name() {
@@ -227,6 +250,9 @@ impl ElfHashTable for ElfGnuHash {
         }
 
         // Symbol not found in the chain
+        #[cfg(feature = "version")]
+        return fallback;
+        #[cfg(not(feature = "version"))]
         None
     }
 }
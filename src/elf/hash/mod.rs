@@ -12,6 +12,7 @@ use crate::elf::{
     ElfDynamic, ElfDynamicHashTab, ElfShdr, ElfStringTable, ElfSymbol, SymbolTable,
     symbol::SymbolInfo,
 };
+use alloc::string::String;
 use custom::CustomHash;
 use gnu::ElfGnuHash;
 use sysv::ElfHash;
@@ -53,6 +54,7 @@ pub(crate) enum HashTable {
 /// This structure holds precomputed hash values and related data that can
 /// be used to speed up symbol lookups in hash tables. Precomputing these
 /// values avoids repeated calculations during the lookup process.
+#[derive(Clone, Copy, Debug)]
 pub struct PreCompute {
     /// GNU hash value for the symbol name
     gnuhash: u32,
@@ -112,6 +114,50 @@ impl HashTable {
         }
     }
 
+    /// Iterate over the names and symbol indices of a custom hash table.
+    ///
+    /// The standard hash tables cannot be enumerated by name, so nothing is
+    /// yielded for them.
+    pub(crate) fn custom_entries(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.into_customhash()
+            .into_iter()
+            .flat_map(|hashtab| hashtab.entries())
+    }
+
+    /// Get the bucket a symbol hashes to, used to order batched lookups.
+    ///
+    /// Custom hash tables have no buckets to walk, so they always yield 0.
+    ///
+    /// # Arguments
+    /// * `symbol` - Information about the symbol.
+    /// * `precompute` - Precomputed hash values, filled in as needed.
+    pub(crate) fn bucket(&self, symbol: &SymbolInfo, precompute: &mut PreCompute) -> usize {
+        match self {
+            HashTable::Gnu(hashtab) => hashtab.bucket(precompute.gnuhash),
+            HashTable::Elf(hashtab) => hashtab.bucket(
+                *precompute
+                    .hash
+                    .get_or_insert_with(|| ElfHash::hash(symbol.name().as_bytes()) as u32),
+            ),
+            HashTable::Custom(_) => 0,
+        }
+    }
+
+    /// Make the symbol at `idx` of a custom hash table reachable under `name`
+    /// instead of `old`.
+    ///
+    /// # Returns
+    /// `false` if this is not a custom hash table.
+    pub(crate) fn rename(&mut self, old: &str, idx: usize, name: String) -> bool {
+        match self {
+            HashTable::Custom(hashtab) => {
+                hashtab.rename(old, idx, name);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Create a hash table from section header information.
     ///
     /// This method creates a custom hash table based on the symbol table
@@ -183,6 +229,34 @@ impl HashTable {
     }
 }
 
+impl PreCompute {
+    /// Get the GNU hash of the symbol name.
+    #[inline]
+    pub(crate) fn gnuhash(&self) -> u32 {
+        self.gnuhash
+    }
+
+    /// Compute every hash value of a symbol name up front.
+    ///
+    /// Unlike [`SymbolInfo::precompute`], which leaves the hashes that only some
+    /// tables need to be filled in on first use, the result can be copied and
+    /// reused for lookups in any number of modules without hashing again.
+    ///
+    /// # Arguments
+    /// * `name` - The symbol name.
+    pub fn new(name: &str) -> Self {
+        let bytes = name.as_bytes();
+        let gnuhash = ElfGnuHash::hash(bytes) as u32;
+        PreCompute {
+            gnuhash,
+            fofs: gnuhash as usize / usize::BITS as usize,
+            fmask: 1 << (gnuhash % (8 * size_of::<usize>() as u32)),
+            hash: Some(ElfHash::hash(bytes) as u32),
+            custom: Some(CustomHash::hash(bytes)),
+        }
+    }
+}
+
 impl SymbolInfo<'_> {
     /// Precompute hash values for efficient symbol lookup.
     ///
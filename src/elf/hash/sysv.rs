@@ -5,6 +5,8 @@
 //! used in many ELF implementations.
 
 use super::ElfHashTable;
+#[cfg(feature = "version")]
+use crate::elf::version::VersionMatch;
 use crate::elf::{ElfSymbol, PreCompute, SymbolTable, symbol::SymbolInfo};
 /// Header structure for SYSV ELF hash tables
 ///
@@ -61,6 +63,15 @@ impl ElfHash {
             chains: chains.cast(),
         }
     }
+
+    /// Get the bucket a symbol hashes to
+    ///
+    /// # Arguments
+    /// * `hash` - The SYSV hash of the symbol name
+    #[inline]
+    pub(crate) fn bucket(&self, hash: u32) -> usize {
+        hash as usize % self.header.nbucket as usize
+    }
 }
 
 impl ElfHashTable for ElfHash {
@@ -135,11 +146,17 @@ impl ElfHashTable for ElfHash {
         let bucket_idx = (hash as usize) % hashtab.header.nbucket as usize;
         let bucket_ptr = unsafe { hashtab.buckets.add(bucket_idx) };
         let mut chain_idx = unsafe { bucket_ptr.read() as usize };
+        // The default version is only used if no exact version match exists
+        #[cfg(feature = "version")]
+        let mut fallback = None;
 
         // Traverse the chain to find the symbol
         loop {
             // End of chain reached
             if chain_idx == 0 {
+                #[cfg(feature = "version")]
+                return fallback;
+                #[cfg(not(feature = "version"))]
                 return None;
             }
 
@@ -150,8 +167,14 @@ impl ElfHashTable for ElfHash {
 
             // Check if this is the symbol we're looking for
             #[cfg(feature = "version")]
-            if sym_name == symbol.name() && table.check_match(chain_idx, symbol.version()) {
-                return Some(cur_symbol);
+            if sym_name == symbol.name() {
+                match table.check_match(chain_idx, symbol.version()) {
+                    VersionMatch::Exact => return Some(cur_symbol),
+                    VersionMatch::Default => {
+                        fallback.get_or_insert(cur_symbol);
+                    }
+                    VersionMatch::None => {}
+                }
             }
             #[cfg(not(feature = "version"))]
             if sym_name == symbol.name() {
@@ -4,7 +4,9 @@ mod defs;
 mod dynamic;
 mod ehdr;
 mod hash;
+mod note;
 mod phdrs;
+mod property;
 mod symbol;
 #[cfg(feature = "version")]
 mod version;
@@ -13,12 +15,25 @@ mod version;
 pub(crate) use defs::*;
 pub(crate) use dynamic::{ElfDynamic, ElfDynamicHashTab};
 pub(crate) use ehdr::ElfHeader;
-pub(crate) use hash::{HashTable, PreCompute};
-pub(crate) use phdrs::ElfPhdrs;
-pub(crate) use symbol::{ElfStringTable, SymbolInfo, SymbolTable};
+pub(crate) use hash::HashTable;
+pub(crate) use note::ElfNotes;
+pub(crate) use phdrs::{ElfPhdrs, check_file_ranges};
+pub(crate) use symbol::{ElfStringTable, SymbolTable};
 
 // Public API exports
 /// Core ELF data types for program headers, relocations, and symbols.
 pub use defs::{ElfPhdr, ElfRel, ElfRela, ElfSymbol};
+/// Iterator over the raw entries of a dynamic section.
+pub use dynamic::DynamicEntries;
+/// Names of the architectures identified by `e_machine`.
+pub use ehdr::machine_name;
 /// ELF ABI constants and definitions from the elf crate.
 pub use elf::abi::*;
+/// Precomputed hash values of a symbol name.
+pub use hash::PreCompute;
+/// Notes of the `PT_NOTE` segments.
+pub use note::{ElfNote, NoteIter};
+/// Hardware features recorded in the `PT_GNU_PROPERTY` segment.
+pub use property::GnuProperties;
+/// Symbol names, optionally prepared for repeated lookups.
+pub use symbol::{PreparedSymbol, SymbolInfo};
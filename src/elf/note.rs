@@ -0,0 +1,189 @@
+//! ELF notes
+//!
+//! `PT_NOTE` segments hold small vendor records, such as the GNU build-id that
+//! symbol servers use to identify the exact build of an object.
+
+use crate::{Result, elf::ElfPhdr, input::ElfReader, segment::ElfSegments};
+use alloc::{boxed::Box, vec::Vec};
+use elf::abi::{NT_GNU_BUILD_ID, PT_LOAD, PT_NOTE};
+
+/// Size of a note header: `n_namesz`, `n_descsz` and `n_type`
+const NOTE_HEADER_SIZE: usize = 12;
+
+/// A note of a `PT_NOTE` segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ElfNote<'a> {
+    name: &'a [u8],
+    n_type: u32,
+    desc: &'a [u8],
+}
+
+impl<'a> ElfNote<'a> {
+    /// Gets the owner of the note, such as `GNU`, without the terminating NUL
+    #[inline]
+    pub fn name(&self) -> &'a [u8] {
+        self.name
+    }
+
+    /// Gets the type of the note, whose meaning depends on the owner
+    #[inline]
+    pub fn n_type(&self) -> u32 {
+        self.n_type
+    }
+
+    /// Gets the descriptor of the note
+    #[inline]
+    pub fn desc(&self) -> &'a [u8] {
+        self.desc
+    }
+}
+
+/// The bytes of a `PT_NOTE` segment
+enum NoteData {
+    /// The segment lies in a `PT_LOAD` segment and is read from the mapping
+    Mapped(&'static [u8]),
+    /// The segment is only present in the file and was copied during load
+    Owned(Box<[u8]>),
+}
+
+/// A `PT_NOTE` segment and the alignment of its entries
+struct NoteSegment {
+    data: NoteData,
+    align: usize,
+}
+
+impl NoteSegment {
+    fn bytes(&self) -> &[u8] {
+        match &self.data {
+            NoteData::Mapped(bytes) => bytes,
+            NoteData::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// The `PT_NOTE` segments of an object
+#[derive(Default)]
+pub(crate) struct ElfNotes {
+    segments: Vec<NoteSegment>,
+}
+
+impl ElfNotes {
+    /// Collects the `PT_NOTE` segments of an object while it is being loaded.
+    ///
+    /// Segments inside the file range of a `PT_LOAD` segment are used from the
+    /// mapping, the others are read from `object`.
+    pub(crate) fn load(
+        phdrs: &[ElfPhdr],
+        segments: &ElfSegments,
+        object: &mut impl ElfReader,
+    ) -> Result<Self> {
+        let mut notes = Self::default();
+        for phdr in phdrs.iter().filter(|phdr| phdr.p_type == PT_NOTE) {
+            let (start, end) = (phdr.p_offset, phdr.p_offset + phdr.p_filesz);
+            let mapped = phdrs.iter().any(|load| {
+                load.p_type == PT_LOAD
+                    && load.p_offset <= start
+                    && end <= load.p_offset + load.p_filesz
+            });
+            let data = if mapped {
+                NoteData::Mapped(segments.get_slice(phdr.p_vaddr as usize, phdr.p_filesz as usize)?)
+            } else {
+                let mut buf = alloc::vec![0; phdr.p_filesz as usize].into_boxed_slice();
+                object.read(&mut buf, phdr.p_offset as usize)?;
+                NoteData::Owned(buf)
+            };
+            notes.push(data, phdr.p_align as usize);
+        }
+        Ok(notes)
+    }
+
+    /// Collects the `PT_NOTE` segments of an image that is already mapped.
+    ///
+    /// Segments outside the mapping are skipped.
+    pub(crate) fn mapped(phdrs: &[ElfPhdr], segments: &ElfSegments) -> Self {
+        let mut notes = Self::default();
+        for phdr in phdrs.iter().filter(|phdr| phdr.p_type == PT_NOTE) {
+            if let Ok(bytes) = segments.get_slice(phdr.p_vaddr as usize, phdr.p_filesz as usize) {
+                notes.push(NoteData::Mapped(bytes), phdr.p_align as usize);
+            }
+        }
+        notes
+    }
+
+    fn push(&mut self, data: NoteData, align: usize) {
+        // Notes are at least 4-byte aligned, even if `p_align` says otherwise
+        let align = align.max(4);
+        self.segments.push(NoteSegment { data, align });
+    }
+
+    /// Iterates over the notes of all segments
+    pub(crate) fn iter(&self) -> NoteIter<'_> {
+        NoteIter {
+            segments: self.segments.iter(),
+            rest: &[],
+            align: 4,
+        }
+    }
+
+    /// Gets the descriptor of the `NT_GNU_BUILD_ID` note owned by `GNU`
+    pub(crate) fn build_id(&self) -> Option<&[u8]> {
+        self.iter()
+            .find(|note| note.name == b"GNU" && u64::from(note.n_type) == NT_GNU_BUILD_ID)
+            .map(|note| note.desc)
+    }
+}
+
+/// An iterator over the notes of the `PT_NOTE` segments of an object.
+///
+/// Iteration of a segment stops at its first malformed note.
+pub struct NoteIter<'a> {
+    segments: core::slice::Iter<'a, NoteSegment>,
+    rest: &'a [u8],
+    align: usize,
+}
+
+impl<'a> NoteIter<'a> {
+    /// Iterates over the notes in `bytes`, whose entries are aligned to `align`
+    pub(crate) fn from_bytes(bytes: &'a [u8], align: usize) -> Self {
+        NoteIter {
+            segments: [].iter(),
+            rest: bytes,
+            align,
+        }
+    }
+}
+
+impl<'a> Iterator for NoteIter<'a> {
+    type Item = ElfNote<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.len() < NOTE_HEADER_SIZE {
+                let segment = self.segments.next()?;
+                self.rest = segment.bytes();
+                self.align = segment.align;
+                continue;
+            }
+            let namesz = read_u32(self.rest, 0) as usize;
+            let descsz = read_u32(self.rest, 4) as usize;
+            let n_type = read_u32(self.rest, 8);
+            let desc_start = (NOTE_HEADER_SIZE + namesz).next_multiple_of(self.align);
+            let (Some(name), Some(desc)) = (
+                self.rest.get(NOTE_HEADER_SIZE..NOTE_HEADER_SIZE + namesz),
+                self.rest.get(desc_start..desc_start + descsz),
+            ) else {
+                self.rest = &[];
+                continue;
+            };
+            let next = (desc_start + descsz).next_multiple_of(self.align);
+            self.rest = self.rest.get(next..).unwrap_or(&[]);
+            let name = name.split(|&b| b == 0).next().unwrap_or(&[]);
+            return Some(ElfNote { name, n_type, desc });
+        }
+    }
+}
+
+/// Reads a native-endian `u32` at `offset`
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
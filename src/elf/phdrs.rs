@@ -1,5 +1,10 @@
-use crate::elf::ElfPhdr;
-use alloc::vec::Vec;
+use crate::{
+    Error, Result,
+    allocator::AllocVec,
+    elf::{Dyn, ElfPhdr, Sym},
+    input::ElfReader,
+};
+use elf::abi::*;
 
 /// Internal representation of ELF program headers
 #[derive(Clone)]
@@ -8,7 +13,7 @@ pub(crate) enum ElfPhdrs {
     Mmap(&'static [ElfPhdr]),
 
     /// Program headers stored in a vector
-    Vec(Vec<ElfPhdr>),
+    Vec(AllocVec<ElfPhdr>),
 }
 
 impl ElfPhdrs {
@@ -19,3 +24,76 @@ impl ElfPhdrs {
         }
     }
 }
+
+/// Checks that the file contents referenced by the program headers lie within
+/// the first `actual` bytes of the object, before any of them is mapped.
+///
+/// This covers the `PT_LOAD` and `PT_DYNAMIC` segments and the string, symbol
+/// and relocation tables named in the dynamic section. A table is checked
+/// from the file offset of its start to its end.
+pub(crate) fn check_file_ranges(
+    phdrs: &[ElfPhdr],
+    object: &mut impl ElfReader,
+    actual: usize,
+) -> Result<()> {
+    let check = |offset: usize, size: usize| {
+        let needed = offset.saturating_add(size);
+        if needed > actual {
+            return Err(Error::Truncated { needed, actual });
+        }
+        Ok(())
+    };
+    for phdr in phdrs
+        .iter()
+        .filter(|phdr| matches!(phdr.p_type, PT_LOAD | PT_DYNAMIC))
+    {
+        check(phdr.p_offset as usize, phdr.p_filesz as usize)?;
+    }
+    let Some(dynamic) = phdrs.iter().find(|phdr| phdr.p_type == PT_DYNAMIC) else {
+        return Ok(());
+    };
+
+    let (mut strtab, mut strsz) = (None, 0);
+    let mut symtab = None;
+    let mut rels = [(None, 0); 3];
+    let mut entry = [0u8; size_of::<Dyn>()];
+    for idx in 0..dynamic.p_filesz as usize / size_of::<Dyn>() {
+        object.read(&mut entry, dynamic.p_offset as usize + idx * size_of::<Dyn>())?;
+        let entry = unsafe { entry.as_ptr().cast::<Dyn>().read_unaligned() };
+        let val = entry.d_un as usize;
+        match entry.d_tag as _ {
+            DT_STRTAB => strtab = Some(val),
+            DT_STRSZ => strsz = val,
+            DT_SYMTAB => symtab = Some(val),
+            DT_RELA => rels[0].0 = Some(val),
+            DT_RELASZ => rels[0].1 = val,
+            DT_REL => rels[1].0 = Some(val),
+            DT_RELSZ => rels[1].1 = val,
+            DT_JMPREL => rels[2].0 = Some(val),
+            DT_PLTRELSZ => rels[2].1 = val,
+            DT_NULL => break,
+            _ => {}
+        }
+    }
+
+    // Tables that do not start in the file contents of a segment are left to
+    // the bounds checks against the mapped image
+    let file_offset = |addr: usize| {
+        phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .find_map(|phdr| {
+                let rel = addr.checked_sub(phdr.p_vaddr as usize)?;
+                (rel < phdr.p_filesz as usize).then(|| phdr.p_offset as usize + rel)
+            })
+    };
+    let tables = [(strtab, strsz), (symtab, size_of::<Sym>())]
+        .into_iter()
+        .chain(rels);
+    for (addr, size) in tables {
+        if let Some(offset) = addr.and_then(file_offset) {
+            check(offset, size)?;
+        }
+    }
+    Ok(())
+}
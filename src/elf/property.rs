@@ -0,0 +1,103 @@
+//! GNU program properties
+//!
+//! The `PT_GNU_PROPERTY` segment covers the `.note.gnu.property` section, in
+//! which the linker records the hardware features that every input object was
+//! built for, such as x86 CET or aarch64 BTI.
+
+use crate::elf::note::{NoteIter, read_u32};
+use elf::abi::{
+    GNU_PROPERTY_AARCH64_FEATURE_1_AND, GNU_PROPERTY_AARCH64_FEATURE_1_BTI,
+    GNU_PROPERTY_AARCH64_FEATURE_1_PAC, NT_GNU_PROPERTY_TYPE_0,
+};
+
+/// Property holding the x86 features all input objects support
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+/// The object is compatible with indirect branch tracking
+const GNU_PROPERTY_X86_FEATURE_1_IBT: u32 = 0x1;
+/// The object is compatible with shadow stacks
+const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 0x2;
+
+/// Notes and properties are aligned to the word size of the ELF class
+const NOTE_ALIGN: usize = size_of::<usize>();
+
+/// The feature bitmasks read from the `PT_GNU_PROPERTY` segment of an object.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GnuProperties {
+    x86_feature_1: u32,
+    aarch64_feature_1: u32,
+}
+
+impl GnuProperties {
+    /// Parses the notes covered by a `PT_GNU_PROPERTY` segment.
+    ///
+    /// Only the first `NT_GNU_PROPERTY_TYPE_0` note owned by `GNU` is used, as in
+    /// the glibc dynamic linker. Parsing stops at the first malformed entry, so
+    /// a truncated note yields the properties read up to that point.
+    pub(crate) fn parse(notes: &[u8]) -> Self {
+        let mut properties = Self::default();
+        if let Some(note) = NoteIter::from_bytes(notes, NOTE_ALIGN).find(|note| {
+            u64::from(note.n_type()) == NT_GNU_PROPERTY_TYPE_0 && note.name() == b"GNU"
+        }) {
+            properties.parse_desc(note.desc());
+        }
+        properties
+    }
+
+    /// Reads the properties of a `NT_GNU_PROPERTY_TYPE_0` descriptor
+    fn parse_desc(&mut self, mut desc: &[u8]) {
+        while desc.len() >= 8 {
+            let pr_type = read_u32(desc, 0);
+            let datasz = read_u32(desc, 4) as usize;
+            let Some(data) = desc.get(8..8 + datasz) else {
+                break;
+            };
+            if datasz == 4 {
+                match pr_type {
+                    GNU_PROPERTY_X86_FEATURE_1_AND => self.x86_feature_1 = read_u32(data, 0),
+                    GNU_PROPERTY_AARCH64_FEATURE_1_AND => {
+                        self.aarch64_feature_1 = read_u32(data, 0)
+                    }
+                    _ => {}
+                }
+            }
+            let next = (8 + datasz).next_multiple_of(NOTE_ALIGN);
+            desc = desc.get(next..).unwrap_or(&[]);
+        }
+    }
+
+    /// Gets the `GNU_PROPERTY_X86_FEATURE_1_AND` bitmask, or zero if absent
+    #[inline]
+    pub fn x86_feature_1_and(&self) -> u32 {
+        self.x86_feature_1
+    }
+
+    /// Gets the `GNU_PROPERTY_AARCH64_FEATURE_1_AND` bitmask, or zero if absent
+    #[inline]
+    pub fn aarch64_feature_1_and(&self) -> u32 {
+        self.aarch64_feature_1
+    }
+
+    /// Whether the object was built for x86 indirect branch tracking
+    #[inline]
+    pub fn has_ibt(&self) -> bool {
+        self.x86_feature_1 & GNU_PROPERTY_X86_FEATURE_1_IBT != 0
+    }
+
+    /// Whether the object was built for x86 shadow stacks
+    #[inline]
+    pub fn has_shstk(&self) -> bool {
+        self.x86_feature_1 & GNU_PROPERTY_X86_FEATURE_1_SHSTK != 0
+    }
+
+    /// Whether the object was built for aarch64 branch target identification
+    #[inline]
+    pub fn has_bti(&self) -> bool {
+        self.aarch64_feature_1 & GNU_PROPERTY_AARCH64_FEATURE_1_BTI != 0
+    }
+
+    /// Whether the object was built for aarch64 pointer authentication
+    #[inline]
+    pub fn has_pac(&self) -> bool {
+        self.aarch64_feature_1 & GNU_PROPERTY_AARCH64_FEATURE_1_PAC != 0
+    }
+}
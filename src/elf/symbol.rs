@@ -9,6 +9,7 @@ use crate::{
     elf::{ElfDynamic, HashTable, PreCompute},
     elf::{ElfShdr, ElfSymbol},
 };
+use alloc::string::String;
 use core::ffi::CStr;
 
 /// ELF string table wrapper
@@ -118,7 +119,7 @@ impl<'symtab> SymbolInfo<'symtab> {
 
     /// Returns the name of the symbol.
     #[inline]
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &'symtab str {
         self.name
     }
 
@@ -128,6 +129,29 @@ impl<'symtab> SymbolInfo<'symtab> {
         self.cname
     }
 
+    /// Makes lookups of the symbol reject definitions of other versions,
+    /// instead of falling back to the default version.
+    #[allow(unused_mut, unused_variables)]
+    pub(crate) fn exact_version(mut self, exact: bool) -> Self {
+        #[cfg(feature = "version")]
+        if let Some(version) = self.version.as_mut() {
+            version.exact = exact;
+        }
+        self
+    }
+
+    /// Returns the name of the required version and the library it is
+    /// required from, if the symbol is versioned.
+    pub(crate) fn required_version(&self) -> Option<(&'symtab str, Option<&'symtab str>)> {
+        #[cfg(feature = "version")]
+        return self
+            .version
+            .as_ref()
+            .map(|version| (version.name(), version.file()));
+        #[cfg(not(feature = "version"))]
+        None
+    }
+
     /// Returns the symbol version information.
     #[cfg(feature = "version")]
     pub(crate) fn version(&self) -> Option<&super::version::SymbolVersion<'symtab>> {
@@ -135,6 +159,49 @@ impl<'symtab> SymbolInfo<'symtab> {
     }
 }
 
+/// A symbol name whose hashes have been computed ahead of time.
+///
+/// Creating one costs the same as a single lookup; afterwards it can be looked
+/// up in any number of modules without hashing the name again.
+pub struct PreparedSymbol<'name> {
+    /// The symbol name and optional version.
+    info: SymbolInfo<'name>,
+
+    /// Hash values for every supported hash table kind.
+    precompute: PreCompute,
+}
+
+impl<'name> PreparedSymbol<'name> {
+    /// Prepares an unversioned symbol name for repeated lookups.
+    pub fn new(name: &'name str) -> Self {
+        Self {
+            info: SymbolInfo::from_str(name, None),
+            precompute: PreCompute::new(name),
+        }
+    }
+
+    /// Prepares a versioned symbol name for repeated lookups.
+    #[cfg(feature = "version")]
+    pub fn with_version(name: &'name str, version: &'name str) -> Self {
+        Self {
+            info: SymbolInfo::from_str(name, Some(version)),
+            precompute: PreCompute::new(name),
+        }
+    }
+
+    /// Returns the name of the symbol.
+    #[inline]
+    pub fn name(&self) -> &'name str {
+        self.info.name()
+    }
+
+    /// Returns the symbol information and a copy of its hash values.
+    #[inline]
+    pub(crate) fn parts(&self) -> (&SymbolInfo<'name>, PreCompute) {
+        (&self.info, self.precompute)
+    }
+}
+
 impl SymbolTable {
     /// Create a symbol table from ELF dynamic section information
     ///
@@ -267,6 +334,29 @@ impl SymbolTable {
         None
     }
 
+    /// Look up a symbol that other modules can bind to
+    ///
+    /// This behaves like [`SymbolTable::lookup_filter`], but additionally
+    /// rejects `STV_HIDDEN` and `STV_INTERNAL` symbols, which are private to
+    /// the module that defines them.
+    ///
+    /// # Arguments
+    /// * `symbol` - Information about the symbol to look up
+    /// * `precompute` - Precomputed hash values to speed up the lookup
+    ///
+    /// # Returns
+    /// * `Some(symbol)` - A reference to the found exported symbol
+    /// * `None` - If no suitable symbol was found
+    #[inline]
+    pub(crate) fn lookup_exported(
+        &self,
+        symbol: &SymbolInfo,
+        precompute: &mut PreCompute,
+    ) -> Option<&ElfSymbol> {
+        self.lookup_filter(symbol, precompute)
+            .filter(|sym| sym.is_exported())
+    }
+
     /// Get a symbol and its information by index
     ///
     /// This method retrieves a symbol and its associated information by index
@@ -312,4 +402,52 @@ impl SymbolTable {
     pub fn count_syms(&self) -> usize {
         self.hashtab.count_syms()
     }
+
+    /// Iterate over all symbols in the symbol table along with their names
+    ///
+    /// The reserved null symbol at index 0 is skipped. The number of entries is
+    /// recovered from the hash table, so this works for GNU hash, SYSV hash and
+    /// section-header-derived tables alike without allocating.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ElfSymbol)> {
+        (1..self.count_syms()).map(|idx| {
+            let symbol = unsafe { &*self.symtab.add(idx) };
+            (self.strtab.get_str(symbol.st_name()), symbol)
+        })
+    }
+
+    /// Iterate over the symbols that can be looked up by name, along with their indices
+    ///
+    /// Only symbol tables built from section headers can be enumerated this way.
+    pub(crate) fn named_symbols(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.hashtab.custom_entries()
+    }
+
+    /// Iterate over every name a symbol can be looked up by, along with the symbol
+    ///
+    /// Unlike [`iter`](Self::iter), this yields the names of renamed symbols
+    /// in a section-header-derived table instead of their original names.
+    pub(crate) fn lookup_names(&self) -> impl Iterator<Item = (&str, &ElfSymbol)> {
+        let custom = self.hashtab.custom_entries().next().is_some();
+        let named = self
+            .named_symbols()
+            .map(|(name, idx)| (name, self.symbol_idx(idx).0));
+        named.chain((!custom).then(|| self.iter()).into_iter().flatten())
+    }
+
+    /// Get a mutable pointer to the symbol at the specified index
+    ///
+    /// Writing through it requires the symbol table to be mapped writable.
+    pub(crate) fn symbol_ptr(&self, idx: usize) -> *mut ElfSymbol {
+        unsafe { self.symtab.add(idx).cast_mut() }
+    }
+
+    /// Make the symbol at `idx` reachable under `name` instead of its own name
+    ///
+    /// # Returns
+    /// `false` if the symbol table cannot be renamed, as is the case for the
+    /// tables referenced by the dynamic section.
+    pub(crate) fn rename_symbol(&mut self, idx: usize, name: String) -> bool {
+        let old = self.strtab.get_str(self.symbol_idx(idx).0.st_name());
+        self.hashtab.rename(old, idx, name)
+    }
 }
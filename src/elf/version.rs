@@ -124,8 +124,13 @@ impl Iterator for VerNeedAuxIterator {
     fn next(&mut self) -> Option<Self::Item> {
         if self.count < self.num {
             let verneed_aux = unsafe { self.ptr.read() };
-            self.ptr = unsafe { self.ptr.add(1) };
-            self.count += 1;
+            self.ptr = unsafe { self.ptr.byte_add(verneed_aux.vna_next as usize) };
+            // A zero offset marks the last entry, whatever the count says
+            self.count = if verneed_aux.vna_next == 0 {
+                self.num
+            } else {
+                self.count + 1
+            };
             Some(verneed_aux)
         } else {
             None
@@ -157,7 +162,11 @@ impl Iterator for VerNeedIterator {
                 num: verneed.vn_cnt as usize,
             };
             self.ptr = unsafe { self.ptr.byte_add(verneed.vn_next as usize) };
-            self.count += 1;
+            self.count = if verneed.vn_next == 0 {
+                self.num
+            } else {
+                self.count + 1
+            };
             Some((verneed, verneed_aux))
         } else {
             None
@@ -202,8 +211,12 @@ impl Iterator for VerDefAuxIterator {
     fn next(&mut self) -> Option<Self::Item> {
         if self.count < self.num {
             let verdef_aux = unsafe { self.ptr.read() };
-            self.ptr = unsafe { self.ptr.add(1) };
-            self.count += 1;
+            self.ptr = unsafe { self.ptr.byte_add(verdef_aux.vda_next as usize) };
+            self.count = if verdef_aux.vda_next == 0 {
+                self.num
+            } else {
+                self.count + 1
+            };
             Some(verdef_aux)
         } else {
             None
@@ -223,7 +236,11 @@ impl Iterator for VerDefIterator {
                 num: verdef.vd_cnt as usize,
             };
             self.ptr = unsafe { self.ptr.byte_add(verdef.vd_next as usize) };
-            self.count += 1;
+            self.count = if verdef.vd_next == 0 {
+                self.num
+            } else {
+                self.count + 1
+            };
             Some((verdef, verdef_aux))
         } else {
             None
@@ -248,13 +265,15 @@ impl IntoIterator for &VerDefTable {
 struct Version {
     name: &'static str,
     hash: u32,
+    /// The library a required version is expected from
+    file: Option<&'static str>,
 }
 
 pub(crate) struct ELFVersion {
     version_ids: VersionIndexTable,
     // 因为verdef和verneed的idx不重叠，因此我们可以使用数组将其存起来
-    // 这样可以加快之后符号版本号的匹配
-    versions: Vec<Version>,
+    // 这样可以加快之后符号版本号的匹配。没有被定义的idx为None
+    versions: Vec<Option<Version>>,
 }
 
 impl ELFVersion {
@@ -265,47 +284,29 @@ impl ELFVersion {
         strtab: &ElfStringTable,
     ) -> Option<ELFVersion> {
         let version_ids_off = version_ids_off?;
-        let mut versions = Vec::new();
-        //记录最大的verison idx
-        let mut ndx_max = 0;
-        if let Some((ptr, num)) = verdefs {
-            let verdef_table = VerDefTable {
-                ptr: ptr.get() as _,
-                num: num.get(),
-            };
-            for (verdef, _) in verdef_table.into_iter() {
-                if ndx_max < verdef.index() {
-                    ndx_max = verdef.index();
-                }
+        let mut versions: Vec<Option<Version>> = Vec::new();
+        let mut insert = |idx: usize, version: Version| {
+            if versions.len() <= idx {
+                versions.resize_with(idx + 1, || None);
             }
-        }
-        if let Some((ptr, num)) = verneeds {
-            let verneed_table = VerNeedTable {
-                ptr: ptr.get() as _,
-                num: num.get(),
-            };
-            for (_, vna_iter) in verneed_table.into_iter() {
-                for aux in vna_iter {
-                    if ndx_max < aux.index() {
-                        ndx_max = aux.index();
-                    }
-                }
-            }
-        }
-        // 分配足够大的version数组
-        versions.reserve(ndx_max + 1);
-        unsafe { versions.set_len(ndx_max + 1) };
+            versions[idx] = Some(version);
+        };
         if let Some((ptr, num)) = verdefs {
             let verdef_table = VerDefTable {
                 ptr: ptr.get() as _,
                 num: num.get(),
             };
             for (verdef, mut vd_iter) in verdef_table.into_iter() {
-                let name = strtab.get_str(vd_iter.next().unwrap().vda_name as usize);
-                versions[verdef.index()] = Version {
-                    name,
+                // 没有名字的版本无法被引用
+                let Some(aux) = vd_iter.next() else {
+                    continue;
+                };
+                let version = Version {
+                    name: strtab.get_str(aux.vda_name as usize),
                     hash: verdef.vd_hash,
+                    file: None,
                 };
+                insert(verdef.index(), version);
             }
         }
         if let Some((ptr, num)) = verneeds {
@@ -313,13 +314,15 @@ impl ELFVersion {
                 ptr: ptr.get() as _,
                 num: num.get(),
             };
-            for (_, vna_iter) in verneed_table.into_iter() {
+            for (verneed, vna_iter) in verneed_table.into_iter() {
+                let file = strtab.get_str(verneed.vn_file as usize);
                 for aux in vna_iter {
-                    let name = strtab.get_str(aux.vna_name as usize);
-                    versions[aux.index()] = Version {
-                        name,
+                    let version = Version {
+                        name: strtab.get_str(aux.vna_name as usize),
                         hash: aux.vna_hash,
+                        file: Some(file),
                     };
+                    insert(aux.index(), version);
                 }
             }
         }
@@ -330,19 +333,42 @@ impl ELFVersion {
             versions,
         })
     }
+
+    /// Returns the version with the given index, if the tables define it
+    fn get(&self, idx: u16) -> Option<&Version> {
+        self.versions.get(idx as usize)?.as_ref()
+    }
+}
+
+/// How well a symbol definition matches a requested version.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VersionMatch {
+    /// The definition has exactly the requested version.
+    Exact,
+    /// The definition is the default version and may be used as a fallback.
+    Default,
+    /// The definition must not be used.
+    None,
 }
 
 pub(crate) struct SymbolVersion<'a> {
     name: &'a str,
     hash: u32,
     hidden: bool,
+    /// The library the version is required from
+    file: Option<&'a str>,
+    /// Whether only a definition of this version may be used
+    pub(crate) exact: bool,
 }
 
 impl<'a> SymbolVersion<'a> {
     /// glibc:_dl_elf_hash
     fn dl_elf_hash(name: &str) -> u32 {
         let bytes = name.as_bytes();
-        let mut hash: u32 = u32::from(bytes[0]);
+        let Some(&first) = bytes.first() else {
+            return 0;
+        };
+        let mut hash: u32 = u32::from(first);
 
         if hash != 0 && bytes.len() > 1 {
             hash = (hash << 4) + u32::from(bytes[1]);
@@ -373,8 +399,20 @@ impl<'a> SymbolVersion<'a> {
             name,
             hash,
             hidden: true,
+            file: None,
+            exact: false,
         }
     }
+
+    /// Returns the name of the version.
+    pub(crate) fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the library the version is required from, if known.
+    pub(crate) fn file(&self) -> Option<&'a str> {
+        self.file
+    }
 }
 
 impl SymbolTable {
@@ -385,31 +423,58 @@ impl SymbolTable {
                 return None;
             }
             let hidden = ver_ndx.is_hidden();
-            let version = &gnu_version.versions[ver_ndx.index() as usize];
+            // 版本表中不存在的idx视为没有版本要求
+            let version = gnu_version.get(ver_ndx.index())?;
             return Some(SymbolVersion {
                 name: version.name,
                 hash: version.hash,
                 hidden,
+                file: version.file,
+                exact: false,
             });
         }
         None
     }
 
-    pub(crate) fn check_match(&self, sym_idx: usize, version: Option<&SymbolVersion>) -> bool {
-        if let Some(version) = version {
-            let gnu_version = self.version.as_ref().unwrap();
-            let ver_ndx = gnu_version.version_ids.get(sym_idx);
-            let def_hidden = ver_ndx.is_hidden();
-            let def_version = &gnu_version.versions[ver_ndx.index() as usize];
-            // 使用版本号一致的符号或者使用默认符号(这里认为第一个不隐藏的符号就是默认符号)
-            if (def_version.hash == version.hash && def_version.name == version.name)
-                || (!version.hidden && !def_hidden)
-            {
-                return true;
-            }
-            return false;
+    /// Checks whether the definition at `sym_idx` satisfies the requested version.
+    ///
+    /// # Returns
+    /// * `VersionMatch::Exact` - The versions match, or neither side is versioned.
+    /// * `VersionMatch::Default` - The definition is the default (non-hidden) version and
+    ///   the request accepts it as a fallback.
+    /// * `VersionMatch::None` - The definition must not be used for this request.
+    pub(crate) fn check_match(
+        &self,
+        sym_idx: usize,
+        version: Option<&SymbolVersion>,
+    ) -> VersionMatch {
+        // 定义符号的库没有版本信息时，任何请求都可以使用该符号
+        let Some(gnu_version) = self.version.as_ref() else {
+            return VersionMatch::Exact;
+        };
+        let ver_ndx = gnu_version.version_ids.get(sym_idx);
+        let def_hidden = ver_ndx.is_hidden();
+        let Some(version) = version else {
+            // 不带版本号的请求不能使用隐藏的版本
+            return if def_hidden {
+                VersionMatch::None
+            } else {
+                VersionMatch::Exact
+            };
+        };
+        // VER_NDX_LOCAL 和 VER_NDX_GLOBAL 没有对应的版本名
+        if ver_ndx.index() > 1
+            && let Some(def_version) = gnu_version.get(ver_ndx.index())
+            && def_version.hash == version.hash
+            && def_version.name == version.name
+        {
+            return VersionMatch::Exact;
+        }
+        // 没有完全一致的版本时可以退回到默认符号
+        if !version.exact && !version.hidden && !def_hidden {
+            VersionMatch::Default
+        } else {
+            VersionMatch::None
         }
-        // 如果需要重定位的符号不使用重定位信息，那么我们始终返回第一个找到的符号
-        true
     }
 }
@@ -1,4 +1,14 @@
-use alloc::borrow::Cow;
+use crate::{
+    arch::{REL_RELATIVE, rel_type_to_str},
+    elf::{ElfRelType, machine_name},
+    image::ElfCore,
+};
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
 use core::fmt::{Debug, Display};
 
 /// Error types used throughout the `elf_loader` library.
@@ -15,6 +25,8 @@ pub enum Error {
     Io {
         /// A descriptive message about the I/O error.
         msg: Cow<'static, str>,
+        /// The underlying error, such as the [`OsError`] of a failed system call.
+        source: Option<BoxedSource>,
     },
 
     /// An error occurred during memory mapping operations.
@@ -26,6 +38,8 @@ pub enum Error {
     Mmap {
         /// A descriptive message about the memory mapping error.
         msg: Cow<'static, str>,
+        /// The underlying error, such as the [`OsError`] of a failed system call.
+        source: Option<BoxedSource>,
     },
 
     /// An error occurred during dynamic library relocation.
@@ -38,6 +52,8 @@ pub enum Error {
     Relocation {
         /// A descriptive message about the relocation error.
         msg: Cow<'static, str>,
+        /// The relocation entry that failed, if the error is tied to one.
+        context: Option<Box<RelocationErrorContext>>,
     },
 
     /// An error occurred while parsing the dynamic section.
@@ -73,6 +89,122 @@ pub enum Error {
         msg: Cow<'static, str>,
     },
 
+    /// The program header table described by the ELF header is malformed.
+    ///
+    /// This error indicates that `e_phentsize` is not the size of a program
+    /// header, that the table lies past the end of the file, or that
+    /// `e_phnum` is `PN_XNUM` without a section header holding the real count.
+    MalformedHeader {
+        /// A descriptive message about the malformed header.
+        msg: Cow<'static, str>,
+        /// The file offset of the program header table (`e_phoff`).
+        phoff: usize,
+        /// The number of program headers, taken from section header 0 for `PN_XNUM`.
+        phnum: usize,
+        /// The size of a program header entry (`e_phentsize`).
+        phentsize: usize,
+    },
+
+    /// A range referenced by the ELF file lies outside the mapped image.
+    ///
+    /// This error indicates a malformed or truncated file, for example a
+    /// `PT_DYNAMIC` segment or a dynamic table whose offset or size points
+    /// past the end of the mapped segments.
+    OutOfBounds {
+        /// The offset of the range relative to the load base.
+        offset: usize,
+        /// The length of the range in bytes.
+        len: usize,
+        /// The number of bytes that are actually mapped.
+        mapped_len: usize,
+    },
+
+    /// The input ends before a range the ELF file references.
+    ///
+    /// This error is returned before anything is mapped when the length of
+    /// the input is known, see [`ElfReader::len`](crate::input::ElfReader::len),
+    /// and a `PT_LOAD` or `PT_DYNAMIC` segment or a table named in the dynamic
+    /// section extends past it, as with a partially downloaded library.
+    Truncated {
+        /// The number of bytes the range needs the input to have.
+        needed: usize,
+        /// The length of the input in bytes.
+        actual: usize,
+    },
+
+    /// The fixed address range of an executable is already in use.
+    ///
+    /// `ET_EXEC` files must be mapped at the addresses in their program headers.
+    /// The loader refuses to place them elsewhere or over existing mappings
+    /// unless [`Loader::allow_fixed_overwrite`](crate::Loader::allow_fixed_overwrite) is set.
+    AddressConflict {
+        /// The address the executable must be loaded at.
+        wanted: usize,
+        /// The length of the range in bytes.
+        len: usize,
+    },
+
+    /// An image needs more address space than the loader allows.
+    ///
+    /// The limit is set with [`Loader::set_max_image_size`](crate::Loader::set_max_image_size)
+    /// and is checked before anything is mapped.
+    ImageTooLarge {
+        /// The bytes of address space the image needs.
+        requested: usize,
+        /// The largest number of bytes a single image may reserve.
+        limit: usize,
+    },
+
+    /// The file was built for another architecture.
+    ///
+    /// Further machines can be accepted with [`Loader::allow_arch`](crate::Loader::allow_arch).
+    ArchMismatch {
+        /// The `e_machine` of the file.
+        found: u16,
+        /// The `e_machine` of the host.
+        expected: u16,
+    },
+
+    /// A symbol was requested from a module that is not initialized.
+    ///
+    /// Returned by [`LoadedCore::get_checked`](crate::image::LoadedCore::get_checked)
+    /// while the module has not finished relocation, or after its finalizers ran.
+    NotInitialized {
+        /// The name of the module.
+        module: String,
+    },
+
+    /// The requested symbol is not defined by the module.
+    SymbolNotFound {
+        /// The name of the module.
+        module: String,
+        /// The name of the symbol.
+        symbol: String,
+    },
+
+    /// Symbols referenced by a module could not be resolved.
+    ///
+    /// Returned instead of [`Error::Relocation`] when
+    /// [`Relocator::collect_missing`](crate::relocation::Relocator::collect_missing)
+    /// is set, listing every unresolved reference of the module at once.
+    MissingSymbols {
+        /// The name of the module being relocated.
+        module: String,
+        /// The unresolved references, in relocation table order.
+        symbols: Vec<MissingSymbol>,
+    },
+
+    /// A prelinked image cannot be loaded or written.
+    ///
+    /// This error typically indicates that:
+    /// * The image was written for another target or format version
+    /// * The dependencies differ from those the image was prelinked against
+    /// * The library uses TLS or lazy binding and cannot be prelinked
+    Prelink {
+        /// A descriptive message about the prelink error.
+        msg: Cow<'static, str>,
+    },
+
     /// An error occurred in a user-defined callback or handler.
     Custom {
         /// A descriptive message about the custom error.
@@ -86,18 +218,464 @@ impl Display for Error {
     /// This implementation provides human-readable error messages for all error variants.
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            Error::Io { msg } => write!(f, "I/O error: {msg}"),
-            Error::Mmap { msg } => write!(f, "Memory mapping error: {msg}"),
+            Error::Io { msg, .. } => write!(f, "I/O error: {msg}"),
+            Error::Mmap { msg, .. } => write!(f, "Memory mapping error: {msg}"),
+            Error::Relocation {
+                msg,
+                context: Some(context),
+            } => write!(f, "Relocation error: {msg} ({context})"),
             Error::Relocation { msg, .. } => write!(f, "Relocation error: {msg}"),
             Error::ParseDynamic { msg } => write!(f, "Dynamic section parsing error: {msg}"),
             Error::ParseEhdr { msg } => write!(f, "ELF header parsing error: {msg}"),
             Error::ParsePhdr { msg, .. } => write!(f, "Program header parsing error: {msg}"),
+            Error::MalformedHeader {
+                msg,
+                phoff,
+                phnum,
+                phentsize,
+            } => write!(
+                f,
+                "Malformed ELF header: {msg} (e_phoff: {phoff:#x}, phnum: {phnum}, e_phentsize: {phentsize})"
+            ),
+            Error::OutOfBounds {
+                offset,
+                len,
+                mapped_len,
+            } => write!(
+                f,
+                "Out of bounds: 0x{offset:x}..+0x{len:x} is outside the 0x{mapped_len:x} mapped bytes"
+            ),
+            Error::Truncated { needed, actual } => write!(
+                f,
+                "Truncated input: 0x{needed:x} bytes needed, the input has 0x{actual:x}"
+            ),
+            Error::AddressConflict { wanted, len } => write!(
+                f,
+                "Address conflict: 0x{wanted:x}..+0x{len:x} is already in use"
+            ),
+            Error::ImageTooLarge { requested, limit } => write!(
+                f,
+                "Image too large: 0x{requested:x} bytes requested, the limit is 0x{limit:x}"
+            ),
+            Error::ArchMismatch { found, expected } => write!(
+                f,
+                "Architecture mismatch: file is for {} (e_machine {found}), expected {} (e_machine {expected})",
+                machine_name(*found).unwrap_or("an unknown machine"),
+                machine_name(*expected).unwrap_or("an unknown machine"),
+            ),
+            Error::NotInitialized { module } => {
+                write!(
+                    f,
+                    "Module not initialized: [{module}] is not fully relocated"
+                )
+            }
+            Error::SymbolNotFound { module, symbol } => {
+                write!(
+                    f,
+                    "Symbol not found: [{symbol}] is not defined by [{module}]"
+                )
+            }
+            Error::MissingSymbols { module, symbols } => {
+                write!(
+                    f,
+                    "Missing symbols: {} unresolved in [{module}]",
+                    symbols.len()
+                )?;
+                for (idx, symbol) in symbols.iter().enumerate() {
+                    f.write_str(if idx == 0 { ": " } else { ", " })?;
+                    write!(f, "{symbol}")?;
+                }
+                Ok(())
+            }
+            Error::Prelink { msg } => write!(f, "Prelink error: {msg}"),
             Error::Custom { msg } => write!(f, "Custom error: {msg}"),
         }
     }
 }
 
-impl core::error::Error for Error {}
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } | Error::Mmap { source, .. } => source
+                .as_deref()
+                .map(|source| source as &(dyn core::error::Error + 'static)),
+            _ => None,
+        }
+    }
+}
+
+/// The error an [`Error::Io`] or [`Error::Mmap`] was caused by.
+pub type BoxedSource = Box<dyn core::error::Error + Send + Sync>;
+
+impl Error {
+    /// Returns the category of the error.
+    ///
+    /// Unlike the error messages, the categories are meant to be matched on.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{ErrorKind, Loader};
+    ///
+    /// let mut loader = Loader::new();
+    /// match loader.load_dylib("libplugin.so").and_then(|lib| lib.relocator().relocate()) {
+    ///     Ok(_) => {}
+    ///     Err(err) => match err.kind() {
+    ///         ErrorKind::Relocate {
+    ///             missing_symbol: Some(name),
+    ///         } => println!("libplugin.so needs {name}"),
+    ///         ErrorKind::Io => println!("cannot read libplugin.so: {err}"),
+    ///         _ => println!("{err}"),
+    ///     },
+    /// }
+    /// ```
+    pub fn kind(&self) -> ErrorKind<'_> {
+        match self {
+            Error::Io { .. } => ErrorKind::Io,
+            Error::Mmap { .. } => ErrorKind::Mmap,
+            Error::Relocation { context, .. } => ErrorKind::Relocate {
+                // Only a failed lookup records the searched sources
+                missing_symbol: context
+                    .as_deref()
+                    .filter(|context| !context.searched.is_empty())
+                    .and_then(RelocationErrorContext::symbol),
+            },
+            Error::MissingSymbols { symbols, .. } => ErrorKind::Relocate {
+                missing_symbol: symbols.first().map(MissingSymbol::name),
+            },
+            Error::ParseDynamic { .. } => ErrorKind::ParseDynamic,
+            Error::ParseEhdr { .. } => ErrorKind::ParseHeader,
+            Error::ParsePhdr { .. } | Error::MalformedHeader { .. } => ErrorKind::ParsePhdr,
+            Error::OutOfBounds { .. } | Error::Truncated { .. } => ErrorKind::OutOfBounds,
+            Error::AddressConflict { .. } | Error::ImageTooLarge { .. } => ErrorKind::AddressSpace,
+            Error::ArchMismatch { .. } => ErrorKind::ArchMismatch,
+            Error::NotInitialized { .. } | Error::SymbolNotFound { .. } => ErrorKind::Symbol,
+            Error::Prelink { .. } => ErrorKind::Prelink,
+            Error::Custom { .. } => ErrorKind::Custom,
+        }
+    }
+
+    /// Returns `true` if relocation failed because a symbol could not be resolved.
+    ///
+    /// This is the case a plugin loader typically handles by retrying with
+    /// more libraries in the scope.
+    #[inline]
+    pub fn is_missing_symbol(&self) -> bool {
+        self.missing_symbol_name().is_some()
+    }
+
+    /// Returns the name of the symbol that could not be resolved, if that is
+    /// why relocation failed.
+    ///
+    /// For [`Error::MissingSymbols`], this is the first unresolved symbol.
+    pub fn missing_symbol_name(&self) -> Option<&str> {
+        match self.kind() {
+            ErrorKind::Relocate { missing_symbol } => missing_symbol,
+            _ => None,
+        }
+    }
+
+    /// Attaches the error that caused an [`Error::Io`] or [`Error::Mmap`].
+    ///
+    /// Other errors are returned unchanged.
+    #[cold]
+    pub(crate) fn with_source(
+        mut self,
+        err: impl core::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        if let Error::Io { source, .. } | Error::Mmap { source, .. } = &mut self {
+            *source = Some(Box::new(err));
+        }
+        self
+    }
+}
+
+/// The category of an [`Error`], returned by [`Error::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind<'a> {
+    /// Reading the file failed, see [`Error::Io`].
+    Io,
+    /// Mapping memory or changing its protection failed, see [`Error::Mmap`].
+    Mmap,
+    /// The ELF header is invalid, see [`Error::ParseEhdr`].
+    ParseHeader,
+    /// The program headers are invalid, see [`Error::ParsePhdr`] and
+    /// [`Error::MalformedHeader`].
+    ParsePhdr,
+    /// The dynamic section is invalid, see [`Error::ParseDynamic`].
+    ParseDynamic,
+    /// The file references data outside the image or the input, see
+    /// [`Error::OutOfBounds`] and [`Error::Truncated`].
+    OutOfBounds,
+    /// The image cannot be placed in the address space, see
+    /// [`Error::AddressConflict`] and [`Error::ImageTooLarge`].
+    AddressSpace,
+    /// The file was built for another architecture, see [`Error::ArchMismatch`].
+    ArchMismatch,
+    /// Relocation failed, see [`Error::Relocation`] and [`Error::MissingSymbols`].
+    Relocate {
+        /// The symbol that could not be resolved, if that is why relocation failed.
+        missing_symbol: Option<&'a str>,
+    },
+    /// A symbol could not be obtained from a loaded module, see
+    /// [`Error::NotInitialized`] and [`Error::SymbolNotFound`].
+    Symbol,
+    /// A prelinked image cannot be used, see [`Error::Prelink`].
+    Prelink,
+    /// A user-defined callback failed, see [`Error::Custom`].
+    Custom,
+}
+
+/// An error code reported by the operating system, such as an `errno` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsError {
+    code: i32,
+}
+
+impl OsError {
+    /// Wraps a raw error code.
+    #[inline]
+    pub(crate) fn new(code: i32) -> Self {
+        Self { code }
+    }
+
+    /// Returns the raw error code.
+    #[inline]
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+}
+
+impl Display for OsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        #[cfg(feature = "std")]
+        return Display::fmt(&std::io::Error::from_raw_os_error(self.code), f);
+        #[cfg(not(feature = "std"))]
+        write!(f, "os error {}", self.code)
+    }
+}
+
+impl core::error::Error for OsError {}
+
+/// The relocation table an entry was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationTable {
+    /// The dynamic relocation table (`DT_RELA`/`DT_REL`).
+    Dynamic,
+    /// The PLT relocation table (`DT_JMPREL`).
+    Plt,
+    /// The compact relative relocation table (`DT_RELR`).
+    Relr,
+}
+
+impl Display for RelocationTable {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RelocationTable::Dynamic => f.write_str("dynamic relocations"),
+            RelocationTable::Plt => f.write_str("PLT relocations"),
+            RelocationTable::Relr => f.write_str("RELR relocations"),
+        }
+    }
+}
+
+/// Details about the relocation entry behind an [`Error::Relocation`].
+#[derive(Debug, Clone)]
+pub struct RelocationErrorContext {
+    file: String,
+    r_type: u32,
+    r_type_str: &'static str,
+    symbol: Option<String>,
+    version: Option<String>,
+    version_file: Option<String>,
+    r_offset: usize,
+    target: usize,
+    entry: Option<(RelocationTable, usize)>,
+    searched: Vec<String>,
+}
+
+impl RelocationErrorContext {
+    /// Collects the details of a relocation entry of `lib`.
+    pub(crate) fn new<D>(rel: &ElfRelType, lib: &ElfCore<D>) -> Self {
+        let r_sym = rel.r_symbol();
+        let symtab = lib.symtab();
+        // The index is not trusted here, since the entry may be the one at fault
+        let syminfo =
+            (r_sym != 0 && r_sym < symtab.count_syms()).then(|| symtab.symbol_idx(r_sym).1);
+        let version = syminfo
+            .as_ref()
+            .and_then(|syminfo| syminfo.required_version());
+        Self {
+            file: lib.name().to_string(),
+            r_type: rel.r_type() as u32,
+            r_type_str: rel.r_type_str(),
+            symbol: syminfo.as_ref().map(|syminfo| syminfo.name().to_string()),
+            version: version.map(|(name, _)| name.to_string()),
+            version_file: version.and_then(|(_, file)| file).map(ToString::to_string),
+            r_offset: rel.r_offset(),
+            target: lib.base() + rel.r_offset(),
+            entry: None,
+            searched: Vec::new(),
+        }
+    }
+
+    /// Collects the details of a word relocated through the `DT_RELR` table of `lib`.
+    pub(crate) fn relr<D>(r_offset: usize, lib: &ElfCore<D>) -> Self {
+        Self {
+            file: lib.name().to_string(),
+            r_type: REL_RELATIVE,
+            r_type_str: rel_type_to_str(REL_RELATIVE as usize),
+            symbol: None,
+            version: None,
+            version_file: None,
+            r_offset,
+            target: lib.base() + r_offset,
+            entry: None,
+            searched: Vec::new(),
+        }
+    }
+
+    /// Records the position of the entry in its relocation table.
+    pub(crate) fn with_entry(mut self, table: RelocationTable, index: usize) -> Self {
+        self.entry = Some((table, index));
+        self
+    }
+
+    /// Records the symbol sources that were searched, in order.
+    pub(crate) fn with_searched(mut self, searched: Vec<String>) -> Self {
+        self.searched = searched;
+        self
+    }
+
+    /// Returns the name of the object being relocated.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Returns the relocation type.
+    pub fn r_type(&self) -> u32 {
+        self.r_type
+    }
+
+    /// Returns the name of the symbol referenced by the relocation, if any.
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+
+    /// Returns the version the relocation requires of its symbol, if any.
+    ///
+    /// This is only known with the `version` feature.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Returns the library the version of the symbol is required from, if any.
+    pub fn version_file(&self) -> Option<&str> {
+        self.version_file.as_deref()
+    }
+
+    /// Returns the offset of the relocated word from the base of the object.
+    pub fn r_offset(&self) -> usize {
+        self.r_offset
+    }
+
+    /// Returns the address of the relocated word, usually a GOT slot.
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    /// Returns the table the entry was read from and its index in that table.
+    ///
+    /// This is `None` for relocations applied to relocatable objects.
+    pub fn entry(&self) -> Option<(RelocationTable, usize)> {
+        self.entry
+    }
+
+    /// Returns the symbol sources that were searched, in order.
+    ///
+    /// The scope libraries appear by name, while the user lookups appear as
+    /// `pre_find` and `post_find`. This is empty if no symbol lookup happened.
+    pub fn searched(&self) -> &[String] {
+        &self.searched
+    }
+}
+
+impl Display for RelocationErrorContext {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "file: {}, relocation type: {}",
+            self.file, self.r_type_str
+        )?;
+        match &self.symbol {
+            Some(symbol) => write!(f, ", symbol: {symbol}")?,
+            None => f.write_str(", no symbol")?,
+        }
+        if let Some(version) = &self.version {
+            write!(f, ", version: {version}")?;
+            if let Some(file) = &self.version_file {
+                write!(f, " (required from {file})")?;
+            }
+        }
+        if let Some((table, index)) = self.entry {
+            write!(f, ", entry: {index} of {table}")?;
+        }
+        write!(
+            f,
+            ", offset: {:#x}, address: {:#x}",
+            self.r_offset, self.target
+        )?;
+        if !self.searched.is_empty() {
+            write!(f, ", searched: {}", self.searched.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A symbol reference that could not be resolved, see [`Error::MissingSymbols`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingSymbol {
+    name: String,
+    r_type: u32,
+    r_type_str: &'static str,
+    r_offset: usize,
+}
+
+impl MissingSymbol {
+    /// Collects the details of the relocation entry `rel`, which references `name`.
+    pub(crate) fn new(name: &str, rel: &ElfRelType) -> Self {
+        Self {
+            name: name.to_string(),
+            r_type: rel.r_type() as u32,
+            r_type_str: rel.r_type_str(),
+            r_offset: rel.r_offset(),
+        }
+    }
+
+    /// Returns the name of the symbol.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the relocation referencing the symbol.
+    pub fn r_type(&self) -> u32 {
+        self.r_type
+    }
+
+    /// Returns the offset of the relocated word from the base of the object.
+    pub fn r_offset(&self) -> usize {
+        self.r_offset
+    }
+}
+
+impl Display for MissingSymbol {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} ({} at {:#x})",
+            self.name, self.r_type_str, self.r_offset
+        )
+    }
+}
 
 /// Creates an I/O error with the specified message.
 ///
@@ -112,7 +690,26 @@ impl core::error::Error for Error {}
 #[inline(never)]
 #[allow(unused)]
 pub(crate) fn io_error(msg: impl Into<Cow<'static, str>>) -> Error {
-    Error::Io { msg: msg.into() }
+    Error::Io {
+        msg: msg.into(),
+        source: None,
+    }
+}
+
+/// Creates a prelink error with the specified message.
+///
+/// This is a convenience function for creating `Error::Prelink` variants.
+///
+/// # Arguments
+/// * `msg` - The error message.
+///
+/// # Returns
+/// An `Error::Prelink` variant with the specified message.
+#[cold]
+#[inline(never)]
+#[cfg(feature = "prelink")]
+pub(crate) fn prelink_error(msg: impl Into<Cow<'static, str>>) -> Error {
+    Error::Prelink { msg: msg.into() }
 }
 
 /// Creates a relocation error with the specified message.
@@ -127,7 +724,10 @@ pub(crate) fn io_error(msg: impl Into<Cow<'static, str>>) -> Error {
 #[cold]
 #[inline(never)]
 pub(crate) fn relocate_error(msg: impl Into<Cow<'static, str>>) -> Error {
-    Error::Relocation { msg: msg.into() }
+    Error::Relocation {
+        msg: msg.into(),
+        context: None,
+    }
 }
 
 /// Creates a dynamic section parsing error with the specified message.
@@ -160,6 +760,21 @@ pub(crate) fn parse_ehdr_error(msg: impl Into<Cow<'static, str>>) -> Error {
     Error::ParseEhdr { msg: msg.into() }
 }
 
+/// Creates a program header parsing error with the specified message.
+///
+/// This is a convenience function for creating `Error::ParsePhdr` variants.
+///
+/// # Arguments
+/// * `msg` - The error message.
+///
+/// # Returns
+/// An `Error::ParsePhdr` variant with the specified message.
+#[cold]
+#[inline(never)]
+pub(crate) fn parse_phdr_error(msg: impl Into<Cow<'static, str>>) -> Error {
+    Error::ParsePhdr { msg: msg.into() }
+}
+
 /// Creates a custom error with the specified message.
 ///
 /// This is a convenience function for creating `Error::Custom` variants.
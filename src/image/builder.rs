@@ -1,19 +1,32 @@
 use crate::{
-    LoadHook, LoadHookContext, Result,
+    LoadHook, LoadHookContext, Result, UnknownDynamicPolicy,
+    allocator::{LoaderAlloc, NulStr},
     elf::{Dyn, ElfPhdr, ElfRelType, ElfShdr, ElfSymbol},
-    elf::{ElfHeader, ElfPhdrs, SymbolTable},
+    elf::{ElfHeader, ElfNotes, ElfPhdrs, GnuProperties, SymbolTable},
+    image::FnArray,
     loader::FnHandler,
-    os::Mmap,
+    os::{Mmap, ProtFlags},
     relocation::StaticRelocation,
-    segment::{ELFRelro, ElfSegments, section::PltGotSection},
+    segment::{ELFRelro, ElfSegments, program::segment_prot, section::PltGotSection},
+    tls::{TlsAllocator, TlsInfo},
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    ffi::{CStr, c_char},
+    marker::PhantomData,
+    ptr::NonNull,
 };
-use alloc::{boxed::Box, string::String, vec::Vec};
-use core::{ffi::c_char, marker::PhantomData, ptr::NonNull};
 use elf::abi::{
-    PT_DYNAMIC, PT_GNU_RELRO, PT_INTERP, PT_LOAD, PT_PHDR, SHN_UNDEF, SHT_INIT_ARRAY, SHT_REL,
-    SHT_RELA, SHT_SYMTAB, STT_FILE,
+    PT_DYNAMIC, PT_GNU_PROPERTY, PT_GNU_RELRO, PT_GNU_STACK, PT_INTERP, PT_LOAD, PT_PHDR, PT_TLS,
+    SHN_UNDEF, SHT_FINI_ARRAY, SHT_INIT_ARRAY, SHT_PROGBITS, SHT_REL, SHT_RELA, SHT_SYMTAB,
+    STT_FILE,
 };
 
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
 /// Builder for creating relocated ELF objects
 ///
 /// This structure is used internally during the loading process to collect
@@ -31,7 +44,10 @@ where
     phdr_mmap: Option<&'static [ElfPhdr]>,
 
     /// Name of the ELF file
-    pub(crate) name: String,
+    pub(crate) name: NulStr,
+
+    /// Path the ELF file was read from, if known
+    pub(crate) path: Option<NulStr>,
 
     /// ELF header
     pub(crate) ehdr: ElfHeader,
@@ -57,6 +73,30 @@ where
     /// Pointer to the interpreter path (PT_INTERP)
     pub(crate) interp: Option<NonNull<c_char>>,
 
+    /// Stack protection requested by the PT_GNU_STACK segment
+    pub(crate) stack_flags: Option<ProtFlags>,
+
+    /// Features recorded in the PT_GNU_PROPERTY segment
+    pub(crate) gnu_properties: Option<GnuProperties>,
+
+    /// Notes of the PT_NOTE segments
+    pub(crate) notes: ElfNotes,
+
+    /// Whether the memory is owned by the caller, who also manages its protections
+    premapped: bool,
+
+    /// TLS template from the PT_TLS segment
+    pub(crate) tls: Option<TlsInfo>,
+
+    /// Allocator for the TLS blocks of the object
+    pub(crate) tls_allocator: Option<Arc<dyn TlsAllocator>>,
+
+    /// How the dynamic tags the loader does not interpret are treated
+    pub(crate) unknown_dynamic: UnknownDynamicPolicy,
+
+    /// Allocator of the per-load data
+    pub(crate) alloc: LoaderAlloc,
+
     /// Phantom data to maintain Mmap type information
     _marker: PhantomData<M>,
 }
@@ -74,21 +114,24 @@ where
     /// * `ehdr` - ELF header
     /// * `init_fn` - Initialization function handler
     /// * `fini_fn` - Finalization function handler
+    /// * `alloc` - Allocator of the per-load data
     ///
     /// # Returns
     /// A new DynamicBuilder instance
     pub(crate) fn new(
         hook: &'hook H,
         segments: ElfSegments,
-        name: String,
+        name: &str,
         ehdr: ElfHeader,
         init_fn: FnHandler,
         fini_fn: FnHandler,
+        alloc: &LoaderAlloc,
     ) -> Self {
         Self {
             hook,
             phdr_mmap: None,
-            name,
+            name: NulStr::new(name, alloc),
+            path: None,
             ehdr,
             relro: None,
             dynamic_ptr: None,
@@ -97,55 +140,145 @@ where
             init_fn,
             fini_fn,
             interp: None,
+            stack_flags: None,
+            gnu_properties: None,
+            notes: ElfNotes::default(),
+            premapped: false,
+            tls: None,
+            tls_allocator: None,
+            unknown_dynamic: UnknownDynamicPolicy::Ignore,
+            alloc: alloc.clone(),
             _marker: PhantomData,
         }
     }
 
+    /// Marks the memory as mapped by the caller, so that no RELRO protection is applied
+    pub(crate) fn premapped(mut self) -> Self {
+        self.premapped = true;
+        self
+    }
+
+    /// Sets the allocator that the TLS block of the object is registered with
+    pub(crate) fn tls_allocator(mut self, allocator: Option<Arc<dyn TlsAllocator>>) -> Self {
+        self.tls_allocator = allocator;
+        self
+    }
+
+    /// Sets how the dynamic tags the loader does not interpret are treated
+    pub(crate) fn unknown_dynamic(mut self, policy: UnknownDynamicPolicy) -> Self {
+        self.unknown_dynamic = policy;
+        self
+    }
+
+    /// Sets the notes of the PT_NOTE segments
+    pub(crate) fn notes(mut self, notes: ElfNotes) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    /// Sets the path the ELF file was read from
+    pub(crate) fn path(mut self, path: &str) -> Self {
+        self.path = Some(NulStr::new(path, &self.alloc));
+        self
+    }
+
+    /// Parse all program headers
+    ///
+    /// # Arguments
+    /// * `phdrs` - Slice of program headers
+    ///
+    /// # Returns
+    /// * `Ok(overrides)` - The protection overrides requested by the hook,
+    ///   keyed by the index of the `PT_LOAD` segment they apply to
+    /// * `Err(Error)` - If parsing fails
+    pub(crate) fn parse_phdrs(&mut self, phdrs: &[ElfPhdr]) -> Result<Vec<(usize, ProtFlags)>> {
+        let mut overrides = Vec::new();
+        let mut load_idx = 0;
+        for phdr in phdrs {
+            let prot = self.parse_phdr(phdr)?;
+            if phdr.p_type == PT_LOAD {
+                if let Some(prot) = prot {
+                    overrides.push((load_idx, prot));
+                }
+                load_idx += 1;
+            }
+        }
+        Ok(overrides)
+    }
+
     /// Parse a program header and extract relevant information
     ///
     /// This method processes a program header and extracts information
     /// needed for relocation, such as the dynamic section, GNU_RELRO
-    /// segment, and interpreter path.
+    /// segment, interpreter path, and GNU_STACK flags.
     ///
     /// # Arguments
     /// * `phdr` - The program header to parse
     ///
     /// # Returns
-    /// * `Ok(())` - If parsing succeeds
+    /// * `Ok(prot)` - The protection override requested by the hook, if any
     /// * `Err(Error)` - If parsing fails
-    pub(crate) fn parse_phdr(&mut self, phdr: &ElfPhdr) -> Result<()> {
-        let mut ctx = LoadHookContext::new(&self.name, phdr, &self.segments, &mut self.user_data);
+    fn parse_phdr(&mut self, phdr: &ElfPhdr) -> Result<Option<ProtFlags>> {
+        let mut prot = None;
+        let mut ctx = LoadHookContext::new(
+            &self.name,
+            phdr,
+            &self.segments,
+            &mut self.user_data,
+            &mut prot,
+        );
         self.hook.call(&mut ctx)?;
 
         // Process different program header types
         match phdr.p_type {
             // Parse the .dynamic section
             PT_DYNAMIC => {
+                let offset = phdr.p_paddr as usize;
+                let len = (phdr.p_memsz as usize).max(size_of::<Dyn>());
+                self.segments.check_range(offset, len)?;
                 self.dynamic_ptr =
-                    Some(NonNull::new(self.segments.get_mut_ptr(phdr.p_paddr as usize)).unwrap())
+                    Some(NonNull::new(self.segments.get_mut_ptr_unchecked(offset)).unwrap())
             }
 
             // Store GNU_RELRO segment information
-            PT_GNU_RELRO => self.relro = Some(ELFRelro::new::<M>(phdr, self.segments.base())),
+            PT_GNU_RELRO if !self.premapped => {
+                self.relro = Some(ELFRelro::new::<M>(phdr, self.segments.base()))
+            }
 
             // Store program header table mapping
             PT_PHDR => {
                 self.phdr_mmap = Some(
                     self.segments
-                        .get_slice::<ElfPhdr>(phdr.p_vaddr as usize, phdr.p_memsz as usize),
+                        .get_slice::<ElfPhdr>(phdr.p_vaddr as usize, phdr.p_memsz as usize)?,
                 );
             }
 
             // Store interpreter path
             PT_INTERP => {
+                let offset = phdr.p_vaddr as usize;
+                self.segments.check_range(offset, phdr.p_memsz as usize)?;
                 self.interp =
-                    Some(NonNull::new(self.segments.get_mut_ptr(phdr.p_vaddr as usize)).unwrap());
+                    Some(NonNull::new(self.segments.get_mut_ptr_unchecked(offset)).unwrap());
+            }
+
+            // Store the TLS template
+            PT_TLS => self.tls = Some(TlsInfo::new(phdr, self.segments.base())),
+
+            // Store the requested stack protection
+            PT_GNU_STACK => self.stack_flags = Some(segment_prot(phdr.p_flags)),
+
+            // Read the hardware features the object was built for
+            PT_GNU_PROPERTY => {
+                let notes = self
+                    .segments
+                    .get_slice::<u8>(phdr.p_vaddr as usize, phdr.p_memsz as usize)?;
+                self.gnu_properties = Some(GnuProperties::parse(notes));
             }
 
             // Ignore other program header types
             _ => {}
         };
-        Ok(())
+        Ok(prot)
     }
 
     /// Create program headers from the parsed data
@@ -160,7 +293,8 @@ where
     /// # Returns
     /// An ElfPhdrs enum containing either mapped or vector-based headers
     pub(crate) fn create_phdrs(&self, phdrs: &[ElfPhdr]) -> ElfPhdrs {
-        let (phdr_start, phdr_end) = self.ehdr.phdr_range();
+        let phdr_start = self.ehdr.e_phoff();
+        let phdr_end = phdr_start + size_of_val(phdrs);
 
         // Get mapped program headers or create them from loaded segments
         self.phdr_mmap
@@ -172,16 +306,23 @@ where
                         let cur_range =
                             phdr.p_offset as usize..(phdr.p_offset + phdr.p_filesz) as usize;
                         if cur_range.contains(&phdr_start) && cur_range.contains(&phdr_end) {
-                            return Some(self.segments.get_slice::<ElfPhdr>(
-                                phdr.p_vaddr as usize + phdr_start - cur_range.start,
-                                self.ehdr.e_phnum() * size_of::<ElfPhdr>(),
-                            ));
+                            return self
+                                .segments
+                                .get_slice::<ElfPhdr>(
+                                    phdr.p_vaddr as usize + phdr_start - cur_range.start,
+                                    size_of_val(phdrs),
+                                )
+                                .ok();
                         }
                         None
                     })
             })
             .map(|phdrs| ElfPhdrs::Mmap(phdrs))
-            .unwrap_or_else(|| ElfPhdrs::Vec(Vec::from(phdrs)))
+            .unwrap_or_else(|| {
+                let mut vec = self.alloc.vec();
+                vec.extend_from_slice(phdrs);
+                ElfPhdrs::Vec(vec)
+            })
     }
 }
 
@@ -192,13 +333,16 @@ where
 /// building the final ElfRelocatable object.
 pub(crate) struct ObjectBuilder {
     /// Name of the ELF file
-    pub(crate) name: String,
+    pub(crate) name: NulStr,
 
     /// Symbol table for the ELF file
     pub(crate) symtab: SymbolTable,
 
-    /// Initialization function array
-    pub(crate) init_array: Option<&'static [fn()]>,
+    /// Constructor arrays (`.init_array` and `.ctors` sections)
+    pub(crate) init_arrays: Vec<FnArray>,
+
+    /// Destructor arrays (`.fini_array` and `.dtors` sections)
+    pub(crate) fini_arrays: Vec<FnArray>,
 
     /// Initialization function handler
     pub(crate) init_fn: FnHandler,
@@ -228,6 +372,7 @@ impl ObjectBuilder {
     /// # Arguments
     /// * `name` - The name of the ELF file
     /// * `shdrs` - Mutable reference to the section headers
+    /// * `shstrndx` - Index of the section name string table
     /// * `init_fn` - Initialization function handler
     /// * `fini_fn` - Finalization function handler
     /// * `segments` - Memory segments of the ELF file
@@ -236,9 +381,11 @@ impl ObjectBuilder {
     ///
     /// # Returns
     /// A new RelocatableBuilder instance
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
-        name: String,
+        name: NulStr,
         shdrs: &mut [ElfShdr],
+        shstrndx: usize,
         init_fn: FnHandler,
         fini_fn: FnHandler,
         segments: ElfSegments,
@@ -259,7 +406,15 @@ impl ObjectBuilder {
         // Initialize optional components
         let mut symtab = None;
         let mut relocation = Vec::with_capacity(shdrs.len());
-        let mut init_array = None;
+        let mut init_arrays = Vec::new();
+        let mut fini_arrays = Vec::new();
+        let section_name = |shdr: &ElfShdr| {
+            let shstrtab = shdrs.get(shstrndx)?;
+            let name = unsafe {
+                CStr::from_ptr((shstrtab.sh_addr as usize + shdr.sh_name as usize) as *const c_char)
+            };
+            name.to_str().ok()
+        };
 
         // Process each section header
         for shdr in shdrs.iter() {
@@ -292,11 +447,17 @@ impl ObjectBuilder {
                     relocation.push(shdr.content());
                 }
 
-                // Initialization array section
-                SHT_INIT_ARRAY => {
-                    let array: &[usize] = shdr.content_mut();
-                    // Transmute the array to function pointers
-                    init_array = Some(unsafe { core::mem::transmute(array) });
+                // Constructor and destructor arrays
+                SHT_INIT_ARRAY | SHT_FINI_ARRAY | SHT_PROGBITS => {
+                    let Some(array) = section_name(shdr).and_then(|name| FnArray::new(shdr, name))
+                    else {
+                        continue;
+                    };
+                    if array.is_dtors() {
+                        fini_arrays.push(array);
+                    } else {
+                        init_arrays.push(array);
+                    }
                 }
 
                 // Other section types are ignored
@@ -314,7 +475,8 @@ impl ObjectBuilder {
             mprotect,
             relocation: StaticRelocation::new(relocation),
             pltgot,
-            init_array,
+            init_arrays,
+            fini_arrays,
         }
     }
 }
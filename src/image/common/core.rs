@@ -5,28 +5,69 @@
 //! relocated and loaded libraries or executables.
 
 use crate::{
-    Result,
-    elf::{Dyn, ElfPhdr},
-    elf::{ElfDynamic, ElfPhdrs, SymbolInfo, SymbolTable},
-    image::{Symbol, common::DynamicInfo},
-    loader::FnHandler,
-    relocation::SymDef,
-    segment::ElfSegments,
+    Error, Result, UnknownDynamicPolicy,
+    allocator::{LoaderAlloc, NulStr},
+    elf::{Dyn, DynamicEntries, ElfNotes, ElfPhdr, NoteIter},
+    elf::{ElfDynamic, ElfPhdrs, ElfSymbol, PreCompute, PreparedSymbol, SymbolInfo, SymbolTable},
+    image::{
+        Extensions, Symbol,
+        common::{CopyRecord, DynamicInfo},
+    },
+    loader::{FnHandler, InitFn},
+    os::ProtFlags,
+    registry,
+    relocation::{self, PltEntry, PostFini, SymDef},
+    segment::{ElfSegments, PAGE_SIZE, program::segment_prot},
+    tls::TlsModule,
 };
 use alloc::{string::String, vec::Vec};
 use core::{
-    ffi::c_void,
+    any::Any,
+    ffi::{c_char, c_void},
     fmt::Debug,
     marker::PhantomData,
-    ptr::NonNull,
+    ops::Range,
+    ptr::{NonNull, null},
     sync::atomic::{AtomicBool, Ordering},
 };
+use elf::abi::{DF_1_NODELETE, PT_LOAD, SHN_ABS, STB_GLOBAL, STB_WEAK, STT_TLS};
+use hashbrown::HashMap;
+use spin::{Mutex, RwLock};
 
 #[cfg(not(feature = "portable-atomic"))]
 use alloc::sync::{Arc, Weak};
 #[cfg(feature = "portable-atomic")]
 use portable_atomic_util::{Arc, Weak};
 
+/// A snapshot of a loaded module, laid out like the first three fields of
+/// glibc's `struct link_map`.
+///
+/// This is what debuggers expect to find through the `r_debug` protocol.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LinkMapView {
+    /// Difference between the load address and the link-time address (`l_addr`)
+    pub l_addr: usize,
+    /// NUL-terminated name of the module (`l_name`)
+    pub l_name: *const c_char,
+    /// Address of the dynamic section, or null if there is none (`l_ld`)
+    pub l_ld: *const Dyn,
+}
+
+/// The layout of a `PT_LOAD` segment of a loaded module, see
+/// [`LoadedCore::segments_layout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentInfo {
+    /// Address range of the pages the segment is mapped to
+    pub vaddr_range: Range<usize>,
+    /// Protection of the pages, as requested by the program header
+    pub prot: ProtFlags,
+    /// Whether the segment has contents read from the file
+    pub from_file: bool,
+    /// Range of the file the contents were read from, if any
+    pub file_range: Option<Range<usize>>,
+}
+
 /// A fully loaded and relocated ELF module.
 ///
 /// This structure represents an ELF object (executable, shared library, or relocatable object)
@@ -34,6 +75,19 @@ use portable_atomic_util::{Arc, Weak};
 ///
 /// It maintains an `Arc` reference to its dependencies to ensure that required
 /// libraries remain in memory as long as this module is alive.
+///
+/// # Thread safety
+///
+/// A `LoadedCore<D>`, and the [`LoadedDylib`](crate::image::LoadedDylib) and
+/// [`LoadedExec`](crate::image::LoadedExec) wrapping it, are `Send` and `Sync`
+/// when `D` is `Send` and `Sync`. [`user_data`](Self::user_data) hands out
+/// shared references to `D`, and `D` is dropped by whichever thread releases
+/// the last handle. Every operation on a shared handle is then thread safe:
+/// symbol lookups only read the symbol tables, and the state that changes
+/// after relocation, such as the lazy binding scope, the lazy fallback and
+/// the RELRO protection, is held behind atomics and locks. The modules that
+/// are still being relocated carry their lazily parsed data in cells and are
+/// not `Sync`.
 #[derive(Debug)]
 pub struct LoadedCore<D> {
     /// The core ELF module data and metadata.
@@ -68,6 +122,57 @@ impl<D> LoadedCore<D> {
         }
     }
 
+    /// Unloads the module like dropping its last handle, but returns the
+    /// error of unmapping its memory instead of reporting it through
+    /// [`set_unmap_error_handler`](crate::set_unmap_error_handler).
+    ///
+    /// The module is only unloaded if this handle holds the last strong
+    /// reference to it; once it is taken, weak references such as the global
+    /// scope can no longer reach the module. Its finalization functions run
+    /// before the memory is unmapped, and the references to its dependencies
+    /// are released after.
+    ///
+    /// # Returns
+    /// * `Ok(result)` - If the module was unloaded, with the result of the unmapping.
+    /// * `Err(self)` - If other modules or handles still reference the module.
+    pub fn try_unload_segments(self) -> core::result::Result<Result<()>, Self> {
+        self.try_unload_with(|inner| {
+            if let Some(tls) = &inner.tls {
+                tls.unregister();
+            }
+            inner.segments.unmap()
+        })
+    }
+
+    /// Takes the last strong reference to the module, then releases it and
+    /// calls `f` before the module and its dependencies are dropped.
+    ///
+    /// Taking the reference is atomic, so no weak reference can be upgraded
+    /// once the module is being unloaded.
+    pub(crate) fn try_unload_with<R>(
+        self,
+        f: impl FnOnce(&CoreInner<D>) -> R,
+    ) -> core::result::Result<R, Self> {
+        let LoadedCore { core, deps } = self;
+        let id = core.inner_addr();
+        let inner = match Arc::try_unwrap(core.inner) {
+            Ok(inner) => inner,
+            Err(inner) => {
+                return Err(LoadedCore {
+                    core: ElfCore { inner },
+                    deps,
+                });
+            }
+        };
+        // The inner data has moved out of the shared allocation, but it is
+        // still known by the address it was registered with
+        inner.release_as(id);
+        let result = f(&inner);
+        drop(inner);
+        drop(deps);
+        Ok(result)
+    }
+
     /// Returns a slice of the libraries this module depends on.
     pub fn deps(&self) -> &[LoadedCore<D>] {
         &self.deps
@@ -85,6 +190,330 @@ impl<D> LoadedCore<D> {
         self.core.base()
     }
 
+    /// Gets the memory length of the ELF object map
+    #[inline]
+    pub fn mapped_len(&self) -> usize {
+        self.core.mapped_len()
+    }
+
+    /// Gets the alignment the base address was loaded at
+    #[inline]
+    pub fn load_align(&self) -> usize {
+        self.core.load_align()
+    }
+
+    /// Gets the address space reserved after the image, as `(start, len)`
+    ///
+    /// See [`Loader::reserve_tail`](crate::Loader::reserve_tail).
+    #[inline]
+    pub fn reserved_tail(&self) -> Option<(usize, usize)> {
+        self.core.segments().reserved_tail()
+    }
+
+    /// Applies `prot` to part of the address space reserved after the image
+    ///
+    /// # Arguments
+    /// * `offset` - Offset of the range from the start of the reserved tail
+    /// * `len` - Length of the range in bytes
+    /// * `prot` - The protection to apply; the range is widened to page boundaries
+    ///
+    /// # Returns
+    /// An error if nothing was reserved, the range does not fit in the
+    /// reservation, or the protection change fails
+    #[inline]
+    pub fn commit_tail(&self, offset: usize, len: usize, prot: ProtFlags) -> Result<()> {
+        self.core.segments().commit_tail(offset, len, prot)
+    }
+
+    /// Gets the address range of the `PT_GNU_RELRO` segment
+    ///
+    /// Protection changes apply to the pages the range overlaps.
+    #[inline]
+    pub fn relro_range(&self) -> Option<Range<usize>> {
+        self.core
+            .inner
+            .dynamic_info
+            .as_ref()
+            .and_then(|info| info.relro.as_ref())
+            .map(|relro| relro.range())
+    }
+
+    /// Returns the `JUMP_SLOT` relocations of the module.
+    ///
+    /// Each [`PltEntry`] names an imported function and the GOT slot its PLT
+    /// stub jumps through, which makes it possible to intercept every call the
+    /// module makes to other modules. Images without a PLT yield nothing.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, input::ElfBinary};
+    ///
+    /// let mut loader = Loader::new();
+    /// # let bytes = &[];
+    /// let lib = loader
+    ///     .load_dylib(ElfBinary::new("liba.so", bytes))
+    ///     .unwrap()
+    ///     .relocator()
+    ///     .relocate()
+    ///     .unwrap();
+    /// for entry in lib.plt_entries() {
+    ///     println!("{} -> {:#x}", entry.symbol(), entry.current_target());
+    /// }
+    /// ```
+    pub fn plt_entries(&self) -> impl Iterator<Item = PltEntry<'_>> {
+        relocation::plt_entries(&self.core.inner)
+    }
+
+    /// Returns the address range of the GOT used by the PLT.
+    ///
+    /// The range starts at `DT_PLTGOT`, with the entries reserved for the
+    /// dynamic linker, and ends after the last `JUMP_SLOT` slot.
+    ///
+    /// # Returns
+    /// `None` if the image has no `DT_PLTGOT` entry.
+    pub fn got_plt_range(&self) -> Option<Range<usize>> {
+        let start = self.core.inner.dynamic_info.as_ref()?.got_plt?.as_ptr() as usize;
+        let end = self
+            .plt_entries()
+            .map(|entry| entry.got_slot() as usize + size_of::<usize>())
+            .fold(start, usize::max);
+        Some(start..end)
+    }
+
+    /// Makes the `PT_GNU_RELRO` segment read-only
+    ///
+    /// This completes a relocation done with
+    /// [`apply_relro(false)`](crate::relocation::Relocator::apply_relro). Nothing
+    /// happens if the protection is already in effect or the image has no
+    /// `PT_GNU_RELRO` segment.
+    pub fn apply_relro(&self) -> Result<()> {
+        match self.core.inner.dynamic_info.as_ref() {
+            Some(info) => info.protect_relro(),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs `f` with the `PT_GNU_RELRO` segment temporarily writable
+    ///
+    /// The read-only protection is restored once `f` returns, and also if it
+    /// panics. If the protection is not in effect, `f` simply runs. `f` must not
+    /// call [`apply_relro`](Self::apply_relro), [`with_relro_writable`](Self::with_relro_writable)
+    /// or [`rebind_symbol`](crate::image::LoadedDylib::rebind_symbol) on this
+    /// image, since they wait for it to finish.
+    ///
+    /// # Returns
+    /// The value returned by `f`, or an error if a protection change fails
+    pub fn with_relro_writable<R>(&self, f: impl FnOnce() -> R) -> Result<R> {
+        match self.core.inner.dynamic_info.as_ref() {
+            Some(info) => info.with_relro_writable(f),
+            None => Ok(f()),
+        }
+    }
+
+    /// Gets the DT_FLAGS_1 value
+    #[inline]
+    pub fn flags_1(&self) -> usize {
+        self.core.flags_1()
+    }
+
+    /// Gets the user data attached to the ELF object
+    #[inline]
+    pub fn user_data(&self) -> &D {
+        self.core.user_data()
+    }
+
+    /// Whether the ELF object is marked with DF_1_NODELETE
+    ///
+    /// Such objects are expected to stay loaded for the lifetime of the process,
+    /// so callers implementing `dlclose` semantics should not unload them.
+    #[inline]
+    pub fn is_nodelete(&self) -> bool {
+        self.core.is_nodelete()
+    }
+
+    /// Whether the ELF object was linked with `-Bsymbolic`
+    ///
+    /// The relocations of such objects bind to their own definitions first.
+    #[inline]
+    pub fn is_symbolic(&self) -> bool {
+        self.core.is_symbolic()
+    }
+
+    /// Whether the ELF object is the main program, a dynamically linked
+    /// executable loaded with [`Loader::load_exec`](crate::Loader::load_exec)
+    ///
+    /// The copies the main program makes of the variables of other objects
+    /// with `R_*_COPY` relocations are the canonical definitions: the modules
+    /// of its scope are redirected to them, and modules relocated later
+    /// against a scope that contains the main program bind to them.
+    #[inline]
+    pub fn is_main_program(&self) -> bool {
+        self.core.is_main_program()
+    }
+
+    /// Gets the number of strong references to the ELF object
+    ///
+    /// A count of `1` means that no other module, scope or handle keeps this
+    /// object alive, so unloading it would actually release it.
+    #[inline]
+    pub fn strong_count(&self) -> usize {
+        self.core.strong_count()
+    }
+
+    /// Returns the address range of the `PT_GNU_EH_FRAME` segment, if present.
+    #[inline]
+    pub fn eh_frame_hdr(&self) -> Option<Range<usize>> {
+        self.core.eh_frame_hdr()
+    }
+
+    /// Returns the TLS module id of the object, if it has one.
+    #[inline]
+    pub fn tls_mod_id(&self) -> Option<usize> {
+        self.core.tls_mod_id()
+    }
+
+    /// Returns the layout of the `PT_LOAD` segments, in program header order
+    ///
+    /// The layout is derived from the program headers and the base address.
+    /// The protections are the ones the program headers request, so neither
+    /// the override of a load hook nor the `PT_GNU_RELRO` protection is
+    /// reflected; see [`relro_range`](Self::relro_range) for the latter.
+    /// Relocatable objects have no program headers and yield nothing.
+    pub fn segments_layout(&self) -> Vec<SegmentInfo> {
+        let base = self.base();
+        let phdrs = self.core.phdrs().unwrap_or_default();
+        phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .map(|phdr| {
+                let start = base + phdr.p_vaddr as usize;
+                let end = start + phdr.p_memsz as usize;
+                let offset = phdr.p_offset as usize;
+                let from_file = phdr.p_filesz != 0;
+                SegmentInfo {
+                    vaddr_range: start & !(PAGE_SIZE - 1)..end.next_multiple_of(PAGE_SIZE),
+                    prot: segment_prot(phdr.p_flags),
+                    from_file,
+                    file_range: from_file.then(|| offset..offset + phdr.p_filesz as usize),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the address ranges of the segments that are not writable
+    ///
+    /// Adjacent and overlapping ranges are merged, and the result is sorted by
+    /// address. This is what an integrity check would hash; like
+    /// [`segments_layout`](Self::segments_layout) it does not include the
+    /// `PT_GNU_RELRO` segment.
+    pub fn read_only_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self
+            .segments_layout()
+            .into_iter()
+            .filter(|segment| !segment.prot.contains(ProtFlags::PROT_WRITE))
+            .map(|segment| segment.vaddr_range)
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    /// Gets the DT_SONAME value
+    #[inline]
+    pub fn soname(&self) -> Option<&str> {
+        self.core.soname()
+    }
+
+    /// Gets the GNU build-id recorded in the `NT_GNU_BUILD_ID` note
+    #[inline]
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.core.build_id()
+    }
+
+    /// Iterates over the notes of the `PT_NOTE` segments
+    #[inline]
+    pub fn note_iter(&self) -> NoteIter<'_> {
+        self.core.note_iter()
+    }
+
+    /// Returns the raw `(d_tag, d_un)` entries of the dynamic section.
+    #[inline]
+    pub fn dynamic_entries(&self) -> DynamicEntries<'_> {
+        self.core.dynamic_entries()
+    }
+
+    /// Returns the module in the layout of the head of glibc's `struct link_map`
+    ///
+    /// The pointers stay valid for as long as this module is loaded.
+    #[inline]
+    pub fn link_map_view(&self) -> LinkMapView {
+        self.core.link_map_view()
+    }
+
+    /// Checks whether an address falls inside the memory mapped for this ELF object
+    ///
+    /// # Arguments
+    /// * `addr` - The address to check
+    #[inline]
+    pub fn contains_addr(&self, addr: usize) -> bool {
+        let start = self.core.segments().memory.as_ptr() as usize;
+        (start..start + self.mapped_len()).contains(&addr)
+    }
+
+    /// Finds the symbol that contains the given address, similar to `dladdr`
+    ///
+    /// A symbol matches if the address lies within `st_value..st_value + st_size`.
+    /// Only if no sized symbol matches, the nearest preceding symbol with zero
+    /// size is used as a fallback. Undefined, absolute and TLS symbols are
+    /// ignored.
+    ///
+    /// # Arguments
+    /// * `addr` - The address to look up
+    ///
+    /// # Returns
+    /// * `Some((name, offset))` - The symbol name and the offset of `addr` from the symbol start
+    /// * `None` - If the address is outside this object or no symbol covers it
+    pub fn symbol_at(&self, addr: usize) -> Option<(&str, usize)> {
+        if !self.contains_addr(addr) {
+            return None;
+        }
+        let base = self.base();
+        let symtab = self.symtab();
+        // The innermost sized symbol covering the address, and the nearest
+        // preceding one without a size
+        let mut sized: Option<(usize, usize)> = None;
+        let mut label: Option<(usize, usize)> = None;
+        for idx in 0..symtab.count_syms() {
+            let (sym, _) = symtab.symbol_idx(idx);
+            if sym.is_undef()
+                || sym.st_shndx() == SHN_ABS as usize
+                || sym.st_type() == STT_TLS
+                || sym.st_name() == 0
+            {
+                continue;
+            }
+            let start = base.wrapping_add(sym.st_value());
+            let size = sym.st_size();
+            if addr < start || (size != 0 && addr - start >= size) {
+                continue;
+            }
+            let best = if size == 0 { &mut label } else { &mut sized };
+            if best.is_none_or(|(_, best_start)| start > best_start) {
+                *best = Some((idx, start));
+            }
+        }
+        sized.or(label).map(|(idx, start)| {
+            let (_, info) = symtab.symbol_idx(idx);
+            (info.name(), addr - start)
+        })
+    }
+
     /// Creates a [`LoadedCore`] from an [`ElfCore`] and its explicit dependencies.
     ///
     /// # Safety
@@ -143,7 +572,10 @@ impl<D> LoadedCore<D> {
     ) -> Self {
         let segments = ElfSegments::new(memory.0, memory.1, munmap);
         Self {
-            core: unsafe { ElfCore::from_raw(name, base, dynamic_ptr, phdrs, segments, user_data) },
+            core: unsafe {
+                ElfCore::from_raw(name, base, dynamic_ptr, phdrs, segments, user_data, None)
+            }
+            .unwrap(),
             deps: Arc::from([]),
         }
     }
@@ -153,6 +585,16 @@ impl<D> LoadedCore<D> {
         &self.core.symtab()
     }
 
+    /// Iterates over the symbols this module exports
+    ///
+    /// Only defined global and weak symbols with default or protected
+    /// visibility are yielded, which is the set other modules can bind to.
+    pub fn exported_symbols(&self) -> impl Iterator<Item = (&str, &ElfSymbol)> {
+        self.symtab().iter().filter(|(_, sym)| {
+            matches!(sym.st_bind(), STB_GLOBAL | STB_WEAK) && !sym.is_undef() && sym.is_exported()
+        })
+    }
+
     /// Gets a pointer to a function or static variable by symbol name
     ///
     /// The symbol is interpreted as-is; no mangling is done. This means
@@ -188,6 +630,15 @@ impl<D> LoadedCore<D> {
     /// };
     /// ```
     ///
+    /// The returned address is never signed for pointer authentication. With
+    /// the `aarch64-pac` feature, [`Symbol::sign_ia`] signs a function pointer
+    /// for code that authenticates the pointers it calls.
+    ///
+    /// Only symbols other modules can bind to are returned: `STV_HIDDEN` and
+    /// `STV_INTERNAL` definitions are skipped, see [`LoadedCore::get_any`].
+    /// `STV_PROTECTED` definitions are returned and flagged by
+    /// [`Symbol::is_protected`].
+    ///
     /// # Arguments
     /// * `name` - The name of the symbol to look up
     ///
@@ -196,18 +647,151 @@ impl<D> LoadedCore<D> {
     /// * `None` - If the symbol is not found
     #[inline]
     pub unsafe fn get<'lib, T>(&'lib self, name: &str) -> Option<Symbol<'lib, T>> {
+        let syminfo = SymbolInfo::from_str(name, None);
+        let mut precompute = syminfo.precompute();
+        self.symtab()
+            .lookup_exported(&syminfo, &mut precompute)
+            .map(|sym| self.symbol(sym))
+    }
+
+    /// Gets a pointer to a function or static variable by symbol name,
+    /// whatever its visibility
+    ///
+    /// This behaves like [`LoadedCore::get`], but also returns `STV_HIDDEN`
+    /// and `STV_INTERNAL` definitions, which the module did not export. It is
+    /// meant for tooling such as debuggers and test harnesses.
+    ///
+    /// # Safety
+    /// Users of this API must specify the correct type of the function
+    /// or variable loaded.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the symbol to look up
+    ///
+    /// # Returns
+    /// * `Some(symbol)` - If the symbol is found
+    /// * `None` - If the symbol is not found
+    #[inline]
+    pub unsafe fn get_any<'lib, T>(&'lib self, name: &str) -> Option<Symbol<'lib, T>> {
         let syminfo = SymbolInfo::from_str(name, None);
         let mut precompute = syminfo.precompute();
         self.symtab()
             .lookup_filter(&syminfo, &mut precompute)
-            .map(|sym| Symbol {
-                ptr: SymDef {
-                    sym: Some(sym),
-                    lib: unsafe { self.core_ref() },
-                }
-                .convert() as _,
-                pd: PhantomData,
+            .map(|sym| self.symbol(sym))
+    }
+
+    /// Gets a pointer to a function or static variable, refusing modules that are not initialized
+    ///
+    /// This behaves like [`LoadedCore::get`], but only hands out symbols of a
+    /// module whose relocation has finished, including the setup of lazy binding.
+    /// A [`LoadedCore`] built from an unrelocated image with [`LoadedCore::from_core`],
+    /// or one whose finalizers already ran, is rejected.
+    ///
+    /// # Safety
+    /// Users of this API must specify the correct type of the function
+    /// or variable loaded.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the symbol to look up
+    ///
+    /// # Returns
+    /// * `Ok(symbol)` - If the module is initialized and defines the symbol
+    /// * `Err(Error::NotInitialized)` - If the module is not initialized
+    /// * `Err(Error::SymbolNotFound)` - If the symbol is not found
+    pub unsafe fn get_checked<'lib, T>(&'lib self, name: &str) -> Result<Symbol<'lib, T>> {
+        if !self.is_initialized() {
+            return Err(Error::NotInitialized {
+                module: self.name().into(),
+            });
+        }
+        unsafe { self.get(name) }.ok_or_else(|| Error::SymbolNotFound {
+            module: self.name().into(),
+            symbol: name.into(),
+        })
+    }
+
+    /// Whether the module is initialized
+    ///
+    /// A module becomes initialized when its relocation has finished, right
+    /// before its initializers run, and stops being initialized when its
+    /// finalizers run.
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.core.is_initialized()
+    }
+
+    /// Gets a pointer to a symbol whose hashes were computed ahead of time
+    ///
+    /// This behaves like [`LoadedCore::get`], but the same [`PreparedSymbol`]
+    /// can be looked up in many modules without hashing its name again.
+    ///
+    /// # Safety
+    /// Users of this API must specify the correct type of the function
+    /// or variable loaded.
+    ///
+    /// # Arguments
+    /// * `symbol` - The prepared symbol to look up
+    ///
+    /// # Returns
+    /// * `Some(symbol)` - If the symbol is found
+    /// * `None` - If the symbol is not found
+    #[inline]
+    pub unsafe fn lookup_prepared<'lib, T>(
+        &'lib self,
+        symbol: &PreparedSymbol,
+    ) -> Option<Symbol<'lib, T>> {
+        let (syminfo, mut precompute) = symbol.parts();
+        self.symtab()
+            .lookup_exported(syminfo, &mut precompute)
+            .map(|sym| self.symbol(sym))
+    }
+
+    /// Looks up many symbols at once
+    ///
+    /// All names are hashed first and then looked up in bucket order, so
+    /// neighbouring lookups touch neighbouring parts of the hash table.
+    ///
+    /// # Arguments
+    /// * `names` - The names of the symbols to look up
+    ///
+    /// # Returns
+    /// The address of each symbol, in the order of `names`, or `None` for
+    /// the ones that are not found.
+    pub fn lookup_batch(&self, names: &[&str]) -> Vec<Option<*const ()>> {
+        let hashtab = &self.symtab().hashtab;
+        let mut pending: Vec<(usize, usize, SymbolInfo, PreCompute)> = names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let syminfo = SymbolInfo::from_str(name, None);
+                let mut precompute = syminfo.precompute();
+                let bucket = hashtab.bucket(&syminfo, &mut precompute);
+                (bucket, idx, syminfo, precompute)
             })
+            .collect();
+        pending.sort_unstable_by_key(|&(bucket, idx, ..)| (bucket, idx));
+
+        let mut addrs = alloc::vec![None; names.len()];
+        for (_, idx, syminfo, mut precompute) in pending {
+            addrs[idx] = self
+                .symtab()
+                .lookup_exported(&syminfo, &mut precompute)
+                .map(|sym| self.symbol::<()>(sym).into_raw());
+        }
+        addrs
+    }
+
+    /// Wraps a definition of this module into a [`Symbol`]
+    fn symbol<T>(&self, sym: &ElfSymbol) -> Symbol<'_, T> {
+        Symbol {
+            ptr: SymDef {
+                sym: Some(sym),
+                lib: unsafe { self.core_ref() },
+            }
+            .convert() as _,
+            protected: sym.is_protected(),
+            pd: PhantomData,
+        }
     }
 
     /// Load a versioned symbol from the ELF object
@@ -218,7 +802,7 @@ impl<D> LoadedCore<D> {
     ///
     /// # Examples
     /// ```no_run
-    /// # use elf_loader::{ElfFile, Symbol, mmap::DefaultMmap, Loader};
+    /// # use elf_loader::{Loader, input::ElfFile};
     /// # let mut loader = Loader::new();
     /// # let lib = loader
     /// #     .load_dylib(ElfFile::from_path("target/liba.so").unwrap())
@@ -243,54 +827,140 @@ impl<D> LoadedCore<D> {
         let syminfo = SymbolInfo::from_str(name, Some(version));
         let mut precompute = syminfo.precompute();
         self.symtab()
-            .lookup_filter(&syminfo, &mut precompute)
-            .map(|sym| Symbol {
-                ptr: SymDef {
-                    sym: Some(sym),
-                    lib: unsafe { self.core_ref() },
-                }
-                .convert() as _,
-                pd: PhantomData,
-            })
+            .lookup_exported(&syminfo, &mut precompute)
+            .map(|sym| self.symbol(sym))
+    }
+}
+
+impl LoadedCore<Extensions> {
+    /// Gets the extension of type `T` attached to the ELF object.
+    ///
+    /// This is a shorthand for `user_data().get::<T>()`.
+    #[inline]
+    pub fn extension<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.user_data().get::<T>()
     }
 }
 
 /// Inner structure for ElfCore
+///
+/// `user_data` is the last field of a `repr(C)` struct so that the offsets of
+/// the other fields do not depend on `D`: lazy binding only receives the
+/// address of the structure and reads it as a `CoreInner<()>`.
+#[repr(C)]
 pub(crate) struct CoreInner<D = ()> {
     /// Indicates whether the component has been initialized
     pub(crate) is_init: AtomicBool,
 
-    /// File short name of the ELF object
-    pub(crate) name: String,
+    /// File short name of the ELF object, NUL-terminated so that it can be
+    /// handed out through [`LinkMapView`]
+    pub(crate) name: NulStr,
 
     /// ELF symbols table
     pub(crate) symtab: SymbolTable,
 
     /// Finalization function
-    pub(crate) fini: Option<fn()>,
+    pub(crate) fini: Option<InitFn>,
 
     /// Finalization array of functions
-    pub(crate) fini_array: Option<&'static [fn()]>,
+    pub(crate) fini_array: Option<&'static [InitFn]>,
 
     /// Custom finalization handler
     pub(crate) fini_handler: FnHandler,
 
-    /// User-defined data
-    pub(crate) user_data: D,
+    /// Hook called once the finalization functions ran
+    pub(crate) post_fini: Mutex<Option<Arc<PostFini>>>,
 
     /// Dynamic information
     pub(crate) dynamic_info: Option<Arc<DynamicInfo>>,
 
+    /// TLS module state, released before the segments holding the TLS template
+    pub(crate) tls: Option<TlsModule>,
+
     /// Memory segments
     pub(crate) segments: ElfSegments,
+
+    /// Whether the component is in the process-wide registry
+    pub(crate) registered: AtomicBool,
+
+    /// Whether the component is in the global scope
+    pub(crate) global: AtomicBool,
+
+    /// Whether lazy binding of the component ignores the global scope,
+    /// because it belongs to a [`Namespace`](crate::image::Namespace)
+    pub(crate) isolated: AtomicBool,
+
+    /// Whether pointer authentication codes are stripped from the addresses
+    /// bound by and to the component
+    #[cfg(feature = "aarch64-pac")]
+    pub(crate) pac: AtomicBool,
+
+    /// User-defined data
+    pub(crate) user_data: D,
+}
+
+impl<D> CoreInner<D> {
+    /// Marks the component as registered
+    #[inline]
+    pub(crate) fn set_registered(&self) {
+        self.registered.store(true, Ordering::Relaxed);
+    }
+
+    /// Marks the component as part of the global scope
+    #[inline]
+    pub(crate) fn set_global(&self) {
+        self.global.store(true, Ordering::Relaxed);
+    }
+
+    /// Keeps lazy binding of the component out of the global scope
+    #[inline]
+    pub(crate) fn set_isolated(&self) {
+        self.isolated.store(true, Ordering::Relaxed);
+    }
+
+    /// Looks a symbol up in the global scope, unless the component is isolated
+    #[inline]
+    pub(crate) fn global_lookup(&self, name: &str) -> Option<*const ()> {
+        if self.isolated.load(Ordering::Relaxed) {
+            return None;
+        }
+        relocation::global_lookup(name)
+    }
+
+    /// Leaves the registry and the global scope, then runs the finalization
+    /// functions
+    fn release(&self) {
+        self.release_as(self as *const Self as usize);
+    }
+
+    /// Same as [`release`](Self::release), for a component registered as `id`
+    fn release_as(&self, id: usize) {
+        // Leave the registry before anything the entry points to is released
+        if self.registered.swap(false, Ordering::Relaxed) {
+            registry::unregister(id);
+        }
+        if self.global.swap(false, Ordering::Relaxed) {
+            relocation::unregister_global(id);
+        }
+        self.run_fini();
+    }
+
+    /// Runs the finalization functions if the component was initialized and
+    /// they have not run yet
+    pub(crate) fn run_fini(&self) {
+        if self.is_init.swap(false, Ordering::AcqRel) {
+            (self.fini_handler)(self.fini, self.fini_array);
+            if let Some(post_fini) = self.post_fini.lock().take() {
+                post_fini(&self.name);
+            }
+        }
+    }
 }
 
 impl<D> Drop for CoreInner<D> {
     /// Executes finalization functions when the component is dropped
     fn drop(&mut self) {
-        if self.is_init.load(Ordering::Relaxed) {
-            (self.fini_handler)(self.fini, self.fini_array);
-        }
+        self.release();
     }
 }
 
@@ -336,16 +1006,29 @@ impl<D> Clone for ElfCore<D> {
     }
 }
 
-// Safety: ModuleInner can be shared between threads
-unsafe impl<D> Sync for CoreInner<D> {}
-// Safety: ModuleInner can be sent between threads
-unsafe impl<D> Send for CoreInner<D> {}
+// Safety: the raw pointers point into the mapped image, which is not written
+// once relocated except for GOT entries, which are updated atomically. The
+// state that changes after relocation is held in atomics and locks, and the
+// handlers are `Send + Sync`. The user data is handed out by shared reference
+// and dropped with the last reference, on any thread.
+unsafe impl<D: Sync> Sync for CoreInner<D> {}
+// Safety: see above
+unsafe impl<D: Send> Send for CoreInner<D> {}
 
 impl<D> ElfCore<D> {
     /// Marks the component as initialized
+    ///
+    /// This publishes all writes made while relocating, so it must only be
+    /// called once the relocation tables and the lazy binding slots are set up.
     #[inline]
     pub(crate) fn set_init(&self) {
-        self.inner.is_init.store(true, Ordering::Relaxed);
+        self.inner.is_init.store(true, Ordering::Release);
+    }
+
+    /// Whether the component has finished relocation and was not finalized since
+    #[inline]
+    pub(crate) fn is_initialized(&self) -> bool {
+        self.inner.is_init.load(Ordering::Acquire)
     }
 
     /// Creates a weak reference to this ELF core.
@@ -376,6 +1059,79 @@ impl<D> ElfCore<D> {
         Arc::get_mut(&mut self.inner).map(|inner| &mut inner.user_data)
     }
 
+    /// Gets the DT_FLAGS_1 value
+    ///
+    /// # Returns
+    /// The flags, or `0` if the object has no dynamic section
+    #[inline]
+    pub fn flags_1(&self) -> usize {
+        self.inner
+            .dynamic_info
+            .as_ref()
+            .map_or(0, |info| info.flags_1)
+    }
+
+    /// Whether the ELF object is marked with DF_1_NODELETE
+    #[inline]
+    pub fn is_nodelete(&self) -> bool {
+        self.flags_1() & DF_1_NODELETE as usize != 0
+    }
+
+    /// Whether the ELF object was linked with `-Bsymbolic`
+    #[inline]
+    pub fn is_symbolic(&self) -> bool {
+        self.inner
+            .dynamic_info
+            .as_ref()
+            .is_some_and(|info| info.symbolic)
+    }
+
+    /// Whether the ELF object is the main program
+    #[inline]
+    pub fn is_main_program(&self) -> bool {
+        self.inner
+            .dynamic_info
+            .as_ref()
+            .is_some_and(|info| info.main_program.load(Ordering::Relaxed))
+    }
+
+    /// Marks the ELF object as the main program
+    pub(crate) fn set_main_program(&self) {
+        if let Some(info) = &self.inner.dynamic_info {
+            info.main_program.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a variable the main program copied with `R_*_COPY`
+    pub(crate) fn record_copy(&self, copy: CopyRecord) {
+        if let Some(info) = &self.inner.dynamic_info {
+            let name = self.symtab().symbol_idx(copy.sym).1.name();
+            info.copy_index
+                .write()
+                .entry(String::from(name))
+                .or_insert(copy.addr);
+            info.copies.write().push(copy);
+        }
+    }
+
+    /// Returns the variables the main program copied with `R_*_COPY`
+    pub(crate) fn copies(&self) -> Vec<CopyRecord> {
+        self.inner
+            .dynamic_info
+            .as_ref()
+            .map_or_else(Vec::new, |info| info.copies.read().clone())
+    }
+
+    /// Returns the address of the copy of the variable `name` if the ELF
+    /// object is the main program and copied it
+    pub(crate) fn copied(&self, name: &str) -> Option<usize> {
+        let info = self.inner.dynamic_info.as_ref()?;
+        if !info.main_program.load(Ordering::Relaxed) {
+            return None;
+        }
+        info.copy_index.read().get(name).copied()
+    }
+
     /// Gets the number of strong references to the ELF object
     #[inline]
     pub fn strong_count(&self) -> usize {
@@ -394,6 +1150,61 @@ impl<D> ElfCore<D> {
         &self.inner.name
     }
 
+    /// Gets the DT_SONAME value
+    ///
+    /// # Returns
+    /// The shared object name, or `None` if the object does not declare one
+    #[inline]
+    pub fn soname(&self) -> Option<&str> {
+        self.inner.dynamic_info.as_ref()?.soname
+    }
+
+    /// Gets the GNU build-id recorded in the `NT_GNU_BUILD_ID` note
+    ///
+    /// # Returns
+    /// The descriptor of the note, or `None` if the object has no build-id
+    #[inline]
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.inner.dynamic_info.as_ref()?.notes.build_id()
+    }
+
+    /// Iterates over the notes of the `PT_NOTE` segments
+    ///
+    /// Notes that are only present in the file were copied during load, so all
+    /// notes stay available for as long as the object is alive.
+    #[inline]
+    pub fn note_iter(&self) -> NoteIter<'_> {
+        match &self.inner.dynamic_info {
+            Some(info) => info.notes.iter(),
+            None => NoteIter::from_bytes(&[], 4),
+        }
+    }
+
+    /// Returns the module in the layout of the head of glibc's `struct link_map`
+    ///
+    /// The pointers stay valid for as long as this module is alive.
+    #[inline]
+    pub fn link_map_view(&self) -> LinkMapView {
+        LinkMapView {
+            l_addr: self.base(),
+            l_name: self.inner.name.as_c_str().as_ptr(),
+            l_ld: self
+                .dynamic_ptr()
+                .map_or(null(), |ptr| ptr.as_ptr().cast_const()),
+        }
+    }
+
+    /// Returns the raw `(d_tag, d_un)` entries of the dynamic section.
+    ///
+    /// The iterator is empty if the object has no dynamic section.
+    #[inline]
+    pub fn dynamic_entries(&self) -> DynamicEntries<'_> {
+        let ptr = self
+            .dynamic_ptr()
+            .map_or(null(), |ptr| ptr.as_ptr().cast_const());
+        unsafe { DynamicEntries::new(ptr) }
+    }
+
     /// Gets the base address of the ELF object
     #[inline]
     pub fn base(&self) -> usize {
@@ -406,6 +1217,25 @@ impl<D> ElfCore<D> {
         self.inner.segments.len()
     }
 
+    /// Gets the alignment the base address was loaded at
+    ///
+    /// This honors the largest `p_align` of the `PT_LOAD` segments, so it can
+    /// exceed the page size for libraries linked with a larger max-page-size.
+    #[inline]
+    pub fn load_align(&self) -> usize {
+        self.inner.segments.align()
+    }
+
+    /// Gets the number of reads issued to the reader to copy the segment contents
+    ///
+    /// Segments mapped from the file take no reads, and neither do scattered
+    /// contents copied out of a temporary mapping of it. The headers are not
+    /// counted.
+    #[inline]
+    pub fn segment_reads(&self) -> usize {
+        self.inner.segments.reads()
+    }
+
     /// Gets the symbol table
     #[inline]
     pub fn symtab(&self) -> &SymbolTable {
@@ -421,41 +1251,105 @@ impl<D> ElfCore<D> {
             .map(|info| info.dynamic_ptr)
     }
 
+    /// Gets the TLS module id assigned by the [`TlsAllocator`](crate::tls::TlsAllocator)
+    ///
+    /// # Returns
+    /// The module id, or `None` if the object has no `PT_TLS` segment or no
+    /// allocator was set on the loader
+    #[inline]
+    pub fn tls_mod_id(&self) -> Option<usize> {
+        self.inner.tls.as_ref()?.mod_id()
+    }
+
+    /// Gets the TLS module state
+    #[inline]
+    pub(crate) fn tls(&self) -> Option<&TlsModule> {
+        self.inner.tls.as_ref()
+    }
+
+    /// Returns the address range of the `PT_GNU_EH_FRAME` segment, if present.
+    ///
+    /// This is the `.eh_frame_hdr` section that unwinders use to locate the
+    /// unwind information of the object.
+    #[inline]
+    pub fn eh_frame_hdr(&self) -> Option<Range<usize>> {
+        registry::eh_frame_hdr(self.base(), self.phdrs()?)
+    }
+
     /// Gets the segments
     #[inline]
     pub(crate) fn segments(&self) -> &ElfSegments {
         &self.inner.segments
     }
 
+    /// Gets the address of the shared inner data, which identifies the object
+    #[inline]
+    pub(crate) fn inner_addr(&self) -> usize {
+        Arc::as_ptr(&self.inner) as usize
+    }
+
     /// Creates an ElfCore from raw components
-    unsafe fn from_raw(
+    ///
+    /// The image is used as it is: nothing is mapped, relocated or initialized.
+    /// With a `fini_handler`, the finalization functions of the image are
+    /// passed to it when the object is dropped.
+    pub(crate) unsafe fn from_raw(
         name: String,
         base: usize,
         dynamic_ptr: *const Dyn,
         phdrs: &'static [ElfPhdr],
         mut segments: ElfSegments,
         user_data: D,
-    ) -> Self {
+        fini_handler: Option<FnHandler>,
+    ) -> Result<Self> {
         segments.offset = (segments.memory.as_ptr() as usize).wrapping_sub(base);
-        let dynamic = ElfDynamic::new(dynamic_ptr, &segments).unwrap();
-        Self {
+        let dynamic = ElfDynamic::new(dynamic_ptr, &segments, UnknownDynamicPolicy::Ignore)?;
+        let (fini, fini_array, fini_handler): (_, _, FnHandler) = match fini_handler {
+            Some(handler) => (dynamic.fini_fn, dynamic.fini_array_fn, handler),
+            None => (None, None, Arc::new(|_, _| {})),
+        };
+        let symtab = SymbolTable::from_dynamic(&dynamic);
+        let soname = dynamic
+            .soname_off
+            .map(|soname_off| symtab.strtab().get_str(soname_off.get()));
+        Ok(Self {
             inner: Arc::new(CoreInner {
-                name,
+                name: NulStr::new(&name, &LoaderAlloc::default()),
                 is_init: AtomicBool::new(true),
-                symtab: SymbolTable::from_dynamic(&dynamic),
+                symtab,
                 dynamic_info: Some(Arc::new(DynamicInfo {
                     dynamic_ptr: NonNull::new(dynamic.dyn_ptr as _).unwrap(),
-                    pltrel: None,
+                    pltrel: &[],
+                    alt_pltrel: &[],
+                    got_plt: dynamic.got_plt,
+                    relro: None,
+                    relro_protected: Mutex::new(false),
                     phdrs: ElfPhdrs::Mmap(phdrs),
-                    lazy_scope: None,
+                    flags_1: dynamic.flags_1,
+                    symbolic: dynamic.symbolic,
+                    lazy_binding: RwLock::new(None),
+                    lazy_fallback: RwLock::new(None),
+                    constructed: AtomicBool::new(false),
+                    main_program: AtomicBool::new(false),
+                    copies: RwLock::new(Vec::new()),
+                    copy_index: RwLock::new(HashMap::new()),
+                    soname,
+                    notes: ElfNotes::mapped(phdrs, &segments),
                 })),
+                tls: None,
                 segments,
-                fini: None,
-                fini_array: None,
-                fini_handler: Arc::new(|_, _| {}),
+                fini,
+                fini_array,
+                fini_handler,
+                post_fini: Mutex::new(None),
+                registered: AtomicBool::new(false),
+                global: AtomicBool::new(false),
+                isolated: AtomicBool::new(false),
+                #[cfg(feature = "aarch64-pac")]
+                pac: AtomicBool::new(false),
                 user_data,
             }),
-        }
+        })
     }
 }
 
@@ -1,21 +1,29 @@
 use crate::{
     LoadHook, Result,
-    elf::{Dyn, ElfPhdr, ElfRelType},
-    elf::{ElfDynamic, ElfPhdrs, SymbolTable},
-    image::{ElfCore, ImageBuilder, common::CoreInner},
+    allocator::{AllocBox, LoaderAlloc, NulStr},
+    elf::{Dyn, DynamicEntries, ElfAltRelType, ElfPhdr, ElfRelType, GnuProperties},
+    elf::{ElfDynamic, ElfNotes, ElfPhdrs, NoteIter, SymbolTable},
+    elf::{RelEntry, RelFields},
+    image::{ElfCore, ImageBuilder, LoadedCore, common::CoreInner},
     loader::FnHandler,
-    os::Mmap,
-    relocation::{DynamicRelocation, SymbolLookup},
-    segment::{ELFRelro, ElfSegments},
+    os::{Mmap, ProtFlags},
+    parse_dynamic_error,
+    relocation::{
+        Auditor, DynamicRelocation, LazyAudit, LazyBinding, PostFini, PreInit, SymbolLookup,
+    },
+    segment::{ELFRelro, ElfSegments, TextSegments, program::ProgramSegments},
+    tls::{TlsAllocator, TlsInfo, TlsModule},
 };
 use alloc::{boxed::Box, string::String, vec::Vec};
 use core::{
     cell::Cell,
     ffi::CStr,
     ops::{Deref, DerefMut},
-    ptr::{NonNull, null},
-    sync::atomic::AtomicBool,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
 };
+use hashbrown::HashMap;
+use spin::{Mutex, RwLock};
 
 #[cfg(not(feature = "portable-atomic"))]
 use alloc::sync::Arc;
@@ -24,11 +32,107 @@ use portable_atomic_util::Arc;
 
 pub(crate) struct DynamicInfo {
     pub(crate) dynamic_ptr: NonNull<Dyn>,
-    pub(crate) pltrel: Option<NonNull<ElfRelType>>,
+    pub(crate) pltrel: &'static [ElfRelType],
+    /// PLT relocations stored in the non-native entry format
+    pub(crate) alt_pltrel: &'static [ElfAltRelType],
+    /// Start of the GOT used by the PLT (`DT_PLTGOT`)
+    pub(crate) got_plt: Option<NonNull<usize>>,
+    /// GNU_RELRO segment information
+    pub(crate) relro: Option<ELFRelro>,
+    /// Whether the RELRO protection is in effect. The lock is held for any change
+    /// of the protection, so that concurrent writers cannot restore it early.
+    pub(crate) relro_protected: Mutex<bool>,
     pub(crate) phdrs: ElfPhdrs,
-    /// Lazy binding scope for symbol resolution during lazy binding
-    /// Stored as trait object for type erasure of different SymbolLookup implementations
-    pub(crate) lazy_scope: Option<Arc<dyn SymbolLookup>>,
+    /// Value of `DT_FLAGS_1`
+    pub(crate) flags_1: usize,
+    /// Whether the object's own definitions take precedence during symbol lookup
+    pub(crate) symbolic: bool,
+    /// Value of `DT_SONAME`
+    pub(crate) soname: Option<&'static str>,
+    /// Notes of the `PT_NOTE` segments
+    pub(crate) notes: ElfNotes,
+    /// Lazy binding state, published once the PLT relocations are prepared
+    pub(crate) lazy_binding: RwLock<Option<Arc<LazyBinding>>>,
+    /// Lookup consulted during lazy binding when `lazy_scope` has no definition
+    pub(crate) lazy_fallback: RwLock<Option<Arc<dyn SymbolLookup + Send + Sync>>>,
+    /// Whether the initialization functions of the object ran
+    pub(crate) constructed: AtomicBool,
+    /// Whether the object is the main program, whose copies of variables are canonical
+    pub(crate) main_program: AtomicBool,
+    /// Variables the main program copied from other objects with `R_*_COPY`
+    pub(crate) copies: RwLock<Vec<CopyRecord>>,
+    /// Addresses of the copies in `copies` by symbol name
+    pub(crate) copy_index: RwLock<HashMap<String, usize>>,
+}
+
+/// A variable the main program copied from another object with `R_*_COPY`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CopyRecord {
+    /// Index of the symbol in the dynamic symbol table of the main program
+    pub(crate) sym: usize,
+    /// Address of the copy
+    pub(crate) addr: usize,
+    /// Address of the definition that was copied
+    pub(crate) def: usize,
+    /// Number of bytes copied
+    pub(crate) len: usize,
+}
+
+impl DynamicInfo {
+    /// Number of PLT relocations, in whichever format the table is stored
+    #[inline]
+    pub(crate) fn pltrel_len(&self) -> usize {
+        self.pltrel.len() + self.alt_pltrel.len()
+    }
+
+    /// Returns the PLT relocation at `idx`, in whichever format the table is stored
+    #[inline]
+    pub(crate) fn pltrel_entry(&self, idx: usize, base: usize) -> Option<RelFields> {
+        match self.pltrel.get(idx) {
+            Some(rel) => Some(rel.fields(base)),
+            None => self
+                .alt_pltrel
+                .get(idx - self.pltrel.len())
+                .map(|rel| rel.fields(base)),
+        }
+    }
+
+    /// Makes the RELRO segment read-only unless it already is
+    pub(crate) fn protect_relro(&self) -> Result<()> {
+        let mut protected = self.relro_protected.lock();
+        if let Some(relro) = &self.relro
+            && !*protected
+        {
+            relro.relro()?;
+            *protected = true;
+        }
+        Ok(())
+    }
+
+    /// Runs `f` with the RELRO segment writable, restoring the protection afterwards
+    ///
+    /// The protection is restored even if `f` panics.
+    pub(crate) fn with_relro_writable<R>(&self, f: impl FnOnce() -> R) -> Result<R> {
+        struct Guard<'a>(&'a ELFRelro);
+
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                let _ = self.0.relro();
+            }
+        }
+
+        let protected = self.relro_protected.lock();
+        let Some(relro) = self.relro.as_ref().filter(|_| *protected) else {
+            return Ok(f());
+        };
+        relro.unprotect()?;
+        let guard = Guard(relro);
+        let result = f();
+        // Report a failure to restore the protection instead of ignoring it in the guard
+        core::mem::forget(guard);
+        relro.relro()?;
+        Ok(result)
+    }
 }
 
 /// Extra data associated with ELF objects during relocation
@@ -45,8 +149,8 @@ struct ElfExtraData {
     /// Dynamic relocation information (rela.dyn and rela.plt)
     relocation: DynamicRelocation,
 
-    /// GNU_RELRO segment information for memory protection
-    relro: Option<ELFRelro>,
+    /// Segments made writable during relocation, for objects with text relocations
+    text: Option<TextSegments>,
 
     /// Initialization function to be called after relocation
     init: Box<dyn Fn()>,
@@ -58,7 +162,7 @@ struct ElfExtraData {
     runpath: Option<&'static str>,
 
     /// List of needed library names from the dynamic section
-    needed_libs: Box<[&'static str]>,
+    needed_libs: AllocBox<[&'static str]>,
 }
 
 /// Data structure used during lazy parsing of ELF objects
@@ -88,14 +192,17 @@ enum State<D> {
         /// Initialization function handler
         init_handler: FnHandler,
 
+        /// Whether DT_PREINIT_ARRAY is run before the initialization functions
+        preinit: bool,
+
         /// Finalization function handler
         fini_handler: FnHandler,
 
         /// Name of the ELF file
-        name: String,
+        name: NulStr,
 
-        /// Pointer to the dynamic section
-        dynamic_ptr: NonNull<Dyn>,
+        /// The parsed dynamic section
+        dynamic: AllocBox<ElfDynamic>,
 
         /// Memory segments
         segments: ElfSegments,
@@ -103,8 +210,20 @@ enum State<D> {
         /// GNU_RELRO segment information
         relro: Option<ELFRelro>,
 
+        /// Non-writable segments, if the object has text relocations
+        text: Option<TextSegments>,
+
+        /// Notes of the PT_NOTE segments
+        notes: ElfNotes,
+
+        /// TLS template and the allocator it is registered with
+        tls: Option<(Arc<dyn TlsAllocator>, Option<TlsInfo>)>,
+
         /// User-defined data
         user_data: D,
+
+        /// Allocator of the per-load data
+        alloc: LoaderAlloc,
     },
 
     /// Initialized state with all data ready for relocation
@@ -123,33 +242,45 @@ impl<D> State<D> {
         let lazy_data = match self {
             State::Uninit {
                 name,
-                dynamic_ptr,
+                dynamic,
                 segments,
                 relro,
+                text,
+                notes,
                 user_data,
                 init_handler,
+                preinit,
                 fini_handler,
                 phdrs,
+                tls,
+                alloc,
             } => {
-                // If we have a dynamic section, parse it and prepare relocation data
-
-                let dynamic = ElfDynamic::new(dynamic_ptr.as_ptr(), &segments).unwrap();
+                // Prepare relocation data from the dynamic section
+                let dynamic = *dynamic;
                 let relocation = DynamicRelocation::new(
                     dynamic.pltrel,
                     dynamic.dynrel,
+                    dynamic.alt_pltrel,
+                    dynamic.alt_dynrel,
                     dynamic.relr,
                     dynamic.rel_count,
+                    &segments,
                 );
 
                 // Create symbol table from dynamic section
                 let symtab = SymbolTable::from_dynamic(&dynamic);
 
                 // Collect needed library names
-                let needed_libs: Vec<&'static str> = dynamic
-                    .needed_libs
-                    .iter()
-                    .map(|needed_lib| symtab.strtab().get_str(needed_lib.get()))
-                    .collect();
+                let needed_libs = alloc.collect(
+                    dynamic
+                        .needed_libs
+                        .iter()
+                        .map(|needed_lib| symtab.strtab().get_str(needed_lib.get())),
+                );
+
+                let soname = dynamic
+                    .soname_off
+                    .map(|soname_off| symtab.strtab().get_str(soname_off.get()));
 
                 // Create the lazy data structure
                 LazyData {
@@ -157,14 +288,16 @@ impl<D> State<D> {
                         // Determine if lazy binding should be enabled
                         lazy: !dynamic.bind_now,
 
-                        // Store GNU_RELRO segment information
-                        relro,
-
                         // Store relocation information
                         relocation,
 
+                        text,
+
                         // Create initialization function
                         init: Box::new(move || {
+                            if preinit && dynamic.preinit_array_fn.is_some() {
+                                init_handler(None, dynamic.preinit_array_fn);
+                            }
                             init_handler(dynamic.init_fn, dynamic.init_array_fn)
                         }),
 
@@ -192,16 +325,35 @@ impl<D> State<D> {
                             fini: dynamic.fini_fn,
                             fini_array: dynamic.fini_array_fn,
                             fini_handler,
+                            post_fini: Mutex::new(None),
+                            tls: tls
+                                .map(|(allocator, info)| TlsModule::new(allocator, info.as_ref())),
                             segments,
                             user_data,
                             dynamic_info: Some(Arc::new(DynamicInfo {
                                 dynamic_ptr: NonNull::new(dynamic.dyn_ptr as _).unwrap(),
-                                pltrel: NonNull::new(
-                                    dynamic.pltrel.map_or(null(), |plt| plt.as_ptr()) as _,
-                                ),
+                                pltrel: dynamic.pltrel.unwrap_or(&[]),
+                                alt_pltrel: dynamic.alt_pltrel.unwrap_or(&[]),
+                                got_plt: dynamic.got_plt,
+                                relro,
+                                relro_protected: Mutex::new(false),
                                 phdrs,
-                                lazy_scope: None,
+                                flags_1: dynamic.flags_1,
+                                symbolic: dynamic.symbolic,
+                                lazy_binding: RwLock::new(None),
+                                lazy_fallback: RwLock::new(None),
+                                constructed: AtomicBool::new(false),
+                                main_program: AtomicBool::new(false),
+                                copies: RwLock::new(Vec::new()),
+                                copy_index: RwLock::new(HashMap::new()),
+                                soname,
+                                notes,
                             })),
+                            registered: AtomicBool::new(false),
+                            global: AtomicBool::new(false),
+                            isolated: AtomicBool::new(false),
+                            #[cfg(feature = "aarch64-pac")]
+                            pac: AtomicBool::new(false),
                         }),
                     },
                 }
@@ -309,10 +461,31 @@ where
     entry: usize,
     /// PT_INTERP segment value (interpreter path).
     interp: Option<&'static str>,
-    /// Name of the ELF file.
-    name: &'static str,
+    /// Stack protection requested by the PT_GNU_STACK segment.
+    stack_flags: Option<ProtFlags>,
+    /// Features recorded in the PT_GNU_PROPERTY segment.
+    gnu_properties: Option<GnuProperties>,
+    /// OS/ABI identification of the ELF header (`EI_OSABI`).
+    osabi: u8,
+    /// ABI version of the ELF header (`EI_ABIVERSION`).
+    abi_version: u8,
+    /// OS- and processor-specific dynamic entries the loader does not interpret.
+    vendor_dynamic: Vec<(i64, u64)>,
+    /// Name of the ELF file, a copy of the one the module keeps once parsed.
+    name: NulStr,
+    /// Path the ELF file was read from.
+    path: NulStr,
     /// Program headers.
     phdrs: ElfPhdrs,
+    /// Whether to add the object to the process-wide registry once relocated.
+    register: bool,
+    /// Whether to add the object to the global scope once relocated.
+    global: bool,
+    /// Whether to strip pointer authentication codes from bound addresses.
+    #[cfg(feature = "aarch64-pac")]
+    pac: bool,
+    /// Auditor notified of the symbol bindings of the object.
+    auditor: Option<Arc<dyn Auditor<D>>>,
     /// Data parsed lazily.
     data: LazyParse<D>,
 }
@@ -348,6 +521,27 @@ impl<D> DynamicImage<D> {
         self.data.extra.lazy
     }
 
+    /// Gets the DT_FLAGS_1 value
+    #[inline]
+    pub fn flags_1(&self) -> usize {
+        self.core_ref().flags_1()
+    }
+
+    /// Whether the ELF object is marked with DF_1_NODELETE
+    #[inline]
+    pub fn is_nodelete(&self) -> bool {
+        self.core_ref().is_nodelete()
+    }
+
+    /// Whether the ELF object was linked with `-Bsymbolic`
+    ///
+    /// Its own definitions are then preferred over `pre_find` and the scope when
+    /// resolving the symbols of its relocations.
+    #[inline]
+    pub fn is_symbolic(&self) -> bool {
+        self.core_ref().is_symbolic()
+    }
+
     /// Gets the DT_RPATH value
     ///
     /// # Returns
@@ -366,6 +560,39 @@ impl<D> DynamicImage<D> {
         self.data.extra.runpath
     }
 
+    /// Gets the DT_SONAME value
+    ///
+    /// # Returns
+    /// An optional string slice containing the shared object name
+    #[inline]
+    pub fn soname(&self) -> Option<&str> {
+        self.core_ref().soname()
+    }
+
+    /// Gets the GNU build-id recorded in the `NT_GNU_BUILD_ID` note
+    #[inline]
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.core_ref().build_id()
+    }
+
+    /// Iterates over the notes of the `PT_NOTE` segments
+    #[inline]
+    pub fn note_iter(&self) -> NoteIter<'_> {
+        self.core_ref().note_iter()
+    }
+
+    /// Returns the raw `(d_tag, d_un)` entries of the dynamic section.
+    #[inline]
+    pub fn dynamic_entries(&self) -> DynamicEntries<'_> {
+        self.core_ref().dynamic_entries()
+    }
+
+    /// Gets the OS- and processor-specific dynamic entries the loader does not interpret
+    #[inline]
+    pub fn vendor_dynamic_entries(&self) -> &[(i64, u64)] {
+        &self.vendor_dynamic
+    }
+
     /// Gets the PT_INTERP value
     ///
     /// # Returns
@@ -375,12 +602,42 @@ impl<D> DynamicImage<D> {
         self.interp
     }
 
+    /// Gets the stack protection requested by the PT_GNU_STACK segment
+    #[inline]
+    pub fn stack_flags(&self) -> Option<ProtFlags> {
+        self.stack_flags
+    }
+
+    /// Gets the OS/ABI identification of the ELF header
+    #[inline]
+    pub fn osabi(&self) -> u8 {
+        self.osabi
+    }
+
+    /// Gets the ABI version of the ELF header
+    #[inline]
+    pub fn abi_version(&self) -> u8 {
+        self.abi_version
+    }
+
+    /// Gets the features recorded in the PT_GNU_PROPERTY segment
+    #[inline]
+    pub fn gnu_properties(&self) -> Option<GnuProperties> {
+        self.gnu_properties
+    }
+
     /// Gets the name of the ELF object
     #[inline]
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Gets the path the ELF object was read from
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
     /// Gets the program headers of the ELF object
     pub fn phdrs(&self) -> &[ElfPhdr] {
         match &self.phdrs {
@@ -389,6 +646,65 @@ impl<D> DynamicImage<D> {
         }
     }
 
+    /// Sets whether the object is added to the process-wide registry once relocated
+    #[inline]
+    pub(crate) fn set_register(&mut self, register: bool) {
+        self.register = register;
+    }
+
+    /// Runs DT_PREINIT_ARRAY before the initialization functions, which is only
+    /// done for executables
+    pub(crate) fn enable_preinit(&mut self) {
+        if let State::Uninit { preinit, .. } = self.data.state.get_mut() {
+            *preinit = true;
+        }
+    }
+
+    /// Whether the object is added to the process-wide registry once relocated
+    #[inline]
+    pub(crate) fn register(&self) -> bool {
+        self.register
+    }
+
+    /// Sets whether the object is added to the global scope once relocated
+    #[inline]
+    pub(crate) fn set_global(&mut self, global: bool) {
+        self.global = global;
+    }
+
+    /// Whether the object is added to the global scope once relocated
+    #[inline]
+    pub(crate) fn global(&self) -> bool {
+        self.global
+    }
+
+    /// Sets whether pointer authentication codes are stripped from the
+    /// addresses bound by and to the object
+    #[cfg(feature = "aarch64-pac")]
+    #[inline]
+    pub(crate) fn set_pac(&mut self, pac: bool) {
+        self.pac = pac;
+    }
+
+    /// Whether pointer authentication codes are stripped from bound addresses
+    #[cfg(feature = "aarch64-pac")]
+    #[inline]
+    pub(crate) fn pac(&self) -> bool {
+        self.pac
+    }
+
+    /// Sets the auditor notified of the symbol bindings of the object
+    #[inline]
+    pub(crate) fn set_auditor(&mut self, auditor: Option<Arc<dyn Auditor<D>>>) {
+        self.auditor = auditor;
+    }
+
+    /// The auditor notified of the symbol bindings of the object
+    #[inline]
+    pub(crate) fn auditor(&self) -> Option<&Arc<dyn Auditor<D>>> {
+        self.auditor.as_ref()
+    }
+
     /// Gets the Global Offset Table pointer
     ///
     /// # Returns
@@ -407,23 +723,46 @@ impl<D> DynamicImage<D> {
         &self.data.extra.relocation
     }
 
-    /// Marks the ELF object as finished and calls the initialization function
-    ///
-    /// This method marks the ELF object as fully initialized and calls
-    /// any registered initialization functions.
+    /// Gets the segments to make writable during relocation, if the object
+    /// has text relocations
     #[inline]
-    pub(crate) fn finish(&self) {
-        self.data.module.set_init();
-        self.data.extra.init.as_ref()();
+    pub(crate) fn text_segments(&self) -> Option<&TextSegments> {
+        self.data.extra.text.as_ref()
     }
 
-    /// Gets the GNU_RELRO segment information
+    /// Marks the ELF object as finished and calls the initialization function
     ///
-    /// # Returns
-    /// An optional reference to the ELFRelro structure
-    #[inline]
-    pub(crate) fn relro(&self) -> Option<&ELFRelro> {
-        self.data.extra.relro.as_ref()
+    /// This method marks the ELF object as fully initialized and calls
+    /// any registered initialization functions. It must run after all
+    /// relocations, including the lazy binding setup, are applied.
+    ///
+    /// `pre_init` runs right before the initialization functions, with the
+    /// object and its dependencies `deps`. If it fails, they are skipped and
+    /// the object is marked as uninitialized again, so that neither the
+    /// finalization functions nor `post_fini` run when it is dropped.
+    pub(crate) fn finish(
+        &self,
+        deps: &Arc<[LoadedCore<D>]>,
+        pre_init: Option<&PreInit<D>>,
+        post_fini: Option<Arc<PostFini>>,
+    ) -> Result<()> {
+        let module = &self.data.module;
+        module.set_init();
+        if let Some(pre_init) = pre_init
+            && let Err(err) = pre_init(&LoadedCore {
+                core: module.clone(),
+                deps: deps.clone(),
+            })
+        {
+            module.inner.is_init.store(false, Ordering::Release);
+            return Err(err);
+        }
+        *module.inner.post_fini.lock() = post_fini;
+        if let Some(info) = &module.inner.dynamic_info {
+            info.constructed.store(true, Ordering::Relaxed);
+        }
+        self.data.extra.init.as_ref()();
+        Ok(())
     }
 
     /// Gets a mutable reference to the user data
@@ -479,11 +818,17 @@ impl<D> DynamicImage<D> {
         D: 'static,
         LazyS: SymbolLookup + Send + Sync + 'static,
     {
-        let info = unsafe {
-            &mut *(Arc::as_ptr(&self.data.module.inner.dynamic_info.as_ref().unwrap())
-                as *mut DynamicInfo)
-        };
-        info.lazy_scope = Some(Arc::new(lazy_scope));
+        let info = self.data.module.inner.dynamic_info.as_ref().unwrap();
+        let audit = self.auditor.clone().map(|auditor| {
+            Arc::new(move |module: &str, name: &str, addr: usize| {
+                auditor.lazy_resolved(module, name, addr)
+            }) as LazyAudit
+        });
+        *info.lazy_binding.write() = Some(Arc::new(LazyBinding::new(
+            Arc::new(lazy_scope),
+            info.pltrel_len(),
+            audit,
+        )));
     }
 }
 
@@ -493,25 +838,41 @@ where
 {
     /// Build the final DynamicImage object
     ///
-    /// This method completes the building process by parsing all program
-    /// headers and constructing the final DynamicImage object.
+    /// This method completes the building process by constructing the final
+    /// DynamicImage object. [`ImageBuilder::parse_phdrs`] must be called first.
     ///
     /// # Arguments
     /// * `phdrs` - Slice of program headers
+    /// * `mapped` - The segments mapped for the object, or `None` if the
+    ///   caller mapped it
     ///
     /// # Returns
-    /// * `Ok(DynamicImage)` - The built relocated ELF object
-    /// * `Err(Error)` - If building fails
-    pub(crate) fn build_dynamic(mut self, phdrs: &[ElfPhdr]) -> Result<DynamicImage<D>> {
+    /// * `Ok(image)` - The built DynamicImage object
+    /// * `Err(Error)` - If the dynamic section is missing or malformed
+    pub(crate) fn build_dynamic(
+        self,
+        phdrs: &[ElfPhdr],
+        mapped: Option<&ProgramSegments>,
+    ) -> Result<DynamicImage<D>> {
         // Determine if this is a dynamic library
         let is_dylib = self.ehdr.is_dylib();
 
-        // Parse all program headers
-        for phdr in phdrs {
-            self.parse_phdr(phdr)?;
-        }
-
-        let dynamic_ptr = self.dynamic_ptr.expect("dynamic section not found");
+        // Only the entries are parsed here; the tables they describe are set up lazily
+        let dynamic_ptr = self
+            .dynamic_ptr
+            .ok_or_else(|| parse_dynamic_error("dynamic section not found"))?;
+        let mut dynamic = self.alloc.boxed(ElfDynamic::new(
+            dynamic_ptr.as_ptr(),
+            &self.segments,
+            self.unknown_dynamic,
+        )?);
+        let vendor_dynamic = core::mem::take(&mut dynamic.vendor_entries);
+
+        // Text relocations need the protection of the segments they patch
+        let text = dynamic.textrel.then(|| match mapped {
+            Some(segments) => segments.text_segments::<M>(),
+            None => TextSegments::from_phdrs::<M>(phdrs, self.segments.base()),
+        });
 
         // Create program headers representation
         let phdrs = self.create_phdrs(phdrs);
@@ -522,18 +883,34 @@ where
             interp: self
                 .interp
                 .map(|s| unsafe { CStr::from_ptr(s.as_ptr()).to_str().unwrap() }),
-            name: unsafe { core::mem::transmute::<&str, &str>(&self.name) },
+            stack_flags: self.stack_flags,
+            gnu_properties: self.gnu_properties,
+            osabi: self.ehdr.osabi(),
+            abi_version: self.ehdr.abi_version(),
+            vendor_dynamic,
+            name: self.name.clone(),
+            path: self.path.unwrap_or_else(|| self.name.clone()),
             phdrs: phdrs.clone(),
+            register: false,
+            global: false,
+            #[cfg(feature = "aarch64-pac")]
+            pac: false,
+            auditor: None,
             data: LazyParse {
                 state: Cell::new(State::Uninit {
                     phdrs,
                     init_handler: self.init_fn,
+                    preinit: false,
                     fini_handler: self.fini_fn,
                     name: self.name,
-                    dynamic_ptr,
+                    dynamic,
                     segments: self.segments,
                     relro: self.relro,
+                    text,
+                    notes: self.notes,
+                    tls: self.tls_allocator.map(|allocator| (allocator, self.tls)),
                     user_data: self.user_data,
+                    alloc: self.alloc,
                 }),
             },
         })
@@ -0,0 +1,97 @@
+use alloc::{boxed::Box, collections::BTreeMap};
+use core::{
+    any::{Any, TypeId},
+    fmt::Debug,
+};
+
+/// User data made of independent values, keyed by their type.
+///
+/// Used as the user data type `D`, it lets several hooks or crates attach their
+/// own data to an image without agreeing on a shared struct. Each type can be
+/// stored once.
+///
+/// # Examples
+/// ```rust
+/// use elf_loader::image::Extensions;
+///
+/// struct LoadCount(usize);
+///
+/// let mut extensions = Extensions::new();
+/// extensions.insert(LoadCount(1));
+/// extensions.get_mut::<LoadCount>().unwrap().0 += 1;
+/// assert_eq!(extensions.get::<LoadCount>().unwrap().0, 2);
+/// assert!(extensions.get::<u32>().is_none());
+/// ```
+#[derive(Default)]
+pub struct Extensions {
+    map: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Creates an empty set of extensions.
+    pub const fn new() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// Stores `value`, replacing and returning the previous value of type `T`.
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    /// Gets the value of type `T`.
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Gets mutable access to the value of type `T`.
+    pub fn get_mut<T: Any + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Gets the value of type `T`, storing the result of `f` first if there is none.
+    pub fn get_or_insert_with<T: Any + Send + Sync>(&mut self, f: impl FnOnce() -> T) -> &mut T {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(f()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Removes and returns the value of type `T`.
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    /// Whether a value of type `T` is stored.
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns the number of stored values.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether no value is stored.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
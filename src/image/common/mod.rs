@@ -1,9 +1,11 @@
 mod core;
 mod dynamic;
+mod extensions;
 mod symbol;
 
 pub(crate) use core::CoreInner;
-pub(crate) use dynamic::{DynamicImage, DynamicInfo};
+pub(crate) use dynamic::{CopyRecord, DynamicImage, DynamicInfo};
 
-pub use core::{ElfCore, ElfCoreRef, LoadedCore};
-pub use symbol::Symbol;
+pub use core::{ElfCore, ElfCoreRef, LinkMapView, LoadedCore, SegmentInfo};
+pub use extensions::Extensions;
+pub use symbol::{OwnedSymbol, Symbol};
@@ -1,4 +1,5 @@
-use core::{marker::PhantomData, ops::Deref};
+use crate::image::LoadedDylib;
+use core::{fmt::Debug, marker::PhantomData, ops::Deref};
 
 /// A typed symbol retrieved from a loaded ELF module.
 ///
@@ -10,6 +11,9 @@ pub struct Symbol<'lib, T: 'lib> {
     /// Raw pointer to the symbol's memory location.
     pub(crate) ptr: *mut (),
 
+    /// Whether the definition has `STV_PROTECTED` visibility.
+    pub(crate) protected: bool,
+
     /// Phantom data to bind the symbol's lifetime to the source library.
     pub(crate) pd: PhantomData<&'lib T>,
 }
@@ -33,6 +37,49 @@ impl<'lib, T> Symbol<'lib, T> {
     pub fn into_raw(self) -> *const () {
         self.ptr
     }
+
+    /// Whether the definition has `STV_PROTECTED` visibility.
+    ///
+    /// References from inside its own library always bind to a protected
+    /// definition, so interposing another definition of the same name does
+    /// not affect them.
+    #[inline]
+    pub fn is_protected(&self) -> bool {
+        self.protected
+    }
+
+    /// Signs the address of a function with the IA key and a zero modifier.
+    ///
+    /// Code built for signed function pointers, such as the arm64e ABI,
+    /// authenticates a pointer before calling it and faults on an unsigned
+    /// one. The signed symbol must only be handed to such code; calling it
+    /// directly from Rust fails on a CPU with pointer authentication. On a CPU
+    /// without it, and on other architectures, the address is unchanged.
+    #[cfg(feature = "aarch64-pac")]
+    pub fn sign_ia(self) -> Self {
+        Symbol {
+            ptr: crate::arch::sign_ia(self.ptr as usize) as *mut (),
+            protected: self.protected,
+            pd: PhantomData,
+        }
+    }
+
+    /// Converts the `Symbol` into an [`OwnedSymbol`] that keeps `lib` loaded.
+    ///
+    /// # Arguments
+    /// * `lib` - The library the symbol was looked up in.
+    ///
+    /// # Safety
+    /// `lib` must be the library the symbol was taken from; otherwise the
+    /// symbol may outlive the memory it points to.
+    pub unsafe fn into_owned<D>(self, lib: &'lib LoadedDylib<D>) -> OwnedSymbol<T, D> {
+        OwnedSymbol {
+            ptr: self.ptr,
+            protected: self.protected,
+            module: lib.clone(),
+            pd: PhantomData,
+        }
+    }
 }
 
 // Safety: Symbol can be sent between threads if T can
@@ -40,3 +87,94 @@ unsafe impl<T: Send> Send for Symbol<'_, T> {}
 
 // Safety: Symbol can be shared between threads if T can
 unsafe impl<T: Sync> Sync for Symbol<'_, T> {}
+
+/// A typed symbol that keeps the library it was retrieved from loaded.
+///
+/// Unlike [`Symbol`], an `OwnedSymbol` is not tied to the lifetime of a borrow.
+/// It holds a reference to the library, so the library and its dependencies stay
+/// mapped until every `OwnedSymbol` and handle to it has been dropped. This makes
+/// it suitable for storing in long-lived structures.
+pub struct OwnedSymbol<T, D = ()> {
+    /// Raw pointer to the symbol's memory location.
+    ptr: *mut (),
+
+    /// Whether the definition has `STV_PROTECTED` visibility.
+    protected: bool,
+
+    /// The library the symbol belongs to.
+    module: LoadedDylib<D>,
+
+    /// Phantom data for the type of the symbol.
+    pd: PhantomData<T>,
+}
+
+impl<T, D> Deref for OwnedSymbol<T, D> {
+    type Target = T;
+
+    /// Accesses the underlying symbol as a reference to type `T`.
+    fn deref(&self) -> &T {
+        unsafe { &*(&self.ptr as *const *mut _ as *const T) }
+    }
+}
+
+impl<T, D> Clone for OwnedSymbol<T, D> {
+    /// Clones the symbol, incrementing the reference count of its library.
+    fn clone(&self) -> Self {
+        OwnedSymbol {
+            ptr: self.ptr,
+            protected: self.protected,
+            module: self.module.clone(),
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<T, D> Debug for OwnedSymbol<T, D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OwnedSymbol")
+            .field("ptr", &self.ptr)
+            .field("module", &self.module.name())
+            .finish()
+    }
+}
+
+impl<T, D> OwnedSymbol<T, D> {
+    /// Gets the library the symbol belongs to.
+    pub fn module(&self) -> &LoadedDylib<D> {
+        &self.module
+    }
+
+    /// Gets the raw memory address of the symbol.
+    ///
+    /// The address is only valid while the library is loaded.
+    pub fn as_raw(&self) -> *const () {
+        self.ptr
+    }
+
+    /// Whether the definition has `STV_PROTECTED` visibility.
+    ///
+    /// See [`Symbol::is_protected`].
+    #[inline]
+    pub fn is_protected(&self) -> bool {
+        self.protected
+    }
+
+    /// Consumes the `OwnedSymbol` and returns a [`Symbol`] that is valid forever.
+    ///
+    /// The reference to the library is leaked, so the library is never unloaded.
+    pub fn leak(self) -> Symbol<'static, T> {
+        let symbol = Symbol {
+            ptr: self.ptr,
+            protected: self.protected,
+            pd: PhantomData,
+        };
+        core::mem::forget(self.module);
+        symbol
+    }
+}
+
+// Safety: the library is kept alive by a thread-safe reference count
+unsafe impl<T: Send, D: Send + Sync> Send for OwnedSymbol<T, D> {}
+
+// Safety: the library is kept alive by a thread-safe reference count
+unsafe impl<T: Sync, D: Send + Sync> Sync for OwnedSymbol<T, D> {}
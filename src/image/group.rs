@@ -0,0 +1,219 @@
+//! Groups of modules that are torn down together
+//!
+//! Dropping a collection of libraries runs their finalizers in whatever order
+//! the collection releases them, which may finalize a library before the
+//! libraries that still use it. [`ModuleGroup`] tracks the dependencies between
+//! its members and finalizes dependents first.
+
+use crate::image::LoadedCore;
+use alloc::vec::Vec;
+use core::{borrow::Borrow, fmt::Debug};
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+/// A set of modules that is finalized as one unit.
+///
+/// A module depends on another one if the latter was part of the scope that
+/// resolved its symbols (see [`LoadedCore::deps`]) or if the dependency was
+/// added with [`add_dependency`](Self::add_dependency). When the group is
+/// dropped or [`fini_all`](Self::fini_all) is called, the finalizers run in
+/// reverse topological order: every module is finalized before the modules
+/// it depends on. Modules that are part of a dependency cycle are finalized in
+/// reverse insertion order.
+///
+/// All finalizers run before the group releases any module, so no module is
+/// unmapped while a finalizer may still call into it.
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::{Loader, image::ModuleGroup, input::ElfFile};
+///
+/// let mut loader = Loader::new();
+/// let liba = loader
+///     .load_dylib(ElfFile::from_path("liba.so").unwrap())
+///     .unwrap()
+///     .relocator()
+///     .relocate()
+///     .unwrap();
+/// let libb = loader
+///     .load_dylib(ElfFile::from_path("libb.so").unwrap())
+///     .unwrap()
+///     .relocator()
+///     .scope([&liba])
+///     .relocate()
+///     .unwrap();
+///
+/// let mut group = ModuleGroup::new();
+/// group.insert(&liba);
+/// group.insert(&libb);
+/// // libb is finalized before liba
+/// drop(group);
+/// ```
+pub struct ModuleGroup<D: 'static> {
+    modules: Vec<LoadedCore<D>>,
+    /// Explicit `(dependent, dependency)` edges
+    edges: Vec<(usize, usize)>,
+}
+
+impl<D> Default for ModuleGroup<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Debug for ModuleGroup<D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ModuleGroup")
+            .field(
+                "modules",
+                &self.modules.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            )
+            .field("edges", &self.edges)
+            .finish()
+    }
+}
+
+impl<D> ModuleGroup<D> {
+    /// Creates an empty group.
+    pub const fn new() -> Self {
+        Self {
+            modules: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    /// Adds a module to the group.
+    ///
+    /// # Returns
+    /// The index of the module in the group. A module that is already a
+    /// member keeps its index.
+    pub fn insert(&mut self, module: impl Borrow<LoadedCore<D>>) -> usize {
+        let module = module.borrow();
+        if let Some(idx) = self.position(module) {
+            return idx;
+        }
+        self.modules.push(module.clone());
+        self.modules.len() - 1
+    }
+
+    /// Records that the module at index `a` depends on the module at index `b`,
+    /// so `a` is finalized before `b`.
+    ///
+    /// # Panics
+    /// If either index is out of bounds.
+    pub fn add_dependency(&mut self, a: usize, b: usize) {
+        assert!(
+            a < self.modules.len() && b < self.modules.len(),
+            "module index out of bounds"
+        );
+        if a != b && !self.edges.contains(&(a, b)) {
+            self.edges.push((a, b));
+        }
+    }
+
+    /// Returns the modules of the group in insertion order.
+    #[inline]
+    pub fn modules(&self) -> &[LoadedCore<D>] {
+        &self.modules
+    }
+
+    /// Returns the number of modules in the group.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// Returns `true` if the group has no modules.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Computes the order in which the modules are finalized.
+    ///
+    /// # Returns
+    /// The indices of all modules, dependents before their dependencies.
+    pub fn fini_order(&self) -> Vec<usize> {
+        let len = self.modules.len();
+        let mut edges = self.edges.clone();
+        for (a, module) in self.modules.iter().enumerate() {
+            for dep in module.deps() {
+                if let Some(b) = self.position(dep)
+                    && a != b
+                    && !edges.contains(&(a, b))
+                {
+                    edges.push((a, b));
+                }
+            }
+        }
+
+        // reach[a][b]: `a` depends on `b`, directly or indirectly
+        let mut reach = alloc::vec![alloc::vec![false; len]; len];
+        for &(a, b) in &edges {
+            reach[a][b] = true;
+        }
+        for k in 0..len {
+            let via = reach[k].clone();
+            for row in reach.iter_mut().filter(|row| row[k]) {
+                row.iter_mut().zip(&via).for_each(|(r, &v)| *r |= v);
+            }
+        }
+
+        // Number of pending dependents of every module
+        let mut dependents = alloc::vec![0usize; len];
+        for &(_, b) in &edges {
+            dependents[b] += 1;
+        }
+        let mut done = alloc::vec![false; len];
+        let mut order = Vec::with_capacity(len);
+        while order.len() < len {
+            // Prefer the latest module without pending dependents. If only
+            // cycles are left, take the latest module of a cycle that no other
+            // pending module depends on from outside the cycle.
+            let next = (0..len)
+                .rev()
+                .find(|&idx| !done[idx] && dependents[idx] == 0)
+                .or_else(|| {
+                    (0..len).rev().find(|&idx| {
+                        !done[idx] && (0..len).all(|u| done[u] || !reach[u][idx] || reach[idx][u])
+                    })
+                })
+                .unwrap();
+            done[next] = true;
+            order.push(next);
+            for &(a, b) in &edges {
+                if a == next && !done[b] {
+                    dependents[b] -= 1;
+                }
+            }
+        }
+        order
+    }
+
+    /// Runs the finalizers of all modules in [`fini_order`](Self::fini_order).
+    ///
+    /// Finalizers run at most once per module, so calling this more than once,
+    /// or dropping the group afterwards, does nothing. The modules stay mapped
+    /// until the group and every other handle to them are dropped.
+    pub fn fini_all(&mut self) {
+        for idx in self.fini_order() {
+            self.modules[idx].core.inner.run_fini();
+        }
+    }
+
+    fn position(&self, module: &LoadedCore<D>) -> Option<usize> {
+        self.modules
+            .iter()
+            .position(|m| Arc::ptr_eq(&m.core.inner, &module.core.inner))
+    }
+}
+
+impl<D> Drop for ModuleGroup<D> {
+    /// Finalizes the modules before any of them is released
+    fn drop(&mut self) {
+        self.fini_all();
+    }
+}
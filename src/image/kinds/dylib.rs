@@ -6,14 +6,27 @@
 
 use crate::{
     LoadHook, Loader, Result,
-    elf::{Dyn, ElfPhdr},
-    image::{LoadedCore, common::DynamicImage},
-    input::{ElfReader, IntoElfReader},
-    os::Mmap,
-    parse_ehdr_error,
-    relocation::{Relocatable, RelocationHandler, Relocator, SymbolLookup},
+    elf::{Dyn, DynamicEntries, EHDR_SIZE, ElfHeader, ElfNotes, ElfPhdr, GnuProperties, NoteIter},
+    image::{ElfCore, ImageBuilder, LoadedCore, OwnedSymbol, common::DynamicImage},
+    input::{ElfPremapped, ElfReader, IntoElfReader},
+    loader::ExecStackPolicy,
+    os::{Mmap, ProtFlags},
+    parse_dynamic_error, parse_ehdr_error, parse_phdr_error,
+    relocation::{
+        Relocatable, RelocateOptions, RelocationHandler, RelocationSession, Relocator,
+        SymbolLookup, rebind_symbol,
+    },
+    segment::{ElfSegments, MASK, PAGE_SIZE},
 };
-use core::{borrow::Borrow, fmt::Debug, ops::Deref};
+use alloc::{borrow::ToOwned, boxed::Box, string::String, vec::Vec};
+use core::{borrow::Borrow, fmt::Debug, ops::Deref, ptr::NonNull};
+use elf::abi::{PT_DYNAMIC, PT_LOAD};
+use hashbrown::HashMap;
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
 
 /// An unrelocated dynamic library.
 ///
@@ -26,7 +39,7 @@ where
     D: 'static,
 {
     /// The common part containing basic ELF object information.
-    pub(crate) inner: DynamicImage<D>,
+    pub(crate) inner: Box<DynamicImage<D>>,
 }
 
 impl<D> Debug for RawDylib<D> {
@@ -52,8 +65,8 @@ impl<D> Relocatable<D> for RawDylib<D> {
         post_find: &PostS,
         pre_handler: PreH,
         post_handler: PostH,
-        lazy: Option<bool>,
         lazy_scope: Option<LazyS>,
+        options: RelocateOptions<'_, D>,
     ) -> Result<Self::Output>
     where
         PreS: SymbolLookup + ?Sized,
@@ -68,8 +81,8 @@ impl<D> Relocatable<D> for RawDylib<D> {
             post_find,
             pre_handler,
             post_handler,
-            lazy,
             lazy_scope,
+            options,
         )?;
         Ok(LoadedDylib { inner })
     }
@@ -106,6 +119,24 @@ impl<D> RawDylib<D> {
         self.inner.is_lazy()
     }
 
+    /// Gets the DT_FLAGS_1 value
+    #[inline]
+    pub fn flags_1(&self) -> usize {
+        self.inner.flags_1()
+    }
+
+    /// Whether the ELF object is marked with DF_1_NODELETE
+    #[inline]
+    pub fn is_nodelete(&self) -> bool {
+        self.inner.is_nodelete()
+    }
+
+    /// Whether the ELF object was linked with `-Bsymbolic`
+    #[inline]
+    pub fn is_symbolic(&self) -> bool {
+        self.inner.is_symbolic()
+    }
+
     /// Gets the DT_RPATH value
     ///
     /// # Returns
@@ -124,6 +155,56 @@ impl<D> RawDylib<D> {
         self.inner.runpath()
     }
 
+    /// Gets the DT_SONAME value
+    ///
+    /// # Returns
+    /// An optional string slice containing the shared object name
+    #[inline]
+    pub fn soname(&self) -> Option<&str> {
+        self.inner.soname()
+    }
+
+    /// Gets the GNU build-id recorded in the `NT_GNU_BUILD_ID` note
+    ///
+    /// The build-id identifies the exact build of the object, for example to
+    /// fetch its debug information from a symbol server.
+    ///
+    /// # Returns
+    /// `None` if the object has no build-id note
+    #[inline]
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.inner.build_id()
+    }
+
+    /// Iterates over the notes of the `PT_NOTE` segments
+    ///
+    /// Each note yields its owner, type and descriptor.
+    #[inline]
+    pub fn note_iter(&self) -> NoteIter<'_> {
+        self.inner.note_iter()
+    }
+
+    /// Returns the raw `(d_tag, d_un)` entries of the dynamic section.
+    ///
+    /// Entries are yielded in order up to the first `DT_NULL`, including tags
+    /// that appear more than once or are not interpreted by the loader.
+    #[inline]
+    pub fn dynamic_entries(&self) -> DynamicEntries<'_> {
+        self.inner.dynamic_entries()
+    }
+
+    /// Returns the `(d_tag, d_un)` entries in the OS- and processor-specific
+    /// ranges that the loader does not interpret, such as the metadata of a
+    /// vendor toolchain.
+    ///
+    /// The entries are only kept if the loader was configured with
+    /// [`UnknownDynamicPolicy::Collect`](crate::UnknownDynamicPolicy::Collect);
+    /// the slice is empty otherwise.
+    #[inline]
+    pub fn vendor_dynamic_entries(&self) -> &[(i64, u64)] {
+        self.inner.vendor_dynamic_entries()
+    }
+
     /// Gets the PT_INTERP value
     ///
     /// # Returns
@@ -133,12 +214,56 @@ impl<D> RawDylib<D> {
         self.inner.interp()
     }
 
+    /// Gets the stack protection requested by the PT_GNU_STACK segment
+    ///
+    /// # Returns
+    /// `None` if the object has no PT_GNU_STACK segment
+    #[inline]
+    pub fn stack_flags(&self) -> Option<ProtFlags> {
+        self.inner.stack_flags()
+    }
+
+    /// Gets the OS/ABI the object was built for (`EI_OSABI`), such as
+    /// `ELFOSABI_GNU` or `ELFOSABI_FREEBSD`
+    ///
+    /// See [`Loader::set_osabi_policy`] for rejecting objects by OS/ABI.
+    #[inline]
+    pub fn osabi(&self) -> u8 {
+        self.inner.osabi()
+    }
+
+    /// Gets the version of the ABI identified by [`osabi`](Self::osabi)
+    /// (`EI_ABIVERSION`)
+    #[inline]
+    pub fn abi_version(&self) -> u8 {
+        self.inner.abi_version()
+    }
+
+    /// Gets the hardware features the object was built for, such as x86 CET
+    /// or aarch64 BTI
+    ///
+    /// # Returns
+    /// `None` if the object has no PT_GNU_PROPERTY segment
+    #[inline]
+    pub fn gnu_properties(&self) -> Option<GnuProperties> {
+        self.inner.gnu_properties()
+    }
+
     /// Gets the name of the ELF object
     #[inline]
     pub fn name(&self) -> &str {
         self.inner.name()
     }
 
+    /// Gets the path the ELF object was read from
+    ///
+    /// This is the full name given by the reader, while [`name`](Self::name) is
+    /// only its last component.
+    #[inline]
+    pub fn path(&self) -> &str {
+        self.inner.path()
+    }
+
     /// Gets the program headers of the ELF object
     pub fn phdrs(&self) -> &[ElfPhdr] {
         self.inner.phdrs()
@@ -189,6 +314,28 @@ impl<D> RawDylib<D> {
     pub fn relocator(self) -> Relocator<Self, (), (), (), (), (), D> {
         Relocator::new(self)
     }
+
+    /// Starts a relocation that is applied a bounded number of entries at a
+    /// time, see [`RelocationSession`].
+    ///
+    /// # Arguments
+    /// * `scope` - The libraries searched for symbols, in order.
+    /// * `pre_find` - Searched before the scope.
+    /// * `post_find` - Searched after the scope.
+    pub fn begin_relocation<I, R, PreS, PostS>(
+        self,
+        scope: I,
+        pre_find: PreS,
+        post_find: PostS,
+    ) -> RelocationSession<D>
+    where
+        I: IntoIterator<Item = R>,
+        R: Borrow<LoadedCore<D>>,
+        PreS: SymbolLookup + 'static,
+        PostS: SymbolLookup + 'static,
+    {
+        RelocationSession::new(*self.inner, scope, Box::new(pre_find), Box::new(post_find))
+    }
 }
 
 impl<M: Mmap, H: LoadHook<D>, D: Default> Loader<M, H, D> {
@@ -218,42 +365,389 @@ impl<M: Mmap, H: LoadHook<D>, D: Default> Loader<M, H, D> {
         I: IntoElfReader<'a>,
     {
         let object = input.into_reader()?;
-        self.load_dylib_internal(object)
+        self.load_dylib_internal::<M>(object)
+    }
+
+    /// Loads a dynamic library through another [`Mmap`] implementation than
+    /// the one of the loader.
+    ///
+    /// Everything else, such as the hook, the init and fini handlers and the
+    /// address space limit, is shared with the other loads. All mappings of the
+    /// library, including the `PT_GNU_RELRO` protection applied during
+    /// relocation and the final unmapping, go through `NewMmap`.
+    ///
+    /// # Arguments
+    /// * `input` - The ELF object to load.
+    ///
+    /// # Returns
+    /// * `Ok(RawDylib)` - The loaded dynamic library.
+    /// * `Err(Error)` - If loading fails.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, os::DefaultMmap};
+    ///
+    /// let mut loader = Loader::new();
+    /// let lib = loader.load_dylib_with_mmap::<DefaultMmap, _>("liba.so").unwrap();
+    /// ```
+    pub fn load_dylib_with_mmap<'a, NewMmap, I>(&mut self, input: I) -> Result<RawDylib<D>>
+    where
+        NewMmap: Mmap,
+        I: IntoElfReader<'a>,
+    {
+        let object = input.into_reader()?;
+        self.load_dylib_internal::<NewMmap>(object)
+    }
+
+    /// Loads a dynamic library from an open file descriptor, such as a `memfd`.
+    ///
+    /// This is a shorthand for [`load_dylib`](Self::load_dylib) with an
+    /// [`ElfFd`](crate::input::ElfFd) named after the target of
+    /// `/proc/self/fd/<fd>`. The segments are mapped from the descriptor.
+    ///
+    /// # Arguments
+    /// * `fd` - The file descriptor of the library.
+    ///
+    /// # Returns
+    /// * `Ok(RawDylib)` - The loaded dynamic library.
+    /// * `Err(Error)` - If loading fails.
+    #[cfg(all(feature = "std", unix))]
+    pub fn load_dylib_from_fd(&mut self, fd: std::os::fd::OwnedFd) -> Result<RawDylib<D>> {
+        self.load_dylib(crate::input::ElfFd::new(None, fd))
     }
 
-    pub(crate) fn load_dylib_internal(
+    pub(crate) fn load_dylib_internal<NewMmap: Mmap>(
         &mut self,
         mut object: impl ElfReader,
     ) -> Result<RawDylib<D>> {
         // Prepare and validate the ELF header
-        let ehdr = self.buf.prepare_ehdr(&mut object)?;
+        let ehdr = self
+            .buf
+            .prepare_ehdr(&mut object, &self.allowed_arch, self.osabi)?;
 
         // Ensure the file is actually a dynamic library
         if !ehdr.is_dylib() {
             return Err(parse_ehdr_error("file type mismatch"));
         }
 
+        let (init_fn, fini_fn) = self.fn_handlers();
         let phdrs = self.buf.prepare_phdrs(&ehdr, &mut object)?;
 
         // Load the relocated common part
-        let inner = Self::load_dynamic_impl(
+        let mut inner = Loader::<NewMmap, H, D>::load_dynamic_impl(
             &self.hook,
-            &self.init_fn,
-            &self.fini_fn,
+            &init_fn,
+            &fini_fn,
+            &self.tls,
+            self.unknown_dynamic,
+            self.extra,
+            true,
+            &self.budget,
+            &self.alloc,
             ehdr,
             phdrs,
             object,
         )?;
+        self.check_execstack(&inner)?;
+        inner.set_register(self.registry);
+        #[cfg(feature = "aarch64-pac")]
+        inner.set_pac(self.pac);
+        inner.set_auditor(self.auditor.clone());
 
         // Wrap in RawDylib and return
-        Ok(RawDylib { inner })
+        Ok(RawDylib {
+            inner: Box::new(inner),
+        })
+    }
+
+    /// Loads a dynamic library from an image that is already mapped into memory.
+    ///
+    /// No memory is allocated and no segment contents are copied: the image
+    /// described by `image` is used in place, and `base()` of the returned
+    /// library is the start of that image. The loader does not change the
+    /// memory protections of the image (including `PT_GNU_RELRO`) and never
+    /// unmaps it.
+    ///
+    /// # Arguments
+    /// * `image` - The premapped ELF image.
+    ///
+    /// # Returns
+    /// * `Ok(RawDylib)` - The loaded dynamic library.
+    /// * `Err(Error)` - If the image is not a valid dynamic library.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use core::ptr::NonNull;
+    /// use elf_loader::{Loader, input::ElfPremapped};
+    ///
+    /// # let (addr, len) = (0x1000 as *mut core::ffi::c_void, 0x1000);
+    /// let mut loader = Loader::new();
+    /// let image = unsafe { ElfPremapped::new("liba.so", NonNull::new(addr).unwrap(), len) };
+    /// let lib = loader.load_dylib_premapped(image).unwrap();
+    /// ```
+    pub fn load_dylib_premapped(&mut self, image: ElfPremapped) -> Result<RawDylib<D>> {
+        let (name, base, len) = image.into_parts();
+        if len < EHDR_SIZE {
+            return Err(parse_ehdr_error("premapped image is too small"));
+        }
+        let bytes = unsafe { core::slice::from_raw_parts(base.as_ptr().cast::<u8>(), len) };
+        let ehdr = ElfHeader::new(bytes, &self.allowed_arch)?.clone();
+        self.osabi.check(&ehdr, &name)?;
+
+        // Ensure the file is actually a dynamic library
+        if !ehdr.is_dylib() {
+            return Err(parse_ehdr_error("file type mismatch"));
+        }
+
+        let phnum = ehdr.phnum(|buf, offset| {
+            let mut reader = bytes;
+            reader.read(buf, offset)
+        })?;
+        let (phdr_start, phdr_end) = ehdr.checked_phdr_range(phnum, Some(len))?;
+        let phdrs: &[ElfPhdr] = unsafe {
+            core::slice::from_raw_parts(
+                bytes[phdr_start..].as_ptr().cast(),
+                (phdr_end - phdr_start) / size_of::<ElfPhdr>(),
+            )
+        };
+        if phdrs.iter().any(|phdr| {
+            phdr.p_type == PT_LOAD
+                && phdr
+                    .p_vaddr
+                    .checked_add(phdr.p_memsz)
+                    .is_none_or(|end| end as usize > len)
+        }) {
+            return Err(parse_ehdr_error("segment is outside the premapped image"));
+        }
+
+        let (init_fn, fini_fn) = self.fn_handlers();
+        let mut builder: ImageBuilder<'_, H, M, D> = ImageBuilder::new(
+            &self.hook,
+            ElfSegments::premapped(base, len),
+            &name,
+            ehdr,
+            init_fn,
+            fini_fn,
+            &self.alloc,
+        );
+        // The caller owns the memory, so protection overrides are ignored
+        builder.parse_phdrs(phdrs)?;
+        let notes = ElfNotes::mapped(phdrs, &builder.segments);
+        let mut inner = builder
+            .premapped()
+            .notes(notes)
+            .tls_allocator(self.tls.clone())
+            .unknown_dynamic(self.unknown_dynamic)
+            .build_dynamic(phdrs, None)?;
+        self.check_execstack(&inner)?;
+        inner.set_register(self.registry);
+        #[cfg(feature = "aarch64-pac")]
+        inner.set_pac(self.pac);
+        inner.set_auditor(self.auditor.clone());
+        Ok(RawDylib {
+            inner: Box::new(inner),
+        })
+    }
+
+    /// Applies the executable stack policy to a loaded library
+    fn check_execstack(&self, inner: &DynamicImage<D>) -> Result<()> {
+        let execstack = inner
+            .stack_flags()
+            .is_some_and(|flags| flags.contains(ProtFlags::PROT_EXEC));
+        if !execstack {
+            return Ok(());
+        }
+        match self.execstack {
+            ExecStackPolicy::Allow => {}
+            ExecStackPolicy::Warn => {
+                #[cfg(feature = "trace")]
+                crate::trace::emit(crate::trace::TraceEvent::ExecStack { lib: inner.name() });
+            }
+            ExecStackPolicy::Deny => {
+                return Err(parse_phdr_error(alloc::format!(
+                    "file [{}]: executable stack requested by PT_GNU_STACK is denied",
+                    inner.name()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads a dynamic library together with all of its `DT_NEEDED` dependencies.
+    ///
+    /// The dependency tree is walked breadth-first starting from `input`. Each
+    /// needed library that has not been loaded yet is handed to `resolver`, which
+    /// is responsible for turning the library name into an [`ElfReader`]. Libraries
+    /// are deduplicated by name, so cycles in `DT_NEEDED` are visited only once.
+    ///
+    /// # Arguments
+    /// * `input` - The root dynamic library.
+    /// * `resolver` - Maps a [`NeededLib`] request to a reader. The request carries
+    ///   the `DT_RPATH`/`DT_RUNPATH` of the library that needs it, so the caller
+    ///   can implement its own search path logic.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<RawDylib>)` - All loaded libraries in dependency order: every library
+    ///   appears after the libraries it depends on, and the root library is last.
+    ///   Libraries that are part of a cycle are ordered by discovery.
+    /// * `Err(Error)` - If the resolver or loading of any library fails.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, input::ElfFile};
+    ///
+    /// let mut loader = Loader::new();
+    /// let libs = loader
+    ///     .load_dylib_with_deps("/path/to/liba.so", |needed| {
+    ///         let dir = needed.runpath().or(needed.rpath()).unwrap_or("/usr/lib");
+    ///         ElfFile::from_path(&format!("{}/{}", dir, needed.name()))
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn load_dylib_with_deps<'a, I, F, R>(
+        &mut self,
+        input: I,
+        resolver: F,
+    ) -> Result<Vec<RawDylib<D>>>
+    where
+        I: IntoElfReader<'a>,
+        F: FnMut(&NeededLib<'_>) -> Result<R>,
+        R: ElfReader,
+    {
+        self.load_dylib_tree(input, resolver, |_| false)
+    }
+
+    /// Like [`load_dylib_with_deps`](Self::load_dylib_with_deps), but the
+    /// dependencies for which `is_present` returns `true` are neither loaded
+    /// nor returned.
+    pub(crate) fn load_dylib_tree<'a, I, F, R, P>(
+        &mut self,
+        input: I,
+        mut resolver: F,
+        mut is_present: P,
+    ) -> Result<Vec<RawDylib<D>>>
+    where
+        I: IntoElfReader<'a>,
+        F: FnMut(&NeededLib<'_>) -> Result<R>,
+        R: ElfReader,
+        P: FnMut(&str) -> bool,
+    {
+        let root = self.load_dylib(input)?;
+        let mut loaded: HashMap<String, usize> = HashMap::new();
+        loaded.insert(root.name().to_owned(), 0);
+        let mut libs = alloc::vec![root];
+        // deps[i] holds the indices of the libraries needed by libs[i]
+        let mut deps: Vec<Vec<usize>> = Vec::new();
+
+        // Breadth-first walk: libs doubles as the work queue
+        let mut cur = 0;
+        while cur < libs.len() {
+            let needed: Vec<String> = libs[cur]
+                .needed_libs()
+                .iter()
+                .map(|name| (*name).to_owned())
+                .collect();
+            let mut cur_deps = Vec::with_capacity(needed.len());
+            for name in needed {
+                if let Some(&idx) = loaded.get(name.as_str()) {
+                    cur_deps.push(idx);
+                    continue;
+                }
+                if is_present(&name) {
+                    continue;
+                }
+                let parent = &libs[cur];
+                let object = resolver(&NeededLib {
+                    name: &name,
+                    parent: parent.name(),
+                    rpath: parent.rpath(),
+                    runpath: parent.runpath(),
+                })?;
+                let lib = self.load_dylib_internal::<M>(object)?;
+                let idx = libs.len();
+                loaded.entry(lib.name().to_owned()).or_insert(idx);
+                loaded.insert(name, idx);
+                libs.push(lib);
+                cur_deps.push(idx);
+            }
+            deps.push(cur_deps);
+            cur += 1;
+        }
+
+        // Post-order DFS from the root gives dependencies before dependents
+        let mut order = Vec::with_capacity(libs.len());
+        let mut visited = alloc::vec![false; libs.len()];
+        let mut stack = alloc::vec![(0usize, 0usize)];
+        visited[0] = true;
+        while let Some((idx, next)) = stack.last_mut() {
+            if let Some(&dep) = deps[*idx].get(*next) {
+                *next += 1;
+                if !visited[dep] {
+                    visited[dep] = true;
+                    stack.push((dep, 0));
+                }
+            } else {
+                order.push(*idx);
+                stack.pop();
+            }
+        }
+
+        let mut libs: Vec<Option<RawDylib<D>>> = libs.into_iter().map(Some).collect();
+        Ok(order
+            .into_iter()
+            .map(|idx| libs[idx].take().unwrap())
+            .collect())
     }
 }
 
-#[derive(Debug, Clone)]
+/// A dependency request passed to the resolver of [`Loader::load_dylib_with_deps`].
+#[derive(Debug, Clone, Copy)]
+pub struct NeededLib<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) parent: &'a str,
+    pub(crate) rpath: Option<&'a str>,
+    pub(crate) runpath: Option<&'a str>,
+}
+
+impl<'a> NeededLib<'a> {
+    /// Returns the library name as written in `DT_NEEDED`.
+    #[inline]
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the name of the library that requires this dependency.
+    #[inline]
+    pub fn parent(&self) -> &'a str {
+        self.parent
+    }
+
+    /// Returns the `DT_RPATH` of the requiring library.
+    #[inline]
+    pub fn rpath(&self) -> Option<&'a str> {
+        self.rpath
+    }
+
+    /// Returns the `DT_RUNPATH` of the requiring library.
+    #[inline]
+    pub fn runpath(&self) -> Option<&'a str> {
+        self.runpath
+    }
+}
+
+#[derive(Debug)]
 /// A relocated dynamic library.
 pub struct LoadedDylib<D> {
-    inner: LoadedCore<D>,
+    pub(crate) inner: LoadedCore<D>,
+}
+
+impl<D> Clone for LoadedDylib<D> {
+    /// Clones the handle, incrementing the reference count of the library.
+    fn clone(&self) -> Self {
+        LoadedDylib {
+            inner: self.inner.clone(),
+        }
+    }
 }
 
 impl<D> Deref for LoadedDylib<D> {
@@ -275,3 +769,176 @@ impl<D> Borrow<LoadedCore<D>> for &LoadedDylib<D> {
         &self.inner
     }
 }
+
+impl<D> LoadedDylib<D> {
+    /// Unloads the dynamic library, similar to `dlclose`.
+    ///
+    /// The library is only unloaded if this handle holds the last strong
+    /// reference to it. In that case the finalization handler is run
+    /// (`DT_FINI_ARRAY` in reverse order, then `DT_FINI` with the default
+    /// handler), the segments are unmapped and the references to its
+    /// dependencies are released.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the library was unloaded.
+    /// * `Err(self)` - If other modules or handles still reference the library.
+    ///   The library is returned unchanged so that the caller can retry later.
+    pub fn try_unload(self) -> core::result::Result<(), Self> {
+        // Dropping the last reference unmaps the memory
+        self.inner
+            .try_unload_with(|_| ())
+            .map_err(|inner| LoadedDylib { inner })
+    }
+
+    /// Unloads the dynamic library like [`try_unload`](Self::try_unload), but
+    /// returns the error of unmapping its memory.
+    ///
+    /// See [`LoadedCore::try_unload_segments`].
+    pub fn try_unload_segments(self) -> core::result::Result<Result<()>, Self> {
+        self.inner
+            .try_unload_segments()
+            .map_err(|inner| LoadedDylib { inner })
+    }
+
+    /// Redirects every PLT call to `name` made by this library to `addr`.
+    ///
+    /// All `JUMP_SLOT` relocations of the library that refer to `name` are
+    /// patched, including slots that have already been bound lazily. If the
+    /// GOT is protected by RELRO, it is made writable for the duration of the
+    /// update. Each GOT entry is written with a single atomic store, so this
+    /// can be called while other slots are being resolved lazily.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the imported function.
+    /// * `addr` - The new target of the calls.
+    ///
+    /// # Returns
+    /// The number of GOT entries that were patched.
+    ///
+    /// # Safety
+    /// `addr` must point to a function with the same signature and calling
+    /// convention as the one it replaces, and must stay valid for as long as
+    /// this library may call it.
+    pub unsafe fn rebind_symbol(&self, name: &str, addr: *const ()) -> Result<usize> {
+        rebind_symbol(&self.inner.core.inner, name, addr as usize)
+    }
+
+    /// Sets the lookup used by lazy binding for symbols missing from the lazy scope.
+    ///
+    /// Replaces any fallback set before. Symbols it cannot resolve either are
+    /// passed to the handler installed with
+    /// [`set_unresolved_handler`](crate::relocation::set_unresolved_handler).
+    ///
+    /// # Arguments
+    /// * `fallback` - The lookup to consult.
+    pub fn set_lazy_fallback<S>(&self, fallback: S)
+    where
+        S: SymbolLookup + Send + Sync + 'static,
+    {
+        if let Some(info) = self.inner.core.inner.dynamic_info.as_ref() {
+            *info.lazy_fallback.write() = Some(Arc::new(fallback));
+        }
+    }
+
+    /// Gets a symbol that keeps the library loaded for as long as it exists.
+    ///
+    /// This behaves like [`LoadedCore::get`], but the returned [`OwnedSymbol`]
+    /// is not bound to a borrow of the library and can be stored anywhere.
+    ///
+    /// # Safety
+    /// Users of this API must specify the correct type of the function
+    /// or variable loaded.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the symbol to look up
+    ///
+    /// # Returns
+    /// * `Some(symbol)` - If the symbol is found
+    /// * `None` - If the symbol is not found
+    pub unsafe fn get_owned<T>(&self, name: &str) -> Option<OwnedSymbol<T, D>> {
+        unsafe { self.get::<T>(name).map(|symbol| symbol.into_owned(self)) }
+    }
+}
+
+impl LoadedDylib<()> {
+    /// Wraps a dynamic library that was mapped and relocated by someone else.
+    ///
+    /// This is meant for images provided by the kernel, such as the vDSO, whose
+    /// address is passed in the `AT_SYSINFO_EHDR` auxiliary vector entry. The ELF
+    /// header, program headers, dynamic section and symbol tables are read where
+    /// they are mapped. The load bias is the difference between `addr` and the
+    /// page containing the lowest `PT_LOAD` address. Nothing is mapped, relocated
+    /// or initialized, and the image is never unmapped.
+    ///
+    /// # Arguments
+    /// * `name` - The name to give to the library.
+    /// * `addr` - The address the ELF header is mapped at.
+    ///
+    /// # Returns
+    /// * `Ok(LoadedDylib)` - The library, ready for symbol lookup.
+    /// * `Err(Error)` - If the image is not a dynamic library of the host.
+    ///
+    /// # Safety
+    /// `addr` must point to a complete, relocated ELF image that stays mapped
+    /// for as long as the returned library or any symbol taken from it is used.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::image::LoadedDylib;
+    ///
+    /// # let addr = 0x1000;
+    /// let vdso = unsafe { LoadedDylib::from_raw_mapped("linux-vdso.so.1", addr) }.unwrap();
+    /// let clock_gettime = unsafe { vdso.get::<()>("__vdso_clock_gettime") };
+    /// ```
+    pub unsafe fn from_raw_mapped(name: &str, addr: usize) -> Result<Self> {
+        let bytes = unsafe { core::slice::from_raw_parts(addr as *const u8, EHDR_SIZE) };
+        let ehdr = ElfHeader::new(bytes, &[])?;
+        if !ehdr.is_dylib() {
+            return Err(parse_ehdr_error("file type mismatch"));
+        }
+        let (phdr_start, phdr_end) = ehdr.phdr_range();
+        let phdrs: &'static [ElfPhdr] = unsafe {
+            core::slice::from_raw_parts(
+                (addr + phdr_start) as *const ElfPhdr,
+                (phdr_end - phdr_start) / size_of::<ElfPhdr>(),
+            )
+        };
+
+        let mut min_vaddr = usize::MAX;
+        let mut max_vaddr = 0;
+        let mut dynamic = None;
+        for phdr in phdrs {
+            match phdr.p_type {
+                PT_LOAD => {
+                    min_vaddr = min_vaddr.min(phdr.p_vaddr as usize);
+                    max_vaddr = max_vaddr.max((phdr.p_vaddr + phdr.p_memsz) as usize);
+                }
+                PT_DYNAMIC => dynamic = Some(phdr.p_vaddr as usize),
+                _ => {}
+            }
+        }
+        if min_vaddr >= max_vaddr {
+            return Err(parse_ehdr_error("image has no loadable segment"));
+        }
+        let dynamic = dynamic.ok_or_else(|| parse_dynamic_error("image has no dynamic section"))?;
+        let min_vaddr = min_vaddr & MASK;
+        let len = ((max_vaddr + PAGE_SIZE - 1) & MASK) - min_vaddr;
+        let base = addr.wrapping_sub(min_vaddr);
+
+        let segments = ElfSegments::premapped(NonNull::new(addr as _).unwrap(), len);
+        let core = unsafe {
+            ElfCore::from_raw(
+                name.to_owned(),
+                base,
+                base.wrapping_add(dynamic) as *const Dyn,
+                phdrs,
+                segments,
+                (),
+                None,
+            )
+        }?;
+        Ok(LoadedDylib {
+            inner: unsafe { LoadedCore::from_core(core) },
+        })
+    }
+}
@@ -5,15 +5,16 @@
 /// synchronous loading of executable files.
 use crate::{
     LoadHook, Loader, Result,
-    elf::ElfPhdr,
+    allocator::NulStr,
+    elf::{ElfPhdr, ElfPhdrs},
     image::{DynamicImage, ImageBuilder, LoadedCore},
     input::{ElfReader, IntoElfReader},
     os::Mmap,
     parse_ehdr_error,
-    relocation::{Relocatable, RelocationHandler, Relocator, SymbolLookup},
+    relocation::{Relocatable, RelocateOptions, RelocationHandler, Relocator, SymbolLookup},
     segment::ElfSegments,
 };
-use alloc::string::String;
+use alloc::boxed::Box;
 use core::fmt::Debug;
 use elf::abi::PT_DYNAMIC;
 
@@ -43,14 +44,21 @@ impl<D> StaticImage<D> {
     pub fn entry(&self) -> usize {
         self.inner.entry
     }
+
+    pub fn phdrs(&self) -> &[ElfPhdr] {
+        self.inner.phdrs.as_slice()
+    }
 }
 
 pub(crate) struct StaticImageInner<D> {
     /// File name of the ELF object
-    pub(crate) name: String,
+    pub(crate) name: NulStr,
 
     pub(crate) entry: usize,
 
+    /// Program headers, in the mapped image when they are loaded with it
+    pub(crate) phdrs: ElfPhdrs,
+
     /// User-defined data
     pub(crate) user_data: D,
 
@@ -68,8 +76,8 @@ impl<D: 'static> Relocatable<D> for RawExec<D> {
         post_find: &PostS,
         pre_handler: PreH,
         post_handler: PostH,
-        lazy: Option<bool>,
         lazy_scope: Option<LazyS>,
+        options: RelocateOptions<'_, D>,
     ) -> Result<Self::Output>
     where
         PreS: SymbolLookup + ?Sized,
@@ -87,8 +95,8 @@ impl<D: 'static> Relocatable<D> for RawExec<D> {
                     post_find,
                     pre_handler,
                     post_handler,
-                    lazy,
                     lazy_scope,
+                    options,
                 )?;
                 Ok(LoadedExec {
                     entry,
@@ -121,7 +129,7 @@ where
     D: 'static,
 {
     /// The common part containing basic ELF object information.
-    Dynamic(DynamicImage<D>),
+    Dynamic(Box<DynamicImage<D>>),
     Static(StaticImage<D>),
 }
 
@@ -159,6 +167,22 @@ impl<D: 'static> RawExec<D> {
         }
     }
 
+    /// Returns the interpreter requested by the `PT_INTERP` segment, if any.
+    pub fn interp(&self) -> Option<&str> {
+        match &self.inner {
+            ExecImageInner::Dynamic(image) => image.interp(),
+            ExecImageInner::Static(_) => None,
+        }
+    }
+
+    /// Returns the program headers of the executable.
+    pub fn phdrs(&self) -> &[ElfPhdr] {
+        match &self.inner {
+            ExecImageInner::Dynamic(image) => image.phdrs(),
+            ExecImageInner::Static(image) => image.phdrs(),
+        }
+    }
+
     /// Returns the total length of memory that will be occupied by the executable after relocation.
     pub fn mapped_len(&self) -> usize {
         match &self.inner {
@@ -166,6 +190,14 @@ impl<D: 'static> RawExec<D> {
             ExecImageInner::Static(image) => image.inner.segments.len(),
         }
     }
+
+    /// Returns the dynamic image of a dynamically linked executable.
+    pub(crate) fn dynamic(&self) -> Option<&DynamicImage<D>> {
+        match &self.inner {
+            ExecImageInner::Dynamic(image) => Some(image),
+            ExecImageInner::Static(_) => None,
+        }
+    }
 }
 
 impl<M: Mmap, H: LoadHook<D>, D: Default> Loader<M, H, D> {
@@ -200,36 +232,55 @@ impl<M: Mmap, H: LoadHook<D>, D: Default> Loader<M, H, D> {
 
     pub(crate) fn load_exec_internal(&mut self, mut object: impl ElfReader) -> Result<RawExec<D>> {
         // Prepare and validate the ELF header
-        let ehdr = self.buf.prepare_ehdr(&mut object)?;
+        let ehdr = self
+            .buf
+            .prepare_ehdr(&mut object, &self.allowed_arch, self.osabi)?;
 
         // Ensure the file is actually an executable
         if !ehdr.is_executable() {
             return Err(parse_ehdr_error("file type mismatch"));
         }
 
+        let (init_fn, fini_fn) = self.fn_handlers();
         let phdrs = self.buf.prepare_phdrs(&ehdr, &mut object)?;
         let has_dynamic = phdrs.iter().any(|phdr| phdr.p_type == PT_DYNAMIC);
 
         if has_dynamic {
             // Load the relocated common part
-            let inner = Self::load_dynamic_impl(
+            let mut inner = Self::load_dynamic_impl(
                 &self.hook,
-                &self.init_fn,
-                &self.fini_fn,
+                &init_fn,
+                &fini_fn,
+                &self.tls,
+                self.unknown_dynamic,
+                self.extra,
+                self.fixed_overwrite,
+                &self.budget,
+                &self.alloc,
                 ehdr,
                 phdrs,
                 object,
             )?;
+            inner.set_register(self.registry);
+            #[cfg(feature = "aarch64-pac")]
+            inner.set_pac(self.pac);
+            inner.set_auditor(self.auditor.clone());
+            inner.enable_preinit();
+            inner.core_ref().set_main_program();
             // Wrap in RawExec and return
             Ok(RawExec {
-                inner: ExecImageInner::Dynamic(inner),
+                inner: ExecImageInner::Dynamic(Box::new(inner)),
             })
         } else {
             // Load as a static module without dynamic section
             let inner = Self::load_static_impl(
                 &self.hook,
-                &self.init_fn,
-                &self.fini_fn,
+                &init_fn,
+                &fini_fn,
+                self.extra,
+                self.fixed_overwrite,
+                &self.budget,
+                &self.alloc,
                 ehdr,
                 phdrs,
                 object,
@@ -276,6 +327,14 @@ impl<D> LoadedExec<D> {
         }
     }
 
+    /// Returns the program headers of the executable.
+    pub fn phdrs(&self) -> &[ElfPhdr] {
+        match &self.inner {
+            LoadedExecInner::Dynamic(module) => unsafe { module.core_ref().phdrs() }.unwrap_or(&[]),
+            LoadedExecInner::Static(static_image) => static_image.phdrs(),
+        }
+    }
+
     /// Returns the total length of memory occupied by the executable.
     pub fn mapped_len(&self) -> usize {
         match &self.inner {
@@ -313,21 +372,17 @@ impl<'hook, H, M: Mmap, D: Default> ImageBuilder<'hook, H, M, D>
 where
     H: LoadHook<D>,
 {
-    pub(crate) fn build_static(mut self, phdrs: &[ElfPhdr]) -> Result<StaticImage<D>> {
-        // Parse all program headers
-        for phdr in phdrs {
-            self.parse_phdr(phdr)?;
-        }
-
+    pub(crate) fn build_static(self, phdrs: ElfPhdrs) -> StaticImage<D> {
         let entry = self.ehdr.e_entry as usize;
         let static_inner = StaticImageInner {
             entry,
+            phdrs,
             name: self.name,
             user_data: self.user_data,
             segments: self.segments,
         };
-        Ok(StaticImage {
+        StaticImage {
             inner: Arc::new(static_inner),
-        })
+        }
     }
 }
@@ -0,0 +1,612 @@
+//! Foreign ELF object handling
+//!
+//! This module maps ELF objects whose class, byte order or machine differ
+//! from the host, e.g. 32-bit RISC-V libraries on an x86_64 host. Such
+//! objects are never executed by the host: they are mapped readable and
+//! writable, their data relocations are applied using guest addresses, and
+//! an emulator is expected to take over from there.
+
+use crate::{
+    LoadHook, Loader, Result,
+    elf::ElfPhdr,
+    input::{ElfReader, IntoElfReader},
+    os::{Mmap, ProtFlags},
+    parse_dynamic_error, parse_ehdr_error, relocate_error,
+    segment::{ElfSegments, ExtraSpace, SegmentBuilder, program::ProgramSegments},
+};
+use alloc::{borrow::ToOwned, format, string::String, vec, vec::Vec};
+use core::{fmt::Debug, ops::Deref, ptr::NonNull};
+use elf::{
+    ParseError,
+    abi::*,
+    dynamic::DynamicTable,
+    endian::AnyEndian,
+    file::{Class, FileHeader, parse_ident},
+    hash::{GnuHashTable, SysVHashTable},
+    parse::ParseAt,
+    relocation::{RelIterator, RelaIterator},
+    segment::{ProgramHeader, SegmentTable},
+    string_table::StringTable,
+    symbol::{Symbol, SymbolTable},
+};
+
+// Relocation types that are missing from `elf::abi`
+const R_386_32: u32 = 1;
+const R_386_GLOB_DAT: u32 = 6;
+const R_386_JMP_SLOT: u32 = 7;
+const R_386_RELATIVE: u32 = 8;
+const R_LARCH_32: u32 = 1;
+const R_LARCH_64: u32 = 2;
+const R_LARCH_RELATIVE: u32 = 3;
+const R_LARCH_JUMP_SLOT: u32 = 5;
+
+/// How a foreign relocation computes its value
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RelocKind {
+    /// Nothing to do
+    None,
+    /// `B + A`
+    Relative,
+    /// `S + A`
+    Absolute,
+    /// `S`, plus the addend for RELA entries
+    Slot,
+}
+
+/// Classifies a relocation type of the given machine and class.
+///
+/// # Returns
+/// `None` if the relocation type is not supported for foreign objects.
+fn reloc_kind(machine: u16, class: Class, r_type: u32) -> Option<RelocKind> {
+    let is_64 = class == Class::ELF64;
+    let kind = match (machine, r_type) {
+        (_, 0) => RelocKind::None,
+        (EM_X86_64, R_X86_64_RELATIVE) => RelocKind::Relative,
+        (EM_X86_64, R_X86_64_64) => RelocKind::Absolute,
+        (EM_X86_64, R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT) => RelocKind::Slot,
+        (EM_386, R_386_RELATIVE) => RelocKind::Relative,
+        (EM_386, R_386_32) => RelocKind::Absolute,
+        (EM_386, R_386_GLOB_DAT | R_386_JMP_SLOT) => RelocKind::Slot,
+        (EM_AARCH64, R_AARCH64_RELATIVE) => RelocKind::Relative,
+        (EM_AARCH64, R_AARCH64_ABS64) => RelocKind::Absolute,
+        (EM_AARCH64, R_AARCH64_GLOB_DAT | R_AARCH64_JUMP_SLOT) => RelocKind::Slot,
+        (EM_ARM, R_ARM_RELATIVE) => RelocKind::Relative,
+        (EM_ARM, R_ARM_ABS32) => RelocKind::Absolute,
+        (EM_ARM, R_ARM_GLOB_DAT | R_ARM_JUMP_SLOT) => RelocKind::Slot,
+        (EM_RISCV, R_RISCV_RELATIVE) => RelocKind::Relative,
+        (EM_RISCV, R_RISCV_64) if is_64 => RelocKind::Absolute,
+        (EM_RISCV, R_RISCV_32) if !is_64 => RelocKind::Absolute,
+        (EM_RISCV, R_RISCV_JUMP_SLOT) => RelocKind::Slot,
+        (EM_LOONGARCH, R_LARCH_RELATIVE) => RelocKind::Relative,
+        (EM_LOONGARCH, R_LARCH_64) if is_64 => RelocKind::Absolute,
+        (EM_LOONGARCH, R_LARCH_32) if !is_64 => RelocKind::Absolute,
+        (EM_LOONGARCH, R_LARCH_JUMP_SLOT) => RelocKind::Slot,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Converts an error of the `elf` crate into a dynamic section parsing error
+#[inline]
+fn dynamic_error(err: ParseError) -> crate::Error {
+    parse_dynamic_error(format!("{err}"))
+}
+
+/// Addresses of the tables described by the dynamic section
+#[derive(Default)]
+struct ForeignDynamic {
+    strtab: u64,
+    strsz: u64,
+    symtab: u64,
+    hash: Option<u64>,
+    gnu_hash: Option<u64>,
+    rel: (u64, u64),
+    rela: (u64, u64),
+    jmprel: (u64, u64),
+    pltrel_is_rela: bool,
+    needed: Vec<u64>,
+}
+
+/// A mapped ELF object of any class, byte order and machine.
+///
+/// All addresses returned by this type are guest addresses unless stated
+/// otherwise. The guest address of a virtual address `vaddr` is
+/// `guest_base + vaddr` for position-independent objects (`ET_DYN`) and
+/// `vaddr` itself for objects linked at a fixed address (`ET_EXEC`).
+pub struct ForeignImage {
+    name: String,
+    class: Class,
+    endian: AnyEndian,
+    machine: u16,
+    entry: u64,
+    bias: usize,
+    phdrs: Vec<ElfPhdr>,
+    dynamic: Option<ForeignDynamic>,
+    segments: ElfSegments,
+}
+
+impl Debug for ForeignImage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ForeignImage")
+            .field("name", &self.name)
+            .field("machine", &self.machine)
+            .field("class", &self.class())
+            .field("guest_base", &self.bias)
+            .finish()
+    }
+}
+
+impl ForeignImage {
+    /// Returns the name of the object.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the `EI_CLASS` of the object (`ELFCLASS32` or `ELFCLASS64`).
+    pub fn class(&self) -> u8 {
+        match self.class {
+            Class::ELF32 => ELFCLASS32,
+            Class::ELF64 => ELFCLASS64,
+        }
+    }
+
+    /// Returns `true` if the object uses big-endian byte order.
+    pub fn is_big_endian(&self) -> bool {
+        self.endian == AnyEndian::Big
+    }
+
+    /// Returns the `e_machine` of the object.
+    pub fn machine(&self) -> u16 {
+        self.machine
+    }
+
+    /// Returns the guest address of the entry point.
+    pub fn entry(&self) -> usize {
+        self.guest_addr(self.entry)
+    }
+
+    /// Returns the value added to virtual addresses to form guest addresses.
+    pub fn guest_base(&self) -> usize {
+        self.bias
+    }
+
+    /// Returns the host address that corresponds to virtual address 0.
+    pub fn base(&self) -> usize {
+        self.segments.base()
+    }
+
+    /// Returns the program headers, converted to the native layout.
+    pub fn phdrs(&self) -> &[ElfPhdr] {
+        &self.phdrs
+    }
+
+    /// Returns the names of the libraries listed in `DT_NEEDED`.
+    pub fn needed_libs(&self) -> Vec<&str> {
+        let Some(dynamic) = &self.dynamic else {
+            return Vec::new();
+        };
+        let Ok(strtab) = self.strtab(dynamic) else {
+            return Vec::new();
+        };
+        dynamic
+            .needed
+            .iter()
+            .filter_map(|&off| strtab.get(off as usize).ok())
+            .collect()
+    }
+
+    /// Returns the guest address of a symbol defined by the object.
+    ///
+    /// Undefined symbols and TLS symbols yield `None`.
+    pub fn get(&self, name: &str) -> Option<usize> {
+        let sym = self.lookup(name)?;
+        if sym.is_undefined() || sym.st_symtype() == STT_TLS {
+            return None;
+        }
+        Some(self.guest_addr(sym.st_value))
+    }
+
+    /// Translates a guest address into a host pointer.
+    ///
+    /// # Returns
+    /// `None` if the address is not inside a `PT_LOAD` segment.
+    pub fn host_ptr(&self, guest_addr: usize) -> Option<NonNull<u8>> {
+        let vaddr = guest_addr.wrapping_sub(self.bias) as u64;
+        self.bytes(vaddr, 1)
+            .map(|bytes| NonNull::from(bytes).cast())
+    }
+
+    #[inline]
+    fn guest_addr(&self, vaddr: u64) -> usize {
+        let addr = self.bias.wrapping_add(vaddr as usize);
+        match self.class {
+            Class::ELF32 => addr as u32 as usize,
+            Class::ELF64 => addr,
+        }
+    }
+
+    #[inline]
+    fn word_size(&self) -> usize {
+        match self.class {
+            Class::ELF32 => 4,
+            Class::ELF64 => 8,
+        }
+    }
+
+    /// Returns the mapped bytes at `vaddr`, up to the end of the containing segment.
+    ///
+    /// `None` is returned if fewer than `min_len` bytes are available.
+    fn bytes(&self, vaddr: u64, min_len: u64) -> Option<&[u8]> {
+        let end = self.phdrs.iter().find_map(|phdr| {
+            let end = phdr.p_vaddr.checked_add(phdr.p_memsz)? as u64;
+            (phdr.p_type == PT_LOAD && (phdr.p_vaddr as u64) <= vaddr && vaddr < end).then_some(end)
+        })?;
+        let len = end - vaddr;
+        if len < min_len {
+            return None;
+        }
+        let ptr = self.segments.get_ptr_unchecked::<u8>(vaddr as usize);
+        Some(unsafe { core::slice::from_raw_parts(ptr, len as usize) })
+    }
+
+    /// Returns exactly `len` mapped bytes at `vaddr`.
+    fn table(&self, vaddr: u64, len: u64) -> Result<&[u8]> {
+        self.bytes(vaddr, len)
+            .map(|bytes| &bytes[..len as usize])
+            .ok_or_else(|| parse_dynamic_error("table is outside the mapped segments"))
+    }
+
+    fn strtab(&self, dynamic: &ForeignDynamic) -> Result<StringTable<'_>> {
+        Ok(StringTable::new(self.table(dynamic.strtab, dynamic.strsz)?))
+    }
+
+    fn symtab(&self, dynamic: &ForeignDynamic) -> Result<SymbolTable<'_, AnyEndian>> {
+        let bytes = self
+            .bytes(dynamic.symtab, 0)
+            .ok_or_else(|| parse_dynamic_error("symbol table is outside the mapped segments"))?;
+        Ok(SymbolTable::new(self.endian, self.class, bytes))
+    }
+
+    /// Finds a symbol through the hash table of the object
+    fn lookup(&self, name: &str) -> Option<Symbol> {
+        let dynamic = self.dynamic.as_ref()?;
+        let symtab = self.symtab(dynamic).ok()?;
+        let strtab = self.strtab(dynamic).ok()?;
+        let found = if let Some(gnu_hash) = dynamic.gnu_hash {
+            GnuHashTable::new(self.endian, self.class, self.bytes(gnu_hash, 0)?)
+                .and_then(|table| table.find(name.as_bytes(), &symtab, &strtab))
+        } else {
+            SysVHashTable::new(self.endian, self.class, self.bytes(dynamic.hash?, 0)?)
+                .and_then(|table| table.find(name.as_bytes(), &symtab, &strtab))
+        };
+        found.ok().flatten().map(|(_, sym)| sym)
+    }
+
+    fn read_word(&self, vaddr: u64) -> Result<u64> {
+        let bytes = self
+            .bytes(vaddr, self.word_size() as u64)
+            .ok_or_else(|| relocate_error("relocation target is outside the mapped segments"))?;
+        let val = match (self.endian, self.class) {
+            (AnyEndian::Little, Class::ELF32) => {
+                u64::from(u32::from_le_bytes(bytes[..4].try_into().unwrap()))
+            }
+            (AnyEndian::Big, Class::ELF32) => {
+                u64::from(u32::from_be_bytes(bytes[..4].try_into().unwrap()))
+            }
+            (AnyEndian::Little, Class::ELF64) => u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            (AnyEndian::Big, Class::ELF64) => u64::from_be_bytes(bytes[..8].try_into().unwrap()),
+        };
+        Ok(val)
+    }
+
+    fn write_word(&self, vaddr: u64, val: u64) -> Result<()> {
+        let size = self.word_size();
+        if self.bytes(vaddr, size as u64).is_none() {
+            return Err(relocate_error(
+                "relocation target is outside the mapped segments",
+            ));
+        }
+        let mut buf = [0u8; 8];
+        match (self.endian, self.class) {
+            (AnyEndian::Little, Class::ELF32) => {
+                buf[..4].copy_from_slice(&(val as u32).to_le_bytes())
+            }
+            (AnyEndian::Big, Class::ELF32) => buf[..4].copy_from_slice(&(val as u32).to_be_bytes()),
+            (AnyEndian::Little, Class::ELF64) => buf = val.to_le_bytes(),
+            (AnyEndian::Big, Class::ELF64) => buf = val.to_be_bytes(),
+        }
+        let dst = self.segments.get_mut_ptr_unchecked::<u8>(vaddr as usize);
+        unsafe { core::ptr::copy_nonoverlapping(buf.as_ptr(), dst, size) };
+        Ok(())
+    }
+
+    /// Parses the dynamic section described by `PT_DYNAMIC`
+    fn parse_dynamic(&mut self) -> Result<()> {
+        let Some(phdr) = self.phdrs.iter().find(|phdr| phdr.p_type == PT_DYNAMIC) else {
+            return Ok(());
+        };
+        let data = self.table(phdr.p_vaddr as u64, phdr.p_memsz as u64)?;
+        let mut dynamic = ForeignDynamic::default();
+        for entry in DynamicTable::new(self.endian, self.class, data).iter() {
+            let val = entry.d_val();
+            match entry.d_tag {
+                DT_NULL => break,
+                DT_NEEDED => dynamic.needed.push(val),
+                DT_STRTAB => dynamic.strtab = val,
+                DT_STRSZ => dynamic.strsz = val,
+                DT_SYMTAB => dynamic.symtab = val,
+                DT_HASH => dynamic.hash = Some(val),
+                DT_GNU_HASH => dynamic.gnu_hash = Some(val),
+                DT_REL => dynamic.rel.0 = val,
+                DT_RELSZ => dynamic.rel.1 = val,
+                DT_RELA => dynamic.rela.0 = val,
+                DT_RELASZ => dynamic.rela.1 = val,
+                DT_JMPREL => dynamic.jmprel.0 = val,
+                DT_PLTRELSZ => dynamic.jmprel.1 = val,
+                DT_PLTREL => dynamic.pltrel_is_rela = val == DT_RELA as u64,
+                _ => {}
+            }
+        }
+        self.dynamic = Some(dynamic);
+        Ok(())
+    }
+
+    /// Resolves the value of the symbol referenced by a relocation
+    fn resolve<F>(&self, dynamic: &ForeignDynamic, r_sym: u32, lookup: &F) -> Result<u64>
+    where
+        F: Fn(&str) -> Option<usize>,
+    {
+        let sym = self
+            .symtab(dynamic)?
+            .get(r_sym as usize)
+            .map_err(dynamic_error)?;
+        if !sym.is_undefined() {
+            return Ok(self.guest_addr(sym.st_value) as u64);
+        }
+        let name = self
+            .strtab(dynamic)?
+            .get(sym.st_name as usize)
+            .map_err(dynamic_error)?;
+        match lookup(name) {
+            Some(addr) => Ok(addr as u64),
+            None if sym.st_bind() == STB_WEAK => Ok(0),
+            None => Err(relocate_error(format!(
+                "{}: symbol {name} not found",
+                self.name
+            ))),
+        }
+    }
+
+    /// Applies a single relocation entry
+    fn relocate_one<F>(
+        &self,
+        dynamic: &ForeignDynamic,
+        r_offset: u64,
+        r_sym: u32,
+        r_type: u32,
+        addend: Option<i64>,
+        lookup: &F,
+    ) -> Result<()>
+    where
+        F: Fn(&str) -> Option<usize>,
+    {
+        let kind = reloc_kind(self.machine, self.class, r_type).ok_or_else(|| {
+            relocate_error(format!(
+                "{}: unsupported relocation type {r_type} for machine {}",
+                self.name, self.machine
+            ))
+        })?;
+        // REL entries keep their addend in the target word
+        let implicit = || self.read_word(r_offset);
+        let val = match kind {
+            RelocKind::None => return Ok(()),
+            RelocKind::Relative => {
+                let addend = match addend {
+                    Some(addend) => addend as u64,
+                    None => implicit()?,
+                };
+                (self.bias as u64).wrapping_add(addend)
+            }
+            RelocKind::Absolute => {
+                let addend = match addend {
+                    Some(addend) => addend as u64,
+                    None => implicit()?,
+                };
+                self.resolve(dynamic, r_sym, lookup)?.wrapping_add(addend)
+            }
+            RelocKind::Slot => self
+                .resolve(dynamic, r_sym, lookup)?
+                .wrapping_add(addend.unwrap_or(0) as u64),
+        };
+        self.write_word(r_offset, val)
+    }
+
+    fn relocate<F>(&self, lookup: &F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<usize>,
+    {
+        let Some(dynamic) = &self.dynamic else {
+            return Ok(());
+        };
+        let (rel, rela) = if dynamic.pltrel_is_rela {
+            (None, Some(dynamic.jmprel))
+        } else {
+            (Some(dynamic.jmprel), None)
+        };
+        for (vaddr, size) in [Some(dynamic.rel), rel].into_iter().flatten() {
+            if size == 0 {
+                continue;
+            }
+            for rel in RelIterator::new(self.endian, self.class, self.table(vaddr, size)?) {
+                self.relocate_one(dynamic, rel.r_offset, rel.r_sym, rel.r_type, None, lookup)?;
+            }
+        }
+        for (vaddr, size) in [Some(dynamic.rela), rela].into_iter().flatten() {
+            if size == 0 {
+                continue;
+            }
+            for rela in RelaIterator::new(self.endian, self.class, self.table(vaddr, size)?) {
+                self.relocate_one(
+                    dynamic,
+                    rela.r_offset,
+                    rela.r_sym,
+                    rela.r_type,
+                    Some(rela.r_addend),
+                    lookup,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A mapped but unrelocated foreign ELF object.
+///
+/// Created by [`Loader::load_foreign`]. Symbols can already be looked up
+/// with [`ForeignImage::get`], which makes it possible to load several
+/// objects before resolving the references between them.
+#[derive(Debug)]
+pub struct RawForeign {
+    inner: ForeignImage,
+}
+
+impl Deref for RawForeign {
+    type Target = ForeignImage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl RawForeign {
+    /// Applies the relocations of the object using guest addresses.
+    ///
+    /// Only relative, absolute, `GLOB_DAT` and `JUMP_SLOT` relocations are
+    /// supported; all of them are bound eagerly. Symbols defined by the object
+    /// bind to its own definitions, and undefined symbols are resolved
+    /// through `lookup`, which must return guest addresses. Unresolved weak
+    /// symbols resolve to 0.
+    ///
+    /// # Arguments
+    /// * `lookup` - Resolves undefined symbols to guest addresses.
+    ///
+    /// # Returns
+    /// * `Ok(LoadedForeign)` - The relocated object.
+    /// * `Err(Error)` - If a symbol is missing or a relocation type is unsupported.
+    pub fn relocate<F>(self, lookup: F) -> Result<LoadedForeign>
+    where
+        F: Fn(&str) -> Option<usize>,
+    {
+        self.inner.relocate(&lookup)?;
+        Ok(LoadedForeign { inner: self.inner })
+    }
+}
+
+/// A relocated foreign ELF object, ready to be handed to an emulator.
+#[derive(Debug)]
+pub struct LoadedForeign {
+    inner: ForeignImage,
+}
+
+impl Deref for LoadedForeign {
+    type Target = ForeignImage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<M: Mmap, H: LoadHook<D>, D: Default> Loader<M, H, D> {
+    /// Loads an ELF object of any class, byte order and machine.
+    ///
+    /// Unlike [`Loader::load_dylib`], the object is not checked against the
+    /// host and is never prepared for execution: its `PT_LOAD` segments are
+    /// mapped readable and writable, and no initialization functions, TLS or
+    /// lazy binding are set up. The load hook is not called.
+    ///
+    /// # Arguments
+    /// * `input` - The object to load.
+    /// * `guest_base` - The guest address at which a position-independent object
+    ///   is loaded. It is ignored for `ET_EXEC` objects.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::Loader;
+    ///
+    /// let mut loader = Loader::new();
+    /// let lib = loader
+    ///     .load_foreign("libguest.so", 0x4000_0000)
+    ///     .unwrap()
+    ///     .relocate(|_| None)
+    ///     .unwrap();
+    /// let main = lib.get("main");
+    /// ```
+    pub fn load_foreign<'a, I>(&mut self, input: I, guest_base: usize) -> Result<RawForeign>
+    where
+        I: IntoElfReader<'a>,
+    {
+        let mut object = input.into_reader()?;
+
+        // Parse the ELF header of either class
+        let mut buf = vec![0u8; EI_NIDENT];
+        object.read(&mut buf, 0)?;
+        let ident =
+            parse_ident::<AnyEndian>(&buf).map_err(|err| parse_ehdr_error(format!("{err}")))?;
+        let (endian, class) = (ident.0, ident.1);
+        let ehdr_size = match class {
+            Class::ELF32 => 52,
+            Class::ELF64 => 64,
+        };
+        buf.resize(ehdr_size, 0);
+        object.read(&mut buf[EI_NIDENT..], EI_NIDENT)?;
+        let ehdr = FileHeader::parse_tail(ident, &buf[EI_NIDENT..])
+            .map_err(|err| parse_ehdr_error(format!("{err}")))?;
+        if ehdr.e_type != ET_DYN && ehdr.e_type != ET_EXEC {
+            return Err(parse_ehdr_error("file type mismatch"));
+        }
+
+        // Read the program headers and convert them to the native layout
+        let phentsize = ProgramHeader::validate_entsize(class, ehdr.e_phentsize as usize)
+            .map_err(|err| parse_ehdr_error(format!("{err}")))?;
+        let mut buf = vec![0u8; phentsize * ehdr.e_phnum as usize];
+        object.read(&mut buf, ehdr.e_phoff as usize)?;
+        let phdrs = SegmentTable::new(endian, class, &buf)
+            .iter()
+            .map(|phdr| ElfPhdr::from_parsed(&phdr))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| parse_ehdr_error("segment does not fit the host address space"))?;
+        if phdrs
+            .iter()
+            .any(|phdr| phdr.p_type == PT_LOAD && phdr.p_vaddr.checked_add(phdr.p_memsz).is_none())
+        {
+            return Err(parse_ehdr_error("segment end overflows the address space"));
+        }
+
+        // Map the segments, letting the host pick the address. Guest code cannot
+        // branch into host memory, so no tail is reserved.
+        let mut phdr_segments = ProgramSegments::new(&phdrs, true, object.as_fd().is_some());
+        let extra = ExtraSpace {
+            tail: 0,
+            ..self.extra
+        };
+        let segments = phdr_segments.load_segments::<M>(&mut object, extra, &self.budget)?;
+        for idx in 0..phdr_segments.segments().len() {
+            phdr_segments.override_prot(idx, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE);
+        }
+        phdr_segments.mprotect::<M>()?;
+
+        let mut inner = ForeignImage {
+            name: object.shortname().to_owned(),
+            class,
+            endian,
+            machine: ehdr.e_machine,
+            entry: ehdr.e_entry,
+            bias: if ehdr.e_type == ET_DYN { guest_base } else { 0 },
+            phdrs,
+            dynamic: None,
+            segments,
+        };
+        inner.parse_dynamic()?;
+        Ok(RawForeign { inner })
+    }
+}
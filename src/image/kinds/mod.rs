@@ -1,9 +1,12 @@
 mod dylib;
 mod exec;
+mod foreign;
 mod object;
 
 pub(crate) use exec::StaticImage;
+pub(crate) use object::FnArray;
 
-pub use dylib::{RawDylib, LoadedDylib};
+pub use dylib::{LoadedDylib, NeededLib, RawDylib};
 pub use exec::{RawExec, LoadedExec};
-pub use object::{LoadedObject, RawObject};
+pub use foreign::{ForeignImage, LoadedForeign, RawForeign};
+pub use object::{LoadedObject, RawObject, Visibility};
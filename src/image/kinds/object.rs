@@ -6,15 +6,21 @@
 
 use crate::{
     LoadHook, Loader, Result,
+    elf::ElfShdr,
     image::{ElfCore, LoadedCore, builder::ObjectBuilder, common::CoreInner},
     input::{ElfReader, IntoElfReader},
-    loader::FnHandler,
+    loader::{FnHandler, InitFn},
     os::Mmap,
-    relocation::{Relocatable, RelocationHandler, Relocator, StaticRelocation, SymbolLookup},
+    relocate_error,
+    relocation::{
+        Relocatable, RelocateOptions, RelocationHandler, Relocator, StaticRelocation, SymbolLookup,
+    },
     segment::section::PltGotSection,
 };
-use alloc::boxed::Box;
+use alloc::{boxed::Box, format, string::String, vec::Vec};
 use core::{borrow::Borrow, fmt::Debug, ops::Deref, sync::atomic::AtomicBool};
+use elf::abi::{SHT_FINI_ARRAY, SHT_INIT_ARRAY, SHT_PROGBITS, STB_LOCAL};
+use spin::Mutex;
 
 #[cfg(not(feature = "portable-atomic"))]
 use alloc::sync::Arc;
@@ -52,7 +58,9 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
     }
 
     pub(crate) fn load_object_internal(&mut self, mut object: impl ElfReader) -> Result<RawObject> {
-        let ehdr = self.buf.prepare_ehdr(&mut object).unwrap();
+        let ehdr = self
+            .buf
+            .prepare_ehdr(&mut object, &self.allowed_arch, self.osabi)?;
         self.load_object_impl(ehdr, object)
     }
 }
@@ -66,6 +74,12 @@ impl ObjectBuilder {
     /// # Returns
     /// A RawObject instance ready for relocation
     pub(crate) fn build(self) -> RawObject {
+        // The destructors are collected from several sections once they run
+        let (fini_fn, fini_arrays) = (self.fini_fn, self.fini_arrays);
+        #[allow(clippy::arc_with_non_send_sync)]
+        let fini_handler: FnHandler =
+            Arc::new(move |_, _| fini_fn(None, Some(&FnArray::merge(&fini_arrays))));
+
         // Create the inner component structure
         let inner = CoreInner {
             is_init: AtomicBool::new(false),
@@ -73,10 +87,17 @@ impl ObjectBuilder {
             symtab: self.symtab,
             fini: None,
             fini_array: None,
-            fini_handler: self.fini_fn,
+            fini_handler,
+            post_fini: Mutex::new(None),
             user_data: (),
             dynamic_info: None,
+            tls: None,
             segments: self.segments,
+            registered: AtomicBool::new(false),
+            global: AtomicBool::new(false),
+            isolated: AtomicBool::new(false),
+            #[cfg(feature = "aarch64-pac")]
+            pac: AtomicBool::new(false),
         };
 
         // Construct and return the ElfRelocatable object
@@ -87,8 +108,9 @@ impl ObjectBuilder {
             pltgot: self.pltgot,
             relocation: self.relocation,
             mprotect: self.mprotect,
-            init_array: self.init_array,
+            init_arrays: self.init_arrays,
             init: self.init_fn,
+            visibility: None,
         }
     }
 }
@@ -114,8 +136,113 @@ pub struct RawObject {
     /// Initialization function handler.
     pub(crate) init: FnHandler,
 
-    /// Initialization function array.
-    pub(crate) init_array: Option<&'static [fn()]>,
+    /// Constructor arrays, run once relocated.
+    pub(crate) init_arrays: Vec<FnArray>,
+
+    /// Decides how the global symbols are exposed once relocated.
+    pub(crate) visibility: Option<VisibilityFn>,
+}
+
+type VisibilityFn = Box<dyn Fn(&str) -> Visibility>;
+
+/// A constructor or destructor array section of a relocatable object.
+///
+/// Besides `SHT_INIT_ARRAY`/`SHT_FINI_ARRAY` sections, the legacy `.ctors` and
+/// `.dtors` sections are recognized. Their entries run from last to first, so
+/// they are reversed when merged, like a static linker does when it places them
+/// in the output `.init_array`/`.fini_array`.
+#[derive(Clone, Copy)]
+pub(crate) struct FnArray {
+    /// Entries of the section, relocated in place.
+    entries: &'static [InitFn],
+    /// Priority from a numeric name suffix, lower values are placed first.
+    priority: Option<u32>,
+    /// Whether the section is `.ctors` or `.dtors`.
+    legacy: bool,
+    /// Whether the section holds destructors.
+    dtors: bool,
+}
+
+impl FnArray {
+    /// Recognizes a function array section by its name.
+    pub(crate) fn new(shdr: &ElfShdr, name: &str) -> Option<Self> {
+        // Split off a suffix such as the one of `.init_array.00100`
+        let (base, suffix) = match name.get(1..).and_then(|rest| rest.find('.')) {
+            Some(pos) => (&name[..pos + 1], Some(&name[pos + 2..])),
+            None => (name, None),
+        };
+        let (legacy, dtors) = match (shdr.sh_type, base) {
+            (SHT_INIT_ARRAY, _) => (false, false),
+            (SHT_FINI_ARRAY, _) => (false, true),
+            (SHT_PROGBITS, ".ctors") => (true, false),
+            (SHT_PROGBITS, ".dtors") => (true, true),
+            _ => return None,
+        };
+        let priority = match suffix {
+            Some(suffix) => {
+                let priority = suffix.parse::<u32>().ok()?;
+                // `.ctors.N` and `.dtors.N` count down from the default priority
+                Some(if legacy {
+                    DEFAULT_PRIORITY.checked_sub(priority)?
+                } else {
+                    priority
+                })
+            }
+            None => None,
+        };
+        let entries = unsafe {
+            core::slice::from_raw_parts(
+                shdr.sh_addr as usize as *const InitFn,
+                shdr.sh_size as usize / size_of::<usize>(),
+            )
+        };
+        Some(Self {
+            entries,
+            priority,
+            legacy,
+            dtors,
+        })
+    }
+
+    /// Whether the section holds destructors.
+    #[inline]
+    pub(crate) fn is_dtors(&self) -> bool {
+        self.dtors
+    }
+
+    /// Concatenates the arrays in the order of a linked `.init_array`/`.fini_array`.
+    ///
+    /// Sections with a priority come first, by ascending priority, followed by the
+    /// others in section order.
+    pub(crate) fn merge(arrays: &[FnArray]) -> Vec<InitFn> {
+        let mut arrays = arrays.to_vec();
+        arrays.sort_by_key(|array| (array.priority.is_none(), array.priority));
+        let mut funcs = Vec::new();
+        for array in arrays {
+            if array.legacy {
+                funcs.extend(array.entries.iter().rev());
+            } else {
+                funcs.extend_from_slice(array.entries);
+            }
+        }
+        funcs
+    }
+}
+
+/// Priority of constructors without an explicit one
+const DEFAULT_PRIORITY: u32 = 65535;
+
+/// How a global symbol of a relocatable object is exposed once relocated.
+///
+/// See [`Relocator::visibility`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Visibility {
+    /// The symbol is hidden from lookups by name, as if it were `STB_LOCAL`.
+    Local,
+    /// The symbol keeps its name and binding.
+    Global,
+    /// The symbol can only be looked up under the given name.
+    Rename(String),
 }
 
 impl Deref for RawObject {
@@ -132,6 +259,57 @@ impl RawObject {
     pub fn relocator(self) -> Relocator<Self, (), (), (), (), (), ()> {
         Relocator::new(self)
     }
+
+    /// Sets how the global symbols are exposed once relocated.
+    pub(crate) fn set_visibility(&mut self, visibility: impl Fn(&str) -> Visibility + 'static) {
+        self.visibility = Some(Box::new(visibility));
+    }
+
+    /// Binds every symbol that is not exposed under its own name locally, so that
+    /// the references of the object itself keep resolving to it.
+    ///
+    /// # Returns
+    /// The symbols to rename once relocated, along with their original binding.
+    pub(crate) fn hide_symbols(&self) -> Vec<(usize, u8, String)> {
+        let Some(visibility) = &self.visibility else {
+            return Vec::new();
+        };
+        let symtab = self.core.symtab();
+        let mut renames = Vec::new();
+        for (name, idx) in symtab.named_symbols() {
+            let sym = symtab.symbol_idx(idx).0;
+            if sym.is_undef() || !sym.is_ok_bind() || !sym.is_ok_type() {
+                continue;
+            }
+            let bind = sym.st_bind();
+            match visibility(name) {
+                Visibility::Global => continue,
+                Visibility::Local => {}
+                Visibility::Rename(new_name) => renames.push((idx, bind, new_name)),
+            }
+            // The symbol table is writable until the object is protected
+            unsafe { (*symtab.symbol_ptr(idx)).set_bind(STB_LOCAL) };
+        }
+        renames
+    }
+
+    /// Restores the binding of the renamed symbols and exposes them under their new names.
+    pub(crate) fn rename_symbols(&mut self, renames: Vec<(usize, u8, String)>) -> Result<()> {
+        if renames.is_empty() {
+            return Ok(());
+        }
+        let Some(inner) = Arc::get_mut(&mut self.core.inner) else {
+            return Err(relocate_error(format!(
+                "file [{}]: cannot rename symbols while the object is shared",
+                self.core.name()
+            )));
+        };
+        for (idx, bind, name) in renames {
+            unsafe { (*inner.symtab.symbol_ptr(idx)).set_bind(bind) };
+            inner.symtab.rename_symbol(idx, name);
+        }
+        Ok(())
+    }
 }
 
 impl Debug for RawObject {
@@ -156,8 +334,8 @@ impl Relocatable<()> for RawObject {
         post_find: &PostS,
         _pre_handler: PreH,
         _post_handler: PostH,
-        _lazy: Option<bool>,
         _lazy_scope: Option<LazyS>,
+        options: RelocateOptions<'_, ()>,
     ) -> Result<Self::Output>
     where
         PreS: SymbolLookup + ?Sized,
@@ -166,7 +344,13 @@ impl Relocatable<()> for RawObject {
         PreH: RelocationHandler,
         PostH: RelocationHandler,
     {
-        let inner = self.relocate_impl(scope, pre_find, post_find)?;
+        let inner = self.relocate_impl(
+            scope,
+            pre_find,
+            post_find,
+            options.pre_init,
+            options.post_fini,
+        )?;
         Ok(LoadedObject { inner })
     }
 }
@@ -6,23 +6,36 @@
 
 use crate::{
     LoadHook, Loader, Result,
-    input::IntoElfReader,
+    elf::{Dyn, ElfHeader, ElfPhdr},
+    input::{ElfReader, IntoElfReader},
     os::Mmap,
-    relocation::{Relocatable, RelocationHandler, Relocator, SymbolLookup},
+    relocation::{Relocatable, RelocateOptions, RelocationHandler, Relocator, SymbolLookup},
 };
+use alloc::vec;
 use core::fmt::Debug;
-use elf::abi::{PT_DYNAMIC, PT_INTERP};
+use elf::abi::{DT_NEEDED, DT_NULL, PF_X, PT_DYNAMIC, PT_INTERP, PT_LOAD};
 
 mod builder;
 mod common;
+mod group;
 mod kinds;
+mod namespace;
+#[cfg(feature = "prelink")]
+mod prelink;
 
 pub(crate) use builder::{ImageBuilder, ObjectBuilder};
-pub(crate) use common::{CoreInner, DynamicImage};
-pub(crate) use kinds::StaticImage;
+pub(crate) use common::{CopyRecord, CoreInner, DynamicImage, DynamicInfo};
+pub(crate) use kinds::{FnArray, StaticImage};
 
-pub use common::{ElfCore, ElfCoreRef, LoadedCore, Symbol};
-pub use kinds::{LoadedDylib, LoadedExec, LoadedObject, RawDylib, RawExec, RawObject};
+pub use common::{
+    ElfCore, ElfCoreRef, Extensions, LinkMapView, LoadedCore, OwnedSymbol, SegmentInfo, Symbol,
+};
+pub use group::ModuleGroup;
+pub use kinds::{
+    ForeignImage, LoadedDylib, LoadedExec, LoadedForeign, LoadedObject, NeededLib, RawDylib,
+    RawExec, RawForeign, RawObject, Visibility,
+};
+pub use namespace::Namespace;
 
 /// A mapped but unrelocated ELF image.
 ///
@@ -102,6 +115,15 @@ impl<D: 'static> RawElf<D> {
             RawElf::Object(object) => object.mapped_len(),
         }
     }
+
+    /// Gets the dynamic image of a dylib or a dynamically linked executable
+    pub(crate) fn dynamic(&self) -> Option<&DynamicImage<D>> {
+        match self {
+            RawElf::Dylib(dylib) => Some(&dylib.inner),
+            RawElf::Exec(exec) => exec.dynamic(),
+            RawElf::Object(_) => None,
+        }
+    }
 }
 
 impl<D> LoadedElf<D> {
@@ -204,8 +226,8 @@ impl<D: 'static> Relocatable<D> for RawElf<D> {
         post_find: &PostS,
         pre_handler: PreH,
         post_handler: PostH,
-        lazy: Option<bool>,
         lazy_scope: Option<LazyS>,
+        options: RelocateOptions<'_, D>,
     ) -> Result<Self::Output>
     where
         D: 'static,
@@ -224,8 +246,8 @@ impl<D: 'static> Relocatable<D> for RawElf<D> {
                     post_find,
                     pre_handler,
                     post_handler,
-                    lazy,
                     lazy_scope,
+                    options,
                 )?;
                 Ok(LoadedElf::Dylib(relocated))
             }
@@ -237,8 +259,8 @@ impl<D: 'static> Relocatable<D> for RawElf<D> {
                     post_find,
                     pre_handler,
                     post_handler,
-                    lazy,
                     lazy_scope,
+                    options,
                 )?;
                 Ok(LoadedElf::Exec(relocated))
             }
@@ -250,8 +272,22 @@ impl<D: 'static> Relocatable<D> for RawElf<D> {
                     post_find,
                     pre_handler,
                     post_handler,
-                    lazy,
                     None::<LazyS>, // ElfRelocatable always uses LazyScope<(), ()>, so pass None
+                    RelocateOptions {
+                        lazy: options.lazy,
+                        strict: options.strict,
+                        apply_relro: options.apply_relro,
+                        collect_missing: options.collect_missing,
+                        policy: options.policy,
+                        version_policy: options.version_policy,
+                        scope_index: None,
+                        self_pos: None,
+                        report: None,
+                        executor: options.executor,
+                        // The core of an object has no user data to hand to the hook
+                        pre_init: None,
+                        post_fini: options.post_fini,
+                    },
                 )?;
                 Ok(LoadedElf::Object(relocated))
             }
@@ -262,6 +298,22 @@ impl<D: 'static> Relocatable<D> for RawElf<D> {
 impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
     /// Load an ELF file into memory
     ///
+    /// The kind of the file is decided from its headers:
+    ///
+    /// | `e_type` | Program headers                                           | Loaded as |
+    /// |----------|-----------------------------------------------------------|-----------|
+    /// | `ET_REL` |                                                           | Object    |
+    /// | `ET_EXEC`|                                                           | Exec      |
+    /// | `ET_DYN` | `PT_INTERP` (dynamic PIE)                                 | Exec      |
+    /// | `ET_DYN` | no `PT_DYNAMIC`                                           | Exec      |
+    /// | `ET_DYN` | no `DT_NEEDED`, `e_entry` in an executable `PT_LOAD` (static PIE) | Exec |
+    /// | `ET_DYN` | anything else                                             | Dylib     |
+    ///
+    /// A shared library that depends on nothing and has an entry point is
+    /// therefore loaded as an executable. Use [`load_dylib`](Self::load_dylib)
+    /// or [`load_exec`](Self::load_exec) to load an `ET_DYN` file as one kind
+    /// regardless of its headers.
+    ///
     /// # Arguments
     /// * `object` - The ELF object to load
     ///
@@ -273,22 +325,53 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
         I: IntoElfReader<'a>,
     {
         let mut object = input.into_reader()?;
-        let ehdr = self.buf.prepare_ehdr(&mut object)?;
+        let ehdr = self
+            .buf
+            .prepare_ehdr(&mut object, &self.allowed_arch, self.osabi)?;
 
         match ehdr.e_type {
             elf::abi::ET_REL => Ok(RawElf::Object(self.load_object_internal(object)?)),
             elf::abi::ET_EXEC => Ok(RawElf::Exec(self.load_exec_internal(object)?)),
             elf::abi::ET_DYN => {
                 let phdrs = self.buf.prepare_phdrs(&ehdr, &mut object)?;
-                let has_dynamic = phdrs.iter().any(|p| p.p_type == PT_DYNAMIC);
-                let is_pie = phdrs.iter().any(|p| p.p_type == PT_INTERP) || !has_dynamic;
-                if is_pie {
+                if is_pie(&ehdr, phdrs, &mut object)? {
                     Ok(RawElf::Exec(self.load_exec_internal(object)?))
                 } else {
-                    Ok(RawElf::Dylib(self.load_dylib_internal(object)?))
+                    Ok(RawElf::Dylib(self.load_dylib_internal::<M>(object)?))
                 }
             }
             _ => Ok(RawElf::Exec(self.load_exec_internal(object)?)),
         }
     }
 }
+
+/// Tells whether an `ET_DYN` file is a position-independent executable
+///
+/// See [`Loader::load`] for the rules.
+fn is_pie(ehdr: &ElfHeader, phdrs: &[ElfPhdr], object: &mut impl ElfReader) -> Result<bool> {
+    if phdrs.iter().any(|phdr| phdr.p_type == PT_INTERP) {
+        return Ok(true);
+    }
+    let Some(dynamic) = phdrs.iter().find(|phdr| phdr.p_type == PT_DYNAMIC) else {
+        return Ok(true);
+    };
+    // A static PIE starts in its own code and loads nothing else
+    let entry = ehdr.e_entry;
+    let entry_in_text = entry != 0
+        && phdrs.iter().any(|phdr| {
+            phdr.p_type == PT_LOAD
+                && phdr.p_flags & PF_X != 0
+                && (phdr.p_vaddr..phdr.p_vaddr + phdr.p_memsz).contains(&entry)
+        });
+    if !entry_in_text {
+        return Ok(false);
+    }
+    let mut data = vec![0u8; dynamic.p_filesz as usize];
+    object.read(&mut data, dynamic.p_offset as usize)?;
+    let has_needed = data
+        .chunks_exact(size_of::<Dyn>())
+        .map(|bytes| unsafe { bytes.as_ptr().cast::<Dyn>().read_unaligned() })
+        .take_while(|entry| entry.d_tag != DT_NULL as _)
+        .any(|entry| entry.d_tag == DT_NEEDED as _);
+    Ok(!has_needed)
+}
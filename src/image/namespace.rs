@@ -0,0 +1,206 @@
+//! Isolated linking namespaces
+//!
+//! A [`Namespace`] is the counterpart of a `dlmopen(LM_ID_NEWLM)` link map:
+//! the libraries loaded into it only see each other and the base modules
+//! shared with it, so the same library can be loaded into several namespaces
+//! and every copy keeps its own symbols.
+
+use crate::{
+    LoadHook, Loader, Result,
+    image::{LoadedCore, LoadedDylib, ModuleGroup, NeededLib, Symbol},
+    input::{ElfReader, IntoElfReader},
+    os::Mmap,
+};
+use alloc::vec::Vec;
+use core::{borrow::Borrow, fmt::Debug};
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+/// A set of libraries linked against each other and nothing else.
+///
+/// The `DT_NEEDED` entries of the libraries loaded with
+/// [`load_and_link`](Self::load_and_link) are satisfied by the members of the
+/// namespace and the modules shared with [`share`](Self::share), matched by
+/// name or `DT_SONAME`; only the remaining ones are loaded. Every library is
+/// relocated against the members loaded before it, then the shared modules.
+/// Lazy binding of the members never consults the
+/// [`global_scope`](crate::global_scope), so no symbol is resolved from
+/// another namespace.
+///
+/// The members are finalized like a [`ModuleGroup`] when the namespace is
+/// dropped: dependents before their dependencies. The shared modules are not
+/// finalized by the namespace.
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::{Loader, image::Namespace, input::ElfFile};
+///
+/// let mut loader = Loader::new();
+/// let mut first = Namespace::new();
+/// let mut second = Namespace::new();
+/// for ns in [&mut first, &mut second] {
+///     ns.load_and_link(&mut loader, "plugin/libplugin.so", |needed| {
+///         ElfFile::from_path(&format!("plugin/{}", needed.name()))
+///     })
+///     .unwrap();
+/// }
+/// // Each namespace has its own copy of the plugin's state
+/// let a = unsafe { first.get::<u32>("plugin/libplugin.so", "state") }.unwrap();
+/// let b = unsafe { second.get::<u32>("plugin/libplugin.so", "state") }.unwrap();
+/// assert_ne!(a.into_raw(), b.into_raw());
+/// ```
+pub struct Namespace<D: 'static> {
+    members: ModuleGroup<D>,
+    shared: Vec<LoadedCore<D>>,
+}
+
+impl<D> Default for Namespace<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Debug for Namespace<D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Namespace")
+            .field("members", &self.members)
+            .field(
+                "shared",
+                &self.shared.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<D> Namespace<D> {
+    /// Creates an empty namespace.
+    pub const fn new() -> Self {
+        Self {
+            members: ModuleGroup::new(),
+            shared: Vec::new(),
+        }
+    }
+
+    /// Makes a module loaded outside of the namespace, such as a libc,
+    /// visible to its members.
+    ///
+    /// The module satisfies the `DT_NEEDED` entries naming it and is searched
+    /// after the members when relocating the libraries loaded afterwards.
+    pub fn share(&mut self, module: impl Borrow<LoadedCore<D>>) {
+        let module = module.borrow();
+        if !self.is_shared(module) {
+            self.shared.push(module.clone());
+        }
+    }
+
+    /// Loads a library and the dependencies the namespace is missing, and
+    /// relocates them against the namespace.
+    ///
+    /// # Arguments
+    /// * `loader` - The loader mapping the libraries.
+    /// * `input` - The root library.
+    /// * `resolver` - Maps a [`NeededLib`] request to a reader, as for
+    ///   [`Loader::load_dylib_with_deps`]. It is only called for the names
+    ///   that neither a member nor a shared module answers to.
+    ///
+    /// # Returns
+    /// * `Ok(LoadedDylib)` - The root library, now a member of the namespace.
+    /// * `Err(Error)` - If resolving, loading or relocating any library fails.
+    ///   The libraries relocated before the failure stay members.
+    pub fn load_and_link<'a, M, H, I, F, R>(
+        &mut self,
+        loader: &mut Loader<M, H, D>,
+        input: I,
+        resolver: F,
+    ) -> Result<LoadedDylib<D>>
+    where
+        M: Mmap,
+        H: LoadHook<D>,
+        D: Default,
+        I: IntoElfReader<'a>,
+        F: FnMut(&NeededLib<'_>) -> Result<R>,
+        R: ElfReader,
+    {
+        let libs = loader.load_dylib_tree(input, resolver, |name| self.find(name).is_some())?;
+        let mut root = None;
+        for lib in libs {
+            lib.core_ref().inner.set_isolated();
+            let scope: Vec<_> = self
+                .members
+                .modules()
+                .iter()
+                .chain(&self.shared)
+                .cloned()
+                .collect();
+            let lib = lib.relocator().scope(scope).relocate()?;
+            self.members.insert(&lib);
+            root = Some(lib);
+        }
+        Ok(root.unwrap())
+    }
+
+    /// Gets a symbol of a member.
+    ///
+    /// # Safety
+    /// Users of this API must specify the correct type of the function
+    /// or variable loaded.
+    ///
+    /// # Arguments
+    /// * `lib_name` - The name or `DT_SONAME` of the member
+    /// * `name` - The name of the symbol to look up
+    ///
+    /// # Returns
+    /// * `Some(symbol)` - If the member defines the symbol
+    /// * `None` - If there is no such member or it does not define the symbol
+    pub unsafe fn get<T>(&self, lib_name: &str, name: &str) -> Option<Symbol<'_, T>> {
+        let lib = self
+            .members
+            .modules()
+            .iter()
+            .find(|lib| answers_to(lib, lib_name))?;
+        unsafe { lib.get::<T>(name) }
+    }
+
+    /// Returns the members of the namespace in the order they were relocated.
+    #[inline]
+    pub fn modules(&self) -> &[LoadedCore<D>] {
+        self.members.modules()
+    }
+
+    /// Returns the modules shared with the namespace.
+    #[inline]
+    pub fn shared(&self) -> &[LoadedCore<D>] {
+        &self.shared
+    }
+
+    /// Runs the finalizers of all members, dependents before their dependencies.
+    ///
+    /// See [`ModuleGroup::fini_all`].
+    pub fn fini_all(&mut self) {
+        self.members.fini_all();
+    }
+
+    /// Finds the member or shared module that a `DT_NEEDED` entry names
+    fn find(&self, name: &str) -> Option<&LoadedCore<D>> {
+        self.members
+            .modules()
+            .iter()
+            .chain(&self.shared)
+            .find(|lib| answers_to(lib, name))
+    }
+
+    fn is_shared(&self, module: &LoadedCore<D>) -> bool {
+        self.shared
+            .iter()
+            .any(|m| Arc::ptr_eq(&m.core.inner, &module.core.inner))
+    }
+}
+
+/// Whether `lib` is the library named `name`
+#[inline]
+fn answers_to<D>(lib: &LoadedCore<D>, name: &str) -> bool {
+    lib.name() == name || lib.soname() == Some(name)
+}
@@ -0,0 +1,531 @@
+//! Prelinked images of relocated dynamic libraries
+//!
+//! [`LoadedDylib::serialize_prelinked`] writes the mapped segments of a relocated
+//! library together with the offsets of the words that hold addresses inside the
+//! library. [`Loader::load_prelinked`] maps such a blob again and only adds the
+//! difference between the new and the recorded base to those words, without
+//! parsing relocation tables or resolving symbols.
+//!
+//! Words that point into a dependency are kept as they are, which is why the
+//! blob records a hash of the names and bases of the dependencies and refuses to
+//! load against a different set.
+//!
+//! The blob holds the library as it was before its constructors ran, and they
+//! run again each time it is loaded, so that no state of the process that wrote
+//! it is carried over.
+
+use crate::{
+    LoadHook, Loader, Result, UnknownDynamicPolicy,
+    arch::{EM_ARCH, REL_DTPMOD, REL_DTPOFF, REL_TPOFF},
+    elf::{ElfDynamic, ElfPhdr},
+    image::{ElfCore, LoadedCore, LoadedDylib},
+    input::{ElfReader, IntoElfReader},
+    os::{MapFlags, Mmap, ProtFlags},
+    prelink_error,
+    relocation::for_each_relr,
+    segment::{ElfSegments, MASK, PAGE_SIZE, program::segment_prot},
+};
+use alloc::{string::String, vec::Vec};
+use core::{ptr::NonNull, sync::atomic::Ordering};
+use elf::abi::{PT_GNU_RELRO, PT_LOAD};
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+const MAGIC: u64 = u64::from_le_bytes(*b"ELPRELNK");
+const VERSION: u64 = 1;
+/// Number of words in the fixed part of the header
+const HEADER_WORDS: usize = 13;
+
+/// The fixed part of a prelinked blob, stored as little-endian words
+struct Header {
+    /// Base address of the library when it was written
+    base: usize,
+    /// Offset of the mapped memory from the base
+    offset: usize,
+    /// Length of the mapped memory
+    len: usize,
+    /// Offset of the dynamic section from the base
+    dynamic: usize,
+    /// Offset of the program headers from the base
+    phdrs: usize,
+    /// Number of program headers
+    phnum: usize,
+    /// Hash of the names and bases of the dependencies
+    deps_hash: u64,
+    /// Length of the name in bytes
+    name_len: usize,
+    /// Number of mapped segments
+    segments: usize,
+    /// Number of base-relative words
+    fixups: usize,
+}
+
+impl Header {
+    fn to_words(&self) -> [u64; HEADER_WORDS] {
+        [
+            MAGIC,
+            VERSION,
+            size_of::<usize>() as u64,
+            u64::from(EM_ARCH),
+            self.base as u64,
+            self.offset as u64,
+            self.len as u64,
+            self.dynamic as u64,
+            self.phdrs as u64,
+            self.phnum as u64,
+            self.deps_hash,
+            self.name_len as u64,
+            ((self.segments as u64) << 32) | self.fixups as u64,
+        ]
+    }
+
+    fn from_words(words: [u64; HEADER_WORDS]) -> Result<Self> {
+        if words[0] != MAGIC {
+            return Err(prelink_error("not a prelinked image"));
+        }
+        if words[1] != VERSION {
+            return Err(prelink_error("unsupported prelinked image version"));
+        }
+        if words[2] != size_of::<usize>() as u64 || words[3] != u64::from(EM_ARCH) {
+            return Err(prelink_error(
+                "prelinked image was written for another target",
+            ));
+        }
+        Ok(Self {
+            base: words[4] as usize,
+            offset: words[5] as usize,
+            len: words[6] as usize,
+            dynamic: words[7] as usize,
+            phdrs: words[8] as usize,
+            phnum: words[9] as usize,
+            deps_hash: words[10],
+            name_len: words[11] as usize,
+            segments: (words[12] >> 32) as usize,
+            fixups: words[12] as u32 as usize,
+        })
+    }
+}
+
+/// A mapped range of a prelinked image, relative to the start of the mapping
+struct Segment {
+    start: usize,
+    len: usize,
+    prot: ProtFlags,
+}
+
+/// Hashes the names and bases of `deps` with FNV-1a, which is stable across runs
+fn deps_hash<D>(deps: &[LoadedCore<D>]) -> u64 {
+    const PRIME: u64 = 0x100_0000_01b3;
+    deps.iter()
+        .flat_map(|dep| {
+            let base = (dep.base() as u64).to_le_bytes();
+            dep.name().bytes().chain([0]).chain(base)
+        })
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+}
+
+/// Reads a blob sequentially
+struct BlobReader<R> {
+    reader: R,
+    pos: usize,
+}
+
+impl<R: ElfReader> BlobReader<R> {
+    /// Fails if `count` items of `size` bytes do not fit in the rest of the
+    /// blob, before anything is allocated for them
+    fn check_remaining(&self, count: usize, size: usize) -> Result<()> {
+        let end = count
+            .checked_mul(size)
+            .and_then(|len| len.checked_add(self.pos));
+        match (end, self.reader.len()) {
+            (Some(end), Some(len)) if end <= len => Ok(()),
+            (Some(_), None) => Ok(()),
+            _ => Err(prelink_error("prelinked image is truncated")),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.reader.read(buf, self.pos)?;
+        self.pos += buf.len();
+        Ok(())
+    }
+
+    fn read_words(&mut self, words: &mut [u64]) -> Result<()> {
+        let mut bytes = [0; 8];
+        for word in words {
+            self.read(&mut bytes)?;
+            *word = u64::from_le_bytes(bytes);
+        }
+        Ok(())
+    }
+}
+
+/// Collects the offsets, from the start of the mapping, of the relocated words
+/// that point into the mapping
+fn collect_fixups<D>(core: &ElfCore<D>, dynamic: &ElfDynamic) -> Result<Vec<u64>> {
+    let segments = core.segments();
+    let memory = segments.memory.as_ptr() as usize;
+    let range = memory..memory + segments.len();
+    let base = core.base();
+
+    let mut fixups = Vec::new();
+    let mut add = |r_offset: usize| {
+        let addr = base + r_offset;
+        let value = unsafe { (addr as *const usize).read_unaligned() };
+        if range.contains(&value) {
+            fixups.push((addr - memory) as u64);
+        }
+    };
+    let mut add_rel = |r_type: u32, r_offset: usize| {
+        let is_tls = matches!(r_type, REL_DTPMOD | REL_DTPOFF | REL_TPOFF);
+        #[cfg(target_arch = "riscv64")]
+        let is_tls = is_tls || r_type == crate::arch::REL_TLSDESC;
+        if is_tls {
+            return Err(prelink_error(
+                "thread-local relocations cannot be prelinked",
+            ));
+        }
+        add(r_offset);
+        Ok(())
+    };
+    for rel in dynamic.dynrel.into_iter().chain(dynamic.pltrel).flatten() {
+        add_rel(rel.r_type() as u32, rel.r_offset())?;
+    }
+    for rel in dynamic
+        .alt_dynrel
+        .into_iter()
+        .chain(dynamic.alt_pltrel)
+        .flatten()
+    {
+        add_rel(rel.r_type() as u32, rel.r_offset())?;
+    }
+    for_each_relr(dynamic.relr.unwrap_or(&[]), &mut add);
+    fixups.sort_unstable();
+    fixups.dedup();
+    Ok(fixups)
+}
+
+impl<D> LoadedCore<D> {
+    /// Writes the relocated image of the library as a prelinked blob.
+    ///
+    /// The blob holds the mapped segments as they are now, and the offsets of the
+    /// relocated words that point into the library. It can be mapped again with
+    /// [`Loader::load_prelinked`] as long as the dependencies of the library are
+    /// loaded at the same addresses. Addresses outside the library and its
+    /// dependencies, such as those returned by `pre_find`, are stored as they are.
+    ///
+    /// Constructors may store pointers to the heap or to other state of the
+    /// process, so a library with `DT_INIT` or `DT_INIT_ARRAY` must be written
+    /// before they run, from [`Relocator::pre_init`](crate::relocation::Relocator::pre_init).
+    /// Libraries with thread-local storage or lazy binding cannot be prelinked,
+    /// since their relocated words depend on the state of the process.
+    ///
+    /// # Arguments
+    /// * `writer` - Called with consecutive parts of the blob.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, input::ElfFile};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let blob = Arc::new(Mutex::new(Vec::new()));
+    /// let out = blob.clone();
+    /// let mut loader = Loader::new();
+    /// let lib = loader
+    ///     .load_dylib(ElfFile::from_path("liba.so").unwrap())
+    ///     .unwrap()
+    ///     .relocator()
+    ///     .lazy(false)
+    ///     .pre_init(move |lib| {
+    ///         lib.serialize_prelinked(|bytes| {
+    ///             out.lock().unwrap().extend_from_slice(bytes);
+    ///             Ok(())
+    ///         })
+    ///     })
+    ///     .relocate()
+    ///     .unwrap();
+    /// ```
+    pub fn serialize_prelinked(&self, mut writer: impl FnMut(&[u8]) -> Result<()>) -> Result<()> {
+        let core = &self.core;
+        if core.inner.tls.is_some() {
+            return Err(prelink_error("libraries with TLS cannot be prelinked"));
+        }
+        let info = core
+            .inner
+            .dynamic_info
+            .as_ref()
+            .ok_or_else(|| prelink_error("library has no dynamic section"))?;
+        if !(info.pltrel.is_empty() && info.alt_pltrel.is_empty())
+            && info.lazy_binding.read().is_some()
+        {
+            return Err(prelink_error("lazily bound libraries cannot be prelinked"));
+        }
+        let segments = core.segments();
+        let dynamic = ElfDynamic::new(
+            info.dynamic_ptr.as_ptr(),
+            segments,
+            UnknownDynamicPolicy::Ignore,
+        )?;
+        if info.constructed.load(Ordering::Relaxed)
+            && (dynamic.init_fn.is_some() || dynamic.init_array_fn.is_some_and(|f| !f.is_empty()))
+        {
+            return Err(prelink_error(
+                "constructors already ran, write the library from pre_init",
+            ));
+        }
+        let memory = segments.memory.as_ptr() as usize;
+        let len = segments.len();
+        let base = core.base();
+        let phdrs = core
+            .phdrs()
+            .filter(|phdrs| (memory..memory + len).contains(&(phdrs.as_ptr() as usize)))
+            .ok_or_else(|| prelink_error("program headers are not mapped"))?;
+
+        let loads: Vec<Segment> = phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .map(|phdr| {
+                let start = (base + phdr.p_vaddr as usize) & MASK;
+                let end = (base + (phdr.p_vaddr + phdr.p_memsz) as usize + PAGE_SIZE - 1) & MASK;
+                Segment {
+                    start: start - memory,
+                    len: end - start,
+                    prot: segment_prot(phdr.p_flags),
+                }
+            })
+            .collect();
+        let fixups = collect_fixups(core, &dynamic)?;
+        let name = core.name().as_bytes();
+        let header = Header {
+            base,
+            offset: memory.wrapping_sub(base),
+            len,
+            dynamic: info.dynamic_ptr.as_ptr() as usize - base,
+            phdrs: phdrs.as_ptr() as usize - base,
+            phnum: phdrs.len(),
+            deps_hash: deps_hash(self.deps()),
+            name_len: name.len(),
+            segments: loads.len(),
+            fixups: fixups.len(),
+        };
+
+        let mut words: Vec<u64> = header.to_words().into();
+        words.extend(
+            loads
+                .iter()
+                .flat_map(|seg| [seg.start as u64, seg.len as u64, seg.prot.bits() as u64]),
+        );
+        words.extend(fixups);
+        let words: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        writer(&words)?;
+        writer(name)?;
+        for seg in &loads {
+            writer(unsafe {
+                core::slice::from_raw_parts((memory + seg.start) as *const u8, seg.len)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
+    /// Loads a library from a blob written by [`LoadedDylib::serialize_prelinked`].
+    ///
+    /// The segments are mapped at `preferred_base`, or at the base recorded in the
+    /// blob if it is `None`. If that range is not available, the library is mapped
+    /// elsewhere and the recorded base-relative words are moved by the difference.
+    /// No symbol is resolved and no relocation table is read.
+    ///
+    /// The constructors run through the init handler of the loader once the
+    /// library is mapped, since the blob holds the state from before they ran,
+    /// and the destructors run through its fini handler when the library is
+    /// dropped.
+    ///
+    /// # Arguments
+    /// * `input` - The blob.
+    /// * `preferred_base` - The base address to map the library at.
+    /// * `deps` - The dependencies of the library, in the order of
+    ///   [`LoadedCore::deps`] when the blob was written. They are kept alive by
+    ///   the returned library.
+    ///
+    /// # Returns
+    /// * `Ok(LoadedDylib)` - The library, ready for symbol lookup.
+    /// * `Err(Error::Prelink)` - If the blob was written for another target, is
+    ///   malformed or truncated, or the names or bases of `deps` differ from
+    ///   the recorded ones.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, input::ElfBinary};
+    ///
+    /// # let blob: Vec<u8> = Vec::new();
+    /// let mut loader = Loader::new();
+    /// let lib = loader
+    ///     .load_prelinked(ElfBinary::new("liba.so", &blob), None, &[])
+    ///     .unwrap();
+    /// ```
+    pub fn load_prelinked<'a, I>(
+        &mut self,
+        input: I,
+        preferred_base: Option<usize>,
+        deps: &[LoadedCore<D>],
+    ) -> Result<LoadedDylib<D>>
+    where
+        I: IntoElfReader<'a>,
+    {
+        let mut reader = BlobReader {
+            reader: input.into_reader()?,
+            pos: 0,
+        };
+        let mut words = [0; HEADER_WORDS];
+        reader.read_words(&mut words)?;
+        let header = Header::from_words(words)?;
+        if header.deps_hash != deps_hash(deps) {
+            return Err(prelink_error(
+                "dependencies differ from those the image was prelinked against",
+            ));
+        }
+        if header.len == 0 || header.offset & !MASK != 0 || header.len & !MASK != 0 {
+            return Err(prelink_error("prelinked image is not page aligned"));
+        }
+        self.budget.check(header.len)?;
+        // The counts come from the blob, so they are bounded by the image and
+        // by what is left of the blob before anything is allocated for them
+        if header.segments > header.len / PAGE_SIZE
+            || header.fixups > header.len / size_of::<usize>()
+            || header.name_len > header.len
+        {
+            return Err(prelink_error("prelinked image header is corrupted"));
+        }
+        reader.check_remaining(header.segments * 3 + header.fixups, size_of::<u64>())?;
+
+        let mut words = alloc::vec![0; header.segments * 3];
+        reader.read_words(&mut words)?;
+        let loads: Vec<Segment> = words
+            .chunks_exact(3)
+            .map(|seg| Segment {
+                start: seg[0] as usize,
+                len: seg[1] as usize,
+                prot: ProtFlags::from_bits_retain(seg[2] as _),
+            })
+            .collect();
+        if loads.iter().any(|seg| {
+            seg.start & !MASK != 0
+                || seg
+                    .start
+                    .checked_add(seg.len)
+                    .is_none_or(|end| end > header.len)
+        }) {
+            return Err(prelink_error("segment is outside the prelinked image"));
+        }
+        let mut fixups = alloc::vec![0; header.fixups];
+        reader.read_words(&mut fixups)?;
+        if fixups
+            .iter()
+            .any(|&fixup| fixup as usize > header.len - size_of::<usize>())
+        {
+            return Err(prelink_error("fixup is outside the prelinked image"));
+        }
+        reader.check_remaining(header.name_len, 1)?;
+        let mut name = alloc::vec![0; header.name_len];
+        reader.read(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| prelink_error("name is not UTF-8"))?;
+
+        // Without MAP_FIXED the preferred address is only a hint
+        let hint = preferred_base
+            .unwrap_or(header.base)
+            .wrapping_add(header.offset);
+        let memory = unsafe {
+            M::mmap_anonymous(
+                hint,
+                header.len,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_PRIVATE,
+            )
+        }?;
+        let mut segments = ElfSegments::new(memory, header.len, M::munmap);
+        self.budget.charge(&mut segments);
+        let memory = memory.as_ptr() as usize;
+        let base = memory.wrapping_sub(header.offset);
+
+        for seg in &loads {
+            reader.read(unsafe {
+                core::slice::from_raw_parts_mut((memory + seg.start) as *mut u8, seg.len)
+            })?;
+        }
+        let delta = base.wrapping_sub(header.base);
+        if delta != 0 {
+            for fixup in fixups {
+                let ptr = (memory + fixup as usize) as *mut usize;
+                unsafe { ptr.write_unaligned(ptr.read_unaligned().wrapping_add(delta)) };
+            }
+        }
+
+        if header
+            .phnum
+            .checked_mul(size_of::<ElfPhdr>())
+            .and_then(|size| header.phdrs.checked_add(size))
+            .is_none_or(|end| header.phdrs < header.offset || end > header.offset + header.len)
+        {
+            return Err(prelink_error(
+                "program headers are outside the prelinked image",
+            ));
+        }
+        let phdrs: &'static [ElfPhdr] = unsafe {
+            core::slice::from_raw_parts((base + header.phdrs) as *const ElfPhdr, header.phnum)
+        };
+        unsafe {
+            M::mprotect(memory_ptr(memory), header.len, ProtFlags::PROT_NONE)?;
+            for seg in &loads {
+                M::mprotect(memory_ptr(memory + seg.start), seg.len, seg.prot)?;
+            }
+            for phdr in phdrs.iter().filter(|phdr| phdr.p_type == PT_GNU_RELRO) {
+                let end = phdr
+                    .p_vaddr
+                    .checked_add(phdr.p_memsz)
+                    .filter(|&end| end as usize <= header.offset + header.len)
+                    .ok_or_else(|| prelink_error("RELRO segment is outside the prelinked image"))?;
+                let start = (base + phdr.p_vaddr as usize) & MASK;
+                let end = (base + end as usize) & MASK;
+                if end > start {
+                    M::mprotect(memory_ptr(start), end - start, ProtFlags::PROT_READ)?;
+                }
+            }
+        }
+
+        let (init, fini) = self.fn_handlers();
+        let dynamic = base.wrapping_add(header.dynamic) as *const _;
+        let core = unsafe {
+            ElfCore::from_raw(
+                name,
+                base,
+                dynamic,
+                phdrs,
+                segments,
+                D::default(),
+                Some(fini),
+            )
+        }?;
+        let dynamic = ElfDynamic::new(dynamic, core.segments(), UnknownDynamicPolicy::Ignore)?;
+        init(dynamic.init_fn, dynamic.init_array_fn);
+        if self.registry {
+            crate::registry::register(&core);
+        }
+        Ok(LoadedDylib {
+            inner: LoadedCore {
+                core,
+                deps: Arc::from(deps),
+            },
+        })
+    }
+}
+
+#[inline]
+fn memory_ptr(addr: usize) -> NonNull<core::ffi::c_void> {
+    unsafe { NonNull::new_unchecked(addr as _) }
+}
@@ -1,9 +1,12 @@
 use super::{ElfReader, IntoElfReader};
-use crate::{Result, os::RawFile};
+#[cfg(all(feature = "mmap-file", unix))]
+use crate::os::{DefaultMmap, MapFlags, Mmap, ProtFlags};
+use crate::{Result, io_error, os::RawFile};
 use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
+use core::{ffi::c_void, ptr::NonNull};
 
 /// An ELF object source backed by an in-memory byte slice.
 ///
@@ -65,7 +68,11 @@ impl<'bytes> ElfReader for ElfBinary<'bytes> {
     /// - `Ok(())` - If the read operation was successful.
     /// - `Err` - If the read operation would go beyond the available data.
     fn read(&mut self, buf: &mut [u8], offset: usize) -> crate::Result<()> {
-        buf.copy_from_slice(&self.bytes[offset..offset + buf.len()]);
+        let src = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or_else(|| io_error("read beyond the end of the ELF data"))?;
+        buf.copy_from_slice(src);
         Ok(())
     }
 
@@ -73,15 +80,145 @@ impl<'bytes> ElfReader for ElfBinary<'bytes> {
     fn as_fd(&self) -> Option<isize> {
         None
     }
+
+    /// Returns the in-memory ELF data.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(self.bytes)
+    }
+}
+
+/// An ELF object source backed by a read-only mapping of a whole file.
+///
+/// The file is mapped once when it is opened. Header and section reads are
+/// served from the mapping instead of `read` system calls, and the file
+/// descriptor is still handed to the loader so segments are mapped directly
+/// from the file. The program header table is the one part that is copied,
+/// into a buffer the [`Loader`](crate::Loader) reuses across loads.
+#[cfg(all(feature = "mmap-file", unix))]
+pub struct ElfMmapFile {
+    /// The underlying OS-specific file handle.
+    inner: RawFile,
+    /// Start of the read-only mapping of the file.
+    ptr: NonNull<c_void>,
+    /// Length of the file in bytes.
+    len: usize,
+}
+
+#[cfg(all(feature = "mmap-file", unix))]
+impl ElfMmapFile {
+    /// Opens the file at the given path and maps it read-only.
+    ///
+    /// # Arguments
+    /// - `path` - The path to the ELF file to open.
+    ///
+    /// # Returns
+    /// - `Ok(ElfMmapFile)` - If the file was opened and mapped.
+    /// - `Err` - If the file could not be opened, is empty, or could not be mapped.
+    pub fn from_path(path: impl AsRef<str>) -> Result<Self> {
+        Self::map(RawFile::from_path(path.as_ref())?)
+    }
+
+    /// Maps a file from an owned file descriptor.
+    ///
+    /// # Safety
+    /// The same requirements as [`ElfFile::from_owned_fd`] apply.
+    ///
+    /// # Arguments
+    /// - `path` - The file path, used for identification and error reporting.
+    /// - `raw_fd` - The raw file descriptor for the open ELF file.
+    ///
+    /// # Returns
+    /// - `Ok(ElfMmapFile)` - If the file was mapped.
+    /// - `Err` - If the file is empty or could not be mapped.
+    pub unsafe fn from_owned_fd(path: &str, raw_fd: i32) -> Result<Self> {
+        Self::map(RawFile::from_owned_fd(path, raw_fd))
+    }
+
+    fn map(inner: RawFile) -> Result<Self> {
+        let len = inner.size()?;
+        if len == 0 {
+            return Err(io_error("file is empty"));
+        }
+        let mut need_copy = false;
+        let ptr = unsafe {
+            DefaultMmap::mmap(
+                None,
+                len,
+                ProtFlags::PROT_READ,
+                MapFlags::MAP_PRIVATE,
+                0,
+                inner.as_fd(),
+                &mut need_copy,
+            )?
+        };
+        if need_copy {
+            // The mapping is read-only, so it can't be filled by hand
+            unsafe { DefaultMmap::munmap(ptr, len)? };
+            return Err(io_error("file mapping is not supported"));
+        }
+        Ok(Self { inner, ptr, len })
+    }
+
+    /// Returns the contents of the file.
+    pub fn bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr().cast(), self.len) }
+    }
+}
+
+#[cfg(all(feature = "mmap-file", unix))]
+impl Drop for ElfMmapFile {
+    fn drop(&mut self) {
+        let _ = unsafe { DefaultMmap::munmap(self.ptr, self.len) };
+    }
+}
+
+#[cfg(all(feature = "mmap-file", unix))]
+impl ElfReader for ElfMmapFile {
+    /// Returns the name of the ELF file.
+    fn file_name(&self) -> &str {
+        self.inner.file_name()
+    }
+
+    /// Copies data out of the file mapping.
+    fn read(&mut self, buf: &mut [u8], offset: usize) -> Result<()> {
+        let src = offset
+            .checked_add(buf.len())
+            .and_then(|end| self.bytes().get(offset..end))
+            .ok_or_else(|| io_error("read offset out of bounds"))?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+
+    /// Returns the raw file descriptor, used to map the segments.
+    fn as_fd(&self) -> Option<isize> {
+        self.inner.as_fd()
+    }
+
+    /// Returns the contents of the file.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(self.bytes())
+    }
+}
+
+/// The file access strategy of an [`ElfFile`].
+enum FileBackend {
+    /// Buffered reads through the file descriptor
+    Read(RawFile),
+    /// Reads served from a mapping of the whole file
+    #[cfg(all(feature = "mmap-file", unix))]
+    Mmap(ElfMmapFile),
 }
 
 /// An ELF object source backed by a file on the filesystem.
 ///
 /// This implementation uses standard file I/O to read ELF data. It also
 /// provides access to the underlying file descriptor for memory mapping.
+///
+/// With the `mmap-file` feature on Unix, files opened with
+/// [`ElfFile::from_path`] are read through an [`ElfMmapFile`] instead.
 pub struct ElfFile {
     /// The underlying OS-specific file handle.
-    inner: RawFile,
+    inner: FileBackend,
 }
 
 impl ElfFile {
@@ -102,7 +239,7 @@ impl ElfFile {
     /// A new [`ElfFile`] instance.
     pub unsafe fn from_owned_fd(path: &str, raw_fd: i32) -> Self {
         ElfFile {
-            inner: RawFile::from_owned_fd(path, raw_fd),
+            inner: FileBackend::Read(RawFile::from_owned_fd(path, raw_fd)),
         }
     }
 
@@ -119,16 +256,31 @@ impl ElfFile {
     /// - `Ok(ElfFile)` - If the file was successfully opened and is accessible.
     /// - `Err` - If the file could not be opened or accessed.
     pub fn from_path(path: impl AsRef<str>) -> Result<Self> {
-        Ok(ElfFile {
-            inner: RawFile::from_path(path.as_ref())?,
-        })
+        #[cfg(all(feature = "mmap-file", unix))]
+        let inner = FileBackend::Mmap(ElfMmapFile::from_path(path)?);
+        #[cfg(not(all(feature = "mmap-file", unix)))]
+        let inner = FileBackend::Read(RawFile::from_path(path.as_ref())?);
+        Ok(ElfFile { inner })
+    }
+}
+
+#[cfg(all(feature = "mmap-file", unix))]
+impl From<ElfMmapFile> for ElfFile {
+    fn from(file: ElfMmapFile) -> Self {
+        ElfFile {
+            inner: FileBackend::Mmap(file),
+        }
     }
 }
 
 impl ElfReader for ElfFile {
     /// Returns the name of the ELF file.
     fn file_name(&self) -> &str {
-        self.inner.file_name()
+        match &self.inner {
+            FileBackend::Read(file) => file.file_name(),
+            #[cfg(all(feature = "mmap-file", unix))]
+            FileBackend::Mmap(file) => file.file_name(),
+        }
     }
 
     /// Reads data from the file-based ELF object.
@@ -145,7 +297,11 @@ impl ElfReader for ElfFile {
     /// - `Ok(())` - If the read operation was successful.
     /// - `Err` - If the read operation failed (e.g., I/O error, invalid offset).
     fn read(&mut self, buf: &mut [u8], offset: usize) -> Result<()> {
-        self.inner.read(buf, offset)
+        match &mut self.inner {
+            FileBackend::Read(file) => file.read(buf, offset),
+            #[cfg(all(feature = "mmap-file", unix))]
+            FileBackend::Mmap(file) => file.read(buf, offset),
+        }
     }
 
     /// Returns the raw file descriptor for the underlying file.
@@ -153,7 +309,437 @@ impl ElfReader for ElfFile {
     /// This enables memory mapping optimizations when available, as the
     /// file descriptor can be used directly with mmap-like operations.
     fn as_fd(&self) -> Option<isize> {
-        self.inner.as_fd()
+        match &self.inner {
+            FileBackend::Read(file) => file.as_fd(),
+            #[cfg(all(feature = "mmap-file", unix))]
+            FileBackend::Mmap(file) => file.as_fd(),
+        }
+    }
+
+    /// Returns the contents of the file if it is mapped.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.inner {
+            FileBackend::Read(_) => None,
+            #[cfg(all(feature = "mmap-file", unix))]
+            FileBackend::Mmap(file) => file.as_bytes(),
+        }
+    }
+}
+
+#[cfg(all(feature = "mmap-file", unix))]
+impl<'a> IntoElfReader<'a> for ElfMmapFile {
+    type Reader = ElfMmapFile;
+
+    fn into_reader(self) -> Result<Self::Reader> {
+        Ok(self)
+    }
+}
+
+/// An ELF image that has already been laid out in memory by the caller.
+///
+/// Unlike [`ElfBinary`], the image is not copied: every segment must already
+/// be present at `base + p_vaddr`, with the ELF header and program headers
+/// mapped at `base` as they are in a regular shared object. Use it with
+/// [`Loader::load_dylib_premapped`](crate::Loader::load_dylib_premapped).
+///
+/// The loader never unmaps the image and never changes its memory protections.
+#[derive(Debug)]
+pub struct ElfPremapped {
+    /// The name assigned to this ELF object.
+    name: String,
+    /// Start of the image; `p_vaddr` offsets are relative to this address.
+    base: NonNull<c_void>,
+    /// Length of the image in bytes.
+    len: usize,
+}
+
+impl ElfPremapped {
+    /// Wraps an already mapped ELF image.
+    ///
+    /// # Safety
+    /// The caller must ensure that:
+    /// - `base..base + len` is mapped and contains the complete image, with every
+    ///   `PT_LOAD` segment placed at its final `p_vaddr` offset.
+    /// - The memory that needs relocating is writable during relocation.
+    /// - The memory stays mapped for as long as the loaded object is alive.
+    ///
+    /// # Arguments
+    /// - `name` - A string identifier for the ELF object.
+    /// - `base` - The address that `p_vaddr` 0 is mapped to.
+    /// - `len` - The length of the image in bytes.
+    ///
+    /// # Returns
+    /// A new [`ElfPremapped`] instance.
+    pub unsafe fn new(name: &str, base: NonNull<c_void>, len: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            len,
+        }
+    }
+
+    /// Returns the name of the ELF object.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the start address of the image.
+    pub fn base(&self) -> usize {
+        self.base.as_ptr() as usize
+    }
+
+    /// Returns the length of the image in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the image is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Splits the image into its name, start address and length.
+    pub(crate) fn into_parts(self) -> (String, NonNull<c_void>, usize) {
+        (self.name, self.base, self.len)
+    }
+}
+
+/// An ELF object source backed by a seekable stream, such as a socket wrapper
+/// or a remote object store client.
+///
+/// Only the ELF header, the program headers and the contents of the loadable
+/// segments are read; the rest of the stream is skipped. Reads that continue
+/// where the previous one ended do not seek.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ElfStream<R> {
+    /// The name assigned to this ELF object.
+    name: String,
+    /// The underlying stream.
+    inner: R,
+    /// Current position of the stream, if known.
+    pos: Option<u64>,
+    /// Length of the stream, if known.
+    len: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + std::io::Seek> ElfStream<R> {
+    /// Wraps a seekable stream.
+    ///
+    /// # Arguments
+    /// - `name` - A string identifier for the ELF object.
+    /// - `inner` - The stream containing the ELF data, starting at offset 0.
+    ///
+    /// # Returns
+    /// A new [`ElfStream`] instance.
+    pub fn new(name: &str, inner: R) -> Self {
+        Self {
+            name: name.to_string(),
+            inner,
+            pos: None,
+            len: None,
+        }
+    }
+
+    /// Sets the length of the stream, such as the `Content-Length` of a
+    /// download.
+    ///
+    /// A stream that ends early is then rejected with
+    /// [`Error::Truncated`](crate::Error::Truncated) before any segment is
+    /// mapped, instead of failing halfway through copying one.
+    pub fn with_len(mut self, len: usize) -> Self {
+        self.len = Some(len);
+        self
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read + std::io::Seek> ElfReader for ElfStream<R> {
+    fn file_name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&mut self, buf: &mut [u8], offset: usize) -> Result<()> {
+        let offset = offset as u64;
+        if self.pos != Some(offset) {
+            self.pos = None;
+            self.inner
+                .seek(std::io::SeekFrom::Start(offset))
+                .map_err(|err| {
+                    crate::io_error(alloc::format!("failed to seek: {err}")).with_source(err)
+                })?;
+        }
+        self.inner.read_exact(buf).map_err(|err| {
+            crate::io_error(alloc::format!("failed to read: {err}")).with_source(err)
+        })?;
+        self.pos = Some(offset + buf.len() as u64);
+        Ok(())
+    }
+
+    fn as_fd(&self) -> Option<isize> {
+        None
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.len
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, R: std::io::Read + std::io::Seek + 'a> IntoElfReader<'a> for ElfStream<R> {
+    type Reader = ElfStream<R>;
+
+    fn into_reader(self) -> Result<Self::Reader> {
+        Ok(self)
+    }
+}
+
+/// An ELF object source backed by a file descriptor without a known path,
+/// such as a `memfd` received over a unix socket.
+///
+/// Headers are read with `pread`, so the file offset of the descriptor is left
+/// alone, and the descriptor is handed to the loader so segments are mapped
+/// directly from it. The file is never read into a buffer as a whole.
+#[cfg(all(feature = "std", unix))]
+#[derive(Debug)]
+pub struct ElfFd {
+    /// The name assigned to this ELF object.
+    name: String,
+    /// The file the descriptor refers to.
+    file: std::fs::File,
+    /// Size of the file, if it could be queried.
+    len: Option<usize>,
+}
+
+#[cfg(all(feature = "std", unix))]
+impl ElfFd {
+    /// Wraps an owned file descriptor.
+    ///
+    /// Without a `name`, the target of `/proc/self/fd/<fd>` is used, such as
+    /// `/memfd:plugin (deleted)` for a `memfd`, or that path itself if the link
+    /// can't be read.
+    ///
+    /// # Arguments
+    /// - `name` - A string identifier for the ELF object, if there is one.
+    /// - `fd` - The file descriptor of the ELF object, readable at any offset.
+    ///
+    /// # Returns
+    /// A new [`ElfFd`] instance.
+    pub fn new(name: Option<&str>, fd: std::os::fd::OwnedFd) -> Self {
+        use std::os::fd::AsRawFd;
+
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => {
+                let link = alloc::format!("/proc/self/fd/{}", fd.as_raw_fd());
+                match std::fs::read_link(&link) {
+                    Ok(target) => target.to_string_lossy().into_owned(),
+                    Err(_) => link,
+                }
+            }
+        };
+        let file = std::fs::File::from(fd);
+        let len = file.metadata().ok().map(|metadata| metadata.len() as usize);
+        Self { name, file, len }
+    }
+
+    /// Wraps a raw file descriptor, taking ownership of it.
+    ///
+    /// # Safety
+    /// `fd` must be an open file descriptor that no other code closes or
+    /// uses as an owned descriptor while this object exists. It is closed when
+    /// the [`ElfFd`] is dropped.
+    ///
+    /// # Arguments
+    /// - `name` - A string identifier for the ELF object, if there is one.
+    /// - `fd` - The raw file descriptor of the ELF object.
+    ///
+    /// # Returns
+    /// A new [`ElfFd`] instance.
+    pub unsafe fn from_raw_fd(name: Option<&str>, fd: std::os::fd::RawFd) -> Self {
+        use std::os::fd::FromRawFd;
+
+        Self::new(name, unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) })
+    }
+
+    /// Returns the file descriptor.
+    pub fn into_inner(self) -> std::os::fd::OwnedFd {
+        self.file.into()
+    }
+}
+
+#[cfg(all(feature = "std", unix))]
+impl ElfReader for ElfFd {
+    fn file_name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&mut self, buf: &mut [u8], offset: usize) -> Result<()> {
+        use std::os::unix::fs::FileExt;
+
+        self.file.read_exact_at(buf, offset as u64).map_err(|err| {
+            crate::io_error(alloc::format!("failed to read: {err}")).with_source(err)
+        })
+    }
+
+    fn as_fd(&self) -> Option<isize> {
+        use std::os::fd::AsRawFd;
+
+        Some(self.file.as_raw_fd() as isize)
+    }
+
+    fn len(&self) -> Option<usize> {
+        self.len
+    }
+}
+
+#[cfg(all(feature = "std", unix))]
+impl<'a> IntoElfReader<'a> for ElfFd {
+    type Reader = ElfFd;
+
+    fn into_reader(self) -> Result<Self::Reader> {
+        Ok(self)
+    }
+}
+
+/// An ELF object source backed by a compressed stream.
+///
+/// The stream is decompressed into an internal buffer only as far as the highest
+/// offset requested so far. The loader reads the headers first and the segments
+/// roughly in file order, so trailing sections such as debug information are
+/// usually never decompressed.
+#[cfg(feature = "compress")]
+pub struct CompressedReader<'a> {
+    /// The name assigned to this ELF object.
+    name: String,
+    /// The decompressing stream.
+    decoder: alloc::boxed::Box<dyn std::io::Read + 'a>,
+    /// The data decompressed so far.
+    buf: Vec<u8>,
+    /// Whether the stream has been decompressed completely.
+    eof: bool,
+}
+
+#[cfg(feature = "compress")]
+impl<'a> CompressedReader<'a> {
+    /// Minimum number of bytes decompressed at a time
+    const CHUNK: usize = 0x10000;
+
+    /// Wraps a zstd compressed stream.
+    ///
+    /// # Arguments
+    /// - `inner` - The compressed data.
+    ///
+    /// # Returns
+    /// A new [`CompressedReader`], or an error if the zstd decoder cannot be created.
+    pub fn zstd<R: std::io::Read + 'a>(inner: R) -> Result<Self> {
+        let decoder = zstd::Decoder::new(inner).map_err(|err| {
+            io_error(alloc::format!("failed to create zstd decoder: {err}")).with_source(err)
+        })?;
+        Ok(Self::new(alloc::boxed::Box::new(decoder)))
+    }
+
+    /// Wraps a gzip compressed stream.
+    ///
+    /// # Arguments
+    /// - `inner` - The compressed data.
+    ///
+    /// # Returns
+    /// A new [`CompressedReader`]. An invalid gzip header is reported by the first read.
+    pub fn gzip<R: std::io::Read + 'a>(inner: R) -> Self {
+        Self::new(alloc::boxed::Box::new(flate2::read::GzDecoder::new(inner)))
+    }
+
+    fn new(decoder: alloc::boxed::Box<dyn std::io::Read + 'a>) -> Self {
+        Self {
+            name: "<compressed>".to_string(),
+            decoder,
+            buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// Sets the name of the ELF object, which defaults to `<compressed>`.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Returns the number of bytes decompressed so far.
+    pub fn decompressed_so_far(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Decompresses until at least `end` bytes are available or the stream ends
+    fn fill(&mut self, end: usize) -> Result<()> {
+        use std::io::Read;
+
+        while !self.eof && self.buf.len() < end {
+            let want = (end - self.buf.len()).max(Self::CHUNK);
+            let got = (&mut self.decoder)
+                .take(want as u64)
+                .read_to_end(&mut self.buf)
+                .map_err(|err| {
+                    io_error(alloc::format!("failed to decompress: {err}")).with_source(err)
+                })?;
+            self.eof = got < want;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compress")]
+impl core::fmt::Debug for CompressedReader<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CompressedReader")
+            .field("name", &self.name)
+            .field("decompressed", &self.buf.len())
+            .field("eof", &self.eof)
+            .finish()
+    }
+}
+
+#[cfg(feature = "compress")]
+impl ElfReader for CompressedReader<'_> {
+    fn file_name(&self) -> &str {
+        &self.name
+    }
+
+    fn read(&mut self, buf: &mut [u8], offset: usize) -> Result<()> {
+        let end = offset
+            .checked_add(buf.len())
+            .ok_or_else(|| io_error("read offset out of bounds"))?;
+        self.fill(end)?;
+        let data = self
+            .buf
+            .get(offset..end)
+            .ok_or_else(|| io_error("read offset out of bounds"))?;
+        buf.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn as_fd(&self) -> Option<isize> {
+        None
+    }
+
+    /// Returns the decompressed data once the whole stream has been decompressed.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        self.eof.then_some(self.buf.as_slice())
+    }
+}
+
+#[cfg(feature = "compress")]
+impl<'a> IntoElfReader<'a> for CompressedReader<'a> {
+    type Reader = CompressedReader<'a>;
+
+    fn into_reader(self) -> Result<Self::Reader> {
+        Ok(self)
     }
 }
 
@@ -178,9 +764,7 @@ impl<'a> ElfReader for &'a [u8] {
     /// - `Err` - If the offset is out of bounds.
     fn read(&mut self, buf: &mut [u8], offset: usize) -> Result<()> {
         if offset + buf.len() > self.len() {
-            return Err(crate::Error::Io {
-                msg: "read offset out of bounds".into(),
-            });
+            return Err(crate::io_error("read offset out of bounds"));
         }
         buf.copy_from_slice(&self[offset..offset + buf.len()]);
         Ok(())
@@ -190,6 +774,11 @@ impl<'a> ElfReader for &'a [u8] {
     fn as_fd(&self) -> Option<isize> {
         None
     }
+
+    /// Returns the slice itself.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(self)
+    }
 }
 
 // Implementation for string slices (file paths)
@@ -5,7 +5,15 @@
 //! to allow uniform handling of different ELF object types during the loading
 //! and relocation process.
 
-pub use backend::{ElfBinary, ElfFile};
+pub use backend::{ElfBinary, ElfFile, ElfPremapped};
+#[cfg(all(feature = "mmap-file", unix))]
+pub use backend::ElfMmapFile;
+#[cfg(feature = "compress")]
+pub use backend::CompressedReader;
+#[cfg(feature = "std")]
+pub use backend::ElfStream;
+#[cfg(all(feature = "std", unix))]
+pub use backend::ElfFd;
 pub use traits::{ElfReader, IntoElfReader};
 
 mod backend;
@@ -1,4 +1,5 @@
 use crate::Result;
+use core::ops::Range;
 
 /// A trait for reading ELF data from various sources.
 ///
@@ -21,6 +22,43 @@ pub trait ElfReader {
     /// Returns `None` for memory-based sources.
     fn as_fd(&self) -> Option<isize>;
 
+    /// Returns the whole ELF object if it is addressable in memory.
+    ///
+    /// When available, the loader parses the ELF header and section contents in
+    /// place instead of reading them into temporary buffers. The program header
+    /// table is still copied into the loader's scratch buffer, as the reader is
+    /// moved into the image while the headers are in use. The default returns
+    /// `None`.
+    fn as_bytes(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Returns the size of the ELF object in bytes, if it is known.
+    ///
+    /// The loader uses it to reject headers that describe tables past the end
+    /// of the object before reading them, and segments or dynamic tables that
+    /// extend past it with [`Error::Truncated`](crate::Error::Truncated)
+    /// before mapping anything. The default returns the length of
+    /// [`as_bytes`](ElfReader::as_bytes).
+    fn len(&self) -> Option<usize> {
+        self.as_bytes().map(<[u8]>::len)
+    }
+
+    /// Announces the file ranges the loader is about to read.
+    ///
+    /// The loader calls this once per object, before copying segment contents,
+    /// with the file ranges of all `PT_LOAD` segments (or allocated sections of
+    /// relocatable objects) in segment order. Readers backed by slow or remote
+    /// storage can fetch just these ranges ahead of the [`read`](ElfReader::read)
+    /// calls that copy them. The default does nothing.
+    ///
+    /// # Arguments
+    /// * `ranges` - The byte ranges within the ELF source.
+    fn read_hint(&mut self, ranges: &[Range<usize>]) -> Result<()> {
+        let _ = ranges;
+        Ok(())
+    }
+
     /// Returns the short name of the ELF object (the filename without the path).
     fn shortname(&self) -> &str {
         let name = self.file_name();
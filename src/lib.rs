@@ -58,6 +58,7 @@
 //! }
 //! ```
 #![no_std]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 #![warn(
     clippy::unnecessary_wraps,
     clippy::unnecessary_lazy_evaluations,
@@ -78,6 +79,8 @@
     clippy::uninit_vec
 )]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 /// Compile-time check for supported architectures
 #[cfg(not(any(
@@ -93,20 +96,39 @@ compile_error!(
     "Unsupported target architecture. Supported architectures: x86_64, aarch64, riscv64, riscv32, loongarch64, x86, arm"
 );
 
+mod allocator;
 pub mod arch;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "debugging")]
+pub mod debug;
 pub mod elf;
 mod error;
 pub mod image;
 pub mod input;
 mod loader;
 pub mod os;
+#[cfg(feature = "plugin-abi")]
+pub mod plugin;
+mod registry;
 pub mod relocation;
 mod segment;
+pub mod tls;
+#[cfg(feature = "trace")]
+pub mod trace;
 
 pub(crate) use error::*;
 
-pub use error::Error;
-pub use loader::{LoadHook, LoadHookContext, Loader};
+pub use error::{
+    BoxedSource, Error, ErrorKind, MissingSymbol, OsError, RelocationErrorContext, RelocationTable,
+};
+pub use loader::{
+    CallConv, ElfKind, ExecStackPolicy, InitFn, InitHandler, InitParams, LoadHook, LoadHookContext,
+    Loader, OsAbiPolicy, UnknownDynamicPolicy,
+};
+pub use registry::{PhdrInfo, iterate_phdr};
+pub use relocation::global_scope;
+pub use segment::{UnmapErrorHandler, set_unmap_error_handler};
 
 /// A type alias for `Result`s returned by `elf_loader` functions.
 ///
@@ -1,33 +1,81 @@
 use crate::{
     Result,
-    elf::{EHDR_SIZE, ElfHeader, ElfPhdr, ElfShdr},
-    image::{DynamicImage, ImageBuilder, ObjectBuilder, RawObject, StaticImage},
-    input::ElfReader,
-    os::{DefaultMmap, Mmap},
-    segment::{ElfSegments, SegmentBuilder, program::ProgramSegments, section::SectionSegments},
+    allocator::{AllocVec, LoaderAlloc, NulStr},
+    elf::{EHDR_SIZE, ElfHeader, ElfNotes, ElfPhdr, ElfShdr, check_file_ranges},
+    image::{DynamicImage, Extensions, ImageBuilder, ObjectBuilder, RawObject, StaticImage},
+    input::{ElfReader, IntoElfReader},
+    os::{DefaultMmap, Mmap, ProtFlags},
+    parse_ehdr_error,
+    relocation::Auditor,
+    segment::{
+        ElfSegments, ExtraSpace, MapBudget, SegmentBuilder, program::ProgramSegments,
+        section::SectionSegments,
+    },
+    tls::TlsAllocator,
 };
-use alloc::{borrow::ToOwned, boxed::Box, vec::Vec};
-use core::marker::PhantomData;
+use alloc::{boxed::Box, format, vec::Vec};
+use core::{
+    any::Any,
+    ffi::{c_char, c_int},
+    marker::PhantomData,
+    ptr::null,
+};
+use elf::abi::{ELFOSABI_GNU, ELFOSABI_SYSV, ET_DYN, ET_EXEC, ET_REL};
 
 #[cfg(not(feature = "portable-atomic"))]
 use alloc::sync::Arc;
 #[cfg(feature = "portable-atomic")]
 use portable_atomic_util::Arc;
 
+/// Scratch buffer for the headers read during loading.
+///
+/// It is made of words so that the headers can be viewed in place, and it
+/// only ever grows, so that a loader reused across loads stops allocating
+/// once it has seen its largest header table.
 pub(crate) struct ElfBuf {
-    buf: Vec<u8>,
+    buf: AllocVec<usize>,
 }
 
 impl ElfBuf {
-    fn new() -> Self {
-        let mut buf = Vec::new();
-        buf.resize(EHDR_SIZE, 0);
-        ElfBuf { buf }
+    fn new(alloc: &LoaderAlloc) -> Self {
+        let mut buf = ElfBuf { buf: alloc.vec() };
+        buf.reserve(EHDR_SIZE);
+        buf
+    }
+
+    /// Makes room for at least `size` bytes.
+    fn reserve(&mut self, size: usize) {
+        let words = size.div_ceil(size_of::<usize>());
+        if words > self.buf.len() {
+            self.buf.resize(words, 0);
+        }
+    }
+
+    /// Returns the first `size` bytes of the buffer, growing it if needed.
+    fn bytes_mut(&mut self, size: usize) -> &mut [u8] {
+        self.reserve(size);
+        unsafe { core::slice::from_raw_parts_mut(self.buf.as_mut_ptr().cast::<u8>(), size) }
     }
 
-    pub(crate) fn prepare_ehdr(&mut self, object: &mut impl ElfReader) -> Result<ElfHeader> {
-        object.read(&mut self.buf[..EHDR_SIZE], 0)?;
-        ElfHeader::new(&self.buf).cloned()
+    pub(crate) fn prepare_ehdr(
+        &mut self,
+        object: &mut impl ElfReader,
+        allowed_arch: &[u16],
+        osabi: OsAbiPolicy,
+    ) -> Result<ElfHeader> {
+        // Parse the header in place when the object is in memory and suitably aligned
+        let ehdr = if let Some(bytes) = object.as_bytes()
+            && bytes.len() >= EHDR_SIZE
+            && bytes.as_ptr().cast::<ElfHeader>().is_aligned()
+        {
+            ElfHeader::new(bytes, allowed_arch)?.clone()
+        } else {
+            let bytes = self.bytes_mut(EHDR_SIZE);
+            object.read(bytes, 0)?;
+            ElfHeader::new(bytes, allowed_arch)?.clone()
+        };
+        osabi.check(&ehdr, object.shortname())?;
+        Ok(ehdr)
     }
 
     pub(crate) fn prepare_phdrs(
@@ -35,18 +83,23 @@ impl ElfBuf {
         ehdr: &ElfHeader,
         object: &mut impl ElfReader,
     ) -> Result<&[ElfPhdr]> {
-        let (phdr_start, phdr_end) = ehdr.phdr_range();
+        let phnum = ehdr.phnum(|buf, offset| object.read(buf, offset))?;
+        let (phdr_start, phdr_end) = ehdr.checked_phdr_range(phnum, object.len())?;
         let size = phdr_end - phdr_start;
-        if size > self.buf.len() {
-            self.buf.resize(size, 0);
-        }
-        object.read(&mut self.buf[..size], phdr_start)?;
-        unsafe {
-            Ok(core::slice::from_raw_parts(
-                self.buf.as_ptr().cast::<ElfPhdr>(),
-                (phdr_end - phdr_start) / size_of::<ElfPhdr>(),
-            ))
+        // Copied even from in-memory objects: the headers outlive the borrow of
+        // the reader, which is moved into the image before they are parsed
+        let bytes = self.bytes_mut(size);
+        object.read(bytes, phdr_start)?;
+        let phdrs = unsafe {
+            core::slice::from_raw_parts(
+                bytes.as_ptr().cast::<ElfPhdr>(),
+                size / size_of::<ElfPhdr>(),
+            )
+        };
+        if let Some(len) = object.len() {
+            check_file_ranges(phdrs, object, len)?;
         }
+        Ok(phdrs)
     }
 
     pub(crate) fn prepare_shdrs_mut(
@@ -56,14 +109,12 @@ impl ElfBuf {
     ) -> Result<&mut [ElfShdr]> {
         let (shdr_start, shdr_end) = ehdr.shdr_range();
         let size = shdr_end - shdr_start;
-        if size > self.buf.len() {
-            self.buf.resize(size, 0);
-        }
-        object.read(&mut self.buf[..size], shdr_start)?;
+        let bytes = self.bytes_mut(size);
+        object.read(bytes, shdr_start)?;
         unsafe {
             Ok(core::slice::from_raw_parts_mut(
-                self.buf.as_mut_ptr().cast::<ElfShdr>(),
-                (shdr_end - shdr_start) / size_of::<ElfShdr>(),
+                bytes.as_mut_ptr().cast::<ElfShdr>(),
+                size / size_of::<ElfShdr>(),
             ))
         }
     }
@@ -75,6 +126,7 @@ pub struct LoadHookContext<'a, D> {
     phdr: &'a ElfPhdr,
     segments: &'a ElfSegments,
     user_data: &'a mut D,
+    prot: &'a mut Option<ProtFlags>,
 }
 
 impl<'a, D> LoadHookContext<'a, D> {
@@ -83,12 +135,14 @@ impl<'a, D> LoadHookContext<'a, D> {
         phdr: &'a ElfPhdr,
         segments: &'a ElfSegments,
         user_data: &'a mut D,
+        prot: &'a mut Option<ProtFlags>,
     ) -> Self {
         Self {
             name,
             phdr,
             segments,
             user_data,
+            prot,
         }
     }
 
@@ -111,6 +165,54 @@ impl<'a, D> LoadHookContext<'a, D> {
     pub fn user_data_mut(&mut self) -> &mut D {
         self.user_data
     }
+
+    /// Replaces the memory protection of the current `PT_LOAD` segment.
+    ///
+    /// The flags take precedence over the segment's `p_flags` and are applied
+    /// before the object is returned to the caller. Relocations that write
+    /// into the segment still require `PROT_WRITE`.
+    ///
+    /// Has no effect on other program header types, or on premapped images
+    /// whose protections are managed by the caller.
+    pub fn override_prot(&mut self, prot: ProtFlags) {
+        *self.prot = Some(prot);
+    }
+
+    /// Leaves the current `PT_LOAD` segment inaccessible.
+    ///
+    /// This is the same as calling [`override_prot`](Self::override_prot)
+    /// with `PROT_NONE`.
+    pub fn skip_segment(&mut self) {
+        *self.prot = Some(ProtFlags::PROT_NONE);
+    }
+
+    /// Returns the protection override set for the current segment, if any.
+    pub fn prot_override(&self) -> Option<ProtFlags> {
+        *self.prot
+    }
+}
+
+impl LoadHookContext<'_, Extensions> {
+    /// Gets the extension of type `T` attached to the ELF object.
+    pub fn extension<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.user_data.get::<T>()
+    }
+
+    /// Gets the extension of type `T`, attaching the result of `f` first if there is none.
+    ///
+    /// The hook runs once per program header, so this is the usual way to
+    /// initialize data on the first call and update it on later ones.
+    pub fn extension_or_insert_with<T: Any + Send + Sync>(
+        &mut self,
+        f: impl FnOnce() -> T,
+    ) -> &mut T {
+        self.user_data.get_or_insert_with(f)
+    }
+
+    /// Attaches `value` to the ELF object, returning the previous extension of type `T`.
+    pub fn insert_extension<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.user_data.insert(value)
+    }
 }
 
 /// Hook trait for processing program headers during loading.
@@ -148,7 +250,220 @@ impl LoadHook for () {
     }
 }
 
-pub(crate) type FnHandler = Arc<dyn Fn(Option<fn()>, Option<&[fn()]>)>;
+/// How a [`Loader`] treats libraries that request an executable stack.
+///
+/// A library requests an executable stack with a `PT_GNU_STACK` program header
+/// that has the `PF_X` flag. The loader never changes the protection of the
+/// stack itself; the policy only decides whether such libraries are loaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecStackPolicy {
+    /// Fail to load the library.
+    Deny,
+    /// Load the library.
+    #[default]
+    Allow,
+    /// Load the library and log a warning with the `log` feature.
+    Warn,
+}
+
+/// How a [`Loader`] treats dynamic tags it does not interpret.
+///
+/// Only tags in the OS-specific (`DT_LOOS..=DT_HIOS`) and processor-specific
+/// (`DT_LOPROC..=DT_HIPROC`) ranges are considered, such as the metadata of a
+/// vendor toolchain. The policy is applied when the dynamic section is parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownDynamicPolicy {
+    /// Skip the tags.
+    #[default]
+    Ignore,
+    /// Keep the tags, see [`RawDylib::vendor_dynamic_entries`](crate::image::RawDylib::vendor_dynamic_entries).
+    Collect,
+    /// Fail to load the object with a [`ParseDynamic`](crate::Error::ParseDynamic)
+    /// error that lists the tags.
+    Error,
+}
+
+/// Which OS/ABI identifications a [`Loader`] accepts.
+///
+/// The `EI_OSABI` and `EI_ABIVERSION` bytes of the ELF header name the
+/// operating system extensions a file relies on. The policy is checked as soon
+/// as the header is read, and rejected files fail to load with a
+/// [`ParseEhdr`](crate::Error::ParseEhdr) error.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum OsAbiPolicy {
+    /// Accept every file, whatever its OS/ABI.
+    #[default]
+    AcceptAll,
+    /// Accept only `ELFOSABI_SYSV` (also `ELFOSABI_NONE`) and `ELFOSABI_GNU`.
+    SysvAndGnuOnly,
+    /// Accept the files for which the function returns `true`, given the
+    /// OS/ABI and the ABI version.
+    Custom(fn(u8, u8) -> bool),
+}
+
+impl OsAbiPolicy {
+    /// Returns whether the policy accepts `osabi` at `abi_version`.
+    pub fn accepts(self, osabi: u8, abi_version: u8) -> bool {
+        match self {
+            OsAbiPolicy::AcceptAll => true,
+            OsAbiPolicy::SysvAndGnuOnly => matches!(osabi, ELFOSABI_SYSV | ELFOSABI_GNU),
+            OsAbiPolicy::Custom(accept) => accept(osabi, abi_version),
+        }
+    }
+
+    pub(crate) fn check(self, ehdr: &ElfHeader, name: &str) -> Result<()> {
+        let (osabi, abi_version) = (ehdr.osabi(), ehdr.abi_version());
+        if self.accepts(osabi, abi_version) {
+            return Ok(());
+        }
+        Err(parse_ehdr_error(format!(
+            "file [{name}]: OS/ABI {osabi} (ABI version {abi_version}) is not accepted"
+        )))
+    }
+}
+
+/// The kind of an ELF file, as reported by [`Loader::probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfKind {
+    /// A shared object (`ET_DYN`).
+    ///
+    /// Position-independent executables are shared objects as well; telling
+    /// them apart requires the program headers.
+    Dylib,
+    /// An executable (`ET_EXEC`).
+    Exec,
+    /// A relocatable object (`ET_REL`).
+    Object,
+}
+
+/// An initialization or finalization function of a loaded object.
+///
+/// The pointer is typed for the C calling convention of the host. Call it with
+/// [`CallConv::call`] when the object follows another convention.
+pub type InitFn = unsafe extern "C" fn();
+
+pub(crate) type FnHandler = Arc<dyn Fn(Option<InitFn>, Option<&[InitFn]>) + Send + Sync>;
+
+/// A handler that runs initialization or finalization functions.
+///
+/// It receives the [`InitParams`] of the loader, followed by the single
+/// function (`DT_INIT`/`DT_FINI`) and the function array of the object.
+/// The finalization handler runs on the thread that drops the last reference to
+/// the object, so it must be `Send` and `Sync`.
+pub type InitHandler = Arc<dyn Fn(&InitParams, Option<InitFn>, Option<&[InitFn]>) + Send + Sync>;
+
+/// The calling convention of the initialization and finalization functions,
+/// used by the default handlers of a [`Loader`].
+///
+/// Set it with [`Loader::set_call_conv`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CallConv {
+    /// The C calling convention of the host.
+    #[default]
+    C,
+    /// The System V AMD64 calling convention, which ELF code follows even when
+    /// it is loaded on a Windows host.
+    #[cfg(target_arch = "x86_64")]
+    SysV64,
+}
+
+impl CallConv {
+    /// Calls an initialization or finalization function.
+    ///
+    /// Initialization functions receive the [`InitParams`] as
+    /// `fn(argc, argv, envp)`, like glibc passes them; finalization functions,
+    /// called with `None`, receive no arguments.
+    ///
+    /// # Safety
+    /// `func` must point to a function of a loaded object that follows this
+    /// calling convention and is safe to call at this point.
+    pub unsafe fn call(self, func: InitFn, params: Option<&InitParams>) {
+        type CInit = unsafe extern "C" fn(c_int, *const *const c_char, *const *const c_char);
+        #[cfg(target_arch = "x86_64")]
+        type SysVInit =
+            unsafe extern "sysv64" fn(c_int, *const *const c_char, *const *const c_char);
+        #[cfg(target_arch = "x86_64")]
+        type SysVFini = unsafe extern "sysv64" fn();
+
+        // This is the only place where the functions are cast to another
+        // signature or convention
+        unsafe {
+            match (self, params) {
+                (CallConv::C, None) => func(),
+                (CallConv::C, Some(params)) => core::mem::transmute::<InitFn, CInit>(func)(
+                    params.argc,
+                    params.argv,
+                    params.envp,
+                ),
+                #[cfg(target_arch = "x86_64")]
+                (CallConv::SysV64, None) => core::mem::transmute::<InitFn, SysVFini>(func)(),
+                #[cfg(target_arch = "x86_64")]
+                (CallConv::SysV64, Some(params)) => core::mem::transmute::<InitFn, SysVInit>(func)(
+                    params.argc,
+                    params.argv,
+                    params.envp,
+                ),
+            }
+        }
+    }
+}
+
+/// Create the default initialization handler, which calls every function with
+/// the [`InitParams`]
+fn default_init(conv: CallConv) -> InitHandler {
+    Arc::new(
+        move |params: &InitParams, func: Option<InitFn>, func_array: Option<&[InitFn]>| {
+            func.iter()
+                .chain(func_array.unwrap_or(&[]).iter())
+                .for_each(|&init| unsafe { conv.call(init, Some(params)) });
+        },
+    )
+}
+
+/// Create the default finalization handler
+fn default_fini(conv: CallConv) -> InitHandler {
+    // Finalizers run in the reverse order of the initializers
+    Arc::new(
+        move |_: &InitParams, func: Option<InitFn>, func_array: Option<&[InitFn]>| {
+            func_array
+                .unwrap_or(&[])
+                .iter()
+                .rev()
+                .chain(func.iter())
+                .for_each(|&fini| unsafe { conv.call(fini, None) });
+        },
+    )
+}
+
+/// The arguments that glibc passes to initialization functions.
+///
+/// Functions in `DT_PREINIT_ARRAY`, `DT_INIT` and `DT_INIT_ARRAY` are called
+/// as `fn(argc, argv, envp)` by the default init handler. The default
+/// parameters are `0` and null pointers.
+#[derive(Debug, Clone, Copy)]
+pub struct InitParams {
+    /// Number of arguments
+    pub argc: c_int,
+    /// NULL-terminated argument vector
+    pub argv: *const *const c_char,
+    /// NULL-terminated environment vector
+    pub envp: *const *const c_char,
+}
+
+// Safety: the vectors are only read, and are expected to live as long as the
+// process, like the ones the kernel passes to `_start`
+unsafe impl Send for InitParams {}
+unsafe impl Sync for InitParams {}
+
+impl Default for InitParams {
+    fn default() -> Self {
+        Self {
+            argc: 0,
+            argv: null(),
+            envp: null(),
+        }
+    }
+}
 
 /// The ELF object loader.
 ///
@@ -169,25 +484,77 @@ where
     D: Default + 'static,
 {
     pub(crate) buf: ElfBuf,
-    pub(crate) init_fn: FnHandler,
-    pub(crate) fini_fn: FnHandler,
+    /// The init handler, or `None` for the default one
+    pub(crate) init: Option<InitHandler>,
+    /// The fini handler, or `None` for the default one
+    pub(crate) fini: Option<InitHandler>,
+    pub(crate) init_params: InitParams,
+    pub(crate) call_conv: CallConv,
     pub(crate) hook: H,
+    pub(crate) registry: bool,
+    #[cfg(feature = "aarch64-pac")]
+    pub(crate) pac: bool,
+    pub(crate) extra: ExtraSpace,
+    pub(crate) execstack: ExecStackPolicy,
+    pub(crate) osabi: OsAbiPolicy,
+    pub(crate) unknown_dynamic: UnknownDynamicPolicy,
+    pub(crate) fixed_overwrite: bool,
+    pub(crate) allowed_arch: Vec<u16>,
+    pub(crate) budget: MapBudget,
+    pub(crate) tls: Option<Arc<dyn TlsAllocator>>,
+    pub(crate) auditor: Option<Arc<dyn Auditor<D>>>,
+    pub(crate) alloc: LoaderAlloc,
     _marker: PhantomData<(M, D)>,
 }
 
 impl Loader<DefaultMmap, (), ()> {
     /// Creates a new `Loader` with default settings.
     pub fn new() -> Self {
-        let c_abi = Arc::new(|func: Option<fn()>, func_array: Option<&[fn()]>| {
-            func.iter()
-                .chain(func_array.unwrap_or(&[]).iter())
-                .for_each(|init| unsafe { core::mem::transmute::<_, &extern "C" fn()>(init) }());
-        });
+        Self::with_alloc(LoaderAlloc::default())
+    }
+
+    /// Creates a new `Loader` that allocates the per-load data from `alloc`.
+    ///
+    /// The header scratch buffer, and the program headers, name, path and
+    /// `DT_NEEDED` list of every loaded module are allocated from `alloc`.
+    /// Data shared through `Arc`, such as the core of each module, and the
+    /// handlers of the loader are still allocated from the global allocator.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// #![feature(allocator_api)]
+    /// use elf_loader::Loader;
+    /// use std::alloc::System;
+    ///
+    /// let mut loader = Loader::new_in(System);
+    /// let lib = loader.load_dylib("liba.so").unwrap();
+    /// ```
+    #[cfg(feature = "allocator_api")]
+    pub fn new_in(alloc: impl core::alloc::Allocator + Send + Sync + 'static) -> Self {
+        Self::with_alloc(LoaderAlloc::new(alloc))
+    }
+
+    fn with_alloc(alloc: LoaderAlloc) -> Self {
         Self {
             hook: (),
-            init_fn: c_abi.clone(),
-            fini_fn: c_abi,
-            buf: ElfBuf::new(),
+            init: None,
+            fini: None,
+            init_params: InitParams::default(),
+            call_conv: CallConv::default(),
+            buf: ElfBuf::new(&alloc),
+            registry: false,
+            #[cfg(feature = "aarch64-pac")]
+            pac: false,
+            extra: ExtraSpace::default(),
+            execstack: ExecStackPolicy::Allow,
+            osabi: OsAbiPolicy::AcceptAll,
+            unknown_dynamic: UnknownDynamicPolicy::Ignore,
+            fixed_overwrite: false,
+            allowed_arch: Vec::new(),
+            budget: MapBudget::default(),
+            tls: None,
+            auditor: None,
+            alloc,
             _marker: PhantomData,
         }
     }
@@ -198,11 +565,12 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
     ///
     /// This handler is responsible for calling the initialization functions
     /// (e.g., `.init` and `.init_array`) of the loaded ELF object.
-    ///
-    /// Note: glibc passes `argc`, `argv`, and `envp` to functions in `.init_array`
-    /// as a non-standard extension.
+    /// Use [`set_init`](Self::set_init) for a handler that receives the
+    /// [`InitParams`] of the loader.
     pub fn with_init(&mut self, init_fn: FnHandler) -> &mut Self {
-        self.init_fn = init_fn;
+        self.init = Some(Arc::new(move |_: &InitParams, func, func_array| {
+            init_fn(func, func_array)
+        }));
         self
     }
 
@@ -210,14 +578,233 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
     ///
     /// This handler is responsible for calling the finalization functions
     /// (e.g., `.fini` and `.fini_array`) of the loaded ELF object.
+    /// The default handler runs `.fini_array` in reverse order followed by `.fini`.
     pub fn with_fini(&mut self, fini_fn: FnHandler) -> &mut Self {
-        self.fini_fn = fini_fn;
+        self.fini = Some(Arc::new(move |_: &InitParams, func, func_array| {
+            fini_fn(func, func_array)
+        }));
+        self
+    }
+
+    /// Sets the initialization function handler, which receives the
+    /// [`InitParams`] of the loader.
+    ///
+    /// For executables, the handler is first called with the `DT_PREINIT_ARRAY`
+    /// entries and no single function, then with `DT_INIT` and `DT_INIT_ARRAY`.
+    /// The default handler calls every function as `fn(argc, argv, envp)`,
+    /// like glibc does.
+    pub fn set_init(&mut self, init: InitHandler) -> &mut Self {
+        self.init = Some(init);
+        self
+    }
+
+    /// Sets the finalization function handler, which receives the
+    /// [`InitParams`] of the loader.
+    pub fn set_fini(&mut self, fini: InitHandler) -> &mut Self {
+        self.fini = Some(fini);
+        self
+    }
+
+    /// Sets the parameters passed to the init and fini handlers of the
+    /// objects loaded from now on.
+    pub fn set_init_params(&mut self, params: InitParams) -> &mut Self {
+        self.init_params = params;
+        self
+    }
+
+    /// Sets the calling convention the default init and fini handlers call
+    /// the functions of the objects loaded from now on with.
+    ///
+    /// The default is [`CallConv::C`]. Use [`CallConv::SysV64`] to run the
+    /// initializers of System V code on a Windows host. Handlers set with
+    /// [`set_init`](Self::set_init) or [`with_init`](Self::with_init) call the
+    /// functions themselves, and can use [`CallConv::call`] to do so.
+    pub fn set_call_conv(&mut self, conv: CallConv) -> &mut Self {
+        self.call_conv = conv;
+        self
+    }
+
+    /// Binds the init and fini handlers to the current [`InitParams`]
+    pub(crate) fn fn_handlers(&self) -> (FnHandler, FnHandler) {
+        let params = self.init_params;
+        let init = self
+            .init
+            .clone()
+            .unwrap_or_else(|| default_init(self.call_conv));
+        let fini = self
+            .fini
+            .clone()
+            .unwrap_or_else(|| default_fini(self.call_conv));
+        (
+            Arc::new(move |func, func_array| init(&params, func, func_array)),
+            Arc::new(move |func, func_array| fini(&params, func, func_array)),
+        )
+    }
+
+    /// Sets the allocator for the TLS blocks of loaded objects.
+    ///
+    /// Dynamic objects with a `PT_TLS` segment are registered with the allocator
+    /// and receive a module id, which is used to resolve the `R_*_DTPMOD`,
+    /// `R_*_DTPOFF` and `R_*_TPOFF` relocations. Without an allocator, these
+    /// relocations are left to the relocation handlers.
+    pub fn with_tls_allocator(&mut self, allocator: impl TlsAllocator + 'static) -> &mut Self {
+        self.tls = Some(Arc::new(allocator));
+        self
+    }
+
+    /// Installs an auditor that observes the symbol bindings of the dynamic
+    /// images loaded afterwards.
+    ///
+    /// The relocation of every such image reports its `GOT`, symbolic and
+    /// eagerly bound `JUMP_SLOT` relocations to [`Auditor::symbol_bound`],
+    /// lazy binding reports to [`Auditor::lazy_resolved`], and the relocated
+    /// image is passed to [`Auditor::object_loaded`]. Without an auditor, none
+    /// of this costs more than a check for its presence.
+    ///
+    /// The auditor is dropped by [`with_hook`](Self::with_hook), which changes
+    /// the user data type.
+    pub fn set_auditor(&mut self, auditor: Arc<dyn Auditor<D>>) -> &mut Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    /// Enables or disables the process-wide module registry.
+    ///
+    /// When enabled, dynamic objects loaded by this loader are added to the
+    /// registry once they have been relocated, and removed when they are dropped.
+    /// Registered objects can be enumerated with [`iterate_phdr`](crate::iterate_phdr),
+    /// for example by an unwinder looking for `.eh_frame_hdr`. With the `debugging`
+    /// feature they are also published to debuggers through [`debug::r_debug`](crate::debug::r_debug).
+    pub fn enable_registry(&mut self, enable: bool) -> &mut Self {
+        self.registry = enable;
+        self
+    }
+
+    /// Enables or disables the stripping of pointer authentication codes.
+    ///
+    /// Libraries built for pointer authentication may hand out signed code
+    /// pointers, for example from an IFUNC resolver, and a host calling or
+    /// comparing them as plain addresses fails. When enabled, the addresses
+    /// of the symbols defined by the dynamic objects loaded by this loader,
+    /// whether bound by a relocation or returned by
+    /// [`LoadedCore::get`](crate::image::LoadedCore::get), and the functions
+    /// these objects bind lazily are stripped of their authentication code.
+    /// Stripping is a no-op on CPUs without pointer authentication and on
+    /// other architectures.
+    ///
+    /// Disabled by default.
+    #[cfg(feature = "aarch64-pac")]
+    pub fn enable_pac(&mut self, enable: bool) -> &mut Self {
+        self.pac = enable;
+        self
+    }
+
+    /// Reserves `bytes` of address space directly after each loaded image.
+    ///
+    /// The reservation is rounded up to the page size and is initially
+    /// inaccessible. It is released together with the image and can be made
+    /// accessible piece by piece with [`LoadedCore::commit_tail`](crate::image::LoadedCore::commit_tail),
+    /// for example to place trampolines within branch range of the image's code.
+    pub fn reserve_tail(&mut self, bytes: usize) -> &mut Self {
+        self.extra.tail = bytes;
+        self
+    }
+
+    /// Surrounds each loaded image with `count` inaccessible guard pages.
+    ///
+    /// The guards are placed before the first segment and after the last one
+    /// (after the [reserved tail](Self::reserve_tail), if any), so a stray
+    /// access just outside an image faults instead of silently hitting a
+    /// neighbouring mapping. They are not part of
+    /// [`mapped_len`](crate::image::LoadedCore::mapped_len) and do not move the
+    /// base address, but they count towards the
+    /// [image size limit](Self::set_max_image_size) and are released together
+    /// with the image. The default is no guard pages.
+    pub fn guard_pages(&mut self, count: usize) -> &mut Self {
+        self.extra.guard_pages = count;
+        self
+    }
+
+    /// Sets how libraries that request an executable stack are treated.
+    ///
+    /// The policy is applied by [`load_dylib`](Self::load_dylib) and
+    /// [`load_dylib_premapped`](Self::load_dylib_premapped); the default is
+    /// [`ExecStackPolicy::Allow`].
+    pub fn set_execstack_policy(&mut self, policy: ExecStackPolicy) -> &mut Self {
+        self.execstack = policy;
+        self
+    }
+
+    /// Sets which OS/ABI identifications of the ELF header are accepted.
+    ///
+    /// The policy applies to every file this loader reads the header of; the
+    /// default is [`OsAbiPolicy::AcceptAll`].
+    pub fn set_osabi_policy(&mut self, policy: OsAbiPolicy) -> &mut Self {
+        self.osabi = policy;
+        self
+    }
+
+    /// Sets how OS- and processor-specific dynamic tags that the loader does
+    /// not interpret are treated.
+    ///
+    /// The policy applies to dynamic libraries and executables; the default is
+    /// [`UnknownDynamicPolicy::Ignore`].
+    pub fn on_unknown_dynamic(&mut self, policy: UnknownDynamicPolicy) -> &mut Self {
+        self.unknown_dynamic = policy;
+        self
+    }
+
+    /// Allows executables to be mapped even if their fixed address range is in use.
+    ///
+    /// By default, [`load_exec`](Self::load_exec) probes the range an `ET_EXEC`
+    /// file must be loaded at and fails with [`Error::AddressConflict`](crate::Error::AddressConflict)
+    /// if any part of it is already mapped. Enabling this skips the check and
+    /// keeps the previous behaviour of passing the address to the OS as a hint.
+    pub fn allow_fixed_overwrite(&mut self, allow: bool) -> &mut Self {
+        self.fixed_overwrite = allow;
+        self
+    }
+
+    /// Accepts files built for the machine `e_machine` in addition to the host's.
+    ///
+    /// By default, files for other architectures are rejected as soon as their
+    /// ELF header is read, with [`Error::ArchMismatch`](crate::Error::ArchMismatch).
+    /// Allowing a machine lets such files be mapped for inspection, for example
+    /// to read their symbols. Relocating them is not supported and fails or
+    /// produces a broken image.
+    pub fn allow_arch(&mut self, e_machine: u16) -> &mut Self {
+        if !self.allowed_arch.contains(&e_machine) {
+            self.allowed_arch.push(e_machine);
+        }
+        self
+    }
+
+    /// Limits the address space a single image may reserve to `bytes`.
+    ///
+    /// The limit covers the span of the image, the space set aside with
+    /// [`reserve_tail`](Self::reserve_tail) and the
+    /// [`guard_pages`](Self::guard_pages). It is checked before anything is
+    /// mapped, and loads that exceed it fail with
+    /// [`Error::ImageTooLarge`](crate::Error::ImageTooLarge). Images loaded with
+    /// [`load_dylib_premapped`](Self::load_dylib_premapped) are not checked.
+    pub fn set_max_image_size(&mut self, bytes: usize) -> &mut Self {
+        self.budget.limit = Some(bytes);
         self
     }
 
+    /// Returns the bytes of address space currently reserved by the images
+    /// this loader has loaded.
+    ///
+    /// The count drops when an image is unloaded, even if the loader is gone
+    /// by then. Premapped images are not counted.
+    pub fn mapped_bytes(&self) -> usize {
+        self.budget.mapped()
+    }
+
     /// Consumes the current loader and returns a new one with the specified hook.
     ///
-    /// This allows replacing the hook type and user data type.
+    /// This allows replacing the hook type and user data type. An auditor
+    /// installed with [`set_auditor`](Self::set_auditor) is dropped.
     ///
     /// # Type Parameters
     /// * `NewD` - The new user data type.
@@ -229,9 +816,24 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
     {
         Loader {
             buf: self.buf,
-            init_fn: self.init_fn,
-            fini_fn: self.fini_fn,
+            init: self.init,
+            fini: self.fini,
+            init_params: self.init_params,
+            call_conv: self.call_conv,
             hook,
+            registry: self.registry,
+            #[cfg(feature = "aarch64-pac")]
+            pac: self.pac,
+            extra: self.extra,
+            execstack: self.execstack,
+            osabi: self.osabi,
+            unknown_dynamic: self.unknown_dynamic,
+            fixed_overwrite: self.fixed_overwrite,
+            allowed_arch: self.allowed_arch,
+            budget: self.budget,
+            tls: self.tls,
+            auditor: None,
+            alloc: self.alloc,
             _marker: PhantomData,
         }
     }
@@ -240,16 +842,68 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
     pub fn with_mmap<NewMmap: Mmap>(self) -> Loader<NewMmap, H, D> {
         Loader {
             buf: self.buf,
-            init_fn: self.init_fn,
-            fini_fn: self.fini_fn,
+            init: self.init,
+            fini: self.fini,
+            init_params: self.init_params,
+            call_conv: self.call_conv,
             hook: self.hook,
+            registry: self.registry,
+            #[cfg(feature = "aarch64-pac")]
+            pac: self.pac,
+            extra: self.extra,
+            execstack: self.execstack,
+            osabi: self.osabi,
+            unknown_dynamic: self.unknown_dynamic,
+            fixed_overwrite: self.fixed_overwrite,
+            allowed_arch: self.allowed_arch,
+            budget: self.budget,
+            tls: self.tls,
+            auditor: self.auditor,
+            alloc: self.alloc,
             _marker: PhantomData,
         }
     }
 
+    /// Reserves room for `phdr_capacity` program headers in the scratch buffer.
+    ///
+    /// The buffer is kept across loads and only grows, so sizing it once avoids
+    /// allocations when many objects are loaded with the same loader.
+    pub fn preallocate(&mut self, phdr_capacity: usize) -> &mut Self {
+        self.buf.reserve(phdr_capacity * size_of::<ElfPhdr>());
+        self
+    }
+
+    /// Reads only the ELF header of `input` and reports the kind of the file.
+    ///
+    /// This is much cheaper than [`load`](Self::load), which also reads the
+    /// program headers and maps the file.
+    ///
+    /// # Returns
+    /// An error if the header is invalid, is for another architecture, or
+    /// describes a file of another type, such as a core dump.
+    pub fn probe<'a, I>(&mut self, input: I) -> Result<ElfKind>
+    where
+        I: IntoElfReader<'a>,
+    {
+        let mut object = input.into_reader()?;
+        let ehdr = self
+            .buf
+            .prepare_ehdr(&mut object, &self.allowed_arch, self.osabi)?;
+        match ehdr.e_type {
+            ET_DYN => Ok(ElfKind::Dylib),
+            ET_EXEC => Ok(ElfKind::Exec),
+            ET_REL => Ok(ElfKind::Object),
+            e_type => Err(parse_ehdr_error(format!(
+                "file [{}]: unsupported ELF type {e_type}",
+                object.shortname()
+            ))),
+        }
+    }
+
     /// Reads the ELF header.
     pub fn read_ehdr(&mut self, object: &mut impl ElfReader) -> Result<ElfHeader> {
-        self.buf.prepare_ehdr(object)
+        self.buf
+            .prepare_ehdr(object, &self.allowed_arch, self.osabi)
     }
 
     /// Reads the program header table.
@@ -261,10 +915,15 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
         self.buf.prepare_phdrs(ehdr, object)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn load_static_impl(
         hook: &H,
         init_fn: &FnHandler,
         fini_fn: &FnHandler,
+        extra: ExtraSpace,
+        fixed_overwrite: bool,
+        budget: &MapBudget,
+        alloc: &LoaderAlloc,
         ehdr: ElfHeader,
         phdrs: &[ElfPhdr],
         mut object: impl ElfReader,
@@ -272,24 +931,41 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
         let init_fn = init_fn.clone();
         let fini_fn = fini_fn.clone();
         let mut phdr_segments =
-            ProgramSegments::new(phdrs, ehdr.is_dylib(), object.as_fd().is_some());
-        let segments = phdr_segments.load_segments::<M>(&mut object)?;
-        phdr_segments.mprotect::<M>()?;
-        let builder: ImageBuilder<'_, H, M, D> = ImageBuilder::new(
+            ProgramSegments::new(phdrs, ehdr.is_dylib(), object.as_fd().is_some())
+                .allow_fixed_overwrite(fixed_overwrite);
+        let segments = phdr_segments.load_segments::<M>(&mut object, extra, budget)?;
+        let mut builder: ImageBuilder<'_, H, M, D> = ImageBuilder::new(
             hook,
             segments,
-            object.shortname().to_owned(),
+            object.shortname(),
             ehdr,
             init_fn,
             fini_fn,
+            alloc,
         );
-        Ok(builder.build_static(phdrs)?)
+        // Protections are applied after the hook so that its overrides take effect
+        for (idx, prot) in builder.parse_phdrs(phdrs)? {
+            phdr_segments.override_prot(idx, prot);
+        }
+        if M::supports_bti() && builder.gnu_properties.is_some_and(|props| props.has_bti()) {
+            phdr_segments.guard_branches();
+        }
+        phdr_segments.mprotect::<M>()?;
+        let phdrs = builder.create_phdrs(phdrs);
+        Ok(builder.build_static(phdrs))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn load_dynamic_impl(
         hook: &H,
         init_fn: &FnHandler,
         fini_fn: &FnHandler,
+        tls: &Option<Arc<dyn TlsAllocator>>,
+        unknown_dynamic: UnknownDynamicPolicy,
+        extra: ExtraSpace,
+        fixed_overwrite: bool,
+        budget: &MapBudget,
+        alloc: &LoaderAlloc,
         ehdr: ElfHeader,
         phdrs: &[ElfPhdr],
         mut object: impl ElfReader,
@@ -297,18 +973,33 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
         let init_fn = init_fn.clone();
         let fini_fn = fini_fn.clone();
         let mut phdr_segments =
-            ProgramSegments::new(phdrs, ehdr.is_dylib(), object.as_fd().is_some());
-        let segments = phdr_segments.load_segments::<M>(&mut object)?;
-        phdr_segments.mprotect::<M>()?;
-        let builder: ImageBuilder<'_, H, M, D> = ImageBuilder::new(
+            ProgramSegments::new(phdrs, ehdr.is_dylib(), object.as_fd().is_some())
+                .allow_fixed_overwrite(fixed_overwrite);
+        let segments = phdr_segments.load_segments::<M>(&mut object, extra, budget)?;
+        let mut builder: ImageBuilder<'_, H, M, D> = ImageBuilder::new(
             hook,
             segments,
-            object.shortname().to_owned(),
+            object.shortname(),
             ehdr,
             init_fn,
             fini_fn,
+            alloc,
         );
-        Ok(builder.build_dynamic(phdrs)?)
+        // Protections are applied after the hook so that its overrides take effect
+        for (idx, prot) in builder.parse_phdrs(phdrs)? {
+            phdr_segments.override_prot(idx, prot);
+        }
+        if M::supports_bti() && builder.gnu_properties.is_some_and(|props| props.has_bti()) {
+            phdr_segments.guard_branches();
+        }
+        phdr_segments.mprotect::<M>()?;
+        let notes = ElfNotes::load(phdrs, &builder.segments, &mut object)?;
+        builder
+            .tls_allocator(tls.clone())
+            .unknown_dynamic(unknown_dynamic)
+            .path(object.file_name())
+            .notes(notes)
+            .build_dynamic(phdrs, Some(&phdr_segments))
     }
 
     /// Load a relocatable ELF object
@@ -317,19 +1008,19 @@ impl<M: Mmap, H: LoadHook<D>, D: Default + 'static> Loader<M, H, D> {
         ehdr: ElfHeader,
         mut object: impl ElfReader,
     ) -> Result<RawObject> {
-        let init_fn = self.init_fn.clone();
-        let fini_fn = self.fini_fn.clone();
+        let (init_fn, fini_fn) = self.fn_handlers();
         let shdrs = self.buf.prepare_shdrs_mut(&ehdr, &mut object).unwrap();
         let mut shdr_segments = SectionSegments::new(shdrs, &mut object);
-        let segments = shdr_segments.load_segments::<M>(&mut object)?;
+        let segments = shdr_segments.load_segments::<M>(&mut object, self.extra, &self.budget)?;
         let pltgot = shdr_segments.take_pltgot();
         let mprotect = Box::new(move || {
             shdr_segments.mprotect::<M>()?;
             Ok(())
         });
         let builder = ObjectBuilder::new(
-            object.shortname().to_owned(),
+            NulStr::new(object.shortname(), &self.alloc),
             shdrs,
+            ehdr.e_shstrndx as usize,
             init_fn,
             fini_fn,
             segments,
@@ -50,6 +50,21 @@ impl Mmap for DefaultMmap {
         Ok(unsafe { NonNull::new_unchecked(ptr as _) })
     }
 
+    /// The allocation can only be freed as a whole, so the requested alignment
+    /// is not applied.
+    unsafe fn mmap_reserve_aligned(
+        len: usize,
+        _align: usize,
+        use_file: bool,
+    ) -> crate::Result<NonNull<core::ffi::c_void>> {
+        unsafe { Self::mmap_reserve(None, len, use_file) }
+    }
+
+    /// There is no address space to share, so any range is considered free.
+    unsafe fn probe(_addr: usize, _len: usize) -> bool {
+        true
+    }
+
     unsafe fn munmap(addr: core::ptr::NonNull<core::ffi::c_void>, len: usize) -> crate::Result<()> {
         unsafe {
             dealloc(
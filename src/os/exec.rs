@@ -0,0 +1,366 @@
+//! Running an executable in the current process
+//!
+//! [`StackBuilder`] lays out the initial stack the kernel would hand to a new
+//! program: `argc`, the `argv` and `envp` arrays and the auxiliary vector. The
+//! resulting [`PreparedStack`] is passed to [`LoadedExec::run`] or
+//! [`RawExec::run`], which switch to it and jump to the entry point. The calling
+//! program never regains control.
+//!
+//! A dynamically linked executable is normally started through its interpreter:
+//! load the executable with [`Loader::load_exec`](crate::Loader::load_exec) and
+//! the path from [`RawExec::interp`] with
+//! [`Loader::load_dylib`](crate::Loader::load_dylib), leave both unrelocated and
+//! register the interpreter with [`StackBuilder::interpreter`].
+
+use crate::{
+    Result,
+    elf::ElfPhdr,
+    image::{LoadedExec, RawDylib, RawExec},
+    io_error,
+    os::{DefaultMmap, MapFlags, Mmap, ProtFlags},
+    segment::PAGE_SIZE,
+};
+use alloc::vec::Vec;
+use core::{ffi::c_void, ptr::NonNull};
+use libc::{
+    AT_BASE, AT_CLKTCK, AT_EGID, AT_ENTRY, AT_EUID, AT_EXECFN, AT_FLAGS, AT_GID, AT_HWCAP,
+    AT_HWCAP2, AT_NULL, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM, AT_PLATFORM, AT_RANDOM, AT_SECURE,
+    AT_SYSINFO_EHDR, AT_UID, c_ulong, getauxval,
+};
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+/// Default size of the stack mapping
+const DEFAULT_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Builder for the initial stack of an executable.
+pub struct StackBuilder {
+    argv: Vec<Vec<u8>>,
+    envp: Vec<Vec<u8>>,
+    stack_size: usize,
+    /// Base and entry point of the interpreter
+    interp: Option<(usize, usize)>,
+}
+
+impl StackBuilder {
+    /// Creates a builder for a program started with `argv` and `envp`.
+    ///
+    /// # Arguments
+    /// * `argv` - The arguments, starting with the program name.
+    /// * `envp` - The environment, as `NAME=value` entries.
+    pub fn new<A, E>(argv: A, envp: E) -> Self
+    where
+        A: IntoIterator,
+        A::Item: AsRef<OsStr>,
+        E: IntoIterator,
+        E::Item: AsRef<OsStr>,
+    {
+        let collect = |item: &OsStr| item.as_bytes().to_vec();
+        Self {
+            argv: argv.into_iter().map(|arg| collect(arg.as_ref())).collect(),
+            envp: envp.into_iter().map(|env| collect(env.as_ref())).collect(),
+            stack_size: DEFAULT_STACK_SIZE,
+            interp: None,
+        }
+    }
+
+    /// Sets the size of the stack mapping. The default is 8 MiB.
+    pub fn stack_size(mut self, bytes: usize) -> Self {
+        self.stack_size = bytes;
+        self
+    }
+
+    /// Starts the program through `interp` instead of its own entry point.
+    ///
+    /// `interp` must not be relocated: the interpreter relocates itself and the
+    /// executable once it runs. Its mapping is leaked so that it stays valid
+    /// after this builder is gone, and `AT_BASE` is set to its base address.
+    pub fn interpreter<D>(mut self, interp: RawDylib<D>) -> Self {
+        self.interp = Some((interp.base(), interp.entry()));
+        core::mem::forget(interp);
+        self
+    }
+
+    /// Builds the stack for a relocated executable.
+    pub fn build<D>(self, exec: &LoadedExec<D>) -> Result<PreparedStack> {
+        self.build_impl(exec.phdrs(), exec.entry())
+    }
+
+    /// Builds the stack for an executable that is left to its interpreter to relocate.
+    pub fn build_raw<D>(self, exec: &RawExec<D>) -> Result<PreparedStack> {
+        self.build_impl(exec.phdrs(), exec.entry())
+    }
+
+    fn build_impl(self, phdrs: &[ElfPhdr], exec_entry: usize) -> Result<PreparedStack> {
+        if self
+            .argv
+            .iter()
+            .chain(self.envp.iter())
+            .any(|item| item.contains(&0))
+        {
+            return Err(io_error(
+                "argument or environment entry contains a NUL byte",
+            ));
+        }
+        let (interp_base, entry) = self.interp.unwrap_or((0, exec_entry));
+
+        let aux = |key: c_ulong| unsafe { getauxval(key) } as usize;
+        let platform = match aux(AT_PLATFORM) {
+            0 => &[][..],
+            ptr => unsafe { core::ffi::CStr::from_ptr(ptr as *const _) }.to_bytes_with_nul(),
+        };
+        let mut random = [0u8; 16];
+        if unsafe { libc::getrandom(random.as_mut_ptr().cast(), random.len(), 0) }
+            != random.len() as isize
+        {
+            return Err(io_error("getrandom failed"));
+        }
+
+        let mut auxv = Vec::from([
+            (AT_PHDR, phdrs.as_ptr() as usize),
+            (AT_PHENT, size_of::<ElfPhdr>()),
+            (AT_PHNUM, phdrs.len()),
+            (AT_PAGESZ, PAGE_SIZE),
+            (AT_BASE, interp_base),
+            (AT_FLAGS, 0),
+            (AT_ENTRY, exec_entry),
+            (AT_UID, aux(AT_UID)),
+            (AT_EUID, aux(AT_EUID)),
+            (AT_GID, aux(AT_GID)),
+            (AT_EGID, aux(AT_EGID)),
+            (AT_SECURE, aux(AT_SECURE)),
+            (AT_CLKTCK, aux(AT_CLKTCK)),
+            (AT_HWCAP, aux(AT_HWCAP)),
+        ]);
+        // Entries the running kernel does not provide are left out rather than zeroed
+        for key in [AT_HWCAP2, AT_SYSINFO_EHDR] {
+            let value = aux(key);
+            if value != 0 {
+                auxv.push((key, value));
+            }
+        }
+
+        let strings = self
+            .argv
+            .iter()
+            .chain(self.envp.iter())
+            .map(|item| item.len() + 1)
+            .sum::<usize>()
+            + platform.len()
+            + random.len();
+        // argc, argv and envp with their terminators, auxv with AT_EXECFN, AT_PLATFORM,
+        // AT_RANDOM and AT_NULL
+        let words = 1 + self.argv.len() + 1 + self.envp.len() + 1 + (auxv.len() + 4) * 2;
+        let len = (self.stack_size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        if strings + words * size_of::<usize>() + 16 > len {
+            return Err(io_error("stack size is too small for the arguments"));
+        }
+
+        let memory = unsafe {
+            DefaultMmap::mmap_anonymous(
+                0,
+                len,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_PRIVATE,
+            )?
+        };
+        let mut stack = PreparedStack {
+            memory,
+            len,
+            sp: 0,
+            entry,
+            exec_entry,
+        };
+
+        // Strings and the random bytes go at the top, the pointer area below them
+        let mut cursor = memory.as_ptr() as usize + len;
+        let mut push = |bytes: &[u8], nul: bool| {
+            cursor -= bytes.len() + usize::from(nul);
+            unsafe {
+                let dst = cursor as *mut u8;
+                dst.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+                if nul {
+                    dst.add(bytes.len()).write(0);
+                }
+            }
+            cursor
+        };
+        let argv: Vec<usize> = self.argv.iter().map(|arg| push(arg, true)).collect();
+        let envp: Vec<usize> = self.envp.iter().map(|env| push(env, true)).collect();
+        let random = push(&random, false);
+        if !platform.is_empty() {
+            auxv.push((AT_PLATFORM, push(platform, false)));
+        }
+        if let Some(&execfn) = argv.first() {
+            auxv.push((AT_EXECFN, execfn));
+        }
+        auxv.push((AT_RANDOM, random));
+        auxv.push((AT_NULL, 0));
+
+        let mut words = Vec::with_capacity(words);
+        words.push(argv.len());
+        words.extend(argv.iter().copied());
+        words.push(0);
+        words.extend(envp.iter().copied());
+        words.push(0);
+        for (key, value) in auxv {
+            words.push(key as usize);
+            words.push(value);
+        }
+        // The ABIs require the stack pointer to be 16-byte aligned at argc
+        let sp = (cursor - words.len() * size_of::<usize>()) & !15;
+        unsafe {
+            (sp as *mut usize).copy_from_nonoverlapping(words.as_ptr(), words.len());
+        }
+        stack.sp = sp;
+        Ok(stack)
+    }
+}
+
+/// An initial stack built by [`StackBuilder`].
+///
+/// The mapping is released when this is dropped without being run.
+pub struct PreparedStack {
+    memory: NonNull<c_void>,
+    len: usize,
+    sp: usize,
+    /// Address control is transferred to
+    entry: usize,
+    /// Entry point of the executable, as recorded in `AT_ENTRY`
+    exec_entry: usize,
+}
+
+impl PreparedStack {
+    /// Returns the initial stack pointer, which points at `argc`.
+    #[inline]
+    pub fn sp(&self) -> usize {
+        self.sp
+    }
+
+    /// Returns the address control is transferred to, which is the entry point
+    /// of the interpreter if one was set.
+    #[inline]
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    unsafe fn enter(self, exec_entry: usize) -> ! {
+        assert_eq!(
+            self.exec_entry, exec_entry,
+            "the stack was built for another executable"
+        );
+        let (entry, sp) = (self.entry, self.sp);
+        core::mem::forget(self);
+        unsafe { handoff(entry, sp) }
+    }
+}
+
+impl Drop for PreparedStack {
+    fn drop(&mut self) {
+        let _ = unsafe { DefaultMmap::munmap(self.memory, self.len) };
+    }
+}
+
+impl<D> LoadedExec<D> {
+    /// Switches to `stack` and jumps to the entry point of the executable.
+    ///
+    /// # Safety
+    /// The executable takes over the process: it may overwrite any memory, and
+    /// this function never returns. All of its dependencies must be loaded and
+    /// the executable fully relocated unless an interpreter was set on the
+    /// [`StackBuilder`].
+    pub unsafe fn run(self, stack: PreparedStack) -> ! {
+        let entry = self.entry();
+        core::mem::forget(self);
+        unsafe { stack.enter(entry) }
+    }
+}
+
+impl<D> RawExec<D> {
+    /// Switches to `stack` and jumps to the entry point of the interpreter or,
+    /// for a static executable, of the executable itself.
+    ///
+    /// # Safety
+    /// See [`LoadedExec::run`]. A dynamically linked executable must have its
+    /// interpreter set on the [`StackBuilder`].
+    pub unsafe fn run(self, stack: PreparedStack) -> ! {
+        let entry = self.entry();
+        core::mem::forget(self);
+        unsafe { stack.enter(entry) }
+    }
+}
+
+/// Sets the stack pointer to `sp` and jumps to `entry`
+///
+/// The register the ABI uses for a function the program registers with `atexit`
+/// is cleared, as is the frame pointer.
+unsafe fn handoff(entry: usize, sp: usize) -> ! {
+    unsafe {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "x86_64")] {
+                core::arch::asm!(
+                    "mov rsp, rsi",
+                    "xor edx, edx",
+                    "xor ebp, ebp",
+                    "jmp rdi",
+                    in("rdi") entry,
+                    in("rsi") sp,
+                    options(noreturn)
+                )
+            } else if #[cfg(target_arch = "x86")] {
+                core::arch::asm!(
+                    "mov esp, eax",
+                    "xor edx, edx",
+                    "xor ebp, ebp",
+                    "jmp ecx",
+                    in("ecx") entry,
+                    in("eax") sp,
+                    options(noreturn)
+                )
+            } else if #[cfg(target_arch = "aarch64")] {
+                // x16 is a valid target for `br` under BTI
+                core::arch::asm!(
+                    "mov sp, x10",
+                    "mov x0, xzr",
+                    "mov x29, xzr",
+                    "mov x30, xzr",
+                    "br x16",
+                    in("x16") entry,
+                    in("x10") sp,
+                    options(noreturn)
+                )
+            } else if #[cfg(any(target_arch = "riscv64", target_arch = "riscv32"))] {
+                core::arch::asm!(
+                    "mv sp, t1",
+                    "li a0, 0",
+                    "li ra, 0",
+                    "li s0, 0",
+                    "jr t0",
+                    in("t0") entry,
+                    in("t1") sp,
+                    options(noreturn)
+                )
+            } else if #[cfg(target_arch = "loongarch64")] {
+                core::arch::asm!(
+                    "move $sp, $t1",
+                    "move $a0, $zero",
+                    "move $ra, $zero",
+                    "move $fp, $zero",
+                    "jr $t0",
+                    in("$t0") entry,
+                    in("$t1") sp,
+                    options(noreturn)
+                )
+            } else if #[cfg(target_arch = "arm")] {
+                core::arch::asm!(
+                    "mov sp, r1",
+                    "mov r0, #0",
+                    "mov lr, #0",
+                    "bx r12",
+                    in("r12") entry,
+                    in("r1") sp,
+                    options(noreturn)
+                )
+            }
+        }
+    }
+}
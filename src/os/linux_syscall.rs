@@ -1,5 +1,5 @@
 use crate::input::ElfReader;
-use crate::{Error, io_error};
+use crate::{Error, OsError, io_error};
 use crate::{
     Result,
     os::{MapFlags, Mmap, ProtFlags},
@@ -165,7 +165,7 @@ fn from_ret(value: usize, msg: &'static str) -> Result<usize> {
         // Truncation of the error value is guaranteed to never occur due to
         // the above check. This is the same check that musl uses:
         // https://git.musl-libc.org/cgit/musl/tree/src/internal/syscall_ret.c?h=v1.1.15
-        return Err(map_error(msg));
+        return Err(map_error(msg).with_source(OsError::new(-(value as isize) as i32)));
     }
     Ok(value)
 }
@@ -173,7 +173,10 @@ fn from_ret(value: usize, msg: &'static str) -> Result<usize> {
 #[cold]
 #[inline(never)]
 fn map_error(msg: &'static str) -> Error {
-    Error::Mmap { msg: msg.into() }
+    Error::Mmap {
+        msg: msg.into(),
+        source: None,
+    }
 }
 
 impl RawFile {
@@ -212,6 +215,18 @@ impl RawFile {
         };
         Ok(RawFile { fd: fd as _, name })
     }
+
+    /// Returns the size of the file in bytes.
+    #[cfg(feature = "mmap-file")]
+    pub(crate) fn size(&self) -> Result<usize> {
+        const SEEK_END: u32 = 2;
+        unsafe {
+            from_io_ret(
+                syscalls::raw_syscall!(Sysno::lseek, self.fd, 0, SEEK_END),
+                "lseek failed",
+            )
+        }
+    }
 }
 
 impl Drop for RawFile {
@@ -258,7 +273,7 @@ fn from_io_ret(value: usize, msg: &'static str) -> Result<usize> {
         // Truncation of the error value is guaranteed to never occur due to
         // the above check. This is the same check that musl uses:
         // https://git.musl-libc.org/cgit/musl/tree/src/internal/syscall_ret.c?h=v1.1.15
-        return Err(io_error(msg));
+        return Err(io_error(msg).with_source(OsError::new(-(value as isize) as i32)));
     }
     Ok(value)
 }
@@ -19,10 +19,28 @@ use core::ffi::c_int;
 
 pub use traits::Mmap;
 
+#[cfg(all(
+    feature = "std",
+    target_os = "linux",
+    any(
+        target_arch = "x86_64",
+        target_arch = "x86",
+        target_arch = "aarch64",
+        target_arch = "riscv64",
+        target_arch = "riscv32",
+        target_arch = "loongarch64",
+        target_arch = "arm"
+    )
+))]
+pub mod exec;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod traits;
 
 bitflags! {
-    #[derive(Clone, Copy, Debug, Default)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
     /// Memory protection flags for controlling access permissions.
     ///
     /// These flags determine what operations can be performed on a mapped memory region.
@@ -39,6 +57,11 @@ bitflags! {
 
         /// Allow executing code in the memory region.
         const PROT_EXEC = 4;
+
+        /// Only allow indirect branches to landing pads in executable memory
+        /// (aarch64 BTI). Backends whose [`Mmap::supports_bti`] returns `false`
+        /// are never passed this flag.
+        const PROT_BTI = 0x10;
     }
 }
 
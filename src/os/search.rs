@@ -0,0 +1,149 @@
+//! Search path resolution for `DT_NEEDED` entries
+//!
+//! The functions here only compute where a dependency lives, following the order
+//! of the glibc dynamic linker. Nothing is opened or loaded, so the result can be
+//! passed to any [`Loader`](crate::Loader) method.
+
+use crate::image::RawDylib;
+use alloc::string::String;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Expansion of `$LIB`
+const LIB: &str = if cfg!(target_pointer_width = "64") {
+    "lib64"
+} else {
+    "lib"
+};
+
+/// Expansion of `$PLATFORM`
+const PLATFORM: &str = if cfg!(target_arch = "x86") {
+    "i686"
+} else {
+    env::consts::ARCH
+};
+
+/// Finds the file of the dependency `needed` of `image`.
+///
+/// This is [`resolve_needed_inherited`] for an image that was loaded directly,
+/// so only its own `DT_RPATH` is considered.
+///
+/// # Returns
+/// The path of the first existing file, or `None` if no directory contains it.
+pub fn resolve_needed<D>(
+    image: &RawDylib<D>,
+    needed: &str,
+    extra_paths: &[PathBuf],
+) -> Option<PathBuf> {
+    resolve_needed_inherited(&[image], needed, extra_paths)
+}
+
+/// Finds the file of the dependency `needed` of `chain[0]`.
+///
+/// `chain` starts with the image that requires `needed`, followed by the image
+/// that loaded it, and so on up to the root. Directories are searched in the
+/// order of the glibc dynamic linker:
+/// 1. A name containing a slash is used as is.
+/// 2. If the requiring image has no `DT_RUNPATH`, the `DT_RPATH` of every image in `chain`.
+/// 3. The directories in `LD_LIBRARY_PATH`.
+/// 4. The `DT_RUNPATH` of the requiring image.
+/// 5. `extra_paths`, which stand in for the system directories.
+///
+/// In `DT_RPATH` and `DT_RUNPATH`, `$ORIGIN` expands to the directory of the
+/// [`path`](RawDylib::path) of the image the entry belongs to, `$LIB` to `lib64` or `lib` depending on the pointer width
+/// and `$PLATFORM` to the name of the target architecture. The `${NAME}` forms are
+/// accepted as well.
+///
+/// # Returns
+/// The path of the first existing file, or `None` if no directory contains it.
+pub fn resolve_needed_inherited<D>(
+    chain: &[&RawDylib<D>],
+    needed: &str,
+    extra_paths: &[PathBuf],
+) -> Option<PathBuf> {
+    if needed.contains('/') {
+        let path = PathBuf::from(needed);
+        return path.is_file().then_some(path);
+    }
+    let find = |dir: &Path| Some(dir.join(needed)).filter(|path| path.is_file());
+
+    let runpath = chain.first().and_then(|image| image.runpath());
+    if runpath.is_none() {
+        for image in chain {
+            let Some(rpath) = image.rpath() else {
+                continue;
+            };
+            let origin = origin(image.path());
+            if let Some(path) = split(rpath).find_map(|dir| find(&expand(dir, &origin))) {
+                return Some(path);
+            }
+        }
+    }
+    if let Some(path) = env::var("LD_LIBRARY_PATH").ok().and_then(|paths| {
+        paths
+            .split([':', ';'])
+            .find_map(|dir| find(Path::new(if dir.is_empty() { "." } else { dir })))
+    }) {
+        return Some(path);
+    }
+    if let Some(runpath) = runpath {
+        let origin = origin(chain[0].path());
+        if let Some(path) = split(runpath).find_map(|dir| find(&expand(dir, &origin))) {
+            return Some(path);
+        }
+    }
+    extra_paths.iter().find_map(|dir| find(dir))
+}
+
+/// Splits a search path list, where an empty entry means the current directory
+fn split(paths: &str) -> impl Iterator<Item = &str> {
+    paths
+        .split(':')
+        .map(|dir| if dir.is_empty() { "." } else { dir })
+}
+
+/// Returns the directory of the image read from `path`
+fn origin(path: &str) -> String {
+    match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy().into_owned(),
+        _ => ".".into(),
+    }
+}
+
+/// Substitutes `$ORIGIN`, `$LIB` and `$PLATFORM` in a search path entry
+///
+/// Unknown names are kept literally.
+fn expand(entry: &str, origin: &str) -> PathBuf {
+    let mut out = String::with_capacity(entry.len());
+    let mut rest = entry;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        let (name, tail) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", rest),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        match name {
+            "ORIGIN" => out.push_str(origin),
+            "LIB" => out.push_str(LIB),
+            "PLATFORM" => out.push_str(PLATFORM),
+            _ => {
+                out.push('$');
+                continue;
+            }
+        }
+        rest = tail;
+    }
+    out.push_str(rest);
+    PathBuf::from(out)
+}
@@ -0,0 +1,196 @@
+//! A memory mapping backend for deterministic tests
+//!
+//! [`BufferMmap`] backs every mapping with heap memory and never changes the
+//! protection of anything, so code embedding the loader can be tested where
+//! the OS mappings are unavailable or undesirable, e.g. under Miri. The
+//! protections the loader asks for are kept in a journal instead.
+use crate::{
+    Error, Result,
+    os::{MapFlags, Mmap, ProtFlags},
+    segment::PAGE_SIZE,
+};
+use alloc::{
+    alloc::{alloc_zeroed, dealloc, handle_alloc_error},
+    vec::Vec,
+};
+use core::{alloc::Layout, ffi::c_void, ptr::NonNull};
+use spin::Mutex;
+
+/// A protection change requested through [`Mmap::mprotect`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProtectionRecord {
+    /// Start of the range
+    pub addr: usize,
+    /// Length of the range in bytes
+    pub len: usize,
+    /// The requested protection
+    pub prot: ProtFlags,
+}
+
+impl ProtectionRecord {
+    /// Returns whether the range of the record contains `addr`
+    pub fn contains(&self, addr: usize) -> bool {
+        (self.addr..self.addr + self.len).contains(&addr)
+    }
+}
+
+/// Live heap regions as `(start, len)`
+static REGIONS: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+/// Every protection change in the order it was requested
+static JOURNAL: Mutex<Vec<ProtectionRecord>> = Mutex::new(Vec::new());
+
+/// An implementation of [`Mmap`] on top of heap allocations.
+///
+/// * `mmap` and `mmap_anonymous` allocate a zeroed, page-aligned region unless
+///   they are asked for a fixed address, which must lie in a region handed out
+///   before. Address hints are ignored.
+/// * `mprotect` only appends a [`ProtectionRecord`] to the journal returned by
+///   [`BufferMmap::protection_log`].
+/// * `munmap` frees a region once it is unmapped as a whole.
+///
+/// The contents of files are always copied, as there is nothing to map them
+/// into. The journal is shared by every loader using the backend, so tests
+/// running in parallel should only look at the records of their own objects.
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::{Loader, input::ElfBinary, os::testing::BufferMmap};
+///
+/// let bytes = std::fs::read("liba.so").unwrap();
+/// let lib = Loader::new()
+///     .with_mmap::<BufferMmap>()
+///     .load_dylib(ElfBinary::new("liba.so", &bytes))
+///     .unwrap();
+/// let range = lib.base()..lib.base() + lib.mapped_len();
+/// assert!(
+///     BufferMmap::protection_log()
+///         .iter()
+///         .any(|record| range.contains(&record.addr))
+/// );
+/// ```
+pub struct BufferMmap;
+
+impl BufferMmap {
+    /// Returns the protection changes requested so far, oldest first
+    pub fn protection_log() -> Vec<ProtectionRecord> {
+        JOURNAL.lock().clone()
+    }
+
+    /// Returns the last protection requested for a range holding `addr`
+    pub fn protection_at(addr: usize) -> Option<ProtFlags> {
+        JOURNAL
+            .lock()
+            .iter()
+            .rev()
+            .find(|record| record.contains(addr))
+            .map(|record| record.prot)
+    }
+
+    /// Empties the journal
+    pub fn clear_protection_log() {
+        JOURNAL.lock().clear();
+    }
+
+    fn allocate(len: usize) -> NonNull<c_void> {
+        let layout = Layout::from_size_align(len.max(1), PAGE_SIZE).unwrap();
+        let memory = unsafe { alloc_zeroed(layout) };
+        if memory.is_null() {
+            handle_alloc_error(layout);
+        }
+        REGIONS.lock().push((memory as usize, len));
+        unsafe { NonNull::new_unchecked(memory as _) }
+    }
+
+    /// Zeroes `addr..addr + len` after checking it was handed out before
+    fn reuse(addr: usize, len: usize) -> Result<NonNull<c_void>> {
+        let known = REGIONS
+            .lock()
+            .iter()
+            .any(|&(start, size)| addr >= start && addr + len <= start + size);
+        if !known {
+            return Err(map_error("fixed mapping outside of the allocated regions"));
+        }
+        unsafe { (addr as *mut u8).write_bytes(0, len) };
+        Ok(unsafe { NonNull::new_unchecked(addr as _) })
+    }
+}
+
+impl Mmap for BufferMmap {
+    unsafe fn mmap(
+        addr: Option<usize>,
+        len: usize,
+        _prot: ProtFlags,
+        flags: MapFlags,
+        _offset: usize,
+        _fd: Option<isize>,
+        need_copy: &mut bool,
+    ) -> Result<NonNull<c_void>> {
+        *need_copy = true;
+        match addr {
+            Some(addr) if flags.contains(MapFlags::MAP_FIXED) => Self::reuse(addr, len),
+            _ => Ok(Self::allocate(len)),
+        }
+    }
+
+    unsafe fn mmap_anonymous(
+        addr: usize,
+        len: usize,
+        _prot: ProtFlags,
+        flags: MapFlags,
+    ) -> Result<NonNull<c_void>> {
+        if flags.contains(MapFlags::MAP_FIXED) {
+            Self::reuse(addr, len)
+        } else {
+            Ok(Self::allocate(len))
+        }
+    }
+
+    /// Regions are only freed as a whole, so the requested alignment is not
+    /// applied.
+    unsafe fn mmap_reserve_aligned(
+        len: usize,
+        _align: usize,
+        use_file: bool,
+    ) -> Result<NonNull<c_void>> {
+        unsafe { Self::mmap_reserve(None, len, use_file) }
+    }
+
+    /// Hints are ignored, so no range can be claimed.
+    unsafe fn probe(_addr: usize, _len: usize) -> bool {
+        false
+    }
+
+    unsafe fn munmap(addr: NonNull<c_void>, len: usize) -> Result<()> {
+        let addr = addr.as_ptr() as usize;
+        let mut regions = REGIONS.lock();
+        if let Some(idx) = regions.iter().position(|&region| region == (addr, len)) {
+            regions.swap_remove(idx);
+            drop(regions);
+            unsafe {
+                dealloc(
+                    addr as _,
+                    Layout::from_size_align_unchecked(len.max(1), PAGE_SIZE),
+                )
+            };
+        }
+        Ok(())
+    }
+
+    unsafe fn mprotect(addr: NonNull<c_void>, len: usize, prot: ProtFlags) -> Result<()> {
+        JOURNAL.lock().push(ProtectionRecord {
+            addr: addr.as_ptr() as usize,
+            len,
+            prot,
+        });
+        Ok(())
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn map_error(msg: &'static str) -> Error {
+    Error::Mmap {
+        msg: msg.into(),
+        source: None,
+    }
+}
@@ -1,7 +1,7 @@
 use core::{ffi::c_void, ptr::NonNull};
 
 use super::{MapFlags, ProtFlags};
-use crate::Result;
+use crate::{Result, segment::PAGE_SIZE};
 
 /// A trait for low-level memory mapping operations.
 ///
@@ -160,4 +160,93 @@ pub trait Mmap {
             )
         }
     }
+
+    /// Checks whether the range `addr..addr + len` is free to be mapped.
+    ///
+    /// Used before loading an executable that must be mapped at a fixed address.
+    /// The default implementation reserves the range with `addr` as a hint through
+    /// [`Mmap::mmap_reserve`], checks that the hint was honoured and releases it again.
+    ///
+    /// # Arguments
+    /// * `addr` - Start of the range (page-aligned).
+    /// * `len` - Size of the range in bytes.
+    ///
+    /// # Returns
+    /// `true` if no part of the range is currently mapped.
+    ///
+    /// # Safety
+    /// Manipulates address space. The range is only reserved for the duration of the call.
+    unsafe fn probe(addr: usize, len: usize) -> bool {
+        match unsafe { Self::mmap_reserve(Some(addr), len, true) } {
+            Ok(ptr) => {
+                let _ = unsafe { Self::munmap(ptr, len) };
+                ptr.as_ptr() as usize == addr
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Reserves a region of virtual address space whose start is aligned to `align`.
+    ///
+    /// Used when a `PT_LOAD` segment requests an alignment larger than the page size.
+    /// The default implementation over-reserves by `align - PAGE_SIZE` bytes through
+    /// [`Mmap::mmap_reserve`] and unmaps the excess on both sides of the aligned region.
+    /// Backends that cannot release part of a reservation should override this method;
+    /// the loader checks the alignment of the returned address rather than assuming it.
+    ///
+    /// # Arguments
+    /// * `len` - Size of the region to reserve in bytes.
+    /// * `align` - Requested alignment, a power of two no smaller than the page size.
+    /// * `use_file` - Hint whether the region will be file-backed (may be ignored).
+    ///
+    /// # Returns
+    /// A pointer to the reserved region on success.
+    ///
+    /// # Safety
+    /// Manipulates address space. The reserved region should not be accessed until properly mapped.
+    unsafe fn mmap_reserve_aligned(
+        len: usize,
+        align: usize,
+        use_file: bool,
+    ) -> Result<NonNull<c_void>> {
+        if align <= PAGE_SIZE {
+            return unsafe { Self::mmap_reserve(None, len, use_file) };
+        }
+        let extra = align - PAGE_SIZE;
+        let ptr = unsafe { Self::mmap_reserve(None, len + extra, use_file) }?;
+        let addr = ptr.as_ptr() as usize;
+        let start = (addr + align - 1) & !(align - 1);
+        let head = start - addr;
+        let tail = extra - head;
+        unsafe {
+            if head != 0 {
+                Self::munmap(ptr, head)?;
+            }
+            if tail != 0 {
+                Self::munmap(NonNull::new_unchecked((start + len) as _), tail)?;
+            }
+            Ok(NonNull::new_unchecked(start as _))
+        }
+    }
+
+    /// Makes the instruction cache coherent with writes to a region of code.
+    ///
+    /// Called after text relocations patched an executable segment and its
+    /// protection was restored. Architectures whose instruction cache does
+    /// not snoop data writes, such as AArch64, must invalidate the range here.
+    /// The default implementation does nothing.
+    ///
+    /// # Arguments
+    /// * `addr` - Start of the region.
+    /// * `len` - Size of the region in bytes.
+    fn flush_icache(_addr: NonNull<c_void>, _len: usize) {}
+
+    /// Reports whether executable memory can be protected with [`ProtFlags::PROT_BTI`].
+    ///
+    /// When it does, the executable segments of objects built for branch target
+    /// identification are protected with the flag. The default implementation
+    /// returns `false`.
+    fn supports_bti() -> bool {
+        false
+    }
 }
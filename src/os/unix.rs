@@ -1,5 +1,5 @@
 use crate::{
-    Error, Result, io_error,
+    Error, OsError, Result, io_error,
     os::{MapFlags, Mmap, ProtFlags},
     input::ElfReader,
 };
@@ -114,6 +114,34 @@ impl Mmap for DefaultMmap {
         };
         Ok(unsafe { NonNull::new_unchecked(ptr) })
     }
+
+    unsafe fn probe(addr: usize, len: usize) -> bool {
+        // Without MAP_FIXED the address is only a hint, which the kernel ignores
+        // if any part of the range is in use
+        let flags = MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS;
+        let ptr = unsafe {
+            mmap(
+                addr as _,
+                len,
+                ProtFlags::PROT_NONE.bits(),
+                flags.bits(),
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return false;
+        }
+        unsafe { munmap(ptr, len) };
+        ptr as usize == addr
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    fn supports_bti() -> bool {
+        // The kernel rejects PROT_BTI on CPUs without the feature
+        const HWCAP2_BTI: libc::c_ulong = 1 << 17;
+        unsafe { libc::getauxval(libc::AT_HWCAP2) & HWCAP2_BTI != 0 }
+    }
 }
 
 impl Drop for RawFile {
@@ -127,7 +155,7 @@ impl RawFile {
         let name = CString::from_str(path).unwrap();
         let fd = unsafe { libc::open(name.as_ptr(), O_RDONLY) };
         if fd == -1 {
-            return Err(io_error("open failed"));
+            return Err(os_error(io_error("open failed")));
         }
         Ok(Self {
             name: path.to_string(),
@@ -141,12 +169,22 @@ impl RawFile {
             fd: raw_fd as isize,
         }
     }
+
+    /// Returns the size of the file in bytes.
+    #[cfg(feature = "mmap-file")]
+    pub(crate) fn size(&self) -> Result<usize> {
+        let size = unsafe { libc::lseek(self.fd as i32, 0, libc::SEEK_END) };
+        if size == -1 {
+            return Err(os_error(io_error("lseek failed")));
+        }
+        Ok(size as usize)
+    }
 }
 
 fn lseek(fd: i32, offset: usize) -> Result<()> {
     let off = unsafe { libc::lseek(fd, offset as _, SEEK_SET) };
     if off == -1 || off as usize != offset {
-        return Err(io_error("lseek failed"));
+        return Err(os_error(io_error("lseek failed")));
     }
     Ok(())
 }
@@ -163,7 +201,7 @@ fn read_exact(fd: i32, mut bytes: &mut [u8]) -> Result<()> {
 
         if result < 0 {
             // 出现错误
-            return Err(io_error("read error"));
+            return Err(os_error(io_error("read error")));
         } else if result == 0 {
             // 意外到达文件末尾
             return Err(io_error("failed to fill buffer"));
@@ -191,10 +229,47 @@ impl ElfReader for RawFile {
     }
 }
 
+/// Returns the `errno` left by the last failed libc call, if it can be read
+/// on this platform.
+fn last_os_error() -> Option<OsError> {
+    #[cfg(target_os = "linux")]
+    let errno = unsafe { libc::__errno_location() };
+    #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+    let errno = unsafe { libc::__errno() };
+    #[cfg(any(
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ))]
+    let errno = unsafe { libc::__error() };
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    )))]
+    let errno: *mut i32 = core::ptr::null_mut();
+    (!errno.is_null()).then(|| OsError::new(unsafe { *errno }))
+}
+
+/// Attaches the `errno` of the failed libc call to `err`.
+#[cold]
+#[inline(never)]
+fn os_error(err: Error) -> Error {
+    match last_os_error() {
+        Some(source) => err.with_source(source),
+        None => err,
+    }
+}
+
 #[cold]
 #[inline(never)]
 fn map_error(msg: &str) -> Error {
-    Error::Mmap {
+    os_error(Error::Mmap {
         msg: msg.to_string().into(),
-    }
+        source: None,
+    })
 }
@@ -1,8 +1,9 @@
 use crate::{
-    ElfReader, Error, Result, io_error,
+    ElfReader, Error, OsError, Result, io_error,
     mmap::{MapFlags, Mmap, ProtFlags},
+    segment::PAGE_SIZE,
 };
-use alloc::{ffi::CString, format, vec::Vec};
+use alloc::{boxed::Box, ffi::CString, format, vec::Vec};
 use core::{
     ffi::{CStr, c_void},
     mem::MaybeUninit,
@@ -102,6 +103,7 @@ impl Mmap for DefaultMmap {
                 let err_code = unsafe { GetLastError() };
                 return Err(Error::Mmap {
                     msg: format!("MapViewOfFile3 failed with error: {}", err_code).into(),
+                    source: Some(Box::new(OsError::new(err_code as i32))),
                 });
             }
             ptr.Value
@@ -151,6 +153,56 @@ impl Mmap for DefaultMmap {
         Ok(())
     }
 
+    /// A reservation cannot be partially released, so the excess on either side of
+    /// the aligned region stays reserved as inaccessible guard pages.
+    unsafe fn mmap_reserve_aligned(
+        len: usize,
+        align: usize,
+        use_file: bool,
+    ) -> Result<NonNull<c_void>> {
+        let extra = align.saturating_sub(PAGE_SIZE);
+        let ptr = unsafe { Self::mmap_reserve(None, len + extra, use_file) }?;
+        let addr = ptr.as_ptr() as usize;
+        let start = (addr + align - 1) & !(align - 1);
+        let head = start - addr;
+        let tail = extra - head;
+        // Without a file the reservation is committed, so the guards must be revoked
+        if !use_file {
+            unsafe {
+                if head != 0 {
+                    Self::mprotect(ptr, head, ProtFlags::PROT_NONE)?;
+                }
+                if tail != 0 {
+                    Self::mprotect(
+                        NonNull::new_unchecked((start + len) as _),
+                        tail,
+                        ProtFlags::PROT_NONE,
+                    )?;
+                }
+            }
+        }
+        Ok(unsafe { NonNull::new_unchecked(start as _) })
+    }
+
+    unsafe fn probe(addr: usize, len: usize) -> bool {
+        // Walk the regions covering the range and require all of them to be free
+        let end = addr + len;
+        let mut cur = addr;
+        while cur < end {
+            let mut info = MaybeUninit::<Memory::MEMORY_BASIC_INFORMATION>::uninit();
+            let size = size_of::<Memory::MEMORY_BASIC_INFORMATION>();
+            if unsafe { Memory::VirtualQuery(cur as _, info.as_mut_ptr(), size) } == 0 {
+                return false;
+            }
+            let info = unsafe { info.assume_init() };
+            if info.State != Memory::MEM_FREE {
+                return false;
+            }
+            cur = info.BaseAddress as usize + info.RegionSize;
+        }
+        true
+    }
+
     unsafe fn mmap_reserve(
         addr: Option<usize>,
         len: usize,
@@ -0,0 +1,56 @@
+// The plugin side of the ABI check. This file has no dependencies, so that
+// the test fixtures, which are built by `rustc` alone, can include it.
+
+/// The name of the function a plugin built with [`export_plugin!`] exports
+/// to report the hash of its entry point signature.
+pub const ABI_SYMBOL: &str = "__ELF_LOADER_ABI";
+
+/// Hashes the signature of an entry point with 64-bit FNV-1a.
+///
+/// The signature is the [`type_name`](core::any::type_name) of the entry
+/// point type, as computed by [`export_plugin!`] in the plugin and by
+/// [`LoadedDylib::entry_point`](crate::image::LoadedDylib::entry_point) in
+/// the host.
+pub const fn abi_hash(signature: &str) -> u64 {
+    let bytes = signature.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        hash ^= bytes[idx] as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        idx += 1;
+    }
+    hash
+}
+
+/// Declares the entry point of a plugin.
+///
+/// `export_plugin!(EntryType, path::to::entry)` checks that `entry` has the
+/// type `EntryType` and exports the function named by [`ABI_SYMBOL`], which
+/// returns the [`abi_hash`] of the `EntryType` signature. The entry point
+/// itself must be exported by the plugin, usually with `#[unsafe(no_mangle)]`.
+/// A plugin declares a single entry point.
+///
+/// # Examples
+/// ```rust,ignore
+/// pub type PluginEntry = extern "C" fn(u32) -> u32;
+///
+/// #[unsafe(no_mangle)]
+/// extern "C" fn plugin_entry(x: u32) -> u32 {
+///     x + 1
+/// }
+///
+/// elf_loader::export_plugin!(PluginEntry, plugin_entry);
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($ty:ty, $entry:path) => {
+        const _: $ty = $entry;
+
+        #[doc(hidden)]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn __ELF_LOADER_ABI() -> u64 {
+            $crate::plugin::abi_hash(::core::any::type_name::<$ty>())
+        }
+    };
+}
@@ -0,0 +1,100 @@
+//! Typed plugin entry points
+//!
+//! Plugin systems look up an entry point by name and cast it to the type the
+//! host expects, which goes wrong silently when the plugin was built against
+//! another version of that type. With this module, the plugin declares its
+//! entry point with [`export_plugin!`](crate::export_plugin), which also
+//! exports the hash of the entry point signature, and the host gets it with
+//! [`LoadedDylib::entry_point`], which refuses a plugin whose hash differs.
+//!
+//! ```rust,no_run
+//! use elf_loader::Loader;
+//!
+//! // Shared by the host and the plugin
+//! pub type PluginEntry = extern "C" fn(u32) -> u32;
+//!
+//! let plugin = Loader::new()
+//!     .load_dylib("libplugin.so")
+//!     .unwrap()
+//!     .relocator()
+//!     .relocate()
+//!     .unwrap();
+//! let entry = unsafe { plugin.entry_point::<PluginEntry>("plugin_entry") }.unwrap();
+//! assert_eq!(entry(1), 2);
+//! ```
+//!
+//! The signature is the [`type_name`](core::any::type_name) of the entry point
+//! type, which is only guaranteed to be the same when the host and the plugin
+//! are built by the same compiler.
+mod abi;
+
+use crate::image::{LoadedDylib, Symbol};
+use core::{
+    any::type_name,
+    fmt::{self, Display},
+};
+
+pub use abi::{ABI_SYMBOL, abi_hash};
+
+/// The reasons [`LoadedDylib::entry_point`] refuses an entry point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbiError {
+    /// The library does not export [`ABI_SYMBOL`], it was not built with
+    /// [`export_plugin!`](crate::export_plugin).
+    MissingAbi,
+    /// The entry point was declared with another signature.
+    Mismatch {
+        /// The hash of the signature requested by the host.
+        expected: u64,
+        /// The hash of the signature declared by the plugin.
+        found: u64,
+    },
+    /// The library does not export the entry point.
+    MissingEntry,
+}
+
+impl Display for AbiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiError::MissingAbi => write!(f, "the library does not export {ABI_SYMBOL}"),
+            AbiError::Mismatch { expected, found } => write!(
+                f,
+                "entry point signature mismatch: expected hash {expected:#018x}, found {found:#018x}"
+            ),
+            AbiError::MissingEntry => write!(f, "the library does not export the entry point"),
+        }
+    }
+}
+
+impl core::error::Error for AbiError {}
+
+impl<D> LoadedDylib<D> {
+    /// Gets the entry point of a plugin declared with
+    /// [`export_plugin!`](crate::export_plugin).
+    ///
+    /// The function named by [`ABI_SYMBOL`] is called to get the hash of the
+    /// signature declared by the plugin, which must match the hash of `T`.
+    ///
+    /// # Safety
+    /// The library must have been built with
+    /// [`export_plugin!`](crate::export_plugin). The hash only guards against
+    /// a plugin declaring another signature, not against one lying about it.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the entry point
+    ///
+    /// # Returns
+    /// * `Ok(symbol)` - If the plugin declared the signature of `T`
+    /// * `Err(AbiError)` - If the ABI symbol or the entry point are missing, or
+    ///   the signatures differ
+    pub unsafe fn entry_point<T>(&self, name: &str) -> Result<Symbol<'_, T>, AbiError> {
+        let abi = unsafe { self.get::<extern "C" fn() -> u64>(ABI_SYMBOL) }
+            .ok_or(AbiError::MissingAbi)?;
+        let expected = abi_hash(type_name::<T>());
+        let found = abi();
+        if found != expected {
+            return Err(AbiError::Mismatch { expected, found });
+        }
+        unsafe { self.get::<T>(name) }.ok_or(AbiError::MissingEntry)
+    }
+}
@@ -0,0 +1,227 @@
+//! Process-wide registry of loaded modules
+//!
+//! Modules loaded by a [`Loader`](crate::Loader) with the registry enabled are
+//! added here once they have been relocated, and removed again when their last
+//! reference is dropped. [`iterate_phdr`] walks the registered modules in the
+//! same way as `dl_iterate_phdr`, which is what unwinders use to find the
+//! `.eh_frame_hdr` of a module.
+use crate::{
+    elf::ElfPhdr,
+    image::{CoreInner, ElfCore},
+};
+use alloc::vec::Vec;
+use core::{
+    mem::ManuallyDrop,
+    ops::{ControlFlow, Range},
+};
+use elf::abi::PT_GNU_EH_FRAME;
+use spin::RwLock;
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::{Arc, Weak};
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::{Arc, Weak};
+
+/// A registered module.
+///
+/// The pointers stay valid for as long as the entry is in the registry, because
+/// a module removes itself before any of its memory is released.
+struct Entry {
+    /// Address of the module's `CoreInner`, used to identify it on removal
+    id: usize,
+    name: *const str,
+    base: usize,
+    phdrs: *const [ElfPhdr],
+    /// Weak reference to the module, whose user data type is erased
+    module: ErasedWeak,
+}
+
+/// A `Weak<CoreInner<D>>` for any `D`, with the functions that handle it
+struct ErasedWeak {
+    ptr: *const (),
+    upgrade: unsafe fn(*const ()) -> bool,
+    release: unsafe fn(*const ()),
+    drop_weak: unsafe fn(*const ()),
+}
+
+impl ErasedWeak {
+    fn new<D>(core: &ElfCore<D>) -> Self {
+        /// Takes a strong reference, if the module is still alive
+        unsafe fn upgrade<D>(ptr: *const ()) -> bool {
+            let weak = ManuallyDrop::new(unsafe { Weak::from_raw(ptr.cast::<CoreInner<D>>()) });
+            weak.upgrade().map(Arc::into_raw).is_some()
+        }
+        /// Releases a strong reference taken by `upgrade`
+        unsafe fn release<D>(ptr: *const ()) {
+            drop(unsafe { Arc::from_raw(ptr.cast::<CoreInner<D>>()) });
+        }
+        unsafe fn drop_weak<D>(ptr: *const ()) {
+            drop(unsafe { Weak::from_raw(ptr.cast::<CoreInner<D>>()) });
+        }
+        Self {
+            ptr: Weak::into_raw(Arc::downgrade(&core.inner)).cast(),
+            upgrade: upgrade::<D>,
+            release: release::<D>,
+            drop_weak: drop_weak::<D>,
+        }
+    }
+}
+
+impl Drop for ErasedWeak {
+    fn drop(&mut self) {
+        unsafe { (self.drop_weak)(self.ptr) };
+    }
+}
+
+/// A module kept alive while the [`iterate_phdr`] callback looks at it
+struct Pinned {
+    name: *const str,
+    base: usize,
+    phdrs: *const [ElfPhdr],
+    ptr: *const (),
+    release: unsafe fn(*const ()),
+}
+
+impl Drop for Pinned {
+    fn drop(&mut self) {
+        // Dropping the last reference unregisters the module, which takes the
+        // registry lock, so this only happens once it is released
+        unsafe { (self.release)(self.ptr) };
+    }
+}
+
+impl Entry {
+    fn pin(&self) -> Option<Pinned> {
+        unsafe { (self.module.upgrade)(self.module.ptr) }.then_some(Pinned {
+            name: self.name,
+            base: self.base,
+            phdrs: self.phdrs,
+            ptr: self.module.ptr,
+            release: self.module.release,
+        })
+    }
+}
+
+// Safety: the entry only points to data owned by a module that is `Send` and `Sync`
+unsafe impl Send for Entry {}
+unsafe impl Sync for Entry {}
+
+static REGISTRY: RwLock<Vec<Entry>> = RwLock::new(Vec::new());
+
+/// Information about a loaded module passed to the [`iterate_phdr`] callback.
+///
+/// This mirrors the `dl_phdr_info` structure used by `dl_iterate_phdr`.
+#[derive(Debug, Clone, Copy)]
+pub struct PhdrInfo<'a> {
+    name: &'a str,
+    base: usize,
+    phdrs: &'a [ElfPhdr],
+}
+
+impl<'a> PhdrInfo<'a> {
+    /// Returns the name of the module.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the base address of the module.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Returns the program headers of the module.
+    pub fn phdrs(&self) -> &'a [ElfPhdr] {
+        self.phdrs
+    }
+
+    /// Returns the address range of the `PT_GNU_EH_FRAME` segment, if present.
+    pub fn eh_frame_hdr(&self) -> Option<Range<usize>> {
+        eh_frame_hdr(self.base, self.phdrs)
+    }
+}
+
+/// Calls `f` for every module in the registry, in load order.
+///
+/// Iteration stops as soon as `f` returns [`ControlFlow::Break`], and that value
+/// is returned. The modules registered when the iteration starts are kept
+/// alive until it ends, and no lock is held while `f` runs, so `f` may load
+/// modules and drop handles to modules, including the one it is looking at.
+/// Modules loaded by `f` are not part of the iteration.
+///
+/// Only modules loaded with [`Loader::enable_registry`](crate::Loader::enable_registry)
+/// are visible here.
+///
+/// # Examples
+/// ```no_run
+/// use core::ops::ControlFlow;
+///
+/// let pc = 0x7f00_0000_1000;
+/// let found = elf_loader::iterate_phdr(|info| {
+///     if info.phdrs().iter().any(|phdr| {
+///         let start = info.base() + phdr.p_vaddr as usize;
+///         (start..start + phdr.p_memsz as usize).contains(&pc)
+///     }) {
+///         return ControlFlow::Break(info.eh_frame_hdr());
+///     }
+///     ControlFlow::Continue(())
+/// });
+/// ```
+pub fn iterate_phdr<B>(mut f: impl FnMut(PhdrInfo<'_>) -> ControlFlow<B>) -> ControlFlow<B> {
+    // The lock is released before `f` runs, since dropping a module from it
+    // would have to take the lock again to unregister the module
+    let modules: Vec<Pinned> = REGISTRY.read().iter().filter_map(Entry::pin).collect();
+    for module in &modules {
+        let info = unsafe {
+            PhdrInfo {
+                name: &*module.name,
+                base: module.base,
+                phdrs: &*module.phdrs,
+            }
+        };
+        f(info)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// Adds a relocated module to the registry.
+pub(crate) fn register<D>(core: &ElfCore<D>) {
+    let Some(phdrs) = core.phdrs() else {
+        return;
+    };
+    let mut registry = REGISTRY.write();
+    core.inner.set_registered();
+    registry.push(Entry {
+        id: core.inner_addr(),
+        name: core.name(),
+        base: core.base(),
+        phdrs,
+        module: ErasedWeak::new(core),
+    });
+    #[cfg(feature = "debugging")]
+    crate::debug::add(core.inner_addr(), core.link_map_view());
+}
+
+/// Removes a module from the registry.
+pub(crate) fn unregister(id: usize) {
+    #[cfg(feature = "debugging")]
+    crate::debug::remove(id);
+    // The weak reference of the entry is dropped after the lock is released
+    let mut registry = REGISTRY.write();
+    let removed = registry
+        .iter()
+        .position(|entry| entry.id == id)
+        .map(|idx| registry.remove(idx));
+    drop(registry);
+    drop(removed);
+}
+
+/// Finds the `PT_GNU_EH_FRAME` segment and returns its address range.
+pub(crate) fn eh_frame_hdr(base: usize, phdrs: &[ElfPhdr]) -> Option<Range<usize>> {
+    phdrs
+        .iter()
+        .find(|phdr| phdr.p_type == PT_GNU_EH_FRAME)
+        .map(|phdr| {
+            let start = base + phdr.p_vaddr as usize;
+            start..start + phdr.p_memsz as usize
+        })
+}
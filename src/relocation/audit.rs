@@ -0,0 +1,111 @@
+//! Auditing of symbol bindings
+use crate::image::LoadedCore;
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+/// Callbacks observing how the symbols of dynamic images are bound, in the
+/// spirit of the `LD_AUDIT` interface of the GNU dynamic linker.
+///
+/// Every method has a default implementation that does nothing, so an
+/// auditor only implements the events it is interested in. An auditor is
+/// installed with [`Loader::set_auditor`](crate::Loader::set_auditor) and sees
+/// the images loaded afterwards.
+///
+/// # Examples
+/// ```rust
+/// use elf_loader::relocation::{Auditor, SymbolBinding};
+///
+/// struct Bindings;
+///
+/// impl Auditor for Bindings {
+///     fn symbol_bound(&self, binding: &SymbolBinding<'_>) -> Option<usize> {
+///         println!(
+///             "binding file {} to {}: normal symbol `{}'",
+///             binding.requester(),
+///             binding.provider().unwrap_or("<lookup>"),
+///             binding.name()
+///         );
+///         None
+///     }
+/// }
+/// ```
+pub trait Auditor<D = ()>: Send + Sync {
+    /// Called once an image is relocated, before it is returned to the caller.
+    fn object_loaded(&self, _core: &LoadedCore<D>) {}
+
+    /// Called when a `GOT`, symbolic or eagerly bound `JUMP_SLOT` relocation
+    /// is bound to a symbol.
+    ///
+    /// # Returns
+    /// The address to bind the relocation to instead of
+    /// [`SymbolBinding::addr`], or `None` to keep it.
+    fn symbol_bound(&self, _binding: &SymbolBinding<'_>) -> Option<usize> {
+        None
+    }
+
+    /// Called when lazy binding resolves the PLT entry of `name` in `module`
+    /// to `addr`.
+    fn lazy_resolved(&self, _module: &str, _name: &str, _addr: usize) {}
+}
+
+/// A symbol reference bound during relocation, passed to
+/// [`Auditor::symbol_bound`].
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolBinding<'a> {
+    requester: &'a str,
+    provider: Option<&'a str>,
+    name: &'a str,
+    addr: usize,
+}
+
+impl<'a> SymbolBinding<'a> {
+    #[inline]
+    pub(crate) fn new(
+        requester: &'a str,
+        provider: Option<&'a str>,
+        name: &'a str,
+        addr: usize,
+    ) -> Self {
+        Self {
+            requester,
+            provider,
+            name,
+            addr,
+        }
+    }
+
+    /// Returns the name of the module holding the relocation.
+    #[inline]
+    pub fn requester(&self) -> &'a str {
+        self.requester
+    }
+
+    /// Returns the name of the module defining the symbol.
+    ///
+    /// It is `None` when the address came from the TLS allocator or a lookup
+    /// function such as `pre_find`, or when an undefined weak reference is
+    /// bound to null.
+    #[inline]
+    pub fn provider(&self) -> Option<&'a str> {
+        self.provider
+    }
+
+    /// Returns the name of the symbol.
+    #[inline]
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the address the relocation is bound to.
+    #[inline]
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+}
+
+/// Forwards the lazy binding events of a module to its auditor, without
+/// depending on the user data type of the module.
+pub(crate) type LazyAudit = Arc<dyn Fn(&str, &str, usize) + Send + Sync>;
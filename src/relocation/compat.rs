@@ -0,0 +1,70 @@
+//! `dlopen` compatible symbol resolution
+use crate::{
+    image::{ElfCoreRef, LoadedCore},
+    relocation::{SymbolLookup, global_lookup},
+};
+use alloc::vec::Vec;
+use bitflags::bitflags;
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    /// The `dlopen` mode replicated by
+    /// [`Relocator::dlopen_compat`](crate::relocation::Relocator::dlopen_compat).
+    ///
+    /// Exactly one of `LAZY` and `NOW` must be set, and at most one of `LOCAL`
+    /// and `GLOBAL`; without either the library is local, like with glibc.
+    pub struct CompatFlags: u32 {
+        /// Bind functions on their first call, like `RTLD_LAZY`.
+        const LAZY = 1 << 0;
+
+        /// Bind every reference before returning, like `RTLD_NOW`.
+        const NOW = 1 << 1;
+
+        /// Keep the library out of the global scope, like `RTLD_LOCAL`.
+        const LOCAL = 1 << 2;
+
+        /// Add the library to the global scope, like `RTLD_GLOBAL`.
+        const GLOBAL = 1 << 3;
+
+        /// Search the library and its dependencies before the global scope,
+        /// like `RTLD_DEEPBIND`.
+        const DEEPBIND = 1 << 4;
+    }
+}
+
+/// The lazy scope installed by
+/// [`Relocator::dlopen_compat`](crate::relocation::Relocator::dlopen_compat).
+///
+/// A function bound lazily is looked up in the global scope as it is when the
+/// function is first called, followed by the library itself and its
+/// dependencies, or the other way around with [`CompatFlags::DEEPBIND`]. The
+/// first definition is taken. Only weak references are held.
+pub struct DlopenScope {
+    /// The library itself followed by its dependencies
+    local: Vec<ElfCoreRef<()>>,
+    deepbind: bool,
+}
+
+impl DlopenScope {
+    pub(crate) fn new(local: Vec<ElfCoreRef<()>>, deepbind: bool) -> Self {
+        DlopenScope { local, deepbind }
+    }
+}
+
+impl SymbolLookup for DlopenScope {
+    fn lookup(&self, name: &str) -> Option<*const ()> {
+        let local = || {
+            self.local.iter().find_map(|lib| unsafe {
+                let core = lib.upgrade()?;
+                LoadedCore::from_core(core)
+                    .get::<()>(name)
+                    .map(|sym| sym.into_raw())
+            })
+        };
+        if self.deepbind {
+            local().or_else(|| global_lookup(name))
+        } else {
+            global_lookup(name).or_else(local)
+        }
+    }
+}
@@ -1,16 +1,32 @@
 //! Relocation of elf objects
 use crate::{
-    Result,
+    Error, RelocationErrorContext, RelocationTable, Result, UnknownDynamicPolicy,
     arch::*,
-    elf::{ElfRelType, ElfRelr},
-    image::{CoreInner, DynamicImage, ElfCoreRef, LoadedCore},
+    elf::{ElfAltRelType, ElfDynamic, ElfRelType, ElfRelr, RelEntry, SymbolInfo, rel_entries},
+    image::{CopyRecord, CoreInner, DynamicImage, DynamicInfo, ElfCore, ElfCoreRef, LoadedCore},
+    registry, relocate_error,
     relocation::{
-        RelocHelper, RelocValue, RelocationContext, RelocationHandler, SymbolLookup,
-        find_symbol_addr, likely, reloc_error, unlikely,
+        Handled, LazyAudit, Lookup, LookupPolicy, ParallelExecutor, Phase, PhaseTimer, PostFini,
+        PreInit, RelocHelper, RelocKind, RelocValue, RelocateOptions, RelocationContext,
+        RelocationHandler, SymbolLookup, call_ifunc, likely, main_program_pos, register_global,
+        reloc_error, unlikely,
     },
+    segment::ElfSegments,
 };
-use alloc::vec::Vec;
-use core::{num::NonZeroUsize, ptr::null_mut};
+use alloc::{boxed::Box, string::ToString, vec::Vec};
+use core::{
+    num::NonZeroUsize,
+    ops::{Deref, Range},
+    sync::atomic::{
+        AtomicUsize,
+        Ordering::{AcqRel, Acquire, Release},
+    },
+};
+use elf::abi::{PF_W, PT_LOAD, STT_GNU_IFUNC};
+use spin::RwLock;
+
+#[cfg(feature = "trace")]
+use crate::trace::TraceEvent;
 
 #[cfg(not(feature = "portable-atomic"))]
 use alloc::sync::Arc;
@@ -26,23 +42,35 @@ where
     /// Weak references to the local libraries for symbol lookup
     libs: Vec<ElfCoreRef<D>>,
     custom_scope: Option<S>,
+    /// Decides whether the local libraries are searched before the parent scope
+    policy: Option<LookupPolicy>,
 }
 
+// Safety: the libraries are dependencies of the module the scope binds, which
+// holds strong references to them for as long as one of its functions can run.
+// The references upgraded by a lookup are therefore never the last ones, and
+// only the symbol tables are read, never the user data.
+unsafe impl<D, S: SymbolLookup + Send> Send for LazyScope<D, S> {}
+// Safety: see above
+unsafe impl<D, S: SymbolLookup + Sync> Sync for LazyScope<D, S> {}
+
 impl<D, S: SymbolLookup> SymbolLookup for LazyScope<D, S> {
     fn lookup(&self, name: &str) -> Option<*const ()> {
-        // First try the parent scope if available
-        if let Some(parent) = &self.custom_scope {
-            if let Some(sym) = parent.lookup(name) {
-                return Some(sym);
-            }
+        let parent = || self.custom_scope.as_ref()?.lookup(name);
+        let local = || {
+            self.libs.iter().find_map(|lib| unsafe {
+                let core = lib.upgrade()?;
+                LoadedCore::from_core(core)
+                    .get::<()>(name)
+                    .map(|sym| sym.into_raw())
+            })
+        };
+        // The parent scope comes first unless the policy prefers the scope
+        if LookupPolicy::scope_first(self.policy.as_ref(), RelocKind::Func, name) {
+            local().or_else(parent)
+        } else {
+            parent().or_else(local)
         }
-        // Then try the local libraries
-        self.libs.iter().find_map(|lib| unsafe {
-            let core = lib.upgrade()?;
-            LoadedCore::from_core(core)
-                .get::<()>(name)
-                .map(|sym| sym.into_raw())
-        })
     }
 }
 
@@ -52,8 +80,7 @@ impl<D, S: SymbolLookup> SymbolLookup for LazyScope<D, S> {
 /// The address must point to a valid IFUNC function.
 #[inline(always)]
 unsafe fn resolve_ifunc(addr: RelocValue<usize>) -> RelocValue<usize> {
-    let ifunc: fn() -> usize = unsafe { core::mem::transmute(addr.0) };
-    RelocValue::new(ifunc())
+    RelocValue::new(unsafe { call_ifunc(addr.0) })
 }
 
 impl<D> DynamicImage<D> {
@@ -64,8 +91,8 @@ impl<D> DynamicImage<D> {
         post_find: &PostS,
         mut pre_handler: PreH,
         mut post_handler: PostH,
-        lazy: Option<bool>,
         lazy_scope: Option<LazyS>,
+        options: RelocateOptions<'_, D>,
     ) -> Result<LoadedCore<D>>
     where
         D: 'static,
@@ -75,14 +102,43 @@ impl<D> DynamicImage<D> {
         PreH: RelocationHandler,
         PostH: RelocationHandler,
     {
+        let RelocateOptions {
+            lazy,
+            strict,
+            apply_relro,
+            collect_missing,
+            policy,
+            version_policy,
+            scope_index,
+            self_pos,
+            report,
+            executor,
+            pre_init,
+            post_fini,
+        } = options;
+        let auditor = self.auditor().cloned();
+        #[cfg(feature = "aarch64-pac")]
+        self.core_ref()
+            .inner
+            .pac
+            .store(self.pac(), core::sync::atomic::Ordering::Relaxed);
         // Optimization: check if relocation is empty
         if self.relocation().is_empty() {
-            let core = self.into_core();
-            let relocated = unsafe { LoadedCore::from_core(core) };
-            return Ok(relocated);
+            // Still run the initializers so that the finalizers run on unload
+            return self.into_loaded(&[], Vec::new(), pre_init, post_fini);
         }
 
-        let is_lazy = lazy.unwrap_or(self.is_lazy());
+        // Without lazy binding support, a default lazy request falls back to eager binding
+        let is_lazy = if self.relocation().supports_lazy() {
+            lazy.unwrap_or(self.is_lazy())
+        } else if lazy == Some(true) {
+            return Err(relocate_error(alloc::format!(
+                "{}: PLT relocations converted from another format can't be bound lazily",
+                self.name()
+            )));
+        } else {
+            false
+        };
         let mut helper = RelocHelper {
             scope,
             pre_find,
@@ -90,15 +146,48 @@ impl<D> DynamicImage<D> {
             pre_handler: &mut pre_handler,
             post_handler: &mut post_handler,
             dependency_flags: alloc::vec![false; scope.len()],
+            report,
+            policy: policy.as_ref(),
+            version_policy,
+            scope_index: scope_index.filter(|index| index.covers(scope)),
+            self_pos,
+            missing: collect_missing.then(Vec::new),
+            auditor: auditor.as_deref(),
+            main_program: main_program_pos(scope),
         };
 
-        self.relocate_relative().relocate_dynrel(&mut helper)?;
+        if strict {
+            self.audit()?;
+        }
+        // Text relocations patch segments that are mapped without write access
+        let text = self.text_segments();
+        if let Some(text) = text {
+            #[cfg(feature = "trace")]
+            crate::trace::emit(TraceEvent::TextRel { lib: self.name() });
+            text.unprotect()?;
+        }
+        let mut timer = PhaseTimer::new(helper.stats().is_some());
+        self.relocate_relative(executor);
+        timer.lap(helper.stats(), Phase::Relative);
+        if let Some(stats) = helper.stats() {
+            stats.add_type(REL_RELATIVE, self.relocation().relative_count());
+        }
+        let dynrel = self.relocate_dynrel(&mut helper, 0..self.relocation().dynrel_len());
+        if let Some(text) = text {
+            text.protect()?;
+        }
+        dynrel?;
+        if self.core_ref().is_main_program() {
+            redirect_to_copies(self.core_ref(), scope)?;
+        }
+        timer.lap(helper.stats(), Phase::Symbolic);
 
-        let deps = {
+        {
             let needed_libs = self.needed_libs();
 
             let lazy_scope = if is_lazy {
-                let libs = if lazy_scope.is_none() {
+                // With a policy, the dependencies are searched alongside the lazy scope
+                let libs = if lazy_scope.is_none() || policy.is_some() {
                     scope
                         .iter()
                         .filter(|lib| needed_libs.contains(&lib.name()))
@@ -110,66 +199,556 @@ impl<D> DynamicImage<D> {
                 Some(LazyScope {
                     libs,
                     custom_scope: lazy_scope,
+                    policy: policy.clone(),
                 })
             } else {
                 None
             };
 
-            self.relocate_pltrel(is_lazy, lazy_scope, &mut helper)?
-                .finish();
+            self.relocate_pltrel(is_lazy, lazy_scope, &mut helper)?;
+            timer.lap(helper.stats(), Phase::Plt);
+            if let Some(symbols) = helper.missing.take()
+                && !symbols.is_empty()
+            {
+                return Err(Error::MissingSymbols {
+                    module: self.name().to_string(),
+                    symbols,
+                });
+            }
+            if !is_lazy && apply_relro {
+                self.protect_relro()?;
+            }
+            timer.lap(helper.stats(), Phase::Relro);
+        }
+        self.into_loaded(scope, helper.dependency_flags, pre_init, post_fini)
+    }
 
-            scope
-                .iter()
-                .zip(helper.dependency_flags)
-                .filter_map(|(module, flag)| {
-                    (flag || needed_libs.contains(&module.name())).then(|| module.clone())
-                })
-                .collect::<Vec<_>>()
+    /// Runs the initializers of a relocated object and publishes it.
+    ///
+    /// The dependencies of the object are the libraries of `scope` flagged in
+    /// `dependency_flags` and the ones it names in `DT_NEEDED`.
+    pub(crate) fn into_loaded(
+        self,
+        scope: &[LoadedCore<D>],
+        dependency_flags: Vec<bool>,
+        pre_init: Option<&PreInit<D>>,
+        post_fini: Option<Arc<PostFini>>,
+    ) -> Result<LoadedCore<D>>
+    where
+        D: 'static,
+    {
+        let needed_libs = self.needed_libs();
+        let deps: Arc<[LoadedCore<D>]> = scope
+            .iter()
+            .zip(dependency_flags)
+            .filter_map(|(module, flag)| {
+                (flag || needed_libs.contains(&module.name())).then(|| module.clone())
+            })
+            .collect();
+        self.finish(&deps, pre_init, post_fini)?;
+
+        let auditor = self.auditor().cloned();
+        let (register, global) = (self.register(), self.global());
+        let core = self.into_core();
+        if register {
+            registry::register(&core);
+        }
+        let relocated = LoadedCore { core, deps };
+        if global {
+            register_global(&relocated);
+        }
+        if let Some(auditor) = auditor {
+            auditor.object_loaded(&relocated);
+        }
+        Ok(relocated)
+    }
+}
+
+/// Handler called when lazy binding cannot resolve a symbol.
+///
+/// It receives the name of the symbol, and the name and base address of the
+/// module whose PLT slot is being bound, and returns the address to bind the
+/// slot to. The handler only borrows the module, it cannot keep it alive.
+pub type UnresolvedHandler = fn(&str, &str, usize) -> Option<*const ()>;
+
+static UNRESOLVED_HANDLER: RwLock<Option<UnresolvedHandler>> = RwLock::new(None);
+
+/// Installs the process-wide handler for symbols that lazy binding cannot resolve.
+///
+/// Lazy binding first looks a symbol up in the lazy scope of the module, then
+/// in the [`global_scope`](crate::global_scope), then in the fallback set with [`LoadedDylib::set_lazy_fallback`](crate::image::LoadedDylib::set_lazy_fallback).
+/// If neither defines it, `handler` is called; if there is no handler or it
+/// returns `None`, the process is aborted with a message naming the symbol
+/// and the module.
+///
+/// # Arguments
+/// * `handler` - The new handler, or `None` to remove the current one.
+///
+/// # Returns
+/// The previously installed handler.
+pub fn set_unresolved_handler(handler: Option<UnresolvedHandler>) -> Option<UnresolvedHandler> {
+    core::mem::replace(&mut *UNRESOLVED_HANDLER.write(), handler)
+}
+
+/// Resolves a symbol that the lazy scope does not define
+#[cold]
+fn lazy_bind_fallback(dylib: &CoreInner, info: &DynamicInfo, name: &str) -> usize {
+    if let Some(sym) = info
+        .lazy_fallback
+        .read()
+        .as_ref()
+        .and_then(|fallback| fallback.lookup(name))
+    {
+        return sym as usize;
+    }
+    let handler = *UNRESOLVED_HANDLER.read();
+    if let Some(handler) = handler
+        && let Some(sym) = handler(name, &dylib.name, dylib.segments.base())
+    {
+        return sym as usize;
+    }
+    panic!(
+        "{}: lazy binding failed, undefined symbol: {}",
+        dylib.name, name
+    );
+}
+
+/// Points the references to the variables the main program `core` copied
+/// with `R_*_COPY` at the copies.
+///
+/// The modules of `scope` are relocated before the main program, so their
+/// `GLOB_DAT` and absolute references bind to the definitions that were
+/// copied. Those that still point into a copied definition are rewritten, in
+/// the main program too, so that every module sees the same variable.
+/// Modules with text relocations are left alone, as their code is mapped
+/// read-only again.
+fn redirect_to_copies<D>(core: &ElfCore<D>, scope: &[LoadedCore<D>]) -> Result<()> {
+    let copies = core.copies();
+    if copies.is_empty() {
+        return Ok(());
+    }
+    let symtab = core.symtab();
+    for module in core::iter::once(core).chain(scope.iter().map(|lib| &lib.core)) {
+        let Some(info) = module.inner.dynamic_info.as_ref() else {
+            continue;
         };
+        let dynamic = ElfDynamic::new(
+            info.dynamic_ptr.as_ptr(),
+            module.segments(),
+            UnknownDynamicPolicy::Ignore,
+        )?;
+        if dynamic.textrel {
+            continue;
+        }
+        let entries = dynamic
+            .dynrel
+            .unwrap_or(&[])
+            .iter()
+            .map(|rel| (rel.r_type(), rel.r_symbol(), rel.r_offset()))
+            .chain(
+                dynamic
+                    .alt_dynrel
+                    .unwrap_or(&[])
+                    .iter()
+                    .map(|rel| (rel.r_type(), rel.r_symbol(), rel.r_offset())),
+            );
+        let segments = module.segments();
+        info.with_relro_writable(|| -> Result<()> {
+            for (r_type, r_sym, r_offset) in entries {
+                let r_type = r_type as u32;
+                if (r_type != REL_GOT && r_type != REL_SYMBOLIC) || r_sym == 0 {
+                    continue;
+                }
+                let value = unsafe { segments.get_ptr::<usize>(r_offset)?.read() };
+                let name = module.symtab().symbol_idx(r_sym).1.name();
+                let copy = copies.iter().find(|copy| {
+                    value.wrapping_sub(copy.def) < copy.len.max(1)
+                        && symtab.symbol_idx(copy.sym).1.name() == name
+                });
+                if let Some(copy) = copy {
+                    let addr = copy.addr + (value - copy.def);
+                    segments.write(r_offset, RelocValue::new(addr));
+                }
+            }
+            Ok(())
+        })??;
+    }
+    Ok(())
+}
+
+/// Resolves a symbol against the definitions of a `-Bsymbolic` object itself
+#[inline]
+fn symbolic_lookup(
+    dylib: &CoreInner,
+    info: &DynamicInfo,
+    syminfo: &SymbolInfo,
+) -> Option<*const ()> {
+    if !info.symbolic {
+        return None;
+    }
+    let sym = dylib
+        .symtab
+        .lookup_filter(syminfo, &mut syminfo.precompute())?;
+    let addr = dylib.segments.base() + sym.st_value();
+    Some(if sym.st_type() == STT_GNU_IFUNC {
+        unsafe { call_ifunc(addr) as _ }
+    } else {
+        addr as _
+    })
+}
+
+/// State of lazy binding for a module
+pub(crate) struct LazyBinding {
+    /// Lookup for the symbols of `JUMP_SLOT` entries
+    scope: Arc<dyn SymbolLookup + Send + Sync>,
+    /// One bit per PLT relocation entry, set once its GOT entry is bound
+    bound: Box<[AtomicUsize]>,
+    /// Notified of every entry bound by `dl_fixup`
+    audit: Option<LazyAudit>,
+}
 
-        Ok(unsafe { LoadedCore::from_core_deps(self.into_core(), deps) })
+impl LazyBinding {
+    const BITS: usize = usize::BITS as usize;
+
+    pub(crate) fn new(
+        scope: Arc<dyn SymbolLookup + Send + Sync>,
+        entries: usize,
+        audit: Option<LazyAudit>,
+    ) -> Self {
+        Self {
+            scope,
+            bound: (0..entries.div_ceil(Self::BITS))
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+            audit,
+        }
+    }
+
+    #[inline]
+    fn is_bound(&self, idx: usize) -> bool {
+        self.bound[idx / Self::BITS].load(Acquire) & (1 << (idx % Self::BITS)) != 0
+    }
+
+    #[inline]
+    fn mark_bound(&self, idx: usize) {
+        self.bound[idx / Self::BITS].fetch_or(1 << (idx % Self::BITS), Release);
     }
 }
 
 /// Lazy binding fixup function called by PLT (Procedure Linkage Table)
+///
+/// Several threads may enter this function for the same slot. The GOT entry is
+/// only replaced if it still holds the value read on entry, and entries that are
+/// already bound are returned as they are, so every caller ends up at the same
+/// address. The PLT stubs read GOT entries with plain aligned word loads, which
+/// are single-copy atomic on all supported architectures. They need no acquire
+/// ordering because the code and data a resolved address points to were
+/// published before relocation of the module finished.
 pub(crate) unsafe extern "C" fn dl_fixup(dylib: &CoreInner, rela_idx: usize) -> usize {
-    // Get the relocation entry for this function call
-    let rela = unsafe {
-        &*dylib
-            .dynamic_info
-            .as_ref()
-            .unwrap()
-            .pltrel
-            .unwrap()
-            .add(rela_idx)
-            .as_ptr()
-    };
-    let r_type = rela.r_type();
-    let r_sym = rela.r_symbol();
-    let segments = &dylib.segments;
+    let info = dylib.dynamic_info.as_ref().unwrap();
+    // Get the relocation entry for this function call, in either table format
+    let (r_offset, r_type, r_sym, _) = info.pltrel_entry(rela_idx, dylib.segments.base()).unwrap();
+    let slot = dylib.segments.get_atomic(r_offset);
 
     // Ensure this is a jump slot relocation for a valid symbol
     assert!(r_type == REL_JUMP_SLOT as usize && r_sym != 0);
 
+    // The slot is read before its bound flag: a rebind marks the slot before
+    // writing it, so a value written by a rebind is never taken as unbound
+    let unbound = slot.load(Acquire);
+    // Another thread bound the slot after this one read it in the PLT stub
+    let binding = info.lazy_binding.read().clone();
+    if binding
+        .as_ref()
+        .is_some_and(|binding| binding.is_bound(rela_idx))
+    {
+        return slot.load(Acquire);
+    }
+
     // Get symbol information
     let (_, syminfo) = dylib.symtab.symbol_idx(r_sym);
 
-    // Look up symbol in local scope
-    let symbol = dylib
-        .dynamic_info
-        .as_ref()
-        .unwrap()
-        .lazy_scope
+    // Look up symbol in the TLS allocator first, then in the object itself if
+    // it is symbolic, then in local scope and finally in the global scope
+    let symbol = match dylib
+        .tls
         .as_ref()
-        .unwrap()
-        .lookup(syminfo.name())
-        .expect("lazy bind fail") as usize;
+        .and_then(|tls| tls.lookup(syminfo.name()))
+        .or_else(|| symbolic_lookup(dylib, info, &syminfo))
+        .or_else(|| binding.as_ref()?.scope.lookup(syminfo.name()))
+        .or_else(|| dylib.global_lookup(syminfo.name()))
+    {
+        Some(symbol) => symbol as usize,
+        None => lazy_bind_fallback(dylib, info, syminfo.name()),
+    };
+    // The PLT stub branches to the slot without authenticating it
+    #[cfg(feature = "aarch64-pac")]
+    let symbol = if dylib.pac.load(core::sync::atomic::Ordering::Relaxed) {
+        crate::arch::strip_pac(symbol)
+    } else {
+        symbol
+    };
 
-    // Write the resolved symbol address to the GOT entry
-    segments.write(rela.r_offset(), RelocValue::new(symbol));
+    // Write the resolved symbol address to the GOT entry, unless a concurrent
+    // fixup or rebind got there first
+    let symbol = match slot.compare_exchange(unbound, symbol, AcqRel, Acquire) {
+        Ok(_) => {
+            if let Some(audit) = binding.as_ref().and_then(|binding| binding.audit.as_ref()) {
+                audit(&dylib.name, syminfo.name(), symbol);
+            }
+            symbol
+        }
+        Err(bound) => bound,
+    };
+    if let Some(binding) = &binding {
+        binding.mark_bound(rela_idx);
+    }
     symbol
 }
 
+/// A `JUMP_SLOT` relocation of a loaded module.
+///
+/// Returned by [`LoadedCore::plt_entries`](crate::image::LoadedCore::plt_entries),
+/// it describes the GOT slot through which the PLT stub of an imported function
+/// jumps.
+pub struct PltEntry<'a> {
+    info: &'a DynamicInfo,
+    segments: &'a ElfSegments,
+    symbol: &'a str,
+    r_offset: usize,
+    /// Index in the PLT relocation table, which lazy binding tracks
+    idx: usize,
+}
+
+impl<'a> PltEntry<'a> {
+    /// Returns the name of the imported function.
+    #[inline]
+    pub fn symbol(&self) -> &'a str {
+        self.symbol
+    }
+
+    /// Returns the address of the GOT slot.
+    ///
+    /// The slot may be protected by RELRO and may be bound lazily at any time,
+    /// so writing through it is the caller's responsibility. Prefer
+    /// [`redirect`](Self::redirect).
+    #[inline]
+    pub fn got_slot(&self) -> *mut usize {
+        self.segments.get_atomic(self.r_offset).as_ptr()
+    }
+
+    /// Returns the address the slot currently points to.
+    ///
+    /// Until a lazily bound slot is resolved, this is the address of the PLT
+    /// code that enters the resolver.
+    #[inline]
+    pub fn current_target(&self) -> usize {
+        self.segments.get_atomic(self.r_offset).load(Acquire)
+    }
+
+    /// Points the slot at `addr`.
+    ///
+    /// The slot is written with a single atomic store, with the RELRO segment
+    /// made writable for the duration of the update if needed. A slot that is
+    /// not bound yet is marked as bound, so lazy binding will not overwrite it.
+    /// This must not be called from the closure passed to
+    /// [`LoadedCore::with_relro_writable`](crate::image::LoadedCore::with_relro_writable)
+    /// on the same module.
+    ///
+    /// # Safety
+    /// `addr` must point to a function with the same signature and calling
+    /// convention as the one it replaces, and must stay valid for as long as
+    /// the module may call it.
+    pub unsafe fn redirect(&self, addr: *const ()) -> Result<()> {
+        let binding = self.info.lazy_binding.read().clone();
+        self.info
+            .with_relro_writable(|| self.store(addr as usize, binding.as_deref()))
+    }
+
+    /// Writes `addr` to the slot and keeps pending fixups from overwriting it
+    ///
+    /// The slot is marked before it is written, see [`dl_fixup`].
+    fn store(&self, addr: usize, binding: Option<&LazyBinding>) {
+        if let Some(binding) = binding {
+            binding.mark_bound(self.idx);
+        }
+        self.segments
+            .write_atomic(self.r_offset, RelocValue::new(addr));
+    }
+}
+
+/// Iterates over the `JUMP_SLOT` relocations of a module, in both table formats
+pub(crate) fn plt_entries<D>(dylib: &CoreInner<D>) -> impl Iterator<Item = PltEntry<'_>> {
+    let base = dylib.segments.base();
+    dylib
+        .dynamic_info
+        .as_deref()
+        .into_iter()
+        .flat_map(move |info| {
+            rel_entries(info.pltrel, info.alt_pltrel, base)
+                .enumerate()
+                .filter(|(_, (_, r_type, r_sym, _))| {
+                    *r_type == REL_JUMP_SLOT as usize && *r_sym != 0
+                })
+                .map(move |(idx, (r_offset, _, r_sym, _))| PltEntry {
+                    info,
+                    segments: &dylib.segments,
+                    symbol: dylib.symtab.symbol_idx(r_sym).1.name(),
+                    r_offset,
+                    idx,
+                })
+        })
+}
+
+/// Point every `JUMP_SLOT` entry that refers to `name` at `addr`
+///
+/// GOT entries are updated with atomic word stores, so this can run while
+/// other slots are being bound lazily. If RELRO protection has already been
+/// applied, the segment is made writable for the duration of the update.
+///
+/// # Returns
+/// The number of GOT entries that were patched.
+pub(crate) fn rebind_symbol<D>(dylib: &CoreInner<D>, name: &str, addr: usize) -> Result<usize> {
+    let Some(info) = dylib.dynamic_info.as_ref() else {
+        return Ok(0);
+    };
+    let binding = info.lazy_binding.read().clone();
+
+    info.with_relro_writable(|| {
+        let mut count = 0;
+        for entry in plt_entries(dylib).filter(|entry| entry.symbol == name) {
+            entry.store(addr, binding.as_deref());
+            count += 1;
+        }
+        count
+    })
+}
+
+/// Symbol sources that `relocate_dynrel` consults for a relocation entry
+#[inline]
+fn symbol_lookup(r_type: u32, r_sym: usize) -> Lookup {
+    match r_type {
+        _ if r_sym == 0 => Lookup::None,
+        // Compared by value, the two types are the same on RISC-V and LoongArch
+        _ if r_type == REL_GOT || r_type == REL_SYMBOLIC => Lookup::All,
+        REL_DTPMOD | REL_DTPOFF | REL_TPOFF | REL_COPY => Lookup::Scope,
+        #[cfg(target_arch = "riscv64")]
+        REL_TLSDESC => Lookup::Scope,
+        _ => Lookup::None,
+    }
+}
+
+/// Offset from the thread pointer of a variable in the static TLS block, or of
+/// the TLS block of `core` itself when `r_sym` is 0
+fn tp_offset<D>(
+    core: &ElfCore<D>,
+    hctx: &RelocationContext<'_, D>,
+    dependency_flags: &mut [bool],
+    r_sym: usize,
+) -> Option<usize> {
+    let (lib, st_value) = if r_sym == 0 {
+        (core, 0)
+    } else {
+        let (symdef, idx) = hctx.find_symdef(r_sym)?;
+        if let Some(idx) = idx {
+            dependency_flags[idx] = true;
+        }
+        (symdef.lib, symdef.sym.unwrap().st_value())
+    };
+    let offset = lib.tls()?.static_offset()?;
+    Some(offset.wrapping_add(st_value as isize) as usize)
+}
+
+/// Returns whether lazy binding can find the symbol of a PLT entry in the lazy
+/// scope or, unless the module is isolated, the global scope
+fn lazy_resolves<D, S: SymbolLookup>(
+    core: &ElfCore<D>,
+    r_sym: usize,
+    lazy_scope: Option<&S>,
+) -> bool {
+    let name = core.symtab().symbol_idx(r_sym).1.name();
+    lazy_scope.is_some_and(|scope| scope.lookup(name).is_some())
+        || core.inner.global_lookup(name).is_some()
+}
+
+/// Minimum number of relative relocation entries processed by a single parallel task
+const MIN_PARALLEL_CHUNK: usize = 4096;
+
+/// Number of entries each parallel task should process
+#[inline]
+fn chunk_size(len: usize, executor: &dyn ParallelExecutor) -> usize {
+    len.div_ceil(executor.parallelism().max(1))
+        .max(MIN_PARALLEL_CHUNK)
+}
+
+/// Apply REL/RELA relative relocations: new_value = base_address + addend
+/// Reports a relocation entry of `core` that is about to be processed
+#[cfg(feature = "trace")]
+fn trace_reloc<D>(core: &ElfCore<D>, rel: &ElfRelType) {
+    let r_sym = rel.r_symbol();
+    let symbol = (r_sym != 0).then(|| core.symtab().symbol_idx(r_sym).1.name());
+    crate::trace::emit(TraceEvent::Reloc {
+        lib: core.name(),
+        r_type: rel.r_type() as u32,
+        offset: rel.r_offset(),
+        symbol,
+    });
+}
+
+fn relocate_rel(base: usize, rel: &[ElfRelType]) {
+    rel.iter().for_each(|rel| {
+        debug_assert!(rel.r_type() == REL_RELATIVE as usize);
+        let r_addend = rel.r_addend(base);
+        let val = RelocValue::new(base) + r_addend;
+        unsafe { ((base + rel.r_offset()) as *mut usize).write(val.0) };
+    })
+}
+
+/// Apply compact relative relocations (RELR format)
+///
+/// An even entry is the link-time address `where` of a relocation. An odd entry
+/// is a bitmap whose bit `n` (`n >= 1`) marks the word `n - 1` slots after the
+/// position reached so far; that position starts right after the last address
+/// entry and every bitmap advances it by `usize::BITS - 1` words. Each marked
+/// word at `where` is relocated in place at `base + where`.
+///
+/// `relr` must start with an address entry.
+fn relocate_relr(base: usize, relr: &[ElfRelr]) {
+    for_each_relr(relr, |vaddr| {
+        let ptr = (base + vaddr) as *mut usize;
+        unsafe { ptr.write(base + ptr.read()) };
+    });
+}
+
+/// Calls `f` with the link-time address of every word described by `relr`.
+///
+/// `relr` must start with an address entry.
+#[inline]
+pub(crate) fn for_each_relr(relr: &[ElfRelr], mut f: impl FnMut(usize)) {
+    const WORD_SIZE: usize = size_of::<usize>();
+    const BITMAP_SLOTS: usize = usize::BITS as usize - 1;
+
+    // Link-time address of the word described by bit 1 of the next bitmap
+    let mut next = 0;
+    relr.iter().for_each(|relr| {
+        let value = relr.value();
+        if (value & 1) == 0 {
+            // Single relocation entry
+            f(value);
+            next = value + WORD_SIZE;
+        } else {
+            // Bitmap of relocations
+            let mut bitmap = value >> 1;
+            while bitmap != 0 {
+                let slot = bitmap.trailing_zeros() as usize;
+                f(next + slot * WORD_SIZE);
+                bitmap &= bitmap - 1;
+            }
+            next += BITMAP_SLOTS * WORD_SIZE;
+        }
+    });
+}
+
 /// Types of relative relocations
 enum RelativeRel {
     /// Standard REL/RELA relocations
@@ -188,14 +767,39 @@ impl RelativeRel {
     }
 }
 
+/// A relocation table that is either mapped from the object or converted from
+/// the non-native entry format
+enum RelTable {
+    /// Table used in place
+    Mapped(&'static [ElfRelType]),
+    /// Table converted from the non-native format
+    Converted(Box<[ElfRelType]>),
+}
+
+impl Deref for RelTable {
+    type Target = [ElfRelType];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match self {
+            RelTable::Mapped(rel) => rel,
+            RelTable::Converted(rel) => rel,
+        }
+    }
+}
+
 /// Holds parsed relocation information
 pub(crate) struct DynamicRelocation {
     /// Relative relocations (REL_RELATIVE)
     relative: RelativeRel,
     /// PLT relocations
-    pltrel: &'static [ElfRelType],
+    pltrel: RelTable,
     /// Other dynamic relocations
     dynrel: &'static [ElfRelType],
+    /// Index of the first entry of `dynrel` in the dynamic relocation table
+    dynrel_start: usize,
+    /// Other dynamic relocations converted from the non-native format
+    alt_dynrel: Box<[ElfRelType]>,
 }
 
 impl<D> DynamicImage<D> {
@@ -212,143 +816,332 @@ impl<D> DynamicImage<D> {
         LazyS: SymbolLookup + Send + Sync + 'static,
         PreH: RelocationHandler + ?Sized,
         PostH: RelocationHandler + ?Sized,
+    {
+        let core = self.core_ref();
+        let reloc = self.relocation();
+        self.bind_pltrel(is_lazy, lazy_scope.as_ref(), helper, 0..reloc.pltrel.len())?;
+
+        if is_lazy {
+            // Ensure lazy scope is available
+            assert!(
+                reloc.pltrel.is_empty() || lazy_scope.is_some(),
+                "{}: lazy scope is not set",
+                core.name()
+            );
+
+            // The scope is published before GOT[1]/GOT[2] route calls to the resolver
+            if let Some(lazy_scope) = lazy_scope {
+                self.set_lazy_scope(lazy_scope);
+            }
+
+            // Prepare for lazy binding if we have PLT relocations
+            if !reloc.pltrel.is_empty() {
+                prepare_lazy_bind(
+                    self.got().unwrap().as_ptr(),
+                    Arc::as_ptr(&core.inner) as usize,
+                );
+            }
+        }
+        Ok(self)
+    }
+
+    /// Apply the PLT relocations of the given entries of the PLT table
+    ///
+    /// With lazy binding, `JUMP_SLOT` entries are only rebased so that they
+    /// go through the resolver.
+    pub(crate) fn bind_pltrel<PreS, PostS, LazyS, PreH, PostH>(
+        &self,
+        is_lazy: bool,
+        lazy_scope: Option<&LazyS>,
+        helper: &mut RelocHelper<'_, '_, D, PreS, PostS, PreH, PostH>,
+        entries: Range<usize>,
+    ) -> Result<&Self>
+    where
+        PreS: SymbolLookup + ?Sized,
+        PostS: SymbolLookup + ?Sized,
+        LazyS: SymbolLookup,
+        PreH: RelocationHandler + ?Sized,
+        PostH: RelocationHandler + ?Sized,
     {
         let scope = helper.scope;
-        let pre_find = helper.pre_find;
-        let post_find = helper.post_find;
         let core = self.core_ref();
         let base = core.base();
         let segments = core.segments();
         let reloc = self.relocation();
-        let symtab = self.symtab();
 
         // Process PLT relocations
-        for rel in reloc.pltrel {
+        'entries: for (entry, rel) in reloc
+            .pltrel
+            .iter()
+            .enumerate()
+            .skip(entries.start)
+            .take(entries.len())
+        {
+            if let Some(stats) = helper.stats() {
+                stats.add_type(rel.r_type() as u32, 1);
+            }
+            #[cfg(feature = "trace")]
+            trace_reloc(core, rel);
             let hctx = RelocationContext::new(rel, core, scope);
             if !helper.handle_pre(&hctx)? {
                 continue;
             }
             let r_type = rel.r_type() as u32;
             let r_sym = rel.r_symbol();
-            let r_addend = rel.r_addend(base);
-
-            // Handle jump slot relocations
-            if likely(r_type == REL_JUMP_SLOT) {
-                if is_lazy {
-                    let addr = RelocValue::new(base) + rel.r_offset();
-                    let ptr = addr.as_mut_ptr::<usize>();
-                    // Even with lazy binding, basic relocation is needed for PLT to work
-                    unsafe {
-                        let origin_val = ptr.read();
-                        let new_val = origin_val + base;
-                        ptr.write(new_val);
-                    }
-                } else {
-                    if let Some((symbol, idx)) =
-                        find_symbol_addr(pre_find, post_find, core, symtab, scope, r_sym)
-                    {
-                        if let Some(idx) = idx {
-                            helper.dependency_flags[idx] = true;
+            let mut retried = false;
+            loop {
+                let r_addend = hctx.addend();
+
+                // Handle jump slot relocations
+                if likely(r_type == REL_JUMP_SLOT) {
+                    if is_lazy {
+                        if let Some(stats) = helper.stats() {
+                            stats.lazy_deferred += 1;
+                        }
+                        let addr = RelocValue::new(base) + rel.r_offset();
+                        let ptr = addr.as_mut_ptr::<usize>();
+                        // Even with lazy binding, basic relocation is needed for PLT to work
+                        unsafe {
+                            let origin_val = ptr.read();
+                            let new_val = origin_val + base;
+                            ptr.write(new_val);
                         }
+                        // The binding is only checked here, the slot still goes through the resolver
+                        if helper.missing.is_some()
+                            && !helper.can_resolve(core, r_sym, r_type)
+                            && !lazy_resolves(core, r_sym, lazy_scope)
+                        {
+                            helper.record_missing(rel, core);
+                        }
+                    } else if let Some(symbol) = helper.find_symbol(core, r_sym, r_type) {
                         segments.write(rel.r_offset(), symbol);
+                    } else {
+                        helper.record_missing(rel, core);
+                    }
+                    continue 'entries;
+                } else if unlikely(r_type == REL_IRELATIVE) {
+                    // Handle indirect function relocations
+                    let addr = RelocValue::new(base) + r_addend;
+                    segments.write(rel.r_offset(), unsafe { resolve_ifunc(addr) });
+                    continue 'entries;
+                }
+                // Handle unknown relocations with the provided handler
+                match helper.handle_post(&hctx)? {
+                    Some(Handled::Done(_)) => continue 'entries,
+                    Some(Handled::Retry) if !retried => retried = true,
+                    _ => {
+                        let context = helper
+                            .error_context(rel, core, Lookup::None)
+                            .with_entry(RelocationTable::Plt, entry);
+                        return Err(reloc_error(context, "Unhandled relocation"));
                     }
                 }
-                continue;
-            } else if unlikely(r_type == REL_IRELATIVE) {
-                // Handle indirect function relocations
-                let addr = RelocValue::new(base) + r_addend;
-                segments.write(rel.r_offset(), unsafe { resolve_ifunc(addr) });
-                continue;
-            }
-            // Handle unknown relocations with the provided handler
-            if helper.handle_post(&hctx)? {
-                return Err(reloc_error(rel, "Unhandled relocation", core));
             }
         }
+        Ok(self)
+    }
 
-        if is_lazy {
-            // Prepare for lazy binding if we have PLT relocations
-            if !reloc.pltrel.is_empty() {
-                prepare_lazy_bind(
-                    self.got().unwrap().as_ptr(),
-                    Arc::as_ptr(&core.inner) as usize,
-                );
-            }
+    /// Apply RELRO (RELocation Read-Only) protection if available
+    pub(crate) fn protect_relro(&self) -> Result<&Self> {
+        let info = self.core_ref().inner.dynamic_info.as_ref().unwrap();
+        info.protect_relro()?;
+        Ok(self)
+    }
 
-            // Ensure lazy scope is available
-            assert!(
-                reloc.pltrel.is_empty() || lazy_scope.is_some(),
-                "{}: lazy scope is not set",
-                core.name()
-            );
+    /// Check every relocation entry before anything is written
+    ///
+    /// Each target must lie inside a writable `PT_LOAD` segment, or any `PT_LOAD`
+    /// segment for an object with text relocations, each symbol index
+    /// inside the dynamic symbol table, and each type must be one that the default
+    /// processing understands.
+    fn audit(&self) -> Result<&Self> {
+        const WORD_SIZE: usize = size_of::<usize>();
+        const BITMAP_SLOTS: usize = usize::BITS as usize - 1;
 
-            // Set the lazy scope if provided
-            if let Some(lazy_scope) = lazy_scope {
-                self.set_lazy_scope(lazy_scope);
-            }
-        } else {
-            // Apply RELRO (RELocation Read-Only) protection if available
-            if let Some(relro) = self.relro() {
-                relro.relro()?;
+        let core = self.core_ref();
+        let reloc = self.relocation();
+        let symtab = self.symtab();
+        let nsyms = symtab.count_syms();
+        let textrel = self.text_segments().is_some();
+        let writable = |start: usize, len: usize| {
+            self.phdrs().iter().any(|phdr| {
+                let vaddr = phdr.p_vaddr as usize;
+                phdr.p_type == PT_LOAD
+                    && (textrel || phdr.p_flags & PF_W != 0)
+                    && start >= vaddr
+                    && start
+                        .checked_add(len)
+                        .is_some_and(|end| end <= vaddr + phdr.p_memsz as usize)
+            })
+        };
+        let check = |rel: &ElfRelType, table: RelocationTable, entry: usize| -> Result<()> {
+            let r_type = rel.r_type() as u32;
+            let r_sym = rel.r_symbol();
+            let supported = match table {
+                RelocationTable::Plt => matches!(r_type, REL_JUMP_SLOT | REL_IRELATIVE),
+                _ => {
+                    r_type == REL_SYMBOLIC
+                        || matches!(
+                            r_type,
+                            REL_NONE
+                                | REL_RELATIVE
+                                | REL_IRELATIVE
+                                | REL_GOT
+                                | REL_DTPMOD
+                                | REL_DTPOFF
+                                | REL_TPOFF
+                                | REL_COPY
+                        )
+                }
+            };
+            // TLS descriptors are only resolved eagerly
+            #[cfg(target_arch = "riscv64")]
+            let supported =
+                supported || (table == RelocationTable::Dynamic && r_type == REL_TLSDESC);
+            let msg = if !supported {
+                "Unsupported relocation type"
+            } else if r_sym >= nsyms {
+                "Symbol index out of bounds"
+            } else {
+                let len = match r_type {
+                    REL_COPY => symtab.symbol_idx(r_sym).0.st_size(),
+                    #[cfg(target_arch = "riscv64")]
+                    REL_TLSDESC => 2 * WORD_SIZE,
+                    _ => WORD_SIZE,
+                };
+                if r_type == REL_NONE || writable(rel.r_offset(), len) {
+                    return Ok(());
+                }
+                "Relocation target is outside the writable segments"
+            };
+            let context = RelocationErrorContext::new(rel, core).with_entry(table, entry);
+            Err(reloc_error(context, msg))
+        };
+
+        match reloc.relative {
+            RelativeRel::Rel(rel) => rel
+                .iter()
+                .enumerate()
+                .try_for_each(|(entry, rel)| check(rel, RelocationTable::Dynamic, entry))?,
+            RelativeRel::Relr(relr) => {
+                let check_word = |entry: usize, vaddr: usize| {
+                    if writable(vaddr, WORD_SIZE) {
+                        return Ok(());
+                    }
+                    let context = RelocationErrorContext::relr(vaddr, core)
+                        .with_entry(RelocationTable::Relr, entry);
+                    Err(reloc_error(
+                        context,
+                        "Relocation target is outside the writable segments",
+                    ))
+                };
+                let mut next = 0;
+                for (entry, relr) in relr.iter().enumerate() {
+                    let value = relr.value();
+                    if (value & 1) == 0 {
+                        check_word(entry, value)?;
+                        next = value + WORD_SIZE;
+                    } else {
+                        let mut bitmap = value >> 1;
+                        while bitmap != 0 {
+                            let slot = bitmap.trailing_zeros() as usize;
+                            check_word(entry, next + slot * WORD_SIZE)?;
+                            bitmap &= bitmap - 1;
+                        }
+                        next += BITMAP_SLOTS * WORD_SIZE;
+                    }
+                }
             }
         }
+        for (entry, rel) in reloc.dynrel() {
+            check(rel, RelocationTable::Dynamic, entry)?;
+        }
+        for (entry, rel) in reloc.pltrel.iter().enumerate() {
+            check(rel, RelocationTable::Plt, entry)?;
+        }
         Ok(self)
     }
 
     /// Perform relative relocations (REL_RELATIVE)
-    fn relocate_relative(&self) -> &Self {
-        let core = self.core_ref();
+    ///
+    /// With an executor, large tables are split into chunks that are relocated in parallel.
+    fn relocate_relative(&self, executor: Option<&dyn ParallelExecutor>) -> &Self {
         let reloc = self.relocation();
-        let segments = core.segments();
-        let base = core.base();
+        let base = self.core_ref().base();
 
         match reloc.relative {
             RelativeRel::Rel(rel) => {
                 assert!(rel.is_empty() || rel[0].r_type() == REL_RELATIVE as usize);
-                // Apply all relative relocations: new_value = base_address + addend
-                rel.iter().for_each(|rel| {
-                    debug_assert!(rel.r_type() == REL_RELATIVE as usize);
-                    let r_addend = rel.r_addend(base);
-                    let val = RelocValue::new(base) + r_addend;
-                    segments.write(rel.r_offset(), val);
-                })
+                match executor {
+                    Some(executor) if rel.len() > MIN_PARALLEL_CHUNK => {
+                        let chunk_size = chunk_size(rel.len(), executor);
+                        executor.execute(
+                            rel.chunks(chunk_size)
+                                .map(|rel| {
+                                    Box::new(move || relocate_rel(base, rel))
+                                        as Box<dyn FnOnce() + Send>
+                                })
+                                .collect(),
+                        );
+                    }
+                    _ => relocate_rel(base, rel),
+                }
             }
-            RelativeRel::Relr(relr) => {
-                // Apply compact relative relocations (RELR format)
-                let mut reloc_addr: *mut usize = null_mut();
-                relr.iter().for_each(|relr| {
-                    let value = relr.value();
-                    unsafe {
-                        if (value & 1) == 0 {
-                            // Single relocation entry
-                            reloc_addr = core.segments().get_mut_ptr(value);
-                            reloc_addr.write(base + reloc_addr.read());
-                            reloc_addr = reloc_addr.add(1);
-                        } else {
-                            // Bitmap of relocations
-                            let mut bitmap = value;
-                            let mut idx = 0;
-                            while bitmap != 0 {
-                                bitmap >>= 1;
-                                if (bitmap & 1) != 0 {
-                                    let ptr = reloc_addr.add(idx);
-                                    ptr.write(base + ptr.read());
-                                }
-                                idx += 1;
-                            }
-                            reloc_addr = reloc_addr.add(usize::BITS as usize - 1);
+            RelativeRel::Relr(relr) => match executor {
+                Some(executor) if relr.len() > MIN_PARALLEL_CHUNK => {
+                    let chunk_size = chunk_size(relr.len(), executor);
+                    let mut tasks: Vec<Box<dyn FnOnce() + Send>> = Vec::new();
+                    let mut rest = relr;
+                    while !rest.is_empty() {
+                        // A chunk must not end right before a bitmap entry, since the
+                        // bitmap is relative to the address entry preceding it
+                        let mut end = chunk_size.min(rest.len());
+                        while end < rest.len() && rest[end].value() & 1 != 0 {
+                            end += 1;
                         }
+                        let (chunk, tail) = rest.split_at(end);
+                        tasks.push(Box::new(move || relocate_relr(base, chunk)));
+                        rest = tail;
                     }
-                });
-            }
+                    executor.execute(tasks);
+                }
+                _ => relocate_relr(base, relr),
+            },
         }
         self
     }
 
-    /// Perform dynamic relocations (non-PLT, non-relative)
-    fn relocate_dynrel<PreS, PostS, PreH, PostH>(
+    /// Apply the relative relocations from entry `start` of their table, up to
+    /// `max` entries, and return the index of the first entry left.
+    ///
+    /// A RELR address entry is always applied together with the bitmap
+    /// entries following it, which may exceed `max`.
+    pub(crate) fn relocate_relative_from(&self, start: usize, max: usize) -> usize {
+        let base = self.core_ref().base();
+        match self.relocation().relative {
+            RelativeRel::Rel(rel) => {
+                let end = start.saturating_add(max).min(rel.len());
+                relocate_rel(base, &rel[start..end]);
+                end
+            }
+            RelativeRel::Relr(relr) => {
+                let mut end = start.saturating_add(max).min(relr.len());
+                // A bitmap entry continues the address entry before it
+                while end < relr.len() && relr[end].value() & 1 != 0 {
+                    end += 1;
+                }
+                relocate_relr(base, &relr[start..end]);
+                end
+            }
+        }
+    }
+
+    /// Perform dynamic relocations (non-PLT, non-relative) of the given
+    /// entries, counted across both table formats
+    pub(crate) fn relocate_dynrel<PreS, PostS, PreH, PostH>(
         &self,
         helper: &mut RelocHelper<'_, '_, D, PreS, PostS, PreH, PostH>,
+        entries: Range<usize>,
     ) -> Result<&Self>
     where
         PreS: SymbolLookup + ?Sized,
@@ -357,8 +1150,6 @@ impl<D> DynamicImage<D> {
         PostH: RelocationHandler + ?Sized,
     {
         let scope = helper.scope;
-        let pre_find = helper.pre_find;
-        let post_find = helper.post_find;
         /*
             Relocation formula components:
             A = Addend used to compute the value of the relocatable field
@@ -368,77 +1159,164 @@ impl<D> DynamicImage<D> {
 
         let core = self.core_ref();
         let reloc = self.relocation();
-        let symtab = self.symtab();
         let segments = core.segments();
         let base = core.base();
 
         // Process each dynamic relocation entry
-        for rel in reloc.dynrel {
+        'entries: for (entry, rel) in reloc.dynrel().skip(entries.start).take(entries.len()) {
+            if let Some(stats) = helper.stats() {
+                stats.add_type(rel.r_type() as u32, 1);
+            }
+            #[cfg(feature = "trace")]
+            trace_reloc(core, rel);
             let hctx = RelocationContext::new(rel, core, scope);
             if !helper.handle_pre(&hctx)? {
                 continue;
             }
             let r_type = rel.r_type() as _;
             let r_sym = rel.r_symbol();
-            let r_addend = rel.r_addend(base);
-
-            match r_type {
-                // Handle GOT and symbolic relocations
-                REL_GOT | REL_SYMBOLIC => {
-                    if let Some((symbol, idx)) =
-                        find_symbol_addr(pre_find, post_find, core, symtab, scope, r_sym)
-                    {
-                        if let Some(idx) = idx {
-                            helper.dependency_flags[idx] = true;
+            let mut retried = false;
+            loop {
+                let r_addend = hctx.addend();
+
+                match r_type {
+                    // Handle GOT and symbolic relocations
+                    REL_GOT | REL_SYMBOLIC => {
+                        if let Some(symbol) = helper.find_symbol(core, r_sym, r_type) {
+                            segments.write(rel.r_offset(), symbol + r_addend);
+                            continue 'entries;
                         }
-                        segments.write(rel.r_offset(), symbol + r_addend);
-                        continue;
                     }
-                }
-                // Handle TLS (Thread Local Storage) offset relocations
-                REL_DTPOFF => {
-                    if let Some((symdef, idx)) = hctx.find_symdef(r_sym) {
-                        if let Some(idx) = idx {
-                            helper.dependency_flags[idx] = true;
+                    // Handle TLS module id relocations
+                    REL_DTPMOD => {
+                        // A symbol index of 0 refers to the module itself
+                        let mod_id = if r_sym == 0 {
+                            core.tls_mod_id()
+                        } else if let Some((symdef, idx)) = hctx.find_symdef(r_sym) {
+                            if let Some(idx) = idx {
+                                helper.dependency_flags[idx] = true;
+                            }
+                            symdef.lib.tls_mod_id()
+                        } else {
+                            None
+                        };
+                        if let Some(mod_id) = mod_id {
+                            segments.write(rel.r_offset(), RelocValue::new(mod_id));
+                            continue 'entries;
                         }
-                        // Calculate offset within TLS block
-                        let tls_val = RelocValue::new(symdef.sym.unwrap().st_value()) + r_addend
-                            - TLS_DTV_OFFSET;
-                        segments.write(rel.r_offset(), tls_val);
-                        continue;
                     }
-                }
-                // Handle copy relocations (typically for global data)
-                REL_COPY => {
-                    if let Some((symdef, idx)) = hctx.find_symdef(r_sym) {
-                        let len = hctx.lib().symtab().symbol_idx(r_sym).0.st_size();
-                        if let Some(idx) = idx {
-                            helper.dependency_flags[idx] = true;
+                    // Handle TLS (Thread Local Storage) offset relocations
+                    REL_DTPOFF => {
+                        if let Some((symdef, idx)) = hctx.find_symdef(r_sym) {
+                            if let Some(idx) = idx {
+                                helper.dependency_flags[idx] = true;
+                            }
+                            // Calculate offset within TLS block
+                            let tls_val = RelocValue::new(symdef.sym.unwrap().st_value())
+                                + r_addend
+                                - TLS_DTV_OFFSET;
+                            segments.write(rel.r_offset(), tls_val);
+                            continue 'entries;
                         }
-                        let dest = core.segments().get_slice_mut::<u8>(rel.r_offset(), len);
-                        let src = symdef
-                            .lib
-                            .segments()
-                            .get_slice(symdef.sym.unwrap().st_value(), len);
-                        dest.copy_from_slice(src);
-                        continue;
                     }
+                    // Handle static TLS offset relocations
+                    REL_TPOFF => {
+                        let tp_offset = tp_offset(core, &hctx, &mut helper.dependency_flags, r_sym);
+                        if let Some(tp_offset) = tp_offset {
+                            segments.write(rel.r_offset(), RelocValue::new(tp_offset) + r_addend);
+                            continue 'entries;
+                        }
+                    }
+                    // Handle TLS descriptors of variables in the static TLS block
+                    #[cfg(target_arch = "riscv64")]
+                    REL_TLSDESC => {
+                        let tp_offset = tp_offset(core, &hctx, &mut helper.dependency_flags, r_sym);
+                        if let Some(tp_offset) = tp_offset {
+                            let resolver = tlsdesc_static as *const () as usize;
+                            segments.write(rel.r_offset(), RelocValue::new(resolver));
+                            segments.write(
+                                rel.r_offset() + size_of::<usize>(),
+                                RelocValue::new(tp_offset) + r_addend,
+                            );
+                            continue 'entries;
+                        }
+                    }
+                    // Handle copy relocations (typically for global data)
+                    REL_COPY => {
+                        if let Some((symdef, idx)) = hctx.find_symdef(r_sym) {
+                            if let Some(idx) = idx {
+                                helper.dependency_flags[idx] = true;
+                            }
+                            let def = symdef.sym.unwrap();
+                            let (sym, syminfo) = self.symtab().symbol_idx(r_sym);
+                            let (ref_size, def_size) = (sym.st_size(), def.st_size());
+                            // Never copy more than either side knows about
+                            let len = ref_size.min(def_size);
+                            if ref_size != def_size {
+                                #[cfg(feature = "trace")]
+                                crate::trace::emit(TraceEvent::CopySizeMismatch {
+                                    lib: core.name(),
+                                    symbol: syminfo.name(),
+                                    referenced: ref_size,
+                                    defined: def_size,
+                                    provider: symdef.lib.name(),
+                                });
+                                if let Some(report) = helper.report.as_deref_mut() {
+                                    report.add_copy_size_mismatch(
+                                        syminfo.name(),
+                                        symdef.lib.name(),
+                                        ref_size,
+                                        def_size,
+                                    );
+                                }
+                            }
+                            let dest = core.segments().get_slice_mut::<u8>(rel.r_offset(), len)?;
+                            let src = symdef.lib.segments().get_slice(def.st_value(), len)?;
+                            dest.copy_from_slice(src);
+                            // The copy of the main program becomes the canonical definition
+                            if core.is_main_program() {
+                                core.record_copy(CopyRecord {
+                                    sym: r_sym,
+                                    addr: dest.as_ptr() as usize,
+                                    def: src.as_ptr() as usize,
+                                    len,
+                                });
+                            }
+                            continue 'entries;
+                        }
+                    }
+                    // Relative relocations not covered by DT_RELACOUNT/DT_RELCOUNT
+                    REL_RELATIVE => {
+                        segments.write(rel.r_offset(), RelocValue::new(base) + r_addend);
+                        continue 'entries;
+                    }
+                    REL_IRELATIVE => {
+                        // Handle indirect function relocations
+                        let addr = RelocValue::new(base) + r_addend;
+                        segments.write(rel.r_offset(), unsafe { resolve_ifunc(addr) });
+                        continue 'entries;
+                    }
+                    // No relocation needed
+                    REL_NONE => continue 'entries,
+                    // Unknown relocation type
+                    _ => {}
                 }
-                REL_IRELATIVE => {
-                    // Handle indirect function relocations
-                    let addr = RelocValue::new(base) + r_addend;
-                    segments.write(rel.r_offset(), unsafe { resolve_ifunc(addr) });
-                    continue;
-                }
-                // No relocation needed
-                REL_NONE => continue,
-                // Unknown relocation type
-                _ => {}
-            }
 
-            // Handle unknown relocations with the provided handler
-            if helper.handle_post(&hctx)? {
-                return Err(reloc_error(rel, "Unhandled relocation", core));
+                // Handle unknown relocations with the provided handler
+                match helper.handle_post(&hctx)? {
+                    Some(Handled::Done(_)) => continue 'entries,
+                    Some(Handled::Retry) if !retried => retried = true,
+                    _ => {
+                        let lookup = symbol_lookup(r_type, r_sym);
+                        if lookup != Lookup::None && helper.record_missing(rel, core) {
+                            continue 'entries;
+                        }
+                        let context = helper
+                            .error_context(rel, core, lookup)
+                            .with_entry(RelocationTable::Dynamic, entry);
+                        return Err(reloc_error(context, "Unhandled relocation"));
+                    }
+                }
             }
         }
         Ok(self)
@@ -446,20 +1324,53 @@ impl<D> DynamicImage<D> {
 }
 
 impl DynamicRelocation {
+    /// Number of words relocated by the relative relocations
+    fn relative_count(&self) -> usize {
+        match self.relative {
+            RelativeRel::Rel(rel) => rel.len(),
+            RelativeRel::Relr(relr) => relr
+                .iter()
+                .map(|relr| match relr.value() {
+                    value if value & 1 == 0 => 1,
+                    value => (value >> 1).count_ones() as usize,
+                })
+                .sum(),
+        }
+    }
+
     /// Create a new DynamicRelocation instance from parsed relocation data
     #[inline]
     pub(crate) fn new(
         pltrel: Option<&'static [ElfRelType]>,
         dynrel: Option<&'static [ElfRelType]>,
+        alt_pltrel: Option<&'static [ElfAltRelType]>,
+        alt_dynrel: Option<&'static [ElfAltRelType]>,
         relr: Option<&'static [ElfRelr]>,
         rela_count: Option<NonZeroUsize>,
+        segments: &ElfSegments,
     ) -> Self {
+        // Tables in the non-native format are converted up front, while the
+        // target words still hold their original contents
+        let alt_pltrel = alt_pltrel.unwrap_or(&[]);
+        let alt_dynrel = exclude_pltrel(alt_dynrel.unwrap_or(&[]), alt_pltrel);
+        let convert = |rel: &'static [ElfAltRelType]| -> Box<[ElfRelType]> {
+            rel.iter().map(|rel| rel.to_native(segments)).collect()
+        };
+        let alt_dynrel = convert(alt_dynrel);
+        let pltrel_table = if alt_pltrel.is_empty() {
+            RelTable::Mapped(pltrel.unwrap_or(&[]))
+        } else {
+            RelTable::Converted(convert(alt_pltrel))
+        };
+
         if let Some(relr) = relr {
             // Use RELR relocations if available (more compact format)
             Self {
                 relative: RelativeRel::Relr(relr),
-                pltrel: pltrel.unwrap_or(&[]),
-                dynrel: dynrel.unwrap_or(&[]),
+                pltrel: pltrel_table,
+                dynrel: exclude_pltrel(dynrel.unwrap_or(&[]), pltrel.unwrap_or(&[])),
+                dynrel_start: 0,
+                alt_dynrel,
             }
         } else {
             // Use traditional REL/RELA relocations
@@ -469,34 +1380,109 @@ impl DynamicRelocation {
 
             // Split relocations into relative and non-relative parts
             let relative = RelativeRel::Rel(&old_dynrel[..nrelative]);
-            let temp_dynrel = &old_dynrel[nrelative..];
-
-            let pltrel = pltrel.unwrap_or(&[]);
-            let dynrel = if unsafe {
-                // Check if dynrel and pltrel are contiguous in memory
-                core::ptr::eq(
-                    old_dynrel.as_ptr().add(old_dynrel.len()),
-                    pltrel.as_ptr().add(pltrel.len()),
-                )
-            } {
-                // If contiguous, exclude pltrel entries from dynrel
-                &temp_dynrel[..temp_dynrel.len() - pltrel.len()]
-            } else {
-                // Otherwise, use all remaining entries
-                temp_dynrel
-            };
+            let dynrel = exclude_pltrel(&old_dynrel[nrelative..], pltrel.unwrap_or(&[]));
 
             Self {
                 relative,
-                pltrel,
+                pltrel: pltrel_table,
                 dynrel,
+                dynrel_start: nrelative,
+                alt_dynrel,
             }
         }
     }
 
+    /// Iterate over all non-relative dynamic relocations, regardless of the
+    /// table format they were read from, along with their index in that table
+    #[inline]
+    fn dynrel(&self) -> impl Iterator<Item = (usize, &ElfRelType)> {
+        let start = self.dynrel_start;
+        self.dynrel
+            .iter()
+            .enumerate()
+            .map(move |(idx, rel)| (start + idx, rel))
+            .chain(self.alt_dynrel.iter().enumerate())
+    }
+
+    /// Number of entries of the relative relocation table
+    #[inline]
+    pub(crate) fn relative_len(&self) -> usize {
+        match self.relative {
+            RelativeRel::Rel(rel) => rel.len(),
+            RelativeRel::Relr(relr) => relr.len(),
+        }
+    }
+
+    /// Number of non-relative dynamic relocations, in both table formats
+    #[inline]
+    pub(crate) fn dynrel_len(&self) -> usize {
+        self.dynrel.len() + self.alt_dynrel.len()
+    }
+
+    /// Number of PLT relocations
+    #[inline]
+    pub(crate) fn pltrel_len(&self) -> usize {
+        self.pltrel.len()
+    }
+
+    /// Whether the PLT relocations can be bound lazily.
+    ///
+    /// The i386 PLT entries push the byte offset of an `Elf32_Rel` entry, which
+    /// does not locate an entry of a table converted from `DT_RELA`. The other
+    /// architectures pass an index that holds for both formats.
+    #[inline]
+    pub(crate) fn supports_lazy(&self) -> bool {
+        cfg!(not(target_arch = "x86")) || matches!(self.pltrel, RelTable::Mapped(_))
+    }
+
     /// Check if there are no relocations to process
     #[inline]
     fn is_empty(&self) -> bool {
-        self.relative.is_empty() && self.dynrel.is_empty() && self.pltrel.is_empty()
+        self.relative.is_empty()
+            && self.dynrel.is_empty()
+            && self.alt_dynrel.is_empty()
+            && self.pltrel.is_empty()
+    }
+}
+
+/// Remove the PLT relocations from a dynamic relocation table when the two
+/// tables are contiguous in memory (DT_RELASZ may include DT_PLTRELSZ)
+#[inline]
+fn exclude_pltrel<T>(dynrel: &'static [T], pltrel: &'static [T]) -> &'static [T] {
+    if !pltrel.is_empty()
+        && dynrel.len() >= pltrel.len()
+        && unsafe {
+            core::ptr::eq(
+                dynrel.as_ptr().add(dynrel.len()),
+                pltrel.as_ptr().add(pltrel.len()),
+            )
+        }
+    {
+        &dynrel[..dynrel.len() - pltrel.len()]
+    } else {
+        dynrel
+    }
+}
+
+impl ElfAltRelType {
+    /// Convert the entry into the native relocation format.
+    ///
+    /// REL entries take their addend from the target word, except for GOT and
+    /// jump slot entries whose word is not an addend. RELA entries store their
+    /// addend into the target word, which is where REL expects it.
+    #[inline]
+    fn to_native(&self, segments: &ElfSegments) -> ElfRelType {
+        let (r_offset, _r_type, _, addend) = self.fields(segments.base());
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_arch = "x86", target_arch = "arm"))] {
+                // A jump slot keeps the PLT address that lazy binding goes through
+                if !matches!(_r_type as u32, REL_GOT | REL_JUMP_SLOT) {
+                    segments.write(r_offset, RelocValue::new(addend as usize));
+                }
+                ElfRelType::new(r_offset, self.r_info())
+            } else {
+                ElfRelType::new(r_offset, self.r_info(), addend)
+            }
+        }
     }
 }
@@ -0,0 +1,141 @@
+//! Precomputed symbol index of a relocation scope
+use crate::{elf::SymbolInfo, image::LoadedCore};
+use alloc::vec::Vec;
+use core::ops::Range;
+use hashbrown::HashMap;
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+/// A precomputed index of the symbols defined by a scope.
+///
+/// Resolving a symbol against a scope normally probes the hash table of every
+/// module in turn until one defines it, so relocating against a scope of
+/// hundreds of modules spends most of its time in modules that never define
+/// the symbol. A `ScopeIndex` records once which modules define a symbol with
+/// a given name hash, and a lookup only probes those modules, in scope order.
+///
+/// The index keeps the modules it was built from alive and is cheap to clone,
+/// so the relocators of any number of modules can share it through
+/// [`Relocator::scope_index`](crate::relocation::Relocator::scope_index). It
+/// is only used by a relocator whose scope holds the same modules in the same
+/// order; any other scope is searched as usual.
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::{Loader, relocation::ScopeIndex};
+///
+/// let mut loader = Loader::new();
+/// let libc = loader.load_dylib("libc.so").unwrap().relocator().relocate().unwrap();
+/// let scope = [libc];
+/// let index = ScopeIndex::build(&scope);
+/// for path in ["liba.so", "libb.so"] {
+///     let plugin = loader
+///         .load_dylib(path)
+///         .unwrap()
+///         .relocator()
+///         .scope(&scope)
+///         .scope_index(&index)
+///         .relocate()
+///         .unwrap();
+/// }
+/// ```
+pub struct ScopeIndex<D = ()> {
+    inner: Arc<IndexInner<D>>,
+}
+
+struct IndexInner<D> {
+    /// The modules the index was built from
+    modules: Vec<LoadedCore<D>>,
+    /// Maps a GNU hash to the range of `candidates` defining a symbol with it
+    buckets: HashMap<u32, Range<u32>>,
+    /// Module indices, grouped by hash and in scope order within a group
+    candidates: Vec<u32>,
+}
+
+impl<D> Clone for ScopeIndex<D> {
+    /// Creates another handle to the same index.
+    fn clone(&self) -> Self {
+        ScopeIndex {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<D> ScopeIndex<D> {
+    /// Builds the index of a scope.
+    ///
+    /// Only the symbols a relocation may bind to are indexed, that is the
+    /// defined global and weak symbols of a supported type.
+    ///
+    /// # Arguments
+    /// * `scope` - The modules to index, in the order they are searched.
+    pub fn build<I, R>(scope: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: core::borrow::Borrow<LoadedCore<D>>,
+    {
+        let modules: Vec<LoadedCore<D>> = scope.into_iter().map(|r| r.borrow().clone()).collect();
+        let mut entries = Vec::new();
+        for (idx, lib) in modules.iter().enumerate() {
+            for (name, sym) in lib.symtab().lookup_names() {
+                if sym.is_undef() || !sym.is_ok_bind() || !sym.is_ok_type() {
+                    continue;
+                }
+                let hash = SymbolInfo::from_str(name, None).precompute().gnuhash();
+                entries.push((hash, idx as u32));
+            }
+        }
+        // The sort is stable, which keeps each group in scope order
+        entries.sort_by_key(|&(hash, _)| hash);
+        entries.dedup();
+
+        let mut buckets = HashMap::new();
+        let mut candidates = Vec::with_capacity(entries.len());
+        for group in entries.chunk_by(|a, b| a.0 == b.0) {
+            let start = candidates.len() as u32;
+            candidates.extend(group.iter().map(|&(_, idx)| idx));
+            buckets.insert(group[0].0, start..candidates.len() as u32);
+        }
+        ScopeIndex {
+            inner: Arc::new(IndexInner {
+                modules,
+                buckets,
+                candidates,
+            }),
+        }
+    }
+
+    /// Returns the number of modules in the index.
+    pub fn len(&self) -> usize {
+        self.inner.modules.len()
+    }
+
+    /// Returns `true` if the index was built from an empty scope.
+    pub fn is_empty(&self) -> bool {
+        self.inner.modules.is_empty()
+    }
+
+    /// Returns whether the index describes `scope`.
+    pub(crate) fn covers(&self, scope: &[LoadedCore<D>]) -> bool {
+        self.inner.modules.len() == scope.len()
+            && self
+                .inner
+                .modules
+                .iter()
+                .zip(scope)
+                .all(|(a, b)| Arc::ptr_eq(&a.core.inner, &b.core.inner))
+    }
+
+    /// Returns the indices of the modules that may define a symbol whose name
+    /// has the GNU hash `hash`, in scope order.
+    #[inline]
+    pub(crate) fn candidates(&self, hash: u32) -> impl Iterator<Item = usize> + '_ {
+        let range = self.inner.buckets.get(&hash).cloned().unwrap_or(0..0);
+        self.inner.candidates[range.start as usize..range.end as usize]
+            .iter()
+            .map(|&idx| idx as usize)
+    }
+}
@@ -0,0 +1,336 @@
+//! Relocation of a batch of images that resolve against each other
+use crate::{
+    Result,
+    image::{LoadedCore, LoadedElf, RawElf},
+    relocation::{
+        ParallelExecutor, Relocatable, RelocateOptions, RelocationHandler, SymbolLookup,
+        VersionPolicy,
+    },
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::borrow::Borrow;
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+/// A builder that relocates a set of images against one shared scope.
+///
+/// The scope used for symbol resolution is computed once for the whole batch:
+/// the base scope set with [`scope`](Self::scope), followed by every dynamic
+/// library and dynamically linked executable of the batch in the order they
+/// were added. Images of the batch therefore resolve references to each other
+/// regardless of the order they are relocated in. Relocatable objects only
+/// resolve through `pre_find` and `post_find`, as with
+/// [`RawElf::relocator`].
+///
+/// Images are relocated in dependency order, so the initializers of an image
+/// run after those of the batch members named in its `DT_NEEDED` entries.
+/// Members of a dependency cycle are relocated in the order they were added.
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::{Loader, relocation::Linker};
+///
+/// let mut loader = Loader::new();
+/// let libc = loader.load("libc.so").unwrap();
+/// let libb = loader.load("libb.so").unwrap();
+/// let liba = loader.load("liba.so").unwrap();
+///
+/// // libc uses libb, which uses liba
+/// let libs = Linker::new()
+///     .image(libc)
+///     .image(libb)
+///     .image(liba)
+///     .link()
+///     .unwrap();
+/// assert_eq!(libs[0].name(), "libc.so");
+/// ```
+pub struct Linker<D: 'static, PreS = (), PostS = (), PreH = (), PostH = ()> {
+    images: Vec<RawElf<D>>,
+    scope: Vec<LoadedCore<D>>,
+    pre_find: PreS,
+    post_find: PostS,
+    pre_handler: PreH,
+    post_handler: PostH,
+    lazy: Option<bool>,
+    executor: Option<Box<dyn ParallelExecutor>>,
+    strict: bool,
+    apply_relro: bool,
+    version_policy: VersionPolicy,
+}
+
+impl<D: 'static> Default for Linker<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: 'static> Linker<D> {
+    /// Creates an empty `Linker`.
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            scope: Vec::new(),
+            pre_find: (),
+            post_find: (),
+            pre_handler: (),
+            post_handler: (),
+            lazy: None,
+            executor: None,
+            strict: false,
+            apply_relro: true,
+            version_policy: VersionPolicy::default(),
+        }
+    }
+}
+
+impl<D: 'static, PreS, PostS, PreH, PostH> Linker<D, PreS, PostS, PreH, PostH>
+where
+    PreS: SymbolLookup,
+    PostS: SymbolLookup,
+    PreH: RelocationHandler,
+    PostH: RelocationHandler,
+{
+    /// Adds an image to the batch.
+    ///
+    /// The relocated image is returned by [`link`](Self::link) at the position
+    /// it was added in.
+    pub fn image(mut self, image: RawElf<D>) -> Self {
+        self.images.push(image);
+        self
+    }
+
+    /// Adds several images to the batch.
+    pub fn images(mut self, images: impl IntoIterator<Item = RawElf<D>>) -> Self {
+        self.images.extend(images);
+        self
+    }
+
+    /// Sets the relocated libraries searched before the images of the batch.
+    ///
+    /// The libraries are searched in the order they are provided.
+    pub fn scope<I, R>(mut self, scope: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Borrow<LoadedCore<D>>,
+    {
+        self.scope = scope.into_iter().map(|r| r.borrow().clone()).collect();
+        self
+    }
+
+    /// Sets the symbol lookup searched before the scope.
+    pub fn pre_find<S2>(self, pre_find: S2) -> Linker<D, S2, PostS, PreH, PostH>
+    where
+        S2: SymbolLookup,
+    {
+        Linker {
+            images: self.images,
+            scope: self.scope,
+            pre_find,
+            post_find: self.post_find,
+            pre_handler: self.pre_handler,
+            post_handler: self.post_handler,
+            lazy: self.lazy,
+            executor: self.executor,
+            strict: self.strict,
+            apply_relro: self.apply_relro,
+            version_policy: self.version_policy,
+        }
+    }
+
+    /// Sets the symbol lookup searched after the scope.
+    pub fn post_find<S2>(self, post_find: S2) -> Linker<D, PreS, S2, PreH, PostH>
+    where
+        S2: SymbolLookup,
+    {
+        Linker {
+            images: self.images,
+            scope: self.scope,
+            pre_find: self.pre_find,
+            post_find,
+            pre_handler: self.pre_handler,
+            post_handler: self.post_handler,
+            lazy: self.lazy,
+            executor: self.executor,
+            strict: self.strict,
+            apply_relro: self.apply_relro,
+            version_policy: self.version_policy,
+        }
+    }
+
+    /// Sets the handler called before the default processing of each relocation.
+    pub fn pre_handler<NewPreH>(self, handler: NewPreH) -> Linker<D, PreS, PostS, NewPreH, PostH>
+    where
+        NewPreH: RelocationHandler,
+    {
+        Linker {
+            images: self.images,
+            scope: self.scope,
+            pre_find: self.pre_find,
+            post_find: self.post_find,
+            pre_handler: handler,
+            post_handler: self.post_handler,
+            lazy: self.lazy,
+            executor: self.executor,
+            strict: self.strict,
+            apply_relro: self.apply_relro,
+            version_policy: self.version_policy,
+        }
+    }
+
+    /// Sets the handler called for relocations the default processing did not handle.
+    pub fn post_handler<NewPostH>(self, handler: NewPostH) -> Linker<D, PreS, PostS, PreH, NewPostH>
+    where
+        NewPostH: RelocationHandler,
+    {
+        Linker {
+            images: self.images,
+            scope: self.scope,
+            pre_find: self.pre_find,
+            post_find: self.post_find,
+            pre_handler: self.pre_handler,
+            post_handler: handler,
+            lazy: self.lazy,
+            executor: self.executor,
+            strict: self.strict,
+            apply_relro: self.apply_relro,
+            version_policy: self.version_policy,
+        }
+    }
+
+    /// Enables or disables lazy binding for every image of the batch.
+    ///
+    /// The lazy scope of each image is made of the members of the shared scope
+    /// named in its `DT_NEEDED` entries.
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = Some(lazy);
+        self
+    }
+
+    /// Applies the relative relocations of every image in parallel on the given executor.
+    pub fn parallel(mut self, executor: impl ParallelExecutor + 'static) -> Self {
+        self.executor = Some(Box::new(executor));
+        self
+    }
+
+    /// Enables or disables the audit pass before each image is relocated.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Enables or disables the RELRO protection applied after relocation.
+    pub fn apply_relro(mut self, apply_relro: bool) -> Self {
+        self.apply_relro = apply_relro;
+        self
+    }
+
+    /// Sets how the versioned references of every image are matched to definitions.
+    ///
+    /// See [`Relocator::version_policy`](crate::relocation::Relocator::version_policy).
+    pub fn version_policy(mut self, version_policy: VersionPolicy) -> Self {
+        self.version_policy = version_policy;
+        self
+    }
+
+    /// Relocates every image of the batch.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<LoadedElf<D>>)` - The relocated images, in the order they were added.
+    /// * `Err(Error)` - If an image fails to relocate. Images relocated before
+    ///   it are released.
+    pub fn link(self) -> Result<Vec<LoadedElf<D>>> {
+        let Linker {
+            images,
+            scope: base,
+            pre_find,
+            post_find,
+            mut pre_handler,
+            mut post_handler,
+            lazy,
+            executor,
+            strict,
+            apply_relro,
+            version_policy,
+        } = self;
+
+        let order = link_order(&images);
+        let mut scope = Vec::with_capacity(base.len() + images.len());
+        scope.extend(base.iter().cloned());
+        // Members of the batch keep the base scope alive for whoever resolves against them
+        let deps: Arc<[LoadedCore<D>]> = Arc::from(base);
+        scope.extend(images.iter().filter_map(|image| {
+            Some(LoadedCore {
+                core: image.dynamic()?.core(),
+                deps: Arc::clone(&deps),
+            })
+        }));
+
+        let mut images: Vec<Option<RawElf<D>>> = images.into_iter().map(Some).collect();
+        let mut loaded: Vec<Option<LoadedElf<D>>> = (0..images.len()).map(|_| None).collect();
+        for idx in order {
+            let image = images[idx].take().unwrap();
+            loaded[idx] = Some(Relocatable::relocate(
+                image,
+                &scope,
+                &pre_find,
+                &post_find,
+                &mut pre_handler,
+                &mut post_handler,
+                None::<()>,
+                RelocateOptions {
+                    lazy,
+                    strict,
+                    apply_relro,
+                    collect_missing: false,
+                    policy: None,
+                    version_policy,
+                    scope_index: None,
+                    self_pos: None,
+                    report: None,
+                    executor: executor.as_deref(),
+                    pre_init: None,
+                    post_fini: None,
+                },
+            )?);
+        }
+        Ok(loaded.into_iter().map(Option::unwrap).collect())
+    }
+}
+
+/// Orders the images so that every image comes after the batch members it needs
+///
+/// Dependencies are matched by file name or `DT_SONAME`. Cycles are broken at
+/// the member that was added first.
+fn link_order<D>(images: &[RawElf<D>]) -> Vec<usize> {
+    fn visit<D>(images: &[RawElf<D>], idx: usize, visited: &mut [bool], order: &mut Vec<usize>) {
+        visited[idx] = true;
+        if let Some(image) = images[idx].dynamic() {
+            for needed in image.needed_libs() {
+                let dep = images.iter().position(|other| {
+                    other.dynamic().is_some_and(|other| {
+                        other.name().rsplit('/').next() == Some(*needed)
+                            || other.soname() == Some(*needed)
+                    })
+                });
+                if let Some(dep) = dep
+                    && !visited[dep]
+                {
+                    visit(images, dep, visited, order);
+                }
+            }
+        }
+        order.push(idx);
+    }
+
+    let mut visited = alloc::vec![false; images.len()];
+    let mut order = Vec::with_capacity(images.len());
+    for idx in 0..images.len() {
+        if !visited[idx] {
+            visit(images, idx, &mut visited, &mut order);
+        }
+    }
+    order
+}
@@ -13,17 +13,47 @@
 //! Relocation involves direct memory manipulation. Ensure proper bounds checking
 //! and avoid corrupting memory during address calculations.
 
+mod audit;
+mod compat;
 mod dynamic;
+mod index;
+mod linker;
+mod policy;
+mod report;
+mod scope;
+mod session;
 mod r#static;
 mod traits;
 mod utils;
 
-pub(crate) use dynamic::{DynamicRelocation, dl_fixup};
+#[cfg(feature = "prelink")]
+pub(crate) use dynamic::for_each_relr;
+pub(crate) use dynamic::{DynamicRelocation, LazyBinding, dl_fixup, plt_entries, rebind_symbol};
 pub(crate) use r#static::{StaticReloc, StaticRelocation};
-pub(crate) use traits::Relocatable;
+pub(crate) use traits::{Relocatable, RelocateOptions};
 pub(crate) use utils::{
-    RelocHelper, RelocValue, Relocator, SymDef, find_symbol_addr, find_symdef_impl, likely,
-    reloc_error, unlikely,
+    Lookup, RelocHelper, RelocValue, Relocator, call_ifunc, find_symdef_impl, likely,
+    main_program_pos, reloc_error, unlikely,
 };
+#[cfg(target_arch = "x86_64")]
+pub(crate) use utils::{find_symbol_addr, searched_sources};
 
-pub use traits::{RelocationContext, RelocationHandler, SymbolLookup};
+pub(crate) use audit::LazyAudit;
+pub use audit::{Auditor, SymbolBinding};
+pub use compat::{CompatFlags, DlopenScope};
+pub use dynamic::{PltEntry, UnresolvedHandler, set_unresolved_handler};
+pub use index::ScopeIndex;
+pub use linker::Linker;
+pub use policy::{LookupOrder, LookupPolicy, LookupPolicyFn, RelocKind, VersionPolicy};
+#[cfg(feature = "std")]
+pub use report::PhaseTimings;
+pub use report::{CopySizeMismatch, RelocationReport, RelocationStats};
+pub(crate) use report::{Phase, PhaseTimer};
+pub use scope::{GlobalScope, global_scope};
+pub use session::{Progress, RelocationSession, TableProgress};
+pub(crate) use scope::{global_lookup, register_global, unregister_global};
+pub use traits::{
+    Handled, ParallelExecutor, PostFini, PreInit, RelocationContext, RelocationHandler,
+    SymbolLookup,
+};
+pub use utils::SymDef;
@@ -0,0 +1,152 @@
+//! Order in which symbol sources are searched during relocation
+use crate::arch::{
+    REL_COPY, REL_DTPMOD, REL_DTPOFF, REL_GOT, REL_JUMP_SLOT, REL_SYMBOLIC, REL_TPOFF,
+};
+use core::fmt::Debug;
+use elf::abi::{STT_COMMON, STT_OBJECT};
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+/// What a relocation binds to, as seen by a [`LookupPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocKind {
+    /// A function: `JUMP_SLOT` entries, and GOT entries and absolute
+    /// relocations of symbols that are not known to be data.
+    Func,
+    /// A data object: GOT entries and absolute relocations of `STT_OBJECT`
+    /// and `STT_COMMON` symbols.
+    Data,
+    /// A thread-local variable: the `DTPMOD`, `DTPOFF` and `TPOFF` relocations.
+    Tls,
+    /// A `COPY` relocation.
+    Copy,
+}
+
+impl RelocKind {
+    /// Classifies a relocation from its type and the type of its symbol.
+    ///
+    /// GOT entries and absolute relocations are told apart by the symbol type
+    /// only, as `REL_GOT` and `REL_SYMBOLIC` are the same type on some
+    /// architectures.
+    pub(crate) fn classify(r_type: u32, st_type: u8) -> Option<Self> {
+        match r_type {
+            REL_JUMP_SLOT => Some(Self::Func),
+            _ if r_type == REL_GOT || r_type == REL_SYMBOLIC => {
+                if matches!(st_type, STT_OBJECT | STT_COMMON) {
+                    Some(Self::Data)
+                } else {
+                    Some(Self::Func)
+                }
+            }
+            REL_DTPMOD | REL_DTPOFF | REL_TPOFF => Some(Self::Tls),
+            #[cfg(target_arch = "riscv64")]
+            crate::arch::REL_TLSDESC => Some(Self::Tls),
+            REL_COPY => Some(Self::Copy),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `pre_find` or the scope is searched first for a symbol.
+///
+/// `post_find` is always searched last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupOrder {
+    /// Search `pre_find`, then the scope.
+    PreFindFirst,
+    /// Search the scope, then `pre_find`.
+    ScopeFirst,
+}
+
+/// A closure deciding the lookup order of a relocation.
+pub type LookupPolicyFn = Arc<dyn Fn(RelocKind, &str) -> LookupOrder + Send + Sync>;
+
+/// Decides, per relocation, whether `pre_find` or the scope wins.
+///
+/// Set with [`Relocator::lookup_policy`](crate::relocation::Relocator::lookup_policy).
+/// Without a policy, `pre_find` is always searched first.
+///
+/// `Tls` and `Copy` relocations need a definition inside a module, so they are
+/// only ever resolved from the scope and the policy is not consulted for them.
+/// Relative relocations (including `RELR`) have no symbol and are never
+/// affected.
+///
+/// # Examples
+/// ```
+/// use elf_loader::relocation::{LookupOrder, LookupPolicy, RelocKind};
+///
+/// // Interpose functions, but let data resolve from the loaded modules
+/// let policy = LookupPolicy::from(|kind: RelocKind, _name: &str| match kind {
+///     RelocKind::Func => LookupOrder::PreFindFirst,
+///     _ => LookupOrder::ScopeFirst,
+/// });
+/// assert_eq!(policy.order(RelocKind::Data, "errno"), LookupOrder::ScopeFirst);
+/// ```
+#[derive(Clone)]
+pub enum LookupPolicy {
+    /// Search `pre_find` first for every relocation.
+    PreferPreFind,
+    /// Search the scope first for every relocation.
+    PreferScope,
+    /// Call the closure with the kind of each relocation and the name of its symbol.
+    Custom(LookupPolicyFn),
+}
+
+impl LookupPolicy {
+    /// Returns the order in which a symbol is searched.
+    ///
+    /// # Arguments
+    /// * `kind` - What the relocation binds to.
+    /// * `name` - The name of the symbol.
+    #[inline]
+    pub fn order(&self, kind: RelocKind, name: &str) -> LookupOrder {
+        match self {
+            LookupPolicy::PreferPreFind => LookupOrder::PreFindFirst,
+            LookupPolicy::PreferScope => LookupOrder::ScopeFirst,
+            LookupPolicy::Custom(f) => f(kind, name),
+        }
+    }
+
+    /// Whether the scope is searched before `pre_find`.
+    #[inline]
+    pub(crate) fn scope_first(policy: Option<&Self>, kind: RelocKind, name: &str) -> bool {
+        policy.is_some_and(|policy| policy.order(kind, name) == LookupOrder::ScopeFirst)
+    }
+}
+
+impl<F> From<F> for LookupPolicy
+where
+    F: Fn(RelocKind, &str) -> LookupOrder + Send + Sync + 'static,
+{
+    fn from(f: F) -> Self {
+        LookupPolicy::Custom(Arc::new(f))
+    }
+}
+
+impl Debug for LookupPolicy {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LookupPolicy::PreferPreFind => f.write_str("PreferPreFind"),
+            LookupPolicy::PreferScope => f.write_str("PreferScope"),
+            LookupPolicy::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// How a versioned symbol reference is matched against the definitions in the
+/// scope, see [`Relocator::version_policy`](crate::relocation::Relocator::version_policy).
+///
+/// Libraries without version information satisfy any reference either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionPolicy {
+    /// Only a definition of the required version satisfies a reference.
+    Strict,
+    /// A reference whose version no library defines falls back to the default
+    /// version of a definition with the same name, like the GNU dynamic linker
+    /// does for the libraries merged into `libc.so.6`.
+    #[default]
+    Permissive,
+}
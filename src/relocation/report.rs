@@ -0,0 +1,208 @@
+//! Diagnostics collected during relocation
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// A `COPY` relocation whose symbol size differs between the referencing
+/// and the defining module.
+///
+/// Only `min(ref_size, def_size)` bytes are copied in this case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopySizeMismatch {
+    name: String,
+    provider: String,
+    ref_size: usize,
+    def_size: usize,
+}
+
+impl CopySizeMismatch {
+    /// Returns the name of the copied symbol.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the module that defines the symbol.
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    /// Returns the symbol size seen by the module being relocated.
+    pub fn ref_size(&self) -> usize {
+        self.ref_size
+    }
+
+    /// Returns the symbol size in the defining module.
+    pub fn def_size(&self) -> usize {
+        self.def_size
+    }
+
+    /// Returns the number of bytes that were actually copied.
+    pub fn copied_size(&self) -> usize {
+        self.ref_size.min(self.def_size)
+    }
+}
+
+/// Time spent in each phase of relocating a dynamic image.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Applying the relative relocations, including the `DT_RELR` table.
+    pub relative: Duration,
+    /// Applying the other entries of the dynamic relocation table.
+    pub symbolic: Duration,
+    /// Binding or preparing the PLT entries.
+    pub plt: Duration,
+    /// Applying the `PT_GNU_RELRO` protection.
+    pub relro: Duration,
+}
+
+/// Counters collected while relocating a module.
+///
+/// Enable the collection with [`Relocator::with_stats`](crate::relocation::Relocator::with_stats),
+/// or use [`Relocator::relocate_with_stats`](crate::relocation::Relocator::relocate_with_stats).
+/// Only dynamic images are instrumented; relocating other objects leaves every
+/// counter at zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelocationStats {
+    /// Number of processed entries per relocation type. Every word relocated
+    /// through the `DT_RELR` table counts as one `R_*_RELATIVE` entry.
+    pub by_type: BTreeMap<u32, usize>,
+    /// Symbols resolved by `pre_find` or the TLS allocator.
+    pub from_pre_find: usize,
+    /// Symbols resolved in the scope, or in the object itself if it is symbolic.
+    pub from_scope: usize,
+    /// Symbols resolved through the `post_find` fallback.
+    pub from_post_find: usize,
+    /// Symbol lookups that found no definition, including undefined weak
+    /// references bound to null.
+    pub unresolved: usize,
+    /// `JUMP_SLOT` entries left to be bound on first call.
+    pub lazy_deferred: usize,
+    /// Time spent in each phase.
+    #[cfg(feature = "std")]
+    pub timings: PhaseTimings,
+}
+
+impl RelocationStats {
+    pub(crate) fn add_type(&mut self, r_type: u32, count: usize) {
+        if count != 0 {
+            *self.by_type.entry(r_type).or_default() += count;
+        }
+    }
+}
+
+/// A phase measured by [`PhaseTimer`]
+#[derive(Clone, Copy)]
+pub(crate) enum Phase {
+    Relative,
+    Symbolic,
+    Plt,
+    Relro,
+}
+
+/// Measures the phases of a relocation when statistics are collected.
+///
+/// Without the `std` feature there is no clock and nothing is measured.
+pub(crate) struct PhaseTimer {
+    #[cfg(feature = "std")]
+    last: Option<Instant>,
+}
+
+impl PhaseTimer {
+    pub(crate) fn new(enabled: bool) -> Self {
+        #[cfg(not(feature = "std"))]
+        let _ = enabled;
+        Self {
+            #[cfg(feature = "std")]
+            last: enabled.then(Instant::now),
+        }
+    }
+
+    /// Adds the time since the previous lap to `phase`
+    pub(crate) fn lap(&mut self, stats: Option<&mut RelocationStats>, phase: Phase) {
+        #[cfg(feature = "std")]
+        if let (Some(last), Some(stats)) = (self.last.as_mut(), stats) {
+            let now = Instant::now();
+            let timings = &mut stats.timings;
+            *match phase {
+                Phase::Relative => &mut timings.relative,
+                Phase::Symbolic => &mut timings.symbolic,
+                Phase::Plt => &mut timings.plt,
+                Phase::Relro => &mut timings.relro,
+            } += now - *last;
+            *last = now;
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = (stats, phase);
+    }
+}
+
+/// A summary of noteworthy events that happened while relocating a module.
+///
+/// Use [`Relocator::relocate_with_report`](crate::relocation::Relocator::relocate_with_report)
+/// to obtain one.
+#[derive(Debug, Clone, Default)]
+pub struct RelocationReport {
+    copy_size_mismatches: Vec<CopySizeMismatch>,
+    post_find_symbols: Vec<String>,
+    stats: Option<RelocationStats>,
+}
+
+impl RelocationReport {
+    /// Returns every `COPY` relocation whose symbol sizes did not match.
+    pub fn copy_size_mismatches(&self) -> &[CopySizeMismatch] {
+        &self.copy_size_mismatches
+    }
+
+    /// Returns the names of the symbols resolved through the `post_find` fallback.
+    pub fn post_find_symbols(&self) -> &[String] {
+        &self.post_find_symbols
+    }
+
+    /// Returns the statistics of the relocation, if they were collected.
+    pub fn stats(&self) -> Option<&RelocationStats> {
+        self.stats.as_ref()
+    }
+
+    /// Returns `true` if nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.copy_size_mismatches.is_empty() && self.post_find_symbols.is_empty()
+    }
+
+    /// Creates an empty report that also collects [`RelocationStats`] if `stats` is set
+    pub(crate) fn new(stats: bool) -> Self {
+        Self {
+            stats: stats.then(RelocationStats::default),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn stats_mut(&mut self) -> Option<&mut RelocationStats> {
+        self.stats.as_mut()
+    }
+
+    pub(crate) fn into_stats(self) -> Option<RelocationStats> {
+        self.stats
+    }
+
+    pub(crate) fn add_copy_size_mismatch(
+        &mut self,
+        name: &str,
+        provider: &str,
+        ref_size: usize,
+        def_size: usize,
+    ) {
+        self.copy_size_mismatches.push(CopySizeMismatch {
+            name: name.into(),
+            provider: provider.into(),
+            ref_size,
+            def_size,
+        });
+    }
+
+    pub(crate) fn add_post_find_symbol(&mut self, name: &str) {
+        self.post_find_symbols.push(name.into());
+    }
+}
@@ -0,0 +1,262 @@
+//! Global symbol scope shared between loaded modules
+use crate::{
+    image::{ElfCoreRef, LoadedCore},
+    relocation::SymbolLookup,
+};
+use alloc::vec::Vec;
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use spin::RwLock;
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::{Arc, Weak};
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::{Arc, Weak};
+
+/// A non-owning reference to a registered [`LoadedCore`].
+struct ScopeEntry<D> {
+    /// Weak reference to the module itself
+    core: ElfCoreRef<D>,
+    /// Weak reference to the dependencies of the module
+    deps: Weak<[LoadedCore<D>]>,
+}
+
+impl<D> ScopeEntry<D> {
+    /// Attempts to upgrade the entry to a [`LoadedCore`].
+    ///
+    /// # Returns
+    /// * `Some(LoadedCore)` - If the module is still alive.
+    /// * `None` - If the module has been dropped.
+    fn upgrade(&self) -> Option<LoadedCore<D>> {
+        let core = self.core.upgrade()?;
+        let deps = self.deps.upgrade()?;
+        Some(LoadedCore { core, deps })
+    }
+}
+
+/// A global symbol scope for building a dynamic linker.
+///
+/// `GlobalScope` keeps an ordered list of registered modules and resolves
+/// symbols by searching them in insertion order. Modules are held through
+/// weak references, so registering a module does not keep it alive; modules
+/// that have been dropped are simply skipped during lookup.
+///
+/// Cloning a `GlobalScope` yields another handle to the same scope, which
+/// makes it suitable as the `lazy_scope` of a [`Relocator`](crate::relocation::Relocator).
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::{Loader, relocation::GlobalScope};
+///
+/// let scope = GlobalScope::new();
+/// let mut loader = Loader::new();
+/// let liba = loader
+///     .load_dylib("liba.so")
+///     .unwrap()
+///     .relocator()
+///     .relocate()
+///     .unwrap();
+/// scope.register(&liba);
+///
+/// let libb = loader
+///     .load_dylib("libb.so")
+///     .unwrap()
+///     .relocator()
+///     .scope(&scope.snapshot())
+///     .lazy(true)
+///     .lazy_scope(scope.clone())
+///     .relocate()
+///     .unwrap();
+/// scope.register(&libb);
+/// ```
+pub struct GlobalScope<D = ()> {
+    entries: Arc<RwLock<Vec<ScopeEntry<D>>>>,
+}
+
+impl<D> Clone for GlobalScope<D> {
+    /// Creates another handle to the same scope.
+    fn clone(&self) -> Self {
+        GlobalScope {
+            entries: Arc::clone(&self.entries),
+        }
+    }
+}
+
+impl<D> Default for GlobalScope<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> GlobalScope<D> {
+    /// Creates an empty scope.
+    pub fn new() -> Self {
+        GlobalScope {
+            entries: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Appends a module to the end of the scope.
+    ///
+    /// Only a weak reference is stored; the caller remains responsible for
+    /// keeping the module alive.
+    pub fn register(&self, lib: &LoadedCore<D>) {
+        self.entries.write().push(ScopeEntry {
+            core: lib.core.downgrade(),
+            deps: Arc::downgrade(&lib.deps),
+        });
+    }
+
+    /// Removes the first module with the given name from the scope.
+    ///
+    /// # Returns
+    /// `true` if a module was removed.
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut entries = self.entries.write();
+        // Drop entries whose modules are gone while we hold the lock
+        entries.retain(|entry| entry.core.upgrade().is_some());
+        let idx = entries
+            .iter()
+            .position(|entry| entry.core.upgrade().is_some_and(|core| core.name() == name));
+        if let Some(idx) = idx {
+            entries.remove(idx);
+            return true;
+        }
+        false
+    }
+
+    /// Returns the modules currently in the scope, in insertion order.
+    ///
+    /// Modules that have been dropped are omitted. The result can be passed
+    /// directly to [`Relocator::scope`](crate::relocation::Relocator::scope).
+    pub fn snapshot(&self) -> Vec<LoadedCore<D>> {
+        self.entries
+            .read()
+            .iter()
+            .filter_map(ScopeEntry::upgrade)
+            .collect()
+    }
+
+    /// Returns the number of registered modules, including ones that have been dropped.
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+}
+
+impl<D> SymbolLookup for GlobalScope<D> {
+    fn lookup(&self, name: &str) -> Option<*const ()> {
+        self.entries.read().iter().find_map(|entry| unsafe {
+            let lib = entry.upgrade()?;
+            lib.get::<()>(name).map(|sym| sym.into_raw())
+        })
+    }
+}
+
+/// A module made visible with [`Relocator::global`](crate::relocation::Relocator::global)
+struct GlobalEntry {
+    /// Address of the module's `CoreInner`, used to identify it on removal
+    id: usize,
+    /// Cleared when the module is released, the entry is pruned on the next registration
+    live: AtomicBool,
+    entry: ScopeEntry<()>,
+}
+
+impl GlobalEntry {
+    /// Upgrades the entry unless its module was released.
+    fn upgrade(&self) -> Option<LoadedCore<()>> {
+        if !self.live.load(Ordering::Acquire) {
+            return None;
+        }
+        self.entry.upgrade()
+    }
+}
+
+/// Modules visible to the lazy binding of every other module, in registration order
+static GLOBAL_SCOPE: RwLock<Vec<GlobalEntry>> = RwLock::new(Vec::new());
+
+/// Returns the modules relocated with [`Relocator::global`](crate::relocation::Relocator::global)
+/// that are still alive, in the order they were relocated.
+///
+/// These modules are searched by lazy binding after the lazy scope of a
+/// module, like the `RTLD_GLOBAL` modules of `dlopen`. The result can be
+/// passed directly to [`Relocator::scope`](crate::relocation::Relocator::scope)
+/// to resolve the eager relocations of a module against them as well.
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::Loader;
+///
+/// let mut loader = Loader::new();
+/// let liba = loader
+///     .load_dylib("liba.so")
+///     .unwrap()
+///     .relocator()
+///     .global(true)
+///     .relocate()
+///     .unwrap();
+///
+/// let libb = loader
+///     .load_dylib("libb.so")
+///     .unwrap()
+///     .relocator()
+///     .scope(elf_loader::global_scope())
+///     .relocate()
+///     .unwrap();
+/// ```
+pub fn global_scope() -> Vec<LoadedCore<()>> {
+    GLOBAL_SCOPE
+        .read()
+        .iter()
+        .filter_map(GlobalEntry::upgrade)
+        .collect()
+}
+
+/// Looks a symbol up in the modules of the global scope.
+///
+/// Lazy binding goes through here, so the scope is searched in place. A module
+/// upgraded here may be released when it is dropped, which is fine under the
+/// read lock since [`unregister_global`] only takes the read lock as well.
+pub(crate) fn global_lookup(name: &str) -> Option<*const ()> {
+    GLOBAL_SCOPE.read().iter().find_map(|global| unsafe {
+        let lib = global.upgrade()?;
+        lib.get::<()>(name).map(|sym| sym.into_raw())
+    })
+}
+
+/// Adds a relocated module to the global scope.
+///
+/// Only modules without user data can be global, which
+/// [`Relocator::global`](crate::relocation::Relocator::global) ensures.
+pub(crate) fn register_global<D: 'static>(lib: &LoadedCore<D>) {
+    let Some(lib) = (lib as &dyn Any).downcast_ref::<LoadedCore<()>>() else {
+        return;
+    };
+    let mut scope = GLOBAL_SCOPE.write();
+    // Pruning only drops weak references, which never releases a module
+    scope.retain(|global| global.live.load(Ordering::Relaxed));
+    lib.core.inner.set_global();
+    scope.push(GlobalEntry {
+        id: lib.core.inner_addr(),
+        live: AtomicBool::new(true),
+        entry: ScopeEntry {
+            core: lib.core.downgrade(),
+            deps: Arc::downgrade(&lib.deps),
+        },
+    });
+}
+
+/// Removes a module from the global scope.
+///
+/// The entry is only marked dead: a module can be released while the scope is
+/// read, and the entry is pruned by the next [`register_global`].
+pub(crate) fn unregister_global(id: usize) {
+    if let Some(global) = GLOBAL_SCOPE
+        .read()
+        .iter()
+        .find(|global| global.id == id && global.live.load(Ordering::Relaxed))
+    {
+        global.live.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,255 @@
+//! Resumable relocation of dynamic libraries
+use crate::{
+    Result,
+    image::{DynamicImage, LoadedCore, LoadedDylib},
+    relocation::{RelocHelper, SymbolLookup, VersionPolicy, main_program_pos},
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{borrow::Borrow, fmt::Debug};
+
+/// How far the entries of one relocation table have been applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableProgress {
+    /// Number of entries applied
+    pub done: usize,
+    /// Number of entries in the table
+    pub total: usize,
+}
+
+impl TableProgress {
+    const fn new(total: usize) -> Self {
+        Self { done: 0, total }
+    }
+
+    /// Whether every entry of the table has been applied.
+    #[inline]
+    pub const fn is_done(&self) -> bool {
+        self.done == self.total
+    }
+
+    /// Number of entries left to apply.
+    #[inline]
+    pub const fn remaining(&self) -> usize {
+        self.total - self.done
+    }
+}
+
+/// Progress of a [`RelocationSession`], table by table.
+///
+/// The tables are applied in the order of the fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// The relative relocations, either `DT_RELR` or the leading
+    /// `R_*_RELATIVE` entries of `DT_RELA`/`DT_REL`.
+    pub relative: TableProgress,
+    /// The other dynamic relocations (`.rela.dyn`).
+    pub dynamic: TableProgress,
+    /// The PLT relocations (`.rela.plt`).
+    pub plt: TableProgress,
+}
+
+impl Progress {
+    /// Whether every relocation has been applied.
+    #[inline]
+    pub const fn is_done(&self) -> bool {
+        self.relative.is_done() && self.dynamic.is_done() && self.plt.is_done()
+    }
+}
+
+/// A relocation of a dynamic library that is applied a bounded number of
+/// entries at a time.
+///
+/// Created by [`RawDylib::begin_relocation`](crate::image::RawDylib::begin_relocation),
+/// it lets a cooperative scheduler spread the relocation of a large library
+/// over several ticks with [`step`](Self::step). The library is bound
+/// eagerly, without lazy binding, and can't be used before
+/// [`finish`](Self::finish) returns it: RELRO protection and the
+/// initializers only run then.
+///
+/// Dropping an unfinished session unmaps the library without running its
+/// initializers or finalizers.
+///
+/// # Examples
+/// ```no_run
+/// use elf_loader::Loader;
+///
+/// let mut loader = Loader::new();
+/// let libb = loader.load_dylib("libb.so").unwrap().relocator().relocate().unwrap();
+/// let lib = loader.load_dylib("liba.so").unwrap();
+/// let mut session = lib.begin_relocation([&libb], (), ());
+/// while !session.step(64).unwrap().is_done() {
+///     // Yield to the other tasks
+/// }
+/// let lib = session.finish().unwrap();
+/// ```
+pub struct RelocationSession<D: 'static> {
+    image: DynamicImage<D>,
+    scope: Vec<LoadedCore<D>>,
+    pre_find: Box<dyn SymbolLookup>,
+    post_find: Box<dyn SymbolLookup>,
+    /// Which libraries of `scope` provided a symbol so far
+    dependency_flags: Vec<bool>,
+    progress: Progress,
+    /// Whether the text segments are writable for the text relocations
+    text_writable: bool,
+}
+
+impl<D> Debug for RelocationSession<D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RelocationSession")
+            .field("name", &self.image.name())
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+impl<D> RelocationSession<D> {
+    pub(crate) fn new<I, R>(
+        image: DynamicImage<D>,
+        scope: I,
+        pre_find: Box<dyn SymbolLookup>,
+        post_find: Box<dyn SymbolLookup>,
+    ) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Borrow<LoadedCore<D>>,
+    {
+        #[cfg(feature = "aarch64-pac")]
+        image
+            .core_ref()
+            .inner
+            .pac
+            .store(image.pac(), core::sync::atomic::Ordering::Relaxed);
+        let scope: Vec<_> = scope.into_iter().map(|lib| lib.borrow().clone()).collect();
+        let reloc = image.relocation();
+        let progress = Progress {
+            relative: TableProgress::new(reloc.relative_len()),
+            dynamic: TableProgress::new(reloc.dynrel_len()),
+            plt: TableProgress::new(reloc.pltrel_len()),
+        };
+        Self {
+            dependency_flags: alloc::vec![false; scope.len()],
+            image,
+            scope,
+            pre_find,
+            post_find,
+            progress,
+            text_writable: false,
+        }
+    }
+
+    /// Returns the name of the library being relocated.
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.image.name()
+    }
+
+    /// Returns how far the relocation has progressed.
+    #[inline]
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// Applies up to `max_entries` relocation entries.
+    ///
+    /// A `DT_RELR` address entry is always applied together with the bitmap
+    /// entries that follow it, so a step may go slightly past `max_entries`.
+    ///
+    /// # Returns
+    /// * `Ok(Progress)` - The progress after this step.
+    /// * `Err(Error)` - If an entry can't be applied. The session can't make
+    ///   progress past that entry and should be dropped.
+    pub fn step(&mut self, max_entries: usize) -> Result<Progress> {
+        // Text relocations are confined to the relative and dynamic tables
+        let textrel_done = self.progress.relative.is_done() && self.progress.dynamic.is_done();
+        self.text_writable(!textrel_done)?;
+
+        let Self {
+            image,
+            scope,
+            pre_find,
+            post_find,
+            dependency_flags,
+            progress,
+            ..
+        } = self;
+        let mut budget = max_entries;
+        let relative = &mut progress.relative;
+        if budget > 0 && !relative.is_done() {
+            let end = image.relocate_relative_from(relative.done, budget);
+            budget = budget.saturating_sub(end - relative.done);
+            relative.done = end;
+        }
+
+        let auditor = image.auditor().cloned();
+        let (mut pre_handler, mut post_handler) = ((), ());
+        let mut helper = RelocHelper {
+            scope,
+            pre_find: &**pre_find,
+            post_find: &**post_find,
+            pre_handler: &mut pre_handler,
+            post_handler: &mut post_handler,
+            dependency_flags: core::mem::take(dependency_flags),
+            report: None,
+            policy: None,
+            version_policy: VersionPolicy::default(),
+            scope_index: None,
+            self_pos: None,
+            missing: None,
+            auditor: auditor.as_deref(),
+            main_program: main_program_pos(scope),
+        };
+        let mut apply = || -> Result<()> {
+            let dynamic = &mut progress.dynamic;
+            if budget > 0 && !dynamic.is_done() {
+                let end = dynamic.done + budget.min(dynamic.remaining());
+                image.relocate_dynrel(&mut helper, dynamic.done..end)?;
+                budget -= end - dynamic.done;
+                dynamic.done = end;
+            }
+            let plt = &mut progress.plt;
+            if budget > 0 && !plt.is_done() {
+                let end = plt.done + budget.min(plt.remaining());
+                image.bind_pltrel(false, None::<&()>, &mut helper, plt.done..end)?;
+                plt.done = end;
+            }
+            Ok(())
+        };
+        let result = apply();
+        *dependency_flags = helper.dependency_flags;
+        result?;
+
+        if self.progress.relative.is_done() && self.progress.dynamic.is_done() {
+            self.text_writable(false)?;
+        }
+        Ok(self.progress)
+    }
+
+    /// Applies the remaining relocations, RELRO protection and the
+    /// initializers, and returns the relocated library.
+    pub fn finish(mut self) -> Result<LoadedDylib<D>> {
+        while !self.step(usize::MAX)?.is_done() {}
+        self.image.protect_relro()?;
+        let inner = self
+            .image
+            .into_loaded(&self.scope, self.dependency_flags, None, None)?;
+        Ok(LoadedDylib { inner })
+    }
+
+    /// Makes the segments patched by text relocations writable or protects
+    /// them again
+    fn text_writable(&mut self, writable: bool) -> Result<()> {
+        if self.text_writable == writable {
+            return Ok(());
+        }
+        if let Some(text) = self.image.text_segments() {
+            if writable {
+                text.unprotect()?;
+            } else {
+                text.protect()?;
+            }
+        }
+        self.text_writable = writable;
+        Ok(())
+    }
+}
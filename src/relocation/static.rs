@@ -2,11 +2,17 @@ use crate::{
     Result,
     arch::StaticRelocator,
     elf::ElfRelType,
-    image::{ElfCore, LoadedCore, RawObject},
-    relocation::SymbolLookup,
+    image::{ElfCore, FnArray, LoadedCore, RawObject},
+    relocation::{PostFini, PreInit, SymbolLookup},
     segment::section::PltGotSection,
 };
 use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::Ordering;
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
 
 pub(crate) struct StaticRelocation {
     relocation: Box<[&'static [ElfRelType]]>,
@@ -26,11 +32,14 @@ impl RawObject {
         scope: &[LoadedCore<()>],
         pre_find: &PreS,
         post_find: &PostS,
+        pre_init: Option<&PreInit<()>>,
+        post_fini: Option<Arc<PostFini>>,
     ) -> Result<LoadedCore<()>>
     where
         PreS: SymbolLookup + ?Sized,
         PostS: SymbolLookup + ?Sized,
     {
+        let renames = self.hide_symbols();
         for reloc in self.relocation.relocation.iter() {
             for rel in *reloc {
                 StaticRelocator::relocate(
@@ -43,9 +52,20 @@ impl RawObject {
                 )?;
             }
         }
+        self.rename_symbols(renames)?;
         (self.mprotect)()?;
-        (self.init)(None, self.init_array);
-        Ok(unsafe { LoadedCore::from_core(self.core) })
+        self.core.set_init();
+        let relocated = unsafe { LoadedCore::from_core(self.core) };
+        if let Some(pre_init) = pre_init
+            && let Err(err) = pre_init(&relocated)
+        {
+            // The finalizers must not run for initializers that never ran
+            relocated.core.inner.is_init.store(false, Ordering::Release);
+            return Err(err);
+        }
+        *relocated.core.inner.post_fini.lock() = post_fini;
+        (self.init)(None, Some(&FnArray::merge(&self.init_arrays)));
+        Ok(relocated)
     }
 }
 
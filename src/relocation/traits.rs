@@ -1,10 +1,14 @@
-use super::{SymDef, find_symdef_impl};
+use super::{
+    LookupPolicy, RelocKind, RelocationReport, ScopeIndex, SymDef, VersionPolicy, find_symdef_impl,
+};
 use crate::{
     Result,
     elf::ElfRelType,
     image::{ElfCore, LoadedCore},
+    relocate_error,
 };
-use alloc::boxed::Box;
+use alloc::{boxed::Box, format, vec::Vec};
+use core::cell::Cell;
 
 #[cfg(not(feature = "portable-atomic"))]
 use alloc::sync::Arc;
@@ -80,6 +84,62 @@ impl SymbolLookup for () {
     }
 }
 
+/// A trait for running independent relocation work on a user-provided thread pool.
+///
+/// When set with [`Relocator::parallel`](crate::relocation::Relocator::parallel),
+/// the relative relocations (`R_*_RELATIVE` and the `RELR` table) are split into
+/// tasks that are handed to [`execute`](ParallelExecutor::execute). Every other
+/// relocation is still processed on the calling thread.
+///
+/// # Examples
+/// ```rust
+/// use elf_loader::relocation::ParallelExecutor;
+///
+/// struct ScopedThreads(usize);
+///
+/// impl ParallelExecutor for ScopedThreads {
+///     fn parallelism(&self) -> usize {
+///         self.0
+///     }
+///
+///     fn execute<'t>(&self, tasks: Vec<Box<dyn FnOnce() + Send + 't>>) {
+///         std::thread::scope(|s| {
+///             for task in tasks {
+///                 s.spawn(task);
+///             }
+///         });
+///     }
+/// }
+/// ```
+pub trait ParallelExecutor {
+    /// Returns the number of tasks the work should be split into.
+    fn parallelism(&self) -> usize;
+
+    /// Runs every task to completion before returning.
+    ///
+    /// The tasks are independent of each other and may run in any order and on
+    /// any thread.
+    ///
+    /// # Arguments
+    /// * `tasks` - The tasks to run.
+    fn execute<'t>(&self, tasks: Vec<Box<dyn FnOnce() + Send + 't>>);
+}
+
+/// The outcome of a relocation handled by a [`RelocationHandler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handled {
+    /// The relocation was applied. Carries the index in the scope of the
+    /// library that provided the symbol, if any, so that it is kept as a
+    /// dependency.
+    Done(Option<usize>),
+    /// Run the default processing of the relocation again, for instance after
+    /// changing its addend with [`RelocationContext::set_addend`].
+    ///
+    /// A post handler may ask for a retry only once per relocation; if the
+    /// default processing still cannot apply it, the relocation fails.
+    Retry,
+}
+
 /// A trait for handling unknown or custom relocations.
 ///
 /// Implement this to provide custom logic for relocations not handled by default,
@@ -88,19 +148,20 @@ impl SymbolLookup for () {
 /// # Examples
 ///
 /// ```rust
-/// use elf_loader::relocation::{RelocationHandler, RelocationContext};
+/// use elf_loader::relocation::{Handled, RelocationHandler, RelocationContext};
 /// use elf_loader::Result;
 ///
 /// struct CustomHandler;
 ///
 /// impl RelocationHandler for CustomHandler {
-///     fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Option<usize>>> {
+///     fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Handled>> {
 ///         let rel = ctx.rel();
 ///         // Handle specific relocation types
 ///         match rel.r_type() {
 ///             0x1234 => {
 ///                 // Custom relocation logic
-///                 Some(Ok(None)) // Handled successfully
+///                 let value = ctx.base().wrapping_add_signed(ctx.addend());
+///                 Some(ctx.write_word(value).map(|()| Handled::Done(None)))
 ///             }
 ///             _ => None, // Fall through to default
 ///         }
@@ -114,11 +175,12 @@ pub trait RelocationHandler {
     /// * `ctx` - Context containing relocation details and scope.
     ///
     /// # Returns
-    /// * `Some(Ok(None))` - Handled successfully, no library dependency.
-    /// * `Some(Ok(Some(idx)))` - Handled successfully, used library at `scope[idx]`.
+    /// * `Some(Ok(Handled::Done(None)))` - Handled successfully, no library dependency.
+    /// * `Some(Ok(Handled::Done(Some(idx))))` - Handled successfully, used library at `scope[idx]`.
+    /// * `Some(Ok(Handled::Retry))` - Run the default processing with the current addend.
     /// * `Some(Err(e))` - Handled but failed with error.
     /// * `None` - Not handled, fall through to default behavior.
-    fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Option<usize>>>;
+    fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Handled>>;
 }
 
 /// Context passed to `RelocationHandler::handle` containing relocation details.
@@ -129,6 +191,9 @@ pub struct RelocationContext<'a, D> {
     rel: &'a ElfRelType,
     lib: &'a ElfCore<D>,
     scope: &'a [LoadedCore<D>],
+    /// Addend used by the default processing, read before anything is
+    /// written to the target of the entry
+    addend: Cell<isize>,
 }
 
 impl<'a, D> RelocationContext<'a, D> {
@@ -139,7 +204,12 @@ impl<'a, D> RelocationContext<'a, D> {
         lib: &'a ElfCore<D>,
         scope: &'a [LoadedCore<D>],
     ) -> Self {
-        Self { rel, lib, scope }
+        Self {
+            rel,
+            lib,
+            scope,
+            addend: Cell::new(rel.reloc_addend(lib.base())),
+        }
     }
 
     /// Access the relocation entry.
@@ -148,6 +218,20 @@ impl<'a, D> RelocationContext<'a, D> {
         self.rel
     }
 
+    /// Classifies what the relocation binds to.
+    ///
+    /// # Returns
+    /// `None` for relocations without a symbol and for types outside the
+    /// ones a [`LookupPolicy`] distinguishes.
+    #[inline]
+    pub fn kind(&self) -> Option<RelocKind> {
+        if self.rel.r_symbol() == 0 {
+            return None;
+        }
+        let (sym, _) = self.lib.symtab().symbol_idx(self.rel.r_symbol());
+        RelocKind::classify(self.rel.r_type() as u32, sym.st_type())
+    }
+
     /// Access the core component where the relocation appears.
     #[inline]
     pub fn lib(&self) -> &ElfCore<D> {
@@ -160,33 +244,142 @@ impl<'a, D> RelocationContext<'a, D> {
         self.scope
     }
 
+    /// Returns the base address of the module being relocated.
+    #[inline]
+    pub fn base(&self) -> usize {
+        self.lib.base()
+    }
+
+    /// Returns the addend used by the default processing.
+    ///
+    /// This is the addend of the entry unless it was replaced with
+    /// [`set_addend`](Self::set_addend). The implicit addend of a `REL` entry
+    /// is read from the target location before any handler runs, so writing
+    /// to the target does not change it. `REL` GOT and jump slot entries have
+    /// an addend of zero.
+    #[inline]
+    pub fn addend(&self) -> isize {
+        self.addend.get()
+    }
+
+    /// Replaces the addend used by the default processing of this relocation.
+    ///
+    /// Return [`Handled::Retry`] to have the relocation processed with it.
+    #[inline]
+    pub fn set_addend(&self, addend: isize) {
+        self.addend.set(addend);
+    }
+
     /// Find symbol definition in the current scope
     #[inline]
     pub fn find_symdef(&self, r_sym: usize) -> Option<(SymDef<'a, D>, Option<usize>)> {
         let symbol = self.lib.symtab();
         let (sym, syminfo) = symbol.symbol_idx(r_sym);
-        find_symdef_impl(self.lib, self.scope, sym, &syminfo)
+        find_symdef_impl(self.lib, self.scope, None, None, sym, &syminfo)
+    }
+
+    /// Resolves the symbol of the relocation entry in the current scope.
+    ///
+    /// # Returns
+    /// `None` if the entry has no symbol or the symbol is not defined in the
+    /// module or its scope. Use [`find_symdef`](Self::find_symdef) to also get
+    /// the scope index of the defining library.
+    #[inline]
+    pub fn symdef(&self) -> Option<SymDef<'a, D>> {
+        match self.rel.r_symbol() {
+            0 => None,
+            r_sym => self.find_symdef(r_sym).map(|(symdef, _)| symdef),
+        }
+    }
+
+    /// Returns a pointer to the location patched by the relocation.
+    ///
+    /// # Returns
+    /// An error if a `T` at the target does not lie within the mapped
+    /// memory of the module.
+    pub fn target_ptr<T>(&self) -> Result<*mut T> {
+        let segments = self.lib.segments();
+        let offset = self.rel.r_offset();
+        let start = segments.offset;
+        let end = start + segments.len();
+        if offset < start || offset.checked_add(size_of::<T>()).is_none_or(|e| e > end) {
+            return Err(relocate_error(format!(
+                "relocation target 0x{offset:x} is outside the mapped segments"
+            )));
+        }
+        Ok(segments.get_mut_ptr_unchecked::<T>(offset))
+    }
+
+    /// Writes a word to the location patched by the relocation.
+    pub fn write_word(&self, value: usize) -> Result<()> {
+        unsafe { self.target_ptr::<usize>()?.write_unaligned(value) };
+        Ok(())
+    }
+
+    /// Writes a 32-bit value to the location patched by the relocation.
+    pub fn write_u32(&self, value: u32) -> Result<()> {
+        unsafe { self.target_ptr::<u32>()?.write_unaligned(value) };
+        Ok(())
     }
 }
 
 impl RelocationHandler for () {
-    fn handle<D>(&mut self, _ctx: &RelocationContext<'_, D>) -> Option<Result<Option<usize>>> {
+    fn handle<D>(&mut self, _ctx: &RelocationContext<'_, D>) -> Option<Result<Handled>> {
         None
     }
 }
 
 impl<H: RelocationHandler + ?Sized> RelocationHandler for &mut H {
-    fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Option<usize>>> {
+    fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Handled>> {
         (**self).handle(ctx)
     }
 }
 
 impl<H: RelocationHandler + ?Sized> RelocationHandler for Box<H> {
-    fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Option<usize>>> {
+    fn handle<D>(&mut self, ctx: &RelocationContext<'_, D>) -> Option<Result<Handled>> {
         (**self).handle(ctx)
     }
 }
 
+/// A hook called with a relocated module before its initializers run.
+///
+/// See [`Relocator::pre_init`](crate::relocation::Relocator::pre_init).
+pub type PreInit<D> = dyn Fn(&LoadedCore<D>) -> Result<()>;
+
+/// A hook called with the name of a module after its finalizers ran.
+///
+/// See [`Relocator::post_fini`](crate::relocation::Relocator::post_fini).
+pub type PostFini = dyn Fn(&str) + Send + Sync;
+
+/// The options of [`Relocatable::relocate`] besides the lookups and handlers.
+pub struct RelocateOptions<'a, D> {
+    /// Whether to enable lazy binding, `None` follows the flags of the object.
+    pub(crate) lazy: Option<bool>,
+    /// Whether to audit the relocation tables before applying them.
+    pub(crate) strict: bool,
+    /// Whether to make the `PT_GNU_RELRO` segment read-only afterwards.
+    pub(crate) apply_relro: bool,
+    /// Whether to report all unresolved symbols at once.
+    pub(crate) collect_missing: bool,
+    /// Order of `pre_find` and the scope, if not the default.
+    pub(crate) policy: Option<LookupPolicy>,
+    /// How versioned references are matched to definitions.
+    pub(crate) version_policy: VersionPolicy,
+    /// Precomputed index of the scope, if any.
+    pub(crate) scope_index: Option<&'a ScopeIndex<D>>,
+    /// Where the object itself is searched among the scope, for lookups that
+    /// take the first definition in order, if any.
+    pub(crate) self_pos: Option<usize>,
+    /// Where to record relocation diagnostics, if requested.
+    pub(crate) report: Option<&'a mut RelocationReport>,
+    /// Thread pool for the relative relocations, if any.
+    pub(crate) executor: Option<&'a dyn ParallelExecutor>,
+    /// Called once the object is relocated, before its initializers run, if any.
+    pub(crate) pre_init: Option<&'a PreInit<D>>,
+    /// Called once the finalizers of the object ran, if any.
+    pub(crate) post_fini: Option<Arc<PostFini>>,
+}
+
 /// A trait for objects that can be relocated.
 ///
 /// Types implementing this trait can undergo symbol resolution and address fixup.
@@ -204,8 +397,8 @@ pub trait Relocatable<D = ()>: Sized {
     /// * `post_find` - Fallback symbol lookup strategy.
     /// * `pre_handler` - Handler called before default relocation logic.
     /// * `post_handler` - Handler called after default logic if not handled.
-    /// * `lazy` - Whether to enable lazy binding.
     /// * `lazy_scope` - Symbol lookup for lazy binding.
+    /// * `options` - The remaining relocation options.
     ///
     /// # Returns
     /// The relocated object on success.
@@ -216,8 +409,8 @@ pub trait Relocatable<D = ()>: Sized {
         post_find: &PostS,
         pre_handler: PreH,
         post_handler: PostH,
-        lazy: Option<bool>,
         lazy_scope: Option<LazyS>,
+        options: RelocateOptions<'_, D>,
     ) -> Result<Self::Output>
     where
         PreS: SymbolLookup + ?Sized,
@@ -1,22 +1,57 @@
 use crate::{
-    Error, Result,
-    elf::{ElfRelType, ElfSymbol, SymbolInfo, SymbolTable},
-    image::{ElfCore, LoadedCore},
+    Error, MissingSymbol, RelocationErrorContext, Result,
+    elf::{ElfRelType, ElfSymbol, PreCompute, SymbolInfo},
+    image::{ElfCore, LoadedCore, RawDylib, RawObject, Visibility},
     relocate_error,
-    relocation::{Relocatable, RelocationContext, RelocationHandler, SymbolLookup},
+    relocation::{
+        Auditor, CompatFlags, DlopenScope, Handled, LookupPolicy, ParallelExecutor, PostFini,
+        PreInit, RelocKind, Relocatable, RelocateOptions, RelocationContext, RelocationHandler,
+        RelocationReport, RelocationStats, ScopeIndex, SymbolBinding, SymbolLookup, VersionPolicy,
+        global_scope,
+    },
+};
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
 };
-use alloc::{format, string::ToString, vec::Vec};
 use core::{
     ops::{Add, Sub},
     ptr::null,
 };
 use elf::abi::STT_GNU_IFUNC;
 
+#[cfg(feature = "trace")]
+use crate::trace::{BindProvider, TraceEvent};
+
 #[cfg(not(feature = "portable-atomic"))]
 use alloc::sync::Arc;
 #[cfg(feature = "portable-atomic")]
 use portable_atomic_util::Arc;
 
+/// Symbol sources consulted while processing a relocation entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lookup {
+    /// No symbol lookup happened
+    None,
+    /// Only the scope libraries were searched
+    Scope,
+    /// The TLS allocator, pre_find, the scope libraries and post_find were searched
+    All,
+}
+
+/// Where `find_symbol` found a definition
+#[derive(Clone, Copy)]
+enum Source<'lib> {
+    PreFind,
+    /// A definition in the module of the given name
+    Scope(&'lib str),
+    PostFind,
+    /// An undefined weak reference that nothing defines
+    Unresolved,
+}
+
 /// Internal context for managing relocation state and handlers.
 pub(crate) struct RelocHelper<
     'a,
@@ -33,6 +68,19 @@ pub(crate) struct RelocHelper<
     pub(crate) pre_handler: &'a mut PreH,
     pub(crate) post_handler: &'a mut PostH,
     pub(crate) dependency_flags: Vec<bool>,
+    pub(crate) report: Option<&'a mut RelocationReport>,
+    pub(crate) policy: Option<&'a LookupPolicy>,
+    pub(crate) version_policy: VersionPolicy,
+    /// The index of `scope`, if one was given for exactly this scope
+    pub(crate) scope_index: Option<&'a ScopeIndex<D>>,
+    /// Where the module itself is searched among `scope`, if the first
+    /// definition in scope order is taken
+    pub(crate) self_pos: Option<usize>,
+    /// The unresolved references, if they are collected instead of failing
+    pub(crate) missing: Option<Vec<MissingSymbol>>,
+    pub(crate) auditor: Option<&'a dyn Auditor<D>>,
+    /// Where the main program is in `scope`, its copies of variables take precedence
+    pub(crate) main_program: Option<usize>,
 }
 
 impl<'a, 'find, D, PreS: ?Sized, PostS: ?Sized, PreH: ?Sized, PostH: ?Sized>
@@ -41,28 +89,229 @@ where
     PreH: RelocationHandler,
     PostH: RelocationHandler,
 {
+    /// Returns the statistics being collected, if any.
     #[inline]
-    pub(crate) fn handle_pre(&mut self, hctx: &RelocationContext<'_, D>) -> Result<bool> {
-        let opt = self.pre_handler.handle(hctx);
-        if let Some(r) = opt {
-            if let Some(idx) = r? {
-                self.dependency_flags[idx] = true;
+    pub(crate) fn stats(&mut self) -> Option<&mut RelocationStats> {
+        self.report.as_deref_mut()?.stats_mut()
+    }
+
+    /// Finds the address of a symbol through the TLS allocator, pre_find and
+    /// the scope in the order of the lookup policy, and post_find, marking the
+    /// providing library as a dependency.
+    #[inline]
+    pub(crate) fn find_symbol(
+        &mut self,
+        core: &ElfCore<D>,
+        r_sym: usize,
+        r_type: u32,
+    ) -> Option<RelocValue<usize>>
+    where
+        PreS: SymbolLookup,
+        PostS: SymbolLookup,
+    {
+        let found = self.find_symbol_impl(core, r_sym, r_type);
+        if let Some(stats) = self.stats() {
+            match found {
+                Some((_, Source::PreFind)) => stats.from_pre_find += 1,
+                Some((_, Source::Scope(_))) => stats.from_scope += 1,
+                Some((_, Source::PostFind)) => stats.from_post_find += 1,
+                Some((_, Source::Unresolved)) | None => stats.unresolved += 1,
+            }
+        }
+        let (value, source) = found?;
+        if let Some(auditor) = self.auditor {
+            let provider = match source {
+                Source::Scope(provider) => Some(provider),
+                _ => None,
+            };
+            let name = core.symtab().symbol_idx(r_sym).1.name();
+            let binding = SymbolBinding::new(core.name(), provider, name, value.0);
+            if let Some(addr) = auditor.symbol_bound(&binding) {
+                return Some(RelocValue::new(addr));
+            }
+        }
+        Some(value)
+    }
+
+    #[inline]
+    fn find_symbol_impl<'lib>(
+        &mut self,
+        core: &'lib ElfCore<D>,
+        r_sym: usize,
+        r_type: u32,
+    ) -> Option<(RelocValue<usize>, Source<'lib>)>
+    where
+        'a: 'lib,
+        PreS: SymbolLookup,
+        PostS: SymbolLookup,
+    {
+        let (dynsym, syminfo) = core.symtab().symbol_idx(r_sym);
+        let syminfo = syminfo.exact_version(self.version_policy == VersionPolicy::Strict);
+        if let Some(addr) = core.tls().and_then(|tls| tls.lookup(syminfo.name())) {
+            #[cfg(feature = "trace")]
+            crate::trace::emit(TraceEvent::SymbolBind {
+                lib: core.name(),
+                symbol: syminfo.name(),
+                provider: BindProvider::TlsAllocator,
+            });
+            return Some((RelocValue::new(addr as usize), Source::PreFind));
+        }
+        if let Some(symdef) = find_symbolic(core, &syminfo) {
+            let value = RelocValue::new(symdef.convert() as usize);
+            return Some((value, Source::Scope(core.name())));
+        }
+        let scope_first = self.policy.is_some()
+            && RelocKind::classify(r_type, dynsym.st_type())
+                .is_some_and(|kind| LookupPolicy::scope_first(self.policy, kind, syminfo.name()));
+        let pre_find = |name: &str| {
+            let addr = self.pre_find.lookup(name)?;
+            #[cfg(feature = "trace")]
+            crate::trace::emit(TraceEvent::SymbolBind {
+                lib: core.name(),
+                symbol: name,
+                provider: BindProvider::PreFind,
+            });
+            Some((RelocValue::new(addr as usize), Source::PreFind))
+        };
+        if !scope_first && let Some(found) = pre_find(syminfo.name()) {
+            return Some(found);
+        }
+        if let Some(idx) = self.main_program
+            && let Some(addr) = self.scope[idx].core.copied(syminfo.name())
+        {
+            self.dependency_flags[idx] = true;
+            let provider = self.scope[idx].name();
+            #[cfg(feature = "trace")]
+            crate::trace::emit(TraceEvent::SymbolBind {
+                lib: core.name(),
+                symbol: syminfo.name(),
+                provider: BindProvider::Module(provider),
+            });
+            return Some((RelocValue::new(addr), Source::Scope(provider)));
+        }
+        let weak_undef = match find_symdef_impl(
+            core,
+            self.scope,
+            self.scope_index,
+            self.self_pos,
+            dynsym,
+            &syminfo,
+        ) {
+            Some((symdef, _)) if symdef.sym.is_none() => true,
+            Some((symdef, idx)) => {
+                if let Some(idx) = idx {
+                    self.dependency_flags[idx] = true;
+                }
+                let provider = symdef.lib.name();
+                let value = RelocValue::new(symdef.convert() as usize);
+                return Some((value, Source::Scope(provider)));
+            }
+            None => false,
+        };
+        if scope_first && let Some(found) = pre_find(syminfo.name()) {
+            return Some(found);
+        }
+        if let Some(addr) = self.post_find.lookup(syminfo.name()) {
+            #[cfg(feature = "trace")]
+            crate::trace::emit(TraceEvent::SymbolBind {
+                lib: core.name(),
+                symbol: syminfo.name(),
+                provider: BindProvider::PostFind,
+            });
+            if let Some(report) = self.report.as_deref_mut() {
+                report.add_post_find_symbol(syminfo.name());
             }
-            return Ok(false);
+            return Some((RelocValue::new(addr as usize), Source::PostFind));
         }
-        Ok(true)
+        // An undefined weak reference that nothing defines resolves to null
+        weak_undef.then(|| (RelocValue::new(0), Source::Unresolved))
     }
 
+    /// Returns whether the symbol of a relocation can be resolved, without
+    /// counting the lookup in the statistics.
     #[inline]
-    pub(crate) fn handle_post(&mut self, hctx: &RelocationContext<'_, D>) -> Result<bool> {
-        let opt = self.post_handler.handle(hctx);
-        if let Some(r) = opt {
-            if let Some(idx) = r? {
-                self.dependency_flags[idx] = true;
+    pub(crate) fn can_resolve(&mut self, core: &ElfCore<D>, r_sym: usize, r_type: u32) -> bool
+    where
+        PreS: SymbolLookup,
+        PostS: SymbolLookup,
+    {
+        self.find_symbol_impl(core, r_sym, r_type).is_some()
+    }
+
+    /// Records the symbol of `rel` as unresolved.
+    ///
+    /// Returns `false` if the unresolved symbols are not collected, or `rel`
+    /// has no symbol, in which case the caller reports the failure itself.
+    #[cold]
+    pub(crate) fn record_missing(&mut self, rel: &ElfRelType, core: &ElfCore<D>) -> bool {
+        let r_sym = rel.r_symbol();
+        let symtab = core.symtab();
+        let Some(missing) = self.missing.as_mut() else {
+            return false;
+        };
+        if r_sym == 0 || r_sym >= symtab.count_syms() {
+            return false;
+        }
+        missing.push(MissingSymbol::new(symtab.symbol_idx(r_sym).1.name(), rel));
+        true
+    }
+
+    /// Describes a failed relocation entry, including the symbol sources
+    /// consulted by `lookup`.
+    #[cold]
+    pub(crate) fn error_context(
+        &self,
+        rel: &ElfRelType,
+        core: &ElfCore<D>,
+        lookup: Lookup,
+    ) -> RelocationErrorContext {
+        let searched = match lookup {
+            Lookup::None => Vec::new(),
+            Lookup::Scope => self
+                .scope
+                .iter()
+                .map(|lib| lib.name().to_string())
+                .collect(),
+            Lookup::All => {
+                let mut searched = searched_sources(self.scope);
+                if core.tls().is_some() {
+                    searched.insert(0, "tls allocator".to_string());
+                }
+                searched
+            }
+        };
+        RelocationErrorContext::new(rel, core).with_searched(searched)
+    }
+
+    /// Runs the pre handler.
+    ///
+    /// Returns `true` if the default processing should run.
+    #[inline]
+    pub(crate) fn handle_pre(&mut self, hctx: &RelocationContext<'_, D>) -> Result<bool> {
+        match self.pre_handler.handle(hctx).transpose()? {
+            Some(Handled::Done(idx)) => {
+                if let Some(idx) = idx {
+                    self.dependency_flags[idx] = true;
+                }
+                Ok(false)
             }
-            return Ok(false);
+            Some(Handled::Retry) | None => Ok(true),
         }
-        Ok(true)
+    }
+
+    /// Runs the post handler.
+    ///
+    /// Returns `None` if the relocation was not handled.
+    #[inline]
+    pub(crate) fn handle_post(
+        &mut self,
+        hctx: &RelocationContext<'_, D>,
+    ) -> Result<Option<Handled>> {
+        let handled = self.post_handler.handle(hctx).transpose()?;
+        if let Some(Handled::Done(Some(idx))) = handled {
+            self.dependency_flags[idx] = true;
+        }
+        Ok(handled)
     }
 }
 
@@ -98,8 +347,25 @@ pub struct Relocator<T, PreS, PostS, LazyS, PreH, PostH, D = ()> {
     post_find: PostS,
     pre_handler: PreH,
     post_handler: PostH,
-    lazy: Option<bool>,
     lazy_scope: Option<LazyS>,
+    options: RelocatorOptions<D>,
+}
+
+/// The options of a [`Relocator`] that keep their type when the lookups or
+/// handlers are replaced
+struct RelocatorOptions<D> {
+    lazy: Option<bool>,
+    executor: Option<Box<dyn ParallelExecutor>>,
+    strict: bool,
+    apply_relro: bool,
+    collect_missing: bool,
+    stats: bool,
+    policy: Option<LookupPolicy>,
+    version_policy: VersionPolicy,
+    scope_index: Option<ScopeIndex<D>>,
+    self_pos: Option<usize>,
+    pre_init: Option<Box<PreInit<D>>>,
+    post_fini: Option<Arc<PostFini>>,
 }
 
 impl<T: Relocatable<D>, D> Relocator<T, (), (), (), (), (), D> {
@@ -112,8 +378,21 @@ impl<T: Relocatable<D>, D> Relocator<T, (), (), (), (), (), D> {
             post_find: (),
             pre_handler: (),
             post_handler: (),
-            lazy: None,
             lazy_scope: None,
+            options: RelocatorOptions {
+                lazy: None,
+                executor: None,
+                strict: false,
+                apply_relro: true,
+                collect_missing: false,
+                stats: false,
+                policy: None,
+                version_policy: VersionPolicy::default(),
+                scope_index: None,
+                self_pos: None,
+                pre_init: None,
+                post_fini: None,
+            },
         }
     }
 }
@@ -142,8 +421,8 @@ where
             post_find: self.post_find,
             pre_handler: self.pre_handler,
             post_handler: self.post_handler,
-            lazy: self.lazy,
             lazy_scope: self.lazy_scope,
+            options: self.options,
         }
     }
 
@@ -159,8 +438,8 @@ where
             post_find: self.post_find,
             pre_handler: self.pre_handler,
             post_handler: self.post_handler,
-            lazy: self.lazy,
             lazy_scope: self.lazy_scope,
+            options: self.options,
         }
     }
 
@@ -179,8 +458,8 @@ where
             post_find,
             pre_handler: self.pre_handler,
             post_handler: self.post_handler,
-            lazy: self.lazy,
             lazy_scope: self.lazy_scope,
+            options: self.options,
         }
     }
 
@@ -199,8 +478,8 @@ where
             post_find,
             pre_handler: self.pre_handler,
             post_handler: self.post_handler,
-            lazy: self.lazy,
             lazy_scope: self.lazy_scope,
+            options: self.options,
         }
     }
 
@@ -234,8 +513,8 @@ where
             post_find: self.post_find,
             pre_handler: handler,
             post_handler: self.post_handler,
-            lazy: self.lazy,
             lazy_scope: self.lazy_scope,
+            options: self.options,
         }
     }
 
@@ -257,8 +536,8 @@ where
             post_find: self.post_find,
             pre_handler: self.pre_handler,
             post_handler: handler,
-            lazy: self.lazy,
             lazy_scope: self.lazy_scope,
+            options: self.options,
         }
     }
 
@@ -268,7 +547,7 @@ where
     /// on-demand when the function is first called, improving startup time.
     /// When disabled, all relocations are resolved immediately.
     pub fn lazy(mut self, lazy: bool) -> Self {
-        self.lazy = Some(lazy);
+        self.options.lazy = Some(lazy);
         self
     }
 
@@ -287,11 +566,221 @@ where
             post_find: self.post_find,
             pre_handler: self.pre_handler,
             post_handler: self.post_handler,
-            lazy: self.lazy,
             lazy_scope: Some(scope),
+            options: self.options,
         }
     }
 
+    /// Sets whether `pre_find` or the scope is searched first, per relocation.
+    ///
+    /// Takes either a preset ([`LookupPolicy::PreferPreFind`],
+    /// [`LookupPolicy::PreferScope`]) or a closure
+    /// `Fn(RelocKind, &str) -> LookupOrder` that is called with the
+    /// [`RelocKind`](crate::relocation::RelocKind) of each relocation and the
+    /// name of its symbol. Without a policy, `pre_find` is searched first.
+    ///
+    /// Lazy binding follows the same policy for its `Func` lookups, with the
+    /// lazy scope in the place of `pre_find` and the dependencies of the module
+    /// found in the scope in the place of the scope.
+    ///
+    /// Relative relocations (`R_*_RELATIVE` and the `RELR` table) have no
+    /// symbol and are not affected. Relocatable objects always search
+    /// `pre_find` first.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, relocation::{LookupOrder, RelocKind}};
+    ///
+    /// let mut loader = Loader::new();
+    /// let lib = loader
+    ///     .load_dylib("liba.so")
+    ///     .unwrap()
+    ///     .relocator()
+    ///     .pre_find_fn(|name| None)
+    ///     .lookup_policy(|kind: RelocKind, _: &str| match kind {
+    ///         RelocKind::Func => LookupOrder::PreFindFirst,
+    ///         _ => LookupOrder::ScopeFirst,
+    ///     })
+    ///     .relocate()
+    ///     .unwrap();
+    /// ```
+    pub fn lookup_policy(mut self, policy: impl Into<LookupPolicy>) -> Self {
+        self.options.policy = Some(policy.into());
+        self
+    }
+
+    /// Applies the relative relocations in parallel on the given executor.
+    ///
+    /// Relative relocations (`R_*_RELATIVE` and the `RELR` table) do not depend
+    /// on symbol lookup, so they are split into chunks and run through
+    /// [`ParallelExecutor::execute`]. All other relocations are still processed
+    /// on the calling thread. Small tables are always relocated serially.
+    pub fn parallel(mut self, executor: impl ParallelExecutor + 'static) -> Self {
+        self.options.executor = Some(Box::new(executor));
+        self
+    }
+
+    /// Enables or disables the audit pass that runs before any relocation is applied.
+    ///
+    /// When enabled, every entry of the relocation tables is checked first: its
+    /// target must lie inside a writable `PT_LOAD` segment, its symbol index must
+    /// be within the dynamic symbol table and its type must be one the default
+    /// processing understands. The first violation is reported as an
+    /// [`Error::Relocation`](crate::Error::Relocation) naming the entry, and nothing
+    /// is written in that case. Types that only a handler knows are rejected as well.
+    ///
+    /// This only affects dynamic images and is disabled by default.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// Enables or disables the RELRO protection applied after relocation.
+    ///
+    /// When disabled, the `PT_GNU_RELRO` segment stays writable so that the
+    /// host can still patch it, and
+    /// [`LoadedCore::apply_relro`](crate::image::LoadedCore::apply_relro) makes
+    /// it read-only later. Lazily bound images are never protected.
+    ///
+    /// Enabled by default.
+    pub fn apply_relro(mut self, apply_relro: bool) -> Self {
+        self.options.apply_relro = apply_relro;
+        self
+    }
+
+    /// Enables or disables the collection of unresolved symbols.
+    ///
+    /// By default relocation stops at the first symbol that cannot be resolved.
+    /// When enabled, the unresolved references are recorded and their slots are
+    /// left untouched, and once every table was processed a single
+    /// [`Error::MissingSymbols`](crate::Error::MissingSymbols) lists all of them.
+    /// `JUMP_SLOT` relocations bound lazily are resolved up front as well,
+    /// without writing the result, so that they cannot abort the process later.
+    ///
+    /// This only affects dynamic images and is disabled by default.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Error, Loader};
+    ///
+    /// let mut loader = Loader::new();
+    /// let lib = loader.load_dylib("liba.so").unwrap();
+    /// if let Err(Error::MissingSymbols { symbols, .. }) =
+    ///     lib.relocator().collect_missing(true).relocate()
+    /// {
+    ///     for symbol in &symbols {
+    ///         println!("undefined: {}", symbol.name());
+    ///     }
+    /// }
+    /// ```
+    pub fn collect_missing(mut self, collect_missing: bool) -> Self {
+        self.options.collect_missing = collect_missing;
+        self
+    }
+
+    /// Sets how versioned symbol references are matched against the scope.
+    ///
+    /// With [`VersionPolicy::Permissive`], a reference to a version that no
+    /// library defines, e.g. one required from `libpthread.so.0` when the scope
+    /// only has a `libc.so.6` that absorbed it, binds to the default version of
+    /// a definition with the same name. [`VersionPolicy::Strict`] fails the
+    /// relocation instead, naming the version and the library it was required
+    /// from.
+    ///
+    /// This only has an effect with the `version` feature and only affects
+    /// dynamic images. Defaults to [`VersionPolicy::Permissive`].
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, relocation::VersionPolicy};
+    ///
+    /// let mut loader = Loader::new();
+    /// let lib = loader
+    ///     .load_dylib("liba.so")
+    ///     .unwrap()
+    ///     .relocator()
+    ///     .version_policy(VersionPolicy::Strict)
+    ///     .relocate()
+    ///     .unwrap();
+    /// ```
+    pub fn version_policy(mut self, version_policy: VersionPolicy) -> Self {
+        self.options.version_policy = version_policy;
+        self
+    }
+
+    /// Sets a precomputed index of the scope for symbol resolution.
+    ///
+    /// With an index built from the same modules as the [`scope`](Self::scope),
+    /// a lookup only probes the modules that may define the symbol instead of
+    /// every module of the scope. Build the index once with
+    /// [`ScopeIndex::build`] and share it between the relocators of every
+    /// module relocated against the scope. An index built from other modules
+    /// is ignored.
+    pub fn scope_index(mut self, index: &ScopeIndex<D>) -> Self {
+        self.options.scope_index = Some(index.clone());
+        self
+    }
+
+    /// Sets a hook called once the module is relocated, before its
+    /// initializers run.
+    ///
+    /// The hook runs after every relocation was applied and the
+    /// `PT_GNU_RELRO` segment was protected, and strictly before `DT_INIT`
+    /// and `DT_INIT_ARRAY`. It can look up the symbols of the module, e.g. to
+    /// seed the state its constructors read. The module passed to the hook
+    /// already holds its dependencies. If the hook fails, the
+    /// initializers do not run, relocation fails with its error and the
+    /// module is unmapped without running its finalizers.
+    ///
+    /// The hook is not called for relocatable objects relocated as a
+    /// [`RawElf`](crate::image::RawElf), whose core has no user data.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Error, Loader};
+    ///
+    /// let mut loader = Loader::new();
+    /// let lib = loader
+    ///     .load_dylib("liba.so")
+    ///     .unwrap()
+    ///     .relocator()
+    ///     .pre_init(|core| {
+    ///         // Hand the configuration to the constructors before they run
+    ///         let config = unsafe { core.get::<()>("config") }.ok_or(Error::Custom {
+    ///             msg: "liba.so has no config".into(),
+    ///         })?;
+    ///         unsafe { config.into_raw().cast::<u32>().cast_mut().write(1) };
+    ///         Ok(())
+    ///     })
+    ///     .relocate()
+    ///     .unwrap();
+    /// ```
+    pub fn pre_init(mut self, pre_init: impl Fn(&LoadedCore<D>) -> Result<()> + 'static) -> Self {
+        self.options.pre_init = Some(Box::new(pre_init));
+        self
+    }
+
+    /// Sets a hook called with the name of the module once its finalizers ran.
+    ///
+    /// The finalizers (`DT_FINI_ARRAY` and `DT_FINI`) run when the last handle
+    /// to the module is dropped or when its group is finalized, and the hook
+    /// runs right after them, while the module is still mapped. It is not
+    /// called for a module whose initializers never ran.
+    pub fn post_fini(mut self, post_fini: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.options.post_fini = Some(Arc::new(post_fini));
+        self
+    }
+
+    /// Enables or disables the collection of [`RelocationStats`].
+    ///
+    /// The statistics are available through [`RelocationReport::stats`] of the
+    /// report returned by [`relocate_with_report`](Self::relocate_with_report).
+    /// [`relocate_with_stats`](Self::relocate_with_stats) always collects them.
+    pub fn with_stats(mut self, stats: bool) -> Self {
+        self.options.stats = stats;
+        self
+    }
+
     /// Executes the relocation process.
     ///
     /// This method consumes the relocator and returns the relocated ELF object.
@@ -310,10 +799,248 @@ where
             &self.post_find,
             self.pre_handler,
             self.post_handler,
-            self.lazy,
             self.lazy_scope,
+            RelocateOptions {
+                lazy: self.options.lazy,
+                strict: self.options.strict,
+                apply_relro: self.options.apply_relro,
+                collect_missing: self.options.collect_missing,
+                policy: self.options.policy,
+                version_policy: self.options.version_policy,
+                scope_index: self.options.scope_index.as_ref(),
+                self_pos: self.options.self_pos,
+                report: None,
+                executor: self.options.executor.as_deref(),
+                pre_init: self.options.pre_init.as_deref(),
+                post_fini: self.options.post_fini,
+            },
         )
     }
+
+    /// Executes the relocation process and collects a [`RelocationReport`].
+    ///
+    /// This behaves like [`relocate`](Self::relocate), but additionally records
+    /// every `COPY` relocation whose symbol size differs from the definition and
+    /// every symbol that could only be resolved through `post_find`.
+    ///
+    /// # Returns
+    /// * `Ok((T::Output, RelocationReport))` - The relocated ELF object and the report.
+    /// * `Err(Error)` - If relocation fails for any reason.
+    pub fn relocate_with_report(self) -> Result<(T::Output, RelocationReport)>
+    where
+        D: 'static,
+    {
+        let mut report = RelocationReport::new(self.options.stats);
+        let output = self.object.relocate(
+            &self.scope,
+            &self.pre_find,
+            &self.post_find,
+            self.pre_handler,
+            self.post_handler,
+            self.lazy_scope,
+            RelocateOptions {
+                lazy: self.options.lazy,
+                strict: self.options.strict,
+                apply_relro: self.options.apply_relro,
+                collect_missing: self.options.collect_missing,
+                policy: self.options.policy,
+                version_policy: self.options.version_policy,
+                scope_index: self.options.scope_index.as_ref(),
+                self_pos: self.options.self_pos,
+                report: Some(&mut report),
+                executor: self.options.executor.as_deref(),
+                pre_init: self.options.pre_init.as_deref(),
+                post_fini: self.options.post_fini,
+            },
+        )?;
+        Ok((output, report))
+    }
+
+    /// Executes the relocation process and collects [`RelocationStats`].
+    ///
+    /// This behaves like [`relocate`](Self::relocate), but additionally counts
+    /// the processed entries per relocation type and where their symbols were
+    /// found. With the `std` feature, the time spent in each phase is measured too.
+    ///
+    /// # Returns
+    /// * `Ok((T::Output, RelocationStats))` - The relocated ELF object and the statistics.
+    /// * `Err(Error)` - If relocation fails for any reason.
+    pub fn relocate_with_stats(mut self) -> Result<(T::Output, RelocationStats)>
+    where
+        D: 'static,
+    {
+        self.options.stats = true;
+        let (output, report) = self.relocate_with_report()?;
+        Ok((output, report.into_stats().unwrap_or_default()))
+    }
+}
+
+/// The relocator returned by [`Relocator::dlopen_compat`]
+type DlopenRelocator<PreS, PostS, PreH, PostH> =
+    Relocator<RawDylib<()>, PreS, PostS, DlopenScope, PreH, PostH, ()>;
+
+impl<PreS, PostS, LazyS, PreH, PostH> Relocator<RawDylib<()>, PreS, PostS, LazyS, PreH, PostH, ()> {
+    /// Makes the library visible to the lazy binding of every other module.
+    ///
+    /// When enabled, the relocated library joins the [`global_scope`](crate::global_scope),
+    /// which lazy binding searches after the lazy scope of a module, like a
+    /// library opened with `RTLD_GLOBAL`. The scope only holds a weak
+    /// reference: the library leaves it when it is dropped.
+    ///
+    /// Disabled by default, which matches `RTLD_LOCAL`.
+    pub fn global(mut self, global: bool) -> Self {
+        self.object.inner.set_global(global);
+        self
+    }
+
+    /// Configures the relocator to bind the library like glibc's `dlopen`.
+    ///
+    /// The [`scope`](Self::scope) must hold the dependencies of the library in
+    /// the order `dlopen` would load them, that is breadth-first from its
+    /// `DT_NEEDED` entries. The preset then:
+    ///
+    /// * searches the [`global_scope`](crate::global_scope), the library itself
+    ///   and its dependencies in this order, or the library and its
+    ///   dependencies first with [`CompatFlags::DEEPBIND`];
+    /// * binds every reference to the first definition found, even a weak one,
+    ///   and an undefined weak reference that nothing defines to null;
+    /// * binds functions on their first call with [`CompatFlags::LAZY`] unless
+    ///   the library was linked with `-z now`, searching the global scope as it
+    ///   is at the time of the call;
+    /// * otherwise fails with [`Error::MissingSymbols`] if any function can't
+    ///   be bound, as with [`collect_missing`](Self::collect_missing);
+    /// * fails on a versioned reference that no module defines, as with
+    ///   [`VersionPolicy::Strict`];
+    /// * adds the library to the global scope with [`CompatFlags::GLOBAL`].
+    ///
+    /// `pre_find` is still searched first, where `dlopen` would search the main
+    /// program, and `post_find` last.
+    ///
+    /// # Errors
+    /// Where the behavior of `dlopen` can't be reproduced, an
+    /// [`Error::Relocation`] is returned instead of binding differently:
+    /// * the flags do not contain exactly one of `LAZY` and `NOW`, or contain
+    ///   both `LOCAL` and `GLOBAL`;
+    /// * a [`lookup_policy`](Self::lookup_policy) was set, which reorders the
+    ///   search;
+    /// * `LAZY` is requested for a library whose PLT relocations had to be
+    ///   converted from another format and are always bound immediately;
+    /// * `GLOBAL` is requested while a dependency is not global: `dlopen` would
+    ///   make it global as well, which only its own relocator can do.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use elf_loader::{Loader, relocation::CompatFlags};
+    ///
+    /// let mut loader = Loader::new();
+    /// let libb = loader
+    ///     .load_dylib("libb.so")
+    ///     .unwrap()
+    ///     .relocator()
+    ///     .dlopen_compat(CompatFlags::NOW | CompatFlags::LOCAL)
+    ///     .unwrap()
+    ///     .relocate()
+    ///     .unwrap();
+    /// let liba = loader
+    ///     .load_dylib("liba.so")
+    ///     .unwrap()
+    ///     .relocator()
+    ///     .scope([&libb])
+    ///     .dlopen_compat(CompatFlags::LAZY | CompatFlags::DEEPBIND)
+    ///     .unwrap()
+    ///     .relocate()
+    ///     .unwrap();
+    /// ```
+    pub fn dlopen_compat(
+        self,
+        flags: CompatFlags,
+    ) -> Result<DlopenRelocator<PreS, PostS, PreH, PostH>> {
+        let name = self.object.inner.name();
+        let lazy = flags.contains(CompatFlags::LAZY);
+        if lazy == flags.contains(CompatFlags::NOW) {
+            return Err(relocate_error(format!(
+                "{name}: exactly one of LAZY and NOW must be set"
+            )));
+        }
+        let global = flags.contains(CompatFlags::GLOBAL);
+        if global && flags.contains(CompatFlags::LOCAL) {
+            return Err(relocate_error(format!(
+                "{name}: LOCAL and GLOBAL can't be set together"
+            )));
+        }
+        if self.options.policy.is_some() {
+            return Err(relocate_error(format!(
+                "{name}: a lookup policy can't be combined with dlopen_compat"
+            )));
+        }
+        let lazy = lazy && self.object.is_lazy();
+        if lazy && !self.object.inner.relocation().supports_lazy() {
+            return Err(relocate_error(format!(
+                "{name}: PLT relocations converted from another format can't be bound lazily"
+            )));
+        }
+        let globals = global_scope();
+        if global
+            && let Some(dep) = self.scope.iter().find(|dep| {
+                !globals
+                    .iter()
+                    .any(|lib| Arc::ptr_eq(&lib.core.inner, &dep.core.inner))
+            })
+        {
+            return Err(relocate_error(format!(
+                "{name}: dependency {} is not global, relocate it with GLOBAL first",
+                dep.name()
+            )));
+        }
+
+        let deepbind = flags.contains(CompatFlags::DEEPBIND);
+        let local = core::iter::once(self.object.core_ref().downgrade())
+            .chain(self.scope.iter().map(|dep| dep.core.downgrade()))
+            .collect();
+        let (scope, self_pos) = if deepbind {
+            let mut scope = self.scope;
+            scope.extend(globals);
+            (scope, 0)
+        } else {
+            let self_pos = globals.len();
+            let mut scope = globals;
+            scope.extend(self.scope);
+            (scope, self_pos)
+        };
+        let mut object = self.object;
+        object.inner.set_global(global);
+        Ok(Relocator {
+            object,
+            scope,
+            pre_find: self.pre_find,
+            post_find: self.post_find,
+            pre_handler: self.pre_handler,
+            post_handler: self.post_handler,
+            lazy_scope: Some(DlopenScope::new(local, deepbind)),
+            options: RelocatorOptions {
+                lazy: Some(lazy),
+                collect_missing: !lazy,
+                policy: None,
+                version_policy: VersionPolicy::Strict,
+                self_pos: Some(self_pos),
+                ..self.options
+            },
+        })
+    }
+}
+
+impl<PreS, PostS, LazyS, PreH, PostH> Relocator<RawObject, PreS, PostS, LazyS, PreH, PostH, ()> {
+    /// Decides how each global symbol of the object is exposed once relocated.
+    ///
+    /// The closure is called with the name of every defined global or weak symbol.
+    /// Symbols made [`Visibility::Local`] or renamed with [`Visibility::Rename`] are
+    /// not returned by [`LoadedCore::get`] under their original name and are not
+    /// used when other modules resolve against this object. References of the
+    /// object itself still resolve to its own definitions.
+    pub fn visibility(mut self, visibility: impl Fn(&str) -> Visibility + 'static) -> Self {
+        self.object.set_visibility(visibility);
+        self
+    }
 }
 
 /// A wrapper type for relocation values, providing type safety and arithmetic operations.
@@ -427,13 +1154,25 @@ impl<'temp, D> SymDef<'temp, D> {
             let base = self.lib.base();
             let sym = unsafe { self.sym.unwrap_unchecked() };
             let addr = base + sym.st_value();
-            if likely(sym.st_type() != STT_GNU_IFUNC) {
-                addr as _
+            let addr = if likely(sym.st_type() != STT_GNU_IFUNC) {
+                addr
             } else {
                 // IFUNC会在运行时确定地址，这里使用的是ifunc的返回值
-                let ifunc: fn() -> usize = unsafe { core::mem::transmute(addr) };
-                ifunc() as _
-            }
+                unsafe { call_ifunc(addr) }
+            };
+            // A resolver built with pointer authentication may return a signed pointer
+            #[cfg(feature = "aarch64-pac")]
+            let addr = if self
+                .lib
+                .inner
+                .pac
+                .load(core::sync::atomic::Ordering::Relaxed)
+            {
+                crate::arch::strip_pac(addr)
+            } else {
+                addr
+            };
+            addr as _
         } else {
             // 未定义的弱符号返回null
             null()
@@ -441,35 +1180,39 @@ impl<'temp, D> SymDef<'temp, D> {
     }
 }
 
-/// Creates a detailed relocation error message.
+/// Calls an IFUNC resolver and returns the address it selects.
+///
+/// The resolver is called with the C calling convention and no arguments.
 ///
-/// Formats an error with relocation type, symbol name (if any), and module information.
+/// # Safety
+/// `resolver` must be the address of a valid IFUNC resolver.
+#[inline]
+pub(crate) unsafe fn call_ifunc(resolver: usize) -> usize {
+    let ifunc: extern "C" fn() -> usize = unsafe { core::mem::transmute(resolver) };
+    ifunc()
+}
+
+/// Creates a detailed relocation error.
+///
+/// The context describes the failing entry: the module, relocation type,
+/// symbol name (if any), target address and the symbol sources searched.
 #[cold]
-pub(crate) fn reloc_error<D, E: core::fmt::Display>(
-    rel: &ElfRelType,
-    err: E,
-    lib: &ElfCore<D>,
-) -> Error {
-    let r_type_str = rel.r_type_str();
-    let r_sym = rel.r_symbol();
-    if r_sym == 0 {
-        relocate_error(format!(
-            "file: {}, relocation type: {}, no symbol, error: {}",
-            lib.name(),
-            r_type_str,
-            err
-        ))
-    } else {
-        relocate_error(format!(
-            "file: {}, relocation type: {}, symbol name: {}, error: {}",
-            lib.name(),
-            r_type_str,
-            lib.symtab().symbol_idx(r_sym).1.name(),
-            err
-        ))
+pub(crate) fn reloc_error<E: core::fmt::Display>(context: RelocationErrorContext, err: E) -> Error {
+    Error::Relocation {
+        msg: err.to_string().into(),
+        context: Some(Box::new(context)),
     }
 }
 
+/// Names of the symbol sources searched by [`find_symbol_addr`], in order.
+pub(crate) fn searched_sources<D>(scope: &[LoadedCore<D>]) -> Vec<String> {
+    let mut searched = Vec::with_capacity(scope.len() + 2);
+    searched.push("pre_find".to_string());
+    searched.extend(scope.iter().map(|lib| lib.name().to_string()));
+    searched.push("post_find".to_string());
+    searched
+}
+
 fn find_weak<'lib, D>(lib: &'lib ElfCore<D>, dynsym: &'lib ElfSymbol) -> Option<SymDef<'lib, D>> {
     // 弱符号 + WEAK 用 0 填充rela offset
     if dynsym.is_weak() && dynsym.is_undef() {
@@ -487,14 +1230,15 @@ fn find_weak<'lib, D>(lib: &'lib ElfCore<D>, dynsym: &'lib ElfSymbol) -> Option<
 
 /// Finds the address of a symbol using the configured lookup strategies.
 ///
-/// Searches in order: pre_find, scope, post_find.
+/// Searches in order: the object itself if it is symbolic, pre_find, scope, post_find.
 /// Returns the resolved address and optionally the library index used.
+#[cfg(target_arch = "x86_64")]
 #[inline]
 pub(crate) fn find_symbol_addr<PreS, PostS, D>(
     pre_find: &PreS,
     post_find: &PostS,
     core: &ElfCore<D>,
-    symtab: &SymbolTable,
+    symtab: &crate::elf::SymbolTable,
     scope: &[LoadedCore<D>],
     r_sym: usize,
 ) -> Option<(RelocValue<usize>, Option<usize>)>
@@ -503,33 +1247,131 @@ where
     PostS: SymbolLookup + ?Sized,
 {
     let (dynsym, syminfo) = symtab.symbol_idx(r_sym);
+    if let Some(symdef) = find_symbolic(core, &syminfo) {
+        return Some((RelocValue::new(symdef.convert() as usize), None));
+    }
     if let Some(addr) = pre_find.lookup(syminfo.name()) {
-        #[cfg(feature = "log")]
-        log::trace!(
-            "binding file [{}] to [pre_find]: symbol [{}]",
-            core.name(),
-            syminfo.name()
-        );
+        #[cfg(feature = "trace")]
+        crate::trace::emit(TraceEvent::SymbolBind {
+            lib: core.name(),
+            symbol: syminfo.name(),
+            provider: BindProvider::PreFind,
+        });
         return Some((RelocValue::new(addr as usize), None));
     }
-    if let Some(res) = find_symdef_impl(core, scope, dynsym, &syminfo) {
-        return Some((RelocValue::new(res.0.convert() as usize), res.1));
-    }
+    let weak_undef = match find_symdef_impl(core, scope, None, None, dynsym, &syminfo) {
+        Some((symdef, _)) if symdef.sym.is_none() => true,
+        Some((symdef, idx)) => return Some((RelocValue::new(symdef.convert() as usize), idx)),
+        None => false,
+    };
     if let Some(addr) = post_find.lookup(syminfo.name()) {
-        #[cfg(feature = "log")]
-        log::trace!(
-            "binding file [{}] to [post_find]: symbol [{}]",
-            core.name(),
-            syminfo.name()
-        );
+        #[cfg(feature = "trace")]
+        crate::trace::emit(TraceEvent::SymbolBind {
+            lib: core.name(),
+            symbol: syminfo.name(),
+            provider: BindProvider::PostFind,
+        });
         return Some((RelocValue::new(addr as usize), None));
     }
+    // An undefined weak reference that nothing defines resolves to null
+    weak_undef.then(|| (RelocValue::new(0), None))
+}
+
+/// Finds the main program in `scope`. The copies it made of variables with
+/// `R_*_COPY` take precedence over every other definition.
+#[inline]
+pub(crate) fn main_program_pos<D>(scope: &[LoadedCore<D>]) -> Option<usize> {
+    scope.iter().position(|lib| lib.is_main_program())
+}
+
+/// Finds the definition of a symbol in `core` itself if it was linked with `-Bsymbolic`.
+#[inline]
+fn find_symbolic<'lib, D>(core: &'lib ElfCore<D>, syminfo: &SymbolInfo) -> Option<SymDef<'lib, D>> {
+    if likely(!core.is_symbolic()) {
+        return None;
+    }
+    let sym = core
+        .symtab()
+        .lookup_filter(syminfo, &mut syminfo.precompute())?;
+    #[cfg(feature = "trace")]
+    crate::trace::emit(TraceEvent::SymbolBind {
+        lib: core.name(),
+        symbol: syminfo.name(),
+        provider: BindProvider::Symbolic,
+    });
+    Some(SymDef {
+        sym: Some(sym),
+        lib: core,
+    })
+}
+
+/// Looks up a definition in `lib` that `core` can bind to.
+///
+/// `STV_HIDDEN` and `STV_INTERNAL` definitions are only visible to the module
+/// that defines them.
+#[inline]
+fn lookup_visible<'lib, D>(
+    core: &ElfCore<D>,
+    lib: &'lib ElfCore<D>,
+    syminfo: &SymbolInfo,
+    precompute: &mut PreCompute,
+) -> Option<&'lib ElfSymbol> {
+    if Arc::as_ptr(&lib.inner) == Arc::as_ptr(&core.inner) {
+        lib.symtab().lookup_filter(syminfo, precompute)
+    } else {
+        lib.symtab().lookup_exported(syminfo, precompute)
+    }
+}
+
+/// Finds the first definition of a symbol in `scope` with `core` searched at
+/// `self_pos`, the way `dlopen` resolves, whether the definition is weak or not.
+fn find_first<'lib, D>(
+    core: &'lib ElfCore<D>,
+    scope: &'lib [LoadedCore<D>],
+    self_pos: usize,
+    syminfo: &SymbolInfo,
+) -> Option<(SymDef<'lib, D>, Option<usize>)> {
+    let mut precompute = syminfo.precompute();
+    let self_pos = self_pos.min(scope.len());
+    let (before, after) = scope.split_at(self_pos);
+    let modules = before
+        .iter()
+        .enumerate()
+        .map(|(i, lib)| (&lib.core, Some(i)))
+        .chain(core::iter::once((core, None)))
+        .chain(
+            after
+                .iter()
+                .enumerate()
+                .map(|(i, lib)| (&lib.core, Some(self_pos + i))),
+        );
+    for (lib, idx) in modules {
+        let Some(sym) = lookup_visible(core, lib, syminfo, &mut precompute) else {
+            continue;
+        };
+        #[cfg(feature = "trace")]
+        crate::trace::emit(TraceEvent::SymbolBind {
+            lib: core.name(),
+            symbol: syminfo.name(),
+            provider: BindProvider::Module(lib.name()),
+        });
+        let same = Arc::as_ptr(&lib.inner) == Arc::as_ptr(&core.inner);
+        return Some((
+            SymDef {
+                sym: Some(sym),
+                lib,
+            },
+            if same { None } else { idx },
+        ));
+    }
     None
 }
 
 pub(crate) fn find_symdef_impl<'lib, D>(
     core: &'lib ElfCore<D>,
     scope: &'lib [LoadedCore<D>],
+    scope_index: Option<&ScopeIndex<D>>,
+    self_pos: Option<usize>,
     sym: &'lib ElfSymbol,
     syminfo: &SymbolInfo,
 ) -> Option<(SymDef<'lib, D>, Option<usize>)> {
@@ -541,33 +1383,55 @@ pub(crate) fn find_symdef_impl<'lib, D>(
             },
             None,
         ))
+    } else if let Some(symdef) = find_symbolic(core, syminfo) {
+        Some((symdef, None))
+    } else if let Some(self_pos) = self_pos {
+        find_first(core, scope, self_pos, syminfo)
+            .or_else(|| find_weak(core, sym).map(|s| (s, None)))
     } else {
         let mut precompute = syminfo.precompute();
-        scope
-            .iter()
-            .enumerate()
-            .find_map(|(i, lib)| {
-                lib.symtab()
-                    .lookup_filter(syminfo, &mut precompute)
-                    .map(|sym| {
-                        #[cfg(feature = "log")]
-                        log::trace!(
-                            "binding file [{}] to [{}]: symbol [{}]",
-                            core.name(),
-                            lib.name(),
-                            syminfo.name()
-                        );
-                        // 如果找到的库和当前 core 指向同一个 ELF（同一 allocation），
-                        // 不返回库索引，避免增加引用或产生生命周期循环导致内存泄漏。
-                        let same = Arc::as_ptr(&lib.core.inner) == Arc::as_ptr(&core.inner);
-                        (
-                            SymDef {
-                                sym: Some(sym),
-                                lib: &lib.core,
-                            },
-                            if same { None } else { Some(i) },
-                        )
-                    })
+        // A weak definition is only used if no strong one follows it in the scope
+        let mut found = None;
+        // The index narrows the search to the modules that may define the name
+        let (indexed, all) = match scope_index {
+            Some(index) => (Some(index.candidates(precompute.gnuhash())), None),
+            None => (None, Some(0..scope.len())),
+        };
+        for i in indexed
+            .into_iter()
+            .flatten()
+            .chain(all.into_iter().flatten())
+        {
+            let lib = &scope[i];
+            if let Some(sym) = lookup_visible(core, &lib.core, syminfo, &mut precompute) {
+                let weak = sym.is_weak();
+                if found.is_none() || !weak {
+                    found = Some((i, sym));
+                }
+                if !weak {
+                    break;
+                }
+            }
+        }
+        found
+            .map(|(i, sym)| {
+                let lib = &scope[i];
+                #[cfg(feature = "trace")]
+                crate::trace::emit(TraceEvent::SymbolBind {
+                    lib: core.name(),
+                    symbol: syminfo.name(),
+                    provider: BindProvider::Module(lib.name()),
+                });
+                // 如果找到的库和当前 core 指向同一个 ELF（同一 allocation），
+                // 不返回库索引，避免增加引用或产生生命周期循环导致内存泄漏。
+                let same = Arc::as_ptr(&lib.core.inner) == Arc::as_ptr(&core.inner);
+                (
+                    SymDef {
+                        sym: Some(sym),
+                        lib: &lib.core,
+                    },
+                    if same { None } else { Some(i) },
+                )
             })
             .or_else(|| find_weak(core, sym).map(|s| (s, None)))
     }
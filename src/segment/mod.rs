@@ -6,11 +6,28 @@
 
 use crate::input::ElfReader;
 use crate::os::{MapFlags, Mmap, ProtFlags};
-use crate::{Result, elf::Phdr, relocation::RelocValue};
+#[cfg(feature = "trace")]
+use crate::trace::TraceEvent;
+use crate::{
+    Error, Result,
+    elf::{ElfPhdr, Phdr},
+    relocation::RelocValue,
+};
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::ffi::c_void;
 use core::fmt::Debug;
+use core::ops::Range;
 use core::ptr::NonNull;
+use core::sync::atomic::{
+    AtomicBool, AtomicUsize,
+    Ordering::{Relaxed, Release},
+};
+use elf::abi::{PF_W, PT_LOAD};
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+use spin::RwLock;
 
 pub(crate) mod program;
 pub(crate) mod section;
@@ -18,6 +35,53 @@ pub(crate) mod section;
 /// Standard page size used for memory mapping operations
 pub const PAGE_SIZE: usize = 0x1000;
 
+/// Handler called when the memory of a module cannot be unmapped while it is
+/// dropped.
+///
+/// It receives the start address and the length of the range, and the error
+/// returned by [`Mmap::munmap`].
+pub type UnmapErrorHandler = fn(usize, usize, Error);
+
+static UNMAP_ERROR_HANDLER: RwLock<Option<UnmapErrorHandler>> = RwLock::new(None);
+
+/// Installs the process-wide handler for unmapping failures during drop.
+///
+/// Dropping a module never panics: a failing [`Mmap::munmap`] is reported as
+/// a [`TraceEvent::MunmapFailed`](crate::trace::TraceEvent::MunmapFailed)
+/// event when the `trace` feature is enabled, then passed to `handler`, and
+/// the range is leaked. Use [`LoadedCore::try_unload_segments`](crate::image::LoadedCore::try_unload_segments)
+/// to observe the error directly instead.
+///
+/// # Arguments
+/// * `handler` - The new handler, or `None` to remove the current one.
+///
+/// # Returns
+/// The previously installed handler.
+pub fn set_unmap_error_handler(handler: Option<UnmapErrorHandler>) -> Option<UnmapErrorHandler> {
+    core::mem::replace(&mut *UNMAP_ERROR_HANDLER.write(), handler)
+}
+
+/// Reports a failed unmapping to the trace function and the handler
+#[cold]
+fn unmap_failed(addr: usize, len: usize, err: Error) {
+    #[cfg(feature = "trace")]
+    crate::trace::emit(TraceEvent::MunmapFailed {
+        addr,
+        len,
+        error: &err,
+    });
+    let handler = *UNMAP_ERROR_HANDLER.read();
+    if let Some(handler) = handler {
+        handler(addr, len, err);
+    }
+}
+
+/// Largest run of file ranges that is copied by a single read
+///
+/// Bigger copies no longer save calls worth mentioning, and `memcpy` switches to
+/// non-temporal stores for them, which is slower on freshly mapped pages.
+const MAX_COPY_RUN: usize = 64 * 1024;
+
 /// Mask used to align addresses to page boundaries
 pub const MASK: usize = !(PAGE_SIZE - 1);
 
@@ -77,6 +141,87 @@ struct FileMapInfo {
     offset: usize,
 }
 
+/// A temporary read-only mapping of the file range holding the contents of
+/// a segment
+///
+/// The mapping is removed when the view is dropped.
+struct FileView {
+    /// Pointer to the mapped memory
+    memory: NonNull<c_void>,
+    /// File offset of the first mapped byte
+    offset: usize,
+    /// Length of the mapped memory
+    len: usize,
+    /// Function pointer to the munmap function
+    munmap: unsafe fn(NonNull<c_void>, usize) -> Result<()>,
+}
+
+impl FileView {
+    /// Map the file range covering all of `map_info`
+    ///
+    /// # Arguments
+    /// * `map_info` - The file ranges to cover, at least one
+    /// * `fd` - The file descriptor of the object
+    /// * `file_len` - The size of the file, if it is known
+    ///
+    /// # Returns
+    /// * `Ok(Some(view))` - The mapping of the range
+    /// * `Ok(None)` - If the range extends past the end of the file, where
+    ///   reading the mapping would fault, or `M` cannot map files
+    /// * `Err(Error)` - If mapping fails
+    fn new<M: Mmap>(
+        map_info: &[FileMapInfo],
+        fd: isize,
+        file_len: Option<usize>,
+    ) -> Result<Option<FileView>> {
+        let start = map_info.iter().map(|info| info.offset).min().unwrap();
+        let end = map_info
+            .iter()
+            .map(|info| info.offset + info.filesz)
+            .max()
+            .unwrap();
+        if file_len.is_some_and(|file_len| end > file_len) {
+            return Ok(None);
+        }
+        let offset = rounddown(start, PAGE_SIZE);
+        let len = roundup(end - offset, PAGE_SIZE);
+        let memory = unsafe { M::mmap_reserve(None, len, true) }?;
+        let view = FileView {
+            memory,
+            offset,
+            len,
+            munmap: M::munmap,
+        };
+        let mut need_copy = false;
+        unsafe {
+            M::mmap(
+                Some(memory.as_ptr() as usize),
+                len,
+                ProtFlags::PROT_READ,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED,
+                offset,
+                Some(fd),
+                &mut need_copy,
+            )
+        }?;
+        Ok((!need_copy).then_some(view))
+    }
+
+    /// Get a pointer to the mapped byte at file offset `offset`
+    #[inline]
+    fn get(&self, offset: usize) -> *const u8 {
+        unsafe { self.memory.as_ptr().cast::<u8>().add(offset - self.offset) }
+    }
+}
+
+impl Drop for FileView {
+    fn drop(&mut self) {
+        if let Err(err) = unsafe { (self.munmap)(self.memory, self.len) } {
+            unmap_failed(self.memory.as_ptr() as usize, self.len, err);
+        }
+    }
+}
+
 /// An ELF segment in memory
 ///
 /// This structure represents a loaded ELF segment with all the
@@ -101,6 +246,8 @@ pub(crate) struct ElfSegment {
     need_copy: bool,
     /// Indicates if this segment comes from a relocatable object
     from_relocatable: bool,
+    /// Indicates if `prot` was replaced after the segment was mapped
+    prot_overridden: bool,
 }
 
 impl ElfSegment {
@@ -120,6 +267,12 @@ impl ElfSegment {
     /// This method maps the segment into memory using the appropriate
     /// memory mapping operations based on the segment's properties.
     ///
+    /// A segment with a single page-aligned file range is mapped privately from
+    /// the file when the reader has a file descriptor, whether it is writable or
+    /// not, so that its pages are shared with the page cache until written. Only
+    /// the pages holding file contents are mapped from the file; the zero-filled
+    /// pages beyond them are mapped by [`ElfSegment::fill_zero`].
+    ///
     /// # Arguments
     /// * `object` - The ELF object to map data from
     ///
@@ -141,59 +294,117 @@ impl ElfSegment {
         debug_assert!(len % PAGE_SIZE == 0);
 
         // Map the segment based on file mapping information
-        if self.map_info.len() == 1 {
-            debug_assert!(self.map_info[0].offset % PAGE_SIZE == 0);
+        if let [info] = self.map_info.as_slice()
+            && info.offset % PAGE_SIZE == 0
+            && let Some(fd) = object.as_fd()
+        {
+            // The zero-filled pages are mapped anonymously afterwards
+            let file_len = roundup(self.content_size, PAGE_SIZE).min(len);
+            if file_len != 0 {
+                unsafe {
+                    M::mmap(
+                        Some(addr),
+                        file_len,
+                        prot,
+                        self.flags,
+                        info.offset,
+                        Some(fd),
+                        &mut need_copy,
+                    )
+                }?;
+            }
+        } else if self.map_info.len() == 1 && object.as_fd().is_some() {
+            // The space was reserved without access for the file mapping, so
+            // back the segment with writable memory and copy its contents
             unsafe {
-                M::mmap(
-                    Some(addr),
+                M::mmap_anonymous(
+                    addr,
                     len,
-                    prot,
+                    ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
                     self.flags,
-                    self.map_info[0].offset,
-                    object.as_fd(),
-                    &mut need_copy,
                 )
-            }?
+            }?;
+            need_copy = true;
         } else {
-            unsafe { M::mmap(Some(addr), len, prot, self.flags, 0, None, &mut need_copy) }?
-        };
+            unsafe { M::mmap(Some(addr), len, prot, self.flags, 0, None, &mut need_copy) }?;
+        }
 
-        #[cfg(feature = "log")]
-        log::trace!(
-            "[Mmap] address: 0x{:x}, length: {}, flags: {:?}, zero_size: {}, map_info: {:?}",
-            addr,
-            len,
-            prot,
-            self.zero_size,
-            self.map_info
-        );
+        #[cfg(feature = "trace")]
+        crate::trace::emit(TraceEvent::Mmap { addr, len, prot });
 
         self.need_copy = need_copy;
         Ok(())
     }
 
+    /// Merge the file ranges that follow each other both in the file and in
+    /// the segment
+    fn coalesce_map_info(&mut self) {
+        self.map_info.dedup_by(|next, info| {
+            let contiguous =
+                next.start == info.start + info.filesz && next.offset == info.offset + info.filesz;
+            if contiguous {
+                info.filesz += next.filesz;
+            }
+            contiguous
+        });
+    }
+
     /// Copy data into the mapped segment
     ///
     /// This method copies data from the ELF object into the mapped
-    /// memory segment when manual copying is required.
+    /// memory segment when manual copying is required. Ranges that are laid
+    /// out the same way in the file and in the segment, such as consecutive
+    /// sections of a relocatable object, are copied with a single read.
+    ///
+    /// Scattered ranges of a reader with a file descriptor are copied out of a
+    /// temporary mapping of the file instead, so that no read is issued at all.
     ///
     /// # Arguments
     /// * `object` - The ELF object to copy data from
     ///
     /// # Returns
-    /// * `Ok(())` - If copying succeeds
+    /// * `Ok(reads)` - The number of reads issued to `object`
     /// * `Err(Error)` - If copying fails
-    fn copy_data(&self, object: &mut impl ElfReader) -> Result<()> {
-        if self.need_copy {
-            let ptr = self.addr.absolute_addr() as *mut u8;
-            for info in self.map_info.iter() {
+    fn copy_data<M: Mmap>(&self, object: &mut impl ElfReader) -> Result<usize> {
+        if !self.need_copy {
+            return Ok(0);
+        }
+        let ptr = self.addr.absolute_addr() as *mut u8;
+        // Readers that are already in memory copy as fast as the mapping would
+        if self.map_info.len() > 1
+            && object.as_bytes().is_none()
+            && let Some(fd) = object.as_fd()
+            && let Some(view) = FileView::new::<M>(&self.map_info, fd, object.len())?
+        {
+            for info in &self.map_info {
                 unsafe {
-                    let dest = core::slice::from_raw_parts_mut(ptr.add(info.start), info.filesz);
-                    object.read(dest, info.offset)?;
-                }
+                    ptr.add(info.start)
+                        .copy_from_nonoverlapping(view.get(info.offset), info.filesz)
+                };
             }
+            return Ok(0);
         }
-        Ok(())
+
+        let mut reads = 0;
+        let mut infos = self.map_info.iter().peekable();
+        while let Some(info) = infos.next() {
+            let mut end = info.start + info.filesz;
+            // The padding between the ranges is copied along, it belongs to no section
+            while let Some(next) = infos.next_if(|next| {
+                next.start >= end
+                    && next.start - end < PAGE_SIZE
+                    && next.start + next.filesz - info.start <= MAX_COPY_RUN
+                    && next.offset.checked_sub(info.offset) == Some(next.start - info.start)
+            }) {
+                end = next.start + next.filesz;
+            }
+            unsafe {
+                let dest = core::slice::from_raw_parts_mut(ptr.add(info.start), end - info.start);
+                object.read(dest, info.offset)?;
+            }
+            reads += 1;
+        }
+        Ok(reads)
     }
 
     /// Change memory protection of the segment
@@ -206,23 +417,30 @@ impl ElfSegment {
     /// * `Ok(())` - If protection change succeeds
     /// * `Err(Error)` - If protection change fails
     fn mprotect<M: Mmap>(&self) -> Result<()> {
-        if self.need_copy || self.from_relocatable {
+        if self.need_copy || self.from_relocatable || self.prot_overridden {
             let len = self.len;
             debug_assert!(len % PAGE_SIZE == 0);
             let addr = self.addr.absolute_addr();
             unsafe { M::mprotect(NonNull::new(addr as _).unwrap(), len, self.prot) }?;
 
-            #[cfg(feature = "log")]
-            log::trace!(
-                "[Mprotect] address: 0x{:x}, length: {}, prot: {:?}",
+            #[cfg(feature = "trace")]
+            crate::trace::emit(TraceEvent::Mprotect {
                 addr,
                 len,
-                self.prot,
-            );
+                prot: self.prot,
+            });
         }
         Ok(())
     }
 
+    /// Replace the memory protection of the segment
+    ///
+    /// The new flags are applied by the next call to [`ElfSegment::mprotect`].
+    fn override_prot(&mut self, prot: ProtFlags) {
+        self.prot = prot;
+        self.prot_overridden = true;
+    }
+
     /// Fill zero-initialized areas of the segment
     ///
     /// This method fills any zero-initialized areas of the segment
@@ -278,10 +496,21 @@ impl ElfSegment {
 pub(crate) trait SegmentBuilder {
     /// Create the address space for the segments
     ///
+    /// Implementations must check the reservation against `budget` before
+    /// mapping anything.
+    ///
+    /// # Arguments
+    /// * `extra` - Address space to reserve around the segments
+    /// * `budget` - The limit of the loader
+    ///
     /// # Returns
     /// * `Ok(ElfSegments)` - The created segment space
     /// * `Err(Error)` - If creation fails
-    fn create_space<M: Mmap>(&mut self) -> Result<ElfSegments>;
+    fn create_space<M: Mmap>(
+        &mut self,
+        extra: ExtraSpace,
+        budget: &MapBudget,
+    ) -> Result<ElfSegments>;
 
     /// Create the individual segments
     ///
@@ -310,16 +539,35 @@ pub(crate) trait SegmentBuilder {
     ///
     /// # Arguments
     /// * `object` - The ELF object to load segments from
+    /// * `extra` - Address space to reserve around the segments
+    /// * `budget` - The limit and the counter the reservation is charged to
     ///
     /// # Returns
     /// * `Ok(ElfSegments)` - The loaded segments
     /// * `Err(Error)` - If loading fails
-    fn load_segments<M: Mmap>(&mut self, object: &mut impl ElfReader) -> Result<ElfSegments> {
+    fn load_segments<M: Mmap>(
+        &mut self,
+        object: &mut impl ElfReader,
+        extra: ExtraSpace,
+        budget: &MapBudget,
+    ) -> Result<ElfSegments> {
         // Create the address space for segments
-        let space = self.create_space::<M>()?;
+        let mut space = self.create_space::<M>(extra, budget)?;
+        budget.charge(&mut space);
+        space.seal_tail()?;
+        space.seal_guards::<M>()?;
         self.create_segments()?;
+        let ranges: Vec<_> = self
+            .segments()
+            .iter()
+            .flat_map(|segment| segment.map_info.iter())
+            .filter(|info| info.filesz != 0)
+            .map(|info| info.offset..info.offset + info.filesz)
+            .collect();
+        object.read_hint(&ranges)?;
         let segments = self.segments_mut();
         let base = space.base();
+        let mut reads = 0;
 
         // Process each segment
         for segment in segments.iter_mut() {
@@ -332,12 +580,33 @@ pub(crate) trait SegmentBuilder {
             //     }
             // }
             segment.mmap_segment::<M>(object)?;
-            segment.copy_data(object)?;
+            reads += segment.copy_data::<M>(object)?;
             segment.fill_zero::<M>()?;
         }
+        space.reads = reads;
         Ok(space)
     }
 
+    /// Replace the memory protection of a segment
+    ///
+    /// # Arguments
+    /// * `idx` - The index of the segment
+    /// * `prot` - The protection to apply instead of the one from the object
+    fn override_prot(&mut self, idx: usize, prot: ProtFlags) {
+        self.segments_mut()[idx].override_prot(prot);
+    }
+
+    /// Add [`ProtFlags::PROT_BTI`] to the protection of the executable segments
+    ///
+    /// The new flags are applied by the next call to [`SegmentBuilder::mprotect`].
+    fn guard_branches(&mut self) {
+        for segment in self.segments_mut() {
+            if segment.prot.contains(ProtFlags::PROT_EXEC) {
+                segment.override_prot(segment.prot | ProtFlags::PROT_BTI);
+            }
+        }
+    }
+
     /// Change memory protection of all segments
     ///
     /// This method adjusts the memory protection of all segments
@@ -361,6 +630,7 @@ pub(crate) trait SegmentBuilder {
 /// which is used to make certain segments read-only after
 /// relocation to improve security.
 #[allow(unused)]
+#[derive(Clone)]
 pub(crate) struct ELFRelro {
     /// Virtual address of the RELRO segment
     addr: usize,
@@ -388,6 +658,78 @@ impl ELFRelro {
     }
 }
 
+/// The non-writable segments of an object with text relocations
+///
+/// The relocations of such an object may patch any of its segments, so the
+/// segments are made writable while the relocations are applied.
+pub(crate) struct TextSegments {
+    /// Start, length and protection of each segment, page-aligned
+    segments: Vec<(usize, usize, ProtFlags)>,
+    /// Function pointer to the mprotect function
+    mprotect: unsafe fn(NonNull<c_void>, usize, ProtFlags) -> Result<()>,
+    /// Function pointer to the icache flush function
+    flush_icache: fn(NonNull<c_void>, usize),
+}
+
+impl TextSegments {
+    /// Create the description of the given segments
+    pub(crate) fn new<M: Mmap>(segments: Vec<(usize, usize, ProtFlags)>) -> TextSegments {
+        TextSegments {
+            segments,
+            mprotect: M::mprotect,
+            flush_icache: M::flush_icache,
+        }
+    }
+
+    /// Collect the non-writable `PT_LOAD` segments from the program headers
+    ///
+    /// # Arguments
+    /// * `phdrs` - The program headers of the object
+    /// * `base` - The base address to which the object is loaded
+    pub(crate) fn from_phdrs<M: Mmap>(phdrs: &[ElfPhdr], base: usize) -> TextSegments {
+        let segments = phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD && phdr.p_flags & PF_W == 0)
+            .map(|phdr| {
+                let start = (base + phdr.p_vaddr as usize) & MASK;
+                let end = roundup(base + (phdr.p_vaddr + phdr.p_memsz) as usize, PAGE_SIZE);
+                (start, end - start, program::segment_prot(phdr.p_flags))
+            })
+            .collect();
+        TextSegments::new::<M>(segments)
+    }
+
+    /// Make the segments writable, and no longer executable
+    pub(crate) fn unprotect(&self) -> Result<()> {
+        for &(addr, len, _) in &self.segments {
+            let prot = ProtFlags::PROT_READ | ProtFlags::PROT_WRITE;
+            unsafe { (self.mprotect)(NonNull::new_unchecked(addr as _), len, prot) }?;
+        }
+        Ok(())
+    }
+
+    /// Restore the protection of the segments
+    pub(crate) fn protect(&self) -> Result<()> {
+        for &(addr, len, prot) in &self.segments {
+            let start = unsafe { NonNull::new_unchecked(addr as _) };
+            unsafe { (self.mprotect)(start, len, prot) }?;
+            if prot.contains(ProtFlags::PROT_EXEC) {
+                (self.flush_icache)(start, len);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Create an error for an invalid access to the reserved tail
+#[cold]
+fn tail_error(msg: &'static str) -> crate::Error {
+    crate::Error::Mmap {
+        msg: msg.into(),
+        source: None,
+    }
+}
+
 /// Round up a value to the nearest alignment boundary
 ///
 /// # Arguments
@@ -417,6 +759,91 @@ fn rounddown(x: usize, align: usize) -> usize {
     x & !(align - 1)
 }
 
+/// Address space a loader reserves around the segments of each image
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ExtraSpace {
+    /// Bytes reserved directly after the segments
+    pub(crate) tail: usize,
+    /// Number of inaccessible guard pages on either side of the reservation
+    pub(crate) guard_pages: usize,
+}
+
+impl ExtraSpace {
+    /// Length of one guard region in bytes
+    #[inline]
+    pub(crate) fn guard_len(&self) -> usize {
+        self.guard_pages * PAGE_SIZE
+    }
+}
+
+/// Address space reserved after the segments of an ELF object
+///
+/// The region is part of the same reservation as the segments, so it is
+/// released together with them.
+#[derive(Clone, Copy)]
+pub(crate) struct ReservedTail {
+    /// Length of the region in bytes, a multiple of the page size
+    len: usize,
+    /// Function pointer to the mprotect function
+    mprotect: unsafe fn(NonNull<c_void>, usize, ProtFlags) -> Result<()>,
+}
+
+impl ReservedTail {
+    /// Create the description of a tail of `len` bytes
+    ///
+    /// # Returns
+    /// `None` if `len` is zero
+    pub(crate) fn new<M: Mmap>(len: usize) -> Option<ReservedTail> {
+        (len != 0).then(|| ReservedTail {
+            len: roundup(len, PAGE_SIZE),
+            mprotect: M::mprotect,
+        })
+    }
+
+    /// Get the length of the region, or zero if there is none
+    #[inline]
+    pub(crate) fn len(tail: &Option<ReservedTail>) -> usize {
+        tail.map_or(0, |tail| tail.len)
+    }
+}
+
+/// The address space limit of a loader and the bytes currently reserved
+/// by the images it loaded
+#[derive(Clone, Default)]
+pub(crate) struct MapBudget {
+    /// The largest reservation a single image may make
+    pub(crate) limit: Option<usize>,
+    /// Bytes reserved by live images, shared with their [`ElfSegments`]
+    pub(crate) mapped: Arc<AtomicUsize>,
+}
+
+impl MapBudget {
+    /// Check that a reservation of `requested` bytes is within the limit
+    ///
+    /// # Returns
+    /// * `Ok(())` - If there is no limit or the reservation fits
+    /// * `Err(Error::ImageTooLarge)` - Otherwise
+    pub(crate) fn check(&self, requested: usize) -> Result<()> {
+        match self.limit {
+            Some(limit) if requested > limit => Err(Error::ImageTooLarge { requested, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Charge the reservation of `segments` to the counter
+    ///
+    /// The bytes are given back when the segments are dropped.
+    pub(crate) fn charge(&self, segments: &mut ElfSegments) {
+        self.mapped.fetch_add(segments.reserved_len(), Relaxed);
+        segments.mapped = Some(self.mapped.clone());
+    }
+
+    /// Get the bytes currently reserved
+    pub(crate) fn mapped(&self) -> usize {
+        self.mapped.load(Relaxed)
+    }
+}
+
 /// The Memory mapping of elf object
 ///
 /// This structure represents the complete memory mapping of an
@@ -431,6 +858,20 @@ pub struct ElfSegments {
     pub(crate) len: usize,
     /// Function pointer to the munmap function
     pub(crate) munmap: unsafe fn(NonNull<c_void>, usize) -> Result<()>,
+    /// Address space reserved after the mapped memory
+    pub(crate) tail: Option<ReservedTail>,
+    /// Length of the guard region before the mapped memory
+    pub(crate) lead_guard: usize,
+    /// Length of the guard region after the reserved tail
+    pub(crate) trail_guard: usize,
+    /// Alignment of the base address
+    pub(crate) align: usize,
+    /// Counter of the loader the reservation was charged to
+    pub(crate) mapped: Option<Arc<AtomicUsize>>,
+    /// Number of reads issued to the reader to copy the segment contents
+    pub(crate) reads: usize,
+    /// Whether the memory has already been unmapped
+    pub(crate) unmapped: AtomicBool,
 }
 
 impl Debug for ElfSegments {
@@ -440,6 +881,10 @@ impl Debug for ElfSegments {
             .field("memory", &self.memory)
             .field("offset", &self.offset)
             .field("len", &self.len)
+            .field("tail", &ReservedTail::len(&self.tail))
+            .field("lead_guard", &self.lead_guard)
+            .field("trail_guard", &self.trail_guard)
+            .field("align", &self.align)
             .finish()
     }
 }
@@ -454,11 +899,34 @@ impl ELFRelro {
     /// * `Err(Error)` - If RELRO protection fails
     #[inline]
     pub(crate) fn relro(&self) -> Result<()> {
+        self.protect(ProtFlags::PROT_READ)
+    }
+
+    /// Address range of the RELRO segment
+    #[inline]
+    pub(crate) fn range(&self) -> Range<usize> {
+        self.addr..self.addr + self.len
+    }
+
+    /// Make the RELRO segment writable again
+    ///
+    /// Used to patch GOT entries after RELRO protection has been applied.
+    /// Call [`ELFRelro::relro`] once the writes are done.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the segment is writable
+    /// * `Err(Error)` - If the protection change fails
+    #[inline]
+    pub(crate) fn unprotect(&self) -> Result<()> {
+        self.protect(ProtFlags::PROT_READ | ProtFlags::PROT_WRITE)
+    }
+
+    fn protect(&self, prot: ProtFlags) -> Result<()> {
         let end = roundup(self.addr + self.len, PAGE_SIZE);
         let start = self.addr & MASK;
         let start_addr = unsafe { NonNull::new_unchecked(start as _) };
         unsafe {
-            (self.mprotect)(start_addr, end - start, ProtFlags::PROT_READ)?;
+            (self.mprotect)(start_addr, end - start, prot)?;
         }
         Ok(())
     }
@@ -466,9 +934,14 @@ impl ELFRelro {
 
 impl Drop for ElfSegments {
     /// Unmap the memory when the ElfSegments is dropped
+    ///
+    /// A failure is reported through [`set_unmap_error_handler`] instead of
+    /// panicking.
     fn drop(&mut self) {
-        unsafe {
-            (self.munmap)(self.memory, self.len).unwrap();
+        let start = self.memory.as_ptr() as usize - self.lead_guard;
+        let len = self.reserved_len();
+        if let Err(err) = self.unmap() {
+            unmap_failed(start, len, err);
         }
     }
 }
@@ -493,9 +966,65 @@ impl ElfSegments {
             offset: 0,
             len,
             munmap,
+            tail: None,
+            lead_guard: 0,
+            trail_guard: 0,
+            align: PAGE_SIZE,
+            mapped: None,
+            reads: 0,
+            unmapped: AtomicBool::new(false),
+        }
+    }
+
+    /// Length of the whole reservation, including the tail and the guards
+    #[inline]
+    pub(crate) fn reserved_len(&self) -> usize {
+        self.lead_guard + self.len + ReservedTail::len(&self.tail) + self.trail_guard
+    }
+
+    /// Create an ElfSegments instance for memory owned by the caller
+    ///
+    /// The memory is never unmapped when the instance is dropped.
+    ///
+    /// # Arguments
+    /// * `memory` - Pointer to the mapped memory
+    /// * `len` - Length of the mapped memory
+    pub(crate) fn premapped(memory: NonNull<c_void>, len: usize) -> Self {
+        #[allow(clippy::unnecessary_wraps)]
+        unsafe fn no_munmap(_addr: NonNull<c_void>, _len: usize) -> Result<()> {
+            Ok(())
+        }
+        Self::new(memory, len, no_munmap)
+    }
+
+    /// Unmap the whole reservation, including the tail and the guards
+    ///
+    /// The reservation is released from the loader's counter even if the
+    /// unmapping fails, and later calls do nothing.
+    pub(crate) fn unmap(&self) -> Result<()> {
+        if self.unmapped.swap(true, Relaxed) {
+            return Ok(());
+        }
+        let len = self.reserved_len();
+        if let Some(mapped) = &self.mapped {
+            mapped.fetch_sub(len, Relaxed);
+        }
+        unsafe {
+            let start = self.memory.as_ptr().cast::<u8>().sub(self.lead_guard);
+            (self.munmap)(NonNull::new_unchecked(start.cast()), len)
         }
     }
 
+    /// Unmap the memory, returning the error of [`Mmap::munmap`] instead of
+    /// reporting it through [`set_unmap_error_handler`]
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the memory was unmapped
+    /// * `Err(Error)` - If unmapping fails; the memory is leaked
+    pub fn try_unmap(self) -> Result<()> {
+        self.unmap()
+    }
+
     /// Get the length of the mapped memory
     ///
     /// # Returns
@@ -505,6 +1034,131 @@ impl ElfSegments {
         self.len
     }
 
+    /// Get the number of reads issued to copy the segment contents
+    ///
+    /// Segments mapped from the file, and scattered contents copied out of a
+    /// temporary mapping of it, take no reads. The headers read before the
+    /// segments are loaded are not counted.
+    ///
+    /// # Returns
+    /// The number of calls to [`ElfReader::read`]
+    #[inline]
+    pub fn reads(&self) -> usize {
+        self.reads
+    }
+
+    /// Get the alignment of the base address
+    ///
+    /// This is the largest `p_align` of the `PT_LOAD` segments that the base
+    /// address honors, and never less than the page size.
+    ///
+    /// # Returns
+    /// The alignment in bytes
+    #[inline]
+    pub fn align(&self) -> usize {
+        self.align
+    }
+
+    /// Get the address space reserved after the mapped memory
+    ///
+    /// # Returns
+    /// The start address and the length of the region, or `None` if no
+    /// space was reserved
+    #[inline]
+    pub fn reserved_tail(&self) -> Option<(usize, usize)> {
+        let tail = self.tail?;
+        Some((self.memory.as_ptr() as usize + self.len, tail.len))
+    }
+
+    /// Make part of the reserved tail accessible
+    ///
+    /// The range is widened to page boundaries. Pages of the tail start out
+    /// zero-filled and inaccessible (`PROT_NONE`).
+    ///
+    /// # Arguments
+    /// * `offset` - Offset of the range from the start of the tail
+    /// * `len` - Length of the range in bytes
+    /// * `prot` - The protection to apply to the range
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the protection change succeeds
+    /// * `Err(Error)` - If the range is outside of the tail or the change fails
+    pub fn commit_tail(&self, offset: usize, len: usize, prot: ProtFlags) -> Result<()> {
+        let Some(tail) = self.tail else {
+            return Err(tail_error("no address space was reserved after the image"));
+        };
+        let end = offset
+            .checked_add(len)
+            .filter(|&end| end <= tail.len)
+            .ok_or_else(|| tail_error("range exceeds the reserved tail"))?;
+        let start = rounddown(offset, PAGE_SIZE);
+        let end = roundup(end, PAGE_SIZE);
+        if start == end {
+            return Ok(());
+        }
+        let addr = self.memory.as_ptr() as usize + self.len + start;
+        unsafe { (tail.mprotect)(NonNull::new_unchecked(addr as _), end - start, prot) }
+    }
+
+    /// Make the whole reserved tail inaccessible
+    fn seal_tail(&self) -> Result<()> {
+        match self.tail {
+            Some(tail) => self.commit_tail(0, tail.len, ProtFlags::PROT_NONE),
+            None => Ok(()),
+        }
+    }
+
+    /// Make the guard regions around the reservation inaccessible
+    ///
+    /// Reservations that are not backed by a file start out writable, so the
+    /// guards have to be protected explicitly.
+    fn seal_guards<M: Mmap>(&self) -> Result<()> {
+        let start = self.memory.as_ptr() as usize;
+        if self.lead_guard != 0 {
+            let addr = start - self.lead_guard;
+            unsafe {
+                M::mprotect(
+                    NonNull::new_unchecked(addr as _),
+                    self.lead_guard,
+                    ProtFlags::PROT_NONE,
+                )
+            }?;
+        }
+        if self.trail_guard != 0 {
+            let addr = start + self.len + ReservedTail::len(&self.tail);
+            unsafe {
+                M::mprotect(
+                    NonNull::new_unchecked(addr as _),
+                    self.trail_guard,
+                    ProtFlags::PROT_NONE,
+                )
+            }?;
+        }
+        Ok(())
+    }
+
+    /// Check that a byte range lies within the mapped memory
+    ///
+    /// # Arguments
+    /// * `start` - Start offset within the mapped memory
+    /// * `len` - Length of the range in bytes
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the whole range is mapped
+    /// * `Err(Error::OutOfBounds)` - If any part of the range is not
+    pub(crate) fn check_range(&self, start: usize, len: usize) -> Result<()> {
+        start
+            .checked_sub(self.offset)
+            .and_then(|rel| rel.checked_add(len))
+            .filter(|&end| end <= self.len)
+            .map(|_| ())
+            .ok_or(Error::OutOfBounds {
+                offset: start,
+                len,
+                mapped_len: self.len,
+            })
+    }
+
     /// Get a slice from the mapped memory
     ///
     /// # Arguments
@@ -512,21 +1166,64 @@ impl ElfSegments {
     /// * `len` - Length of the slice in bytes
     ///
     /// # Returns
+    /// * `Ok(slice)` - A static slice of the requested type
+    /// * `Err(Error::OutOfBounds)` - If the range is not mapped
+    #[inline]
+    pub(crate) fn get_slice<T>(&self, start: usize, len: usize) -> Result<&'static [T]> {
+        self.check_range(start, len)?;
+        Ok(unsafe { self.get_slice_unchecked(start, len) })
+    }
+
+    /// Get a mutable slice from the mapped memory
+    ///
+    /// # Arguments
+    /// * `start` - Start offset within the mapped memory
+    /// * `len` - Length of the slice in bytes
+    ///
+    /// # Returns
+    /// * `Ok(slice)` - A static mutable slice of the requested type
+    /// * `Err(Error::OutOfBounds)` - If the range is not mapped
+    pub(crate) fn get_slice_mut<T>(&self, start: usize, len: usize) -> Result<&'static mut [T]> {
+        self.check_range(start, len)?;
+        Ok(unsafe { self.get_slice_mut_unchecked(start, len) })
+    }
+
+    /// Get a pointer to a `T` in the mapped memory
+    ///
+    /// # Arguments
+    /// * `offset` - Offset within the mapped memory
+    ///
+    /// # Returns
+    /// * `Ok(ptr)` - A pointer of the requested type
+    /// * `Err(Error::OutOfBounds)` - If the `T` at `offset` is not mapped
+    #[inline]
+    pub(crate) fn get_ptr<T>(&self, offset: usize) -> Result<*const T> {
+        self.check_range(offset, size_of::<T>())?;
+        Ok(self.get_ptr_unchecked(offset))
+    }
+
+    /// Get a slice from the mapped memory without checking its bounds
+    ///
+    /// # Arguments
+    /// * `start` - Start offset within the mapped memory
+    /// * `len` - Length of the slice in bytes
+    ///
+    /// # Returns
     /// A static slice of the requested type
     ///
     /// # Safety
     /// The caller must ensure the requested range is valid and
     /// the type T is appropriate for the data at that location.
     #[inline]
-    pub(crate) fn get_slice<T>(&self, start: usize, len: usize) -> &'static [T] {
+    pub(crate) unsafe fn get_slice_unchecked<T>(&self, start: usize, len: usize) -> &'static [T] {
         unsafe {
             // Ensure the slice is within the mapped ELF segments
             debug_assert!(start + len - self.offset <= self.len);
-            core::slice::from_raw_parts(self.get_ptr::<T>(start), len / size_of::<T>())
+            core::slice::from_raw_parts(self.get_ptr_unchecked::<T>(start), len / size_of::<T>())
         }
     }
 
-    /// Get a mutable slice from the mapped memory
+    /// Get a mutable slice from the mapped memory without checking its bounds
     ///
     /// # Arguments
     /// * `start` - Start offset within the mapped memory
@@ -538,52 +1235,71 @@ impl ElfSegments {
     /// # Safety
     /// The caller must ensure the requested range is valid and
     /// the type T is appropriate for the data at that location.
-    pub(crate) fn get_slice_mut<T>(&self, start: usize, len: usize) -> &'static mut [T] {
+    pub(crate) unsafe fn get_slice_mut_unchecked<T>(
+        &self,
+        start: usize,
+        len: usize,
+    ) -> &'static mut [T] {
         unsafe {
             // Ensure the slice is within the mapped ELF segments
             debug_assert!(start + len - self.offset <= self.len);
-            core::slice::from_raw_parts_mut(self.get_mut_ptr::<T>(start), len / size_of::<T>())
+            core::slice::from_raw_parts_mut(
+                self.get_mut_ptr_unchecked::<T>(start),
+                len / size_of::<T>(),
+            )
         }
     }
 
-    /// Get a pointer from the mapped memory
+    /// Get a pointer from the mapped memory without checking its bounds
+    ///
+    /// Computing the pointer is always safe; dereferencing it is only sound
+    /// if the caller has validated `offset` beforehand.
     ///
     /// # Arguments
     /// * `offset` - Offset within the mapped memory
     ///
     /// # Returns
     /// A pointer of the requested type
-    ///
-    /// # Safety
-    /// The caller must ensure the requested offset is valid and
-    /// the type T is appropriate for the data at that location.
     #[inline]
-    pub(crate) fn get_ptr<T>(&self, offset: usize) -> *const T {
+    pub(crate) fn get_ptr_unchecked<T>(&self, offset: usize) -> *const T {
         // Ensure offset is within the mapped ELF segments
         debug_assert!(offset - self.offset < self.len);
         (self.base() + offset) as *const T
     }
 
-    /// Get a mutable pointer from the mapped memory
+    /// Get a mutable pointer from the mapped memory without checking its bounds
+    ///
+    /// Computing the pointer is always safe; dereferencing it is only sound
+    /// if the caller has validated `offset` beforehand.
     ///
     /// # Arguments
     /// * `offset` - Offset within the mapped memory
     ///
     /// # Returns
     /// A mutable pointer of the requested type
-    ///
-    /// # Safety
-    /// The caller must ensure the requested offset is valid and
-    /// the type T is appropriate for the data at that location.
     #[inline]
-    pub(crate) fn get_mut_ptr<T>(&self, offset: usize) -> *mut T {
-        self.get_ptr::<T>(offset) as *mut T
+    pub(crate) fn get_mut_ptr_unchecked<T>(&self, offset: usize) -> *mut T {
+        self.get_ptr_unchecked::<T>(offset) as *mut T
     }
 
     /// Write a value into the mapped memory
     #[inline]
     pub(crate) fn write<T>(&self, r_offset: usize, val: RelocValue<T>) {
-        unsafe { self.get_mut_ptr::<T>(r_offset).write(val.0) };
+        unsafe { self.get_mut_ptr_unchecked::<T>(r_offset).write(val.0) };
+    }
+
+    /// Atomically store a word at the given offset
+    ///
+    /// Used for GOT entries that may be read or written concurrently.
+    #[inline]
+    pub(crate) fn write_atomic(&self, r_offset: usize, val: RelocValue<usize>) {
+        self.get_atomic(r_offset).store(val.0, Release);
+    }
+
+    /// Get the word at the given offset for atomic access
+    #[inline]
+    pub(crate) fn get_atomic(&self, r_offset: usize) -> &AtomicUsize {
+        unsafe { AtomicUsize::from_ptr(self.get_mut_ptr_unchecked::<usize>(r_offset)) }
     }
 
     /// Get the base address of the mapped memory
@@ -1,18 +1,19 @@
 use crate::{
-    Result,
+    Error, Result,
     elf::ElfPhdr,
     os::{MapFlags, Mmap, ProtFlags},
     segment::{
-        Address, ElfSegment, ElfSegments, FileMapInfo, PAGE_SIZE, SegmentBuilder, rounddown,
-        roundup,
+        Address, ElfSegment, ElfSegments, ExtraSpace, FileMapInfo, MapBudget, PAGE_SIZE,
+        ReservedTail, SegmentBuilder, TextSegments, rounddown, roundup,
     },
 };
 use alloc::vec::Vec;
+use core::sync::atomic::AtomicBool;
 use elf::abi::{PF_R, PF_W, PF_X, PT_LOAD};
 
 /// Convert ELF program header flags to memory protection flags
 #[inline]
-fn segment_prot(p_flag: u32) -> ProtFlags {
+pub(crate) fn segment_prot(p_flag: u32) -> ProtFlags {
     // Map ELF flags (PF_X, PF_W, PF_R) to memory protection flags
     // PF_X (execute) -> PROT_EXEC (bit 2)
     // PF_W (write)   -> PROT_WRITE (bit 1)
@@ -26,6 +27,7 @@ pub(crate) struct ProgramSegments<'phdr> {
     segments: Vec<ElfSegment>,
     is_dylib: bool,
     use_file: bool,
+    fixed_overwrite: bool,
 }
 
 impl<'phdr> ProgramSegments<'phdr> {
@@ -36,19 +38,44 @@ impl<'phdr> ProgramSegments<'phdr> {
             segments: Vec::new(),
             is_dylib,
             use_file,
+            fixed_overwrite: false,
         }
     }
+
+    /// Skip the check that the fixed address range of an executable is free
+    pub(crate) fn allow_fixed_overwrite(mut self, allow: bool) -> Self {
+        self.fixed_overwrite = allow;
+        self
+    }
+
+    /// Collect the segments that are not writable, with the protection they
+    /// were given, for an object with text relocations
+    pub(crate) fn text_segments<M: Mmap>(&self) -> TextSegments {
+        let segments = self
+            .segments()
+            .iter()
+            .filter(|segment| !segment.prot.contains(ProtFlags::PROT_WRITE))
+            .map(|segment| (segment.addr.absolute_addr(), segment.len, segment.prot))
+            .collect();
+        TextSegments::new::<M>(segments)
+    }
 }
 
 /// Parse segments to determine memory layout requirements
 #[inline]
-fn parse_segments(phdrs: &[ElfPhdr], is_dylib: bool) -> (Option<usize>, usize, usize) {
+fn parse_segments(phdrs: &[ElfPhdr], is_dylib: bool) -> (Option<usize>, usize, usize, usize) {
     let mut min_vaddr = usize::MAX;
     let mut max_vaddr = 0;
+    let mut align = PAGE_SIZE;
 
     // Find the minimum and maximum virtual addresses of LOAD segments
     for phdr in phdrs {
         if phdr.p_type == PT_LOAD {
+            // Values that are not a power of two are invalid and ignored
+            let p_align = phdr.p_align as usize;
+            if p_align.is_power_of_two() {
+                align = align.max(p_align);
+            }
             let vaddr_start = phdr.p_vaddr as usize;
             let vaddr_end = (phdr.p_vaddr + phdr.p_memsz) as usize;
             if vaddr_start < min_vaddr {
@@ -71,19 +98,76 @@ fn parse_segments(phdrs: &[ElfPhdr], is_dylib: bool) -> (Option<usize>, usize, u
         if is_dylib { None } else { Some(min_vaddr) },
         total_size,
         min_vaddr,
+        align,
     )
 }
 
 impl SegmentBuilder for ProgramSegments<'_> {
     /// Reserve memory space for all segments
-    fn create_space<M: Mmap>(&mut self) -> Result<ElfSegments> {
-        let (addr, len, min_vaddr) = parse_segments(self.phdrs, self.is_dylib);
-        let ptr = unsafe { M::mmap_reserve(addr, len, self.use_file) }?;
+    fn create_space<M: Mmap>(
+        &mut self,
+        extra: ExtraSpace,
+        budget: &MapBudget,
+    ) -> Result<ElfSegments> {
+        let (addr, len, min_vaddr, align) = parse_segments(self.phdrs, self.is_dylib);
+        let tail = ReservedTail::new::<M>(extra.tail);
+        let trail_guard = extra.guard_len();
+        // The base is only aligned when the first segment starts on an aligned address
+        let aligned = addr.is_none() && align > PAGE_SIZE && min_vaddr % align == 0;
+        let lead_guard = match addr {
+            // The leading guard keeps an aligned base aligned
+            None if aligned => roundup(trail_guard, align),
+            None => trail_guard,
+            // An executable at a fixed address has no room below address zero
+            Some(addr) if addr < trail_guard => 0,
+            Some(_) => trail_guard,
+        };
+        let addr = addr.map(|addr| addr - lead_guard);
+        let total_len = lead_guard + len + ReservedTail::len(&tail) + trail_guard;
+        budget.check(total_len)?;
+        let check = addr.filter(|_| !self.fixed_overwrite);
+        if let Some(wanted) = check
+            && !unsafe { M::probe(wanted, total_len) }
+        {
+            return Err(Error::AddressConflict {
+                wanted,
+                len: total_len,
+            });
+        }
+        let ptr = if aligned {
+            unsafe { M::mmap_reserve_aligned(total_len, align, self.use_file) }?
+        } else {
+            unsafe { M::mmap_reserve(addr, total_len, self.use_file) }?
+        };
+        // The range may have been taken between the probe and the reservation
+        if let Some(wanted) = check
+            && ptr.as_ptr() as usize != wanted
+        {
+            unsafe { M::munmap(ptr, total_len) }?;
+            return Err(Error::AddressConflict {
+                wanted,
+                len: total_len,
+            });
+        }
+        let ptr = unsafe { ptr.byte_add(lead_guard) };
+        // Report the alignment that was actually obtained
+        let base = (ptr.as_ptr() as usize).wrapping_sub(min_vaddr);
+        let align = match base {
+            0 => align,
+            base => align.min(1 << base.trailing_zeros()),
+        };
         Ok(ElfSegments {
             memory: ptr,
             offset: min_vaddr,
             len,
             munmap: M::munmap,
+            tail,
+            lead_guard,
+            trail_guard,
+            align,
+            mapped: None,
+            reads: 0,
+            unmapped: AtomicBool::new(false),
         })
     }
 
@@ -138,6 +222,7 @@ impl ElfPhdr {
             }],
             need_copy: false,
             from_relocatable: false,
+            prot_overridden: false,
         }
     }
 }
@@ -6,11 +6,12 @@ use crate::{
     os::{MapFlags, Mmap, ProtFlags},
     relocation::{RelocValue, StaticReloc},
     segment::{
-        Address, ElfSegment, ElfSegments, FileMapInfo, PAGE_SIZE, SegmentBuilder, rounddown,
-        roundup,
+        Address, ElfSegment, ElfSegments, ExtraSpace, FileMapInfo, MapBudget, PAGE_SIZE,
+        ReservedTail, SegmentBuilder, rounddown, roundup,
     },
 };
 use alloc::vec::Vec;
+use core::sync::atomic::AtomicBool;
 use elf::abi::{SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE, SHT_NOBITS, SHT_REL, SHT_RELA};
 use hashbrown::{HashMap, HashSet, hash_map::Entry};
 
@@ -52,20 +53,39 @@ fn flags_to_idx(flags: u64) -> usize {
 
 impl SegmentBuilder for SectionSegments {
     /// Reserve memory space for all segments
-    fn create_space<M: Mmap>(&mut self) -> Result<ElfSegments> {
+    fn create_space<M: Mmap>(
+        &mut self,
+        extra: ExtraSpace,
+        budget: &MapBudget,
+    ) -> Result<ElfSegments> {
         let len = self.total_size;
-        let memory = unsafe { M::mmap_reserve(None, len, false) }?;
+        let tail = ReservedTail::new::<M>(extra.tail);
+        let guard = extra.guard_len();
+        let total_len = guard + len + ReservedTail::len(&tail) + guard;
+        budget.check(total_len)?;
+        let ptr = unsafe { M::mmap_reserve(None, total_len, false) }?;
         Ok(ElfSegments {
-            memory,
+            memory: unsafe { ptr.byte_add(guard) },
             offset: 0,
             len,
             munmap: M::munmap,
+            tail,
+            lead_guard: guard,
+            trail_guard: guard,
+            align: PAGE_SIZE,
+            mapped: None,
+            reads: 0,
+            unmapped: AtomicBool::new(false),
         })
     }
 
     /// Create individual segments from section headers
-    /// In this implementation, segments are pre-created in `new`, so this is a no-op
+    /// In this implementation, segments are pre-created in `new`, so only the
+    /// file ranges of their sections that follow each other are merged
     fn create_segments(&mut self) -> Result<()> {
+        for segment in &mut self.segments {
+            segment.coalesce_map_info();
+        }
         Ok(())
     }
 
@@ -184,12 +204,24 @@ impl PltGotSection {
                 continue;
             }
 
-            let mut buf = alloc::vec![0u8; size];
-            if object.read(&mut buf, shdr.sh_offset as usize).is_err() {
-                continue;
-            }
+            let offset = shdr.sh_offset as usize;
+            let mut buf = Vec::new();
+            // Use the table in place when the object is in memory
+            let data = match object
+                .as_bytes()
+                .and_then(|bytes| bytes.get(offset..offset.checked_add(size)?))
+            {
+                Some(data) => data,
+                None => {
+                    buf.resize(size, 0);
+                    if object.read(&mut buf, offset).is_err() {
+                        continue;
+                    }
+                    &buf
+                }
+            };
 
-            for chunk in buf.chunks(entsize) {
+            for chunk in data.chunks(entsize) {
                 if chunk.len() < entsize {
                     break;
                 }
@@ -468,6 +500,7 @@ impl<'shdr> SectionUnit<'shdr> {
             flags: MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED,
             map_info,
             from_relocatable: true,
+            prot_overridden: false,
         };
         Some(segment)
     }
@@ -0,0 +1,171 @@
+//! Thread-local storage support
+//!
+//! Modules with a `PT_TLS` segment are registered with the [`TlsAllocator`] set
+//! through [`Loader::with_tls_allocator`](crate::Loader::with_tls_allocator),
+//! which assigns them a module id. The relocation code then fills the TLS
+//! relocations from it:
+//! - `R_*_DTPMOD` receives the module id of the defining module.
+//! - `R_*_DTPOFF` receives the offset of the variable inside its TLS block.
+//! - `R_*_TPOFF` receives the offset from the thread pointer, which is only
+//!   available if the allocator places the module in the static TLS block.
+//!
+//! With the `std` feature, [`ThreadLocalAllocator`] provides a ready-made
+//! allocator that keeps one block per thread and module, together with a
+//! `__tls_get_addr` implementation that is bound automatically.
+use crate::elf::ElfPhdr;
+use core::slice;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(not(feature = "portable-atomic"))]
+use alloc::sync::Arc;
+#[cfg(feature = "portable-atomic")]
+use portable_atomic_util::Arc;
+
+#[cfg(feature = "std")]
+mod thread_local;
+
+#[cfg(feature = "std")]
+pub use thread_local::{ThreadLocalAllocator, tls_get_addr};
+
+/// The argument of `__tls_get_addr`: a module id and an offset inside its TLS block.
+///
+/// The two words are filled by a `R_*_DTPMOD` and `R_*_DTPOFF` relocation pair.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlsIndex {
+    /// Module id assigned by the [`TlsAllocator`].
+    pub module: usize,
+    /// Offset of the variable inside the TLS block of the module.
+    pub offset: usize,
+}
+
+/// The TLS template of a module, described by its `PT_TLS` segment.
+#[derive(Debug, Clone, Copy)]
+pub struct TlsInfo {
+    image: *const u8,
+    image_len: usize,
+    mem_size: usize,
+    align: usize,
+}
+
+// Safety: the template is read-only and lives as long as the module
+unsafe impl Send for TlsInfo {}
+unsafe impl Sync for TlsInfo {}
+
+impl TlsInfo {
+    /// Creates the template description from the `PT_TLS` program header.
+    pub(crate) fn new(phdr: &ElfPhdr, base: usize) -> Self {
+        Self {
+            image: (base + phdr.p_vaddr as usize) as *const u8,
+            image_len: phdr.p_filesz as usize,
+            mem_size: phdr.p_memsz as usize,
+            align: phdr.p_align as usize,
+        }
+    }
+
+    /// Returns the initialization image (`.tdata`) of the TLS block.
+    ///
+    /// The rest of the block, up to [`mem_size`](Self::mem_size), is zero-initialized (`.tbss`).
+    pub fn image(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.image, self.image_len) }
+    }
+
+    /// Returns the size of the TLS block in bytes.
+    pub fn mem_size(&self) -> usize {
+        self.mem_size
+    }
+
+    /// Returns the required alignment of the TLS block.
+    pub fn align(&self) -> usize {
+        self.align
+    }
+}
+
+/// A trait for managing the TLS blocks of loaded modules.
+///
+/// Implement this trait to integrate loaded modules with the TLS runtime of the
+/// host, then register it with [`Loader::with_tls_allocator`](crate::Loader::with_tls_allocator).
+pub trait TlsAllocator: Send + Sync {
+    /// Registers the TLS template of a module.
+    ///
+    /// # Arguments
+    /// * `info` - The TLS template of the module.
+    ///
+    /// # Returns
+    /// * `Some(id)` - The module id, which must not be `0`.
+    /// * `None` - If the module can't be registered. TLS relocations against
+    ///   the module will then be reported as unhandled.
+    fn register(&self, info: &TlsInfo) -> Option<usize>;
+
+    /// Releases a module id when its module is dropped.
+    fn unregister(&self, mod_id: usize);
+
+    /// Returns the offset of the TLS block of a module from the thread pointer.
+    ///
+    /// This is only possible for modules placed in the static TLS block, and is
+    /// needed to resolve `R_*_TPOFF` relocations. The default returns `None`.
+    fn static_offset(&self, mod_id: usize) -> Option<isize> {
+        let _ = mod_id;
+        None
+    }
+
+    /// Provides runtime symbols such as `__tls_get_addr`.
+    ///
+    /// This lookup takes precedence over every other symbol source. The default
+    /// provides no symbols.
+    fn lookup(&self, name: &str) -> Option<*const ()> {
+        let _ = name;
+        None
+    }
+}
+
+/// The TLS state of a loaded module.
+pub(crate) struct TlsModule {
+    allocator: Arc<dyn TlsAllocator>,
+    /// Module id, if the module has a `PT_TLS` segment
+    mod_id: Option<usize>,
+    /// Whether the template has already been unregistered
+    unregistered: AtomicBool,
+}
+
+impl TlsModule {
+    /// Registers the module's TLS template, if any, with the allocator.
+    pub(crate) fn new(allocator: Arc<dyn TlsAllocator>, info: Option<&TlsInfo>) -> Self {
+        let mod_id = info.and_then(|info| allocator.register(info));
+        Self {
+            allocator,
+            mod_id,
+            unregistered: AtomicBool::new(false),
+        }
+    }
+
+    /// Unregisters the module's TLS template, if it has not been already.
+    pub(crate) fn unregister(&self) {
+        if let Some(mod_id) = self.mod_id
+            && !self.unregistered.swap(true, Ordering::Relaxed)
+        {
+            self.allocator.unregister(mod_id);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn mod_id(&self) -> Option<usize> {
+        self.mod_id
+    }
+
+    #[inline]
+    pub(crate) fn static_offset(&self) -> Option<isize> {
+        self.allocator.static_offset(self.mod_id?)
+    }
+
+    #[inline]
+    pub(crate) fn lookup(&self, name: &str) -> Option<*const ()> {
+        self.allocator.lookup(name)
+    }
+}
+
+impl Drop for TlsModule {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
@@ -0,0 +1,108 @@
+//! A TLS allocator backed by the thread-locals of the standard library
+use super::{TlsAllocator, TlsIndex, TlsInfo};
+use crate::arch::TLS_DTV_OFFSET;
+use core::{
+    cell::RefCell,
+    ffi::c_void,
+    ptr::{copy_nonoverlapping, null_mut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use hashbrown::HashMap;
+use spin::RwLock;
+use std::alloc::{Layout, alloc_zeroed, dealloc};
+
+/// Module ids are never reused, so a stale block can't be mistaken for a new module
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+static TEMPLATES: RwLock<Option<HashMap<usize, TlsInfo>>> = RwLock::new(None);
+
+/// A TLS block owned by the current thread
+struct Block {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl Drop for Block {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr, self.layout) };
+    }
+}
+
+std::thread_local! {
+    static BLOCKS: RefCell<HashMap<usize, Block>> = RefCell::new(HashMap::new());
+}
+
+/// A [`TlsAllocator`] that allocates one block per thread and module on first use.
+///
+/// Blocks are allocated by [`tls_get_addr`], which is bound to every
+/// `__tls_get_addr` reference of the modules using this allocator, and are
+/// released when their thread exits. Static TLS is not available, so `R_*_TPOFF`
+/// relocations are not supported.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ThreadLocalAllocator;
+
+impl TlsAllocator for ThreadLocalAllocator {
+    fn register(&self, info: &TlsInfo) -> Option<usize> {
+        let mod_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        TEMPLATES
+            .write()
+            .get_or_insert_with(HashMap::new)
+            .insert(mod_id, *info);
+        Some(mod_id)
+    }
+
+    fn unregister(&self, mod_id: usize) {
+        if let Some(templates) = TEMPLATES.write().as_mut() {
+            templates.remove(&mod_id);
+        }
+        // Blocks of other threads are released when those threads exit
+        let _ = BLOCKS.try_with(|blocks| blocks.borrow_mut().remove(&mod_id));
+    }
+
+    fn lookup(&self, name: &str) -> Option<*const ()> {
+        (name == "__tls_get_addr").then_some(tls_get_addr as *const ())
+    }
+}
+
+/// Returns the address of a TLS variable for the current thread.
+///
+/// This is the `__tls_get_addr` implementation used by [`ThreadLocalAllocator`].
+/// The TLS block of the module is allocated and initialized on first use.
+///
+/// # Safety
+/// `ti` must point to a valid [`TlsIndex`].
+///
+/// # Returns
+/// The address of the variable, or null if the module is not registered.
+pub unsafe extern "C" fn tls_get_addr(ti: *const TlsIndex) -> *mut c_void {
+    let TlsIndex { module, offset } = unsafe { *ti };
+    let block = BLOCKS.with(|blocks| {
+        let mut blocks = blocks.borrow_mut();
+        if let Some(block) = blocks.get(&module) {
+            return block.ptr;
+        }
+        let Some(info) = TEMPLATES
+            .read()
+            .as_ref()
+            .and_then(|templates| templates.get(&module).copied())
+        else {
+            return null_mut();
+        };
+        let Ok(layout) = Layout::from_size_align(info.mem_size().max(1), info.align().max(1))
+        else {
+            return null_mut();
+        };
+        let ptr = unsafe { alloc_zeroed(layout) };
+        if ptr.is_null() {
+            return null_mut();
+        }
+        let image = info.image();
+        unsafe { copy_nonoverlapping(image.as_ptr(), ptr, image.len()) };
+        blocks.insert(module, Block { ptr, layout });
+        ptr
+    });
+    if block.is_null() {
+        return null_mut();
+    }
+    unsafe { block.add(offset + TLS_DTV_OFFSET).cast() }
+}
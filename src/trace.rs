@@ -0,0 +1,245 @@
+//! Lightweight tracing of the loader's activity
+//!
+//! The loader reports what it maps, protects, relocates and binds as
+//! [`TraceEvent`]s. The events borrow everything they describe and never
+//! allocate, so a trace function can be installed in environments without the
+//! `log` crate, such as a kernel or a bare-metal loader that can only print a
+//! string:
+//!
+//! ```rust
+//! use elf_loader::trace::{TraceEvent, set_trace_fn};
+//!
+//! fn trace(event: TraceEvent<'_>) {
+//!     if let TraceEvent::Mmap { addr, len, .. } = event {
+//!         // Hand the range to the console driver
+//!         let _ = (addr, len);
+//!     }
+//! }
+//!
+//! set_trace_fn(Some(trace));
+//! ```
+//!
+//! The `trace` example prints every event through a fixed buffer, the way a
+//! `no_std` loader like `mini-loader` would with its `print_str`.
+//!
+//! With the `log` feature every event is also logged, warnings at the `warn`
+//! level and everything else at the `trace` level.
+use crate::{Error, os::ProtFlags};
+use core::fmt::{self, Display};
+use spin::RwLock;
+
+/// A function receiving the events of the loader.
+pub type TraceFn = fn(TraceEvent<'_>);
+
+static TRACE_FN: RwLock<Option<TraceFn>> = RwLock::new(None);
+
+/// Installs the process-wide trace function.
+///
+/// # Arguments
+/// * `trace` - The new trace function, or `None` to remove the current one.
+///
+/// # Returns
+/// The previously installed trace function.
+pub fn set_trace_fn(trace: Option<TraceFn>) -> Option<TraceFn> {
+    core::mem::replace(&mut *TRACE_FN.write(), trace)
+}
+
+/// Reports an event to the logger and the trace function
+#[inline]
+pub(crate) fn emit(event: TraceEvent<'_>) {
+    #[cfg(feature = "log")]
+    if event.is_warning() {
+        log::warn!("{event}");
+    } else {
+        log::trace!("{event}");
+    }
+    let trace = *TRACE_FN.read();
+    if let Some(trace) = trace {
+        trace(event);
+    }
+}
+
+/// Where a symbol reference was bound, as reported by
+/// [`TraceEvent::SymbolBind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindProvider<'a> {
+    /// A module of the relocation scope, by name
+    Module(&'a str),
+    /// The referencing module itself, which was linked with `-Bsymbolic`
+    Symbolic,
+    /// The TLS allocator of the module
+    TlsAllocator,
+    /// The `pre_find` lookup of the relocator
+    PreFind,
+    /// The `post_find` lookup of the relocator
+    PostFind,
+}
+
+impl Display for BindProvider<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindProvider::Module(name) => write!(f, "[{name}]"),
+            BindProvider::Symbolic => f.write_str("itself (symbolic)"),
+            BindProvider::TlsAllocator => f.write_str("[tls allocator]"),
+            BindProvider::PreFind => f.write_str("[pre_find]"),
+            BindProvider::PostFind => f.write_str("[post_find]"),
+        }
+    }
+}
+
+/// An event of the loader.
+///
+/// The `Display` implementation renders an event as a single line without a
+/// trailing newline.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum TraceEvent<'a> {
+    /// A segment was mapped.
+    Mmap {
+        /// Start address of the mapping
+        addr: usize,
+        /// Length of the mapping in bytes
+        len: usize,
+        /// Protection the segment ends up with
+        prot: ProtFlags,
+    },
+    /// The protection of a segment was changed.
+    Mprotect {
+        /// Start address of the range
+        addr: usize,
+        /// Length of the range in bytes
+        len: usize,
+        /// The new protection
+        prot: ProtFlags,
+    },
+    /// A relocation entry is about to be processed.
+    Reloc {
+        /// Name of the module being relocated
+        lib: &'a str,
+        /// Type of the relocation
+        r_type: u32,
+        /// Offset of the relocated word from the base address
+        offset: usize,
+        /// Name of the referenced symbol, if any
+        symbol: Option<&'a str>,
+    },
+    /// A symbol reference was bound.
+    SymbolBind {
+        /// Name of the module holding the reference
+        lib: &'a str,
+        /// Name of the symbol
+        symbol: &'a str,
+        /// Where the definition was found
+        provider: BindProvider<'a>,
+    },
+    /// A module requested an executable stack, which the loader allowed with
+    /// a warning.
+    ExecStack {
+        /// Name of the module
+        lib: &'a str,
+    },
+    /// A module has text relocations, so its read-only segments are made
+    /// writable while it is relocated and its code pages can no longer be
+    /// shared with other processes.
+    TextRel {
+        /// Name of the module
+        lib: &'a str,
+    },
+    /// A copy relocation referenced a symbol with a different size than its
+    /// definition; the smaller size was copied.
+    CopySizeMismatch {
+        /// Name of the module holding the relocation
+        lib: &'a str,
+        /// Name of the symbol
+        symbol: &'a str,
+        /// Size of the symbol in the referencing module
+        referenced: usize,
+        /// Size of the symbol in the defining module
+        defined: usize,
+        /// Name of the defining module
+        provider: &'a str,
+    },
+    /// The memory of a module could not be unmapped while it was dropped,
+    /// and was leaked.
+    MunmapFailed {
+        /// Start address of the range
+        addr: usize,
+        /// Length of the range in bytes
+        len: usize,
+        /// The error returned by the unmapping function
+        error: &'a Error,
+    },
+}
+
+impl TraceEvent<'_> {
+    /// Returns whether the event points at a likely problem.
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            TraceEvent::ExecStack { .. }
+                | TraceEvent::TextRel { .. }
+                | TraceEvent::CopySizeMismatch { .. }
+                | TraceEvent::MunmapFailed { .. }
+        )
+    }
+}
+
+impl Display for TraceEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TraceEvent::Mmap { addr, len, prot } => {
+                write!(
+                    f,
+                    "[Mmap] address: 0x{addr:x}, length: {len}, prot: {prot:?}"
+                )
+            }
+            TraceEvent::Mprotect { addr, len, prot } => {
+                write!(
+                    f,
+                    "[Mprotect] address: 0x{addr:x}, length: {len}, prot: {prot:?}"
+                )
+            }
+            TraceEvent::Reloc {
+                lib,
+                r_type,
+                offset,
+                symbol,
+            } => {
+                write!(
+                    f,
+                    "relocating file [{lib}]: type {r_type} at offset 0x{offset:x}"
+                )?;
+                match symbol {
+                    Some(symbol) => write!(f, ", symbol [{symbol}]"),
+                    None => Ok(()),
+                }
+            }
+            TraceEvent::SymbolBind {
+                lib,
+                symbol,
+                provider,
+            } => write!(f, "binding file [{lib}] to {provider}: symbol [{symbol}]"),
+            TraceEvent::ExecStack { lib } => {
+                write!(f, "file [{lib}]: PT_GNU_STACK requests an executable stack")
+            }
+            TraceEvent::TextRel { lib } => write!(
+                f,
+                "file [{lib}]: DT_TEXTREL requires writing to read-only segments, their pages can no longer be shared"
+            ),
+            TraceEvent::CopySizeMismatch {
+                lib,
+                symbol,
+                referenced,
+                defined,
+                provider,
+            } => write!(
+                f,
+                "file [{lib}]: copy relocation size mismatch for symbol [{symbol}]: {referenced} bytes referenced, {defined} bytes defined in [{provider}]"
+            ),
+            TraceEvent::MunmapFailed { addr, len, error } => write!(
+                f,
+                "[Munmap] failed at address: 0x{addr:x}, length: {len}: {error}"
+            ),
+        }
+    }
+}
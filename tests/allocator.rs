@@ -0,0 +1,103 @@
+#![cfg(feature = "allocator_api")]
+#![feature(allocator_api)]
+
+use elf_loader::{Loader, input::ElfBinary, os::DefaultMmap};
+use gen_elf::{Arch, DylibWriter, SymbolDesc};
+use std::{
+    alloc::{AllocError, Allocator, GlobalAlloc, Layout, System},
+    ptr::NonNull,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// Counts the allocations made through the global allocator
+struct CountingGlobal;
+
+static GLOBAL_ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingGlobal {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        GLOBAL_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        GLOBAL_ALLOCS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingGlobal = CountingGlobal;
+
+/// Counts the allocations and the bytes in use of a loader allocator
+#[derive(Default)]
+struct Counters {
+    allocs: AtomicUsize,
+    live: AtomicUsize,
+}
+
+struct CountingAlloc(Arc<Counters>);
+
+unsafe impl Allocator for CountingAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.0.allocs.fetch_add(1, Ordering::Relaxed);
+        self.0.live.fetch_add(layout.size(), Ordering::Relaxed);
+        System.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.0.live.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.deallocate(ptr, layout) }
+    }
+}
+
+/// Loads and relocates `bytes`, returning the number of global allocations it made
+fn load_and_count(loader: &mut Loader<DefaultMmap, ()>, bytes: &[u8]) -> usize {
+    let before = GLOBAL_ALLOCS.load(Ordering::Relaxed);
+    let raw = loader
+        .load_dylib(ElfBinary::new("/tmp/libcounted.so", bytes))
+        .expect("Failed to load library");
+    assert_eq!(raw.path(), "/tmp/libcounted.so");
+    let lib = raw
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let count = GLOBAL_ALLOCS.load(Ordering::Relaxed) - before;
+    assert_eq!(lib.name(), "libcounted.so");
+    assert!(unsafe { lib.get::<()>("counted_var") }.is_some());
+    count
+}
+
+#[test]
+fn per_load_allocations_use_loader_allocator() {
+    let symbols: Vec<_> = (0..4)
+        .map(|i| SymbolDesc::global_object(&format!("var_{i}"), &[0; 8]))
+        .chain([SymbolDesc::global_object("counted_var", &[0; 8])])
+        .collect();
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+
+    let global_only = load_and_count(&mut Loader::new(), &output.data);
+
+    let counters = Arc::new(Counters::default());
+    let mut loader = Loader::new_in(CountingAlloc(counters.clone()));
+    let before = counters.allocs.load(Ordering::Relaxed);
+    let global = load_and_count(&mut loader, &output.data);
+    let routed = counters.allocs.load(Ordering::Relaxed) - before;
+
+    // The header buffer, name, path and dynamic section were moved off the global allocator
+    assert!(routed >= 4, "only {routed} allocations were routed");
+    assert_eq!(global + routed, global_only);
+
+    // Everything allocated for the module is returned once the loader is gone
+    drop(loader);
+    assert_eq!(counters.live.load(Ordering::Relaxed), 0);
+}
@@ -0,0 +1,19 @@
+#![cfg(all(feature = "capi", target_os = "linux"))]
+
+// Links the C API that the host calls
+extern crate elf_loader;
+
+use std::ffi::{CString, c_char, c_int};
+
+#[link(name = "capi_host", kind = "static")]
+unsafe extern "C" {
+    /// Runs the C host of `tests/fixtures/c/capi/host.c`, returning the line
+    /// of the first failed check or 0
+    fn capi_host_run(dir: *const c_char) -> c_int;
+}
+
+#[test]
+fn c_host() {
+    let dir = CString::new(env!("TEST_ARTIFACTS")).unwrap();
+    assert_eq!(unsafe { capi_host_run(dir.as_ptr()) }, 0);
+}
@@ -0,0 +1,160 @@
+#![cfg(all(target_os = "linux", target_env = "gnu"))]
+
+use elf_loader::{Loader, image::LoadedDylib, os::DefaultMmap, relocation::CompatFlags};
+use libloading::os::unix::{Library, RTLD_GLOBAL, RTLD_LAZY, RTLD_LOCAL, RTLD_NOW};
+use std::ffi::{CStr, c_char, c_int};
+
+/// Not exported by libloading
+const RTLD_DEEPBIND: c_int = 0x8;
+
+/// The probes of `libcompat_top.so`, each returning the name of the
+/// definition its reference was bound to
+const PROBES: [&str; 3] = ["probe_foo", "probe_bar", "probe_missing"];
+
+type Probe = extern "C" fn() -> *const c_char;
+
+fn path(name: &str) -> String {
+    format!("{}/libcompat_{name}.so", env!("TEST_ARTIFACTS"))
+}
+
+fn call(probe: Probe) -> String {
+    unsafe { CStr::from_ptr(probe()) }
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+fn system_bindings(flags: c_int) -> Vec<String> {
+    let global = unsafe { Library::open(Some(path("global")), RTLD_NOW | RTLD_GLOBAL) }.unwrap();
+    let top = unsafe { Library::open(Some(path("top")), flags) }.unwrap();
+    let bindings = PROBES
+        .iter()
+        .map(|name| call(*unsafe { top.get::<Probe>(name.as_bytes()) }.unwrap()))
+        .collect();
+    drop(top);
+    drop(global);
+    bindings
+}
+
+fn open(
+    loader: &mut Loader<DefaultMmap, ()>,
+    name: &str,
+    deps: &[&LoadedDylib<()>],
+    flags: CompatFlags,
+) -> LoadedDylib<()> {
+    loader
+        .load_dylib(path(name))
+        .unwrap()
+        .relocator()
+        .scope(deps.iter().copied())
+        .dlopen_compat(flags)
+        .unwrap()
+        .relocate()
+        .unwrap()
+}
+
+fn compat_bindings(flags: CompatFlags) -> Vec<String> {
+    let mut loader = Loader::new();
+    let _global = open(
+        &mut loader,
+        "global",
+        &[],
+        CompatFlags::NOW | CompatFlags::GLOBAL,
+    );
+    // `dlopen` opens the dependencies with the mode of the library
+    let dep1 = open(&mut loader, "dep1", &[], flags);
+    let dep2 = open(&mut loader, "dep2", &[], flags);
+    let top = open(&mut loader, "top", &[&dep1, &dep2], flags);
+    PROBES
+        .iter()
+        .map(|name| call(*unsafe { top.get::<Probe>(name) }.unwrap()))
+        .collect()
+}
+
+#[test]
+fn dlopen_compat_matches_system() {
+    let matrix = [
+        (RTLD_NOW | RTLD_LOCAL, CompatFlags::NOW | CompatFlags::LOCAL),
+        (
+            RTLD_LAZY | RTLD_LOCAL,
+            CompatFlags::LAZY | CompatFlags::LOCAL,
+        ),
+        (
+            RTLD_NOW | RTLD_DEEPBIND,
+            CompatFlags::NOW | CompatFlags::DEEPBIND,
+        ),
+        (
+            RTLD_LAZY | RTLD_DEEPBIND,
+            CompatFlags::LAZY | CompatFlags::DEEPBIND,
+        ),
+    ];
+    for (system, compat) in matrix {
+        let expected = system_bindings(system);
+        // The global definition only wins without DEEPBIND, and the first
+        // definition wins even if it is weak
+        let foo = if system & RTLD_DEEPBIND != 0 {
+            "top"
+        } else {
+            "global"
+        };
+        assert_eq!(expected, [foo, "dep1 weak", "null"]);
+        assert_eq!(compat_bindings(compat), expected, "{compat:?}");
+    }
+}
+
+#[test]
+fn dlopen_compat_lazy_binding() {
+    // `compat_late` is only defined once `libcompat_late.so` is open
+    assert!(unsafe { Library::open(Some(path("lazy")), RTLD_NOW) }.is_err());
+    let lazy = unsafe { Library::open(Some(path("lazy")), RTLD_LAZY) }.unwrap();
+    let late = unsafe { Library::open(Some(path("late")), RTLD_NOW | RTLD_GLOBAL) }.unwrap();
+    let expected = call(*unsafe { lazy.get::<Probe>(b"probe_late") }.unwrap());
+    drop(lazy);
+    drop(late);
+
+    let mut loader = Loader::new();
+    let now = loader
+        .load_dylib(path("lazy"))
+        .unwrap()
+        .relocator()
+        .dlopen_compat(CompatFlags::NOW)
+        .unwrap()
+        .relocate();
+    assert!(now.is_err());
+    let lazy = open(&mut loader, "lazy", &[], CompatFlags::LAZY);
+    let _late = open(
+        &mut loader,
+        "late",
+        &[],
+        CompatFlags::NOW | CompatFlags::GLOBAL,
+    );
+    let probe = unsafe { lazy.get::<Probe>("probe_late") }.unwrap();
+    assert_eq!(call(*probe), expected);
+}
+
+#[test]
+fn dlopen_compat_rejects_unreproducible_modes() {
+    let mut loader = Loader::new();
+    let mut relocator = |flags| {
+        loader
+            .load_dylib(path("dep1"))
+            .unwrap()
+            .relocator()
+            .dlopen_compat(flags)
+            .err()
+    };
+    assert!(relocator(CompatFlags::LOCAL).is_some());
+    assert!(relocator(CompatFlags::NOW | CompatFlags::LAZY).is_some());
+    assert!(relocator(CompatFlags::NOW | CompatFlags::LOCAL | CompatFlags::GLOBAL).is_some());
+
+    // `dlopen` would make the dependencies global as well
+    let dep1 = open(&mut loader, "dep1", &[], CompatFlags::NOW);
+    let dep2 = open(&mut loader, "dep2", &[], CompatFlags::NOW);
+    let top = loader
+        .load_dylib(path("top"))
+        .unwrap()
+        .relocator()
+        .scope([&dep1, &dep2])
+        .dlopen_compat(CompatFlags::NOW | CompatFlags::GLOBAL);
+    assert!(top.is_err());
+}
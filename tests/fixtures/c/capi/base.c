@@ -0,0 +1,2 @@
+/* Depended on by libcapi_top.so */
+int base_value(void) { return 41; }
@@ -0,0 +1,106 @@
+/* A C host driving the C API, called by tests/capi.rs */
+#include "elf_loader.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define CHECK(cond)                                                            \
+    do {                                                                       \
+        if (!(cond)) {                                                         \
+            const char *error = elf_loader_last_error_message();              \
+            fprintf(stderr, "%s:%d: check failed: %s (last error: %s)\n",      \
+                    __FILE__, __LINE__, #cond, error ? error : "none");        \
+            return __LINE__;                                                   \
+        }                                                                      \
+    } while (0)
+
+typedef int (*value_fn)(void);
+
+static int host_offset(void) { return 1; }
+
+static const void *lookup(void *user, const char *name) {
+    if (strcmp(name, "host_offset") == 0) {
+        ++*(int *)user;
+        return (const void *)host_offset;
+    }
+    return NULL;
+}
+
+static unsigned char *read_file(const char *dir, const char *name, size_t *len) {
+    char path[4096];
+    snprintf(path, sizeof(path), "%s/%s", dir, name);
+    FILE *file = fopen(path, "rb");
+    if (!file) {
+        return NULL;
+    }
+    fseek(file, 0, SEEK_END);
+    *len = (size_t)ftell(file);
+    fseek(file, 0, SEEK_SET);
+    unsigned char *bytes = malloc(*len);
+    if (bytes && fread(bytes, 1, *len, file) != *len) {
+        free(bytes);
+        bytes = NULL;
+    }
+    fclose(file);
+    return bytes;
+}
+
+static int load(ElfLoader *loader, const char *dir, const char *name, ElfModule **module) {
+    size_t len;
+    unsigned char *bytes = read_file(dir, name, &len);
+    if (!bytes) {
+        return -1;
+    }
+    int ret = elf_loader_load_dylib(loader, name, bytes, len, module);
+    /* The loader copies the library */
+    free(bytes);
+    return ret;
+}
+
+int capi_host_run(const char *dir) {
+    ElfLoader *loader = elf_loader_new();
+    CHECK(loader != NULL);
+
+    ElfModule *base;
+    CHECK(load(loader, dir, "libcapi_base.so", &base) == 0);
+    CHECK(elf_dylib_relocate(base, NULL, 0, NULL, NULL) == 0);
+    CHECK(elf_dylib_relocate(base, NULL, 0, NULL, NULL) == -1);
+
+    ElfModule *top;
+    CHECK(load(loader, dir, "libcapi_top.so", &top) == 0);
+    CHECK(elf_module_get(top, "top_value") == NULL);
+    CHECK(strstr(elf_loader_last_error_message(), "not relocated") != NULL);
+
+    /* Modules outside of the scope are not searched, even once relocated */
+    ElfModule *unscoped;
+    int unscoped_calls = 0;
+    CHECK(load(loader, dir, "libcapi_top.so", &unscoped) == 0);
+    CHECK(elf_dylib_relocate(unscoped, NULL, 0, lookup, &unscoped_calls) == -1);
+    CHECK(unscoped_calls == 1);
+    CHECK(strstr(elf_loader_last_error_message(), "base_value") != NULL);
+    elf_module_unload(unscoped);
+
+    int calls = 0;
+    const ElfModule *scope[] = {base};
+    CHECK(elf_dylib_relocate(top, scope, 1, lookup, &calls) == 0);
+    CHECK(calls == 1);
+
+    value_fn top_value = (value_fn)elf_module_get(top, "top_value");
+    CHECK(top_value != NULL);
+    CHECK(top_value() == 42);
+
+    CHECK(elf_module_get(base, "missing") == NULL);
+    CHECK(strstr(elf_loader_last_error_message(), "missing") != NULL);
+
+    ElfModule *bad;
+    CHECK(elf_loader_load_dylib(loader, "bad.so", (const unsigned char *)"bad", 3, &bad) == -1);
+    CHECK(elf_loader_last_error_message() != NULL);
+
+    /* The modules outlive the loader, and top keeps base alive */
+    elf_loader_free(loader);
+    elf_module_unload(base);
+    CHECK(top_value() == 42);
+    elf_module_unload(top);
+    return 0;
+}
@@ -0,0 +1,5 @@
+/* Calls into libcapi_base.so and into the host */
+int base_value(void);
+int host_offset(void);
+
+int top_value(void) { return base_value() + host_offset(); }
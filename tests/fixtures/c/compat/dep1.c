@@ -0,0 +1,2 @@
+/* The first dependency of libcompat_top.so */
+__attribute__((weak)) const char *compat_bar(void) { return "dep1 weak"; }
@@ -0,0 +1,2 @@
+/* The second dependency of libcompat_top.so */
+const char *compat_bar(void) { return "dep2"; }
@@ -0,0 +1,2 @@
+/* Opened with RTLD_GLOBAL before libcompat_top.so */
+const char *compat_foo(void) { return "global"; }
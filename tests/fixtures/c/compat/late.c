@@ -0,0 +1,2 @@
+/* Opened with RTLD_GLOBAL after libcompat_lazy.so */
+const char *compat_late(void) { return "late"; }
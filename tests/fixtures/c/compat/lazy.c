@@ -0,0 +1,4 @@
+/* References a function that is only defined once libcompat_late.so is open */
+const char *compat_late(void);
+
+const char *probe_late(void) { return compat_late(); }
@@ -0,0 +1,8 @@
+/* Each probe returns the name of the definition its reference was bound to */
+const char *compat_foo(void) { return "top"; }
+const char *compat_bar(void);
+__attribute__((weak)) const char *compat_missing(void);
+
+const char *probe_foo(void) { return compat_foo(); }
+const char *probe_bar(void) { return compat_bar(); }
+const char *probe_missing(void) { return compat_missing ? compat_missing() : "null"; }
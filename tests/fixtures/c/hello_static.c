@@ -0,0 +1,6 @@
+#include <stdio.h>
+
+int main() {
+  printf("hello from a static executable\n");
+  return 0;
+}
@@ -0,0 +1,5 @@
+/* Reports its constructor and destructor to the host */
+void record_event(const char *event);
+
+__attribute__((constructor)) static void init(void) { record_event("constructor"); }
+__attribute__((destructor)) static void fini(void) { record_event("destructor"); }
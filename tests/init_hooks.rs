@@ -0,0 +1,90 @@
+#![cfg(target_os = "linux")]
+
+use elf_loader::{Error, Loader, image::LoadedDylib};
+use std::{
+    cell::RefCell,
+    ffi::{CStr, c_char},
+};
+
+thread_local! {
+    /// The events of the modules loaded on the current thread, in order
+    static EVENTS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+fn record(event: &'static str) {
+    EVENTS.with_borrow_mut(|events| events.push(event));
+}
+
+extern "C" fn record_event(event: *const c_char) {
+    match unsafe { CStr::from_ptr(event) }.to_str().unwrap() {
+        "constructor" => record("constructor"),
+        "destructor" => record("destructor"),
+        event => panic!("unexpected event {event}"),
+    }
+}
+
+fn load(fail: bool) -> elf_loader::Result<LoadedDylib<()>> {
+    Loader::new()
+        .load_dylib(format!("{}/libinit_hooks.so", env!("TEST_ARTIFACTS")))
+        .unwrap()
+        .relocator()
+        .pre_find_fn(|name| (name == "record_event").then_some(record_event as *const ()))
+        // `pre_find` is not consulted by lazy binding
+        .lazy(false)
+        .pre_init(move |core| {
+            assert!(core.is_initialized());
+            record("pre_init");
+            if fail {
+                return Err(Error::Custom {
+                    msg: "rejected".into(),
+                });
+            }
+            Ok(())
+        })
+        .post_fini(|name| {
+            assert_eq!(name, "libinit_hooks.so");
+            record("post_fini");
+        })
+        .relocate()
+}
+
+#[test]
+fn init_hooks_order() {
+    let lib = load(false).unwrap();
+    EVENTS.with_borrow(|events| assert_eq!(events, &["pre_init", "constructor"]));
+    drop(lib);
+    EVENTS.with_borrow(|events| {
+        assert_eq!(
+            events,
+            &["pre_init", "constructor", "destructor", "post_fini"]
+        )
+    });
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn sysv64_call_conv() {
+    use elf_loader::CallConv;
+
+    // Linux code follows the System V convention, which is also the C one
+    let mut loader = Loader::new();
+    loader.set_call_conv(CallConv::SysV64);
+    let lib = loader
+        .load_dylib(format!("{}/libinit_hooks.so", env!("TEST_ARTIFACTS")))
+        .unwrap()
+        .relocator()
+        .pre_find_fn(|name| (name == "record_event").then_some(record_event as *const ()))
+        .lazy(false)
+        .relocate()
+        .unwrap();
+    drop(lib);
+    EVENTS.with_borrow(|events| assert_eq!(events, &["constructor", "destructor"]));
+}
+
+#[test]
+fn failing_pre_init_skips_init() {
+    let err = load(true).unwrap_err();
+    assert!(matches!(err, Error::Custom { .. }), "{err}");
+    // Neither the constructor nor, once the module is gone, the destructor ran
+    EVENTS.with_borrow(|events| assert_eq!(events, &["pre_init"]));
+}
@@ -1,13 +1,17 @@
 use elf_loader::{
-    Loader,
+    Error, Loader, RelocationTable,
     arch::{
-        REL_COPY, REL_DTPOFF, REL_GOT, REL_IRELATIVE, REL_JUMP_SLOT, REL_RELATIVE, REL_SYMBOLIC,
+        REL_COPY, REL_DTPMOD, REL_DTPOFF, REL_GOT, REL_IRELATIVE, REL_JUMP_SLOT, REL_RELATIVE,
+        REL_SYMBOLIC, REL_TPOFF, TLS_DTV_OFFSET,
     },
-    input::ElfBinary,
+    input::{ElfBinary, ElfPremapped},
+    relocation::{GlobalScope, ParallelExecutor, SymbolLookup},
+    tls::{TlsAllocator, TlsInfo},
 };
 use gen_elf::{Arch, DylibWriter, ElfWriterConfig, ObjectWriter, RelocEntry, SymbolDesc};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 const EXTERNAL_FUNC_NAME: &str = "external_func";
 const EXTERNAL_FUNC_NAME2: &str = "external_func2";
@@ -115,18 +119,52 @@ fn get_relocs_dynamic() -> Vec<RelocEntry> {
 
 #[test]
 fn dynamic_linking() {
-    run_dynamic_linking(false);
+    run_dynamic_linking(false, None);
 }
 
 #[test]
 fn dynamic_linking_with_lazy() {
-    run_dynamic_linking(true);
+    run_dynamic_linking(true, None);
 }
 
-fn run_dynamic_linking(is_lazy: bool) {
+#[test]
+fn dynamic_linking_with_alt_rel_format() {
+    // Emit the relocation format that is not native to the current architecture
+    let is_rela = !Arch::current().is_rela();
+    run_dynamic_linking(false, Some(is_rela));
+    if cfg!(target_arch = "x86") {
+        // The i386 PLT entries can only locate `Elf32_Rel` entries
+        let relocs = [RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT)];
+        let symbols = [SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME)];
+        let output = DylibWriter::with_config(
+            Arch::current(),
+            ElfWriterConfig::default().with_rela(is_rela),
+        )
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+        let (_, symbol_lookup) = get_symbol_lookup();
+        let err = Loader::new()
+            .load_dylib(ElfBinary::new("libaltlazy.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .lazy(true)
+            .lazy_scope(symbol_lookup)
+            .relocate()
+            .expect_err("Lazy binding of a converted table should fail");
+        assert!(err.to_string().contains("bound lazily"), "{err}");
+    } else {
+        run_dynamic_linking(true, Some(is_rela));
+        run_lazy_bind_first_call(ElfWriterConfig::default().with_rela(is_rela));
+    }
+}
+
+fn run_dynamic_linking(is_lazy: bool, use_rela: Option<bool>) {
     let arch = Arch::current();
     // 1. Generate helper library that defines the symbol to be copied
-    let config = ElfWriterConfig::default().with_ifunc_resolver_val(IFUNC_RESOLVER_VALUE);
+    let mut config = ElfWriterConfig::default().with_ifunc_resolver_val(IFUNC_RESOLVER_VALUE);
+    if let Some(is_rela) = use_rela {
+        config = config.with_rela(is_rela);
+    }
     let helper_writer = DylibWriter::with_config(arch, config.clone());
     let helper_symbols = vec![
         SymbolDesc::global_object(
@@ -487,3 +525,4478 @@ fn static_linking() {
         }
     }
 }
+
+#[test]
+fn object_symbol_visibility() {
+    use elf_loader::image::Visibility;
+
+    const HELPER_NAME: &str = "helper_init";
+    const RENAMED_NAME: &str = "plugin_helper_init";
+
+    let arch = Arch::current();
+    if arch != Arch::X86_64 {
+        println!("Skipping test for unsupported architecture: {:?}", arch);
+        return;
+    }
+
+    // Both plugins define `helper_init` and store its address at offset 0x10
+    let plugin = ObjectWriter::new(arch)
+        .write(
+            &[SymbolDesc::global_object(HELPER_NAME, &[0u8; 32])],
+            &[RelocEntry::with_name(HELPER_NAME, REL_SYMBOLIC)],
+        )
+        .expect("Failed to generate object");
+    let user = ObjectWriter::new(arch)
+        .write(
+            &[
+                SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 32]),
+                SymbolDesc::undefined_object(HELPER_NAME),
+            ],
+            &[RelocEntry::with_name(HELPER_NAME, REL_SYMBOLIC)],
+        )
+        .expect("Failed to generate object");
+    let slot =
+        |addr: usize| unsafe { read_u64((addr + plugin.reloc_offsets[0] as usize) as *const u8) }
+            as usize;
+
+    let mut loader = Loader::new();
+    let global = loader
+        .load_object(ElfBinary::new("global.o", &plugin.data))
+        .expect("Failed to load object")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate object");
+    let global_addr = unsafe { global.get::<u8>(HELPER_NAME).unwrap().into_raw() } as usize;
+
+    let local = loader
+        .load_object(ElfBinary::new("local.o", &plugin.data))
+        .expect("Failed to load object")
+        .relocator()
+        .visibility(|name| match name {
+            HELPER_NAME => Visibility::Local,
+            _ => Visibility::Global,
+        })
+        .scope([&global])
+        .relocate()
+        .expect("Failed to relocate object");
+    assert!(unsafe { local.get::<u8>(HELPER_NAME) }.is_none());
+
+    // The renamed definition is still the one its own object refers to
+    let renamed = loader
+        .load_object(ElfBinary::new("renamed.o", &plugin.data))
+        .expect("Failed to load object")
+        .relocator()
+        .visibility(|name| Visibility::Rename(format!("plugin_{name}")))
+        .scope([&global])
+        .relocate()
+        .expect("Failed to relocate object");
+    assert!(unsafe { renamed.get::<u8>(HELPER_NAME) }.is_none());
+    let renamed_addr = unsafe { renamed.get::<u8>(RENAMED_NAME).unwrap().into_raw() } as usize;
+    assert_ne!(renamed_addr, global_addr);
+    assert_eq!(slot(renamed_addr), renamed_addr);
+
+    // Neither hidden definition is used to resolve other modules
+    let user = loader
+        .load_object(ElfBinary::new("user.o", &user.data))
+        .expect("Failed to load object")
+        .relocator()
+        .scope([&local, &renamed, &global])
+        .relocate()
+        .expect("Failed to relocate object");
+    let user_addr = unsafe { user.get::<u8>(LOCAL_VAR_NAME).unwrap().into_raw() } as usize;
+    assert_eq!(slot(user_addr), global_addr);
+}
+
+#[test]
+fn object_constructors() {
+    use std::sync::Mutex;
+
+    static CALLS: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+    macro_rules! recorders {
+        ($($name:ident),*) => {
+            [$((stringify!($name), {
+                extern "C" fn $name() {
+                    CALLS.lock().unwrap().push(stringify!($name));
+                }
+                $name as *const ()
+            })),*]
+        };
+    }
+
+    let arch = Arch::current();
+    if arch != Arch::X86_64 {
+        println!("Skipping test for unsupported architecture: {:?}", arch);
+        return;
+    }
+
+    let funcs = recorders!(
+        init_a, init_b, init_early, ctor_x, ctor_y, fini_a, fini_b, dtor_x, dtor_y
+    );
+    let symbols: Vec<_> = funcs
+        .iter()
+        .map(|(name, _)| SymbolDesc::undefined_func(*name))
+        .chain([SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8])])
+        .collect();
+    let output = ObjectWriter::new(arch)
+        .with_fn_array(".init_array", &["init_a", "init_b"])
+        .with_fn_array(".init_array.00100", &["init_early"])
+        .with_fn_array(".ctors", &["ctor_x", "ctor_y"])
+        .with_fn_array(".fini_array", &["fini_a", "fini_b"])
+        .with_fn_array(".dtors", &["dtor_x", "dtor_y"])
+        .write(&symbols, &[])
+        .expect("Failed to generate object");
+
+    let raw = Loader::new()
+        .load_object(ElfBinary::new("ctors.o", &output.data))
+        .expect("Failed to load object");
+    // Nothing runs for an object that is never relocated
+    drop(raw);
+    assert!(CALLS.lock().unwrap().is_empty());
+
+    let obj = Loader::new()
+        .load_object(ElfBinary::new("ctors.o", &output.data))
+        .expect("Failed to load object")
+        .relocator()
+        .pre_find_fn(|name| funcs.iter().find(|(func, _)| *func == name).map(|f| f.1))
+        .relocate()
+        .expect("Failed to relocate object");
+    assert_eq!(
+        *CALLS.lock().unwrap(),
+        ["init_early", "init_a", "init_b", "ctor_y", "ctor_x"]
+    );
+
+    CALLS.lock().unwrap().clear();
+    drop(obj);
+    assert_eq!(
+        *CALLS.lock().unwrap(),
+        ["dtor_x", "dtor_y", "fini_b", "fini_a"]
+    );
+}
+
+#[test]
+fn global_scope() {
+    let arch = Arch::current();
+    let writer = DylibWriter::new(arch);
+    let symbols = vec![SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8])];
+    let output = writer
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+
+    let scope = GlobalScope::new();
+    let mut loader = Loader::new();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libscope.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    scope.register(&lib);
+
+    let expected = unsafe { lib.get::<()>(LOCAL_VAR_NAME).unwrap().into_raw() };
+    assert_eq!(scope.lookup(LOCAL_VAR_NAME), Some(expected));
+    assert_eq!(scope.snapshot().len(), 1);
+
+    // The scope only holds weak references
+    drop(lib);
+    assert_eq!(scope.lookup(LOCAL_VAR_NAME), None);
+    assert!(scope.snapshot().is_empty());
+    assert!(!scope.unregister("libscope.so"));
+}
+
+#[test]
+fn weak_symbols() {
+    use gen_elf::SymbolScope;
+
+    const WEAK_NAME: &str = "weak_var";
+    const GMON_NAME: &str = "__gmon_start__";
+
+    let arch = Arch::current();
+    let define = |data: &[u8], scope: SymbolScope| {
+        DylibWriter::new(arch)
+            .write(
+                &[],
+                &[SymbolDesc::global_object(WEAK_NAME, data).with_scope(scope)],
+            )
+            .expect("Failed to generate ELF")
+    };
+    let weak_output = define(&[1; 8], SymbolScope::Weak);
+    let strong_output = define(&[2; 8], SymbolScope::Global);
+    let user_output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(WEAK_NAME, REL_GOT),
+                RelocEntry::with_name(GMON_NAME, REL_GOT),
+            ],
+            &[
+                SymbolDesc::undefined_object(WEAK_NAME),
+                SymbolDesc::undefined_func(GMON_NAME).with_scope(SymbolScope::Weak),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let mut load = |name: &str, data: &[u8]| {
+        loader
+            .load_dylib(ElfBinary::new(name, data))
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let weak = load("libweak.so", &weak_output.data);
+    let strong = load("libstrong.so", &strong_output.data);
+    let addr = |lib: &elf_loader::image::LoadedDylib<()>| unsafe {
+        lib.get::<u8>(WEAK_NAME).unwrap().into_raw() as usize
+    };
+    let (weak_addr, strong_addr) = (addr(&weak), addr(&strong));
+
+    for (scope, expected) in [
+        (vec![&weak, &strong], strong_addr),
+        (vec![&strong, &weak], strong_addr),
+        (vec![&weak], weak_addr),
+    ] {
+        let user = loader
+            .load_dylib(ElfBinary::new("libuser.so", &user_output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .scope(scope)
+            .relocate()
+            .expect("Failed to relocate library");
+        let slot = |idx: usize| unsafe {
+            ((user.base() + user_output.relocations[idx].vaddr as usize) as *const usize).read()
+        };
+        assert_eq!(slot(0), expected);
+        // Nothing defines `__gmon_start__`, so it resolves to null
+        assert_eq!(slot(1), 0);
+    }
+
+    // The fallback lookup still gets a chance before a weak reference becomes null
+    static GMON: u8 = 0;
+    let user = loader
+        .load_dylib(ElfBinary::new("libuser.so", &user_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&strong])
+        .post_find_fn(|name| (name == GMON_NAME).then_some(&raw const GMON as *const ()))
+        .relocate()
+        .expect("Failed to relocate library");
+    let slot = unsafe {
+        ((user.base() + user_output.relocations[1].vaddr as usize) as *const usize).read()
+    };
+    assert_eq!(slot, &raw const GMON as usize);
+}
+
+#[test]
+fn symbol_visibility() {
+    use gen_elf::SymbolVisibility;
+
+    const HIDDEN_NAME: &str = "hidden_var";
+    const INTERNAL_NAME: &str = "internal_var";
+    const PROTECTED_NAME: &str = "protected_var";
+
+    let arch = Arch::current();
+    let provider_output = DylibWriter::new(arch)
+        .write(
+            &[],
+            &[
+                SymbolDesc::global_object(HIDDEN_NAME, &[1; 8])
+                    .with_visibility(SymbolVisibility::Hidden),
+                SymbolDesc::global_object(INTERNAL_NAME, &[2; 8])
+                    .with_visibility(SymbolVisibility::Internal),
+                SymbolDesc::global_object(PROTECTED_NAME, &[3; 8])
+                    .with_visibility(SymbolVisibility::Protected),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let fallback_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(HIDDEN_NAME, &[4; 8])])
+        .expect("Failed to generate ELF");
+    let user_output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(HIDDEN_NAME, REL_GOT),
+                RelocEntry::with_name(PROTECTED_NAME, REL_GOT),
+            ],
+            &[
+                SymbolDesc::undefined_object(HIDDEN_NAME),
+                SymbolDesc::undefined_object(PROTECTED_NAME),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let mut load = |name: &str, data: &[u8]| {
+        loader
+            .load_dylib(ElfBinary::new(name, data))
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let provider = load("libprovider.so", &provider_output.data);
+    let fallback = load("libfallback.so", &fallback_output.data);
+
+    // Hidden and internal definitions are only reachable through `get_any`
+    unsafe {
+        assert!(provider.get::<u64>(HIDDEN_NAME).is_none());
+        assert!(provider.get::<u64>(INTERNAL_NAME).is_none());
+        assert_eq!(**provider.get_any::<*const u8>(HIDDEN_NAME).unwrap(), 1);
+        assert_eq!(**provider.get_any::<*const u8>(INTERNAL_NAME).unwrap(), 2);
+        assert!(!provider.get_any::<u64>(HIDDEN_NAME).unwrap().is_protected());
+        let protected = provider.get::<*const u8>(PROTECTED_NAME).unwrap();
+        assert!(protected.is_protected());
+        assert_eq!(**protected, 3);
+    }
+    let exported: Vec<_> = provider.exported_symbols().map(|(name, _)| name).collect();
+    assert!(exported.contains(&PROTECTED_NAME));
+    assert!(!exported.contains(&HIDDEN_NAME));
+    assert!(!exported.contains(&INTERNAL_NAME));
+
+    // Relocation skips the hidden definition of the first module in the scope
+    let user = loader
+        .load_dylib(ElfBinary::new("libuser.so", &user_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&provider, &fallback])
+        .relocate()
+        .expect("Failed to relocate library");
+    let slot = |idx: usize| unsafe {
+        ((user.base() + user_output.relocations[idx].vaddr as usize) as *const usize).read()
+    };
+    let addr = |lib: &elf_loader::image::LoadedDylib<()>, name: &str| unsafe {
+        lib.get_any::<u8>(name).unwrap().into_raw() as usize
+    };
+    assert_eq!(slot(0), addr(&fallback, HIDDEN_NAME));
+    assert_eq!(slot(1), addr(&provider, PROTECTED_NAME));
+
+    // Without another definition, the hidden symbol is undefined
+    let result = loader
+        .load_dylib(ElfBinary::new("libuser.so", &user_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&provider])
+        .relocate();
+    assert!(result.is_err());
+}
+
+#[test]
+fn symbol_at_address() {
+    let arch = Arch::current();
+    let writer = DylibWriter::new(arch);
+    // `outer` is declared larger than its contents, so the zero-size `label`
+    // placed after them lies inside it
+    let symbols = vec![
+        SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 16]),
+        SymbolDesc::global_object(COPY_VAR_NAME, &[0u8; 8]),
+        SymbolDesc::global_object("outer", &[0u8; 8]).with_size(64),
+        SymbolDesc::global_object("label", &[0u8; 8]).with_size(0),
+    ];
+    let output = writer
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libaddr.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let addr = unsafe { lib.get::<()>(LOCAL_VAR_NAME).unwrap().into_raw() } as usize;
+    assert!(lib.contains_addr(addr));
+    assert_eq!(lib.symbol_at(addr), Some((LOCAL_VAR_NAME, 0)));
+    assert_eq!(lib.symbol_at(addr + 5), Some((LOCAL_VAR_NAME, 5)));
+
+    // A zero-size symbol is only a fallback for addresses no sized symbol covers
+    let outer = unsafe { lib.get::<()>("outer").unwrap().into_raw() } as usize;
+    let label = unsafe { lib.get::<()>("label").unwrap().into_raw() } as usize;
+    assert!((outer + 1..outer + 56).contains(&label));
+    assert_eq!(lib.symbol_at(label + 4), Some(("outer", label + 4 - outer)));
+    assert_eq!(
+        lib.symbol_at(outer + 64),
+        Some(("label", outer + 64 - label))
+    );
+
+    let end = lib.base() + lib.mapped_len();
+    assert!(!lib.contains_addr(end));
+    assert_eq!(lib.symbol_at(end), None);
+}
+
+#[test]
+fn batch_lookup() {
+    use elf_loader::elf::PreparedSymbol;
+
+    let arch = Arch::current();
+    let names: Vec<String> = (0..64).map(|i| format!("sym_{i}")).collect();
+    let symbols: Vec<_> = names
+        .iter()
+        .map(|name| SymbolDesc::global_object(name.as_str(), &[0u8; 8]))
+        .collect();
+    let output = DylibWriter::new(arch)
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let mut load = |name: &str| {
+        loader
+            .load_dylib(ElfBinary::new(name, &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let lib1 = load("libbatch1.so");
+    let lib2 = load("libbatch2.so");
+
+    let mut queries: Vec<&str> = names.iter().rev().map(String::as_str).collect();
+    queries.push(EXTERNAL_VAR_NAME);
+    let addrs = lib1.lookup_batch(&queries);
+    assert_eq!(addrs.len(), queries.len());
+    for (name, addr) in queries.iter().zip(&addrs) {
+        let expected = unsafe { lib1.get::<()>(name) }.map(|sym| sym.into_raw());
+        assert_eq!(*addr, expected, "{name}");
+    }
+    assert_eq!(addrs.last(), Some(&None));
+
+    // One prepared symbol resolves in every module
+    let prepared = PreparedSymbol::new("sym_7");
+    assert_eq!(prepared.name(), "sym_7");
+    for lib in [&lib1, &lib2] {
+        let found = unsafe { lib.lookup_prepared::<()>(&prepared) }.map(|sym| sym.into_raw());
+        assert_eq!(
+            found,
+            unsafe { lib.get::<()>("sym_7") }.map(|sym| sym.into_raw())
+        );
+        assert!(found.is_some());
+    }
+    assert_ne!(
+        unsafe { lib1.lookup_prepared::<()>(&prepared) }.map(|sym| sym.into_raw()),
+        unsafe { lib2.lookup_prepared::<()>(&prepared) }.map(|sym| sym.into_raw())
+    );
+    let missing = PreparedSymbol::new(EXTERNAL_VAR_NAME);
+    assert!(unsafe { lib1.lookup_prepared::<()>(&missing) }.is_none());
+}
+
+#[test]
+fn symbol_iteration() {
+    use gen_elf::SymbolScope;
+    use object::{Object, ObjectSymbol};
+    use std::collections::BTreeSet;
+
+    // The same view of the dynamic symbol table as `readelf --dyn-syms`
+    let dyn_syms = |data: &[u8]| -> BTreeSet<(String, u64, bool)> {
+        let file = object::File::parse(data).expect("Failed to parse ELF");
+        file.dynamic_symbols()
+            .map(|sym| {
+                (
+                    sym.name().unwrap().to_owned(),
+                    sym.address(),
+                    sym.is_undefined(),
+                )
+            })
+            .collect()
+    };
+
+    let arch = Arch::current();
+    let symbols = vec![
+        SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8]),
+        SymbolDesc::global_object(COPY_VAR_NAME, &[0u8; 8]).with_scope(SymbolScope::Weak),
+        SymbolDesc::undefined_object(EXTERNAL_VAR_NAME),
+    ];
+    let output = DylibWriter::new(arch)
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libiter.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let iterated: BTreeSet<_> = lib
+        .symtab()
+        .iter()
+        .map(|(name, sym)| (name.to_owned(), sym.st_value() as u64, sym.is_undef()))
+        .collect();
+    assert_eq!(iterated, dyn_syms(&output.data));
+
+    let exported: BTreeSet<_> = lib.exported_symbols().map(|(name, _)| name).collect();
+    assert!(exported.contains(LOCAL_VAR_NAME));
+    assert!(exported.contains(COPY_VAR_NAME));
+    assert!(!exported.contains(EXTERNAL_VAR_NAME));
+
+    // The vDSO is looked up through its GNU hash table
+    #[cfg(target_os = "linux")]
+    {
+        use elf_loader::image::LoadedDylib;
+
+        let addr = unsafe { libc::getauxval(libc::AT_SYSINFO_EHDR) } as usize;
+        if addr != 0 {
+            let vdso = unsafe { LoadedDylib::from_raw_mapped("linux-vdso.so.1", addr) }
+                .expect("Failed to wrap the vDSO");
+            let image = unsafe { std::slice::from_raw_parts(addr as *const u8, vdso.mapped_len()) };
+            let iterated: BTreeSet<_> = vdso
+                .symtab()
+                .iter()
+                .map(|(name, sym)| (name.to_owned(), sym.st_value() as u64, sym.is_undef()))
+                .collect();
+            assert_eq!(iterated, dyn_syms(image));
+        }
+    }
+}
+
+#[test]
+fn try_unload() {
+    let arch = Arch::current();
+    let liba_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_func(EXTERNAL_FUNC_NAME, &[0xc3])])
+        .expect("Failed to generate ELF");
+    let libb_output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT)],
+            &[SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME)],
+        )
+        .expect("Failed to generate ELF");
+
+    // Records the finalization handler, then the module it ran for
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let fini_events = events.clone();
+    let mut loader = Loader::new();
+    loader.with_fini(Arc::new(move |_, _| {
+        fini_events.lock().unwrap().push("fini".to_string());
+    }));
+    let post_fini = |events: &Arc<std::sync::Mutex<Vec<String>>>| {
+        let events = events.clone();
+        move |name: &str| events.lock().unwrap().push(name.to_string())
+    };
+    let fini_count = || events.lock().unwrap().len() / 2;
+
+    let liba = loader
+        .load_dylib(ElfBinary::new("liba.so", &liba_output.data))
+        .expect("Failed to load liba")
+        .relocator()
+        .post_fini(post_fini(&events))
+        .relocate()
+        .expect("Failed to relocate liba");
+    assert_eq!(liba.strong_count(), 1);
+
+    // libb keeps liba alive through its lazy scope
+    let lazy_lib = (*liba).clone();
+    let libb = loader
+        .load_dylib(ElfBinary::new("libb.so", &libb_output.data))
+        .expect("Failed to load libb")
+        .relocator()
+        .lazy(true)
+        .lazy_scope(move |name: &str| unsafe { lazy_lib.get::<()>(name).map(|sym| sym.into_raw()) })
+        .post_fini(post_fini(&events))
+        .relocate()
+        .expect("Failed to relocate libb");
+    assert_eq!(liba.strong_count(), 2);
+
+    let liba = liba
+        .try_unload()
+        .expect_err("liba is still referenced by libb");
+    assert_eq!(fini_count(), 0);
+
+    libb.try_unload().expect("Failed to unload libb");
+    assert_eq!(fini_count(), 1);
+    assert_eq!(liba.strong_count(), 1);
+
+    let weak = unsafe { liba.core_ref() }.downgrade();
+    liba.try_unload().expect("Failed to unload liba");
+    assert!(weak.upgrade().is_none());
+    // The finalizers of each library ran once, the dependent library first
+    assert_eq!(
+        *events.lock().unwrap(),
+        ["fini", "libb.so", "fini", "liba.so"]
+    );
+}
+
+#[test]
+fn owned_symbol() {
+    use elf_loader::image::OwnedSymbol;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<OwnedSymbol<extern "C" fn()>>();
+
+    const VALUE: u64 = 0x1122_3344_5566_7788;
+
+    let output = DylibWriter::new(Arch::current())
+        .write(
+            &[],
+            &[SymbolDesc::global_object(
+                LOCAL_VAR_NAME,
+                &VALUE.to_ne_bytes(),
+            )],
+        )
+        .expect("Failed to generate ELF");
+    let fini_count = Arc::new(AtomicUsize::new(0));
+    let counter = fini_count.clone();
+    let mut loader = Loader::new();
+    loader.with_fini(Arc::new(move |_, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    }));
+    let lib = loader
+        .load_dylib(ElfBinary::new("libowned.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let owned: OwnedSymbol<*const u64> =
+        unsafe { lib.get_owned(LOCAL_VAR_NAME) }.expect("missing symbol");
+    let converted = unsafe {
+        lib.get::<*const u64>(LOCAL_VAR_NAME)
+            .unwrap()
+            .into_owned(&lib)
+    };
+    assert!(unsafe { lib.get_owned::<*const u64>("missing") }.is_none());
+    drop(lib);
+
+    // The symbols keep the library mapped after the last handle is gone
+    let copy = owned.clone();
+    drop(owned);
+    assert_eq!(unsafe { **copy }, VALUE);
+    drop(copy);
+    assert_eq!(converted.module().name(), "libowned.so");
+    assert_eq!(unsafe { **converted }, VALUE);
+    assert!(loader.mapped_bytes() != 0);
+    assert_eq!(fini_count.load(Ordering::SeqCst), 0);
+
+    drop(converted);
+    assert_eq!(loader.mapped_bytes(), 0);
+    assert_eq!(fini_count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn module_group_fini_order() {
+    use elf_loader::image::ModuleGroup;
+    use std::sync::Mutex;
+
+    let arch = Arch::current();
+    let base_output = DylibWriter::new(arch)
+        .write(
+            &[],
+            &[SymbolDesc::global_object(EXTERNAL_VAR_NAME, &[0; 8])],
+        )
+        .expect("Failed to generate ELF");
+    let mid_output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(EXTERNAL_VAR_NAME, REL_GOT)],
+            &[
+                SymbolDesc::undefined_object(EXTERNAL_VAR_NAME),
+                SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8]),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let top_output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(LOCAL_VAR_NAME, REL_GOT)],
+            &[SymbolDesc::undefined_object(LOCAL_VAR_NAME)],
+        )
+        .expect("Failed to generate ELF");
+
+    // One loader per library, so that the fini handler knows which one runs
+    let finalized = Arc::new(Mutex::new(Vec::new()));
+    let loader = |name: &'static str| {
+        let finalized = finalized.clone();
+        let mut loader = Loader::new();
+        loader.with_fini(Arc::new(move |_, _| finalized.lock().unwrap().push(name)));
+        loader
+    };
+    let base = loader("libbase.so")
+        .load_dylib(ElfBinary::new("libbase.so", &base_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let mid = loader("libmid.so")
+        .load_dylib(ElfBinary::new("libmid.so", &mid_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&base])
+        .relocate()
+        .expect("Failed to relocate library");
+    let top = loader("libtop.so")
+        .load_dylib(ElfBinary::new("libtop.so", &top_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&mid])
+        .relocate()
+        .expect("Failed to relocate library");
+
+    // Insertion order differs from the dependency order
+    let mut group = ModuleGroup::new();
+    assert_eq!(group.insert(&top), 0);
+    assert_eq!(group.insert(&base), 1);
+    assert_eq!(group.insert(&mid), 2);
+    assert_eq!(group.insert(&base), 1);
+    assert_eq!(group.fini_order(), [0, 2, 1]);
+
+    group.fini_all();
+    assert_eq!(
+        *finalized.lock().unwrap(),
+        ["libtop.so", "libmid.so", "libbase.so"]
+    );
+    // Finalizers run only once, even when the group and the libraries are dropped
+    drop(group);
+    drop((top, mid, base));
+    assert_eq!(finalized.lock().unwrap().len(), 3);
+
+    // A cycle falls back to reverse insertion order
+    let libs: Vec<_> = ["liba.so", "libb.so", "libc.so"]
+        .into_iter()
+        .map(|name| {
+            loader(name)
+                .load_dylib(ElfBinary::new(name, &base_output.data))
+                .expect("Failed to load library")
+                .relocator()
+                .relocate()
+                .expect("Failed to relocate library")
+        })
+        .collect();
+    let mut group = ModuleGroup::new();
+    for lib in &libs {
+        group.insert(lib);
+    }
+    group.add_dependency(0, 1);
+    group.add_dependency(1, 0);
+    assert_eq!(group.fini_order(), [2, 1, 0]);
+    // The cycle is still finalized before the module it depends on
+    group.add_dependency(0, 2);
+    assert_eq!(group.fini_order(), [1, 0, 2]);
+
+    finalized.lock().unwrap().clear();
+    drop(group);
+    assert_eq!(
+        *finalized.lock().unwrap(),
+        ["libb.so", "liba.so", "libc.so"]
+    );
+}
+
+#[test]
+fn init_params() {
+    use elf_loader::InitParams;
+    use std::sync::Mutex;
+
+    let arch = Arch::current();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+
+    let args = [c"prog".as_ptr(), c"--flag".as_ptr(), std::ptr::null()];
+    let env = [c"KEY=value".as_ptr(), std::ptr::null()];
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let mut loader = Loader::new();
+    let (init_calls, fini_calls) = (calls.clone(), calls.clone());
+    loader
+        .set_init_params(InitParams {
+            argc: 2,
+            argv: args.as_ptr(),
+            envp: env.as_ptr(),
+        })
+        .set_init(Arc::new(move |params: &InitParams, _, _| {
+            init_calls.lock().unwrap().push((
+                "init",
+                params.argc,
+                params.argv as usize,
+                params.envp as usize,
+            ));
+        }))
+        .set_fini(Arc::new(move |params: &InitParams, _, _| {
+            fini_calls.lock().unwrap().push((
+                "fini",
+                params.argc,
+                params.argv as usize,
+                params.envp as usize,
+            ));
+        }));
+
+    let lib = loader
+        .load_dylib(ElfBinary::new("libinit.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    drop(lib);
+
+    let expected = |name| (name, 2, args.as_ptr() as usize, env.as_ptr() as usize);
+    assert_eq!(*calls.lock().unwrap(), [expected("init"), expected("fini")]);
+}
+
+#[test]
+fn dynamic_flags_1() {
+    use object::elf::{DF_1_NODELETE, DF_1_NOW};
+
+    let arch = Arch::current();
+    let relocs = [RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT)];
+    let symbols = [SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME)];
+    let mut loader = Loader::new();
+
+    // Without DT_FLAGS_1 the library defaults to lazy binding
+    let output = DylibWriter::new(arch)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+    let lib = loader
+        .load_dylib(ElfBinary::new("liblazy.so", &output.data))
+        .expect("Failed to load library");
+    assert!(lib.is_lazy());
+    assert_eq!(lib.flags_1(), 0);
+    assert!(!lib.is_nodelete());
+
+    // `-z now -z nodelete` only sets DT_FLAGS_1
+    let config = ElfWriterConfig::default().with_flags_1((DF_1_NOW | DF_1_NODELETE) as u64);
+    let output = DylibWriter::with_config(arch, config)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+    let lib = loader
+        .load_dylib(ElfBinary::new("libnow.so", &output.data))
+        .expect("Failed to load library");
+    assert!(!lib.is_lazy());
+    assert_eq!(lib.flags_1(), (DF_1_NOW | DF_1_NODELETE) as usize);
+    assert!(lib.is_nodelete());
+
+    let (_, symbol_lookup) = get_symbol_lookup();
+    let lib = lib
+        .relocator()
+        .pre_find(symbol_lookup)
+        .relocate()
+        .expect("Failed to relocate library");
+    assert!(lib.is_nodelete());
+}
+
+#[test]
+fn symbolic_binding() {
+    use object::elf::DF_SYMBOLIC;
+
+    const SHARED_VAR: &str = "shared_var";
+    const SHARED_FUNC: &str = "shared_func";
+    static PRE_FIND_VAR: u8 = 0;
+
+    let arch = Arch::current();
+    // `ret` on x86_64, only called through the lazy binding path below
+    let symbols = [
+        SymbolDesc::global_object(SHARED_VAR, &[1; 8]),
+        SymbolDesc::global_func(SHARED_FUNC, &[0xc3]),
+    ];
+    let relocs = [
+        RelocEntry::with_name(SHARED_VAR, REL_GOT),
+        RelocEntry::with_name(SHARED_FUNC, REL_JUMP_SLOT),
+    ];
+    let host_output = DylibWriter::new(arch)
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    let host = loader
+        .load_dylib(ElfBinary::new("libhost.so", &host_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let host_func = unsafe { host.get::<()>(SHARED_FUNC).unwrap().into_raw() as usize };
+
+    for symbolic in [false, true] {
+        let config = if symbolic {
+            ElfWriterConfig::default().with_flags(DF_SYMBOLIC as u64)
+        } else {
+            ElfWriterConfig::default()
+        };
+        let output = DylibWriter::with_config(arch, config)
+            .write(&relocs, &symbols)
+            .expect("Failed to generate ELF");
+        let raw = loader
+            .load_dylib(ElfBinary::new("libplugin.so", &output.data))
+            .expect("Failed to load library");
+        assert_eq!(raw.is_symbolic(), symbolic);
+        let plugin = raw
+            .relocator()
+            .scope([&host])
+            .pre_find_fn(|name| (name == SHARED_VAR).then_some(&raw const PRE_FIND_VAR as _))
+            .lazy(false)
+            .relocate()
+            .expect("Failed to relocate library");
+        assert_eq!(plugin.is_symbolic(), symbolic);
+
+        let slot = |idx: usize| unsafe {
+            ((plugin.base() + output.relocations[idx].vaddr as usize) as *const usize).read()
+        };
+        let own = |name: &str| unsafe { plugin.get::<()>(name).unwrap().into_raw() as usize };
+        if symbolic {
+            assert_eq!(slot(0), own(SHARED_VAR));
+            assert_eq!(slot(1), own(SHARED_FUNC));
+        } else {
+            assert_eq!(slot(0), &raw const PRE_FIND_VAR as usize);
+            assert_eq!(slot(1), host_func);
+        }
+    }
+
+    // Lazy binding also prefers the definition of a symbolic object
+    #[cfg(target_arch = "x86_64")]
+    {
+        let config = ElfWriterConfig::default().with_flags(DF_SYMBOLIC as u64);
+        let output = DylibWriter::with_config(arch, config)
+            .write(&relocs, &symbols)
+            .expect("Failed to generate ELF");
+        let plugin = loader
+            .load_dylib(ElfBinary::new("libplugin.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .scope([&host])
+            .lazy(true)
+            .lazy_scope(move |name: &str| (name == SHARED_FUNC).then_some(host_func as _))
+            .relocate()
+            .expect("Failed to relocate library");
+        let helper: extern "C" fn() = unsafe {
+            core::mem::transmute(
+                plugin
+                    .get::<()>(&format!("{SHARED_FUNC}@helper"))
+                    .expect("Failed to get helper function")
+                    .into_raw(),
+            )
+        };
+        helper();
+        let slot = unsafe {
+            ((plugin.base() + output.relocations[1].vaddr as usize) as *const usize).read()
+        };
+        let own = unsafe { plugin.get::<()>(SHARED_FUNC).unwrap().into_raw() };
+        assert_eq!(slot, own as usize);
+    }
+}
+
+#[test]
+fn lookup_policy() {
+    use elf_loader::relocation::{LookupOrder, LookupPolicy, RelocKind};
+
+    const SHARED_VAR: &str = "shared_var";
+    const SHARED_FUNC: &str = "shared_func";
+    static PRE_FIND_VAR: u8 = 0;
+
+    let arch = Arch::current();
+    let host_output = DylibWriter::new(arch)
+        .write(
+            &[],
+            &[
+                SymbolDesc::global_object(SHARED_VAR, &[1; 8]),
+                SymbolDesc::global_func(SHARED_FUNC, &[0xc3]),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(SHARED_VAR, REL_SYMBOLIC),
+                RelocEntry::with_name(SHARED_FUNC, REL_GOT),
+                RelocEntry::with_name(SHARED_FUNC, REL_JUMP_SLOT),
+            ],
+            &[
+                SymbolDesc::undefined_object(SHARED_VAR),
+                SymbolDesc::undefined_func(SHARED_FUNC),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let host = loader
+        .load_dylib(ElfBinary::new("libhost.so", &host_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let host_var = unsafe { host.get::<()>(SHARED_VAR).unwrap().into_raw() as usize };
+    let host_func = unsafe { host.get::<()>(SHARED_FUNC).unwrap().into_raw() as usize };
+    let pre_find = &raw const PRE_FIND_VAR as usize;
+
+    // Functions are interposed, data resolves from the loaded modules
+    let split = LookupPolicy::from(|kind: RelocKind, _: &str| match kind {
+        RelocKind::Func => LookupOrder::PreFindFirst,
+        _ => LookupOrder::ScopeFirst,
+    });
+    let cases = [
+        (None, [pre_find, pre_find, pre_find]),
+        (
+            Some(LookupPolicy::PreferPreFind),
+            [pre_find, pre_find, pre_find],
+        ),
+        (
+            Some(LookupPolicy::PreferScope),
+            [host_var, host_func, host_func],
+        ),
+        (Some(split), [host_var, pre_find, pre_find]),
+    ];
+    for (policy, expected) in cases {
+        let relocator = loader
+            .load_dylib(ElfBinary::new("libplugin.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .scope([&host])
+            .pre_find_fn(|_| Some(&raw const PRE_FIND_VAR as _))
+            .lazy(false);
+        let relocator = match policy {
+            Some(policy) => relocator.lookup_policy(policy),
+            None => relocator,
+        };
+        let plugin = relocator.relocate().expect("Failed to relocate library");
+        for (idx, expected) in expected.into_iter().enumerate() {
+            let reloc = &output.relocations[idx];
+            let slot = unsafe { ((plugin.base() + reloc.vaddr as usize) as *const usize).read() };
+            assert_eq!(slot, expected + reloc.addend as usize, "relocation {idx}");
+        }
+    }
+}
+
+#[test]
+fn needed_pair() {
+    use gen_elf::DylibPair;
+
+    let arch = Arch::current();
+    let pair = DylibPair::new(arch).expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let base = loader
+        .load_dylib(ElfBinary::new("libbase.so", &pair.base.data))
+        .expect("Failed to load library");
+    assert!(base.needed_libs().is_empty());
+    let base = base
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let dep = loader
+        .load_dylib(ElfBinary::new("libdep.so", &pair.dep.data))
+        .expect("Failed to load library");
+    assert_eq!(dep.soname(), Some(DylibPair::DEP_SONAME));
+    assert_eq!(dep.needed_libs(), [DylibPair::BASE_SONAME]);
+    let dep = dep
+        .relocator()
+        .scope([&base])
+        .lazy(false)
+        .relocate()
+        .expect("Failed to relocate library");
+
+    for (name, r_type) in [
+        (DylibPair::FUNC_NAME, arch.jump_slot_reloc()),
+        (DylibPair::VAR_NAME, arch.glob_dat_reloc()),
+    ] {
+        let expected = unsafe { base.get::<()>(name).unwrap().into_raw() } as usize;
+        let reloc = pair
+            .dep
+            .relocations
+            .iter()
+            .find(|reloc| reloc.r_type == r_type)
+            .unwrap();
+        let value = unsafe { ((dep.base() + reloc.vaddr as usize) as *const usize).read() };
+        assert_eq!(value, expected, "{name}");
+    }
+}
+
+#[test]
+fn namespaces() {
+    use elf_loader::image::Namespace;
+    use gen_elf::DylibPair;
+
+    let arch = Arch::current();
+    let pair = DylibPair::new(arch).expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    let resolver = |needed: &elf_loader::image::NeededLib<'_>| {
+        assert_eq!(needed.name(), DylibPair::BASE_SONAME);
+        assert_eq!(needed.parent(), "libdep.so");
+        Ok(ElfBinary::new("libbase.so", &pair.base.data))
+    };
+    let slot = |dep: &elf_loader::image::LoadedDylib<()>| {
+        let reloc = pair
+            .dep
+            .relocations
+            .iter()
+            .find(|reloc| reloc.r_type == arch.glob_dat_reloc())
+            .unwrap();
+        unsafe { ((dep.base() + reloc.vaddr as usize) as *const usize).read() }
+    };
+
+    // The same pair of libraries loaded twice, each copy bound to its own base
+    let mut namespaces = [Namespace::new(), Namespace::new()];
+    let mut vars = Vec::new();
+    for ns in &mut namespaces {
+        let dep = ns
+            .load_and_link(
+                &mut loader,
+                ElfBinary::new("libdep.so", &pair.dep.data),
+                resolver,
+            )
+            .expect("Failed to load namespace");
+        let names: Vec<_> = ns.modules().iter().map(|m| m.name()).collect();
+        assert_eq!(names, ["libbase.so", "libdep.so"]);
+        let var = unsafe { ns.get::<()>(DylibPair::BASE_SONAME, DylibPair::VAR_NAME) }
+            .expect("Symbol not found")
+            .into_raw() as usize;
+        assert_eq!(slot(&dep), var);
+        vars.push(var);
+    }
+    assert_ne!(vars[0], vars[1]);
+    assert!(unsafe { namespaces[0].get::<()>("libdep.so", DylibPair::VAR_NAME) }.is_none());
+
+    // Loading into an existing namespace reuses its members
+    let again = namespaces[1]
+        .load_and_link(
+            &mut loader,
+            ElfBinary::new("libdep2.so", &pair.dep.data),
+            |_: &elf_loader::image::NeededLib<'_>| -> elf_loader::Result<ElfBinary<'_>> {
+                unreachable!("libbase.so is a member")
+            },
+        )
+        .expect("Failed to load library");
+    assert_eq!(slot(&again), vars[1]);
+    assert_eq!(namespaces[1].modules().len(), 3);
+
+    // A shared base module satisfies the dependency of the members
+    let base = loader
+        .load_dylib(ElfBinary::new("libbase.so", &pair.base.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let mut ns = Namespace::new();
+    ns.share(&base);
+    let dep = ns
+        .load_and_link(
+            &mut loader,
+            ElfBinary::new("libdep.so", &pair.dep.data),
+            |_: &elf_loader::image::NeededLib<'_>| -> elf_loader::Result<ElfBinary<'_>> {
+                unreachable!("libbase.so is shared")
+            },
+        )
+        .expect("Failed to load library");
+    let var = unsafe { base.get::<()>(DylibPair::VAR_NAME) }
+        .unwrap()
+        .into_raw() as usize;
+    assert_eq!(slot(&dep), var);
+    assert_eq!(ns.modules().len(), 1);
+    assert_eq!(ns.shared().len(), 1);
+}
+
+#[test]
+fn dynamic_entries() {
+    use elf_loader::elf::{DT_NEEDED, DT_SONAME, DT_STRTAB};
+    use std::ffi::CStr;
+
+    let arch = Arch::current();
+    let symbols = [SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8])];
+    let config = ElfWriterConfig::default().with_soname("libsoname.so.1");
+    let output = DylibWriter::with_config(arch, config)
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let raw = loader
+        .load_dylib(ElfBinary::new("libfile.so", &output.data))
+        .expect("Failed to load library");
+    assert_eq!(raw.soname(), Some("libsoname.so.1"));
+    let raw_entries: Vec<_> = raw.dynamic_entries().collect();
+    let dynamic_ptr = raw.dynamic_ptr().unwrap().as_ptr().cast_const();
+
+    let lib = raw
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    assert_eq!(lib.name(), "libfile.so");
+    assert_eq!(lib.soname(), Some("libsoname.so.1"));
+
+    // Entries are read straight from memory and stop before DT_NULL
+    let entries: Vec<_> = lib.dynamic_entries().collect();
+    assert_eq!(entries, raw_entries);
+    assert!(entries.iter().all(|&(tag, _)| tag != 0));
+    assert!(!entries.iter().any(|&(tag, _)| tag == DT_NEEDED));
+    let strtab = entries
+        .iter()
+        .find(|&&(tag, _)| tag == DT_STRTAB)
+        .map(|&(_, val)| val)
+        .unwrap();
+    let soname = entries
+        .iter()
+        .find(|&&(tag, _)| tag == DT_SONAME)
+        .map(|&(_, val)| val)
+        .unwrap();
+    let soname = unsafe { CStr::from_ptr((lib.base() + (strtab + soname) as usize) as _) };
+    assert_eq!(soname.to_str(), Ok("libsoname.so.1"));
+
+    let link_map = lib.link_map_view();
+    assert_eq!(link_map.l_addr, lib.base());
+    assert_eq!(
+        unsafe { CStr::from_ptr(link_map.l_name) }.to_str(),
+        Ok("libfile.so")
+    );
+    assert_eq!(link_map.l_ld, dynamic_ptr);
+}
+
+#[test]
+fn relocation_report() {
+    let arch = Arch::current();
+    let def_data: Vec<u8> = (1..=32).collect();
+    let helper_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(COPY_VAR_NAME, &def_data)])
+        .expect("Failed to generate helper ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(COPY_VAR_NAME, REL_COPY),
+                RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT),
+            ],
+            &[
+                SymbolDesc::undefined_object(COPY_VAR_NAME).with_size(8),
+                SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let helper = loader
+        .load_dylib(ElfBinary::new("libhelper.so", &helper_output.data))
+        .expect("Failed to load helper library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate helper library");
+    let (lib, report) = loader
+        .load_dylib(ElfBinary::new("libreport.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&helper])
+        .lazy(false)
+        .post_find_fn(|name| (name == EXTERNAL_FUNC_NAME).then_some(external_func as *const ()))
+        .relocate_with_report()
+        .expect("Failed to relocate library");
+
+    let mismatches = report.copy_size_mismatches();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].name(), COPY_VAR_NAME);
+    assert_eq!(mismatches[0].provider(), "libhelper.so");
+    assert_eq!(mismatches[0].ref_size(), 8);
+    assert_eq!(mismatches[0].def_size(), 32);
+    assert_eq!(mismatches[0].copied_size(), 8);
+    assert_eq!(report.post_find_symbols(), [EXTERNAL_FUNC_NAME]);
+
+    // Only the referenced size is copied
+    let copy = output
+        .relocations
+        .iter()
+        .find(|reloc| reloc.r_type == REL_COPY)
+        .unwrap();
+    let dest =
+        unsafe { std::slice::from_raw_parts((lib.base() + copy.vaddr as usize) as *const u8, 8) };
+    assert_eq!(dest, &def_data[..8]);
+}
+
+#[test]
+fn exec_copy_relocation() {
+    let arch = Arch::current();
+    let def_data = [7u8; 8];
+    let provider_output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(COPY_VAR_NAME, REL_GOT)],
+            &[SymbolDesc::global_object(COPY_VAR_NAME, &def_data)],
+        )
+        .expect("Failed to generate ELF");
+    // References the variable, once relocated before and once after the executable
+    let user_output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(COPY_VAR_NAME, REL_GOT)],
+            &[SymbolDesc::undefined_object(COPY_VAR_NAME)],
+        )
+        .expect("Failed to generate ELF");
+    // The executable references its copy before making it
+    let exec_output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(COPY_VAR_NAME, REL_GOT),
+                RelocEntry::with_name(COPY_VAR_NAME, REL_COPY),
+            ],
+            &[SymbolDesc::undefined_object(COPY_VAR_NAME).with_size(8)],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let provider = loader
+        .load_dylib(ElfBinary::new("libprovider.so", &provider_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let load_user = |loader: &mut Loader<_, _>, scope: Vec<&elf_loader::image::LoadedCore<()>>| {
+        loader
+            .load_dylib(ElfBinary::new("libuser.so", &user_output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .scope(scope)
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let early = load_user(&mut loader, vec![&provider]);
+    let exec = loader
+        .load_exec(ElfBinary::new("exec", &exec_output.data))
+        .expect("Failed to load executable")
+        .relocator()
+        .scope([&provider, &early])
+        .relocate()
+        .expect("Failed to relocate executable");
+    let exec_core = exec.core_ref().expect("dynamic executable");
+    assert!(exec_core.is_main_program());
+    assert!(!provider.is_main_program());
+    let late = load_user(&mut loader, vec![&provider, exec_core]);
+
+    let slot =
+        |base: usize, vaddr: u64| unsafe { ((base + vaddr as usize) as *const usize).read() };
+    let copy_addr = exec_core.base() + exec_output.relocations[1].vaddr as usize;
+    assert_eq!(unsafe { (copy_addr as *const [u8; 8]).read() }, def_data);
+
+    // Every module sees the copy of the executable
+    assert_eq!(
+        slot(exec_core.base(), exec_output.relocations[0].vaddr),
+        copy_addr
+    );
+    assert_eq!(
+        slot(provider.base(), provider_output.relocations[0].vaddr),
+        copy_addr
+    );
+    assert_eq!(
+        slot(early.base(), user_output.relocations[0].vaddr),
+        copy_addr
+    );
+    assert_eq!(
+        slot(late.base(), user_output.relocations[0].vaddr),
+        copy_addr
+    );
+    // The executable is a dependency of the module that binds to its copy
+    assert!(late.deps().iter().any(|dep| dep.is_main_program()));
+
+    // Without the executable in the scope, the definition of the provider is used
+    let other = load_user(&mut loader, vec![&provider]);
+    let def_addr = unsafe { provider.get::<u8>(COPY_VAR_NAME).unwrap().into_raw() as usize };
+    assert_eq!(
+        slot(other.base(), user_output.relocations[0].vaddr),
+        def_addr
+    );
+}
+
+#[test]
+fn relocation_stats() {
+    use gen_elf::SymbolScope;
+    use std::collections::BTreeMap;
+
+    const SCOPE_VAR: &str = "scope_var";
+    const MISSING_VAR: &str = "missing_var";
+
+    let arch = Arch::current();
+    let scope_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(SCOPE_VAR, &[0u8; 8])])
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(EXTERNAL_VAR_NAME, REL_GOT),
+                RelocEntry::with_name(SCOPE_VAR, REL_SYMBOLIC),
+                RelocEntry::with_name(MISSING_VAR, REL_GOT),
+                RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT),
+                RelocEntry::with_name(EXTERNAL_FUNC_NAME2, REL_JUMP_SLOT),
+                RelocEntry::new(REL_RELATIVE),
+            ],
+            &[
+                SymbolDesc::undefined_object(EXTERNAL_VAR_NAME),
+                SymbolDesc::undefined_object(SCOPE_VAR),
+                SymbolDesc::undefined_object(MISSING_VAR).with_scope(SymbolScope::Weak),
+                SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+                SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME2),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let scope_lib = loader
+        .load_dylib(ElfBinary::new("libscope.so", &scope_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let mut expected = BTreeMap::new();
+    for reloc in &output.relocations {
+        *expected.entry(reloc.r_type).or_insert(0) += 1;
+    }
+
+    let (_, stats) = loader
+        .load_dylib(ElfBinary::new("libstats.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&scope_lib])
+        .pre_find_fn(|name| {
+            (name == EXTERNAL_VAR_NAME || name == EXTERNAL_FUNC_NAME)
+                .then_some(&raw const EXTERNAL_VAR as *const ())
+        })
+        .post_find_fn(|name| (name == EXTERNAL_FUNC_NAME2).then_some(external_func as *const ()))
+        .lazy(false)
+        .relocate_with_stats()
+        .expect("Failed to relocate library");
+    assert_eq!(stats.by_type, expected);
+    assert_eq!(stats.from_pre_find, 2);
+    assert_eq!(stats.from_scope, 1);
+    assert_eq!(stats.from_post_find, 1);
+    assert_eq!(stats.unresolved, 1);
+    assert_eq!(stats.lazy_deferred, 0);
+
+    // Lazy slots are only counted, and the report carries the statistics on request
+    let (_, symbol_lookup) = get_symbol_lookup();
+    let (_, report) = loader
+        .load_dylib(ElfBinary::new("libstats.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&scope_lib])
+        .pre_find(symbol_lookup.clone())
+        .lazy(true)
+        .lazy_scope(symbol_lookup)
+        .with_stats(true)
+        .relocate_with_report()
+        .expect("Failed to relocate library");
+    let stats = report.stats().expect("Statistics were not collected");
+    assert_eq!(stats.by_type, expected);
+    assert_eq!(stats.lazy_deferred, 2);
+    assert_eq!(stats.from_pre_find, 1);
+    assert_eq!(stats.from_scope, 1);
+    assert_eq!(stats.unresolved, 1);
+}
+
+#[test]
+fn load_premapped() {
+    use object::{Object, ObjectSegment};
+    use std::alloc::{Layout, alloc_zeroed, dealloc};
+
+    let arch = Arch::current();
+    let var_data: Vec<u8> = (1..=16).collect();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &var_data)])
+        .expect("Failed to generate ELF");
+
+    // Lay out the PT_LOAD segments ourselves, as an external mapper would
+    let file = object::File::parse(&*output.data).expect("Failed to parse ELF");
+    let len = file
+        .segments()
+        .map(|seg| seg.address() + seg.size())
+        .max()
+        .unwrap() as usize;
+    let layout = Layout::from_size_align(len, 0x1000).unwrap();
+    let memory = unsafe { alloc_zeroed(layout) };
+    for seg in file.segments() {
+        let data = seg.data().unwrap();
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                memory.add(seg.address() as usize),
+                data.len(),
+            )
+        };
+    }
+
+    let mut loader = Loader::new();
+    let image = unsafe {
+        ElfPremapped::new(
+            "libpremapped.so",
+            core::ptr::NonNull::new(memory.cast()).unwrap(),
+            len,
+        )
+    };
+    let lib = loader
+        .load_dylib_premapped(image)
+        .expect("Failed to load premapped library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate premapped library");
+    assert_eq!(lib.base(), memory as usize);
+
+    let sym = unsafe { lib.get::<[u8; 16]>(LOCAL_VAR_NAME) }.expect("Symbol not found");
+    let addr = sym.into_raw() as usize;
+    assert!((memory as usize..memory as usize + len).contains(&addr));
+    assert_eq!(unsafe { &*(addr as *const [u8; 16]) }[..], var_data[..]);
+
+    // Too small to hold the segments
+    let image = unsafe {
+        ElfPremapped::new(
+            "libtruncated.so",
+            core::ptr::NonNull::new(memory.cast()).unwrap(),
+            len / 2,
+        )
+    };
+    assert!(loader.load_dylib_premapped(image).is_err());
+
+    // A segment whose end overflows the address space
+    let crafted = unsafe { alloc_zeroed(layout) };
+    unsafe { core::ptr::copy_nonoverlapping(memory, crafted, len) };
+    // Offsets of e_phoff, and of p_vaddr and p_memsz in a program header
+    #[cfg(target_pointer_width = "64")]
+    let (e_phoff, p_vaddr, p_memsz) = (0x20, 16, 40);
+    #[cfg(target_pointer_width = "32")]
+    let (e_phoff, p_vaddr, p_memsz) = (0x1c, 8, 20);
+    unsafe {
+        let mut phdr = crafted.add(crafted.add(e_phoff).cast::<usize>().read_unaligned());
+        while phdr.cast::<u32>().read() != object::elf::PT_LOAD {
+            phdr = phdr.add(size_of::<elf_loader::elf::ElfPhdr>());
+        }
+        phdr.add(p_vaddr)
+            .cast::<usize>()
+            .write_unaligned(!0 - 0xfff);
+        phdr.add(p_memsz).cast::<usize>().write_unaligned(0x2000);
+    }
+    let image = unsafe {
+        ElfPremapped::new(
+            "liboverflow.so",
+            core::ptr::NonNull::new(crafted.cast()).unwrap(),
+            len,
+        )
+    };
+    assert!(loader.load_dylib_premapped(image).is_err());
+    unsafe { dealloc(crafted, layout) };
+
+    drop(lib);
+    unsafe { dealloc(memory, layout) };
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+#[test]
+fn load_vdso() {
+    use elf_loader::image::LoadedDylib;
+
+    #[cfg(target_arch = "x86_64")]
+    const CLOCK_GETTIME: (&str, &str) = ("__vdso_clock_gettime", "LINUX_2.6");
+    #[cfg(target_arch = "aarch64")]
+    const CLOCK_GETTIME: (&str, &str) = ("__kernel_clock_gettime", "LINUX_2.6.39");
+    type ClockGettime = extern "C" fn(libc::clockid_t, *mut libc::timespec) -> libc::c_int;
+
+    let addr = unsafe { libc::getauxval(libc::AT_SYSINFO_EHDR) } as usize;
+    if addr == 0 {
+        // The kernel did not map a vDSO into this process
+        return;
+    }
+    let vdso = unsafe { LoadedDylib::from_raw_mapped("linux-vdso.so.1", addr) }
+        .expect("Failed to wrap the vDSO");
+    assert_eq!(vdso.name(), "linux-vdso.so.1");
+    assert_eq!(vdso.soname(), Some("linux-vdso.so.1"));
+
+    let (name, _version) = CLOCK_GETTIME;
+    let sym = unsafe { vdso.get::<()>(name) }
+        .expect("missing clock_gettime")
+        .into_raw();
+    assert!((addr..addr + vdso.mapped_len()).contains(&(sym as usize)));
+    #[cfg(feature = "version")]
+    assert_eq!(
+        unsafe { vdso.get_version::<()>(name, _version) }.map(|sym| sym.into_raw()),
+        Some(sym)
+    );
+
+    let clock_gettime: ClockGettime = unsafe { core::mem::transmute(sym) };
+    let now = |f: &dyn Fn(*mut libc::timespec) -> libc::c_int| {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        assert_eq!(f(&mut ts), 0);
+        (ts.tv_sec, ts.tv_nsec)
+    };
+    let before = now(&|ts| unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, ts) });
+    let during = now(&|ts| clock_gettime(libc::CLOCK_MONOTONIC, ts));
+    let after = now(&|ts| unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, ts) });
+    assert!(before <= during && during <= after);
+
+    // The kernel's mapping stays in place
+    drop(vdso);
+    assert_eq!(mapping_perms(addr).as_deref(), Some("r-xp"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn text_relocations() {
+    let arch = Arch::current();
+    let provider_output = DylibWriter::new(arch)
+        .write(
+            &[],
+            &[SymbolDesc::global_object(EXTERNAL_VAR_NAME, &[7; 8])],
+        )
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    let provider = loader
+        .load_dylib(ElfBinary::new("libprovider.so", &provider_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    // The absolute relocation patches a word of the executable segment
+    let output = DylibWriter::with_config(arch, ElfWriterConfig::default().with_text_relocs(true))
+        .write(
+            &[RelocEntry::with_name(EXTERNAL_VAR_NAME, REL_SYMBOLIC)],
+            &[SymbolDesc::undefined_object(EXTERNAL_VAR_NAME)],
+        )
+        .expect("Failed to generate ELF");
+    let lib = loader
+        .load_dylib(ElfBinary::new("libtextrel.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&provider])
+        .strict(true)
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let reloc = &output.relocations[0];
+    let slot = lib.base() + reloc.vaddr as usize;
+    let var = unsafe { provider.get::<()>(EXTERNAL_VAR_NAME) }
+        .unwrap()
+        .into_raw();
+    assert_eq!(
+        unsafe { (slot as *const usize).read() },
+        (var as usize).wrapping_add(reloc.addend as usize)
+    );
+    // The protection of the segment is restored afterwards
+    assert_eq!(mapping_perms(slot).as_deref(), Some("r-xp"));
+}
+
+/// Returns the permissions of the mapping that contains `addr`
+#[cfg(target_os = "linux")]
+fn mapping_perms(addr: usize) -> Option<String> {
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    maps.lines().find_map(|line| {
+        let (range, rest) = line.split_once(' ')?;
+        let (start, end) = range.split_once('-')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        let end = usize::from_str_radix(end, 16).ok()?;
+        (start..end).contains(&addr).then(|| rest[..4].to_owned())
+    })
+}
+
+#[test]
+fn hook_extensions() {
+    use elf_loader::{LoadHookContext, image::Extensions};
+    use object::elf::PT_LOAD;
+
+    /// Data of a profiler that counts the loaded segments
+    struct Segments(usize);
+    /// Data of a scanner that records the largest segment
+    #[derive(Default)]
+    struct Largest(u64);
+
+    fn profile(ctx: &mut LoadHookContext<'_, Extensions>) {
+        if ctx.phdr().p_type == PT_LOAD {
+            ctx.extension_or_insert_with(|| Segments(0)).0 += 1;
+        }
+    }
+
+    fn scan(ctx: &mut LoadHookContext<'_, Extensions>) {
+        let size = ctx.phdr().p_memsz;
+        let largest = ctx.extension_or_insert_with(Largest::default);
+        largest.0 = largest.0.max(size);
+    }
+
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_func("local_func", &[0xc3])])
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new().with_hook(|ctx: &mut LoadHookContext<'_, Extensions>| {
+        profile(ctx);
+        scan(ctx);
+        Ok(())
+    });
+    let lib = loader
+        .load_dylib(ElfBinary::new("libext.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let segments = lib
+        .extension::<Segments>()
+        .expect("missing profiler data")
+        .0;
+    assert!(segments > 0);
+    assert!(lib.extension::<Largest>().expect("missing scanner data").0 > 0);
+    assert!(lib.extension::<u32>().is_none());
+    assert_eq!(lib.user_data().len(), 2);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reserved_tail() {
+    use elf_loader::os::ProtFlags;
+
+    let arch = Arch::current();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let plain = loader
+        .load_dylib(ElfBinary::new("libplain.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    assert!(plain.reserved_tail().is_none());
+    assert!(plain.commit_tail(0, 1, ProtFlags::PROT_READ).is_err());
+
+    // The reservation is rounded up to whole pages
+    loader.reserve_tail(0x2800);
+    let lib = loader
+        .load_dylib(ElfBinary::new("libtail.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let (start, len) = lib.reserved_tail().expect("missing tail");
+    assert_eq!(start, lib.base() + lib.mapped_len());
+    assert_eq!(len, 0x3000);
+    for page in (start..start + len).step_by(0x1000) {
+        assert_eq!(mapping_perms(page).as_deref(), Some("---p"));
+    }
+
+    assert!(
+        lib.commit_tail(0x2000, 0x1001, ProtFlags::PROT_READ)
+            .is_err()
+    );
+    lib.commit_tail(0x1008, 8, ProtFlags::PROT_READ | ProtFlags::PROT_WRITE)
+        .expect("Failed to commit tail");
+    assert_eq!(mapping_perms(start).as_deref(), Some("---p"));
+    assert_eq!(mapping_perms(start + 0x1000).as_deref(), Some("rw-p"));
+    assert_eq!(mapping_perms(start + 0x2000).as_deref(), Some("---p"));
+    let slot = (start + 0x1008) as *mut u64;
+    unsafe {
+        assert_eq!(slot.read(), 0);
+        slot.write(0xdead_beef);
+        assert_eq!(slot.read(), 0xdead_beef);
+    }
+
+    drop(lib);
+    drop(plain);
+}
+
+/// Runs every task on its own scoped thread and counts the tasks
+struct ScopedThreads(usize, Arc<AtomicUsize>);
+
+impl ParallelExecutor for ScopedThreads {
+    fn parallelism(&self) -> usize {
+        self.0
+    }
+
+    fn execute<'t>(&self, tasks: Vec<Box<dyn FnOnce() + Send + 't>>) {
+        self.1.fetch_add(tasks.len(), Ordering::SeqCst);
+        std::thread::scope(|s| {
+            for task in tasks {
+                s.spawn(task);
+            }
+        });
+    }
+}
+
+#[test]
+fn parallel_relative_relocation() {
+    const RELATIVE_COUNT: usize = 100_000;
+
+    let arch = Arch::current();
+    let relocs: Vec<_> = (0..RELATIVE_COUNT)
+        .map(|i| RelocEntry::new(REL_RELATIVE).with_addend(i as i64 * 8))
+        .collect();
+    let output = DylibWriter::new(arch)
+        .write(&relocs, &[])
+        .expect("Failed to generate ELF");
+
+    let task_count = Arc::new(AtomicUsize::new(0));
+    let mut loader = Loader::new();
+    let serial = loader
+        .load_dylib(ElfBinary::new("libserial.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let parallel = loader
+        .load_dylib(ElfBinary::new("libparallel.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .parallel(ScopedThreads(4, task_count.clone()))
+        .relocate()
+        .expect("Failed to relocate library");
+
+    // Compare every relocated word, relative to each library's base
+    let read = |base: usize, vaddr: u64| unsafe { *((base + vaddr as usize) as *const usize) };
+    let mut count = 0;
+    for reloc in output
+        .relocations
+        .iter()
+        .filter(|r| r.r_type == REL_RELATIVE)
+    {
+        let serial_value = read(serial.base(), reloc.vaddr);
+        let parallel_value = read(parallel.base(), reloc.vaddr);
+        assert_eq!(serial_value - serial.base(), reloc.addend as usize);
+        assert_eq!(
+            parallel_value - parallel.base(),
+            serial_value - serial.base()
+        );
+        count += 1;
+    }
+    assert_eq!(count, RELATIVE_COUNT);
+    assert_eq!(task_count.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn relr_relocation() {
+    // One address entry followed by bitmaps, the first two of them full
+    const RELATIVE_COUNT: usize = 1 + 2 * (usize::BITS as usize - 1) + 10;
+
+    let arch = Arch::current();
+    let relocs: Vec<_> = (0..RELATIVE_COUNT)
+        .map(|_| RelocEntry::relative(arch))
+        .collect();
+    let symbols = [SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8])];
+    let config = ElfWriterConfig::default().with_relr(true);
+    let output = DylibWriter::with_config(arch, config)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let lib = loader
+        .load_dylib(ElfBinary::new("librelr.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    // The address entry points into the middle of a page
+    let first = output.relocations[0].vaddr as usize;
+    assert_ne!(first % 0x1000, 0);
+    assert_eq!(output.relocations.len(), RELATIVE_COUNT);
+    for reloc in &output.relocations {
+        let value = unsafe { *((lib.base() + reloc.vaddr as usize) as *const usize) };
+        assert_eq!(
+            value - lib.base(),
+            reloc.addend as usize,
+            "slot {}",
+            (reloc.vaddr as usize - first) / size_of::<usize>()
+        );
+    }
+}
+
+#[test]
+fn ifunc_from_scope() {
+    const IFUNC_NAME: &str = "ifunc_func";
+
+    let arch = Arch::current();
+    let config = ElfWriterConfig::default().with_ifunc_resolver_val(IFUNC_RESOLVER_VALUE);
+    let def_output = DylibWriter::with_config(arch, config)
+        .write(&[], &[SymbolDesc::global_ifunc(IFUNC_NAME)])
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(IFUNC_NAME, REL_JUMP_SLOT),
+                RelocEntry::with_name(IFUNC_NAME, REL_SYMBOLIC),
+            ],
+            &[SymbolDesc::undefined_func(IFUNC_NAME)],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let liba = loader
+        .load_dylib(ElfBinary::new("libifunc.so", &def_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let expected = liba.base() + IFUNC_RESOLVER_VALUE as usize;
+
+    // Symbol lookup, which lazy binding goes through, returns the resolved address
+    let sym = unsafe { liba.get::<()>(IFUNC_NAME) }.expect("Symbol not found");
+    assert_eq!(sym.into_raw() as usize, expected);
+
+    let libb = loader
+        .load_dylib(ElfBinary::new("libuser.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&liba])
+        .lazy(false)
+        .relocate()
+        .expect("Failed to relocate library");
+    for reloc in &output.relocations {
+        let value = unsafe { *((libb.base() + reloc.vaddr as usize) as *const usize) };
+        let expected = if reloc.r_type == REL_SYMBOLIC {
+            (expected as i64 + reloc.addend) as usize
+        } else {
+            expected
+        };
+        assert_eq!(value, expected, "r_type {}", reloc.r_type);
+    }
+}
+
+#[test]
+fn lazy_ifunc_from_scope() {
+    const IFUNC_NAME: &str = "ifunc_func";
+
+    let arch = Arch::current();
+    let relocs = [RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT)];
+    let symbols = [
+        SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+        SymbolDesc::global_ifunc(IFUNC_NAME),
+    ];
+    let write_def = |config: ElfWriterConfig| {
+        DylibWriter::with_config(arch, config)
+            .write(&relocs, &symbols)
+            .expect("Failed to generate ELF")
+    };
+    let (_, symbol_lookup) = get_symbol_lookup();
+    let load_def = |data: &[u8]| {
+        Loader::new()
+            .load_dylib(ElfBinary::new("libifunc.so", data))
+            .expect("Failed to load library")
+            .relocator()
+            .pre_find(symbol_lookup.clone())
+            .lazy(false)
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let helper_offset = |lib: &elf_loader::image::LoadedDylib<()>| {
+        unsafe { lib.get::<()>(&format!("{EXTERNAL_FUNC_NAME}@helper")) }
+            .expect("Symbol not found")
+            .into_raw() as usize
+            - lib.base()
+    };
+
+    // The resolver must return something callable, so point it at the helper of
+    // the library, which calls `external_func` through its own PLT. The layout
+    // does not depend on the resolver value, so the offset stays valid.
+    let offset = helper_offset(&load_def(&write_def(ElfWriterConfig::default()).data));
+    let def_output = write_def(ElfWriterConfig::default().with_ifunc_resolver_val(offset as u64));
+    let liba = load_def(&def_output.data);
+    assert_eq!(helper_offset(&liba), offset);
+
+    let output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(IFUNC_NAME, REL_JUMP_SLOT)],
+            &[SymbolDesc::undefined_func(IFUNC_NAME)],
+        )
+        .expect("Failed to generate ELF");
+    let lazy_lib = liba.clone();
+    let libb = Loader::new()
+        .load_dylib(ElfBinary::new("libuser.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(true)
+        .lazy_scope(move |name: &str| unsafe { lazy_lib.get::<()>(name).map(|sym| sym.into_raw()) })
+        .relocate()
+        .expect("Failed to relocate library");
+
+    // dl_fixup binds the slot to the address returned by the resolver, not to
+    // the resolver itself
+    let slot = (libb.base() + output.relocations[0].vaddr as usize) as *const usize;
+    let expected = liba.base() + offset;
+    assert_ne!(unsafe { slot.read() }, expected);
+
+    let helper: ExternalFunc = unsafe {
+        core::mem::transmute(
+            libb.get::<()>(&format!("{IFUNC_NAME}@helper"))
+                .expect("Failed to get helper function")
+                .into_raw(),
+        )
+    };
+    let v_val = F64x2([1.5, 2.5]);
+    let result = helper(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+    let direct = external_func(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+    assert!((result - direct).abs() < 0.0001);
+    assert_eq!(unsafe { slot.read() }, expected);
+}
+
+#[test]
+fn phdr_registry() {
+    use object::elf::PT_DYNAMIC;
+    use std::ops::ControlFlow;
+
+    let find = |name: &str| {
+        elf_loader::iterate_phdr(|info| {
+            if info.name() == name {
+                let has_dynamic = info.phdrs().iter().any(|phdr| phdr.p_type == PT_DYNAMIC);
+                ControlFlow::Break((info.base(), has_dynamic))
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .break_value()
+    };
+
+    let arch = Arch::current();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+
+    // Libraries are only registered when the loader opts in
+    let mut loader = Loader::new();
+    let unregistered = loader
+        .load_dylib(ElfBinary::new("libunregistered.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    assert!(find("libunregistered.so").is_none());
+
+    loader.enable_registry(true);
+    let lib = loader
+        .load_dylib(ElfBinary::new("libregistered.so", &output.data))
+        .expect("Failed to load library");
+    assert!(find("libregistered.so").is_none());
+    let lib = lib
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let (base, has_dynamic) = find("libregistered.so").expect("Library not registered");
+    assert_eq!(base, lib.base());
+    assert!(has_dynamic);
+    // gen-elf does not emit unwind information
+    assert!(lib.eh_frame_hdr().is_none());
+
+    drop(lib);
+    assert!(find("libregistered.so").is_none());
+    drop(unregistered);
+}
+
+#[test]
+fn phdr_registry_reentrant() {
+    use object::elf::PT_LOAD;
+    use std::ops::ControlFlow;
+
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    loader.enable_registry(true);
+    let mut load = |name: &str| {
+        loader
+            .load_dylib(ElfBinary::new(name, &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let mut lib = Some(load("libreentrant.so"));
+    let mut loaded = None;
+
+    // The callback drops the last handle to the module it looks at and loads
+    // another one, which must neither deadlock nor unmap the module under it
+    let mut seen = 0;
+    let _ = elf_loader::iterate_phdr(|info| {
+        if info.name() == "libreentrant.so" {
+            drop(lib.take());
+            loaded = Some(load("libnested.so"));
+            assert!(info.phdrs().iter().any(|phdr| phdr.p_type == PT_LOAD));
+            seen += 1;
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    assert_eq!(seen, 1);
+
+    // The module was unregistered once the iteration released it
+    let names = || {
+        let mut names = Vec::new();
+        let _ = elf_loader::iterate_phdr(|info| {
+            names.push(info.name().to_string());
+            ControlFlow::<()>::Continue(())
+        });
+        names
+    };
+    let names = names();
+    assert!(!names.iter().any(|name| name == "libreentrant.so"));
+    assert!(names.iter().any(|name| name == "libnested.so"));
+    drop(loaded);
+}
+
+#[cfg(feature = "debugging")]
+#[test]
+fn r_debug_list() {
+    use elf_loader::debug::{RDebug, RT_CONSISTENT, chain_r_debug, r_debug};
+    use std::ffi::CStr;
+    use std::ptr::null_mut;
+
+    let namespace = |r_version| {
+        Box::leak(Box::new(RDebug {
+            r_version,
+            r_map: null_mut(),
+            r_brk: 0,
+            r_state: RT_CONSISTENT,
+            r_ldbase: 0,
+            r_next: null_mut(),
+        })) as *mut RDebug
+    };
+    // Old protocol versions have no `r_next` to chain through
+    let legacy = namespace(1);
+    assert!(!unsafe { chain_r_debug(legacy) });
+    assert!(unsafe { (*legacy).r_next.is_null() });
+    // Chaining is idempotent
+    let system = namespace(2);
+    assert!(unsafe { chain_r_debug(system) });
+    assert!(unsafe { chain_r_debug(system) });
+    assert_eq!(unsafe { (*system).r_next }, r_debug());
+    assert!(unsafe { (*r_debug()).r_next.is_null() });
+
+    // Other tests may publish modules concurrently, so only look for our own entry
+    let find = |name: &str| unsafe {
+        let r_debug = r_debug();
+        assert_eq!((*r_debug).r_version, 2);
+        assert_eq!((*r_debug).r_state, RT_CONSISTENT);
+        let mut map = (*r_debug).r_map;
+        while !map.is_null() {
+            if CStr::from_ptr((*map).l_name).to_str() == Ok(name) {
+                if !(*map).l_next.is_null() {
+                    assert_eq!((*(*map).l_next).l_prev, map);
+                }
+                return Some(((*map).l_addr, (*map).l_ld));
+            }
+            map = (*map).l_next;
+        }
+        None
+    };
+
+    let arch = Arch::current();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    loader.enable_registry(true);
+    let raw = loader
+        .load_dylib(ElfBinary::new("libdebugged.so", &output.data))
+        .expect("Failed to load library");
+    assert!(find("libdebugged.so").is_none());
+    let dynamic_ptr = raw.dynamic_ptr().unwrap().as_ptr().cast_const();
+    let lib = raw
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    assert_eq!(
+        find("libdebugged.so"),
+        Some((lib.base(), dynamic_ptr)),
+        "library not published"
+    );
+    assert_ne!(unsafe { (*r_debug()).r_brk }, 0);
+
+    drop(lib);
+    assert!(find("libdebugged.so").is_none());
+}
+
+/// A TLS allocator placing every module in a fake static TLS block
+#[derive(Default)]
+struct StaticTls {
+    next_id: AtomicUsize,
+    live: Arc<AtomicUsize>,
+}
+
+impl StaticTls {
+    const BLOCK_SIZE: isize = 0x1000;
+}
+
+impl TlsAllocator for StaticTls {
+    fn register(&self, info: &TlsInfo) -> Option<usize> {
+        assert_eq!(
+            info.image(),
+            &[0xAA, 0xBB, 0xCC, 0xDD, 0x11, 0x22, 0x33, 0x44]
+        );
+        self.live.fetch_add(1, Ordering::Relaxed);
+        Some(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn unregister(&self, _mod_id: usize) {
+        self.live.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn static_offset(&self, mod_id: usize) -> Option<isize> {
+        Some(-(mod_id as isize) * Self::BLOCK_SIZE)
+    }
+}
+
+#[test]
+fn tls_relocation() {
+    let arch = Arch::current();
+    let def_output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::new(REL_DTPMOD)],
+            &[
+                SymbolDesc::global_tls(EXTERNAL_TLS_NAME, &[0xAA, 0xBB, 0xCC, 0xDD]),
+                SymbolDesc::global_tls(EXTERNAL_TLS_NAME2, &[0x11, 0x22, 0x33, 0x44]),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(EXTERNAL_TLS_NAME2, REL_DTPMOD),
+                RelocEntry::with_name(EXTERNAL_TLS_NAME2, REL_DTPOFF),
+                RelocEntry::with_name(EXTERNAL_TLS_NAME2, REL_TPOFF),
+            ],
+            &[SymbolDesc::undefined_tls(EXTERNAL_TLS_NAME2)],
+        )
+        .expect("Failed to generate ELF");
+
+    let allocator = StaticTls::default();
+    let live = allocator.live.clone();
+    let mut loader = Loader::new();
+    loader.with_tls_allocator(allocator);
+    let liba = loader
+        .load_dylib(ElfBinary::new("libtls.so", &def_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let mod_id = liba.tls_mod_id().expect("Module id not assigned");
+    assert_eq!(live.load(Ordering::Relaxed), 1);
+
+    // A DTPMOD relocation without a symbol refers to the module itself
+    let reloc = &def_output.relocations[0];
+    let value = unsafe { *((liba.base() + reloc.vaddr as usize) as *const usize) };
+    assert_eq!(value, mod_id);
+
+    let libb = loader
+        .load_dylib(ElfBinary::new("libtls_user.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&liba])
+        .relocate()
+        .expect("Failed to relocate library");
+    // libb has no PT_TLS segment
+    assert!(libb.tls_mod_id().is_none());
+
+    let st_value = unsafe { liba.get::<()>(EXTERNAL_TLS_NAME2) }
+        .expect("Symbol not found")
+        .into_raw() as usize
+        - liba.base();
+    assert_eq!(st_value, 4);
+    for reloc in &output.relocations {
+        let value = unsafe { *((libb.base() + reloc.vaddr as usize) as *const usize) };
+        let expected = match reloc.r_type {
+            REL_DTPMOD => mod_id,
+            REL_DTPOFF => st_value - TLS_DTV_OFFSET,
+            REL_TPOFF => (st_value as isize - mod_id as isize * StaticTls::BLOCK_SIZE) as usize,
+            _ => unreachable!(),
+        };
+        assert_eq!(value, expected, "r_type {}", reloc.r_type);
+    }
+
+    drop(libb);
+    drop(liba);
+    assert_eq!(live.load(Ordering::Relaxed), 0);
+}
+
+#[cfg(target_arch = "riscv64")]
+#[test]
+fn tlsdesc_relocation() {
+    use elf_loader::arch::REL_TLSDESC;
+
+    let arch = Arch::current();
+    let def_output = DylibWriter::new(arch)
+        .write(
+            &[],
+            &[
+                SymbolDesc::global_tls(EXTERNAL_TLS_NAME, &[0xAA, 0xBB, 0xCC, 0xDD]),
+                SymbolDesc::global_tls(EXTERNAL_TLS_NAME2, &[0x11, 0x22, 0x33, 0x44]),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(EXTERNAL_TLS_NAME2, REL_TLSDESC)],
+            &[SymbolDesc::undefined_tls(EXTERNAL_TLS_NAME2)],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    loader.with_tls_allocator(StaticTls::default());
+    let liba = loader
+        .load_dylib(ElfBinary::new("libtls.so", &def_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let mod_id = liba.tls_mod_id().expect("Module id not assigned");
+    let libb = loader
+        .load_dylib(ElfBinary::new("libtls_user.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&liba])
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let st_value = unsafe { liba.get::<()>(EXTERNAL_TLS_NAME2) }
+        .expect("Symbol not found")
+        .into_raw() as usize
+        - liba.base();
+    let expected = (st_value as isize - mod_id as isize * StaticTls::BLOCK_SIZE) as usize;
+    let reloc = &output.relocations[0];
+    let desc = (libb.base() + reloc.vaddr as usize) as *const usize;
+    let (resolver, arg) = unsafe { (desc.read(), desc.add(1).read()) };
+    assert_eq!(arg, expected);
+
+    // Call the resolver the way compiled code does
+    let offset: usize;
+    unsafe {
+        core::arch::asm!(
+            "jalr t0, 0({resolver})",
+            resolver = in(reg) resolver,
+            inout("a0") desc => offset,
+            out("t0") _,
+        );
+    }
+    assert_eq!(offset, expected);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn thread_local_tls() {
+    use elf_loader::tls::{ThreadLocalAllocator, TlsIndex, tls_get_addr};
+
+    const TLS_GET_ADDR: &str = "__tls_get_addr";
+
+    let arch = Arch::current();
+    let def_output = DylibWriter::new(arch)
+        .write(
+            &[],
+            &[SymbolDesc::global_tls(
+                EXTERNAL_TLS_NAME,
+                &[0xAA, 0xBB, 0xCC, 0xDD],
+            )],
+        )
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(EXTERNAL_TLS_NAME, REL_DTPMOD),
+                RelocEntry::with_name(EXTERNAL_TLS_NAME, REL_DTPOFF),
+                RelocEntry::with_name(TLS_GET_ADDR, REL_GOT),
+            ],
+            &[
+                SymbolDesc::undefined_tls(EXTERNAL_TLS_NAME),
+                SymbolDesc::undefined_func(TLS_GET_ADDR),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    loader.with_tls_allocator(ThreadLocalAllocator);
+    let liba = loader
+        .load_dylib(ElfBinary::new("libtls.so", &def_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let libb = loader
+        .load_dylib(ElfBinary::new("libtls_user.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&liba])
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let slot = |r_type| {
+        let reloc = output
+            .relocations
+            .iter()
+            .find(|reloc| reloc.r_type == r_type)
+            .unwrap();
+        unsafe { *((libb.base() + reloc.vaddr as usize) as *const usize) }
+    };
+    // __tls_get_addr is provided by the allocator
+    assert_eq!(slot(REL_GOT), tls_get_addr as *const () as usize);
+
+    let index = TlsIndex {
+        module: slot(REL_DTPMOD),
+        offset: slot(REL_DTPOFF),
+    };
+    assert_eq!(Some(index.module), liba.tls_mod_id());
+    let addr = unsafe { tls_get_addr(&index) } as *mut u32;
+    assert!(!addr.is_null());
+    assert_eq!(unsafe { addr.read_unaligned() }, 0xDDCCBBAA);
+    unsafe { addr.write_unaligned(42) };
+    assert_eq!(
+        unsafe { (tls_get_addr(&index) as *const u32).read_unaligned() },
+        42
+    );
+
+    // Other threads get their own copy of the initial image
+    let other = std::thread::spawn(move || unsafe {
+        (tls_get_addr(&index) as *const u32).read_unaligned()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(other, 0xDDCCBBAA);
+}
+
+#[test]
+fn missing_symbol_error() {
+    const MISSING_NAME: &str = "missing_symbol";
+
+    let arch = Arch::current();
+    let def_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name(MISSING_NAME, REL_GOT)],
+            &[SymbolDesc::undefined_object(MISSING_NAME)],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let liba = loader
+        .load_dylib(ElfBinary::new("libdef.so", &def_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let libb = loader
+        .load_dylib(ElfBinary::new("libmissing.so", &output.data))
+        .expect("Failed to load library");
+    let base = libb.base();
+    let err = match libb.relocator().scope([&liba]).relocate() {
+        Ok(_) => panic!("relocation should fail"),
+        Err(err) => err,
+    };
+
+    let msg = err.to_string();
+    assert!(msg.contains(MISSING_NAME), "{msg}");
+    assert!(msg.contains("libmissing.so"), "{msg}");
+    assert!(err.is_missing_symbol());
+    assert_eq!(err.missing_symbol_name(), Some(MISSING_NAME));
+    let Error::Relocation {
+        context: Some(context),
+        ..
+    } = err
+    else {
+        panic!("missing relocation context: {msg}");
+    };
+    let reloc = &output.relocations[0];
+    assert_eq!(context.file(), "libmissing.so");
+    assert_eq!(context.symbol(), Some(MISSING_NAME));
+    assert_eq!(context.r_type(), REL_GOT);
+    assert_eq!(context.r_offset(), reloc.vaddr as usize);
+    assert_eq!(context.target(), base + reloc.vaddr as usize);
+    assert_eq!(context.entry(), Some((RelocationTable::Dynamic, 0)));
+    assert_eq!(context.searched(), ["pre_find", "libdef.so", "post_find"]);
+}
+
+#[test]
+fn relocation_handler() {
+    use elf_loader::relocation::{Handled, RelocationContext, RelocationHandler};
+
+    const REL_CUSTOM: u32 = 0xfa;
+
+    /// Moves `REL_SYMBOLIC` entries 16 bytes further into their symbol
+    struct ShiftSymbolic;
+
+    impl RelocationHandler for ShiftSymbolic {
+        fn handle<D>(
+            &mut self,
+            ctx: &RelocationContext<'_, D>,
+        ) -> Option<elf_loader::Result<Handled>> {
+            if ctx.rel().r_type() as u32 != REL_SYMBOLIC {
+                return None;
+            }
+            ctx.set_addend(ctx.addend() + 16);
+            Some(Ok(Handled::Retry))
+        }
+    }
+
+    /// Stores the offset of the symbol from the load base for `REL_CUSTOM` entries
+    struct Custom;
+
+    impl RelocationHandler for Custom {
+        fn handle<D>(
+            &mut self,
+            ctx: &RelocationContext<'_, D>,
+        ) -> Option<elf_loader::Result<Handled>> {
+            if ctx.rel().r_type() as u32 != REL_CUSTOM {
+                return None;
+            }
+            let symdef = ctx.symdef()?;
+            let value = symdef.convert() as usize - ctx.base();
+            Some(ctx.write_word(value).map(|()| Handled::Done(None)))
+        }
+    }
+
+    /// Asks for a retry that cannot succeed
+    struct AlwaysRetry;
+
+    impl RelocationHandler for AlwaysRetry {
+        fn handle<D>(
+            &mut self,
+            _ctx: &RelocationContext<'_, D>,
+        ) -> Option<elf_loader::Result<Handled>> {
+            Some(Ok(Handled::Retry))
+        }
+    }
+
+    let arch = Arch::current();
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(LOCAL_VAR_NAME, REL_CUSTOM),
+                RelocEntry::with_name(LOCAL_VAR_NAME, REL_SYMBOLIC),
+            ],
+            &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 32])],
+        )
+        .expect("Failed to generate ELF");
+    let reloc = |r_type: u32| {
+        output
+            .relocations
+            .iter()
+            .find(|rel| rel.r_type == r_type)
+            .unwrap()
+    };
+
+    let mut loader = Loader::new();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libhandler.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .pre_handler(ShiftSymbolic)
+        .post_handler(Custom)
+        .relocate()
+        .expect("Failed to relocate library");
+    let var = unsafe { lib.get::<u8>(LOCAL_VAR_NAME).unwrap().into_raw() } as usize;
+    let read = |r_type: u32| unsafe {
+        ((lib.base() + reloc(r_type).vaddr as usize) as *const usize).read()
+    };
+    assert_eq!(read(REL_CUSTOM), var - lib.base());
+    let addend = reloc(REL_SYMBOLIC).addend as usize;
+    assert_eq!(read(REL_SYMBOLIC), var + addend + 16);
+
+    let err = match loader
+        .load_dylib(ElfBinary::new("libretry.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .post_handler(AlwaysRetry)
+        .relocate()
+    {
+        Ok(_) => panic!("relocation should fail"),
+        Err(err) => err,
+    };
+    let msg = err.to_string();
+    assert!(msg.contains("Unhandled relocation"), "{msg}");
+}
+
+#[test]
+fn implicit_addend_before_handler() {
+    use elf_loader::relocation::{Handled, RelocationContext, RelocationHandler};
+
+    /// Overwrites the target of `REL_SYMBOLIC` entries and leaves them to the default processing
+    struct Clobber;
+
+    impl RelocationHandler for Clobber {
+        fn handle<D>(
+            &mut self,
+            ctx: &RelocationContext<'_, D>,
+        ) -> Option<elf_loader::Result<Handled>> {
+            if ctx.rel().r_type() as u32 == REL_SYMBOLIC {
+                ctx.write_word(0xdead).unwrap();
+            }
+            None
+        }
+    }
+
+    let output = DylibWriter::new(Arch::current())
+        .write(
+            &[RelocEntry::with_name(LOCAL_VAR_NAME, REL_SYMBOLIC)],
+            &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 32])],
+        )
+        .expect("Failed to generate ELF");
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libclobber.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .pre_handler(Clobber)
+        .relocate()
+        .expect("Failed to relocate library");
+
+    // REL entries keep the addend that was in place before the handler ran
+    let reloc = &output.relocations[0];
+    let var = unsafe { lib.get::<u8>(LOCAL_VAR_NAME).unwrap().into_raw() } as usize;
+    let value = unsafe { ((lib.base() + reloc.vaddr as usize) as *const usize).read() };
+    assert_eq!(value, var + reloc.addend as usize);
+}
+
+#[cfg(target_arch = "arm")]
+#[test]
+fn thumb_function_binding() {
+    const THUMB_FUNC: &str = "thumb_func";
+
+    let arch = Arch::current();
+    // `movs r0, #42; bx lr` in Thumb state
+    let provider_output = DylibWriter::new(arch)
+        .write(
+            &[],
+            &[SymbolDesc::global_func(THUMB_FUNC, &[0x2a, 0x20, 0x70, 0x47]).with_thumb()],
+        )
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    let provider = loader
+        .load_dylib(ElfBinary::new("libthumb.so", &provider_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let func = unsafe { provider.get::<()>(THUMB_FUNC).unwrap().into_raw() as usize };
+    assert_eq!(func & 1, 1);
+
+    let relocs = [
+        RelocEntry::with_name(THUMB_FUNC, REL_JUMP_SLOT),
+        RelocEntry::with_name(THUMB_FUNC, REL_GOT),
+        RelocEntry::with_name(THUMB_FUNC, REL_SYMBOLIC),
+    ];
+    let output = DylibWriter::new(arch)
+        .write(&relocs, &[SymbolDesc::undefined_func(THUMB_FUNC)])
+        .expect("Failed to generate ELF");
+    let reloc = |r_type: u32| {
+        output
+            .relocations
+            .iter()
+            .find(|rel| rel.r_type == r_type)
+            .unwrap()
+    };
+
+    for lazy in [false, true] {
+        let plugin = loader
+            .load_dylib(ElfBinary::new("libcaller.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .scope([&provider])
+            .lazy(lazy)
+            .lazy_scope(move |name: &str| (name == THUMB_FUNC).then_some(func as _))
+            .relocate()
+            .expect("Failed to relocate library");
+        let read = |r_type: u32| unsafe {
+            ((plugin.base() + reloc(r_type).vaddr as usize) as *const usize).read()
+        };
+        assert_eq!(read(REL_GOT), func);
+        assert_eq!(
+            read(REL_SYMBOLIC),
+            func + reloc(REL_SYMBOLIC).addend as usize
+        );
+
+        // The PLT switches to Thumb state through the bound address
+        let helper: extern "C" fn() -> u32 = unsafe {
+            core::mem::transmute(
+                plugin
+                    .get::<()>(&format!("{THUMB_FUNC}@helper"))
+                    .expect("Failed to get helper function")
+                    .into_raw(),
+            )
+        };
+        assert_eq!(helper(), 42);
+        assert_eq!(read(REL_JUMP_SLOT), func);
+    }
+}
+
+#[test]
+fn strict_relocation() {
+    use object::{Object, ObjectSection};
+
+    const REL_CUSTOM: u32 = 0xfa;
+
+    let arch = Arch::current();
+    let write = |r_type: u32| {
+        DylibWriter::new(arch)
+            .write(
+                &[RelocEntry::with_name(LOCAL_VAR_NAME, r_type)],
+                &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8])],
+            )
+            .expect("Failed to generate ELF")
+    };
+    let mut loader = Loader::new();
+    let mut relocate = |name: &str, data: &[u8]| {
+        loader
+            .load_dylib(ElfBinary::new(name, data))
+            .expect("Failed to load library")
+            .relocator()
+            .strict(true)
+            .relocate()
+    };
+    let rejected = |res: elf_loader::Result<_>| {
+        let err = match res {
+            Ok(_) => panic!("relocation should fail"),
+            Err(err) => err,
+        };
+        let msg = err.to_string();
+        let Error::Relocation {
+            context: Some(context),
+            ..
+        } = err
+        else {
+            panic!("missing relocation context: {msg}");
+        };
+        assert_eq!(context.entry(), Some((RelocationTable::Dynamic, 0)));
+        (msg, context.r_offset())
+    };
+
+    relocate("libstrict.so", &write(REL_SYMBOLIC).data).expect("Failed to relocate library");
+
+    let output = write(REL_CUSTOM);
+    let (msg, r_offset) = rejected(relocate("libcustom.so", &output.data));
+    assert!(msg.contains("Unsupported relocation type"), "{msg}");
+    assert_eq!(r_offset, output.relocations[0].vaddr as usize);
+
+    // Point the entry far past the end of the mapped segments
+    let mut output = write(REL_SYMBOLIC);
+    let start = {
+        let file = object::File::parse(&*output.data).expect("Failed to parse ELF");
+        let section = file
+            .sections()
+            .find(|section| {
+                section
+                    .name()
+                    .is_ok_and(|name| name.ends_with("rel.dyn") || name.ends_with("rela.dyn"))
+            })
+            .expect("Missing dynamic relocation table");
+        section.file_range().unwrap().0 as usize
+    };
+    let bad_offset = 0x4000_0000usize;
+    output.data[start..start + size_of::<usize>()].copy_from_slice(&bad_offset.to_le_bytes());
+    let (msg, r_offset) = rejected(relocate("liboutside.so", &output.data));
+    assert!(msg.contains("outside the writable segments"), "{msg}");
+    assert_eq!(r_offset, bad_offset);
+}
+
+#[test]
+fn probe_kind() {
+    use elf_loader::ElfKind;
+
+    let arch = Arch::current();
+    let symbols = [SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8])];
+    let dylib = DylibWriter::new(arch)
+        .write(&[], &symbols)
+        .expect("Failed to generate ELF");
+    let object = ObjectWriter::new(arch)
+        .write(&symbols, &[])
+        .expect("Failed to generate object");
+
+    let mut loader = Loader::new();
+    loader.preallocate(32);
+    let probe = |loader: &mut Loader<_, _>, name, data| loader.probe(ElfBinary::new(name, data));
+    assert_eq!(
+        probe(&mut loader, "libprobe.so", &dylib.data).unwrap(),
+        ElfKind::Dylib
+    );
+    assert_eq!(
+        probe(&mut loader, "probe.o", &object.data).unwrap(),
+        ElfKind::Object
+    );
+    assert!(probe(&mut loader, "garbage", &[0u8; 64]).is_err());
+
+    // The loader still works after probing
+    loader
+        .load_dylib(ElfBinary::new("libprobe.so", &dylib.data))
+        .expect("Failed to load library");
+}
+
+#[test]
+fn rebind_symbol() {
+    extern "C" fn replacement() {}
+
+    let arch = Arch::current();
+    let relocs = [RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT)];
+    let symbols = [SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME)];
+    let mut loader = Loader::new();
+
+    for is_lazy in [true, false] {
+        // Eager binding applies RELRO, so the GOT has to be unprotected for the update
+        let config = ElfWriterConfig::default().with_relro(!is_lazy);
+        let output = DylibWriter::with_config(arch, config)
+            .write(&relocs, &symbols)
+            .expect("Failed to generate ELF");
+        let (_, symbol_lookup) = get_symbol_lookup();
+        let lib = loader
+            .load_dylib(ElfBinary::new("librebind.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .pre_find(symbol_lookup.clone())
+            .lazy(is_lazy)
+            .lazy_scope(symbol_lookup)
+            .relocate()
+            .expect("Failed to relocate library");
+
+        let slot = (lib.base() + output.relocations[0].vaddr as usize) as *const usize;
+        let patched = unsafe { lib.rebind_symbol(EXTERNAL_FUNC_NAME, replacement as *const ()) }
+            .expect("Failed to rebind symbol");
+        assert_eq!(patched, 1);
+        assert_eq!(unsafe { slot.read() }, replacement as *const () as usize);
+
+        let patched = unsafe { lib.rebind_symbol("unknown_func", replacement as *const ()) }
+            .expect("Failed to rebind symbol");
+        assert_eq!(patched, 0);
+    }
+}
+
+#[test]
+fn plt_entries() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    extern "C" fn counter() {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let arch = Arch::current();
+    let relocs = [
+        RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT),
+        RelocEntry::with_name(EXTERNAL_FUNC_NAME2, REL_JUMP_SLOT),
+    ];
+    let symbols = [
+        SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+        SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME2),
+    ];
+    let mut loader = Loader::new();
+
+    for is_lazy in [true, false] {
+        let config = ElfWriterConfig::default().with_relro(!is_lazy);
+        let output = DylibWriter::with_config(arch, config)
+            .write(&relocs, &symbols)
+            .expect("Failed to generate ELF");
+        let (symbol_map, symbol_lookup) = get_symbol_lookup();
+        let lib = loader
+            .load_dylib(ElfBinary::new("libplt.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .pre_find(symbol_lookup.clone())
+            .lazy(is_lazy)
+            .lazy_scope(symbol_lookup)
+            .relocate()
+            .expect("Failed to relocate library");
+
+        let entries: Vec<_> = lib.plt_entries().collect();
+        let mut names: Vec<_> = entries.iter().map(|entry| entry.symbol()).collect();
+        names.sort();
+        assert_eq!(names, [EXTERNAL_FUNC_NAME, EXTERNAL_FUNC_NAME2]);
+        let range = lib.got_plt_range().expect("The library has no DT_PLTGOT");
+        for entry in &entries {
+            let slot = entry.got_slot() as usize;
+            assert!(range.contains(&slot));
+            let reloc = output
+                .relocations
+                .iter()
+                .find(|reloc| lib.base() + reloc.vaddr as usize == slot)
+                .expect("The slot is not a relocation target");
+            assert_eq!(reloc.r_type, REL_JUMP_SLOT);
+            assert_eq!(entry.current_target(), unsafe { *entry.got_slot() });
+            if !is_lazy {
+                assert_eq!(entry.current_target(), symbol_map[entry.symbol()]);
+            }
+        }
+
+        // A lazily bound slot keeps the redirection once it is called
+        let entry = entries
+            .iter()
+            .find(|entry| entry.symbol() == EXTERNAL_FUNC_NAME)
+            .unwrap();
+        unsafe { entry.redirect(counter as *const ()) }.expect("Failed to redirect slot");
+        assert_eq!(entry.current_target(), counter as *const () as usize);
+        let helper = unsafe {
+            lib.get::<extern "C" fn()>(&format!("{EXTERNAL_FUNC_NAME}@helper"))
+                .unwrap()
+        };
+        let before = CALLS.load(Ordering::SeqCst);
+        helper();
+        helper();
+        assert_eq!(CALLS.load(Ordering::SeqCst), before + 2);
+        assert_eq!(entry.current_target(), counter as *const () as usize);
+    }
+}
+
+#[test]
+fn lazy_bind_first_call() {
+    run_lazy_bind_first_call(ElfWriterConfig::default());
+}
+
+fn run_lazy_bind_first_call(config: ElfWriterConfig) {
+    let relocs = [RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT)];
+    let symbols = [SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME)];
+    let output = DylibWriter::with_config(Arch::current(), config)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+    let (_, symbol_lookup) = get_symbol_lookup();
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libfirstcall.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(true)
+        .lazy_scope(symbol_lookup)
+        .relocate()
+        .expect("Failed to relocate library");
+
+    // The slot goes through the architecture's resolver trampoline until the first call
+    let slot = (lib.base() + output.relocations[0].vaddr as usize) as *const usize;
+    assert_ne!(unsafe { slot.read() }, external_func as *const () as usize);
+
+    let helper: ExternalFunc = unsafe {
+        core::mem::transmute(
+            lib.get::<()>(&format!("{EXTERNAL_FUNC_NAME}@helper"))
+                .expect("Failed to get helper function")
+                .into_raw(),
+        )
+    };
+    let v_val = F64x2([1.5, 2.5]);
+    let expected = external_func(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+    let result = helper(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+    assert!((result - expected).abs() < 0.0001);
+    assert_eq!(unsafe { slot.read() }, external_func as *const () as usize);
+}
+
+// Runs under qemu-user through the loongarch64 entry of the CI test matrix
+#[cfg(target_arch = "loongarch64")]
+#[test]
+fn loongarch64_lazy_bind_slot_index() {
+    extern "C" fn doubled_func(
+        a1: i64,
+        a2: i64,
+        a3: i64,
+        a4: i64,
+        a5: i64,
+        a6: i64,
+        a7: i64,
+        a8: i64,
+        v1: F64x2,
+        f1: f64,
+        f2: f64,
+        f3: f64,
+        f4: f64,
+        f5: f64,
+        f6: f64,
+        f7: f64,
+    ) -> f64 {
+        2.0 * external_func(
+            a1, a2, a3, a4, a5, a6, a7, a8, v1, f1, f2, f3, f4, f5, f6, f7,
+        )
+    }
+
+    let relocs = [
+        RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT),
+        RelocEntry::with_name(EXTERNAL_FUNC_NAME2, REL_JUMP_SLOT),
+    ];
+    let symbols = [
+        SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+        SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME2),
+    ];
+    let output = DylibWriter::new(Arch::current())
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libslotindex.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(true)
+        .lazy_scope(|name: &str| match name {
+            EXTERNAL_FUNC_NAME => Some(external_func as *const ()),
+            EXTERNAL_FUNC_NAME2 => Some(doubled_func as *const ()),
+            _ => None,
+        })
+        .relocate()
+        .expect("Failed to relocate library");
+    let slots = output
+        .relocations
+        .iter()
+        .map(|reloc| (lib.base() + reloc.vaddr as usize) as *const usize)
+        .collect::<Vec<_>>();
+    let v_val = F64x2([1.5, 2.5]);
+    let call = |name: &str| {
+        let helper: ExternalFunc = unsafe {
+            core::mem::transmute(
+                lib.get::<()>(&format!("{name}@helper"))
+                    .expect("Failed to get helper function")
+                    .into_raw(),
+            )
+        };
+        helper(
+            1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+        )
+    };
+    let expected = external_func(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+
+    // PLT0 recovers the relocation index from the offset of the GOT slot in $t1,
+    // so binding the second slot first must leave the first one untouched
+    let unbound = unsafe { slots[0].read() };
+    assert!((call(EXTERNAL_FUNC_NAME2) - 2.0 * expected).abs() < 0.0001);
+    assert_eq!(
+        unsafe { slots[1].read() },
+        doubled_func as *const () as usize
+    );
+    assert_eq!(unsafe { slots[0].read() }, unbound);
+
+    assert!((call(EXTERNAL_FUNC_NAME) - expected).abs() < 0.0001);
+    assert_eq!(
+        unsafe { slots[0].read() },
+        external_func as *const () as usize
+    );
+}
+
+#[test]
+fn lazy_bind_fallback() {
+    use elf_loader::relocation::set_unresolved_handler;
+
+    fn handler(name: &str, lib: &str, base: usize) -> Option<*const ()> {
+        (name == EXTERNAL_FUNC_NAME2 && lib == "libfallback.so" && base != 0)
+            .then_some(external_func as *const ())
+    }
+
+    let arch = Arch::current();
+    let relocs = [
+        RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT),
+        RelocEntry::with_name(EXTERNAL_FUNC_NAME2, REL_JUMP_SLOT),
+    ];
+    let symbols = [
+        SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+        SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME2),
+    ];
+    let output = DylibWriter::new(arch)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+
+    // The lazy scope knows neither function
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libfallback.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(true)
+        .lazy_scope(|_: &str| None)
+        .relocate()
+        .expect("Failed to relocate library");
+    lib.set_lazy_fallback(|name: &str| {
+        (name == EXTERNAL_FUNC_NAME).then_some(external_func as *const ())
+    });
+    assert!(set_unresolved_handler(Some(handler)).is_none());
+
+    let v_val = F64x2([9.9, 10.10]);
+    let expected = external_func(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+    for (reloc, name) in output
+        .relocations
+        .iter()
+        .zip([EXTERNAL_FUNC_NAME, EXTERNAL_FUNC_NAME2])
+    {
+        let helper: ExternalFunc = unsafe {
+            core::mem::transmute(
+                lib.get::<()>(&format!("{name}@helper"))
+                    .expect("Failed to get helper function")
+                    .into_raw(),
+            )
+        };
+        let result = helper(
+            1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+        );
+        assert!((result - expected).abs() < 0.0001, "{name}");
+        let slot = (lib.base() + reloc.vaddr as usize) as *const usize;
+        assert_eq!(unsafe { slot.read() }, external_func as *const () as usize);
+    }
+    assert!(set_unresolved_handler(None).is_some());
+}
+
+#[test]
+fn global_lazy_binding() {
+    const PROVIDED_FUNC: &str = "provided_func";
+
+    let arch = Arch::current();
+    let in_global_scope = |name: &str| {
+        elf_loader::global_scope()
+            .iter()
+            .any(|lib| lib.name() == name)
+    };
+    // `ret` on x86_64, only called through the lazy binding path below
+    let provider_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_func(PROVIDED_FUNC, &[0xc3])])
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    let local = loader
+        .load_dylib(ElfBinary::new("liblocal.so", &provider_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let provider = loader
+        .load_dylib(ElfBinary::new("libprovider.so", &provider_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .global(true)
+        .relocate()
+        .expect("Failed to relocate library");
+    assert!(!in_global_scope("liblocal.so"));
+    assert!(in_global_scope("libprovider.so"));
+    let provided = unsafe { provider.get::<()>(PROVIDED_FUNC).unwrap().into_raw() as usize };
+
+    let relocs = [RelocEntry::with_name(PROVIDED_FUNC, REL_JUMP_SLOT)];
+    let symbols = [SymbolDesc::undefined_func(PROVIDED_FUNC)];
+    let output = DylibWriter::new(arch)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+
+    // The snapshot works as the scope of eager relocation
+    let plugin = loader
+        .load_dylib(ElfBinary::new("libplugin.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope(elf_loader::global_scope())
+        .lazy(false)
+        .relocate()
+        .expect("Failed to relocate library");
+    let slot = (plugin.base() + output.relocations[0].vaddr as usize) as *const usize;
+    assert_eq!(unsafe { slot.read() }, provided);
+
+    // Lazy binding searches the global scope once the lazy scope has no definition
+    #[cfg(target_arch = "x86_64")]
+    {
+        let plugin = loader
+            .load_dylib(ElfBinary::new("libplugin.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .lazy(true)
+            .lazy_scope(|_: &str| None)
+            .relocate()
+            .expect("Failed to relocate library");
+        let helper: extern "C" fn() = unsafe {
+            core::mem::transmute(
+                plugin
+                    .get::<()>(&format!("{PROVIDED_FUNC}@helper"))
+                    .expect("Failed to get helper function")
+                    .into_raw(),
+            )
+        };
+        helper();
+        let slot = (plugin.base() + output.relocations[0].vaddr as usize) as *const usize;
+        assert_eq!(unsafe { slot.read() }, provided);
+    }
+
+    // The library leaves the global scope when it is dropped
+    drop(plugin);
+    drop(provider);
+    assert!(!in_global_scope("libprovider.so"));
+    drop(local);
+}
+
+#[test]
+fn lazy_bind_concurrent() {
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 64;
+    const FUNCS: [&str; 4] = ["lazy_func0", "lazy_func1", "lazy_func2", "lazy_func3"];
+
+    let arch = Arch::current();
+    let relocs = FUNCS.map(|name| RelocEntry::with_name(name, REL_JUMP_SLOT));
+    let symbols = FUNCS.map(SymbolDesc::undefined_func);
+    let output = DylibWriter::new(arch)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let counter = lookups.clone();
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libconcurrent.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(true)
+        .lazy_scope(move |name: &str| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            FUNCS.contains(&name).then_some(external_func as *const ())
+        })
+        .relocate()
+        .expect("Failed to relocate library");
+    let helpers: Vec<usize> = FUNCS
+        .iter()
+        .map(|name| unsafe {
+            lib.get::<()>(&format!("{name}@helper"))
+                .expect("Failed to get helper function")
+                .into_raw() as usize
+        })
+        .collect();
+
+    let v_val = F64x2([9.9, 10.10]);
+    let expected = external_func(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+    let barrier = std::sync::Barrier::new(THREADS);
+    std::thread::scope(|s| {
+        for i in 0..THREADS {
+            let (helpers, barrier) = (&helpers, &barrier);
+            s.spawn(move || {
+                barrier.wait();
+                for round in 0..ROUNDS {
+                    // Threads start on different functions to mix first calls
+                    let helper = helpers[(i + round) % helpers.len()];
+                    let helper: ExternalFunc = unsafe { core::mem::transmute(helper) };
+                    let result = helper(
+                        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+                    );
+                    assert!((result - expected).abs() < 0.0001);
+                }
+            });
+        }
+    });
+
+    for reloc in &output.relocations {
+        let slot = (lib.base() + reloc.vaddr as usize) as *const usize;
+        assert_eq!(unsafe { slot.read() }, external_func as *const () as usize);
+    }
+    // Each slot is bound at most once per racing thread, never once per call
+    let lookups = lookups.load(Ordering::Relaxed);
+    assert!((FUNCS.len()..=FUNCS.len() * THREADS).contains(&lookups));
+}
+
+#[cfg(all(feature = "mmap-file", unix))]
+#[test]
+fn mmap_file() {
+    use elf_loader::input::{ElfFile, ElfMmapFile, ElfReader};
+
+    let arch = Arch::current();
+    let content: Vec<u8> = (0..64).collect();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &content)])
+        .expect("Failed to generate ELF");
+    let path = std::env::temp_dir().join(format!("elf_loader_mmap_{}.so", std::process::id()));
+    std::fs::write(&path, &output.data).expect("Failed to write ELF to file");
+    let path = path.to_str().unwrap();
+
+    let mut file = ElfMmapFile::from_path(path).expect("Failed to map file");
+    assert_eq!(file.bytes(), &output.data[..]);
+    assert!(file.as_fd().is_some());
+    let mut buf = [0u8; 4];
+    file.read(&mut buf, 0).unwrap();
+    assert_eq!(&buf, b"\x7fELF");
+    assert!(file.read(&mut buf, output.data.len() - 2).is_err());
+
+    let mut loader = Loader::new();
+    for lib in [
+        loader.load_dylib(file),
+        loader.load_dylib(ElfFile::from_path(path).unwrap()),
+    ] {
+        let lib = lib
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library");
+        let var = unsafe { lib.get::<()>(LOCAL_VAR_NAME) }.expect("Symbol not found");
+        let var = unsafe { &*(var.into_raw() as *const [u8; 64]) };
+        assert_eq!(&var[..], &content[..]);
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn writable_segments_mapped_from_file() {
+    use elf_loader::input::{ElfFile, ElfReader, IntoElfReader};
+
+    /// Counts the bytes copied through the reader
+    struct CountingFile {
+        inner: ElfFile,
+        read: usize,
+    }
+
+    impl ElfReader for &mut CountingFile {
+        fn file_name(&self) -> &str {
+            self.inner.file_name()
+        }
+
+        fn read(&mut self, buf: &mut [u8], offset: usize) -> elf_loader::Result<()> {
+            self.read += buf.len();
+            self.inner.read(buf, offset)
+        }
+
+        fn as_fd(&self) -> Option<isize> {
+            self.inner.as_fd()
+        }
+    }
+
+    impl<'a> IntoElfReader<'a> for &'a mut CountingFile {
+        type Reader = Self;
+
+        fn into_reader(self) -> elf_loader::Result<Self> {
+            Ok(self)
+        }
+    }
+
+    // A library with a few megabytes of writable data
+    let content: Vec<u8> = (0..4 << 20).map(|i| i as u8).collect();
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &content)])
+        .expect("Failed to generate ELF");
+    let path = std::env::temp_dir().join(format!("elf_loader_cow_{}.so", std::process::id()));
+    std::fs::write(&path, &output.data).expect("Failed to write ELF to file");
+    let path = path.to_str().unwrap();
+
+    let mut file = CountingFile {
+        inner: ElfFile::from_path(path).unwrap(),
+        read: 0,
+    };
+    let lib = Loader::new()
+        .load_dylib(&mut file)
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    // Only the headers are read, the segments are mapped from the file
+    assert!(file.read < 64 << 10, "{} bytes were read", file.read);
+
+    let var = unsafe { lib.get::<()>(LOCAL_VAR_NAME) }.expect("Symbol not found");
+    let var = unsafe { core::slice::from_raw_parts_mut(var.into_raw() as *mut u8, content.len()) };
+    assert_eq!(var, &content[..]);
+    // Writes go to private copies of the pages and never reach the file
+    var.fill(0xff);
+    assert_eq!(std::fs::read(path).unwrap(), output.data);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn per_load_mmap() {
+    use core::{ffi::c_void, ptr::NonNull};
+    use elf_loader::os::{DefaultMmap, MapFlags, Mmap, ProtFlags};
+
+    static CALLS: [AtomicUsize; 2] = [const { AtomicUsize::new(0) }; 2];
+
+    /// Forwards to the default implementation and counts the calls
+    struct CountingMmap<const ID: usize>;
+
+    impl<const ID: usize> Mmap for CountingMmap<ID> {
+        unsafe fn mmap(
+            addr: Option<usize>,
+            len: usize,
+            prot: ProtFlags,
+            flags: MapFlags,
+            offset: usize,
+            fd: Option<isize>,
+            need_copy: &mut bool,
+        ) -> elf_loader::Result<NonNull<c_void>> {
+            CALLS[ID].fetch_add(1, Ordering::Relaxed);
+            unsafe { DefaultMmap::mmap(addr, len, prot, flags, offset, fd, need_copy) }
+        }
+
+        unsafe fn mmap_anonymous(
+            addr: usize,
+            len: usize,
+            prot: ProtFlags,
+            flags: MapFlags,
+        ) -> elf_loader::Result<NonNull<c_void>> {
+            CALLS[ID].fetch_add(1, Ordering::Relaxed);
+            unsafe { DefaultMmap::mmap_anonymous(addr, len, prot, flags) }
+        }
+
+        unsafe fn mmap_reserve(
+            addr: Option<usize>,
+            len: usize,
+            use_file: bool,
+        ) -> elf_loader::Result<NonNull<c_void>> {
+            CALLS[ID].fetch_add(1, Ordering::Relaxed);
+            unsafe { DefaultMmap::mmap_reserve(addr, len, use_file) }
+        }
+
+        unsafe fn munmap(addr: NonNull<c_void>, len: usize) -> elf_loader::Result<()> {
+            CALLS[ID].fetch_add(1, Ordering::Relaxed);
+            unsafe { DefaultMmap::munmap(addr, len) }
+        }
+
+        unsafe fn mprotect(
+            addr: NonNull<c_void>,
+            len: usize,
+            prot: ProtFlags,
+        ) -> elf_loader::Result<()> {
+            CALLS[ID].fetch_add(1, Ordering::Relaxed);
+            unsafe { DefaultMmap::mprotect(addr, len, prot) }
+        }
+    }
+
+    let calls = || CALLS.each_ref().map(|calls| calls.load(Ordering::Relaxed));
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[7; 8])])
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new().with_mmap::<CountingMmap<0>>();
+
+    let lib = loader
+        .load_dylib(ElfBinary::new("liba.so", &output.data))
+        .expect("Failed to load library");
+    let [loaded, other] = calls();
+    assert!(loaded > 0);
+    assert_eq!(other, 0);
+
+    // The mappings, the RELRO protection and the unmapping all go through
+    // the implementation chosen for the load
+    let arena = loader
+        .load_dylib_with_mmap::<CountingMmap<1>, _>(ElfBinary::new("libb.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let var = unsafe { arena.get::<()>(LOCAL_VAR_NAME) }.expect("Symbol not found");
+    assert_eq!(unsafe { *(var.into_raw() as *const [u8; 8]) }, [7; 8]);
+    let [after_load, relocated] = calls();
+    assert_eq!(after_load, loaded);
+    assert!(relocated > 0);
+    drop(arena);
+    assert_eq!(calls(), [loaded, relocated + 1]);
+    drop(lib);
+    assert_eq!(calls(), [loaded + 1, relocated + 1]);
+}
+
+#[test]
+fn munmap_failure() {
+    use core::{ffi::c_void, ptr::NonNull};
+    use elf_loader::os::{DefaultMmap, MapFlags, Mmap, ProtFlags};
+
+    static FAILED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Unmaps the memory, then reports a failure like a partially reclaimed region
+    struct FailingMmap;
+
+    impl Mmap for FailingMmap {
+        unsafe fn mmap(
+            addr: Option<usize>,
+            len: usize,
+            prot: ProtFlags,
+            flags: MapFlags,
+            offset: usize,
+            fd: Option<isize>,
+            need_copy: &mut bool,
+        ) -> elf_loader::Result<NonNull<c_void>> {
+            unsafe { DefaultMmap::mmap(addr, len, prot, flags, offset, fd, need_copy) }
+        }
+
+        unsafe fn mmap_anonymous(
+            addr: usize,
+            len: usize,
+            prot: ProtFlags,
+            flags: MapFlags,
+        ) -> elf_loader::Result<NonNull<c_void>> {
+            unsafe { DefaultMmap::mmap_anonymous(addr, len, prot, flags) }
+        }
+
+        unsafe fn mmap_reserve(
+            addr: Option<usize>,
+            len: usize,
+            use_file: bool,
+        ) -> elf_loader::Result<NonNull<c_void>> {
+            unsafe { DefaultMmap::mmap_reserve(addr, len, use_file) }
+        }
+
+        unsafe fn munmap(addr: NonNull<c_void>, len: usize) -> elf_loader::Result<()> {
+            unsafe { DefaultMmap::munmap(addr, len) }?;
+            Err(Error::Mmap {
+                msg: "region already reclaimed".into(),
+                source: None,
+            })
+        }
+
+        unsafe fn mprotect(
+            addr: NonNull<c_void>,
+            len: usize,
+            prot: ProtFlags,
+        ) -> elf_loader::Result<()> {
+            unsafe { DefaultMmap::mprotect(addr, len, prot) }
+        }
+    }
+
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new().with_mmap::<FailingMmap>();
+    let mut load = || {
+        loader
+            .load_dylib(ElfBinary::new("libfail.so", &output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+
+    // Dropping the library reports the failure instead of panicking
+    let previous = elf_loader::set_unmap_error_handler(Some(|addr, len, err| {
+        assert!(addr != 0 && len != 0);
+        assert!(matches!(err, Error::Mmap { .. }), "{err}");
+        FAILED.fetch_add(1, Ordering::Relaxed);
+    }));
+    assert!(previous.is_none());
+    drop(load());
+    assert_eq!(FAILED.load(Ordering::Relaxed), 1);
+
+    // The error can be observed directly from the last handle
+    let lib = load();
+    let other = lib.clone();
+    let lib = lib
+        .try_unload_segments()
+        .expect_err("Library is still shared");
+    drop(other);
+    let weak = unsafe { lib.core_ref() }.downgrade();
+    let err = lib
+        .try_unload_segments()
+        .expect("Library is not shared")
+        .expect_err("Unmapping should fail");
+    assert!(matches!(err, Error::Mmap { .. }), "{err}");
+    assert_eq!(FAILED.load(Ordering::Relaxed), 1);
+    // The unloaded library can no longer be reached through weak references
+    assert!(weak.upgrade().is_none());
+
+    elf_loader::set_unmap_error_handler(None);
+}
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+#[test]
+fn sealed_memfd() {
+    use elf_loader::input::{ElfFd, ElfReader, IntoElfReader};
+    use std::{fs::File, io::Write, os::fd::FromRawFd};
+
+    /// Records the length of the reads that go through the reader
+    struct CountingFd {
+        inner: ElfFd,
+        reads: Vec<usize>,
+    }
+
+    impl ElfReader for &mut CountingFd {
+        fn file_name(&self) -> &str {
+            self.inner.file_name()
+        }
+
+        fn read(&mut self, buf: &mut [u8], offset: usize) -> elf_loader::Result<()> {
+            self.reads.push(buf.len());
+            self.inner.read(buf, offset)
+        }
+
+        fn as_fd(&self) -> Option<isize> {
+            self.inner.as_fd()
+        }
+
+        fn len(&self) -> Option<usize> {
+            self.inner.len()
+        }
+    }
+
+    impl<'a> IntoElfReader<'a> for &'a mut CountingFd {
+        type Reader = Self;
+
+        fn into_reader(self) -> elf_loader::Result<Self> {
+            Ok(self)
+        }
+    }
+
+    let content: Vec<u8> = (0..1 << 20).map(|i| i as u8).collect();
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &content)])
+        .expect("Failed to generate ELF");
+    let memfd = |data: &[u8]| {
+        let fd = unsafe { libc::memfd_create(c"plugin".as_ptr(), libc::MFD_ALLOW_SEALING) };
+        assert!(fd >= 0);
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(data).unwrap();
+        let seals =
+            libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+        assert_eq!(unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) }, 0);
+        file
+    };
+
+    let mut loader = Loader::new();
+    let mut fd = CountingFd {
+        inner: ElfFd::new(None, memfd(&output.data).into()),
+        reads: Vec::new(),
+    };
+    assert_eq!(fd.inner.file_name(), "/memfd:plugin (deleted)");
+    let lib = loader.load_dylib(&mut fd).expect("Failed to load library");
+    // Only the headers are read, the segments are mapped from the memfd
+    assert_eq!(lib.core_ref().segment_reads(), 0);
+    assert!(fd.reads.iter().sum::<usize>() < 64 << 10, "{:?}", fd.reads);
+    let lib = lib
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let var = unsafe { lib.get::<()>(LOCAL_VAR_NAME) }.expect("Symbol not found");
+    let var = unsafe { core::slice::from_raw_parts(var.into_raw() as *const u8, content.len()) };
+    assert_eq!(var, &content[..]);
+
+    let lib = loader
+        .load_dylib_from_fd(memfd(&output.data).into())
+        .expect("Failed to load library");
+    assert_eq!(lib.name(), "memfd:plugin (deleted)");
+}
+
+#[cfg(all(feature = "std", unix))]
+#[test]
+fn search_paths() {
+    use elf_loader::{
+        input::ElfFile,
+        os::search::{resolve_needed, resolve_needed_inherited},
+    };
+    use std::path::PathBuf;
+
+    let arch = Arch::current();
+    let root = std::env::temp_dir().join(format!("elf_loader_search_{}", std::process::id()));
+    for dir in ["app", "lib", "lib2", "child"] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    let write = |path: PathBuf, config: ElfWriterConfig| {
+        let output = DylibWriter::with_config(arch, config)
+            .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+            .expect("Failed to generate ELF");
+        std::fs::write(&path, &output.data).unwrap();
+        path
+    };
+    let app = write(
+        root.join("app/libapp.so"),
+        ElfWriterConfig::default().with_rpath("/nonexistent::$ORIGIN/../lib"),
+    );
+    let both = write(
+        root.join("app/libboth.so"),
+        ElfWriterConfig::default()
+            .with_rpath("$ORIGIN/../lib")
+            .with_runpath("${ORIGIN}/../lib2"),
+    );
+    let child = write(root.join("child/libchild.so"), ElfWriterConfig::default());
+    let dep = write(root.join("lib/libdep.so"), ElfWriterConfig::default());
+    let dep2 = write(root.join("lib2/libdep.so"), ElfWriterConfig::default());
+    let only = write(root.join("lib/libonly.so"), ElfWriterConfig::default());
+
+    let mut loader = Loader::new();
+    let mut load = |path: &PathBuf| {
+        loader
+            .load_dylib(ElfFile::from_path(path.to_str().unwrap()).unwrap())
+            .expect("Failed to load library")
+    };
+    let app = load(&app);
+    let both = load(&both);
+    let child = load(&child);
+    let same = |found: Option<PathBuf>, expected: &PathBuf| {
+        assert_eq!(
+            found.map(|path| path.canonicalize().unwrap()),
+            Some(expected.canonicalize().unwrap())
+        );
+    };
+
+    // $ORIGIN expansion, skipping directories without the file
+    same(resolve_needed(&app, "libdep.so", &[]), &dep);
+    // DT_RUNPATH disables DT_RPATH
+    same(resolve_needed(&both, "libdep.so", &[]), &dep2);
+    // DT_RPATH is inherited from the loaders, DT_RUNPATH stops that
+    assert!(resolve_needed(&child, "libonly.so", &[]).is_none());
+    same(
+        resolve_needed_inherited(&[&child, &app], "libonly.so", &[]),
+        &only,
+    );
+    assert!(resolve_needed_inherited(&[&both, &app], "libonly.so", &[]).is_none());
+    // Extra directories come last
+    same(
+        resolve_needed(&child, "libonly.so", &[root.join("lib2"), root.join("lib")]),
+        &only,
+    );
+    same(
+        resolve_needed(&app, "libdep.so", &[root.join("lib2")]),
+        &dep,
+    );
+    // Names with a slash are taken as they are
+    same(resolve_needed(&child, dep2.to_str().unwrap(), &[]), &dep2);
+    assert!(resolve_needed(&app, "./libmissing.so", &[root.join("lib")]).is_none());
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn load_dylib_with_deps() {
+    let arch = Arch::current();
+    let build = |needed: &[&str]| {
+        DylibWriter::with_config(
+            arch,
+            ElfWriterConfig::default()
+                .with_needed(needed)
+                .with_runpath("/opt/deps"),
+        )
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF")
+        .data
+    };
+    let libs: HashMap<&str, Vec<u8>> = [
+        ("libroot.so", build(&["liba.so", "libb.so"])),
+        ("liba.so", build(&["libc.so"])),
+        ("libb.so", build(&["libc.so", "liba.so"])),
+        ("libc.so", build(&[])),
+        ("libx.so", build(&["liby.so"])),
+        ("liby.so", build(&["libx.so"])),
+        ("libbroken.so", build(&["liba.so", "libmissing.so"])),
+    ]
+    .into_iter()
+    .collect();
+    let names = |loaded: &[elf_loader::image::RawDylib<()>]| -> Vec<String> {
+        loaded.iter().map(|lib| lib.name().to_owned()).collect()
+    };
+
+    // Dependencies come before their dependents, each library is loaded once
+    let mut requests = Vec::new();
+    let mut loader = Loader::new();
+    let loaded = loader
+        .load_dylib_with_deps(
+            ElfBinary::new("libroot.so", &libs["libroot.so"]),
+            |needed| {
+                assert_eq!(needed.runpath(), Some("/opt/deps"));
+                requests.push((needed.parent().to_owned(), needed.name().to_owned()));
+                Ok(ElfBinary::new(needed.name(), &libs[needed.name()]))
+            },
+        )
+        .expect("Failed to load dependency tree");
+    assert_eq!(
+        names(&loaded),
+        ["libc.so", "liba.so", "libb.so", "libroot.so"]
+    );
+    assert_eq!(
+        requests,
+        [
+            ("libroot.so", "liba.so"),
+            ("libroot.so", "libb.so"),
+            ("liba.so", "libc.so"),
+        ]
+        .map(|(parent, name)| (parent.to_owned(), name.to_owned()))
+    );
+
+    // A DT_NEEDED cycle terminates with every member loaded once
+    let mut count = 0;
+    let loaded = loader
+        .load_dylib_with_deps(ElfBinary::new("libx.so", &libs["libx.so"]), |needed| {
+            count += 1;
+            Ok(ElfBinary::new(needed.name(), &libs[needed.name()]))
+        })
+        .expect("Failed to load dependency cycle");
+    assert_eq!(count, 1);
+    assert_eq!(names(&loaded), ["liby.so", "libx.so"]);
+
+    // A missing dependency fails the whole load with the resolver's error
+    let err = loader
+        .load_dylib_with_deps(
+            ElfBinary::new("libbroken.so", &libs["libbroken.so"]),
+            |needed| match libs.get(needed.name()) {
+                Some(data) => Ok(ElfBinary::new(needed.name(), data)),
+                None => Err(Error::Custom {
+                    msg: format!("{} not found", needed.name()).into(),
+                }),
+            },
+        )
+        .expect_err("Missing dependency should fail");
+    assert!(err.to_string().contains("libmissing.so not found"), "{err}");
+}
+
+#[test]
+fn load_foreign() {
+    const GUEST_BASE: usize = 0x4000_0000;
+    const GUEST_FUNC: usize = 0x1234_5678;
+
+    // 32-bit objects with RELA (RISC-V) and REL (ARM) relocations
+    for arch in [Arch::Riscv32, Arch::Arm] {
+        let relocs = [
+            RelocEntry::relative(arch),
+            RelocEntry::abs(LOCAL_VAR_NAME, arch),
+            RelocEntry::jump_slot(EXTERNAL_FUNC_NAME, arch),
+        ];
+        let symbols = [
+            SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8]),
+            SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+        ];
+        let output = DylibWriter::new(arch)
+            .write(&relocs, &symbols)
+            .expect("Failed to generate ELF");
+
+        let mut loader = Loader::new();
+        let lib = loader
+            .load_foreign(ElfBinary::new("libguest.so", &output.data), GUEST_BASE)
+            .expect("Failed to load foreign library")
+            .relocate(|name| (name == EXTERNAL_FUNC_NAME).then_some(GUEST_FUNC))
+            .expect("Failed to relocate foreign library");
+        assert_eq!(lib.class(), object::elf::ELFCLASS32);
+
+        let var = lib.get(LOCAL_VAR_NAME).expect("missing symbol");
+        assert!(var > GUEST_BASE && lib.host_ptr(var).is_some());
+        assert!(lib.get(EXTERNAL_FUNC_NAME).is_none());
+
+        assert_eq!(output.relocations.len(), relocs.len());
+        for reloc in &output.relocations {
+            let expected = if reloc.r_type == arch.relative_reloc() {
+                GUEST_BASE + reloc.addend as usize
+            } else if reloc.r_type == arch.abs_reloc() {
+                var + reloc.addend as usize
+            } else {
+                GUEST_FUNC
+            };
+            let slot = lib
+                .host_ptr(GUEST_BASE + reloc.vaddr as usize)
+                .expect("relocation outside of the image");
+            let val = unsafe { slot.cast::<u32>().read_unaligned() };
+            assert_eq!(val as usize, expected, "relocation type {}", reloc.r_type);
+        }
+    }
+
+    // A 64-bit object with a segment whose end overflows the address space
+    let mut data = DylibWriter::new(Arch::Riscv64)
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8])])
+        .expect("Failed to generate ELF")
+        .data;
+    let word = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+    let mut phdr = word(&data, 0x20) as usize;
+    while u32::from_le_bytes(data[phdr..phdr + 4].try_into().unwrap()) != object::elf::PT_LOAD {
+        phdr += 56;
+    }
+    data[phdr + 16..phdr + 24].copy_from_slice(&(u64::MAX - 0xfff).to_le_bytes());
+    data[phdr + 40..phdr + 48].copy_from_slice(&0x2000u64.to_le_bytes());
+    let err = Loader::new()
+        .load_foreign(ElfBinary::new("liboverflow.so", &data), GUEST_BASE)
+        .map(|_| ())
+        .unwrap_err();
+    assert!(err.to_string().contains("overflows"), "{err}");
+}
+
+#[test]
+fn link_batch() {
+    use elf_loader::relocation::Linker;
+
+    let arch = Arch::current();
+    let base_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object("batch_base", &[0; 8])])
+        .expect("Failed to generate ELF");
+    // Each member of the batch uses the other one and the base library
+    let member = |own: &str, other: &str| {
+        DylibWriter::new(arch)
+            .write(
+                &[
+                    RelocEntry::with_name(other, REL_GOT),
+                    RelocEntry::with_name("batch_base", REL_GOT),
+                ],
+                &[
+                    SymbolDesc::global_object(own, &[0; 8]),
+                    SymbolDesc::undefined_object(other),
+                    SymbolDesc::undefined_object("batch_base"),
+                ],
+            )
+            .expect("Failed to generate ELF")
+    };
+    let a_output = member("batch_a", "batch_b");
+    let b_output = member("batch_b", "batch_a");
+
+    let mut loader = Loader::new();
+    let base = loader
+        .load_dylib(ElfBinary::new("libbase.so", &base_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let libs = Linker::new()
+        .scope([&base])
+        .image(
+            loader
+                .load(ElfBinary::new("liba.so", &a_output.data))
+                .unwrap(),
+        )
+        .image(
+            loader
+                .load(ElfBinary::new("libb.so", &b_output.data))
+                .unwrap(),
+        )
+        .link()
+        .expect("Failed to link batch");
+    let names: Vec<_> = libs.iter().map(|lib| lib.name()).collect();
+    assert_eq!(names, ["liba.so", "libb.so"]);
+
+    let (a, b) = (libs[0].as_dylib().unwrap(), libs[1].as_dylib().unwrap());
+    let addr = |lib: &elf_loader::image::LoadedDylib<()>, name: &str| unsafe {
+        lib.get::<u8>(name).unwrap().into_raw() as usize
+    };
+    let slot = |lib: &elf_loader::image::LoadedDylib<()>, vaddr: u64| unsafe {
+        ((lib.base() + vaddr as usize) as *const usize).read()
+    };
+    let base_addr = addr(&base, "batch_base");
+    // The earlier member sees the later one and the other way around
+    assert_eq!(slot(a, a_output.relocations[0].vaddr), addr(b, "batch_b"));
+    assert_eq!(slot(b, b_output.relocations[0].vaddr), addr(a, "batch_a"));
+    assert_eq!(slot(a, a_output.relocations[1].vaddr), base_addr);
+    assert_eq!(slot(b, b_output.relocations[1].vaddr), base_addr);
+
+    let deps = |lib: &elf_loader::image::LoadedDylib<()>| {
+        let mut names: Vec<_> = lib.deps().iter().map(|dep| dep.name().to_owned()).collect();
+        names.sort();
+        names
+    };
+    assert_eq!(deps(a), ["libb.so", "libbase.so"]);
+    assert_eq!(deps(b), ["liba.so", "libbase.so"]);
+}
+
+#[test]
+fn get_checked() {
+    use elf_loader::image::LoadedCore;
+
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object(LOCAL_VAR_NAME, &[0; 8])])
+        .expect("Failed to generate ELF");
+    let raw = Loader::new()
+        .load_dylib(ElfBinary::new("libchecked.so", &output.data))
+        .expect("Failed to load library");
+
+    // A core taken before relocation must not hand out symbols
+    let early = unsafe { LoadedCore::from_core(raw.core()) };
+    assert!(!early.is_initialized());
+    assert!(unsafe { early.get::<u64>(LOCAL_VAR_NAME) }.is_some());
+    assert!(matches!(
+        unsafe { early.get_checked::<u64>(LOCAL_VAR_NAME) },
+        Err(Error::NotInitialized { module }) if module == "libchecked.so"
+    ));
+
+    let lib = raw.relocator().relocate().expect("Failed to relocate");
+    assert!(lib.is_initialized() && early.is_initialized());
+    let sym = unsafe { lib.get_checked::<u64>(LOCAL_VAR_NAME) }.expect("symbol is defined");
+    assert_eq!(
+        sym.into_raw() as usize,
+        unsafe { lib.get::<u64>(LOCAL_VAR_NAME) }
+            .unwrap()
+            .into_raw() as usize
+    );
+    assert!(matches!(
+        unsafe { lib.get_checked::<u64>("missing") },
+        Err(Error::SymbolNotFound { symbol, .. }) if symbol == "missing"
+    ));
+}
+
+#[test]
+fn object_sections_copied_together() {
+    use elf_loader::input::{ElfReader, IntoElfReader};
+
+    /// Records the length of the reads that go through the reader
+    struct CountingReader<'a> {
+        inner: ElfBinary<'a>,
+        reads: Vec<usize>,
+    }
+
+    impl ElfReader for &mut CountingReader<'_> {
+        fn file_name(&self) -> &str {
+            self.inner.file_name()
+        }
+
+        fn read(&mut self, buf: &mut [u8], offset: usize) -> elf_loader::Result<()> {
+            self.reads.push(buf.len());
+            self.inner.read(buf, offset)
+        }
+
+        fn as_fd(&self) -> Option<isize> {
+            None
+        }
+
+        fn as_bytes(&self) -> Option<&[u8]> {
+            self.inner.as_bytes()
+        }
+    }
+
+    impl<'a> IntoElfReader<'a> for &'a mut CountingReader<'_> {
+        type Reader = Self;
+
+        fn into_reader(self) -> elf_loader::Result<Self> {
+            Ok(self)
+        }
+    }
+
+    let names: Vec<String> = (0..64).map(|i| format!("var_{i}")).collect();
+    let symbols: Vec<SymbolDesc> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| SymbolDesc::global_object(name, &[i as u8; 64]))
+        .collect();
+    let output = ObjectWriter::new(Arch::current())
+        .with_section_per_symbol()
+        .write(&symbols, &[])
+        .expect("Failed to generate ELF");
+
+    let mut reader = CountingReader {
+        inner: ElfBinary::new("sections.o", &output.data),
+        reads: Vec::new(),
+    };
+    let obj = Loader::new()
+        .load_object(&mut reader)
+        .expect("Failed to load relocatable object");
+    // The data sections are adjacent in the file and copied by a single read
+    assert_eq!(reader.reads.iter().filter(|&&len| len <= 64).count(), 0);
+    assert!(reader.reads.contains(&(64 * 64)));
+
+    let obj = obj.relocator().relocate().expect("Failed to relocate");
+    for (i, name) in names.iter().enumerate() {
+        let var = unsafe { obj.get::<()>(name).unwrap() };
+        let var = unsafe { &*(var.into_raw() as *const [u8; 64]) };
+        assert_eq!(*var, [i as u8; 64], "{name}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn object_sections_copied_from_file_mapping() {
+    use elf_loader::input::{ElfFile, ElfReader, IntoElfReader};
+
+    /// Counts the reads that go through the reader
+    struct CountingFile {
+        inner: ElfFile,
+        fd: bool,
+        reads: usize,
+    }
+
+    impl ElfReader for &mut CountingFile {
+        fn file_name(&self) -> &str {
+            self.inner.file_name()
+        }
+
+        fn read(&mut self, buf: &mut [u8], offset: usize) -> elf_loader::Result<()> {
+            self.reads += 1;
+            self.inner.read(buf, offset)
+        }
+
+        fn as_fd(&self) -> Option<isize> {
+            self.inner.as_fd().filter(|_| self.fd)
+        }
+    }
+
+    impl<'a> IntoElfReader<'a> for &'a mut CountingFile {
+        type Reader = Self;
+
+        fn into_reader(self) -> elf_loader::Result<Self> {
+            Ok(self)
+        }
+    }
+
+    // The code between the variables separates them in the file, but not in
+    // the writable segment
+    let symbols: Vec<SymbolDesc> = (0..8)
+        .flat_map(|i| {
+            [
+                SymbolDesc::global_object(format!("var_{i}"), &[i as u8; 64]),
+                SymbolDesc::global_func(format!("func_{i}"), &[0xc3; 0x1000]),
+            ]
+        })
+        .collect();
+    let output = ObjectWriter::new(Arch::current())
+        .with_section_per_symbol()
+        .write(&symbols, &[])
+        .expect("Failed to generate ELF");
+    let path = std::env::temp_dir().join(format!("elf_loader_view_{}.o", std::process::id()));
+    std::fs::write(&path, &output.data).expect("Failed to write ELF to file");
+    let path = path.to_str().unwrap();
+
+    let load = |fd| {
+        let mut file = CountingFile {
+            inner: ElfFile::from_path(path).unwrap(),
+            fd,
+            reads: 0,
+        };
+        let obj = Loader::new()
+            .load_object(&mut file)
+            .expect("Failed to load relocatable object");
+        (obj, file.reads)
+    };
+    let (read, total_read) = load(false);
+    let (mapped, total_mapped) = load(true);
+    std::fs::remove_file(path).unwrap();
+
+    // Each variable and function takes a read of its own, unless they are
+    // copied out of a mapping
+    assert_eq!(read.segment_reads() - mapped.segment_reads(), 16);
+    assert_eq!(
+        total_read - total_mapped,
+        read.segment_reads() - mapped.segment_reads()
+    );
+
+    let obj = mapped.relocator().relocate().expect("Failed to relocate");
+    for i in 0..8 {
+        let var = unsafe { obj.get::<()>(&format!("var_{i}")).unwrap() };
+        let var = unsafe { &*(var.into_raw() as *const [u8; 64]) };
+        assert_eq!(*var, [i as u8; 64], "var_{i}");
+    }
+}
+
+#[test]
+fn collect_missing_symbols() {
+    let arch = Arch::current();
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(EXTERNAL_VAR_NAME, REL_GOT),
+                RelocEntry::with_name(EXTERNAL_FUNC_NAME2, REL_SYMBOLIC),
+                RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT),
+                RelocEntry::new(REL_RELATIVE),
+            ],
+            &[
+                SymbolDesc::undefined_object(EXTERNAL_VAR_NAME),
+                SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME2),
+                SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+
+    // Fail-fast stays the default
+    let err = loader
+        .load_dylib(ElfBinary::new("libmissing.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .unwrap_err();
+    assert!(matches!(err, Error::Relocation { .. }), "{err}");
+
+    // The lazily bound slot is checked too
+    let err = loader
+        .load_dylib(ElfBinary::new("libmissing.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(true)
+        .collect_missing(true)
+        .relocate()
+        .unwrap_err();
+    let Error::MissingSymbols { module, symbols } = &err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(module, "libmissing.so");
+    assert_eq!(err.missing_symbol_name(), Some(symbols[0].name()));
+    let mut found: Vec<_> = symbols
+        .iter()
+        .map(|symbol| (symbol.name(), symbol.r_type(), symbol.r_offset()))
+        .collect();
+    found.sort();
+    let mut expected: Vec<_> = output
+        .relocations
+        .iter()
+        .filter(|reloc| reloc.r_type != REL_RELATIVE)
+        .map(|reloc| {
+            let name = match reloc.r_type {
+                REL_GOT => EXTERNAL_VAR_NAME,
+                REL_SYMBOLIC => EXTERNAL_FUNC_NAME2,
+                _ => EXTERNAL_FUNC_NAME,
+            };
+            (name, reloc.r_type, reloc.vaddr as usize)
+        })
+        .collect();
+    expected.sort();
+    assert_eq!(found, expected);
+    for name in [EXTERNAL_VAR_NAME, EXTERNAL_FUNC_NAME2, EXTERNAL_FUNC_NAME] {
+        assert!(err.to_string().contains(name), "{err}");
+    }
+
+    // Symbols found in the lazy scope are not reported
+    let err = loader
+        .load_dylib(ElfBinary::new("libmissing.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(true)
+        .lazy_scope(|name: &str| (name == EXTERNAL_FUNC_NAME).then_some(0x1000 as *const ()))
+        .collect_missing(true)
+        .relocate()
+        .unwrap_err();
+    let Error::MissingSymbols { symbols, .. } = err else {
+        panic!("unexpected error: {err}");
+    };
+    let mut names: Vec<_> = symbols.iter().map(|symbol| symbol.name()).collect();
+    names.sort();
+    assert_eq!(names, [EXTERNAL_FUNC_NAME2, EXTERNAL_VAR_NAME]);
+
+    loader
+        .load_dylib(ElfBinary::new("libmissing.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .pre_find_fn(|_| Some(0x1000 as *const ()))
+        .collect_missing(true)
+        .relocate()
+        .expect("Failed to relocate library");
+}
+
+#[test]
+fn auditor_bindings() {
+    use elf_loader::{
+        image::LoadedCore,
+        relocation::{Auditor, SymbolBinding},
+    };
+    use std::sync::Mutex;
+
+    const HOST_VAR: &str = "host_var";
+    static OVERRIDE: usize = 0;
+
+    /// Requester, provider, name and address of a binding
+    type Binding = (String, Option<String>, String, usize);
+
+    #[derive(Default)]
+    struct Log {
+        loaded: Mutex<Vec<String>>,
+        bound: Mutex<Vec<Binding>>,
+        lazy: Mutex<Vec<(String, String, usize)>>,
+    }
+
+    impl Auditor for Log {
+        fn object_loaded(&self, core: &LoadedCore<()>) {
+            self.loaded.lock().unwrap().push(core.name().to_owned());
+        }
+
+        fn symbol_bound(&self, binding: &SymbolBinding<'_>) -> Option<usize> {
+            self.bound.lock().unwrap().push((
+                binding.requester().to_owned(),
+                binding.provider().map(str::to_owned),
+                binding.name().to_owned(),
+                binding.addr(),
+            ));
+            (binding.name() == EXTERNAL_VAR_NAME).then_some(&raw const OVERRIDE as usize)
+        }
+
+        fn lazy_resolved(&self, module: &str, name: &str, addr: usize) {
+            self.lazy
+                .lock()
+                .unwrap()
+                .push((module.to_owned(), name.to_owned(), addr));
+        }
+    }
+
+    let arch = Arch::current();
+    let host_output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(HOST_VAR, &[1; 8])])
+        .expect("Failed to generate ELF");
+    let output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(HOST_VAR, REL_GOT),
+                RelocEntry::with_name(EXTERNAL_VAR_NAME, REL_SYMBOLIC),
+                RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT),
+            ],
+            &[
+                SymbolDesc::undefined_object(HOST_VAR),
+                SymbolDesc::undefined_object(EXTERNAL_VAR_NAME),
+                SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let log = Arc::new(Log::default());
+    let mut loader = Loader::new();
+    loader.set_auditor(log.clone());
+    let host = loader
+        .load_dylib(ElfBinary::new("libhost.so", &host_output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let (symbol_map, symbol_lookup) = get_symbol_lookup();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libaudited.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&host])
+        .pre_find(symbol_lookup.clone())
+        .lazy(true)
+        .lazy_scope(symbol_lookup)
+        .relocate()
+        .expect("Failed to relocate library");
+    assert_eq!(*log.loaded.lock().unwrap(), ["libhost.so", "libaudited.so"]);
+
+    let host_var = unsafe { host.get::<()>(HOST_VAR).unwrap().into_raw() as usize };
+    let mut bound = log.bound.lock().unwrap().clone();
+    bound.sort();
+    assert_eq!(
+        bound,
+        [
+            (
+                "libaudited.so".to_owned(),
+                None,
+                EXTERNAL_VAR_NAME.to_owned(),
+                symbol_map[EXTERNAL_VAR_NAME]
+            ),
+            (
+                "libaudited.so".to_owned(),
+                Some("libhost.so".to_owned()),
+                HOST_VAR.to_owned(),
+                host_var
+            ),
+        ]
+    );
+
+    // The auditor replaced the address of the symbolic relocation
+    let slots: Vec<_> = output
+        .relocations
+        .iter()
+        .map(|reloc| {
+            let value = unsafe { ((lib.base() + reloc.vaddr as usize) as *const usize).read() };
+            (reloc.addend as usize, value)
+        })
+        .collect();
+    assert!(slots.iter().any(|&(_, value)| value == host_var));
+    let overridden = &raw const OVERRIDE as usize;
+    assert!(
+        slots
+            .iter()
+            .any(|&(addend, value)| value == overridden.wrapping_add(addend))
+    );
+
+    // Lazy binding reports the PLT entry on the first call
+    assert!(log.lazy.lock().unwrap().is_empty());
+    let helper: ExternalFunc = unsafe {
+        core::mem::transmute(
+            lib.get::<()>(&format!("{EXTERNAL_FUNC_NAME}@helper"))
+                .expect("Failed to get helper function")
+                .into_raw(),
+        )
+    };
+    let v_val = F64x2([1.5, 2.5]);
+    helper(
+        1, 2, 3, 4, 5, 6, 7, 8, v_val, 1.1, 2.2, 3.3, 4.4, 5.5, 6.6, 7.7,
+    );
+    assert_eq!(
+        *log.lazy.lock().unwrap(),
+        [(
+            "libaudited.so".to_owned(),
+            EXTERNAL_FUNC_NAME.to_owned(),
+            external_func as *const () as usize
+        )]
+    );
+}
+
+#[test]
+fn scope_index() {
+    use elf_loader::{image::LoadedDylib, relocation::ScopeIndex};
+    use gen_elf::SymbolScope;
+
+    const WEAK_NAME: &str = "weak_var";
+
+    let arch = Arch::current();
+    let define = |symbols: &[SymbolDesc]| {
+        DylibWriter::new(arch)
+            .write(&[], symbols)
+            .expect("Failed to generate ELF")
+    };
+    let weak_output =
+        define(&[SymbolDesc::global_object(WEAK_NAME, &[1; 8]).with_scope(SymbolScope::Weak)]);
+    let strong_output = define(&[SymbolDesc::global_object(WEAK_NAME, &[2; 8])]);
+    let var_output = define(&[SymbolDesc::global_object(EXTERNAL_VAR_NAME, &[3; 8])]);
+    let user_output = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(WEAK_NAME, REL_GOT),
+                RelocEntry::with_name(EXTERNAL_VAR_NAME, REL_GOT),
+            ],
+            &[
+                SymbolDesc::undefined_object(WEAK_NAME),
+                SymbolDesc::undefined_object(EXTERNAL_VAR_NAME),
+            ],
+        )
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let mut load = |name: &str, data: &[u8]| {
+        loader
+            .load_dylib(ElfBinary::new(name, data))
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let weak = load("libweak.so", &weak_output.data);
+    let strong = load("libstrong.so", &strong_output.data);
+    let var = load("libvar.so", &var_output.data);
+    let addr = |lib: &LoadedDylib<()>, name: &str| unsafe {
+        lib.get::<u8>(name).unwrap().into_raw() as usize
+    };
+    let strong_addr = addr(&strong, WEAK_NAME);
+    let var_addr = addr(&var, EXTERNAL_VAR_NAME);
+
+    let scope = [weak.clone(), strong.clone(), var.clone()];
+    let index = ScopeIndex::build(&scope);
+    assert_eq!(index.len(), 3);
+    let other = ScopeIndex::build([&var]);
+
+    // The index shared by several relocators binds like a search of the scope,
+    // and an index of other modules is ignored
+    for index in [&index, &index, &other] {
+        let user = loader
+            .load_dylib(ElfBinary::new("libuser.so", &user_output.data))
+            .expect("Failed to load library")
+            .relocator()
+            .scope(&scope)
+            .scope_index(index)
+            .relocate()
+            .expect("Failed to relocate library");
+        let slot = |idx: usize| unsafe {
+            ((user.base() + user_output.relocations[idx].vaddr as usize) as *const usize).read()
+        };
+        assert_eq!(slot(0), strong_addr);
+        assert_eq!(slot(1), var_addr);
+    }
+}
+
+#[test]
+fn relocation_session() {
+    use gen_elf::DylibPair;
+
+    let arch = Arch::current();
+    let pair = DylibPair::new(arch).expect("Failed to generate ELF");
+    let relocs = [
+        RelocEntry::relative(arch).with_addend(0x10),
+        RelocEntry::relative(arch).with_addend(0x20),
+        RelocEntry::relative(arch).with_addend(0x30),
+        RelocEntry::abs(DylibPair::VAR_NAME, arch).with_addend(8),
+        RelocEntry::glob_dat(DylibPair::VAR_NAME, arch),
+        RelocEntry::jump_slot(DylibPair::FUNC_NAME, arch),
+    ];
+    let symbols = [
+        SymbolDesc::undefined_func(DylibPair::FUNC_NAME),
+        SymbolDesc::undefined_object(DylibPair::VAR_NAME),
+    ];
+    let output = DylibWriter::new(arch)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let base = loader
+        .load_dylib(ElfBinary::new("libbase.so", &pair.base.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let mut load = || {
+        loader
+            .load_dylib(ElfBinary::new("libsession.so", &output.data))
+            .expect("Failed to load library")
+    };
+
+    let oneshot = load()
+        .relocator()
+        .scope([&base])
+        .lazy(false)
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let mut session = load().begin_relocation([&base], (), ());
+    let total = session.progress();
+    assert!(!total.is_done());
+    let mut steps = 0;
+    loop {
+        let before = session.progress();
+        let progress = session.step(1).expect("Failed to step relocation");
+        steps += 1;
+        let done =
+            |p: elf_loader::relocation::Progress| p.relative.done + p.dynamic.done + p.plt.done;
+        assert!(done(progress) > done(before));
+        if progress.is_done() {
+            break;
+        }
+    }
+    assert_eq!(
+        steps,
+        total.relative.total + total.dynamic.total + total.plt.total
+    );
+    let stepped = session.finish().expect("Failed to finish relocation");
+
+    let relative = arch.relative_reloc();
+    for reloc in &output.relocations {
+        let read = |lib: &elf_loader::image::LoadedDylib<()>| {
+            let value = unsafe { ((lib.base() + reloc.vaddr as usize) as *const usize).read() };
+            if reloc.r_type == relative {
+                value - lib.base()
+            } else {
+                value
+            }
+        };
+        assert_eq!(read(&stepped), read(&oneshot), "{:#x}", reloc.vaddr);
+    }
+}
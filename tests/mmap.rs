@@ -0,0 +1,162 @@
+#![cfg(feature = "testing")]
+
+use elf_loader::{
+    LoadHookContext, Loader,
+    arch::REL_JUMP_SLOT,
+    input::ElfBinary,
+    os::{ProtFlags, testing::BufferMmap},
+};
+use gen_elf::{Arch, DylibWriter, ElfWriterConfig, RelocEntry, SymbolDesc};
+use object::elf::{PF_R, PF_W, PF_X, PT_LOAD};
+
+const EXTERNAL_FUNC_NAME: &str = "external_func";
+const PAGE_SIZE: usize = 0x1000;
+
+extern "C" fn external_func() {}
+
+fn segment_prot(p_flags: u32) -> ProtFlags {
+    let mut prot = ProtFlags::PROT_NONE;
+    if p_flags & PF_R != 0 {
+        prot |= ProtFlags::PROT_READ;
+    }
+    if p_flags & PF_W != 0 {
+        prot |= ProtFlags::PROT_WRITE;
+    }
+    if p_flags & PF_X != 0 {
+        prot |= ProtFlags::PROT_EXEC;
+    }
+    prot
+}
+
+#[test]
+fn segment_protections() {
+    let output = DylibWriter::new(Arch::current())
+        .write(
+            &[],
+            &[
+                SymbolDesc::global_func("local_func", &[0xc3]),
+                SymbolDesc::global_object("local_var", &[0; 8]),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let lib = Loader::new()
+        .with_mmap::<BufferMmap>()
+        .load_dylib(ElfBinary::new("libsegments.so", &output.data))
+        .expect("Failed to load library");
+    let phdrs = lib.phdrs().to_vec();
+    let lib = lib
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let mut exec = 0;
+    for phdr in phdrs.iter().filter(|phdr| phdr.p_type == PT_LOAD) {
+        let prot = BufferMmap::protection_at(lib.base() + phdr.p_vaddr as usize);
+        assert_eq!(prot, Some(segment_prot(phdr.p_flags)), "{phdr:?}");
+        if phdr.p_flags & PF_X != 0 {
+            assert_eq!(prot, Some(ProtFlags::PROT_READ | ProtFlags::PROT_EXEC));
+            exec += 1;
+        }
+    }
+    assert_eq!(exec, 1);
+}
+
+#[test]
+fn hook_prot_override() {
+    let arch = Arch::current();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_func("local_func", &[0xc3])])
+        .expect("Failed to generate ELF");
+    let path = std::env::temp_dir().join(format!("elf_loader_prot_{}.so", std::process::id()));
+    std::fs::write(&path, &output.data).unwrap();
+
+    // Strip execute permission from every segment and remember the decisions
+    let mut loader = Loader::new().with_mmap::<BufferMmap>().with_hook(
+        |ctx: &mut LoadHookContext<'_, Vec<ProtFlags>>| {
+            if ctx.phdr().p_type == PT_LOAD && ctx.phdr().p_flags & PF_X != 0 {
+                ctx.override_prot(ProtFlags::PROT_READ);
+                let prot = ctx.prot_override().unwrap();
+                ctx.user_data_mut().push(prot);
+            }
+            Ok(())
+        },
+    );
+    let from_file = loader
+        .load_dylib(path.to_str().unwrap())
+        .expect("Failed to load library");
+    let from_memory = loader
+        .load_dylib(ElfBinary::new("libprot.so", &output.data))
+        .expect("Failed to load library");
+    std::fs::remove_file(&path).unwrap();
+
+    for lib in [&from_file, &from_memory] {
+        assert_eq!(lib.user_data()[..], [ProtFlags::PROT_READ]);
+        let text = lib
+            .phdrs()
+            .iter()
+            .find(|phdr| phdr.p_type == PT_LOAD && phdr.p_flags & PF_X != 0)
+            .expect("missing executable segment");
+        let prot = BufferMmap::protection_at(lib.base() + text.p_vaddr as usize);
+        assert_eq!(prot, Some(ProtFlags::PROT_READ));
+    }
+}
+
+#[test]
+fn deferred_relro() {
+    extern "C" fn replacement() {}
+
+    let relocs = [RelocEntry::with_name(EXTERNAL_FUNC_NAME, REL_JUMP_SLOT)];
+    let symbols = [SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME)];
+    let config = ElfWriterConfig::default().with_relro(true);
+    let output = DylibWriter::with_config(Arch::current(), config)
+        .write(&relocs, &symbols)
+        .expect("Failed to generate ELF");
+    let lib = Loader::new()
+        .with_mmap::<BufferMmap>()
+        .load_dylib(ElfBinary::new("librelro.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .pre_find_fn(|name| (name == EXTERNAL_FUNC_NAME).then_some(external_func as *const ()))
+        .lazy(false)
+        .apply_relro(false)
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let slot = (lib.base() + output.relocations[0].vaddr as usize) as *mut usize;
+    let relro = lib.relro_range().unwrap();
+    assert!(relro.contains(&(slot as usize)));
+    let writable = || {
+        BufferMmap::protection_at(slot as usize)
+            .unwrap()
+            .contains(ProtFlags::PROT_WRITE)
+    };
+    // The protection covers the pages the range overlaps
+    let pages = relro.start & !(PAGE_SIZE - 1)..relro.end.next_multiple_of(PAGE_SIZE);
+    let relro_applied = || {
+        BufferMmap::protection_log().iter().any(|record| {
+            (record.addr..record.addr + record.len) == pages && record.prot == ProtFlags::PROT_READ
+        })
+    };
+
+    // The GOT stays writable until the host applies the protection itself
+    assert!(writable());
+    assert!(!relro_applied());
+    lib.apply_relro().expect("Failed to apply RELRO");
+    assert!(!writable());
+    assert!(relro_applied());
+
+    lib.with_relro_writable(|| {
+        assert!(writable());
+        unsafe { slot.write(replacement as *const () as usize) };
+    })
+    .expect("Failed to patch the GOT");
+    assert_eq!(unsafe { slot.read() }, replacement as *const () as usize);
+    assert!(!writable());
+
+    // A panicking closure must not leave the segment writable
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = lib.with_relro_writable(|| panic!("patch failed"));
+    }));
+    assert!(result.is_err());
+    assert!(!writable());
+}
@@ -1,8 +1,1041 @@
+use elf_loader::{Error, ErrorKind, Loader, input::ElfBinary};
+use gen_elf::{Arch, DylibWriter, SymbolDesc};
+
 #[test]
 fn wrong_name_fails() {
     let mut loader = elf_loader::Loader::new();
-    let _ = loader
+    let err = loader
         .load_dylib("target/this_location_is_definitely_non existent:^~")
         .err()
         .unwrap();
+    assert_eq!(err.kind(), ErrorKind::Io);
+
+    // The error of the failed system call is kept as the source
+    #[cfg(target_os = "linux")]
+    {
+        use std::error::Error as _;
+
+        let source = err.source().expect("missing source");
+        let os_error = source.downcast_ref::<elf_loader::OsError>().unwrap();
+        assert_eq!(os_error.code(), 2);
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Returns the file offsets of every program header with the given type
+fn phdr_offsets(data: &[u8], p_type: u32) -> Vec<usize> {
+    let phoff = read_u64(data, 0x20) as usize;
+    let phentsize = u16::from_le_bytes([data[0x36], data[0x37]]) as usize;
+    let phnum = u16::from_le_bytes([data[0x38], data[0x39]]) as usize;
+    (0..phnum)
+        .map(|i| phoff + i * phentsize)
+        .filter(|&off| u32::from_le_bytes(data[off..off + 4].try_into().unwrap()) == p_type)
+        .collect()
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn malformed_input_fails() {
+    use elf_loader::elf::{DT_NULL, DT_STRTAB, PT_DYNAMIC, PT_LOAD};
+
+    const WILD: u64 = 0x4000_0000;
+
+    let data = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF")
+        .data;
+    let mut loader = Loader::new();
+    let mut load = |data: &[u8]| loader.load(ElfBinary::new("libmalformed.so", data));
+    load(&data).expect("Failed to load the intact library");
+
+    // Every truncation that cuts into a loadable segment must be rejected
+    let file_end = phdr_offsets(&data, PT_LOAD)
+        .into_iter()
+        .map(|off| (read_u64(&data, off + 8) + read_u64(&data, off + 32)) as usize)
+        .max()
+        .unwrap();
+    for len in 0..file_end {
+        assert!(load(&data[..len]).is_err(), "truncated to {len} bytes");
+    }
+
+    // A dynamic segment pointing past the end of the image
+    let dynamic = phdr_offsets(&data, PT_DYNAMIC)[0];
+    let mut corrupted = data.clone();
+    for field in [16, 24] {
+        corrupted[dynamic + field..dynamic + field + 8].copy_from_slice(&WILD.to_le_bytes());
+    }
+    assert!(matches!(
+        load(&corrupted),
+        Err(Error::OutOfBounds { offset, .. }) if offset == WILD as usize
+    ));
+
+    // A dynamic segment larger than the image
+    let mut corrupted = data.clone();
+    corrupted[dynamic + 40..dynamic + 48].copy_from_slice(&WILD.to_le_bytes());
+    assert!(matches!(
+        load(&corrupted),
+        Err(Error::OutOfBounds { len, .. }) if len == WILD as usize
+    ));
+
+    // A string table pointing past the end of the image
+    let mut corrupted = data.clone();
+    let mut entry = read_u64(&data, dynamic + 8) as usize;
+    loop {
+        let tag = read_u64(&corrupted, entry) as i64;
+        assert_ne!(tag, DT_NULL, "missing DT_STRTAB");
+        if tag == DT_STRTAB {
+            corrupted[entry + 8..entry + 16].copy_from_slice(&WILD.to_le_bytes());
+            break;
+        }
+        entry += 16;
+    }
+    assert!(matches!(
+        load(&corrupted),
+        Err(Error::OutOfBounds { offset, .. }) if offset == WILD as usize
+    ));
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn program_header_table() {
+    const PN_XNUM: u16 = 0xffff;
+
+    let data = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[7u8; 8])])
+        .expect("Failed to generate ELF");
+    let data = data.data;
+    let phnum = u16::from_le_bytes([data[0x38], data[0x39]]);
+    let set_u16 = |data: &mut [u8], offset: usize, value: u16| {
+        data[offset..offset + 2].copy_from_slice(&value.to_le_bytes())
+    };
+    let mut loader = Loader::new();
+    let mut load = |data: &[u8]| loader.load_dylib(ElfBinary::new("libphdr.so", data));
+
+    // The count moves to the sh_info field of a section header 0 appended to the file
+    let mut extended = data.clone();
+    let shoff = extended.len() as u64;
+    extended.extend_from_slice(&[0u8; 64]);
+    extended[shoff as usize + 44..shoff as usize + 48]
+        .copy_from_slice(&u32::from(phnum).to_le_bytes());
+    extended[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+    set_u16(&mut extended, 0x3a, 64);
+    set_u16(&mut extended, 0x3c, 1);
+    set_u16(&mut extended, 0x38, PN_XNUM);
+    let lib = load(&extended).expect("Failed to load library with PN_XNUM");
+    assert_eq!(lib.phdrs().len(), phnum as usize);
+    let lib = lib
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let var = unsafe { lib.get::<()>("var").unwrap().into_raw() };
+    assert_eq!(unsafe { *var.cast::<[u8; 8]>() }, [7u8; 8]);
+
+    // PN_XNUM without section headers
+    let mut corrupted = extended.clone();
+    corrupted[0x28..0x30].fill(0);
+    assert!(matches!(
+        load(&corrupted),
+        Err(Error::MalformedHeader { phnum: 0xffff, .. })
+    ));
+
+    // An entry size that is not the one of the host
+    let mut corrupted = data.clone();
+    set_u16(&mut corrupted, 0x36, 0x40);
+    assert!(matches!(
+        load(&corrupted),
+        Err(Error::MalformedHeader { phentsize: 0x40, phnum: n, .. }) if n == phnum as usize
+    ));
+
+    // A table that ends past the end of the file
+    let mut corrupted = data.clone();
+    let phoff = (data.len() - 8) as u64;
+    corrupted[0x20..0x28].copy_from_slice(&phoff.to_le_bytes());
+    let err = load(&corrupted).unwrap_err();
+    assert!(
+        matches!(err, Error::MalformedHeader { phoff: off, .. } if off == phoff as usize),
+        "{err}"
+    );
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+fn fixed_address_conflict() {
+    use elf_loader::elf::PT_LOAD;
+
+    const FIXED: u64 = 0x2000_0000_0000;
+
+    // Turn a library into a static executable linked at a fixed address
+    let mut data = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF")
+        .data;
+    data[0x10..0x12].copy_from_slice(&2u16.to_le_bytes());
+    let loads = phdr_offsets(&data, PT_LOAD);
+    let phoff = read_u64(&data, 0x20) as usize;
+    let phentsize = u16::from_le_bytes([data[0x36], data[0x37]]) as usize;
+    let phnum = u16::from_le_bytes([data[0x38], data[0x39]]) as usize;
+    for off in (0..phnum).map(|i| phoff + i * phentsize) {
+        if loads.contains(&off) {
+            for field in [16, 24] {
+                let vaddr = read_u64(&data, off + field) + FIXED;
+                data[off + field..off + field + 8].copy_from_slice(&vaddr.to_le_bytes());
+            }
+        } else {
+            data[off..off + 4].copy_from_slice(&0u32.to_le_bytes());
+        }
+    }
+
+    let mut loader = Loader::new();
+    let _exec = loader
+        .load_exec(ElfBinary::new("fixed", &data))
+        .expect("Failed to load the executable");
+
+    // The range is now in use, so a second copy cannot be mapped
+    let err = loader
+        .load_exec(ElfBinary::new("fixed", &data))
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::AddressConflict { wanted, .. } if wanted == FIXED as usize));
+
+    // Opting out restores the hint-only behaviour
+    loader.allow_fixed_overwrite(true);
+    loader
+        .load_exec(ElfBinary::new("fixed", &data))
+        .expect("Failed to load the executable");
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn image_size_limit() {
+    use elf_loader::elf::PT_LOAD;
+
+    const HUGE: u64 = 1 << 40;
+
+    let data = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF")
+        .data;
+    let mut loader = Loader::new();
+
+    // Every image is counted until it is dropped
+    let a = loader
+        .load_dylib(ElfBinary::new("liba.so", &data))
+        .expect("Failed to load the library");
+    let one = loader.mapped_bytes();
+    assert!(one >= a.mapped_len());
+    let b = loader
+        .load_dylib(ElfBinary::new("libb.so", &data))
+        .expect("Failed to load the library");
+    assert_eq!(loader.mapped_bytes(), one * 2);
+    drop(a);
+    assert_eq!(loader.mapped_bytes(), one);
+    drop(b);
+    assert_eq!(loader.mapped_bytes(), 0);
+
+    // A segment claiming a terabyte of memory is rejected before it is mapped
+    let mut oversized = data.clone();
+    let last = *phdr_offsets(&data, PT_LOAD).last().unwrap();
+    oversized[last + 40..last + 48].copy_from_slice(&HUGE.to_le_bytes());
+    loader.set_max_image_size(one);
+    assert!(matches!(
+        loader.load_dylib(ElfBinary::new("libhuge.so", &oversized)),
+        Err(Error::ImageTooLarge { requested, limit }) if requested > HUGE as usize && limit == one
+    ));
+    assert_eq!(loader.mapped_bytes(), 0);
+
+    // Images within the limit still load
+    let _c = loader
+        .load_dylib(ElfBinary::new("libc.so", &data))
+        .expect("Failed to load the library");
+    assert_eq!(loader.mapped_bytes(), one);
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn arch_mismatch() {
+    use elf_loader::elf::{EM_AARCH64, EM_X86_64};
+
+    let foreign = DylibWriter::new(Arch::Aarch64)
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF")
+        .data;
+    let mut loader = Loader::new();
+    let err = loader
+        .load_dylib(ElfBinary::new("libforeign.so", &foreign))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::ArchMismatch {
+            found: EM_AARCH64,
+            expected: EM_X86_64
+        }
+    ));
+    assert!(err.to_string().contains("aarch64"), "{err}");
+    assert_eq!(err.kind(), ErrorKind::ArchMismatch);
+    assert!(!err.is_missing_symbol());
+
+    // Other fields of the identification are checked as well
+    let mut big_endian = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF")
+        .data;
+    big_endian[5] = 2;
+    let err = loader
+        .load_dylib(ElfBinary::new("libbig.so", &big_endian))
+        .unwrap_err();
+    assert!(err.to_string().contains("byte order"), "{err}");
+
+    // Whitelisted machines can be mapped for inspection
+    loader.allow_arch(EM_AARCH64);
+    let lib = loader
+        .load_dylib(ElfBinary::new("libforeign.so", &foreign))
+        .expect("Failed to load the whitelisted library");
+    assert_eq!(lib.name(), "libforeign.so");
+}
+
+#[test]
+fn osabi_policy() {
+    use elf_loader::OsAbiPolicy;
+
+    const ELFOSABI_GNU: u8 = 3;
+    const ELFOSABI_FREEBSD: u8 = 9;
+    const BOGUS: u8 = 0x7f;
+
+    let output = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF");
+    let branded = |osabi: u8, abi_version: u8| {
+        let mut data = output.data.clone();
+        data[7] = osabi;
+        data[8] = abi_version;
+        data
+    };
+    let fixtures = [
+        (0, 0, branded(0, 0)),
+        (ELFOSABI_GNU, 0, branded(ELFOSABI_GNU, 0)),
+        (ELFOSABI_FREEBSD, 1, branded(ELFOSABI_FREEBSD, 1)),
+        (BOGUS, 0, branded(BOGUS, 0)),
+    ];
+
+    let policies = [
+        (OsAbiPolicy::AcceptAll, [true, true, true, true]),
+        (OsAbiPolicy::SysvAndGnuOnly, [true, true, false, false]),
+        (
+            OsAbiPolicy::Custom(|osabi, abi_version| osabi == ELFOSABI_FREEBSD && abi_version == 1),
+            [false, false, true, false],
+        ),
+    ];
+    for (policy, accepted) in policies {
+        let mut loader = Loader::new();
+        loader.set_osabi_policy(policy);
+        for ((osabi, abi_version, data), accepted) in fixtures.iter().zip(accepted) {
+            let result = loader.load_dylib(ElfBinary::new("libbranded.so", data));
+            if accepted {
+                let lib = result.expect("Failed to load library");
+                assert_eq!((lib.osabi(), lib.abi_version()), (*osabi, *abi_version));
+            } else {
+                let err = result.unwrap_err();
+                assert!(matches!(err, Error::ParseEhdr { .. }), "{err}");
+                assert!(err.to_string().contains("libbranded.so"), "{err}");
+            }
+            assert_eq!(
+                loader.probe(ElfBinary::new("libbranded.so", data)).is_ok(),
+                accepted
+            );
+        }
+    }
+}
+
+#[test]
+fn unknown_dynamic_policy() {
+    use elf_loader::UnknownDynamicPolicy;
+    use gen_elf::ElfWriterConfig;
+
+    const VENDOR_TAG: i64 = 0x6000_1234;
+    const PROC_TAG: i64 = 0x7000_0042;
+    // `DT_VALRNGLO`, in the GNU range above `DT_HIOS`
+    const GNU_TAG: i64 = 0x6fff_fd00;
+
+    let config = ElfWriterConfig::default()
+        .with_dynamic_entry(VENDOR_TAG, 0xdead)
+        .with_dynamic_entry(PROC_TAG, 7)
+        .with_dynamic_entry(GNU_TAG, 1)
+        .with_dynamic_entry(VENDOR_TAG, 0xbeef);
+    let output = DylibWriter::with_config(Arch::current(), config)
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF");
+    let plain = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF");
+
+    let mut loader = Loader::new();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libvendor.so", &output.data))
+        .expect("Failed to load library");
+    assert!(lib.vendor_dynamic_entries().is_empty());
+
+    loader.on_unknown_dynamic(UnknownDynamicPolicy::Collect);
+    let lib = loader
+        .load_dylib(ElfBinary::new("libvendor.so", &output.data))
+        .expect("Failed to load library");
+    // Tags the loader interprets, and the GNU range, are not collected
+    assert_eq!(
+        lib.vendor_dynamic_entries(),
+        &[(VENDOR_TAG, 0xdead), (PROC_TAG, 7), (VENDOR_TAG, 0xbeef)]
+    );
+
+    loader.on_unknown_dynamic(UnknownDynamicPolicy::Error);
+    let err = loader
+        .load_dylib(ElfBinary::new("libvendor.so", &output.data))
+        .unwrap_err();
+    assert!(matches!(err, Error::ParseDynamic { .. }), "{err}");
+    let msg = err.to_string();
+    assert!(
+        msg.contains("0x60001234") && msg.contains("0x70000042"),
+        "{msg}"
+    );
+    // Files without such tags still load
+    loader
+        .load_dylib(ElfBinary::new("libplain.so", &plain.data))
+        .expect("Failed to load library");
+}
+
+/// Runs the test `name` again in a child process with `ELF_LOADER_RUN_EXEC`
+/// set, as the executable it starts replaces the running program
+#[cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+fn exec_in_child(name: &str, path: &str, run: fn(&str) -> !) -> String {
+    use std::{env, path::Path, process::Command};
+
+    // Built by build.rs, a missing file means the C toolchain failed
+    assert!(Path::new(path).exists(), "{path} was not built");
+    if env::var_os("ELF_LOADER_RUN_EXEC").is_some() {
+        run(path);
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args([name, "--exact", "--nocapture", "--test-threads=1"])
+        .env("ELF_LOADER_RUN_EXEC", "1")
+        .output()
+        .expect("Failed to spawn the child process");
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+#[cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+fn run_static_exec() {
+    use elf_loader::os::exec::StackBuilder;
+
+    let path = concat!(env!("TEST_ARTIFACTS"), "/hello_static");
+    let stdout = exec_in_child("run_static_exec", path, |path| {
+        let exec = Loader::new()
+            .load_exec(path)
+            .expect("Failed to load the executable")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate the executable");
+        assert!(exec.is_static());
+        let stack = StackBuilder::new([path], ["HOME=/"])
+            .stack_size(0x10000)
+            .build(&exec)
+            .expect("Failed to build the stack");
+        unsafe { exec.run(stack) }
+    });
+    assert!(stdout.contains("hello from a static executable"));
+}
+
+#[test]
+#[cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+fn run_dynamic_exec() {
+    use elf_loader::os::exec::StackBuilder;
+
+    let path = concat!(env!("TEST_ARTIFACTS"), "/hello_pie");
+    let stdout = exec_in_child("run_dynamic_exec", path, |path| {
+        let mut loader = Loader::new();
+        let exec = loader
+            .load_exec(path)
+            .expect("Failed to load the executable");
+        // The interpreter relocates the executable and maps its dependencies
+        let interp = exec.interp().expect("Missing PT_INTERP").to_owned();
+        let interp = loader
+            .load_dylib(interp.as_str())
+            .expect("Failed to load the interpreter");
+        let stack = StackBuilder::new([path], ["HOME=/"])
+            .interpreter(interp)
+            .build_raw(&exec)
+            .expect("Failed to build the stack");
+        unsafe { exec.run(stack) }
+    });
+    // Built from the same source as the static executable
+    assert!(stdout.contains("hello from a static executable"));
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn classify_pie() {
+    use elf_loader::image::RawElf;
+    use std::path::Path;
+
+    // Built by build.rs when the C toolchain supports it
+    for (path, interp) in [
+        ("target/hello_static_pie", false),
+        ("target/hello_pie", true),
+    ] {
+        if !Path::new(path).exists() {
+            continue;
+        }
+        let Ok(RawElf::Exec(exec)) = Loader::new().load(path) else {
+            panic!("{path} is not loaded as an executable");
+        };
+        assert_eq!(exec.interp().is_some(), interp, "{path}");
+    }
+
+    // A library without dependencies or an entry point
+    let data = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_func("func", &[0xc3])])
+        .expect("Failed to generate ELF")
+        .data;
+    let lib = Loader::new().load(ElfBinary::new("libplain.so", &data));
+    assert!(matches!(lib, Ok(RawElf::Dylib(_))));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn guard_pages() {
+    use std::{env, os::unix::process::ExitStatusExt, process::Command};
+
+    const PAGE_SIZE: usize = 0x1000;
+    const CHILD: &str = "ELF_LOADER_GUARD_WRITE";
+    const SIGSEGV: i32 = 11;
+
+    let data = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF")
+        .data;
+    let mut loader = Loader::new();
+    loader.guard_pages(1);
+    let lib = loader
+        .load_dylib(ElfBinary::new("libguard.so", &data))
+        .expect("Failed to load the library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate the library");
+
+    // The write faults, so it is made in a child test process
+    if let Some(side) = env::var_os(CHILD) {
+        let addr = if side == "after" {
+            lib.base() + lib.mapped_len()
+        } else {
+            lib.base() - 1
+        };
+        unsafe { (addr as *mut u8).write_volatile(1) };
+        return;
+    }
+
+    // The guards are reserved but not reported
+    let mut unguarded = Loader::new();
+    let plain = unguarded
+        .load_dylib(ElfBinary::new("libplain.so", &data))
+        .expect("Failed to load the library");
+    assert_eq!(lib.mapped_len(), plain.mapped_len());
+    assert_eq!(
+        loader.mapped_bytes(),
+        unguarded.mapped_bytes() + 2 * PAGE_SIZE
+    );
+    drop(lib);
+    assert_eq!(loader.mapped_bytes(), 0);
+
+    for side in ["before", "after"] {
+        let output = Command::new(env::current_exe().unwrap())
+            .args(["guard_pages", "--exact", "--nocapture", "--test-threads=1"])
+            .env(CHILD, side)
+            .output()
+            .expect("Failed to spawn the child process");
+        assert_eq!(output.status.signal(), Some(SIGSEGV), "{side}");
+    }
+}
+
+#[test]
+#[cfg(feature = "compress")]
+fn compressed_reader() {
+    use elf_loader::input::{CompressedReader, ElfReader};
+    use std::{
+        cell::Cell,
+        io::{Read, Write},
+        rc::Rc,
+    };
+
+    /// Counts the compressed bytes the decoder pulls
+    struct Counting<'a>(&'a [u8], Rc<Cell<usize>>);
+
+    impl Read for Counting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = Read::read(&mut self.0, buf)?;
+            self.1.set(self.1.get() + n);
+            Ok(n)
+        }
+    }
+
+    let mut data = DylibWriter::new(Arch::current())
+        .write(
+            &[],
+            &[SymbolDesc::global_object("var", &42u64.to_ne_bytes())],
+        )
+        .expect("Failed to generate ELF")
+        .data;
+    let elf_len = data.len();
+    // Incompressible trailing data stands in for debug sections
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    data.extend((0..0x40_0000).map(|_| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as u8
+    }));
+
+    let zstd = zstd::encode_all(data.as_slice(), 0).unwrap();
+    let consumed = Rc::new(Cell::new(0));
+    let lib = Loader::new()
+        .load_dylib(
+            CompressedReader::zstd(Counting(&zstd, consumed.clone()))
+                .unwrap()
+                .with_name("libzstd.so"),
+        )
+        .expect("Failed to load the zstd library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate the zstd library");
+    assert_eq!(lib.name(), "libzstd.so");
+    assert_eq!(unsafe { **lib.get::<*const u64>("var").unwrap() }, 42);
+    assert!(consumed.get() < zstd.len() / 2);
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder.write_all(&data).unwrap();
+    let gzip = encoder.finish().unwrap();
+    let mut reader = CompressedReader::gzip(gzip.as_slice());
+    let mut magic = [0u8; 4];
+    reader.read(&mut magic, 0).unwrap();
+    assert_eq!(&magic, b"\x7fELF");
+    assert!(reader.decompressed_so_far() < elf_len + 0x10000 + 1);
+    let lib = Loader::new()
+        .load_dylib(reader)
+        .expect("Failed to load the gzip library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate the gzip library");
+    assert_eq!(unsafe { **lib.get::<*const u64>("var").unwrap() }, 42);
+
+    // Reads past the end of the stream fail
+    let mut reader = CompressedReader::gzip(gzip.as_slice());
+    assert!(reader.read(&mut magic, data.len() - 2).is_err());
+    assert_eq!(reader.decompressed_so_far(), data.len());
+}
+
+#[test]
+fn segments_layout() {
+    use elf_loader::os::ProtFlags;
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let data = DylibWriter::new(Arch::current())
+        .write(
+            &[],
+            &[
+                SymbolDesc::global_func("func", &[0xc3]),
+                SymbolDesc::global_object("var", &[7u8; 8]),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    let mut loader = Loader::new();
+    let mut load = |name: &str| {
+        loader
+            .load_dylib(ElfBinary::new(name, &data.data))
+            .expect("Failed to load library")
+            .relocator()
+            .relocate()
+            .expect("Failed to relocate library")
+    };
+    let (first, second) = (load("libfirst.so"), load("libsecond.so"));
+    assert_ne!(first.base(), second.base());
+
+    let text_hash = |lib: &elf_loader::image::LoadedDylib<()>| {
+        let layout = lib.segments_layout();
+        let text = layout
+            .iter()
+            .find(|segment| segment.prot.contains(ProtFlags::PROT_EXEC))
+            .expect("missing text segment");
+        assert!(text.from_file && text.file_range.is_some());
+        assert!(lib.contains_addr(text.vaddr_range.start));
+        assert!(lib.read_only_ranges().iter().any(
+            |range| range.start <= text.vaddr_range.start && text.vaddr_range.end <= range.end
+        ));
+        let bytes = unsafe {
+            std::slice::from_raw_parts(text.vaddr_range.start as *const u8, text.vaddr_range.len())
+        };
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(text_hash(&first), text_hash(&second));
+
+    // The writable data is not part of the read-only ranges
+    let var = unsafe { first.get::<u8>("var").unwrap().into_raw() } as usize;
+    let writable = first
+        .segments_layout()
+        .into_iter()
+        .find(|segment| segment.vaddr_range.contains(&var))
+        .expect("missing data segment");
+    assert!(writable.prot.contains(ProtFlags::PROT_WRITE));
+    assert!(
+        !first
+            .read_only_ranges()
+            .iter()
+            .any(|range| range.contains(&var))
+    );
+}
+
+#[test]
+fn name_survives_relocation() {
+    let data = DylibWriter::new(Arch::current())
+        .write(&[], &[SymbolDesc::global_object("var", &[0; 8])])
+        .expect("Failed to generate ELF");
+    let name = String::from("libname.so");
+    let mut loader = Loader::new();
+    let raw = loader
+        .load_dylib(ElfBinary::new(&name, &data.data))
+        .expect("Failed to load library");
+    drop(name);
+
+    // Parsing the dynamic section moves the name into the module
+    let _ = raw.soname();
+    assert_eq!(raw.name().as_bytes(), b"libname.so");
+    let raw = Box::new(raw);
+    let lib = raw
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    assert_eq!(lib.name().as_bytes(), b"libname.so");
+}
+
+/// Returns a library with a relocation and the end of its last segment in the file
+fn truncation_fixture() -> (Vec<u8>, usize) {
+    use elf_loader::elf::PT_LOAD;
+    use gen_elf::RelocEntry;
+
+    let data = DylibWriter::new(Arch::current())
+        .write(
+            &[RelocEntry::with_name("ext", elf_loader::arch::REL_GOT)],
+            &[
+                SymbolDesc::global_object("var", &[7u8; 8]),
+                SymbolDesc::undefined_object("ext"),
+            ],
+        )
+        .expect("Failed to generate ELF")
+        .data;
+    let file_end = phdr_offsets(&data, PT_LOAD)
+        .into_iter()
+        .map(|off| (read_u64(&data, off + 8) + read_u64(&data, off + 32)) as usize)
+        .max()
+        .unwrap();
+    (data, file_end)
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn truncated_input_fails() {
+    let (data, file_end) = truncation_fixture();
+    let phdr_end = {
+        let phnum = u16::from_le_bytes([data[0x38], data[0x39]]) as usize;
+        read_u64(&data, 0x20) as usize + phnum * 56
+    };
+
+    // Truncated at lengths picked by a fixed xorshift sequence, with the
+    // garbage a partial download may leave behind
+    let mut loader = Loader::new();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut rejected = 0;
+    for _ in 0..256 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let len = state as usize % file_end;
+        let mut truncated = data[..len].to_vec();
+        if state & 1 == 1 {
+            let garbage = (state >> 8) as usize % 64;
+            truncated.extend(core::iter::repeat_n(0xa5, garbage.min(file_end - len - 1)));
+        }
+        match loader.load(ElfBinary::new("libtruncated.so", &truncated)) {
+            Ok(_) => panic!("truncated to {len} bytes"),
+            Err(Error::Truncated { needed, actual }) => {
+                assert!(len >= phdr_end);
+                assert_eq!(actual, truncated.len());
+                assert!(needed > actual && needed <= file_end);
+                rejected += 1;
+            }
+            Err(_) => {}
+        }
+    }
+    assert!(rejected > 0);
+}
+
+#[test]
+#[cfg(all(feature = "std", target_pointer_width = "64"))]
+fn truncated_stream_fails() {
+    use elf_loader::input::ElfStream;
+    use std::io::Cursor;
+
+    // A stream is only checked when it is given a length
+    let (data, file_end) = truncation_fixture();
+    let mut loader = Loader::new();
+    let truncated = &data[..file_end - 1];
+    let stream = ElfStream::new("libtruncated.so", Cursor::new(truncated));
+    assert!(matches!(
+        loader.load_dylib(stream.with_len(truncated.len())),
+        Err(Error::Truncated { needed, actual }) if needed == file_end && actual == file_end - 1
+    ));
+    let stream = ElfStream::new("libtruncated.so", Cursor::new(truncated));
+    assert!(!matches!(
+        loader.load_dylib(stream),
+        Err(Error::Truncated { .. }) | Ok(_)
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn stream_reader() {
+    use elf_loader::input::ElfStream;
+    use object::elf::PT_LOAD;
+    use std::{
+        io::{Cursor, Read, Seek, SeekFrom},
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    /// A stream counting the bytes read from it
+    struct Counting(Cursor<Vec<u8>>, Arc<AtomicUsize>);
+
+    impl Read for Counting {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.0.read(buf)?;
+            self.1.fetch_add(len, Ordering::Relaxed);
+            Ok(len)
+        }
+    }
+
+    impl Seek for Counting {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    let arch = Arch::current();
+    let content: Vec<u8> = (0..=255).cycle().take(0x3000).collect();
+    let output = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object("var", &content)])
+        .expect("Failed to generate ELF");
+    // Data past the last segment, like debug information, is never fetched
+    let mut data = output.data.clone();
+    data.resize(data.len() + 0x40000, 0xcc);
+    let len = data.len();
+
+    let bytes_read = Arc::new(AtomicUsize::new(0));
+    let stream = ElfStream::new(
+        "libstream.so",
+        Counting(Cursor::new(data), bytes_read.clone()),
+    );
+    let raw = Loader::new()
+        .load_dylib(stream)
+        .expect("Failed to load library");
+    // Segment reads start at page boundaries, so up to a page of padding is read per segment
+    let loads = raw.phdrs().iter().filter(|phdr| phdr.p_type == PT_LOAD);
+    let filesz: usize = loads.clone().map(|phdr| phdr.p_filesz as usize).sum();
+    let bound = filesz + loads.count() * 0x1000;
+
+    let lib = raw
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let var = unsafe { lib.get::<()>("var") }.expect("Symbol not found");
+    let var = unsafe { &*(var.into_raw() as *const [u8; 0x3000]) };
+    assert_eq!(&var[..], &content[..]);
+
+    let bytes_read = bytes_read.load(Ordering::Relaxed);
+    assert!(
+        bytes_read <= bound,
+        "read {bytes_read} bytes, expected at most {bound}"
+    );
+    assert!(bytes_read < len / 4);
+}
+
+#[test]
+fn execstack_policy() {
+    use elf::abi::{PF_R, PF_W, PF_X};
+    use elf_loader::{ExecStackPolicy, os::ProtFlags};
+    use gen_elf::ElfWriterConfig;
+
+    let arch = Arch::current();
+    let write = |flags: u32| {
+        DylibWriter::with_config(arch, ElfWriterConfig::default().with_gnu_stack(flags))
+            .write(&[], &[SymbolDesc::global_object("var", &[0; 8])])
+            .expect("Failed to generate ELF")
+    };
+    let execstack = write(PF_R | PF_W | PF_X);
+    let noexec = write(PF_R | PF_W);
+
+    let mut loader = Loader::new();
+    for policy in [
+        ExecStackPolicy::Allow,
+        ExecStackPolicy::Warn,
+        ExecStackPolicy::Deny,
+    ] {
+        loader.set_execstack_policy(policy);
+        let lib = loader
+            .load_dylib(ElfBinary::new("libnoexec.so", &noexec.data))
+            .expect("Failed to load library");
+        assert_eq!(
+            lib.stack_flags(),
+            Some(ProtFlags::PROT_READ | ProtFlags::PROT_WRITE)
+        );
+
+        let res = loader.load_dylib(ElfBinary::new("libexecstack.so", &execstack.data));
+        if policy == ExecStackPolicy::Deny {
+            let msg = res.unwrap_err().to_string();
+            assert!(msg.contains("libexecstack.so"), "{msg}");
+        } else {
+            let lib = res.expect("Failed to load library");
+            assert!(lib.stack_flags().unwrap().contains(ProtFlags::PROT_EXEC));
+        }
+    }
+}
+
+#[test]
+fn segment_alignment() {
+    use gen_elf::ElfWriterConfig;
+
+    const ALIGN: usize = 0x10000;
+
+    let arch = Arch::current();
+    let config = ElfWriterConfig::default().with_page_size(ALIGN as u64);
+    let output = DylibWriter::with_config(arch, config)
+        .write(&[], &[SymbolDesc::global_object("var", &[0u8; 8])])
+        .expect("Failed to generate ELF");
+
+    // A single aligned base could be luck, so keep several alive at once
+    let mut loader = Loader::new();
+    let libs: Vec<_> = (0..8)
+        .map(|i| {
+            loader
+                .load_dylib(ElfBinary::new(&format!("libalign{i}.so"), &output.data))
+                .expect("Failed to load library")
+                .relocator()
+                .relocate()
+                .expect("Failed to relocate library")
+        })
+        .collect();
+    for lib in &libs {
+        assert_eq!(lib.base() % ALIGN, 0, "{:#x}", lib.base());
+        assert!(lib.load_align() >= ALIGN);
+        let var = unsafe { lib.get::<u64>("var").unwrap().into_raw() } as usize;
+        assert!(lib.contains_addr(var));
+    }
+}
+
+#[test]
+fn gnu_properties() {
+    use elf::abi::{GNU_PROPERTY_AARCH64_FEATURE_1_AND, GNU_PROPERTY_AARCH64_FEATURE_1_BTI};
+    use gen_elf::ElfWriterConfig;
+
+    const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+    const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 0x2;
+
+    let arch = Arch::current();
+    let write = |config: ElfWriterConfig| {
+        DylibWriter::with_config(arch, config)
+            .write(&[], &[SymbolDesc::global_object("var", &[0; 8])])
+            .expect("Failed to generate ELF")
+    };
+    let mut loader = Loader::new();
+
+    let plain = write(ElfWriterConfig::default());
+    let lib = loader
+        .load_dylib(ElfBinary::new("libplain.so", &plain.data))
+        .expect("Failed to load library");
+    assert_eq!(lib.gnu_properties(), None);
+
+    // An unknown property between the known ones is skipped
+    let marked = write(
+        ElfWriterConfig::default()
+            .with_gnu_property(
+                GNU_PROPERTY_X86_FEATURE_1_AND,
+                GNU_PROPERTY_X86_FEATURE_1_SHSTK,
+            )
+            .with_gnu_property(0xc000_8000, u32::MAX)
+            .with_gnu_property(
+                GNU_PROPERTY_AARCH64_FEATURE_1_AND,
+                GNU_PROPERTY_AARCH64_FEATURE_1_BTI,
+            ),
+    );
+    let lib = loader
+        .load_dylib(ElfBinary::new("libmarked.so", &marked.data))
+        .expect("Failed to load library");
+    let props = lib.gnu_properties().expect("missing PT_GNU_PROPERTY");
+    assert!(props.has_shstk() && !props.has_ibt());
+    assert!(props.has_bti() && !props.has_pac());
+    assert_eq!(props.x86_feature_1_and(), GNU_PROPERTY_X86_FEATURE_1_SHSTK);
+    assert_eq!(
+        props.aarch64_feature_1_and(),
+        GNU_PROPERTY_AARCH64_FEATURE_1_BTI
+    );
+}
+
+#[test]
+fn build_id() {
+    use elf::abi::NT_GNU_BUILD_ID;
+    use gen_elf::ElfWriterConfig;
+
+    const BUILD_ID: [u8; 20] = *b"\x01\x23\x45\x67\x89\xab\xcd\xef0123456789ab";
+
+    let arch = Arch::current();
+    let write = |config: ElfWriterConfig| {
+        DylibWriter::with_config(arch, config)
+            .write(&[], &[SymbolDesc::global_object("var", &[0; 8])])
+            .expect("Failed to generate ELF")
+    };
+    let mut loader = Loader::new();
+
+    let plain = write(ElfWriterConfig::default());
+    let lib = loader
+        .load_dylib(ElfBinary::new("libplain.so", &plain.data))
+        .expect("Failed to load library");
+    assert_eq!(lib.build_id(), None);
+    assert_eq!(lib.note_iter().count(), 0);
+
+    // The note is read from the mapping in the first case and from the file in the second
+    for config in [
+        ElfWriterConfig::default().with_build_id(BUILD_ID),
+        ElfWriterConfig::default().with_unmapped_build_id(BUILD_ID),
+    ] {
+        let output = write(config);
+        let lib = loader
+            .load_dylib(ElfBinary::new("libbuildid.so", &output.data))
+            .expect("Failed to load library");
+        assert_eq!(lib.build_id(), Some(&BUILD_ID[..]));
+        let notes: Vec<_> = lib
+            .note_iter()
+            .map(|note| (note.name().to_vec(), note.n_type(), note.desc().to_vec()))
+            .collect();
+        assert_eq!(
+            notes,
+            [(b"GNU".to_vec(), NT_GNU_BUILD_ID as u32, BUILD_ID.to_vec())]
+        );
+
+        // The build-id outlives the input it was read from
+        drop(output);
+        let lib = lib.relocator().relocate().expect("Failed to relocate");
+        assert_eq!(lib.build_id(), Some(&BUILD_ID[..]));
+    }
 }
@@ -0,0 +1,66 @@
+#![cfg(feature = "aarch64-pac")]
+
+use elf_loader::{
+    Loader,
+    arch::{REL_GOT, REL_JUMP_SLOT},
+    input::ElfBinary,
+};
+use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+
+extern "C" fn answer() -> u64 {
+    42
+}
+
+#[test]
+fn pac_stripping() {
+    let arch = Arch::current();
+    let provider = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object("value", &[7; 8])])
+        .unwrap();
+    let user = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name("value", REL_GOT),
+                RelocEntry::with_name("answer", REL_JUMP_SLOT),
+            ],
+            &[
+                SymbolDesc::undefined_object("value"),
+                SymbolDesc::undefined_func("answer"),
+            ],
+        )
+        .unwrap();
+
+    let mut loader = Loader::new();
+    loader.enable_pac(true);
+    let provider = loader
+        .load_dylib(ElfBinary::new("libprovider.so", &provider.data))
+        .unwrap()
+        .relocator()
+        .relocate()
+        .unwrap();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libuser.so", &user.data))
+        .unwrap()
+        .relocator()
+        .scope([&provider])
+        .lazy(true)
+        .lazy_scope(|name: &str| (name == "answer").then_some(answer as *const ()))
+        .relocate()
+        .unwrap();
+
+    // Addresses without an authentication code are left alone
+    let value = unsafe { provider.get::<u64>("value") }.unwrap().into_raw();
+    let slot = (lib.base() + user.relocations[0].vaddr as usize) as *const usize;
+    assert_eq!(unsafe { slot.read() }, value as usize);
+
+    let helper = unsafe { lib.get::<extern "C" fn() -> u64>("answer@helper") }.unwrap();
+    assert_eq!(helper(), 42);
+    let slot = (lib.base() + user.relocations[1].vaddr as usize) as *const usize;
+    assert_eq!(unsafe { slot.read() }, answer as *const () as usize);
+
+    // Signing does nothing without pointer authentication
+    let signed = helper.clone().sign_ia().into_raw();
+    if cfg!(not(target_arch = "aarch64")) {
+        assert_eq!(signed, helper.into_raw());
+    }
+}
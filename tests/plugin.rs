@@ -0,0 +1,42 @@
+#![cfg(all(feature = "plugin-abi", target_os = "linux"))]
+
+use elf_loader::{Loader, image::LoadedDylib, plugin::AbiError};
+
+/// The entry point type of `libplugin.so`
+type PluginEntry = extern "C" fn(u32) -> u32;
+
+fn load(name: &str) -> LoadedDylib<()> {
+    Loader::new()
+        .load_dylib(format!("{}/{name}", env!("TEST_ARTIFACTS")))
+        .unwrap()
+        .relocator()
+        .relocate()
+        .unwrap()
+}
+
+#[test]
+fn plugin_entry_point() {
+    let plugin = load("libplugin.so");
+    let entry = unsafe { plugin.entry_point::<PluginEntry>("plugin_entry") }.unwrap();
+    assert_eq!(entry(41), 42);
+
+    // A signature that differs from the declared one
+    let err = unsafe { plugin.entry_point::<extern "C" fn(u64) -> u64>("plugin_entry") }
+        .err()
+        .unwrap();
+    assert!(matches!(err, AbiError::Mismatch { expected, found } if expected != found));
+
+    assert_eq!(
+        unsafe { plugin.entry_point::<PluginEntry>("missing_entry") }.err(),
+        Some(AbiError::MissingEntry)
+    );
+}
+
+#[test]
+fn plugin_without_abi() {
+    let lib = load("liba.so");
+    assert_eq!(
+        unsafe { lib.entry_point::<PluginEntry>("a") }.err(),
+        Some(AbiError::MissingAbi)
+    );
+}
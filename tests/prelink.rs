@@ -0,0 +1,284 @@
+#![cfg(feature = "prelink")]
+
+use elf_loader::{
+    Error, Loader,
+    arch::{REL_GOT, REL_RELATIVE, REL_SYMBOLIC},
+    image::LoadedDylib,
+    input::ElfBinary,
+};
+use gen_elf::{Arch, DylibWriter, ElfWriteOutput, ElfWriterConfig, RelocEntry, SymbolDesc};
+
+const HOST_VAR: &str = "host_var";
+const PLUGIN_VAR: &str = "plugin_var";
+const PLUGIN_FUNC: &str = "plugin_func";
+
+/// Builds a host library and a plugin that uses a variable of the host, its own
+/// variable and a relative relocation
+fn build() -> (Vec<u8>, ElfWriteOutput) {
+    let arch = Arch::current();
+    let host = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object(HOST_VAR, &[1; 8])])
+        .expect("Failed to generate ELF");
+    let plugin = DylibWriter::new(arch)
+        .write(
+            &[
+                RelocEntry::with_name(HOST_VAR, REL_GOT),
+                RelocEntry::with_name(PLUGIN_VAR, REL_SYMBOLIC),
+                RelocEntry::new(REL_RELATIVE),
+            ],
+            &[
+                SymbolDesc::undefined_object(HOST_VAR),
+                SymbolDesc::global_object(PLUGIN_VAR, &[2; 8]),
+                SymbolDesc::global_func(PLUGIN_FUNC, &[0xc3]),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    (host.data, plugin)
+}
+
+fn serialize(lib: &LoadedDylib<()>) -> Vec<u8> {
+    let mut blob = Vec::new();
+    lib.serialize_prelinked(|bytes| {
+        blob.extend_from_slice(bytes);
+        Ok(())
+    })
+    .expect("Failed to serialize library");
+    blob
+}
+
+/// Reads the relocated words of `lib`, relative to its base when they point into it
+fn slots(lib: &LoadedDylib<()>, plugin: &ElfWriteOutput) -> Vec<usize> {
+    let range = lib.base()..lib.base() + lib.mapped_len();
+    plugin
+        .relocations
+        .iter()
+        .map(|reloc| {
+            let value = unsafe { ((lib.base() + reloc.vaddr as usize) as *const usize).read() };
+            if range.contains(&value) {
+                value - lib.base()
+            } else {
+                value
+            }
+        })
+        .collect()
+}
+
+fn symbols(lib: &LoadedDylib<()>) -> Vec<usize> {
+    [PLUGIN_VAR, PLUGIN_FUNC]
+        .iter()
+        .map(|name| unsafe { lib.get::<()>(name).unwrap().into_raw() as usize })
+        .collect()
+}
+
+#[test]
+fn prelinked_round_trip() {
+    let (host_data, plugin_data) = build();
+    let mut loader = Loader::new();
+    let host = loader
+        .load_dylib(ElfBinary::new("libhost.so", &host_data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let plugin = loader
+        .load_dylib(ElfBinary::new("libplugin.so", &plugin_data.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&host])
+        .lazy(false)
+        .relocate()
+        .expect("Failed to relocate library");
+    let blob = serialize(&plugin);
+    let base = plugin.base();
+    let expected_symbols = symbols(&plugin);
+    let expected_slots = slots(&plugin, &plugin_data);
+    let host_var = unsafe { host.get::<()>(HOST_VAR).unwrap().into_raw() as usize };
+    assert!(expected_slots.contains(&host_var));
+    drop(plugin);
+
+    // The freed range is reused, so the symbols are where they were
+    let deps = [(*host).clone()];
+    let reloaded = loader
+        .load_prelinked(ElfBinary::new("libplugin.so", &blob), Some(base), &deps)
+        .expect("Failed to load prelinked library");
+    assert_eq!(reloaded.name(), "libplugin.so");
+    assert_eq!(reloaded.base(), base);
+    assert_eq!(symbols(&reloaded), expected_symbols);
+    assert_eq!(slots(&reloaded, &plugin_data), expected_slots);
+    assert_eq!(reloaded.deps().len(), 1);
+
+    // The range is taken now, so the second copy is rebased through the fixups
+    let rebased = loader
+        .load_prelinked(ElfBinary::new("libplugin.so", &blob), Some(base), &deps)
+        .expect("Failed to load prelinked library");
+    assert_ne!(rebased.base(), base);
+    let delta = rebased.base().wrapping_sub(base);
+    let moved: Vec<_> = expected_symbols
+        .iter()
+        .map(|addr| addr.wrapping_add(delta))
+        .collect();
+    assert_eq!(symbols(&rebased), moved);
+    assert_eq!(slots(&rebased, &plugin_data), expected_slots);
+}
+
+#[test]
+fn prelinked_dependency_mismatch() {
+    let (host_data, plugin_data) = build();
+    let mut loader = Loader::new();
+    let host = loader
+        .load_dylib(ElfBinary::new("libhost.so", &host_data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let plugin = loader
+        .load_dylib(ElfBinary::new("libplugin.so", &plugin_data.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&host])
+        .lazy(false)
+        .relocate()
+        .expect("Failed to relocate library");
+    let blob = serialize(&plugin);
+
+    // Another copy of the host lives at another address
+    let other_host = loader
+        .load_dylib(ElfBinary::new("libhost.so", &host_data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    for deps in [vec![], vec![(*other_host).clone()]] {
+        let err = loader
+            .load_prelinked(ElfBinary::new("libplugin.so", &blob), None, &deps)
+            .unwrap_err();
+        assert!(matches!(err, Error::Prelink { .. }), "{err}");
+    }
+
+    let err = loader
+        .load_prelinked(ElfBinary::new("libplugin.so", &plugin_data.data), None, &[])
+        .unwrap_err();
+    assert!(matches!(err, Error::Prelink { .. }), "{err}");
+}
+
+#[test]
+fn prelinked_constructors() {
+    use std::sync::{Arc, Mutex};
+
+    const DT_INIT: i64 = 12;
+    const DT_FINI: i64 = 13;
+    // The handlers below record the functions instead of calling them
+    const CTOR: u64 = 0x40;
+
+    let plugin = DylibWriter::with_config(
+        Arch::current(),
+        ElfWriterConfig::default()
+            .with_dynamic_entry(DT_INIT, CTOR)
+            .with_dynamic_entry(DT_FINI, CTOR),
+    )
+    .write(&[], &[SymbolDesc::global_object(PLUGIN_VAR, &[2; 8])])
+    .expect("Failed to generate ELF");
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let mut loader = Loader::new();
+    let (init_calls, fini_calls) = (calls.clone(), calls.clone());
+    loader.with_init(Arc::new(move |func, _| {
+        init_calls
+            .lock()
+            .unwrap()
+            .push(("init", func.map(|func| func as usize)));
+    }));
+    loader.with_fini(Arc::new(move |func, _| {
+        fini_calls
+            .lock()
+            .unwrap()
+            .push(("fini", func.map(|func| func as usize)));
+    }));
+    let ctor = |lib: &LoadedDylib<()>| Some(lib.base() + CTOR as usize);
+
+    // The image is written from pre_init, before the constructors run
+    let blob = Arc::new(Mutex::new(Vec::new()));
+    let out = blob.clone();
+    let lib = loader
+        .load_dylib(ElfBinary::new("libplugin.so", &plugin.data))
+        .expect("Failed to load library")
+        .relocator()
+        .lazy(false)
+        .pre_init(move |lib| {
+            lib.serialize_prelinked(|bytes| {
+                out.lock().unwrap().extend_from_slice(bytes);
+                Ok(())
+            })
+        })
+        .relocate()
+        .expect("Failed to relocate library");
+    assert_eq!(*calls.lock().unwrap(), [("init", ctor(&lib))]);
+
+    // Afterwards the state of the constructors would be written too
+    let err = lib.serialize_prelinked(|_| Ok(())).unwrap_err();
+    assert!(matches!(err, Error::Prelink { .. }), "{err}");
+
+    // Loading the image runs the constructors again, and dropping it the destructors
+    let blob = blob.lock().unwrap().clone();
+    let reloaded = loader
+        .load_prelinked(ElfBinary::new("libplugin.so", &blob), None, &[])
+        .expect("Failed to load prelinked library");
+    let expected = [("init", ctor(&lib)), ("init", ctor(&reloaded))];
+    assert_eq!(*calls.lock().unwrap(), expected);
+    let expected = [expected[0], expected[1], ("fini", ctor(&reloaded))];
+    drop(reloaded);
+    assert_eq!(*calls.lock().unwrap(), expected);
+}
+
+#[test]
+fn prelinked_corrupted_header() {
+    let (host_data, plugin_data) = build();
+    let mut loader = Loader::new();
+    let host = loader
+        .load_dylib(ElfBinary::new("libhost.so", &host_data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library");
+    let plugin = loader
+        .load_dylib(ElfBinary::new("libplugin.so", &plugin_data.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&host])
+        .lazy(false)
+        .relocate()
+        .expect("Failed to relocate library");
+    let blob = serialize(&plugin);
+    let deps = [(*host).clone()];
+
+    // Counts of segments, fixups and name bytes past what the blob holds
+    const COUNTS: usize = 12 * 8;
+    const NAME_LEN: usize = 11 * 8;
+    const PHNUM: usize = 9 * 8;
+    for (offset, value) in [
+        (COUNTS, u64::MAX),
+        (COUNTS, (1 << 32) | 0xffff),
+        (COUNTS, 0xffff_ffff),
+        (NAME_LEN, u64::MAX),
+        (NAME_LEN, 0x10_0000),
+        (PHNUM, u64::MAX / 2),
+    ] {
+        let mut blob = blob.clone();
+        blob[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+        let err = loader
+            .load_prelinked(ElfBinary::new("libplugin.so", &blob), None, &deps)
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Prelink { .. }),
+            "{offset} {value:#x}: {err}"
+        );
+    }
+
+    // A blob cut short is rejected
+    let short = &blob[..COUNTS + 8];
+    assert!(
+        loader
+            .load_prelinked(ElfBinary::new("libplugin.so", short), None, &deps)
+            .is_err()
+    );
+}
@@ -0,0 +1,157 @@
+//! Shared library handles used from several threads at once.
+//!
+//! Run under ThreadSanitizer with
+//! `RUSTFLAGS=-Zsanitizer=thread cargo +nightly test -Zbuild-std --target x86_64-unknown-linux-gnu --test threads`.
+use elf_loader::{
+    Loader,
+    arch::REL_JUMP_SLOT,
+    image::{LoadedDylib, OwnedSymbol},
+    input::ElfBinary,
+};
+use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+use std::sync::{
+    Arc, Barrier,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+const THREADS: usize = 4;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn handles_are_send_sync() {
+    assert_send_sync::<LoadedDylib<()>>();
+    assert_send_sync::<LoadedDylib<Arc<String>>>();
+    assert_send_sync::<OwnedSymbol<extern "C" fn() -> u64, ()>>();
+}
+
+extern "C" fn answer() -> u64 {
+    42
+}
+
+#[test]
+fn concurrent_lookups() {
+    let arch = Arch::current();
+    let provider = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object("value", &[7; 8])])
+        .unwrap();
+    let user = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name("answer", REL_JUMP_SLOT)],
+            &[
+                SymbolDesc::global_object("local", &[3; 8]),
+                SymbolDesc::undefined_func("answer"),
+            ],
+        )
+        .unwrap();
+
+    let mut loader = Loader::new();
+    let mut load = |data: &[u8]| {
+        loader
+            .load_dylib(ElfBinary::new("libshared.so", data))
+            .unwrap()
+            .relocator()
+            .lazy(true)
+            .lazy_scope(|name: &str| (name == "answer").then_some(answer as *const ()))
+            .relocate()
+            .unwrap()
+    };
+    let lib = load(&user.data);
+
+    // The workers look symbols up and bind the PLT lazily while the main
+    // thread loads and unloads other libraries and swaps the lazy fallback
+    struct Stop<'a>(&'a AtomicBool);
+    impl Drop for Stop<'_> {
+        // Stop the workers even if the main thread panics
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Release);
+        }
+    }
+    let done = AtomicBool::new(false);
+    let barrier = Barrier::new(THREADS + 1);
+    std::thread::scope(|s| {
+        let _stop = Stop(&done);
+        for _ in 0..THREADS {
+            let (lib, done, barrier) = (lib.clone(), &done, &barrier);
+            s.spawn(move || {
+                barrier.wait();
+                while !done.load(Ordering::Acquire) {
+                    let local = unsafe { lib.get::<()>("local") }.unwrap().into_raw();
+                    assert_eq!(unsafe { *local.cast::<[u8; 8]>() }, [3; 8]);
+                    let helper =
+                        unsafe { lib.get_owned::<extern "C" fn() -> u64>("answer@helper") }
+                            .unwrap();
+                    assert_eq!(helper(), 42);
+                    assert!(unsafe { lib.get::<()>("value") }.is_none());
+                }
+            });
+        }
+        barrier.wait();
+        for _ in 0..64 {
+            let provider = load(&provider.data);
+            let value = unsafe { provider.get::<()>("value") }.unwrap().into_raw();
+            assert_eq!(unsafe { *value.cast::<[u8; 8]>() }, [7; 8]);
+            lib.set_lazy_fallback(|_: &str| None);
+            drop(provider);
+        }
+    });
+}
+
+extern "C" fn other_answer() -> u64 {
+    43
+}
+
+#[test]
+fn concurrent_fixups_and_rebind() {
+    const FUNCS: [&str; 4] = ["answer0", "answer1", "answer2", "answer3"];
+    let output = DylibWriter::new(Arch::current())
+        .write(
+            &FUNCS.map(|name| RelocEntry::with_name(name, REL_JUMP_SLOT)),
+            &FUNCS.map(SymbolDesc::undefined_func),
+        )
+        .unwrap();
+
+    let mut loader = Loader::new();
+    for _ in 0..32 {
+        let lib = loader
+            .load_dylib(ElfBinary::new("libfixup.so", &output.data))
+            .unwrap()
+            .relocator()
+            .lazy(true)
+            .lazy_scope(|name: &str| FUNCS.contains(&name).then_some(answer as *const ()))
+            .relocate()
+            .unwrap();
+
+        // The workers bind every slot lazily while the main thread rebinds
+        // the first one, which no pending fixup may overwrite
+        let barrier = Barrier::new(THREADS + 1);
+        std::thread::scope(|s| {
+            for _ in 0..THREADS {
+                let (lib, barrier) = (&lib, &barrier);
+                s.spawn(move || {
+                    barrier.wait();
+                    for name in FUNCS {
+                        let helper =
+                            unsafe { lib.get::<extern "C" fn() -> u64>(&format!("{name}@helper")) }
+                                .unwrap();
+                        assert!(matches!(helper(), 42 | 43));
+                    }
+                });
+            }
+            barrier.wait();
+            let count = unsafe { lib.rebind_symbol(FUNCS[0], other_answer as *const ()) };
+            assert_eq!(count.unwrap(), 1);
+        });
+
+        for (name, reloc) in FUNCS.iter().zip(&output.relocations) {
+            let slot =
+                unsafe { AtomicUsize::from_ptr((lib.base() + reloc.vaddr as usize) as *mut usize) };
+            let expected = if *name == FUNCS[0] {
+                other_answer as *const () as usize
+            } else {
+                answer as *const () as usize
+            };
+            assert_eq!(slot.load(Ordering::Acquire), expected, "{name}");
+        }
+    }
+}
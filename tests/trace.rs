@@ -0,0 +1,72 @@
+#![cfg(feature = "trace")]
+
+use elf_loader::{
+    Loader,
+    arch::REL_GOT,
+    input::ElfBinary,
+    trace::{BindProvider, TraceEvent, set_trace_fn},
+};
+use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+use std::sync::Mutex;
+
+static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(event: TraceEvent<'_>) {
+    let line = match event {
+        TraceEvent::Mmap { .. } => "mmap".to_string(),
+        TraceEvent::Reloc {
+            lib,
+            r_type,
+            symbol,
+            ..
+        } if r_type == REL_GOT => format!("reloc {lib} {}", symbol.unwrap_or("")),
+        TraceEvent::SymbolBind {
+            lib,
+            symbol,
+            provider: BindProvider::Module(provider),
+        } => format!("bind {lib} {symbol} {provider}"),
+        _ => return,
+    };
+    EVENTS.lock().unwrap().push(line);
+}
+
+#[test]
+fn trace_events() {
+    let arch = Arch::current();
+    let libc = DylibWriter::new(arch)
+        .write(&[], &[SymbolDesc::global_object("errno", &[0; 8])])
+        .unwrap();
+    let libfoo = DylibWriter::new(arch)
+        .write(
+            &[RelocEntry::with_name("errno", REL_GOT)],
+            &[SymbolDesc::undefined_object("errno")],
+        )
+        .unwrap();
+
+    assert!(set_trace_fn(Some(record)).is_none());
+    let mut loader = Loader::new();
+    let libc = loader
+        .load_dylib(ElfBinary::new("libc.so", &libc.data))
+        .unwrap()
+        .relocator()
+        .relocate()
+        .unwrap();
+    let _libfoo = loader
+        .load_dylib(ElfBinary::new("libfoo.so", &libfoo.data))
+        .unwrap()
+        .relocator()
+        .scope([&libc])
+        .relocate()
+        .unwrap();
+    assert!(set_trace_fn(None).is_some());
+
+    let events = EVENTS.lock().unwrap();
+    assert!(events.iter().any(|line| line == "mmap"));
+    let reloc = events
+        .iter()
+        .position(|line| line == "reloc libfoo.so errno");
+    let bind = events
+        .iter()
+        .position(|line| line == "bind libfoo.so errno libc.so");
+    assert!(reloc.unwrap() < bind.unwrap(), "{events:?}");
+}
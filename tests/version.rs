@@ -0,0 +1,157 @@
+#![cfg(feature = "version")]
+
+use elf_loader::{
+    Error, Loader,
+    arch::REL_GOT,
+    image::LoadedDylib,
+    input::ElfBinary,
+    relocation::{Linker, VersionPolicy},
+};
+use gen_elf::{Arch, DylibWriter, ElfWriteOutput, ElfWriterConfig, RelocEntry, SymbolDesc};
+use object::{Object, ObjectSection};
+
+/// A libc that absorbed libpthread, like glibc 2.34 and later
+fn libc() -> LoadedDylib<()> {
+    let config = ElfWriterConfig::default().with_soname("libc.so.6");
+    let output = DylibWriter::with_config(Arch::current(), config)
+        .write(
+            &[],
+            &[
+                SymbolDesc::global_func("pthread_create", &[0xc3]).with_version("GLIBC_2.34"),
+                SymbolDesc::global_func("malloc", &[0xc3]).with_version("GLIBC_2.2.5"),
+                SymbolDesc::global_func("memcpy", &[0xc3]).with_hidden_version("GLIBC_2.2.5"),
+            ],
+        )
+        .expect("Failed to generate ELF");
+    Loader::new()
+        .load_dylib(ElfBinary::new("libc.so.6", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .relocate()
+        .expect("Failed to relocate library")
+}
+
+/// A library built against a glibc that still shipped a separate libpthread
+fn libapp() -> ElfWriteOutput {
+    DylibWriter::new(Arch::current())
+        .write(
+            &[
+                RelocEntry::with_name("pthread_create", REL_GOT),
+                RelocEntry::with_name("malloc", REL_GOT),
+            ],
+            &[
+                SymbolDesc::undefined_func("pthread_create")
+                    .with_needed_version("GLIBC_2.2.5", "libpthread.so.0"),
+                SymbolDesc::undefined_func("malloc")
+                    .with_needed_version("GLIBC_2.2.5", "libc.so.6"),
+            ],
+        )
+        .expect("Failed to generate ELF")
+}
+
+fn got_slot(lib: &LoadedDylib<()>, output: &ElfWriteOutput, idx: usize) -> usize {
+    unsafe { *((lib.base() + output.relocations[idx].vaddr as usize) as *const usize) }
+}
+
+#[test]
+fn version_definitions() {
+    let libc = libc();
+    let malloc = unsafe { libc.get::<()>("malloc") }.unwrap().into_raw();
+    let versioned = unsafe { libc.get_version::<()>("malloc", "GLIBC_2.2.5") };
+    assert_eq!(versioned.map(|sym| sym.into_raw()), Some(malloc));
+    assert!(unsafe { libc.get_version::<()>("malloc", "GLIBC_2.34") }.is_none());
+
+    // A non-default version is only found by its version
+    assert!(unsafe { libc.get::<()>("memcpy") }.is_none());
+    assert!(unsafe { libc.get_version::<()>("memcpy", "GLIBC_2.2.5") }.is_some());
+}
+
+#[test]
+fn permissive_version_fallback() {
+    let libc = libc();
+    let output = libapp();
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libapp.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&libc])
+        .relocate()
+        .expect("Failed to relocate library");
+
+    let pthread_create = unsafe { libc.get::<()>("pthread_create") }.unwrap();
+    let malloc = unsafe { libc.get::<()>("malloc") }.unwrap();
+    assert_eq!(
+        got_slot(&lib, &output, 0),
+        pthread_create.into_raw() as usize
+    );
+    assert_eq!(got_slot(&lib, &output, 1), malloc.into_raw() as usize);
+}
+
+#[test]
+fn strict_version_error() {
+    let libc = libc();
+    let output = libapp();
+    let err = Loader::new()
+        .load_dylib(ElfBinary::new("libapp.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&libc])
+        .version_policy(VersionPolicy::Strict)
+        .relocate()
+        .expect_err("The required version is not defined");
+
+    let message = err.to_string();
+    let Error::Relocation {
+        context: Some(context),
+        ..
+    } = err
+    else {
+        panic!("unexpected error: {message}");
+    };
+    assert_eq!(context.symbol(), Some("pthread_create"));
+    assert_eq!(context.version(), Some("GLIBC_2.2.5"));
+    assert_eq!(context.version_file(), Some("libpthread.so.0"));
+    assert!(message.contains("GLIBC_2.2.5"), "{message}");
+    assert!(message.contains("libpthread.so.0"), "{message}");
+
+    // The policy of a linker applies to every image of the batch
+    let image = Loader::new()
+        .load(ElfBinary::new("libapp.so", &output.data))
+        .expect("Failed to load library");
+    let err = Linker::new()
+        .scope([&libc])
+        .image(image)
+        .version_policy(VersionPolicy::Strict)
+        .link()
+        .expect_err("The required version is not defined");
+    assert!(err.to_string().contains("libpthread.so.0"), "{err}");
+}
+
+#[test]
+fn unknown_version_index() {
+    let libc = libc();
+    let mut output = libapp();
+
+    // Point every version index past the end of the version tables
+    let file = object::File::parse(&*output.data).unwrap();
+    let versym = file.section_by_name(".gnu.version").unwrap();
+    let (offset, size) = versym.file_range().unwrap();
+    for entry in output.data[offset as usize..(offset + size) as usize]
+        .chunks_mut(2)
+        .skip(1)
+    {
+        entry.copy_from_slice(&0x7ffu16.to_le_bytes());
+    }
+
+    // The references are treated as unversioned
+    let lib = Loader::new()
+        .load_dylib(ElfBinary::new("libapp.so", &output.data))
+        .expect("Failed to load library")
+        .relocator()
+        .scope([&libc])
+        .version_policy(VersionPolicy::Strict)
+        .relocate()
+        .expect("Failed to relocate library");
+    let malloc = unsafe { libc.get::<()>("malloc") }.unwrap();
+    assert_eq!(got_slot(&lib, &output, 1), malloc.into_raw() as usize);
+}
@@ -195,6 +195,11 @@ impl RelocType {
         }
     }
 
+    /// Check if a relocation is a TLS descriptor, which occupies two GOT slots
+    pub(crate) fn is_tlsdesc_reloc(&self, arch: Arch) -> bool {
+        arch == Arch::Riscv64 && self.as_u32() == R_RISCV_TLSDESC
+    }
+
     /// Check if a relocation is RELATIVE type (doesn't depend on symbols)
     pub(crate) fn is_relative_reloc(&self, arch: Arch) -> bool {
         let r_type = self.as_u32();
@@ -273,6 +278,7 @@ impl RelocType {
                 r_type == R_RISCV_TLS_DTPMOD64
                     || r_type == R_RISCV_TLS_DTPREL64
                     || r_type == R_RISCV_TLS_TPREL64
+                    || self.is_tlsdesc_reloc(arch)
             }
             Arch::Loongarch64 => {
                 r_type == R_LARCH_TLS_DTPMOD64
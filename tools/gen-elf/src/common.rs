@@ -7,6 +7,8 @@ pub enum SymbolType {
     Object,
     /// Thread-local storage symbol.
     Tls,
+    /// GNU indirect function symbol (`STT_GNU_IFUNC`); its code is the resolver.
+    Ifunc,
 }
 
 /// Visibility and binding scope of an ELF symbol.
@@ -20,6 +22,20 @@ pub enum SymbolScope {
     Weak,
 }
 
+/// Visibility of an ELF symbol, stored in the low bits of `st_other`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SymbolVisibility {
+    /// `STV_DEFAULT`: visible to other objects and preemptible.
+    #[default]
+    Default,
+    /// `STV_INTERNAL`: like hidden, with processor-specific semantics.
+    Internal,
+    /// `STV_HIDDEN`: not visible to other objects.
+    Hidden,
+    /// `STV_PROTECTED`: visible to other objects, but not preemptible.
+    Protected,
+}
+
 /// Purpose or category of an ELF section.
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum SectionKind {
@@ -30,6 +46,7 @@ pub enum SectionKind {
     RelaPlt,
     RelDyn,
     RelPlt,
+    RelrDyn,
     Dynamic,
     Hash,
     ShStrTab,
@@ -39,6 +56,17 @@ pub enum SectionKind {
     Got,
     GotPlt,
     Tls,
+    NoteGnuProperty,
+    /// `.note.gnu.build-id` loaded with the read-only segment
+    NoteGnuBuildId,
+    /// `.note.gnu.build-id` that is only present in the file
+    NoteGnuBuildIdUnmapped,
+    /// `.gnu.version`, the version index of every dynamic symbol
+    GnuVersion,
+    /// `.gnu.version_d`, the versions the library defines
+    GnuVersionD,
+    /// `.gnu.version_r`, the versions the library requires
+    GnuVersionR,
 }
 
 /// Content of an ELF section.
@@ -63,6 +91,25 @@ pub struct SymbolDesc {
     pub content: Option<Content>,
     /// Optional size of the symbol. If None, it may be calculated from content.
     pub size: Option<u64>,
+    /// Whether the function is Thumb code, which sets bit 0 of its value.
+    pub thumb: bool,
+    /// Optional GNU version of the symbol.
+    pub version: Option<SymbolVersion>,
+    /// Visibility of the symbol. Only dynamic libraries record it.
+    pub visibility: SymbolVisibility,
+}
+
+/// GNU version of a symbol, emitted in the `.gnu.version*` sections of
+/// dynamic libraries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymbolVersion {
+    /// Name of the version (e.g. `GLIBC_2.2.5`).
+    pub name: String,
+    /// Whether a definition is not the default version, i.e. `name@VERSION`
+    /// rather than `name@@VERSION`.
+    pub hidden: bool,
+    /// Library an undefined symbol requires the version from.
+    pub file: Option<String>,
 }
 
 impl SymbolDesc {
@@ -77,6 +124,27 @@ impl SymbolDesc {
                 kind: SectionKind::Text,
             }),
             size: Some(code.len() as u64),
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
+        }
+    }
+
+    /// Create a global IFUNC symbol.
+    ///
+    /// The resolver code is generated by [`DylibWriter`](crate::DylibWriter) and
+    /// returns the configured `ifunc_resolver_val`, like `__ifunc_resolver`.
+    /// Only supported for dynamic libraries.
+    pub fn global_ifunc(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            sym_type: SymbolType::Ifunc,
+            scope: SymbolScope::Global,
+            content: None,
+            size: None,
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
         }
     }
 
@@ -91,6 +159,9 @@ impl SymbolDesc {
                 kind: SectionKind::Data,
             }),
             size: Some(data.len() as u64),
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
         }
     }
 
@@ -102,6 +173,9 @@ impl SymbolDesc {
             scope: SymbolScope::Global,
             content: None,
             size: None,
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
         }
     }
 
@@ -113,6 +187,9 @@ impl SymbolDesc {
             scope: SymbolScope::Global,
             content: None,
             size: None,
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
         }
     }
 
@@ -127,6 +204,9 @@ impl SymbolDesc {
                 kind: SectionKind::Tls,
             }),
             size: Some(data.len() as u64),
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
         }
     }
 
@@ -138,6 +218,9 @@ impl SymbolDesc {
             scope: SymbolScope::Global,
             content: None,
             size: None,
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
         }
     }
 
@@ -153,6 +236,9 @@ impl SymbolDesc {
                 kind: SectionKind::Plt,
             }),
             size: Some(size),
+            thumb: false,
+            version: None,
+            visibility: SymbolVisibility::Default,
         }
     }
 
@@ -162,11 +248,60 @@ impl SymbolDesc {
         self
     }
 
+    /// Mark the function as Thumb code.
+    ///
+    /// The symbol value gets bit 0 set, like the Thumb functions of ARM
+    /// toolchains. Only supported for dynamic libraries.
+    pub fn with_thumb(mut self) -> Self {
+        self.thumb = true;
+        self
+    }
+
+    /// Define the symbol as the default version `version` (`name@@version`).
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(SymbolVersion {
+            name: version.into(),
+            hidden: false,
+            file: None,
+        });
+        self
+    }
+
+    /// Define the symbol as the non-default version `version` (`name@version`).
+    pub fn with_hidden_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(SymbolVersion {
+            name: version.into(),
+            hidden: true,
+            file: None,
+        });
+        self
+    }
+
+    /// Require `version` of an undefined symbol from the library `file`.
+    pub fn with_needed_version(
+        mut self,
+        version: impl Into<String>,
+        file: impl Into<String>,
+    ) -> Self {
+        self.version = Some(SymbolVersion {
+            name: version.into(),
+            hidden: false,
+            file: Some(file.into()),
+        });
+        self
+    }
+
     /// Set a custom scope for the symbol.
     pub fn with_scope(mut self, scope: SymbolScope) -> Self {
         self.scope = scope;
         self
     }
+
+    /// Set the visibility of the symbol.
+    pub fn with_visibility(mut self, visibility: SymbolVisibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
 }
 
 /// Wrapper for architecture-specific relocation types.
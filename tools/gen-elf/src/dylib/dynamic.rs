@@ -10,6 +10,11 @@ use crate::dylib::{
     REL_SIZE_32, REL_SIZE_64, RELA_SIZE_32, RELA_SIZE_64, SYM_SIZE_32, SYM_SIZE_64,
 };
 
+// RELR tags, which are not provided by `object::elf`
+const DT_RELRSZ: i64 = 35;
+const DT_RELR: i64 = 36;
+const DT_RELRENT: i64 = 37;
+
 #[derive(Debug, Clone)]
 struct DynamicEntry {
     tag: i64,
@@ -18,15 +23,22 @@ struct DynamicEntry {
 
 pub(crate) struct DynamicMetadata {
     arch: Arch,
+    is_rela: bool,
     dyn_entries: Vec<DynamicEntry>,
     dynamic_id: SectionId,
 }
 
 impl DynamicMetadata {
-    pub(crate) fn new(arch: Arch, sections: &[Section], allocator: &mut SectionAllocator) -> Self {
+    pub(crate) fn new(
+        arch: Arch,
+        is_rela: bool,
+        sections: &[Section],
+        allocator: &mut SectionAllocator,
+    ) -> Self {
         let dynamic_id = allocator.allocate(0);
         let mut instance = Self {
             arch,
+            is_rela,
             dyn_entries: vec![],
             dynamic_id,
         };
@@ -51,7 +63,7 @@ impl DynamicMetadata {
 
     fn init_from_sections(&mut self, sections: &[Section]) {
         let is_64 = self.arch.is_64();
-        let is_rela = self.arch.is_rela();
+        let is_rela = self.is_rela;
         for sec in sections {
             let vaddr = sec.header.addr;
             let size = sec.header.size;
@@ -70,6 +82,18 @@ impl DynamicMetadata {
                 SectionKind::Hash => {
                     self.add_entry(DT_HASH as i64, vaddr);
                 }
+                // The counts are filled in by `VersionMetaData::update_dynamic`
+                SectionKind::GnuVersion => {
+                    self.add_entry(DT_VERSYM as i64, vaddr);
+                }
+                SectionKind::GnuVersionD => {
+                    self.add_entry(DT_VERDEF as i64, vaddr);
+                    self.add_entry(DT_VERDEFNUM as i64, 0);
+                }
+                SectionKind::GnuVersionR => {
+                    self.add_entry(DT_VERNEED as i64, vaddr);
+                    self.add_entry(DT_VERNEEDNUM as i64, 0);
+                }
                 SectionKind::Got => {
                     self.add_entry(DT_PLTGOT as i64, vaddr);
                 }
@@ -91,6 +115,11 @@ impl DynamicMetadata {
                     );
                     self.add_entry(DT_RELCOUNT as i64, 0);
                 }
+                SectionKind::RelrDyn => {
+                    self.add_entry(DT_RELR, vaddr);
+                    self.add_entry(DT_RELRSZ, size);
+                    self.add_entry(DT_RELRENT, if is_64 { 8 } else { 4 });
+                }
                 SectionKind::RelaPlt | SectionKind::RelPlt => {
                     self.add_entry(DT_JMPREL as i64, vaddr);
                     self.add_entry(DT_PLTRELSZ as i64, size);
@@ -117,16 +146,20 @@ impl DynamicMetadata {
         if let Some(entry) = self.dyn_entries.iter_mut().find(|e| e.tag == tag) {
             entry.value = value;
         } else {
-            // Insert before DT_NULL if it exists
-            if let Some(pos) = self
-                .dyn_entries
-                .iter()
-                .position(|e| e.tag == DT_NULL as i64)
-            {
-                self.dyn_entries.insert(pos, DynamicEntry { tag, value });
-            } else {
-                self.add_entry(tag, value);
-            }
+            self.insert_entry(tag, value);
+        }
+    }
+
+    /// Insert a new entry before DT_NULL, even if the tag already exists
+    pub(crate) fn insert_entry(&mut self, tag: i64, value: u64) {
+        if let Some(pos) = self
+            .dyn_entries
+            .iter()
+            .position(|e| e.tag == DT_NULL as i64)
+        {
+            self.dyn_entries.insert(pos, DynamicEntry { tag, value });
+        } else {
+            self.add_entry(tag, value);
         }
     }
 
@@ -139,7 +172,7 @@ impl DynamicMetadata {
         got_plt_vaddr: u64,
     ) -> Result<()> {
         let is_64 = self.arch.is_64();
-        let is_rela = self.arch.is_rela();
+        let is_rela = self.is_rela;
         self.update_entry(DT_STRTAB as i64, shdr_manager.get_vaddr(SectionKind::DynStr));
         self.update_entry(DT_SYMTAB as i64, shdr_manager.get_vaddr(SectionKind::DynSym));
         self.update_entry(DT_HASH as i64, shdr_manager.get_vaddr(SectionKind::Hash));
@@ -167,6 +200,19 @@ impl DynamicMetadata {
             self.update_entry(DT_PLTRELSZ as i64, rel_plt_size);
             self.update_entry(DT_RELCOUNT as i64, reloc.relative_count() as u64);
         }
+        for (kind, tag) in [
+            (SectionKind::GnuVersion, DT_VERSYM),
+            (SectionKind::GnuVersionD, DT_VERDEF),
+            (SectionKind::GnuVersionR, DT_VERNEED),
+        ] {
+            if self.dyn_entries.iter().any(|e| e.tag == tag as i64) {
+                self.update_entry(tag as i64, shdr_manager.get_vaddr(kind));
+            }
+        }
+        if reloc.is_relr() {
+            self.update_entry(DT_RELR, shdr_manager.get_vaddr(SectionKind::RelrDyn));
+            self.update_entry(DT_RELRSZ, shdr_manager.get_size(SectionKind::RelrDyn));
+        }
         let dyn_id = shdr_manager.get_data_id(SectionKind::Dynamic);
         self.write_to_vec(allocator.get_mut(&dyn_id), is_64)?;
         Ok(())
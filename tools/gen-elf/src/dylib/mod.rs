@@ -4,10 +4,11 @@ use crate::dylib::data::DataMetaData;
 use crate::dylib::dynamic::DynamicMetadata;
 use crate::dylib::layout::ElfLayout;
 use crate::dylib::reloc::RelocMetaData;
-use crate::dylib::shdr::{SectionAllocator, ShdrManager};
+use crate::dylib::shdr::{Section, SectionAllocator, SectionHeader, ShdrManager};
 use crate::dylib::symtab::SymTabMetadata;
 use crate::dylib::text::CodeMetaData;
 use crate::dylib::tls::TlsMetaData;
+use crate::dylib::version::VersionMetaData;
 use anyhow::Result;
 use byteorder::{LittleEndian, WriteBytesExt};
 use object::elf::*;
@@ -21,6 +22,7 @@ pub(crate) mod shdr;
 pub(crate) mod symtab;
 pub(crate) mod text;
 mod tls;
+mod version;
 
 fn align_up(val: u64, align: u64) -> u64 {
     (val + align - 1) / align * align
@@ -59,6 +61,36 @@ pub struct ElfWriterConfig {
     pub page_size: u64,
     /// Custom value for IFUNC resolver to return (default: None, returns PLT0 address)
     pub ifunc_resolver_val: Option<u64>,
+    /// Override the relocation format (default: None, follows the architecture)
+    pub use_rela: Option<bool>,
+    /// Value of the `DT_FLAGS` entry (default: None, entry is omitted)
+    pub flags: Option<u64>,
+    /// Value of the `DT_FLAGS_1` entry (default: None, entry is omitted)
+    pub flags_1: Option<u64>,
+    /// Emit a `PT_GNU_RELRO` header covering the writable segment (default: false)
+    pub relro: bool,
+    /// Flags of a `PT_GNU_STACK` header (default: None, header is omitted)
+    pub gnu_stack: Option<u32>,
+    /// Pack RELATIVE relocations into a `.relr.dyn` section (default: false)
+    pub relr: bool,
+    /// Value of the `DT_SONAME` entry (default: None, entry is omitted)
+    pub soname: Option<String>,
+    /// Values of the `DT_NEEDED` entries (default: empty, no entries)
+    pub needed: Vec<String>,
+    /// Value of the `DT_RPATH` entry (default: None, entry is omitted)
+    pub rpath: Option<String>,
+    /// Value of the `DT_RUNPATH` entry (default: None, entry is omitted)
+    pub runpath: Option<String>,
+    /// `(pr_type, bitmask)` pairs of a `.note.gnu.property` section (default: empty, section is omitted)
+    pub gnu_properties: Vec<(u32, u32)>,
+    /// Descriptor of a `NT_GNU_BUILD_ID` note (default: None, note is omitted)
+    pub build_id: Option<Vec<u8>>,
+    /// Keep the build-id note out of the loadable segments (default: false)
+    pub build_id_unmapped: bool,
+    /// Place `.got` in the executable segment and emit `DT_TEXTREL` (default: false)
+    pub text_relocs: bool,
+    /// Extra `(d_tag, d_un)` entries appended to `.dynamic` (default: empty)
+    pub extra_dynamic: Vec<(i64, u64)>,
 }
 
 impl Default for ElfWriterConfig {
@@ -67,6 +99,21 @@ impl Default for ElfWriterConfig {
             base_addr: 0,
             page_size: 0x1000,
             ifunc_resolver_val: None,
+            use_rela: None,
+            flags: None,
+            flags_1: None,
+            relro: false,
+            gnu_stack: None,
+            relr: false,
+            soname: None,
+            needed: vec![],
+            rpath: None,
+            runpath: None,
+            gnu_properties: vec![],
+            build_id: None,
+            build_id_unmapped: false,
+            text_relocs: false,
+            extra_dynamic: vec![],
         }
     }
 }
@@ -94,6 +141,103 @@ impl ElfWriterConfig {
         self.ifunc_resolver_val = Some(val);
         self
     }
+
+    /// Emit `DT_RELA` tables if `true`, or `DT_REL` tables if `false`
+    pub fn with_rela(mut self, is_rela: bool) -> Self {
+        self.use_rela = Some(is_rela);
+        self
+    }
+
+    /// Emit a `DT_FLAGS` entry with the given flags (e.g. `DF_SYMBOLIC`)
+    pub fn with_flags(mut self, flags: u64) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Emit a `DT_FLAGS_1` entry with the given flags (e.g. `DF_1_NOW`)
+    pub fn with_flags_1(mut self, flags: u64) -> Self {
+        self.flags_1 = Some(flags);
+        self
+    }
+
+    /// Emit a `PT_GNU_RELRO` header covering the whole writable segment
+    pub fn with_relro(mut self, relro: bool) -> Self {
+        self.relro = relro;
+        self
+    }
+
+    /// Emit a `PT_GNU_STACK` header with the given flags (e.g. `PF_R | PF_W | PF_X`)
+    pub fn with_gnu_stack(mut self, flags: u32) -> Self {
+        self.gnu_stack = Some(flags);
+        self
+    }
+
+    /// Emit RELATIVE relocations as a `DT_RELR` table instead of `DT_REL`/`DT_RELA` entries
+    pub fn with_relr(mut self, relr: bool) -> Self {
+        self.relr = relr;
+        self
+    }
+
+    /// Emit a `DT_SONAME` entry with the given name
+    pub fn with_soname(mut self, soname: impl Into<String>) -> Self {
+        self.soname = Some(soname.into());
+        self
+    }
+
+    /// Emit one `DT_NEEDED` entry per name, in order
+    pub fn with_needed(mut self, needed: &[&str]) -> Self {
+        self.needed
+            .extend(needed.iter().map(|name| name.to_string()));
+        self
+    }
+
+    /// Emit a `DT_RPATH` entry with the given search path
+    pub fn with_rpath(mut self, rpath: impl Into<String>) -> Self {
+        self.rpath = Some(rpath.into());
+        self
+    }
+
+    /// Emit a `DT_RUNPATH` entry with the given search path
+    pub fn with_runpath(mut self, runpath: impl Into<String>) -> Self {
+        self.runpath = Some(runpath.into());
+        self
+    }
+
+    /// Add a 4-byte property (e.g. `GNU_PROPERTY_AARCH64_FEATURE_1_AND`) to a
+    /// `.note.gnu.property` section covered by a `PT_GNU_PROPERTY` header
+    pub fn with_gnu_property(mut self, pr_type: u32, bitmask: u32) -> Self {
+        self.gnu_properties.push((pr_type, bitmask));
+        self
+    }
+
+    /// Emit a `.note.gnu.build-id` section holding `build_id`, covered by a
+    /// `PT_NOTE` header inside the read-only segment
+    pub fn with_build_id(mut self, build_id: impl Into<Vec<u8>>) -> Self {
+        self.build_id = Some(build_id.into());
+        self
+    }
+
+    /// Like [`with_build_id`](Self::with_build_id), but the note is only
+    /// present in the file and no `PT_LOAD` segment covers it
+    pub fn with_unmapped_build_id(mut self, build_id: impl Into<Vec<u8>>) -> Self {
+        self.build_id = Some(build_id.into());
+        self.build_id_unmapped = true;
+        self
+    }
+
+    /// Place `.got` in the read-execute segment and emit a `DT_TEXTREL` entry,
+    /// so that the GOT relocations patch the code segment like the text
+    /// relocations of objects built without `-fPIC`
+    pub fn with_text_relocs(mut self, text_relocs: bool) -> Self {
+        self.text_relocs = text_relocs;
+        self
+    }
+
+    /// Append a raw `(tag, value)` entry to `.dynamic`, before `DT_NULL`
+    pub fn with_dynamic_entry(mut self, tag: i64, value: u64) -> Self {
+        self.extra_dynamic.push((tag, value));
+        self
+    }
 }
 
 /// Relocation metadata for testing and verification
@@ -183,9 +327,40 @@ impl DylibWriter {
         symbols: &[SymbolDesc],
     ) -> Result<ElfWriteOutput> {
         let is_64 = self.arch.is_64();
+        let is_rela = self.config.use_rela.unwrap_or(self.arch.is_rela());
         let mut allocator = SectionAllocator::new();
         let mut symtab = SymTabMetadata::new(self.arch, symbols, raw_relocs, &mut allocator);
-        let mut reloc = RelocMetaData::new(self.arch, raw_relocs, &symtab, &mut allocator)?;
+        let soname_off = self
+            .config
+            .soname
+            .as_ref()
+            .map(|soname| symtab.add_dynstr(soname, &mut allocator));
+        let needed_offs: Vec<_> = self
+            .config
+            .needed
+            .iter()
+            .map(|needed| symtab.add_dynstr(needed, &mut allocator))
+            .collect();
+        let rpath_off = self
+            .config
+            .rpath
+            .as_ref()
+            .map(|rpath| symtab.add_dynstr(rpath, &mut allocator));
+        let runpath_off = self
+            .config
+            .runpath
+            .as_ref()
+            .map(|runpath| symtab.add_dynstr(runpath, &mut allocator));
+        let version =
+            VersionMetaData::new(&mut symtab, self.config.soname.as_deref(), &mut allocator)?;
+        let mut reloc = RelocMetaData::new(
+            self.arch,
+            is_rela,
+            self.config.relr,
+            raw_relocs,
+            &symtab,
+            &mut allocator,
+        )?;
 
         let data = DataMetaData::new(&reloc, &symtab, &mut allocator);
         let mut text = CodeMetaData::new(&symtab, &mut allocator);
@@ -197,14 +372,51 @@ impl DylibWriter {
         data.create_sections(&mut sections);
         tls.create_section(&mut sections);
         symtab.create_sections(&mut sections);
+        if let Some(version) = &version {
+            version.create_sections(&mut sections);
+        }
         reloc.create_sections(&mut sections)?;
 
         // 2. Create .dynamic section (placeholder)
-        let mut dyn_meta = DynamicMetadata::new(self.arch, &sections, &mut allocator);
+        let mut dyn_meta = DynamicMetadata::new(self.arch, is_rela, &sections, &mut allocator);
+        if let Some(flags) = self.config.flags {
+            dyn_meta.update_entry(DT_FLAGS as i64, flags);
+        }
+        if let Some(flags_1) = self.config.flags_1 {
+            dyn_meta.update_entry(DT_FLAGS_1 as i64, flags_1);
+        }
+        if self.config.text_relocs {
+            dyn_meta.update_entry(DT_TEXTREL as i64, 0);
+        }
+        if let Some(soname_off) = soname_off {
+            dyn_meta.update_entry(DT_SONAME as i64, soname_off as u64);
+        }
+        for needed_off in needed_offs {
+            dyn_meta.insert_entry(DT_NEEDED as i64, needed_off as u64);
+        }
+        if let Some(rpath_off) = rpath_off {
+            dyn_meta.update_entry(DT_RPATH as i64, rpath_off as u64);
+        }
+        if let Some(runpath_off) = runpath_off {
+            dyn_meta.update_entry(DT_RUNPATH as i64, runpath_off as u64);
+        }
+        for &(tag, value) in &self.config.extra_dynamic {
+            dyn_meta.insert_entry(tag, value);
+        }
         dyn_meta.create_section(&mut sections);
+        if !self.config.gnu_properties.is_empty() {
+            sections.push(self.create_gnu_property_section(&mut allocator)?);
+        }
+        if let Some(build_id) = &self.config.build_id {
+            sections.push(self.create_build_id_section(build_id, &mut allocator)?);
+        }
 
         // 3. Initialize ShdrManager and Layout
-        let mut shdr_manager = ShdrManager::new();
+        let mut shdr_manager = ShdrManager::new(self.config.relro, self.config.gnu_stack);
+        shdr_manager.set_text_got(self.config.text_relocs);
+        if let Some(version) = &version {
+            version.update_counts(&mut dyn_meta, &mut shdr_manager);
+        }
         for sec in sections {
             shdr_manager.add_section(sec.header, sec.data);
         }
@@ -291,6 +503,68 @@ impl DylibWriter {
         })
     }
 
+    /// Build a `.note.gnu.property` section holding a single `NT_GNU_PROPERTY_TYPE_0` note
+    fn create_gnu_property_section(&self, allocator: &mut SectionAllocator) -> Result<Section> {
+        let align = if self.arch.is_64() { 8 } else { 4 };
+        let mut desc = vec![];
+        for &(pr_type, bitmask) in &self.config.gnu_properties {
+            desc.write_u32::<LittleEndian>(pr_type)?;
+            desc.write_u32::<LittleEndian>(4)?;
+            desc.write_u32::<LittleEndian>(bitmask)?;
+            desc.resize(desc.len().next_multiple_of(align), 0);
+        }
+        let mut note = vec![];
+        note.write_u32::<LittleEndian>(4)?;
+        note.write_u32::<LittleEndian>(desc.len() as u32)?;
+        note.write_u32::<LittleEndian>(NT_GNU_PROPERTY_TYPE_0)?;
+        note.extend_from_slice(b"GNU\0");
+        note.resize(note.len().next_multiple_of(align), 0);
+        note.extend_from_slice(&desc);
+        let size = note.len() as u64;
+        Ok(Section {
+            header: SectionHeader {
+                name_off: 0,
+                shtype: SectionKind::NoteGnuProperty,
+                addr: 0,
+                offset: 0,
+                size,
+                addralign: align as u64,
+            },
+            data: allocator.allocate_with_data(note),
+        })
+    }
+
+    /// Build a `.note.gnu.build-id` section holding a single `NT_GNU_BUILD_ID` note
+    fn create_build_id_section(
+        &self,
+        build_id: &[u8],
+        allocator: &mut SectionAllocator,
+    ) -> Result<Section> {
+        let mut note = vec![];
+        note.write_u32::<LittleEndian>(4)?;
+        note.write_u32::<LittleEndian>(build_id.len() as u32)?;
+        note.write_u32::<LittleEndian>(NT_GNU_BUILD_ID)?;
+        note.extend_from_slice(b"GNU\0");
+        note.extend_from_slice(build_id);
+        note.resize(note.len().next_multiple_of(4), 0);
+        let size = note.len() as u64;
+        Ok(Section {
+            header: SectionHeader {
+                name_off: 0,
+                shtype: if self.config.build_id_unmapped {
+                    SectionKind::NoteGnuBuildIdUnmapped
+                } else {
+                    SectionKind::NoteGnuBuildId
+                },
+                addr: 0,
+                offset: 0,
+                size,
+                addralign: 4,
+            },
+            data: allocator.allocate_with_data(note),
+        })
+    }
+
     fn get_ident(&self) -> [u8; 16] {
         let mut ident = [0u8; 16];
         ident[0] = 0x7f;
@@ -339,6 +613,61 @@ impl DylibWriter {
     }
 }
 
+/// A base library and a library depending on it through `DT_NEEDED`.
+///
+/// The base library exports [`FUNC_NAME`](Self::FUNC_NAME) and
+/// [`VAR_NAME`](Self::VAR_NAME); the dependent library imports them through
+/// `JUMP_SLOT` and `GLOB_DAT` relocations.
+pub struct DylibPair {
+    /// The library named [`BASE_SONAME`](Self::BASE_SONAME).
+    pub base: ElfWriteOutput,
+    /// The library named [`DEP_SONAME`](Self::DEP_SONAME), needing the base library.
+    pub dep: ElfWriteOutput,
+}
+
+impl DylibPair {
+    /// `DT_SONAME` of the base library, also the `DT_NEEDED` entry of the dependent library.
+    pub const BASE_SONAME: &'static str = "libbase.so";
+    /// `DT_SONAME` of the dependent library.
+    pub const DEP_SONAME: &'static str = "libdep.so";
+    /// Function exported by the base library.
+    pub const FUNC_NAME: &'static str = "base_func";
+    /// Object exported by the base library.
+    pub const VAR_NAME: &'static str = "base_var";
+
+    /// Generate both libraries for the specified architecture.
+    pub fn new(arch: Arch) -> Result<Self> {
+        let base = DylibWriter::with_config(
+            arch,
+            ElfWriterConfig::default().with_soname(Self::BASE_SONAME),
+        )
+        .write(
+            &[],
+            &[
+                SymbolDesc::global_func(Self::FUNC_NAME, &[0; 4]),
+                SymbolDesc::global_object(Self::VAR_NAME, &[0; 8]),
+            ],
+        )?;
+        let dep = DylibWriter::with_config(
+            arch,
+            ElfWriterConfig::default()
+                .with_soname(Self::DEP_SONAME)
+                .with_needed(&[Self::BASE_SONAME]),
+        )
+        .write(
+            &[
+                RelocEntry::jump_slot(Self::FUNC_NAME, arch),
+                RelocEntry::glob_dat(Self::VAR_NAME, arch),
+            ],
+            &[
+                SymbolDesc::undefined_func(Self::FUNC_NAME),
+                SymbolDesc::undefined_object(Self::VAR_NAME),
+            ],
+        )?;
+        Ok(Self { base, dep })
+    }
+}
+
 struct ElfHeader {
     ident: [u8; 16],
     type_: u16,
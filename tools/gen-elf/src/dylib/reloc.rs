@@ -13,15 +13,19 @@ use byteorder::{LittleEndian, WriteBytesExt};
 
 pub(crate) struct RelocMetaData {
     arch: Arch,
+    is_rela: bool,
+    is_relr: bool,
     relocs: Vec<Reloc>,
     relative_count: usize,
     got_count: usize,
+    tlsdesc_count: usize,
     copy_count: usize,
     plt_count: usize,
     irelative_count: usize,
     total_copy_size: u64,
     rel_dyn_id: SectionId,
     rel_plt_id: SectionId,
+    relr_id: SectionId,
     got_id: SectionId,
     got_plt_id: SectionId,
 }
@@ -29,6 +33,8 @@ pub(crate) struct RelocMetaData {
 impl RelocMetaData {
     pub(crate) fn new(
         arch: Arch,
+        is_rela: bool,
+        is_relr: bool,
         raw: &[RelocEntry],
         symbols: &SymTabMetadata,
         allocator: &mut SectionAllocator,
@@ -58,6 +64,10 @@ impl RelocMetaData {
 
         let relative_count = relative_relocs.len();
         let got_count = got_relocs.len();
+        let tlsdesc_count = got_relocs
+            .iter()
+            .filter(|r| r.r_type.is_tlsdesc_reloc(arch))
+            .count();
         let copy_count = copy_relocs.len();
         let plt_count = plt_relocs.len();
         let irelative_count = irelative_relocs.len();
@@ -87,7 +97,12 @@ impl RelocMetaData {
                 r_type: r.r_type,
                 sym_size: 0,
             });
-            got_slot_idx += 1;
+            // A TLS descriptor takes a slot for the resolver and one for its argument
+            got_slot_idx += if r.r_type.is_tlsdesc_reloc(arch) {
+                2
+            } else {
+                1
+            };
         }
         // Process COPY relocations (relative to DATA)
         let mut current_copy_offset = symbols.get_data_content().len() as u64;
@@ -133,33 +148,40 @@ impl RelocMetaData {
         }
 
         let is_64 = arch.is_64();
-        let is_rela = arch.is_rela();
         let entry_size = if is_64 {
             if is_rela { 24 } else { 16 }
         } else {
             if is_rela { 12 } else { 8 }
         };
 
+        // With RELR, RELATIVE relocations are moved out of .rel(a).dyn
+        let rel_dyn_count = if is_relr { 0 } else { relative_count };
         let rel_dyn_id = allocator
-            .allocate((relative_count + got_count + copy_count + irelative_count) * entry_size);
+            .allocate((rel_dyn_count + got_count + copy_count + irelative_count) * entry_size);
         let rel_plt_id = allocator.allocate(plt_count * entry_size);
+        let relr_id = allocator.allocate(0);
 
         let got_id = allocator.allocate(
-            ((1 + relative_count + got_count + irelative_count) as u64 * word_size) as usize,
+            ((1 + relative_count + got_count + tlsdesc_count + irelative_count) as u64 * word_size)
+                as usize,
         );
         let got_plt_id = allocator.allocate(((3 + plt_count) as u64 * word_size) as usize);
 
         Ok(Self {
             arch,
+            is_rela,
+            is_relr,
             relocs,
             relative_count,
             got_count,
+            tlsdesc_count,
             copy_count,
             plt_count,
             irelative_count,
             total_copy_size,
             rel_dyn_id,
             rel_plt_id,
+            relr_id,
             got_id,
             got_plt_id,
         })
@@ -167,7 +189,7 @@ impl RelocMetaData {
 
     fn rel_entry_size(&self) -> usize {
         let is_64 = self.arch.is_64();
-        let is_rela = self.arch.is_rela();
+        let is_rela = self.is_rela;
         if is_64 {
             if is_rela { 24 } else { 16 }
         } else {
@@ -181,10 +203,22 @@ impl RelocMetaData {
 
     fn rel_dyn_size(&self) -> u64 {
         let entry_size = self.rel_entry_size();
-        ((self.relative_count + self.got_count + self.copy_count + self.irelative_count)
+        ((self.relative_count() + self.got_count + self.copy_count + self.irelative_count)
             * entry_size) as u64
     }
 
+    /// Offsets of the GOT slots covered by RELR, relative to the GOT
+    fn relr_offsets(&self) -> impl Iterator<Item = u64> + '_ {
+        let count = if self.is_relr { self.relative_count } else { 0 };
+        self.relocs[..count].iter().map(|r| r.offset)
+    }
+
+    fn relr_size(&self) -> u64 {
+        // The GOT is word aligned, so the encoding does not depend on its address
+        let word_size = self.word_size();
+        (encode_relr(self.relr_offsets(), word_size as u64).len() * word_size) as u64
+    }
+
     fn rel_plt_size(&self) -> u64 {
         let entry_size = self.rel_entry_size();
         (self.plt_count * entry_size) as u64
@@ -192,7 +226,8 @@ impl RelocMetaData {
 
     fn got_size(&self) -> u64 {
         let word_size = self.word_size();
-        ((1 + self.relative_count + self.got_count + self.irelative_count) * word_size) as u64
+        ((1 + self.relative_count + self.got_count + self.tlsdesc_count + self.irelative_count)
+            * word_size) as u64
     }
 
     fn got_plt_size(&self) -> u64 {
@@ -223,8 +258,13 @@ impl RelocMetaData {
         self.total_copy_size
     }
 
+    pub(crate) fn is_relr(&self) -> bool {
+        self.is_relr
+    }
+
+    /// Number of RELATIVE relocations stored in .rel(a).dyn
     pub(crate) fn relative_count(&self) -> usize {
-        self.relative_count
+        if self.is_relr { 0 } else { self.relative_count }
     }
 
     pub(crate) fn patch_all(
@@ -261,7 +301,7 @@ impl RelocMetaData {
         allocator: &mut SectionAllocator,
     ) -> Result<()> {
         let is_64 = self.arch.is_64();
-        let is_rela = self.arch.is_rela();
+        let is_rela = self.is_rela;
         allocator.get_mut(&self.rel_dyn_id).clear();
         allocator.get_mut(&self.rel_plt_id).clear();
 
@@ -293,6 +333,7 @@ impl RelocMetaData {
             }
             let is_copy = i >= copy_start && i < copy_end;
             let is_plt = i >= plt_start && i < plt_end;
+            let is_relr = self.is_relr && i < self.relative_count;
 
             if is_plt {
                 let plt_idx = i - plt_start;
@@ -320,8 +361,8 @@ impl RelocMetaData {
                 (got_plt_vaddr, &self.rel_plt_id)
             };
 
-            // For REL (non-RELA) relocations, the addend must be stored in the target location
-            if !is_rela && !is_plt && !is_copy {
+            // For REL (non-RELA) and RELR relocations, the addend must be stored in the target location
+            if (!is_rela || is_relr) && !is_plt && !is_copy {
                 let offset = reloc.offset as usize;
                 // If i < copy_start, it's in GOT
                 // If i >= copy_end && i < plt_start, it's IRELATIVE in GOT
@@ -339,7 +380,20 @@ impl RelocMetaData {
             }
 
             reloc.offset += base;
-            reloc.write(allocator.get_mut(section_id), is_64, is_rela)?;
+            if !is_relr {
+                reloc.write(allocator.get_mut(section_id), is_64, is_rela)?;
+            }
+        }
+
+        let word_size = self.word_size() as u64;
+        let relr = allocator.get_mut(&self.relr_id);
+        relr.clear();
+        for entry in encode_relr(self.relr_offsets(), word_size) {
+            if is_64 {
+                relr.write_u64::<LittleEndian>(entry)?;
+            } else {
+                relr.write_u32::<LittleEndian>(entry as u32)?;
+            }
         }
         Ok(())
     }
@@ -399,7 +453,7 @@ impl RelocMetaData {
 
     pub(crate) fn create_sections(&self, sections: &mut Vec<Section>) -> Result<()> {
         let is_64 = self.arch.is_64();
-        let is_rela = self.arch.is_rela();
+        let is_rela = self.is_rela;
 
         sections.push(Section {
             header: SectionHeader {
@@ -432,6 +486,20 @@ impl RelocMetaData {
             data: self.rel_plt_id,
         });
 
+        if self.is_relr {
+            sections.push(Section {
+                header: SectionHeader {
+                    name_off: 0,
+                    shtype: SectionKind::RelrDyn,
+                    addr: 0,
+                    offset: 0,
+                    size: self.relr_size(),
+                    addralign: if is_64 { 8 } else { 4 },
+                },
+                data: self.relr_id,
+            });
+        }
+
         sections.push(Section {
             header: SectionHeader {
                 name_off: 0,
@@ -496,3 +564,34 @@ impl Reloc {
         Ok(())
     }
 }
+
+/// Encodes sorted, word aligned addresses as a RELR table.
+///
+/// Each address entry is followed by bitmap entries whose bit `n` (for `n >= 1`)
+/// covers the word `n - 1` slots after the last covered position.
+fn encode_relr(offsets: impl Iterator<Item = u64>, word_size: u64) -> Vec<u64> {
+    let bits = word_size * 8 - 1;
+    let mut offsets = offsets.peekable();
+    let mut entries = Vec::new();
+    while let Some(addr) = offsets.next() {
+        entries.push(addr);
+        let mut next = addr + word_size;
+        loop {
+            let mut bitmap = 0;
+            while let Some(&offset) = offsets.peek() {
+                let slot = (offset - next) / word_size;
+                if slot >= bits {
+                    break;
+                }
+                bitmap |= 1 << (slot + 1);
+                offsets.next();
+            }
+            if bitmap == 0 {
+                break;
+            }
+            entries.push(bitmap | 1);
+            next += bits * word_size;
+        }
+    }
+    entries
+}
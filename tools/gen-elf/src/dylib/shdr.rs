@@ -28,6 +28,7 @@ impl SectionKind {
             SectionKind::RelaPlt => ".rela.plt",
             SectionKind::RelDyn => ".rel.dyn",
             SectionKind::RelPlt => ".rel.plt",
+            SectionKind::RelrDyn => ".relr.dyn",
             SectionKind::Dynamic => ".dynamic",
             SectionKind::Hash => ".hash",
             SectionKind::ShStrTab => ".shstrtab",
@@ -37,9 +38,23 @@ impl SectionKind {
             SectionKind::Got => ".got",
             SectionKind::GotPlt => ".got.plt",
             SectionKind::Tls => ".tdata",
+            SectionKind::NoteGnuProperty => ".note.gnu.property",
+            SectionKind::NoteGnuBuildId | SectionKind::NoteGnuBuildIdUnmapped => {
+                ".note.gnu.build-id"
+            }
+            SectionKind::GnuVersion => ".gnu.version",
+            SectionKind::GnuVersionD => ".gnu.version_d",
+            SectionKind::GnuVersionR => ".gnu.version_r",
         }
     }
 
+    fn is_build_id(&self) -> bool {
+        matches!(
+            self,
+            SectionKind::NoteGnuBuildId | SectionKind::NoteGnuBuildIdUnmapped
+        )
+    }
+
     fn shtype(&self) -> u32 {
         match self {
             SectionKind::Null => SHT_NULL,
@@ -47,11 +62,18 @@ impl SectionKind {
             SectionKind::DynSym => SHT_DYNSYM,
             SectionKind::RelaDyn | SectionKind::RelaPlt => SHT_RELA,
             SectionKind::RelDyn | SectionKind::RelPlt => SHT_REL,
+            SectionKind::RelrDyn => SHT_RELR,
             SectionKind::Dynamic => SHT_DYNAMIC,
             SectionKind::Hash => SHT_HASH,
             SectionKind::Plt | SectionKind::Text | SectionKind::Data => SHT_PROGBITS,
             SectionKind::Got | SectionKind::GotPlt => SHT_PROGBITS,
             SectionKind::Tls => SHT_PROGBITS,
+            SectionKind::NoteGnuProperty
+            | SectionKind::NoteGnuBuildId
+            | SectionKind::NoteGnuBuildIdUnmapped => SHT_NOTE,
+            SectionKind::GnuVersion => SHT_GNU_VERSYM,
+            SectionKind::GnuVersionD => SHT_GNU_VERDEF,
+            SectionKind::GnuVersionR => SHT_GNU_VERNEED,
         }
     }
 
@@ -69,11 +91,25 @@ impl SectionKind {
             | SectionKind::RelaPlt
             | SectionKind::RelDyn
             | SectionKind::RelPlt
-            | SectionKind::Hash => SHF_ALLOC as u64,
+            | SectionKind::RelrDyn
+            | SectionKind::Hash
+            | SectionKind::NoteGnuProperty
+            | SectionKind::NoteGnuBuildId
+            | SectionKind::GnuVersion
+            | SectionKind::GnuVersionD
+            | SectionKind::GnuVersionR => SHF_ALLOC as u64,
             _ => 0,
         }
     }
 
+    /// Flags of the section, with `.got` moved into the executable segment if `text_got`
+    fn flags_for(&self, text_got: bool) -> u64 {
+        match self {
+            SectionKind::Got if text_got => (SHF_ALLOC | SHF_EXECINSTR) as u64,
+            _ => self.flags(),
+        }
+    }
+
     fn entsize(&self, is_64: bool) -> u64 {
         match self {
             SectionKind::DynSym => {
@@ -105,7 +141,8 @@ impl SectionKind {
                 }
             }
             SectionKind::Hash => HASH_SIZE,
-            SectionKind::Got => {
+            SectionKind::GnuVersion => 2,
+            SectionKind::Got | SectionKind::RelrDyn => {
                 if is_64 {
                     8
                 } else {
@@ -138,7 +175,12 @@ impl SectionKind {
                 *map.get(&SectionKind::DynSym).unwrap() as u32
             }
             SectionKind::Dynamic => *map.get(&SectionKind::DynStr).unwrap() as u32,
-            SectionKind::Hash => *map.get(&SectionKind::DynSym).unwrap() as u32,
+            SectionKind::Hash | SectionKind::GnuVersion => {
+                *map.get(&SectionKind::DynSym).unwrap() as u32
+            }
+            SectionKind::GnuVersionD | SectionKind::GnuVersionR => {
+                *map.get(&SectionKind::DynStr).unwrap() as u32
+            }
             SectionKind::Null => 0,
             _ => 0,
         }
@@ -189,18 +231,45 @@ pub(crate) struct ShdrManager {
     rx_secs: Option<Vec<Section>>,
     r_secs: Option<Vec<Section>>,
     rw_secs: Option<Vec<Section>>,
+    relro: bool,
+    gnu_stack: Option<u32>,
+    /// Whether `.got` is placed in the executable segment
+    text_got: bool,
+    /// `sh_info` values that depend on the content of a section
+    infos: HashMap<SectionKind, u32>,
 }
 
 impl ShdrManager {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(relro: bool, gnu_stack: Option<u32>) -> Self {
         Self {
             shdrs: vec![],
             rx_secs: None,
             r_secs: None,
             rw_secs: None,
+            relro,
+            gnu_stack,
+            text_got: false,
+            infos: HashMap::new(),
         }
     }
 
+    /// Place `.got` in the executable segment, so that its relocations are text relocations
+    pub(crate) fn set_text_got(&mut self, text_got: bool) {
+        self.text_got = text_got;
+    }
+
+    /// Set the `sh_info` of the section of the given kind
+    pub(crate) fn set_info(&mut self, shtype: SectionKind, info: u32) {
+        self.infos.insert(shtype, info);
+    }
+
+    fn info(&self, shtype: SectionKind, map: &HashMap<SectionKind, usize>) -> u32 {
+        self.infos
+            .get(&shtype)
+            .copied()
+            .unwrap_or_else(|| shtype.info(map))
+    }
+
     pub(crate) fn add_section(&mut self, header: SectionHeader, data: SectionId) {
         self.shdrs.push(Section { header, data });
     }
@@ -208,8 +277,9 @@ impl ShdrManager {
     pub(crate) fn layout(&mut self, layout: &mut ElfLayout) {
         // 1. Sort sections by flags to group them into segments
         // Order: R (Read-only) -> RX (Code) -> RW (Read-write) -> Non-Alloc (Metadata)
+        let text_got = self.text_got;
         self.shdrs.sort_by_key(|s| {
-            let flags = s.header.shtype.flags();
+            let flags = s.header.shtype.flags_for(text_got);
             if flags & (SHF_ALLOC as u64) != 0 {
                 if flags & (SHF_WRITE as u64) == 0 && flags & (SHF_EXECINSTR as u64) == 0 {
                     0 // R: .hash, .dynsym, .dynstr, .rela.dyn
@@ -226,7 +296,7 @@ impl ShdrManager {
         // 2. Assign offsets and virtual addresses
         let mut last_group = None;
         for sec in &mut self.shdrs {
-            let flags = sec.header.shtype.flags();
+            let flags = sec.header.shtype.flags_for(self.text_got);
             let current_group = if flags & (SHF_ALLOC as u64) != 0 {
                 if flags & (SHF_WRITE as u64) == 0 && flags & (SHF_EXECINSTR as u64) == 0 {
                     Some(0)
@@ -260,7 +330,7 @@ impl ShdrManager {
         let mut rw = vec![];
 
         for sec in &self.shdrs {
-            let flags = sec.header.shtype.flags();
+            let flags = sec.header.shtype.flags_for(self.text_got);
             if flags & (SHF_ALLOC as u64) != 0 {
                 if flags & (SHF_EXECINSTR as u64) != 0 {
                     rx.push(sec.clone());
@@ -362,9 +432,11 @@ impl ShdrManager {
         let mut has_rw = false;
         let mut has_dynamic = false;
         let mut has_tls = false;
+        let mut has_property = false;
+        let mut has_note = false;
 
         for sec in &self.shdrs {
-            let flags = sec.header.shtype.flags();
+            let flags = sec.header.shtype.flags_for(self.text_got);
             if flags & (SHF_ALLOC as u64) != 0 {
                 if flags & (SHF_EXECINSTR as u64) != 0 {
                     has_rx = true;
@@ -380,6 +452,12 @@ impl ShdrManager {
             if sec.header.shtype == SectionKind::Tls {
                 has_tls = true;
             }
+            if sec.header.shtype == SectionKind::NoteGnuProperty {
+                has_property = true;
+            }
+            if sec.header.shtype.is_build_id() {
+                has_note = true;
+            }
         }
 
         if has_rx {
@@ -390,6 +468,9 @@ impl ShdrManager {
         }
         if has_rw {
             count += 1;
+            if self.relro {
+                count += 1;
+            }
         }
         if has_dynamic {
             count += 1;
@@ -397,6 +478,15 @@ impl ShdrManager {
         if has_tls {
             count += 1;
         }
+        if self.gnu_stack.is_some() {
+            count += 1;
+        }
+        if has_property {
+            count += 1;
+        }
+        if has_note {
+            count += 1;
+        }
         count
     }
 
@@ -422,23 +512,23 @@ impl ShdrManager {
             if is_64 {
                 writer.write_u32::<LittleEndian>(h.name_off)?;
                 writer.write_u32::<LittleEndian>(h.shtype.shtype())?;
-                writer.write_u64::<LittleEndian>(h.shtype.flags())?;
+                writer.write_u64::<LittleEndian>(h.shtype.flags_for(self.text_got))?;
                 writer.write_u64::<LittleEndian>(h.addr)?;
                 writer.write_u64::<LittleEndian>(h.offset)?;
                 writer.write_u64::<LittleEndian>(h.size)?;
                 writer.write_u32::<LittleEndian>(h.shtype.link(&map))?;
-                writer.write_u32::<LittleEndian>(h.shtype.info(&map))?;
+                writer.write_u32::<LittleEndian>(self.info(h.shtype, &map))?;
                 writer.write_u64::<LittleEndian>(h.addralign)?;
                 writer.write_u64::<LittleEndian>(h.shtype.entsize(is_64))?;
             } else {
                 writer.write_u32::<LittleEndian>(h.name_off)?;
                 writer.write_u32::<LittleEndian>(h.shtype.shtype())?;
-                writer.write_u32::<LittleEndian>(h.shtype.flags() as u32)?;
+                writer.write_u32::<LittleEndian>(h.shtype.flags_for(self.text_got) as u32)?;
                 writer.write_u32::<LittleEndian>(h.addr as u32)?;
                 writer.write_u32::<LittleEndian>(h.offset as u32)?;
                 writer.write_u32::<LittleEndian>(h.size as u32)?;
                 writer.write_u32::<LittleEndian>(h.shtype.link(&map))?;
-                writer.write_u32::<LittleEndian>(h.shtype.info(&map))?;
+                writer.write_u32::<LittleEndian>(self.info(h.shtype, &map))?;
                 writer.write_u32::<LittleEndian>(h.addralign as u32)?;
                 writer.write_u32::<LittleEndian>(h.shtype.entsize(is_64) as u32)?;
             }
@@ -557,7 +647,11 @@ impl ShdrManager {
         }
 
         // 5. PT_TLS
-        if let Some(tls_sec) = self.shdrs.iter().find(|s| s.header.shtype == SectionKind::Tls) {
+        if let Some(tls_sec) = self
+            .shdrs
+            .iter()
+            .find(|s| s.header.shtype == SectionKind::Tls)
+        {
             self.write_phdr(
                 &mut writer,
                 is_64,
@@ -571,6 +665,63 @@ impl ShdrManager {
             )?;
         }
 
+        // 6. PT_GNU_RELRO
+        if self.relro
+            && let (Some(first), Some(last)) = (rw_secs.first(), rw_secs.last())
+        {
+            let p_filesz = (last.header.offset + last.header.size) - first.header.offset;
+            self.write_phdr(
+                &mut writer,
+                is_64,
+                PT_GNU_RELRO,
+                PF_R,
+                first.header.offset,
+                first.header.addr,
+                p_filesz,
+                p_filesz,
+                1,
+            )?;
+        }
+
+        // 7. PT_GNU_STACK
+        if let Some(flags) = self.gnu_stack {
+            self.write_phdr(&mut writer, is_64, PT_GNU_STACK, flags, 0, 0, 0, 0, 16)?;
+        }
+
+        // 8. PT_GNU_PROPERTY
+        if let Some(note) = self
+            .shdrs
+            .iter()
+            .find(|s| s.header.shtype == SectionKind::NoteGnuProperty)
+        {
+            self.write_phdr(
+                &mut writer,
+                is_64,
+                PT_GNU_PROPERTY,
+                PF_R,
+                note.header.offset,
+                note.header.addr,
+                note.header.size,
+                note.header.size,
+                note.header.addralign,
+            )?;
+        }
+
+        // 9. PT_NOTE
+        if let Some(note) = self.shdrs.iter().find(|s| s.header.shtype.is_build_id()) {
+            self.write_phdr(
+                &mut writer,
+                is_64,
+                PT_NOTE,
+                PF_R,
+                note.header.offset,
+                note.header.addr,
+                note.header.size,
+                note.header.size,
+                note.header.addralign,
+            )?;
+        }
+
         Ok(())
     }
 
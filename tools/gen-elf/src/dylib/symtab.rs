@@ -1,4 +1,4 @@
-use crate::common::{SectionKind, SymbolDesc, SymbolScope, SymbolType};
+use crate::common::{Content, SectionKind, SymbolDesc, SymbolScope, SymbolType, SymbolVisibility};
 use crate::dylib::{
     StringTable,
     shdr::{Section, SectionAllocator, SectionHeader, SectionId},
@@ -73,6 +73,16 @@ impl SymTabMetadata {
         sym_idx
     }
 
+    /// Appends a string to the already written `.dynstr` section and returns its offset
+    pub(crate) fn add_dynstr(&mut self, s: &str, allocator: &mut SectionAllocator) -> u32 {
+        let off = self.dynstr.add(s);
+        let dynstr = allocator.get_mut(&self.dynstr_id);
+        dynstr.extend_from_slice(s.as_bytes());
+        dynstr.push(0);
+        self.dynstr_size = dynstr.len() as u64;
+        off
+    }
+
     pub(crate) fn new(
         arch: Arch,
         symbols: &[SymbolDesc],
@@ -153,10 +163,17 @@ impl SymTabMetadata {
         }
     }
 
-    fn add_single_symbol(&mut self, s: SymbolDesc) -> usize {
+    fn add_single_symbol(&mut self, mut s: SymbolDesc) -> usize {
         if let Some(idx) = self.sym_index.get(&s.name) {
             return *idx;
         }
+        // IFUNC symbols are always defined, with a generated resolver as their code
+        if s.sym_type == SymbolType::Ifunc && s.content.is_none() {
+            s.content = Some(Content {
+                data: crate::arch::get_ifunc_resolver_code(self.arch),
+                kind: SectionKind::Text,
+            });
+        }
         let name = s.name.clone();
         let name_idx = self.dynstr.cur_idx();
 
@@ -169,6 +186,7 @@ impl SymTabMetadata {
                 SymbolType::Func => STT_FUNC,
                 SymbolType::Object => STT_OBJECT,
                 SymbolType::Tls => STT_TLS,
+                SymbolType::Ifunc => STT_GNU_IFUNC,
             };
 
         let (shdr_type, value) = if let Some(content) = &s.content {
@@ -199,7 +217,7 @@ impl SymTabMetadata {
         } else {
             // Undefined symbols
             let shdr_type = match s.sym_type {
-                SymbolType::Func => SectionKind::Text,
+                SymbolType::Func | SymbolType::Ifunc => SectionKind::Text,
                 SymbolType::Object => SectionKind::Data,
                 SymbolType::Tls => SectionKind::Tls,
             };
@@ -209,9 +227,14 @@ impl SymTabMetadata {
         let sym = Symbol {
             name_idx,
             info,
-            other: 0,
+            other: match s.visibility {
+                SymbolVisibility::Default => STV_DEFAULT,
+                SymbolVisibility::Internal => STV_INTERNAL,
+                SymbolVisibility::Hidden => STV_HIDDEN,
+                SymbolVisibility::Protected => STV_PROTECTED,
+            },
             shndx: 0,
-            value,
+            value: value | s.thumb as u64,
             size: s
                 .size
                 .unwrap_or_else(|| s.content.as_ref().map(|c| c.data.len() as u64).unwrap_or(0)),
@@ -305,6 +328,16 @@ impl SymTabMetadata {
         }
     }
 
+    /// The number of dynamic symbols, including the null symbol
+    pub(crate) fn sym_count(&self) -> usize {
+        self.dynsym.len()
+    }
+
+    /// The description of every symbol but the null symbol, in dynamic symbol table order
+    pub(crate) fn symbol_descs(&self) -> &[SymbolDesc] {
+        &self.symbols
+    }
+
     pub(crate) fn get_sym_idx(&self, name: &str) -> Option<usize> {
         self.sym_index.get(name).cloned()
     }
@@ -431,8 +464,15 @@ impl SymTabMetadata {
         text_vaddr: u64,
         target_vaddr: u64,
     ) {
-        let resolver_name = IFUNC_RESOLVER_NAME;
-        if let Some(&resolver_idx) = self.sym_index.get(resolver_name) {
+        let ifunc_names = self
+            .symbols
+            .iter()
+            .filter(|s| s.sym_type == SymbolType::Ifunc)
+            .map(|s| s.name.as_str());
+        for resolver_name in core::iter::once(IFUNC_RESOLVER_NAME).chain(ifunc_names) {
+            let Some(&resolver_idx) = self.sym_index.get(resolver_name) else {
+                continue;
+            };
             let resolver_sym = &self.dynsym[resolver_idx];
             let resolver_text_off = (resolver_sym.value - text_vaddr) as usize;
             crate::arch::patch_ifunc_resolver(
@@ -0,0 +1,236 @@
+use crate::common::SectionKind;
+use crate::dylib::dynamic::DynamicMetadata;
+use crate::dylib::shdr::{Section, SectionAllocator, SectionHeader, SectionId, ShdrManager};
+use crate::dylib::symtab::SymTabMetadata;
+use anyhow::{Result, bail};
+use byteorder::{LittleEndian, WriteBytesExt};
+use object::elf::*;
+
+const VERDEF_SIZE: u32 = 20;
+const VERDAUX_SIZE: u32 = 8;
+const VERNEED_SIZE: u32 = 16;
+const VERNAUX_SIZE: u32 = 16;
+
+/// The SysV hash of a version name, as stored in `vd_hash` and `vna_hash`
+fn elf_hash(name: &str) -> u32 {
+    let mut hash = 0u32;
+    for &byte in name.as_bytes() {
+        hash = (hash << 4).wrapping_add(byte as u32);
+        let high = hash & 0xf000_0000;
+        if high != 0 {
+            hash ^= high >> 24;
+        }
+        hash &= !high;
+    }
+    hash
+}
+
+/// A `.gnu.version_d` or `.gnu.version_r` section and its number of entries
+struct VersionTable {
+    id: SectionId,
+    size: u64,
+    count: u32,
+}
+
+pub(crate) struct VersionMetaData {
+    versym_id: SectionId,
+    versym_size: u64,
+    verdef: Option<VersionTable>,
+    verneed: Option<VersionTable>,
+}
+
+impl VersionMetaData {
+    /// Build the version sections if any symbol is versioned
+    ///
+    /// Every version the library defines gets a `.gnu.version_d` entry after the
+    /// base entry named after `soname`, and every library versions are required
+    /// from gets a `.gnu.version_r` entry.
+    pub(crate) fn new(
+        symtab: &mut SymTabMetadata,
+        soname: Option<&str>,
+        allocator: &mut SectionAllocator,
+    ) -> Result<Option<Self>> {
+        // The strings are appended to `.dynstr` while the names are still in use
+        let symbols = symtab.symbol_descs().to_vec();
+        if symbols.iter().all(|s| s.version.is_none()) {
+            return Ok(None);
+        }
+
+        // Index 0 and 1 stand for local and global symbols, the defined
+        // versions come next and the required ones last
+        let mut defined: Vec<&str> = vec![];
+        for s in &symbols {
+            if let Some(version) = &s.version
+                && s.content.is_some()
+                && !defined.contains(&version.name.as_str())
+            {
+                defined.push(&version.name);
+            }
+        }
+        let mut next_ndx = defined.len() as u16 + 2;
+        let mut needed: Vec<(&str, Vec<(&str, u16)>)> = vec![];
+        let mut versym = vec![0u16; symtab.sym_count()];
+        for (idx, s) in symbols.iter().enumerate() {
+            versym[idx + 1] = match &s.version {
+                None => VER_NDX_GLOBAL,
+                Some(version) if s.content.is_some() => {
+                    let pos = defined.iter().position(|&name| name == version.name);
+                    let ndx = pos.unwrap() as u16 + 2;
+                    if version.hidden {
+                        ndx | VERSYM_HIDDEN
+                    } else {
+                        ndx
+                    }
+                }
+                Some(version) => {
+                    let Some(file) = &version.file else {
+                        bail!(
+                            "undefined symbol {} requires version {} without a library",
+                            s.name,
+                            version.name
+                        );
+                    };
+                    let pos = match needed.iter().position(|(name, _)| name == file) {
+                        Some(pos) => pos,
+                        None => {
+                            needed.push((file, vec![]));
+                            needed.len() - 1
+                        }
+                    };
+                    let versions = &mut needed[pos].1;
+                    match versions.iter().find(|(name, _)| *name == version.name) {
+                        Some(&(_, ndx)) => ndx,
+                        None => {
+                            versions.push((&version.name, next_ndx));
+                            next_ndx += 1;
+                            next_ndx - 1
+                        }
+                    }
+                }
+            };
+        }
+
+        let mut versym_data = vec![];
+        for ndx in versym {
+            versym_data.write_u16::<LittleEndian>(ndx)?;
+        }
+        let versym_size = versym_data.len() as u64;
+        let versym_id = allocator.allocate_with_data(versym_data);
+
+        let verdef = if defined.is_empty() {
+            None
+        } else {
+            let base = soname.unwrap_or_default();
+            let entries: Vec<_> = core::iter::once((base, VER_FLG_BASE))
+                .chain(defined.iter().map(|&name| (name, 0)))
+                .collect();
+            let mut data = vec![];
+            for (i, &(name, flags)) in entries.iter().enumerate() {
+                let name_off = symtab.add_dynstr(name, allocator);
+                let is_last = i + 1 == entries.len();
+                data.write_u16::<LittleEndian>(VER_DEF_CURRENT)?;
+                data.write_u16::<LittleEndian>(flags)?;
+                data.write_u16::<LittleEndian>((i + 1) as u16)?;
+                data.write_u16::<LittleEndian>(1)?;
+                data.write_u32::<LittleEndian>(elf_hash(name))?;
+                data.write_u32::<LittleEndian>(VERDEF_SIZE)?;
+                data.write_u32::<LittleEndian>(if is_last {
+                    0
+                } else {
+                    VERDEF_SIZE + VERDAUX_SIZE
+                })?;
+                data.write_u32::<LittleEndian>(name_off)?;
+                data.write_u32::<LittleEndian>(0)?;
+            }
+            Some(VersionTable {
+                size: data.len() as u64,
+                id: allocator.allocate_with_data(data),
+                count: entries.len() as u32,
+            })
+        };
+
+        let verneed = if needed.is_empty() {
+            None
+        } else {
+            let mut data = vec![];
+            for (i, (file, versions)) in needed.iter().enumerate() {
+                let file_off = symtab.add_dynstr(file, allocator);
+                let is_last = i + 1 == needed.len();
+                let aux_size = VERNAUX_SIZE * versions.len() as u32;
+                data.write_u16::<LittleEndian>(VER_NEED_CURRENT)?;
+                data.write_u16::<LittleEndian>(versions.len() as u16)?;
+                data.write_u32::<LittleEndian>(file_off)?;
+                data.write_u32::<LittleEndian>(VERNEED_SIZE)?;
+                data.write_u32::<LittleEndian>(if is_last { 0 } else { VERNEED_SIZE + aux_size })?;
+                for (j, &(name, ndx)) in versions.iter().enumerate() {
+                    let name_off = symtab.add_dynstr(name, allocator);
+                    data.write_u32::<LittleEndian>(elf_hash(name))?;
+                    data.write_u16::<LittleEndian>(0)?;
+                    data.write_u16::<LittleEndian>(ndx)?;
+                    data.write_u32::<LittleEndian>(name_off)?;
+                    data.write_u32::<LittleEndian>(if j + 1 == versions.len() {
+                        0
+                    } else {
+                        VERNAUX_SIZE
+                    })?;
+                }
+            }
+            Some(VersionTable {
+                size: data.len() as u64,
+                id: allocator.allocate_with_data(data),
+                count: needed.len() as u32,
+            })
+        };
+
+        Ok(Some(Self {
+            versym_id,
+            versym_size,
+            verdef,
+            verneed,
+        }))
+    }
+
+    pub(crate) fn create_sections(&self, sections: &mut Vec<Section>) {
+        sections.push(Section {
+            header: SectionHeader {
+                name_off: 0,
+                shtype: SectionKind::GnuVersion,
+                addr: 0,
+                offset: 0,
+                size: self.versym_size,
+                addralign: 2,
+            },
+            data: self.versym_id,
+        });
+        for (table, shtype) in [
+            (&self.verdef, SectionKind::GnuVersionD),
+            (&self.verneed, SectionKind::GnuVersionR),
+        ] {
+            if let Some(table) = table {
+                sections.push(Section {
+                    header: SectionHeader {
+                        name_off: 0,
+                        shtype,
+                        addr: 0,
+                        offset: 0,
+                        size: table.size,
+                        addralign: 4,
+                    },
+                    data: table.id,
+                });
+            }
+        }
+    }
+
+    /// Record the number of entries of each table in `.dynamic` and in the section headers
+    pub(crate) fn update_counts(&self, dyn_meta: &mut DynamicMetadata, shdr: &mut ShdrManager) {
+        if let Some(verdef) = &self.verdef {
+            dyn_meta.update_entry(DT_VERDEFNUM as i64, verdef.count as u64);
+            shdr.set_info(SectionKind::GnuVersionD, verdef.count);
+        }
+        if let Some(verneed) = &self.verneed {
+            dyn_meta.update_entry(DT_VERNEEDNUM as i64, verneed.count as u64);
+            shdr.set_info(SectionKind::GnuVersionR, verneed.count);
+        }
+    }
+}
@@ -7,6 +7,9 @@ mod dylib;
 mod relocatable;
 
 pub use arch::Arch;
-pub use common::{RelocEntry, RelocType, SectionKind, SymbolDesc, SymbolScope, SymbolType};
-pub use dylib::{DylibWriter, ElfWriteOutput, ElfWriterConfig, RelocationInfo};
+pub use common::{
+    RelocEntry, RelocType, SectionKind, SymbolDesc, SymbolScope, SymbolType, SymbolVersion,
+    SymbolVisibility,
+};
+pub use dylib::{DylibPair, DylibWriter, ElfWriteOutput, ElfWriterConfig, RelocationInfo};
 pub use relocatable::{ObjectElfOutput, ObjectWriter};
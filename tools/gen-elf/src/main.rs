@@ -1,6 +1,6 @@
 use anyhow::Result;
 use clap::Parser;
-use gen_elf::{Arch, DylibWriter, RelocEntry, SymbolDesc};
+use gen_elf::{Arch, DylibWriter, ElfWriterConfig, RelocEntry, SymbolDesc};
 use std::path::{Path, PathBuf};
 
 const EXTERNAL_FUNC_NAME: &str = "external_func";
@@ -11,7 +11,7 @@ const EXTERNAL_TLS_NAME2: &str = "external_tls2";
 const COPY_VAR_NAME: &str = "copy_var";
 const LOCAL_VAR_NAME: &str = "local_var";
 
-fn gen_dynamic_elf(out_path: &Path, arch: Arch) -> Result<()> {
+fn gen_dynamic_elf(out_path: &Path, arch: Arch, needed: &[String]) -> Result<()> {
     let mut out = out_path.to_path_buf();
     if out.extension().and_then(|s| s.to_str()) != Some("so") {
         out.set_extension("so");
@@ -29,8 +29,9 @@ fn gen_dynamic_elf(out_path: &Path, arch: Arch) -> Result<()> {
         RelocEntry::irelative(arch),
     ];
 
-    // Use default configuration for standard ELF generation
-    let writer = DylibWriter::new(arch);
+    let needed: Vec<_> = needed.iter().map(String::as_str).collect();
+    let config = ElfWriterConfig::default().with_needed(&needed);
+    let writer = DylibWriter::with_config(arch, config);
     let symbols = vec![
         SymbolDesc::global_object(LOCAL_VAR_NAME, &[0u8; 8]),
         SymbolDesc::undefined_func(EXTERNAL_FUNC_NAME),
@@ -63,9 +64,9 @@ fn gen_relocatable_elf(out_path: &Path, arch: Arch) -> Result<()> {
     Ok(())
 }
 
-pub fn gen_elf(out_path: &Path, arch: Arch, dynamic: bool) -> Result<()> {
+pub fn gen_elf(out_path: &Path, arch: Arch, dynamic: bool, needed: &[String]) -> Result<()> {
     if dynamic {
-        gen_dynamic_elf(out_path, arch)?;
+        gen_dynamic_elf(out_path, arch, needed)?;
     } else {
         gen_relocatable_elf(out_path, arch)?;
     }
@@ -84,6 +85,9 @@ struct Args {
     /// Enable dynamic relocations
     #[arg(short,  action = clap::ArgAction::SetTrue)]
     dynamic: bool,
+    /// Add a DT_NEEDED entry to the dynamic library (repeatable)
+    #[arg(long, value_name = "NAME")]
+    needed: Vec<String>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -104,7 +108,7 @@ fn main() -> anyhow::Result<()> {
         args.dynamic
     );
 
-    gen_elf(&output, args.target, args.dynamic)?;
+    gen_elf(&output, args.target, args.dynamic, &args.needed)?;
 
     Ok(())
 }
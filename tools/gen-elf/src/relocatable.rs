@@ -4,9 +4,10 @@ use crate::common::{
 };
 use anyhow::Result;
 use object::{
-    Architecture, BinaryFormat, Endianness, SectionKind as ObjectSectionKind, SymbolKind,
-    SymbolScope,
-    write::{Object, Relocation, Symbol, SymbolSection},
+    Architecture, BinaryFormat, Endianness, RelocationEncoding, RelocationKind, SectionFlags,
+    SectionKind as ObjectSectionKind, SymbolKind, SymbolScope,
+    elf::{SHF_ALLOC, SHF_WRITE, SHT_FINI_ARRAY, SHT_INIT_ARRAY},
+    write::{Object, Relocation, RelocationFlags, Symbol, SymbolSection},
 };
 use std::collections::HashMap;
 
@@ -18,20 +19,60 @@ pub struct ObjectElfOutput {
     pub reloc_offsets: Vec<u64>,
 }
 
+/// A section holding an array of function pointers, such as `.init_array`.
+struct FnArray {
+    name: String,
+    funcs: Vec<String>,
+}
+
 /// A writer for generating relocatable object (.o) ELF files.
 pub struct ObjectWriter {
     arch: Arch,
+    arrays: Vec<FnArray>,
+    section_per_symbol: bool,
 }
 
 impl ObjectWriter {
     /// Create a new ObjectWriter for the specified architecture.
     pub fn new(arch: Arch) -> Self {
-        Self { arch }
+        Self {
+            arch,
+            arrays: Vec::new(),
+            section_per_symbol: false,
+        }
+    }
+
+    /// Put every defined symbol in a section of its own, named after the symbol
+    /// (e.g. `.text.foo`), like `-ffunction-sections -fdata-sections`.
+    ///
+    /// Relocations still apply to the first `.text` or `.data` section.
+    pub fn with_section_per_symbol(mut self) -> Self {
+        self.section_per_symbol = true;
+        self
+    }
+
+    /// Add a section holding the addresses of `funcs`.
+    ///
+    /// Sections named `.init_array*` and `.fini_array*` get the `SHT_INIT_ARRAY` and
+    /// `SHT_FINI_ARRAY` types, any other name (e.g. `.ctors`) is `SHT_PROGBITS`. Every
+    /// function must be one of the symbols passed to [`write`](Self::write).
+    pub fn with_fn_array(mut self, name: &str, funcs: &[&str]) -> Self {
+        self.arrays.push(FnArray {
+            name: name.to_owned(),
+            funcs: funcs.iter().map(|func| func.to_string()).collect(),
+        });
+        self
     }
 
     /// Generate the relocatable ELF bytes and metadata.
     pub fn write(&self, symbols: &[SymbolDesc], relocs: &[RelocEntry]) -> Result<ObjectElfOutput> {
-        gen_static_elf(self.arch, symbols, relocs)
+        gen_static_elf(
+            self.arch,
+            symbols,
+            relocs,
+            &self.arrays,
+            self.section_per_symbol,
+        )
     }
 
     /// Write the generated relocatable ELF to a file and return the metadata.
@@ -51,6 +92,8 @@ fn gen_static_elf(
     arch: Arch,
     symbols: &[SymbolDesc],
     relocs: &[RelocEntry],
+    arrays: &[FnArray],
+    section_per_symbol: bool,
 ) -> Result<ObjectElfOutput> {
     let obj_arch: Architecture = arch.into();
     let mut obj = Object::new(BinaryFormat::Elf, obj_arch, Endianness::Little);
@@ -62,16 +105,23 @@ fn gen_static_elf(
     // First pass: create sections and add defined symbols
     for sym_desc in symbols {
         if let Some(content) = &sym_desc.content {
-            let section_id = *section_map.entry(content.kind).or_insert_with(|| {
-                let (name, kind) = match content.kind {
-                    SectionKind::Text => (".text", ObjectSectionKind::Text),
-                    SectionKind::Data => (".data", ObjectSectionKind::Data),
-                    SectionKind::Plt => (".plt", ObjectSectionKind::Text),
-                    SectionKind::Tls => (".tdata", ObjectSectionKind::Tls),
-                    _ => (".data", ObjectSectionKind::Data),
-                };
-                obj.add_section(vec![], name.as_bytes().to_vec(), kind)
-            });
+            let (name, kind) = match content.kind {
+                SectionKind::Text => (".text", ObjectSectionKind::Text),
+                SectionKind::Data => (".data", ObjectSectionKind::Data),
+                SectionKind::Plt => (".plt", ObjectSectionKind::Text),
+                SectionKind::Tls => (".tdata", ObjectSectionKind::Tls),
+                _ => (".data", ObjectSectionKind::Data),
+            };
+            let section_id = if section_per_symbol {
+                let name = format!("{name}.{}", sym_desc.name);
+                let section_id = obj.add_section(vec![], name.into_bytes(), kind);
+                section_map.entry(content.kind).or_insert(section_id);
+                section_id
+            } else {
+                *section_map
+                    .entry(content.kind)
+                    .or_insert_with(|| obj.add_section(vec![], name.as_bytes().to_vec(), kind))
+            };
 
             let offset = obj.append_section_data(section_id, &content.data, 8);
 
@@ -80,7 +130,7 @@ fn gen_static_elf(
                 value: offset,
                 size: content.data.len() as u64,
                 kind: match sym_desc.sym_type {
-                    SymbolType::Func => SymbolKind::Text,
+                    SymbolType::Func | SymbolType::Ifunc => SymbolKind::Text,
                     SymbolType::Object => SymbolKind::Data,
                     SymbolType::Tls => SymbolKind::Tls,
                 },
@@ -105,7 +155,7 @@ fn gen_static_elf(
                 value: 0,
                 size: 0,
                 kind: match sym_desc.sym_type {
-                    SymbolType::Func => SymbolKind::Text,
+                    SymbolType::Func | SymbolType::Ifunc => SymbolKind::Text,
                     SymbolType::Object => SymbolKind::Data,
                     SymbolType::Tls => SymbolKind::Tls,
                 },
@@ -162,6 +212,45 @@ fn gen_static_elf(
         }
     }
 
+    // Function pointer arrays, filled in by absolute relocations
+    let word_size = if arch.is_64() { 8 } else { 4 };
+    for array in arrays {
+        let kind = if array.name.starts_with(".init_array") {
+            ObjectSectionKind::Elf(SHT_INIT_ARRAY)
+        } else if array.name.starts_with(".fini_array") {
+            ObjectSectionKind::Elf(SHT_FINI_ARRAY)
+        } else {
+            ObjectSectionKind::Data
+        };
+        let section_id = obj.add_section(vec![], array.name.as_bytes().to_vec(), kind);
+        obj.section_mut(section_id).flags = SectionFlags::Elf {
+            sh_flags: (SHF_ALLOC | SHF_WRITE) as u64,
+        };
+        obj.append_section_data(
+            section_id,
+            &vec![0; array.funcs.len() * word_size as usize],
+            word_size,
+        );
+        for (idx, func) in array.funcs.iter().enumerate() {
+            let symbol = *symbol_map
+                .get(func)
+                .ok_or_else(|| anyhow::anyhow!("Symbol not found for array entry: {func}"))?;
+            obj.add_relocation(
+                section_id,
+                Relocation {
+                    offset: idx as u64 * word_size,
+                    symbol,
+                    addend: 0,
+                    flags: RelocationFlags::Generic {
+                        kind: RelocationKind::Absolute,
+                        encoding: RelocationEncoding::Generic,
+                        size: word_size as u8 * 8,
+                    },
+                },
+            )?;
+        }
+    }
+
     // Write object file bytes
     let elf_data = obj.write()?;
 